@@ -32,6 +32,7 @@ use console::style;
 use jmap_client::client::{Client, Credentials};
 use modules::{
     cli::{Cli, Commands},
+    config::cmd_config,
     database::cmd_database,
     export::cmd_export,
     get,
@@ -72,6 +73,7 @@ async fn main() -> std::io::Result<()> {
                 cmd_export(build_client(&args.url, credentials).await, command).await
             }
             Commands::Database(command) => cmd_database(&args.url, credentials, command).await,
+            Commands::Config(command) => cmd_config(&args.url, credentials, command).await,
             Commands::Queue(_) | Commands::Report(_) => unreachable!(),
         }
     } else {
@@ -41,11 +41,11 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Import JMAP accounts and Maildir/mbox mailboxes
+    /// Import JMAP accounts, Maildir/mbox mailboxes and remote IMAP accounts
     #[clap(subcommand)]
     Import(ImportCommands),
 
-    /// Export JMAP accounts
+    /// Export and back up JMAP accounts
     #[clap(subcommand)]
     Export(ExportCommands),
 
@@ -53,6 +53,10 @@ pub enum Commands {
     #[clap(subcommand)]
     Database(DatabaseCommands),
 
+    /// Manage settings stored in the database
+    #[clap(subcommand)]
+    Config(ConfigCommands),
+
     /// Manage SMTP message queue
     #[clap(subcommand)]
     Queue(QueueCommands),
@@ -92,6 +96,48 @@ pub enum ImportCommands {
         /// Path to the exported account directory
         path: String,
     },
+    /// Import messages and folders from a remote IMAP server
+    Imap {
+        /// Number of messages to import concurrently, defaults to the number of CPUs.
+        #[clap(short, long)]
+        num_concurrent: Option<usize>,
+
+        /// Source IMAP server URL, imaps://host[:port] for implicit TLS or imap://host[:port] for STARTTLS
+        #[clap(short, long)]
+        source: String,
+
+        /// Source server credentials as login:secret, either the user's or an administrator's
+        #[clap(long)]
+        source_credentials: String,
+
+        /// User to log in as when using administrator credentials (SASL authorization identity)
+        #[clap(long)]
+        source_authzid: Option<String>,
+
+        /// Accept invalid TLS certificates from the source server
+        #[clap(long)]
+        allow_invalid_certs: bool,
+
+        /// Path to the checkpoint file used to resume and incrementally sync the import, defaults to '<account>.imap-sync.json'
+        #[clap(long)]
+        checkpoint: Option<String>,
+
+        /// Account name or email to import messages into
+        account: String,
+    },
+    /// Restore a JMAP account from backup archives, restored objects are assigned new ids
+    Backup {
+        /// Number of concurrent requests, defaults to the number of CPUs.
+        #[clap(short, long)]
+        num_concurrent: Option<usize>,
+
+        /// Account name or email to restore the backup into
+        account: String,
+
+        /// Backup archives to restore, the full backup followed by any incremental ones
+        #[clap(required = true)]
+        archives: Vec<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -108,6 +154,38 @@ pub enum ExportCommands {
         /// Path to export the account to
         path: String,
     },
+    /// Export messages and folders to Maildir/mbox
+    Messages {
+        #[clap(value_enum)]
+        #[clap(short, long)]
+        format: MailboxFormat,
+
+        /// Number of concurrent blob downloads to perform, defaults to the number of CPUs.
+        #[clap(short, long)]
+        num_concurrent: Option<usize>,
+
+        /// Account name or email to export messages from
+        account: String,
+
+        /// Path to export the messages to
+        path: String,
+    },
+    /// Create a full or incremental backup archive of a JMAP account
+    Backup {
+        /// Number of concurrent blob downloads to perform, defaults to the number of CPUs.
+        #[clap(short, long)]
+        num_concurrent: Option<usize>,
+
+        /// Previous backup archive to create an incremental backup from
+        #[clap(short, long)]
+        base: Option<String>,
+
+        /// Account name or email to back up
+        account: String,
+
+        /// Path of the backup archive to create
+        path: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -128,6 +206,93 @@ pub enum DatabaseCommands {
 
     /// Purge expired blobs
     Purge {},
+
+    /// Rebuild the full-text index
+    Reindex {
+        /// Account name to reindex, defaults to all accounts
+        account: Option<String>,
+    },
+
+    /// Show the full-text indexing queue status
+    IndexStatus {},
+
+    /// Remove messages older than their mailbox retention period
+    Expunge {
+        /// Account name to expunge, defaults to all accounts
+        account: Option<String>,
+    },
+
+    /// Place a JMAP account on legal hold
+    Hold {
+        /// Account name to place on hold
+        account: String,
+    },
+
+    /// Lift the legal hold of a JMAP account and remove its retained messages
+    Release {
+        /// Account name to release
+        account: String,
+    },
+
+    /// Show the legal hold status and retained messages of a JMAP account
+    HoldStatus {
+        /// Account name
+        account: String,
+    },
+
+    /// Allow an account to send messages from the addresses of another account
+    Delegate {
+        /// Account whose addresses will be used
+        owner: String,
+        /// Account that will be allowed to send
+        delegate: String,
+        /// Identify the delegate in the Sender header instead of sending as the owner
+        #[clap(long)]
+        on_behalf: bool,
+    },
+
+    /// Revoke the sending rights granted by an account
+    Undelegate {
+        /// Account that granted the rights
+        owner: String,
+        /// Account to revoke the rights from
+        delegate: String,
+    },
+
+    /// List the accounts allowed to send from the addresses of an account
+    Delegates {
+        /// Account name
+        owner: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// List settings stored in the database
+    List {
+        /// Only list settings starting with this prefix
+        prefix: Option<String>,
+    },
+    /// Display the value of a setting stored in the database
+    Get {
+        /// Setting key
+        key: String,
+    },
+    /// Validate and store a setting in the database
+    Set {
+        /// Setting key
+        key: String,
+        /// Setting value
+        value: String,
+    },
+    /// Delete settings from the database, reverting to the local configuration
+    Delete {
+        /// Setting keys to delete
+        #[clap(required = true)]
+        keys: Vec<String>,
+    },
+    /// Reload the server configuration
+    Reload {},
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -212,6 +377,13 @@ pub enum QueueCommands {
         // Cancel one or multiple message ids
         ids: Vec<String>,
     },
+
+    /// Shows the outbound limits learned for remote hosts
+    Limits {
+        /// Filter by domain or MX hostname
+        #[clap(short, long)]
+        domain: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -241,6 +413,53 @@ pub enum ReportCommands {
         #[clap(required = true)]
         ids: Vec<String>,
     },
+
+    /// Shows reports received from other hosts
+    Incoming {
+        /// Filter by reported domain
+        #[clap(short, long)]
+        domain: Option<String>,
+        /// Filter by reporting organization
+        #[clap(short, long)]
+        reporter: Option<String>,
+        /// Filter by report type
+        #[clap(short, long)]
+        #[clap(value_enum)]
+        format: Option<ReportFormat>,
+        /// Show reports covering dates after a certain datetime
+        #[clap(short = 'a', long)]
+        #[arg(value_parser = parse_datetime)]
+        from: Option<DateTime>,
+        /// Show reports covering dates before a certain datetime
+        #[clap(short = 'b', long)]
+        #[arg(value_parser = parse_datetime)]
+        to: Option<DateTime>,
+        /// Number of items to show per page
+        #[clap(short, long)]
+        page_size: Option<usize>,
+    },
+
+    /// Summarizes pass and fail rates per source IP of received reports
+    Summary {
+        /// Filter by reported domain
+        #[clap(short, long)]
+        domain: Option<String>,
+        /// Filter by reporting organization
+        #[clap(short, long)]
+        reporter: Option<String>,
+        /// Filter by report type
+        #[clap(short, long)]
+        #[clap(value_enum)]
+        format: Option<ReportFormat>,
+        /// Include reports covering dates after a certain datetime
+        #[clap(short = 'a', long)]
+        #[arg(value_parser = parse_datetime)]
+        from: Option<DateTime>,
+        /// Include reports covering dates before a certain datetime
+        #[clap(short = 'b', long)]
+        #[arg(value_parser = parse_datetime)]
+        to: Option<DateTime>,
+    },
 }
 
 impl Commands {
@@ -257,6 +476,9 @@ pub enum ReportFormat {
     /// TLS report
     #[serde(rename = "tls")]
     Tls,
+    /// Abuse feedback report
+    #[serde(rename = "arf")]
+    Arf,
 }
 
 fn parse_datetime(arg: &str) -> Result<DateTime, &'static str> {
@@ -0,0 +1,145 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::collections::BTreeMap;
+
+use jmap_client::client::Credentials;
+use prettytable::{Attr, Cell, Row, Table};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Method,
+};
+use serde::de::DeserializeOwned;
+
+use super::{cli::ConfigCommands, is_localhost, UnwrapResult};
+
+pub async fn cmd_config(url: &str, credentials: Credentials, command: ConfigCommands) {
+    match command {
+        ConfigCommands::List { prefix } => {
+            let settings = admin_request::<BTreeMap<String, String>>(
+                Method::GET,
+                &format!("{}/admin/settings/list/{}", url, prefix.unwrap_or_default()),
+                None,
+                &credentials,
+            )
+            .await;
+
+            if !settings.is_empty() {
+                let mut table = Table::new();
+                table.add_row(Row::new(
+                    ["Key", "Value"]
+                        .iter()
+                        .map(|p| Cell::new(p).with_style(Attr::Bold))
+                        .collect(),
+                ));
+                for (key, value) in &settings {
+                    table.add_row(Row::new(vec![Cell::new(key), Cell::new(value)]));
+                }
+                eprintln!();
+                table.printstd();
+                eprintln!();
+            } else {
+                eprintln!("No settings found.");
+            }
+        }
+        ConfigCommands::Get { key } => {
+            println!(
+                "{}",
+                admin_request::<String>(
+                    Method::GET,
+                    &format!("{}/admin/settings/get/{}", url, key),
+                    None,
+                    &credentials,
+                )
+                .await
+            );
+        }
+        ConfigCommands::Set { key, value } => {
+            admin_request::<String>(
+                Method::POST,
+                &format!("{}/admin/settings/set", url),
+                serde_json::to_string(&BTreeMap::from([(key, value)]))
+                    .unwrap_result("serialize settings")
+                    .into(),
+                &credentials,
+            )
+            .await;
+            eprintln!("Success.");
+        }
+        ConfigCommands::Delete { keys } => {
+            admin_request::<String>(
+                Method::GET,
+                &format!("{}/admin/settings/delete/{}", url, keys.join("/")),
+                None,
+                &credentials,
+            )
+            .await;
+            eprintln!("Success.");
+        }
+        ConfigCommands::Reload {} => {
+            admin_request::<String>(
+                Method::GET,
+                &format!("{}/admin/config/reload", url),
+                None,
+                &credentials,
+            )
+            .await;
+            eprintln!("Success.");
+        }
+    }
+}
+
+async fn admin_request<T: DeserializeOwned>(
+    method: Method,
+    url: &str,
+    body: Option<String>,
+    credentials: &Credentials,
+) -> T {
+    let mut request = reqwest::Client::builder()
+        .danger_accept_invalid_certs(is_localhost(url))
+        .build()
+        .unwrap_or_default()
+        .request(method, url)
+        .header(
+            AUTHORIZATION,
+            match credentials {
+                Credentials::Basic(s) => format!("Basic {s}"),
+                Credentials::Bearer(s) => format!("Bearer {s}"),
+            },
+        );
+    if let Some(body) = body {
+        request = request.header(CONTENT_TYPE, "application/json").body(body);
+    }
+
+    let response = request.send().await.unwrap_result("send request");
+    if response.status().is_success() {
+        serde_json::from_slice(&response.bytes().await.unwrap_result("fetch bytes"))
+            .unwrap_result("deserialize response")
+    } else {
+        eprintln!(
+            "Request Failed: {}",
+            response.text().await.unwrap_result("fetch text")
+        );
+        std::process::exit(1);
+    }
+}
@@ -34,8 +34,50 @@ pub async fn cmd_database(url: &str, credentials: Credentials, command: Database
             new_account,
         } => format!("{}/admin/account/rename/{}/{}", url, account, new_account),
         DatabaseCommands::Purge {} => format!("{}/admin/blob/purge", url),
+        DatabaseCommands::Reindex { account } => match account {
+            Some(account) => format!("{}/admin/fts/reindex/{}", url, account),
+            None => format!("{}/admin/fts/reindex", url),
+        },
+        DatabaseCommands::IndexStatus {} => format!("{}/admin/fts/status", url),
+        DatabaseCommands::Expunge { account } => match account {
+            Some(account) => format!("{}/admin/retention/purge/{}", url, account),
+            None => format!("{}/admin/retention/purge", url),
+        },
+        DatabaseCommands::Hold { account } => format!("{}/admin/hold/enable/{}", url, account),
+        DatabaseCommands::Release { account } => {
+            format!("{}/admin/hold/release/{}", url, account)
+        }
+        DatabaseCommands::HoldStatus { account } => {
+            format!("{}/admin/hold/status/{}", url, account)
+        }
+        DatabaseCommands::Delegate {
+            owner,
+            delegate,
+            on_behalf,
+        } => format!(
+            "{}/admin/delegate/grant/{}/{}/{}",
+            url,
+            owner,
+            delegate,
+            if on_behalf {
+                "send-on-behalf"
+            } else {
+                "send-as"
+            }
+        ),
+        DatabaseCommands::Undelegate { owner, delegate } => {
+            format!("{}/admin/delegate/revoke/{}/{}", url, owner, delegate)
+        }
+        DatabaseCommands::Delegates { owner } => {
+            format!("{}/admin/delegate/list/{}", url, owner)
+        }
     };
 
+    let print_response = url.contains("/admin/fts/")
+        || url.contains("/admin/retention/")
+        || url.contains("/admin/hold/release/")
+        || url.contains("/admin/hold/status/")
+        || url.contains("/admin/delegate/list/");
     let response = reqwest::Client::builder()
         .danger_accept_invalid_certs(is_localhost(&url))
         .build()
@@ -52,7 +94,11 @@ pub async fn cmd_database(url: &str, credentials: Credentials, command: Database
         .await
         .unwrap_result("send GET request");
     if response.status().is_success() {
-        eprintln!("Success.");
+        if print_response {
+            println!("{}", response.text().await.unwrap_result("fetch text"));
+        } else {
+            eprintln!("Success.");
+        }
     } else {
         eprintln!(
             "Request Failed: {}",
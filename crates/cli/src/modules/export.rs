@@ -22,6 +22,9 @@
 */
 
 use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufWriter, Read, Write},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -31,16 +34,21 @@ use jmap_client::{
     client::Client,
     email::{self, Email},
     identity::{self, Identity},
-    mailbox::{self, Mailbox},
+    mailbox::{self, Mailbox, Role},
+    push_subscription::{self, PushSubscription},
     sieve::{self, SieveScript},
     vacation_response::{self, VacationResponse},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 
 use crate::modules::RETRY_ATTEMPTS;
 
-use super::{cli::ExportCommands, name_to_id, UnwrapResult};
+use super::{
+    cli::{ExportCommands, MailboxFormat},
+    import::build_mailbox_tree,
+    name_to_id, UnwrapResult,
+};
 
 pub async fn cmd_export(mut client: Client, command: ExportCommands) {
     match command {
@@ -96,21 +104,7 @@ pub async fn cmd_export(mut client: Client, command: ExportCommands) {
                 blob_path.push(&blob_id);
 
                 futures.push(async move {
-                    let mut retry_count = 0;
-
-                    let bytes = loop {
-                        match client.download(&blob_id).await {
-                            Ok(bytes) => break bytes,
-                            Err(_) if retry_count < RETRY_ATTEMPTS => {
-                                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                                retry_count += 1;
-                            }
-                            result => {
-                                result.unwrap_result("download blob");
-                                return;
-                            }
-                        }
-                    };
+                    let bytes = download_blob(&client, &blob_id).await;
 
                     tokio::fs::OpenOptions::new()
                         .create(true)
@@ -132,6 +126,58 @@ pub async fn cmd_export(mut client: Client, command: ExportCommands) {
             // Wait for remaining futures
             while futures.next().await.is_some() {}
         }
+
+        ExportCommands::Messages {
+            format,
+            num_concurrent,
+            account,
+            path,
+        } => {
+            client.set_default_account_id(name_to_id(&client, &account).await);
+            let max_objects_in_get = client
+                .session()
+                .core_capabilities()
+                .map(|c| c.max_objects_in_get())
+                .unwrap_or(500);
+            let path = PathBuf::from(path);
+            if !path.is_dir() {
+                eprintln!("Directory {} does not exist.", path.display());
+                std::process::exit(1);
+            }
+
+            export_messages(
+                &client,
+                max_objects_in_get,
+                format,
+                &path,
+                num_concurrent.unwrap_or_else(num_cpus::get),
+            )
+            .await;
+        }
+
+        ExportCommands::Backup {
+            num_concurrent,
+            base,
+            account,
+            path,
+        } => {
+            client.set_default_account_id(name_to_id(&client, &account).await);
+            let max_objects_in_get = client
+                .session()
+                .core_capabilities()
+                .map(|c| c.max_objects_in_get())
+                .unwrap_or(500);
+
+            export_backup(
+                &client,
+                max_objects_in_get,
+                &account,
+                base.map(PathBuf::from).as_deref(),
+                Path::new(&path),
+                num_concurrent.unwrap_or_else(num_cpus::get),
+            )
+            .await;
+        }
     }
 }
 
@@ -223,6 +269,7 @@ pub async fn fetch_emails(client: &Client, max_objects_in_get: usize) -> Vec<Ema
             email::Property::ReceivedAt,
             email::Property::BlobId,
             email::Property::MessageId,
+            email::Property::ThreadId,
         ]);
 
         let mut response = request
@@ -441,3 +488,394 @@ async fn write_file<T: Serialize>(path: &Path, name: &str, contents: Vec<T>) ->
         .unwrap_result(&format!("write to {}", path.display()));
     len
 }
+
+pub async fn download_blob(client: &Client, blob_id: &str) -> Vec<u8> {
+    let mut retry_count = 0;
+
+    loop {
+        match client.download(blob_id).await {
+            Ok(bytes) => return bytes,
+            Err(_) if retry_count < RETRY_ATTEMPTS => {
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                retry_count += 1;
+            }
+            Err(err) => {
+                eprintln!("Failed to download blob {blob_id}: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+enum Folder {
+    Mbox(BufWriter<File>),
+    Maildir(PathBuf),
+}
+
+async fn export_messages(
+    client: &Client,
+    max_objects_in_get: usize,
+    format: MailboxFormat,
+    path: &Path,
+    num_concurrent: usize,
+) {
+    let mailboxes = fetch_mailboxes(client, max_objects_in_get).await;
+    let mut emails = fetch_emails(client, max_objects_in_get).await;
+    emails.sort_unstable_by_key(|email| email.received_at().unwrap_or_default());
+
+    // Create a Maildir folder or mbox file for each mailbox
+    let mut folders = HashMap::with_capacity(mailboxes.len());
+    for (name, mailbox) in build_mailbox_tree(&mailboxes) {
+        let name = name.into_iter().map(sanitize_name).collect::<Vec<_>>();
+        let mut folder_path = path.to_path_buf();
+        let folder = match format {
+            MailboxFormat::Mbox => {
+                for (pos, part) in name.iter().enumerate() {
+                    if pos < name.len() - 1 {
+                        folder_path.push(part);
+                    } else {
+                        folder_path.push(format!("{part}.mbox"));
+                    }
+                }
+                if let Some(parent) = folder_path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .unwrap_result(&format!("create directory {}", parent.display()));
+                }
+                Folder::Mbox(BufWriter::new(
+                    File::create(&folder_path)
+                        .unwrap_result(&format!("create {}", folder_path.display())),
+                ))
+            }
+            MailboxFormat::Maildir | MailboxFormat::MaildirNested => {
+                if mailbox.role() != Role::Inbox {
+                    if format == MailboxFormat::Maildir {
+                        folder_path.push(format!(".{}", name.join(".")));
+                    } else {
+                        folder_path.extend(name.iter());
+                    }
+                }
+                for dir in ["cur", "new", "tmp"] {
+                    let dir_path = folder_path.join(dir);
+                    std::fs::create_dir_all(&dir_path)
+                        .unwrap_result(&format!("create directory {}", dir_path.display()));
+                }
+                Folder::Maildir(folder_path.join("cur"))
+            }
+        };
+        folders.insert(mailbox.id().unwrap_result("obtain mailbox id"), folder);
+    }
+
+    // Download messages and write them to every folder they belong to
+    eprintln!(
+        "Exporting {} messages to {} folders...",
+        emails.len(),
+        folders.len()
+    );
+    let mut total_exported = 0;
+    let mut messages = futures::stream::iter(emails.iter().filter_map(|email| {
+        if let Some(blob_id) = email.blob_id() {
+            Some(async move { (email, download_blob(client, blob_id).await) })
+        } else {
+            eprintln!(
+                "Warning: email {:?} has no blobId",
+                email.id().unwrap_or_default()
+            );
+            None
+        }
+    }))
+    .buffered(num_concurrent);
+
+    while let Some((email, contents)) = messages.next().await {
+        let received_at = email.received_at().unwrap_or_default();
+
+        for mailbox_id in email.mailbox_ids() {
+            match folders.get_mut(mailbox_id) {
+                Some(Folder::Mbox(file)) => {
+                    write_mbox_message(file, received_at, &contents)
+                        .unwrap_result("write mbox message");
+                }
+                Some(Folder::Maildir(folder_path)) => {
+                    let mut flags = email
+                        .keywords()
+                        .iter()
+                        .filter_map(|keyword| match *keyword {
+                            "$draft" => Some('D'),
+                            "$flagged" => Some('F'),
+                            "$forwarded" => Some('P'),
+                            "$answered" => Some('R'),
+                            "$seen" => Some('S'),
+                            "$deleted" => Some('T'),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    flags.sort_unstable();
+                    let message_path = folder_path.join(format!(
+                        "{}.{}.stalwart:2,{}",
+                        received_at,
+                        email.id().unwrap_or_default(),
+                        flags.into_iter().collect::<String>()
+                    ));
+                    std::fs::write(&message_path, &contents)
+                        .unwrap_result(&format!("write {}", message_path.display()));
+                }
+                None => (),
+            }
+        }
+        total_exported += 1;
+    }
+
+    for folder in folders.values_mut() {
+        if let Folder::Mbox(file) = folder {
+            file.flush().unwrap_result("write mbox file");
+        }
+    }
+
+    eprintln!("Exported {} messages.", total_exported);
+}
+
+fn write_mbox_message(
+    file: &mut BufWriter<File>,
+    received_at: i64,
+    contents: &[u8],
+) -> std::io::Result<()> {
+    writeln!(
+        file,
+        "From MAILER-DAEMON {}",
+        chrono::NaiveDateTime::from_timestamp_opt(received_at, 0)
+            .unwrap_or_default()
+            .format("%a %b %e %H:%M:%S %Y")
+    )?;
+
+    // Quote lines starting with "From ", including already quoted ones (mboxrd)
+    for line in contents.split_inclusive(|&ch| ch == b'\n') {
+        if line
+            .iter()
+            .position(|&ch| ch != b'>')
+            .map_or(false, |pos| line[pos..].starts_with(b"From "))
+        {
+            file.write_all(b">")?;
+        }
+        file.write_all(line)?;
+    }
+    if !contents.ends_with(b"\n") {
+        file.write_all(b"\n")?;
+    }
+    file.write_all(b"\n")
+}
+
+fn sanitize_name(name: &str) -> String {
+    match name {
+        "." | ".." => name.replace('.', "_"),
+        _ => name.replace(['/', '\\'], "_"),
+    }
+}
+
+const BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub account: String,
+    pub created: i64,
+    pub email_state: String,
+    pub base_state: Option<String>,
+}
+
+async fn export_backup(
+    client: &Client,
+    max_objects_in_get: usize,
+    account: &str,
+    base: Option<&Path>,
+    path: &Path,
+    num_concurrent: usize,
+) {
+    // Obtain the current state before fetching any objects, changes made
+    // during the backup will be included in the next incremental backup.
+    let mut request = client.build();
+    request.get_email().ids(Vec::<String>::new());
+    let email_state = request
+        .send_get_email()
+        .await
+        .unwrap_result("fetch email state")
+        .state()
+        .to_string();
+
+    // Obtain the emails created since the base backup
+    let mut base_state = None;
+    let mut created_ids = HashSet::new();
+    if let Some(base) = base {
+        let manifest = read_backup_manifest(base);
+        let mut since_state = manifest.email_state.clone();
+        loop {
+            match client.email_changes(&since_state, None).await {
+                Ok(changes) => {
+                    created_ids.extend(changes.created().iter().cloned());
+                    if changes.has_more_changes() {
+                        since_state = changes.new_state().to_string();
+                    } else {
+                        base_state = manifest.email_state.into();
+                        break;
+                    }
+                }
+                Err(err) => {
+                    eprintln!(
+                        concat!(
+                            "Failed to obtain changes since the base backup ({}), ",
+                            "creating a full backup."
+                        ),
+                        err
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    // Fetch objects
+    let mailboxes = fetch_mailboxes(client, max_objects_in_get).await;
+    let emails = fetch_emails(client, max_objects_in_get).await;
+    let sieve_scripts = fetch_sieve_scripts(client, max_objects_in_get).await;
+    let identities = fetch_identities(client).await;
+    let vacation_responses = fetch_vacation_responses(client).await;
+    let push_subscriptions = if client.session().username().eq_ignore_ascii_case(account) {
+        fetch_push_subscriptions(client).await
+    } else {
+        eprintln!("Skipping push subscriptions, these can only be backed up by the account owner.");
+        Vec::new()
+    };
+
+    // Only download the messages added since the base backup
+    let mut blobs = emails
+        .iter()
+        .filter(|email| {
+            base_state.is_none() || email.id().map_or(false, |id| created_ids.contains(id))
+        })
+        .filter_map(|email| email.blob_id())
+        .collect::<Vec<_>>();
+    blobs.extend(sieve_scripts.iter().filter_map(|script| script.blob_id()));
+
+    // Write archive
+    let mut archive = tar::Builder::new(BufWriter::new(
+        File::create(path).unwrap_result(&format!("create {}", path.display())),
+    ));
+    let manifest = BackupManifest {
+        version: BACKUP_VERSION,
+        account: account.to_string(),
+        created: chrono::Utc::now().timestamp(),
+        email_state,
+        base_state,
+    };
+    for (name, contents) in [
+        ("manifest.json", serde_json::to_vec(&manifest)),
+        ("mailboxes.json", serde_json::to_vec(&mailboxes)),
+        ("emails.json", serde_json::to_vec(&emails)),
+        ("sieve.json", serde_json::to_vec(&sieve_scripts)),
+        ("identities.json", serde_json::to_vec(&identities)),
+        ("vacation.json", serde_json::to_vec(&vacation_responses)),
+        ("push.json", serde_json::to_vec(&push_subscriptions)),
+    ] {
+        append_archive_entry(
+            &mut archive,
+            name,
+            &contents.unwrap_result("serialize backup"),
+        );
+    }
+
+    eprintln!(
+        concat!(
+            "Backing up {} mailboxes, {} emails ({} blobs), {} sieve scripts, ",
+            "{} identities and {} push subscriptions..."
+        ),
+        mailboxes.len(),
+        emails.len(),
+        blobs.len(),
+        sieve_scripts.len(),
+        identities.len(),
+        push_subscriptions.len()
+    );
+    let mut downloads = futures::stream::iter(
+        blobs
+            .into_iter()
+            .map(|blob_id| async move { (blob_id, download_blob(client, blob_id).await) }),
+    )
+    .buffer_unordered(num_concurrent);
+    while let Some((blob_id, contents)) = downloads.next().await {
+        append_archive_entry(&mut archive, &format!("blobs/{blob_id}"), &contents);
+    }
+
+    archive
+        .into_inner()
+        .and_then(|mut file| file.flush())
+        .unwrap_result("write backup archive");
+
+    eprintln!(
+        "Created {} backup {}.",
+        if manifest.base_state.is_some() {
+            "incremental"
+        } else {
+            "full"
+        },
+        path.display()
+    );
+}
+
+pub fn read_backup_manifest(path: &Path) -> BackupManifest {
+    let mut archive =
+        tar::Archive::new(File::open(path).unwrap_result(&format!("open {}", path.display())));
+    for entry in archive
+        .entries()
+        .unwrap_result(&format!("read {}", path.display()))
+    {
+        let mut entry = entry.unwrap_result(&format!("read {}", path.display()));
+        if entry
+            .path()
+            .map_or(false, |p| p == Path::new("manifest.json"))
+        {
+            let mut contents = Vec::new();
+            entry
+                .read_to_end(&mut contents)
+                .unwrap_result(&format!("read {}", path.display()));
+            let manifest = serde_json::from_slice::<BackupManifest>(&contents)
+                .unwrap_result("parse backup manifest");
+            if manifest.version != BACKUP_VERSION {
+                eprintln!(
+                    "Unsupported backup version {} in {}.",
+                    manifest.version,
+                    path.display()
+                );
+                std::process::exit(1);
+            }
+            return manifest;
+        }
+    }
+
+    eprintln!("{} is not a backup archive.", path.display());
+    std::process::exit(1);
+}
+
+fn append_archive_entry(archive: &mut tar::Builder<BufWriter<File>>, name: &str, contents: &[u8]) {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    archive
+        .append_data(&mut header, name, contents)
+        .unwrap_result("write backup archive");
+}
+
+pub async fn fetch_push_subscriptions(client: &Client) -> Vec<PushSubscription> {
+    let mut request = client.build();
+    request.get_push_subscription().properties([
+        push_subscription::Property::Id,
+        push_subscription::Property::DeviceClientId,
+        push_subscription::Property::Url,
+        push_subscription::Property::Keys,
+        push_subscription::Property::Expires,
+        push_subscription::Property::Types,
+    ]);
+    request
+        .send_get_push_subscription()
+        .await
+        .unwrap_result("send JMAP request")
+        .take_list()
+}
@@ -0,0 +1,648 @@
+/*
+ * Copyright (c) 2020-2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine,
+};
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+pub struct ImapClient<T: AsyncRead + AsyncWrite + Unpin> {
+    stream: BufReader<T>,
+    timeout: Duration,
+    tag: usize,
+}
+
+#[derive(Debug)]
+pub enum ImapError {
+    Io(std::io::Error),
+    InvalidUrl(String),
+    InvalidResponse(String),
+    CommandFailed(String),
+    TLSInvalidName,
+    Disconnected,
+    Timeout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Atom(String),
+    Text(Vec<u8>),
+    ListOpen,
+    ListClose,
+}
+
+#[derive(Debug)]
+pub struct Folder {
+    pub name: String,
+    pub path: Vec<String>,
+    pub special_use: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct FolderStatus {
+    pub uid_validity: u32,
+    pub total_messages: u32,
+    pub highest_modseq: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Message {
+    pub uid: u32,
+    pub flags: Vec<String>,
+    pub modseq: Option<u64>,
+    pub internal_date: Option<i64>,
+    pub contents: Option<Vec<u8>>,
+}
+
+impl ImapClient<TlsStream<TcpStream>> {
+    pub async fn connect(
+        url: &str,
+        timeout: Duration,
+        allow_invalid_certs: bool,
+    ) -> Result<Self, ImapError> {
+        let (tls_implicit, address) = if let Some(address) = url.strip_prefix("imaps://") {
+            (true, address)
+        } else if let Some(address) = url.strip_prefix("imap://") {
+            (false, address)
+        } else {
+            (true, url)
+        };
+        let address = address.trim_end_matches('/');
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse::<u16>()
+                    .map_err(|_| ImapError::InvalidUrl(url.to_string()))?,
+            ),
+            None => (address, if tls_implicit { 993 } else { 143 }),
+        };
+        if host.is_empty() {
+            return Err(ImapError::InvalidUrl(url.to_string()));
+        }
+        let tls_connector = build_tls_connector(allow_invalid_certs);
+        let tls_hostname = ServerName::try_from(host).map_err(|_| ImapError::TLSInvalidName)?;
+
+        let stream = tokio::time::timeout(timeout, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| ImapError::Timeout)??;
+
+        let stream = if tls_implicit {
+            stream
+        } else {
+            let mut client = ImapClient {
+                stream: BufReader::new(stream),
+                timeout,
+                tag: 0,
+            };
+            client.expect_greeting().await?;
+            client.command("STARTTLS").await?;
+            client.stream.into_inner()
+        };
+
+        let mut client = ImapClient {
+            stream: BufReader::new(
+                tokio::time::timeout(timeout, tls_connector.connect(tls_hostname, stream))
+                    .await
+                    .map_err(|_| ImapError::Timeout)??,
+            ),
+            timeout,
+            tag: 0,
+        };
+        if tls_implicit {
+            client.expect_greeting().await?;
+        }
+
+        Ok(client)
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> ImapClient<T> {
+    pub async fn login(&mut self, login: &str, secret: &str) -> Result<(), ImapError> {
+        self.command(&format!("LOGIN {} {}", quote(login), quote(secret)))
+            .await
+            .map(|_| ())
+    }
+
+    // Authenticates with SASL PLAIN, which allows administrators to log in
+    // on behalf of another user by specifying an authorization identity.
+    pub async fn authenticate_plain(
+        &mut self,
+        authzid: &str,
+        login: &str,
+        secret: &str,
+    ) -> Result<(), ImapError> {
+        let tag = self.next_tag();
+        self.write(format!("{tag} AUTHENTICATE PLAIN\r\n").as_bytes())
+            .await?;
+        let response = self.read_response().await?;
+        if !response.first().map_or(false, |token| token.is_atom("+")) {
+            return Err(ImapError::InvalidResponse(response_text(&response)));
+        }
+        self.write(
+            format!(
+                "{}\r\n",
+                STANDARD.encode(format!("{authzid}\0{login}\0{secret}"))
+            )
+            .as_bytes(),
+        )
+        .await?;
+        self.read_tagged(&tag).await.map(|_| ())
+    }
+
+    pub async fn list(&mut self) -> Result<Vec<Folder>, ImapError> {
+        let mut folders = Vec::new();
+
+        for response in self.command("LIST \"\" \"*\"").await? {
+            if response.len() < 3
+                || !response[0].is_atom("*")
+                || !response[1].is_atom("LIST")
+                || response[2] != Token::ListOpen
+            {
+                continue;
+            }
+            let mut tokens = response.into_iter().skip(3);
+
+            let mut special_use = None;
+            let mut is_selectable = true;
+            for token in tokens.by_ref() {
+                match token {
+                    Token::Atom(attr) => {
+                        let attr = attr.to_ascii_lowercase();
+                        match attr.as_str() {
+                            "\\noselect" | "\\nonexistent" => {
+                                is_selectable = false;
+                            }
+                            "\\archive" | "\\drafts" | "\\junk" | "\\sent" | "\\trash" => {
+                                special_use = attr[1..].to_string().into();
+                            }
+                            _ => (),
+                        }
+                    }
+                    Token::ListClose => break,
+                    _ => (),
+                }
+            }
+
+            let delimiter = match tokens.next() {
+                Some(Token::Text(delimiter)) => String::from_utf8(delimiter).ok(),
+                _ => None,
+            };
+            let name = match tokens.next() {
+                Some(Token::Atom(name)) => name,
+                Some(Token::Text(name)) => String::from_utf8(name)
+                    .map_err(|_| ImapError::InvalidResponse("Invalid folder name".into()))?,
+                _ => continue,
+            };
+            if !is_selectable {
+                continue;
+            }
+
+            let decoded_name = utf7_decode(&name).unwrap_or_else(|| name.clone());
+            let path = match delimiter {
+                Some(delimiter) if !delimiter.is_empty() => decoded_name
+                    .split(delimiter.as_str())
+                    .filter(|part| !part.is_empty())
+                    .map(|part| part.to_string())
+                    .collect(),
+                _ => vec![decoded_name],
+            };
+
+            folders.push(Folder {
+                name,
+                path,
+                special_use,
+            });
+        }
+
+        Ok(folders)
+    }
+
+    pub async fn capabilities(&mut self) -> Result<Vec<String>, ImapError> {
+        let mut capabilities = Vec::new();
+
+        for response in self.command("CAPABILITY").await? {
+            if response.len() > 2 && response[0].is_atom("*") && response[1].is_atom("CAPABILITY") {
+                capabilities.extend(
+                    response
+                        .into_iter()
+                        .skip(2)
+                        .filter_map(|token| match token {
+                            Token::Atom(capability) => Some(capability.to_ascii_uppercase()),
+                            _ => None,
+                        }),
+                );
+            }
+        }
+
+        Ok(capabilities)
+    }
+
+    // Opens a folder in read-only mode, enabling CONDSTORE if requested so that
+    // the server reports the folder's HIGHESTMODSEQ.
+    pub async fn examine(
+        &mut self,
+        folder: &str,
+        condstore: bool,
+    ) -> Result<FolderStatus, ImapError> {
+        let mut uid_validity = None;
+        let mut total_messages = 0;
+        let mut highest_modseq = None;
+
+        let command = if condstore {
+            format!("EXAMINE {} (CONDSTORE)", quote(folder))
+        } else {
+            format!("EXAMINE {}", quote(folder))
+        };
+        for response in self.command(&command).await? {
+            if response.len() < 3 || !response[0].is_atom("*") {
+                continue;
+            }
+            if response[2].is_atom("EXISTS") {
+                if let Token::Atom(exists) = &response[1] {
+                    total_messages = exists.parse().unwrap_or_default();
+                }
+            } else if response[1].is_atom("OK") {
+                let value = response.get(3).and_then(|token| match token {
+                    Token::Atom(atom) => Some(atom.trim_end_matches(']')),
+                    _ => None,
+                });
+                if response[2].is_atom("[UIDVALIDITY") {
+                    uid_validity = value.and_then(|value| value.parse().ok());
+                } else if response[2].is_atom("[HIGHESTMODSEQ") {
+                    highest_modseq = value.and_then(|value| value.parse().ok());
+                }
+            }
+        }
+
+        uid_validity
+            .map(|uid_validity| FolderStatus {
+                uid_validity,
+                total_messages,
+                highest_modseq,
+            })
+            .ok_or_else(|| {
+                ImapError::InvalidResponse(format!("No UIDVALIDITY returned for folder {folder:?}"))
+            })
+    }
+
+    pub async fn uid_fetch(&mut self, uids: &str, items: &str) -> Result<Vec<Message>, ImapError> {
+        let mut messages = Vec::new();
+
+        for response in self.command(&format!("UID FETCH {uids} {items}")).await? {
+            if response.len() < 4
+                || !response[0].is_atom("*")
+                || !response[2].is_atom("FETCH")
+                || response[3] != Token::ListOpen
+            {
+                continue;
+            }
+            let mut tokens = response.into_iter().skip(4);
+
+            let mut message = Message::default();
+            while let Some(Token::Atom(item)) = tokens.next() {
+                let item = item.to_ascii_uppercase();
+                match (item.as_str(), tokens.next()) {
+                    ("UID", Some(Token::Atom(uid))) => {
+                        message.uid = uid.parse().unwrap_or_default();
+                    }
+                    ("FLAGS", Some(Token::ListOpen)) => {
+                        for token in tokens.by_ref() {
+                            match token {
+                                Token::Atom(flag) => message.flags.push(flag),
+                                _ => break,
+                            }
+                        }
+                    }
+                    ("MODSEQ", Some(Token::ListOpen)) => {
+                        if let Some(Token::Atom(modseq)) = tokens.next() {
+                            message.modseq = modseq.parse().ok();
+                        }
+                        tokens.next();
+                    }
+                    ("INTERNALDATE", Some(Token::Text(date))) => {
+                        message.internal_date = std::str::from_utf8(&date)
+                            .ok()
+                            .and_then(|date| {
+                                chrono::DateTime::parse_from_str(
+                                    date.trim(),
+                                    "%d-%b-%Y %H:%M:%S %z",
+                                )
+                                .ok()
+                            })
+                            .map(|date| date.timestamp());
+                    }
+                    ("BODY[]", Some(Token::Text(contents))) => {
+                        message.contents = contents.into();
+                    }
+                    (_, Some(Token::ListOpen)) => {
+                        let mut depth = 1;
+                        for token in tokens.by_ref() {
+                            match token {
+                                Token::ListOpen => depth += 1,
+                                Token::ListClose => {
+                                    depth -= 1;
+                                    if depth == 0 {
+                                        break;
+                                    }
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            if message.uid != 0 {
+                messages.push(message);
+            }
+        }
+
+        Ok(messages)
+    }
+
+    pub async fn logout(&mut self) -> Result<(), ImapError> {
+        self.command("LOGOUT").await.map(|_| ())
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Vec<Vec<Token>>, ImapError> {
+        let tag = self.next_tag();
+        self.write(format!("{tag} {command}\r\n").as_bytes())
+            .await?;
+        self.read_tagged(&tag).await
+    }
+
+    async fn read_tagged(&mut self, tag: &str) -> Result<Vec<Vec<Token>>, ImapError> {
+        let mut responses = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            match response.first() {
+                Some(Token::Atom(response_tag)) if response_tag == tag => {
+                    return if response.get(1).map_or(false, |token| token.is_atom("OK")) {
+                        Ok(responses)
+                    } else {
+                        Err(ImapError::CommandFailed(response_text(&response[1..])))
+                    };
+                }
+                _ => responses.push(response),
+            }
+        }
+    }
+
+    async fn expect_greeting(&mut self) -> Result<(), ImapError> {
+        let response = self.read_response().await?;
+        if response.len() > 1 && response[0].is_atom("*") && response[1].is_atom("OK") {
+            Ok(())
+        } else {
+            Err(ImapError::InvalidResponse(response_text(&response)))
+        }
+    }
+
+    async fn read_response(&mut self) -> Result<Vec<Token>, ImapError> {
+        tokio::time::timeout(self.timeout, async {
+            let mut tokens = Vec::new();
+            let mut line = Vec::with_capacity(128);
+
+            loop {
+                line.clear();
+                if self.stream.read_until(b'\n', &mut line).await? == 0 {
+                    return Err(ImapError::Disconnected);
+                }
+
+                if let Some(size) = tokenize(&line, &mut tokens) {
+                    let mut literal = vec![0u8; size];
+                    self.stream.read_exact(&mut literal).await?;
+                    tokens.push(Token::Text(literal));
+                } else {
+                    return Ok(tokens);
+                }
+            }
+        })
+        .await
+        .map_err(|_| ImapError::Timeout)?
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<(), ImapError> {
+        let stream = self.stream.get_mut();
+        stream.write_all(bytes).await?;
+        stream.flush().await.map_err(Into::into)
+    }
+
+    fn next_tag(&mut self) -> String {
+        self.tag += 1;
+        format!("C{}", self.tag)
+    }
+}
+
+impl Token {
+    fn is_atom(&self, value: &str) -> bool {
+        matches!(self, Token::Atom(atom) if atom.eq_ignore_ascii_case(value))
+    }
+}
+
+// Tokenizes a response line, returning the size of the literal that follows it, if any.
+fn tokenize(line: &[u8], tokens: &mut Vec<Token>) -> Option<usize> {
+    let mut pos = 0;
+
+    while let Some(&ch) = line.get(pos) {
+        match ch {
+            b' ' | b'\r' | b'\n' => {
+                pos += 1;
+            }
+            b'(' => {
+                tokens.push(Token::ListOpen);
+                pos += 1;
+            }
+            b')' => {
+                tokens.push(Token::ListClose);
+                pos += 1;
+            }
+            b'"' => {
+                let mut text = Vec::new();
+                pos += 1;
+                while let Some(&ch) = line.get(pos) {
+                    pos += 1;
+                    match ch {
+                        b'\\' => {
+                            if let Some(&ch) = line.get(pos) {
+                                text.push(ch);
+                                pos += 1;
+                            }
+                        }
+                        b'"' => break,
+                        _ => text.push(ch),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            b'{' if line[pos..].ends_with(b"}\r\n") || line[pos..].ends_with(b"}\n") => {
+                let literal = std::str::from_utf8(&line[pos + 1..])
+                    .unwrap_or_default()
+                    .trim_end()
+                    .trim_end_matches('}')
+                    .trim_end_matches('+');
+                if let Ok(size) = literal.parse::<usize>() {
+                    return Some(size);
+                }
+                tokens.push(Token::Atom(
+                    String::from_utf8_lossy(&line[pos..]).trim().to_string(),
+                ));
+                return None;
+            }
+            _ => {
+                let start = pos;
+                while let Some(&ch) = line.get(pos) {
+                    if matches!(ch, b' ' | b'(' | b')' | b'"' | b'\r' | b'\n') {
+                        break;
+                    }
+                    pos += 1;
+                }
+                tokens.push(Token::Atom(
+                    String::from_utf8_lossy(&line[start..pos]).into_owned(),
+                ));
+            }
+        }
+    }
+
+    None
+}
+
+fn response_text(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Atom(atom) => atom.clone(),
+            Token::Text(text) => String::from_utf8_lossy(text).into_owned(),
+            Token::ListOpen => "(".to_string(),
+            Token::ListClose => ")".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Decodes a folder name encoded in modified UTF-7 (RFC 3501, section 5.1.3).
+fn utf7_decode(name: &str) -> Option<String> {
+    let mut result = String::with_capacity(name.len());
+    let mut parts = name.split('&');
+    result.push_str(parts.next()?);
+
+    for part in parts {
+        let (encoded, rest) = part.split_once('-')?;
+        if encoded.is_empty() {
+            result.push('&');
+        } else {
+            let bytes = STANDARD_NO_PAD.decode(encoded.replace(',', "/")).ok()?;
+            result.push_str(
+                &String::from_utf16(
+                    &bytes
+                        .chunks_exact(2)
+                        .map(|ch| u16::from_be_bytes([ch[0], ch[1]]))
+                        .collect::<Vec<_>>(),
+                )
+                .ok()?,
+            );
+        }
+        result.push_str(rest);
+    }
+
+    Some(result)
+}
+
+fn build_tls_connector(allow_invalid_certs: bool) -> TlsConnector {
+    let config = if !allow_invalid_certs {
+        let mut root_cert_store = RootCertStore::empty();
+        root_cert_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_cert_store)
+            .with_no_client_auth()
+    } else {
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(DummyVerifier))
+            .with_no_client_auth()
+    };
+
+    TlsConnector::from(Arc::new(config))
+}
+
+struct DummyVerifier;
+
+impl ServerCertVerifier for DummyVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+impl From<std::io::Error> for ImapError {
+    fn from(err: std::io::Error) -> Self {
+        ImapError::Io(err)
+    }
+}
+
+impl Display for ImapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImapError::Io(err) => write!(f, "I/O error: {err}"),
+            ImapError::InvalidUrl(url) => write!(f, "Invalid IMAP server URL {url:?}"),
+            ImapError::InvalidResponse(response) => write!(f, "Invalid response: {response}"),
+            ImapError::CommandFailed(response) => write!(f, "Command failed: {response}"),
+            ImapError::TLSInvalidName => write!(f, "Invalid TLS name"),
+            ImapError::Disconnected => write!(f, "Connection closed by server"),
+            ImapError::Timeout => write!(f, "Connection timed out"),
+        }
+    }
+}
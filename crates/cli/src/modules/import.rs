@@ -22,13 +22,14 @@
 */
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     io::{self, Cursor},
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc, Mutex,
     },
+    time::Duration,
 };
 
 use console::style;
@@ -43,17 +44,19 @@ use mail_parser::mailbox::{
     maildir,
     mbox::{self, MessageIterator},
 };
-use serde::de::DeserializeOwned;
-use tokio::{fs::File, io::AsyncReadExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::{fs::File, io::AsyncReadExt, net::TcpStream};
+use tokio_rustls::client::TlsStream;
 
 use crate::modules::{name_to_id, UnwrapResult, RETRY_ATTEMPTS};
 
 use super::{
     cli::{ImportCommands, MailboxFormat},
     export::{
-        fetch_emails, fetch_identities, fetch_mailboxes, fetch_sieve_scripts,
-        fetch_vacation_responses,
+        fetch_emails, fetch_identities, fetch_mailboxes, fetch_push_subscriptions,
+        fetch_sieve_scripts, fetch_vacation_responses, read_backup_manifest,
     },
+    imap::{Folder, ImapClient},
     read_file,
 };
 
@@ -428,23 +431,632 @@ pub async fn cmd_import(mut client: Client, command: ImportCommands) {
                 eprintln!("Path '{}' does not exist.", path.display());
                 return;
             }
-            let num_concurrent = num_concurrent.unwrap_or_else(num_cpus::get);
+            import_account(&client, &path, num_concurrent.unwrap_or_else(num_cpus::get)).await;
+        }
+
+        ImportCommands::Backup {
+            num_concurrent,
+            account,
+            archives,
+        } => {
+            client.set_default_account_id(name_to_id(&client, &account).await);
+
+            // Extract the archives in order, so that the metadata of the latest backup
+            // is restored along with the messages stored in any of the archives.
+            // Objects are recreated through the JMAP API, so they are assigned new ids.
+            let temp_dir = tempfile::TempDir::new().unwrap_result("create temporary directory");
+            let path = temp_dir.path();
+            let mut email_state = None;
+            for archive in &archives {
+                let archive = Path::new(archive);
+                let manifest = read_backup_manifest(archive);
+                if manifest.base_state != email_state {
+                    eprintln!(
+                        concat!(
+                            "Backup {} does not follow the previous one, ",
+                            "archives must be listed starting with the full backup."
+                        ),
+                        archive.display()
+                    );
+                    std::process::exit(1);
+                }
+                tar::Archive::new(
+                    std::fs::File::open(archive)
+                        .unwrap_result(&format!("open {}", archive.display())),
+                )
+                .unpack(path)
+                .unwrap_result(&format!("extract {}", archive.display()));
+                email_state = manifest.email_state.into();
+            }
+
+            import_account(&client, path, num_concurrent.unwrap_or_else(num_cpus::get)).await;
+            if client.session().username().eq_ignore_ascii_case(&account) {
+                import_push_subscriptions(&client, path).await;
+            } else {
+                eprintln!(
+                    "Skipping push subscriptions, these can only be restored by the account owner."
+                );
+            }
+        }
+
+        ImportCommands::Imap {
+            num_concurrent,
+            source,
+            source_credentials,
+            source_authzid,
+            allow_invalid_certs,
+            checkpoint,
+            account,
+        } => {
+            client.set_default_account_id(name_to_id(&client, &account).await);
+            let checkpoint =
+                PathBuf::from(checkpoint.unwrap_or_else(|| format!("{account}.imap-sync.json")));
+            let (login, secret) = source_credentials
+                .split_once(':')
+                .unwrap_result("parse source credentials, expected login:secret");
 
-            // Import objects
-            import_emails(
+            // Connect to the source server
+            eprintln!(
+                "{} Connecting to {}...",
+                style("[1/4]").bold().dim(),
+                source
+            );
+            let mut imap =
+                ImapClient::connect(&source, Duration::from_secs(60), allow_invalid_certs)
+                    .await
+                    .unwrap_result("connect to source IMAP server");
+            let result = if let Some(authzid) = &source_authzid {
+                imap.authenticate_plain(authzid, login, secret).await
+            } else {
+                imap.login(login, secret).await
+            };
+            result.unwrap_result("authenticate with source IMAP server");
+
+            import_imap(
                 &client,
-                &path,
-                import_mailboxes(&client, &path).await.into(),
-                num_concurrent,
+                &mut imap,
+                &checkpoint,
+                num_concurrent.unwrap_or_else(num_cpus::get),
             )
             .await;
-            import_sieve_scripts(&client, &path, num_concurrent).await;
-            import_identities(&client, &path).await;
-            import_vacation_responses(&client, &path).await;
+            let _ = imap.logout().await;
         }
     }
 }
 
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct ImapCheckpoint {
+    mailboxes: HashMap<String, ImapMailboxCheckpoint>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct ImapMailboxCheckpoint {
+    uid_validity: u32,
+    last_uid: u32,
+    failed_uids: Vec<u32>,
+    #[serde(default)]
+    highest_modseq: Option<u64>,
+    #[serde(default)]
+    messages: BTreeMap<u32, ImapImportedMessage>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct ImapImportedMessage {
+    id: String,
+    keywords: Vec<String>,
+}
+
+async fn import_imap(
+    client: &Client,
+    imap: &mut ImapClient<TlsStream<TcpStream>>,
+    checkpoint_path: &Path,
+    num_concurrent: usize,
+) {
+    // Load the checkpoint from any previous run
+    let mut checkpoint = if checkpoint_path.exists() {
+        serde_json::from_slice::<ImapCheckpoint>(
+            &std::fs::read(checkpoint_path).unwrap_result("read checkpoint file"),
+        )
+        .unwrap_result("parse checkpoint file")
+    } else {
+        ImapCheckpoint::default()
+    };
+
+    // Fetch source folders
+    eprintln!("{} Fetching source folders...", style("[2/4]").bold().dim());
+    let condstore = imap
+        .capabilities()
+        .await
+        .unwrap_result("fetch source IMAP server capabilities")
+        .iter()
+        .any(|capability| capability == "CONDSTORE");
+    let mut folders = imap.list().await.unwrap_result("list source folders");
+    folders.sort_unstable_by_key(|folder| folder.path.len());
+
+    // Map source folders to mailboxes, creating any missing ones
+    eprintln!(
+        "{} Creating missing mailboxes...",
+        style("[3/4]").bold().dim()
+    );
+    let existing_mailboxes = fetch_mailboxes(
+        client,
+        client
+            .session()
+            .core_capabilities()
+            .map(|c| c.max_objects_in_get())
+            .unwrap_or(500),
+    )
+    .await;
+    let existing_tree = build_mailbox_tree(&existing_mailboxes);
+    let inbox_id = existing_mailboxes
+        .iter()
+        .find(|m| m.role() == Role::Inbox)
+        .and_then(|m| m.id())
+        .unwrap_result("locate Inbox on account, please check the server logs.");
+    let find_existing = |path: &[String]| -> Option<String> {
+        if path.len() == 1 && path[0].eq_ignore_ascii_case("INBOX") {
+            Some(inbox_id.to_string())
+        } else {
+            existing_tree
+                .get(&path.iter().map(|p| p.as_str()).collect::<Vec<_>>())
+                .and_then(|m| m.id())
+                .map(|id| id.to_string())
+        }
+    };
+
+    let mut request = client.build();
+    let set_request = request.set_mailbox();
+    let mut create_ids: HashMap<Vec<String>, String> = HashMap::new();
+    let mut folder_ids = Vec::with_capacity(folders.len());
+    for folder in &folders {
+        // Use the existing mailbox with the same role or path
+        let role = folder_role(folder);
+        let existing_id = if role != Role::None {
+            existing_mailboxes
+                .iter()
+                .find(|m| m.role() == role)
+                .and_then(|m| m.id())
+                .map(|id| id.to_string())
+        } else {
+            None
+        };
+        if let Some(id) = existing_id.or_else(|| find_existing(&folder.path)) {
+            folder_ids.push(Some(id));
+            continue;
+        }
+
+        // Create the mailbox and any missing parents
+        for pos in 1..=folder.path.len() {
+            let path = &folder.path[..pos];
+            if create_ids.contains_key(path) || find_existing(path).is_some() {
+                continue;
+            }
+
+            let create_request = set_request.create().name(&path[pos - 1]);
+            if pos > 1 {
+                let parent_path = &path[..pos - 1];
+                if let Some(parent_id) = create_ids.get(parent_path) {
+                    create_request.parent_id_ref(parent_id);
+                } else {
+                    create_request.parent_id(find_existing(parent_path));
+                }
+            } else {
+                create_request.parent_id(None::<String>);
+            }
+            if pos == folder.path.len() {
+                create_request.role(folder_role(folder));
+            }
+            create_ids.insert(path.to_vec(), create_request.create_id().unwrap());
+        }
+        folder_ids.push(None);
+    }
+
+    if !create_ids.is_empty() {
+        let mut response = request
+            .send_set_mailbox()
+            .await
+            .unwrap_result("create mailboxes");
+        for (folder, folder_id) in folders.iter().zip(folder_ids.iter_mut()) {
+            if folder_id.is_none() {
+                *folder_id = response
+                    .created(&create_ids[&folder.path])
+                    .unwrap_result("create mailbox")
+                    .take_id()
+                    .into();
+            }
+        }
+    }
+
+    // Synchronize flags and expunges of previously imported messages, then import
+    // new messages as well as any that failed to import in previous runs
+    eprintln!("{} Importing messages...", style("[4/4]").bold().dim());
+    let mut total_imported = 0;
+    let mut failures = Vec::new();
+    let progress_style =
+        ProgressStyle::with_template("{prefix:.bold.dim} {wide_bar} {pos}/{len}").unwrap();
+    let max_objects_in_set = client
+        .session()
+        .core_capabilities()
+        .map(|c| c.max_objects_in_set())
+        .unwrap_or(500);
+
+    for (folder, folder_id) in folders.iter().zip(folder_ids) {
+        let mailbox_id = folder_id.unwrap();
+        let folder_name = folder.path.join("/");
+        let status = match imap.examine(&folder.name, condstore).await {
+            Ok(status) => status,
+            Err(err) => {
+                failures.push(format!("Failed to select folder {folder_name:?}: {err}"));
+                continue;
+            }
+        };
+
+        let mut mailbox_checkpoint = checkpoint
+            .mailboxes
+            .remove(&folder.name)
+            .unwrap_or_default();
+        if mailbox_checkpoint.uid_validity != status.uid_validity {
+            if mailbox_checkpoint.uid_validity != 0 {
+                eprintln!(
+                    concat!(
+                        "UIDVALIDITY of folder {:?} changed, ",
+                        "all its messages will be imported again."
+                    ),
+                    folder_name
+                );
+
+                // Remove the copies imported under the previous UIDVALIDITY
+                let ids = mailbox_checkpoint
+                    .messages
+                    .values()
+                    .map(|message| message.id.clone())
+                    .collect::<Vec<_>>();
+                for (id, result) in destroy_emails(client, ids, max_objects_in_set).await {
+                    if let Err(err) = result {
+                        failures.push(format!(
+                            "Failed to remove message {id} from folder {folder_name:?}: {err}"
+                        ));
+                    }
+                }
+            }
+            mailbox_checkpoint = ImapMailboxCheckpoint {
+                uid_validity: status.uid_validity,
+                ..Default::default()
+            };
+        }
+
+        // Flags only need to be fetched if the folder changed since the last run
+        let fetch_flags = status.highest_modseq.is_none()
+            || status.highest_modseq != mailbox_checkpoint.highest_modseq;
+        let server_messages = if status.total_messages > 0 {
+            imap.uid_fetch("1:*", if fetch_flags { "(UID FLAGS)" } else { "(UID)" })
+                .await
+        } else {
+            Ok(Vec::new())
+        };
+        let server_messages = match server_messages {
+            Ok(messages) => messages,
+            Err(err) => {
+                failures.push(format!(
+                    "Failed to fetch message list for folder {folder_name:?}: {err}"
+                ));
+                checkpoint
+                    .mailboxes
+                    .insert(folder.name.clone(), mailbox_checkpoint);
+                continue;
+            }
+        };
+
+        // Messages imported before the checkpoint tracked their ids can't be
+        // synchronized, so only messages above last_uid or that failed are imported.
+        let failed_uids = std::mem::take(&mut mailbox_checkpoint.failed_uids)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let mut server_uids = HashSet::with_capacity(server_messages.len());
+        let mut uids = Vec::new();
+        let mut keyword_updates = Vec::new();
+        for message in server_messages {
+            server_uids.insert(message.uid);
+            if let Some(imported) = mailbox_checkpoint.messages.get(&message.uid) {
+                if fetch_flags {
+                    let keywords = imap_keywords(&message.flags);
+                    if keywords != imported.keywords {
+                        keyword_updates.push((message.uid, imported.id.clone(), keywords));
+                    }
+                }
+            } else if message.uid > mailbox_checkpoint.last_uid
+                || failed_uids.contains(&message.uid)
+            {
+                uids.push(message.uid);
+            }
+        }
+        uids.sort_unstable();
+        uids.dedup();
+
+        // Remove messages that were expunged from the source folder
+        let mut is_synced = true;
+        let expunged = mailbox_checkpoint
+            .messages
+            .iter()
+            .filter(|(uid, _)| !server_uids.contains(uid))
+            .map(|(uid, message)| (message.id.clone(), *uid))
+            .collect::<HashMap<_, _>>();
+        for (id, result) in destroy_emails(
+            client,
+            expunged.keys().cloned().collect(),
+            max_objects_in_set,
+        )
+        .await
+        {
+            match result {
+                Ok(_) => {
+                    mailbox_checkpoint.messages.remove(&expunged[&id]);
+                }
+                Err(err) => {
+                    failures.push(format!(
+                        "Failed to remove expunged message {id} from folder {folder_name:?}: {err}"
+                    ));
+                    is_synced = false;
+                }
+            }
+        }
+
+        // Update the keywords of messages whose flags changed
+        for updates in keyword_updates.chunks(max_objects_in_set) {
+            let mut request = client.build();
+            let set_request = request.set_email();
+            for (_, id, keywords) in updates {
+                set_request.update(id).keywords(keywords.iter());
+            }
+            let mut response = match request.send_set_email().await {
+                Ok(response) => response,
+                Err(err) => {
+                    failures.push(format!(
+                        "Failed to update flags in folder {folder_name:?}: {err}"
+                    ));
+                    is_synced = false;
+                    continue;
+                }
+            };
+            for (uid, id, keywords) in updates {
+                match response.updated(id) {
+                    Ok(_) => {
+                        if let Some(imported) = mailbox_checkpoint.messages.get_mut(uid) {
+                            imported.keywords = keywords.clone();
+                        }
+                    }
+                    Err(err) => {
+                        failures.push(format!(
+                            "Failed to update flags of message {uid} from folder {:?}: {}",
+                            folder_name, err
+                        ));
+                        is_synced = false;
+                    }
+                }
+            }
+        }
+        mailbox_checkpoint.highest_modseq = status.highest_modseq.filter(|_| is_synced);
+
+        let pb = ProgressBar::new(uids.len() as u64);
+        pb.set_style(progress_style.clone());
+        pb.set_prefix(folder_name.clone());
+
+        let first_new_uid = mailbox_checkpoint.last_uid;
+        let mut lowest_failed_uid = None;
+        for batch in uids.chunks(num_concurrent) {
+            let mut batch_failures = Vec::new();
+            let messages = match imap
+                .uid_fetch(
+                    &batch
+                        .iter()
+                        .map(|uid| uid.to_string())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    "(UID FLAGS INTERNALDATE BODY.PEEK[])",
+                )
+                .await
+            {
+                Ok(messages) => messages,
+                Err(err) => {
+                    failures.push(format!(
+                        "Failed to fetch messages from folder {folder_name:?}: {err}"
+                    ));
+                    batch_failures.extend_from_slice(batch);
+                    pb.inc(batch.len() as u64);
+                    Vec::new()
+                }
+            };
+
+            // Messages expunged after the message list was fetched are not returned
+            if batch_failures.is_empty() {
+                let returned_uids = messages.iter().map(|m| m.uid).collect::<HashSet<_>>();
+                for uid in batch.iter().filter(|uid| !returned_uids.contains(uid)) {
+                    failures.push(format!(
+                        "Message {uid} from folder {folder_name:?} was not returned by the server"
+                    ));
+                    batch_failures.push(*uid);
+                    pb.inc(1);
+                }
+            }
+
+            let mut futures = FuturesUnordered::new();
+            for message in messages {
+                let mailbox_id = mailbox_id.as_str();
+                futures.push(async move {
+                    let contents = if let Some(contents) = message.contents {
+                        contents
+                    } else {
+                        return (message.uid, Err("No message contents returned".to_string()));
+                    };
+                    let keywords = imap_keywords(&message.flags);
+
+                    let mut retry_count = 0;
+                    loop {
+                        match client
+                            .email_import(
+                                contents.clone(),
+                                [mailbox_id],
+                                if !keywords.is_empty() {
+                                    Some(keywords.clone())
+                                } else {
+                                    None
+                                },
+                                message.internal_date,
+                            )
+                            .await
+                        {
+                            Ok(mut email) => {
+                                return (
+                                    message.uid,
+                                    Ok(ImapImportedMessage {
+                                        id: email.take_id(),
+                                        keywords,
+                                    }),
+                                )
+                            }
+                            Err(_) if retry_count < RETRY_ATTEMPTS => {
+                                retry_count += 1;
+                            }
+                            Err(err) => return (message.uid, Err(err.to_string())),
+                        }
+                    }
+                });
+            }
+
+            while let Some((uid, result)) = futures.next().await {
+                match result {
+                    Ok(imported) => {
+                        mailbox_checkpoint.messages.insert(uid, imported);
+                        total_imported += 1;
+                    }
+                    Err(err) => {
+                        failures.push(format!(
+                            "Failed to import message {uid} from folder {folder_name:?}: {err}"
+                        ));
+                        batch_failures.push(uid);
+                    }
+                }
+                pb.inc(1);
+            }
+
+            // Save progress after each batch so the import can be resumed, keeping
+            // last_uid below the lowest new message that could not be imported
+            lowest_failed_uid = batch_failures
+                .iter()
+                .copied()
+                .filter(|uid| *uid > first_new_uid)
+                .chain(lowest_failed_uid)
+                .min();
+            let last_uid = match lowest_failed_uid {
+                Some(uid) => uid - 1,
+                None => batch.iter().copied().max().unwrap_or_default(),
+            };
+            mailbox_checkpoint.last_uid = mailbox_checkpoint.last_uid.max(last_uid);
+            mailbox_checkpoint.failed_uids.extend(batch_failures);
+            checkpoint
+                .mailboxes
+                .insert(folder.name.clone(), mailbox_checkpoint.clone());
+            save_checkpoint(checkpoint_path, &checkpoint);
+        }
+
+        pb.finish();
+        checkpoint
+            .mailboxes
+            .insert(folder.name.clone(), mailbox_checkpoint);
+        save_checkpoint(checkpoint_path, &checkpoint);
+    }
+
+    eprintln!("\n\nSuccessfully imported {} messages.\n", total_imported);
+    if !failures.is_empty() {
+        eprintln!(
+            "There were {} failures, run the import again to retry them:\n",
+            failures.len()
+        );
+        for failure in failures.iter() {
+            eprintln!("{}", failure);
+        }
+    }
+}
+
+fn folder_role(folder: &Folder) -> Role {
+    match folder.special_use.as_deref() {
+        Some("archive") => Role::Archive,
+        Some("drafts") => Role::Drafts,
+        Some("junk") => Role::Junk,
+        Some("sent") => Role::Sent,
+        Some("trash") => Role::Trash,
+        _ => Role::None,
+    }
+}
+
+// Destroys the given emails, returning the result for each id.
+async fn destroy_emails(
+    client: &Client,
+    ids: Vec<String>,
+    max_objects_in_set: usize,
+) -> Vec<(String, Result<(), String>)> {
+    let mut results = Vec::with_capacity(ids.len());
+
+    for ids in ids.chunks(max_objects_in_set) {
+        let mut request = client.build();
+        request.set_email().destroy(ids);
+        match request.send_set_email().await {
+            Ok(mut response) => {
+                for id in ids {
+                    results.push((
+                        id.clone(),
+                        response.destroyed(id).map_err(|err| err.to_string()),
+                    ));
+                }
+            }
+            Err(err) => {
+                results.extend(ids.iter().map(|id| (id.clone(), Err(err.to_string()))));
+            }
+        }
+    }
+
+    results
+}
+
+fn imap_keywords(flags: &[String]) -> Vec<String> {
+    let mut keywords = flags
+        .iter()
+        .filter_map(|flag| match flag.to_ascii_lowercase().as_str() {
+            "\\seen" => Some("$seen".to_string()),
+            "\\answered" => Some("$answered".to_string()),
+            "\\flagged" => Some("$flagged".to_string()),
+            "\\draft" => Some("$draft".to_string()),
+            "\\deleted" => Some("$deleted".to_string()),
+            flag if flag.starts_with('\\') => None,
+            flag => Some(flag.to_string()),
+        })
+        .collect::<Vec<_>>();
+    keywords.sort_unstable();
+    keywords.dedup();
+    keywords
+}
+
+fn save_checkpoint(path: &Path, checkpoint: &ImapCheckpoint) {
+    std::fs::write(
+        path,
+        serde_json::to_string_pretty(checkpoint).unwrap_result("serialize checkpoint"),
+    )
+    .unwrap_result("write checkpoint file");
+}
+
+async fn import_account(client: &Client, path: &Path, num_concurrent: usize) {
+    import_emails(
+        client,
+        path,
+        import_mailboxes(client, path).await.into(),
+        num_concurrent,
+    )
+    .await;
+    import_sieve_scripts(client, path, num_concurrent).await;
+    import_identities(client, path).await;
+    import_vacation_responses(client, path).await;
+}
+
 async fn import_mailboxes(client: &Client, path: &Path) -> HashMap<String, String> {
     // Deserialize mailboxes
     let mailboxes = read_json::<jmap_client::mailbox::Mailbox>(path, "mailboxes.json").await;
@@ -939,7 +1551,57 @@ async fn import_vacation_responses(client: &Client, path: &Path) {
     }
 }
 
-fn build_mailbox_tree(
+async fn import_push_subscriptions(client: &Client, path: &Path) {
+    let push_subscriptions =
+        read_json::<jmap_client::push_subscription::PushSubscription>(path, "push.json").await;
+    if push_subscriptions.is_empty() {
+        return;
+    }
+    let existing_push_subscriptions = fetch_push_subscriptions(client).await;
+    let mut total_imported = 0;
+    let mut total_existing = 0;
+
+    for push_subscription in &push_subscriptions {
+        let (device_client_id, url) = if let (Some(device_client_id), Some(url)) = (
+            push_subscription.device_client_id(),
+            push_subscription.url(),
+        ) {
+            (device_client_id, url)
+        } else {
+            eprintln!("Skipping push subscription with no deviceClientId and/or url.");
+            continue;
+        };
+        if existing_push_subscriptions
+            .iter()
+            .any(|existing| existing.device_client_id() == Some(device_client_id))
+        {
+            total_existing += 1;
+            continue;
+        }
+
+        // The push service has to verify the subscription again before it is activated
+        match client
+            .push_subscription_create(device_client_id, url, push_subscription.keys().cloned())
+            .await
+        {
+            Ok(_) => {
+                total_imported += 1;
+            }
+            Err(err) => {
+                eprintln!("Failed to import push subscription for {device_client_id}: {err}");
+            }
+        }
+    }
+
+    eprintln!(
+        "Successfully processed {} push subscriptions ({} imported, {} already exist).",
+        total_imported + total_existing,
+        total_imported,
+        total_existing
+    );
+}
+
+pub fn build_mailbox_tree(
     mailboxes: &[jmap_client::mailbox::Mailbox],
 ) -> HashMap<Vec<&str>, &jmap_client::mailbox::Mailbox> {
     let mut path = Vec::new();
@@ -32,8 +32,10 @@ use jmap_client::{
 };
 
 pub mod cli;
+pub mod config;
 pub mod database;
 pub mod export;
+pub mod imap;
 pub mod import;
 pub mod queue;
 pub mod report;
@@ -64,6 +64,18 @@ pub struct Recipient {
     pub orcpt: Option<String>,
 }
 
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct AdaptiveLimit {
+    pub key: String,
+    pub concurrency: u64,
+    pub in_flight: u64,
+    pub rate: u64,
+    pub rate_period: u64,
+    pub deferrals: u64,
+    #[serde(deserialize_with = "deserialize_maybe_datetime")]
+    pub last_deferral: Option<DateTime>,
+}
+
 #[derive(Debug, PartialEq, Eq, Deserialize)]
 pub enum Status {
     #[serde(rename = "scheduled")]
@@ -394,6 +406,52 @@ pub async fn cmd_queue(url: &str, credentials: Credentials, command: QueueComman
             }
             eprintln!();
         }
+        QueueCommands::Limits { domain } => {
+            let mut query = form_urlencoded::Serializer::new(format!("{url}/admin/queue/limits?"));
+            if let Some(domain) = &domain {
+                query.append_pair("key", domain);
+            }
+            let limits =
+                smtp_manage_request::<Vec<AdaptiveLimit>>(&query.finish(), &credentials).await;
+
+            if !limits.is_empty() {
+                let mut table = Table::new();
+                table.add_row(Row::new(
+                    [
+                        "Host",
+                        "Concurrency",
+                        "In-flight",
+                        "Rate",
+                        "Deferrals",
+                        "Last Deferral",
+                    ]
+                    .iter()
+                    .map(|p| Cell::new(p).with_style(Attr::Bold))
+                    .collect(),
+                ));
+                for limit in &limits {
+                    table.add_row(Row::new(vec![
+                        Cell::new(&limit.key),
+                        Cell::new(&limit.concurrency.to_string()),
+                        Cell::new(&limit.in_flight.to_string()),
+                        Cell::new(&format!("{}/{}s", limit.rate, limit.rate_period)),
+                        Cell::new(&limit.deferrals.to_string()),
+                        Cell::new(
+                            &limit
+                                .last_deferral
+                                .as_ref()
+                                .map(|dt| dt.to_rfc822())
+                                .unwrap_or_else(|| "None".to_string()),
+                        ),
+                    ]));
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+            }
+            eprintln!("\n{} host(s) found.", limits.len());
+        }
     }
 }
 
@@ -21,6 +21,8 @@
  * for more details.
 */
 
+use std::net::IpAddr;
+
 use super::cli::{ReportCommands, ReportFormat};
 use crate::modules::queue::{deserialize_datetime, smtp_manage_request};
 use console::Term;
@@ -42,6 +44,29 @@ pub struct Report {
     pub size: usize,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncomingReport {
+    #[serde(rename = "type")]
+    pub type_: ReportFormat,
+    pub domains: Vec<String>,
+    pub reporter: String,
+    pub range_from: u64,
+    pub range_to: u64,
+    pub sources: Vec<SourceSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceSummary {
+    pub ip: Option<IpAddr>,
+    pub total: u64,
+    pub pass: u64,
+    pub fail: u64,
+    pub dkim_fail: u64,
+    pub spf_fail: u64,
+}
+
 pub async fn cmd_report(url: &str, credentials: Credentials, command: ReportCommands) {
     match command {
         ReportCommands::List {
@@ -186,7 +211,188 @@ pub async fn cmd_report(url: &str, credentials: Credentials, command: ReportComm
             }
             eprintln!();
         }
+        ReportCommands::Incoming {
+            domain,
+            reporter,
+            format,
+            from,
+            to,
+            page_size,
+        } => {
+            let stdout = Term::buffered_stdout();
+            let query = incoming_query(
+                format!("{url}/admin/incoming/list?"),
+                domain,
+                reporter,
+                format,
+                from,
+                to,
+            );
+
+            let ids = smtp_manage_request::<Vec<String>>(&query, &credentials).await;
+            let ids_len = ids.len();
+            let page_size = page_size.map(|p| std::cmp::max(p, 1)).unwrap_or(20);
+            let pages_total = (ids_len as f64 / page_size as f64).ceil() as usize;
+            for (page_num, chunk) in ids.chunks(page_size).enumerate() {
+                // Build table
+                let mut table = Table::new();
+                table.add_row(Row::new(
+                    [
+                        "ID",
+                        "Type",
+                        "Reporter",
+                        "Domains",
+                        "From Date",
+                        "To Date",
+                        "Pass",
+                        "Fail",
+                    ]
+                    .iter()
+                    .map(|p| Cell::new(p).with_style(Attr::Bold))
+                    .collect(),
+                ));
+                for (report, id) in smtp_manage_request::<Vec<Option<IncomingReport>>>(
+                    &format!("{url}/admin/incoming/status?ids={}", chunk.join(",")),
+                    &credentials,
+                )
+                .await
+                .into_iter()
+                .zip(chunk)
+                {
+                    if let Some(report) = report {
+                        table.add_row(Row::new(vec![
+                            Cell::new(id),
+                            Cell::new(report.type_.name()),
+                            Cell::new(&report.reporter),
+                            Cell::new(&report.domains.join(", ")),
+                            Cell::new(
+                                &DateTime::from_timestamp(report.range_from as i64).to_rfc822(),
+                            ),
+                            Cell::new(
+                                &DateTime::from_timestamp(report.range_to as i64).to_rfc822(),
+                            ),
+                            Cell::new(
+                                &report
+                                    .sources
+                                    .iter()
+                                    .map(|s| s.pass)
+                                    .sum::<u64>()
+                                    .to_string(),
+                            ),
+                            Cell::new(
+                                &report
+                                    .sources
+                                    .iter()
+                                    .map(|s| s.fail)
+                                    .sum::<u64>()
+                                    .to_string(),
+                            ),
+                        ]));
+                    }
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+                if page_num + 1 != pages_total {
+                    eprintln!("\n--- Press any key to continue or 'q' to exit ---");
+                    if let Ok('q' | 'Q') = stdout.read_char() {
+                        break;
+                    }
+                }
+            }
+            eprintln!("\n{ids_len} incoming report(s) found.")
+        }
+        ReportCommands::Summary {
+            domain,
+            reporter,
+            format,
+            from,
+            to,
+        } => {
+            let query = incoming_query(
+                format!("{url}/admin/incoming/summary?"),
+                domain,
+                reporter,
+                format,
+                from,
+                to,
+            );
+
+            let sources = smtp_manage_request::<Vec<SourceSummary>>(&query, &credentials).await;
+            if !sources.is_empty() {
+                let mut table = Table::new();
+                table.add_row(Row::new(
+                    [
+                        "Source IP",
+                        "Messages",
+                        "Pass",
+                        "Fail",
+                        "Pass Rate",
+                        "DKIM Fail",
+                        "SPF Fail",
+                    ]
+                    .iter()
+                    .map(|p| Cell::new(p).with_style(Attr::Bold))
+                    .collect(),
+                ));
+                for source in &sources {
+                    table.add_row(Row::new(vec![
+                        Cell::new(
+                            &source
+                                .ip
+                                .map(|ip| ip.to_string())
+                                .unwrap_or_else(|| "Unknown".to_string()),
+                        ),
+                        Cell::new(&source.total.to_string()),
+                        Cell::new(&source.pass.to_string()),
+                        Cell::new(&source.fail.to_string()),
+                        Cell::new(&if source.total > 0 {
+                            format!("{:.1}%", source.pass as f64 * 100.0 / source.total as f64)
+                        } else {
+                            "-".to_string()
+                        }),
+                        Cell::new(&source.dkim_fail.to_string()),
+                        Cell::new(&source.spf_fail.to_string()),
+                    ]));
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+            }
+            eprintln!("\n{} source(s) found.", sources.len())
+        }
+    }
+}
+
+fn incoming_query(
+    url: String,
+    domain: Option<String>,
+    reporter: Option<String>,
+    format: Option<ReportFormat>,
+    from: Option<DateTime>,
+    to: Option<DateTime>,
+) -> String {
+    let mut query = form_urlencoded::Serializer::new(url);
+
+    if let Some(domain) = &domain {
+        query.append_pair("domain", domain);
     }
+    if let Some(reporter) = &reporter {
+        query.append_pair("reporter", reporter);
+    }
+    if let Some(format) = &format {
+        query.append_pair("type", format.id());
+    }
+    if let Some(from) = from {
+        query.append_pair("from", &from.to_rfc3339());
+    }
+    if let Some(to) = to {
+        query.append_pair("to", &to.to_rfc3339());
+    }
+
+    query.finish()
 }
 
 impl ReportFormat {
@@ -194,6 +400,7 @@ impl ReportFormat {
         match self {
             ReportFormat::Dmarc => "dmarc",
             ReportFormat::Tls => "tls",
+            ReportFormat::Arf => "arf",
         }
     }
 
@@ -201,6 +408,7 @@ impl ReportFormat {
         match self {
             ReportFormat::Dmarc => "DMARC",
             ReportFormat::Tls => "TLS",
+            ReportFormat::Arf => "ARF",
         }
     }
 }
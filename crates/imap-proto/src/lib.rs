@@ -89,6 +89,20 @@ pub enum Command {
 
     // RFC 2971
     Id,
+
+    // RFC 5465
+    Notify,
+
+    // RFC 4978
+    Compress,
+
+    // RFC 4467
+    GenUrlAuth,
+    ResetKey,
+    UrlFetch,
+
+    // RFC 8508
+    Replace(bool),
 }
 
 impl Command {
@@ -103,6 +117,7 @@ impl Command {
                 | Command::Expunge(true)
                 | Command::Sort(true)
                 | Command::Thread(true)
+                | Command::Replace(true)
         )
     }
 }
@@ -167,6 +182,19 @@ pub enum ResponseCode {
 
     // USEATTR
     UseAttr,
+
+    // NOTIFY
+    BadEvent,
+    NotificationOverflow,
+
+    // COMPRESS
+    CompressionActive,
+
+    // CATENATE
+    BadUrl {
+        url: String,
+    },
+    TooBig,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
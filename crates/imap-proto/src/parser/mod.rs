@@ -24,6 +24,7 @@
 pub mod acl;
 pub mod append;
 pub mod authenticate;
+pub mod compress;
 pub mod copy_move;
 pub mod create;
 pub mod delete;
@@ -32,7 +33,9 @@ pub mod fetch;
 pub mod list;
 pub mod login;
 pub mod lsub;
+pub mod notify;
 pub mod rename;
+pub mod replace;
 pub mod search;
 pub mod select;
 pub mod sort;
@@ -40,13 +43,14 @@ pub mod status;
 pub mod store;
 pub mod subscribe;
 pub mod thread;
+pub mod urlauth;
 
 use std::{borrow::Cow, str::FromStr};
 
 use chrono::{DateTime, NaiveDate};
 
 use crate::{
-    protocol::{Flag, Sequence},
+    protocol::{Flag, PartialRange, Sequence},
     receiver::CommandParser,
     Command,
 };
@@ -94,6 +98,12 @@ impl CommandParser for Command {
             b"MYRIGHTS" => Some(Command::MyRights),
             b"UNAUTHENTICATE" => Some(Command::Unauthenticate),
             b"ID" => Some(Command::Id),
+            b"NOTIFY" => Some(Command::Notify),
+            b"COMPRESS" => Some(Command::Compress),
+            b"GENURLAUTH" => Some(Command::GenUrlAuth),
+            b"RESETKEY" => Some(Command::ResetKey),
+            b"URLFETCH" => Some(Command::UrlFetch),
+            b"REPLACE" => Some(Command::Replace(uid)),
             _ => None,
         }
     }
@@ -215,6 +225,29 @@ pub fn parse_number<T: FromStr>(value: &[u8]) -> Result<T> {
         .map_err(|_| Cow::from(format!("Expected a number, found {:?}.", string)))
 }
 
+pub fn parse_partial_range(value: &[u8]) -> Result<PartialRange> {
+    let value = std::str::from_utf8(value)
+        .map_err(|_| Cow::from("Expected a partial range, found an invalid UTF-8 string."))?;
+    let (start, end) = value
+        .split_once(':')
+        .ok_or_else(|| Cow::from(format!("Invalid partial range {:?}.", value)))?;
+    let from_end = start.starts_with('-');
+    if from_end != end.starts_with('-') {
+        return Err(Cow::from(format!("Invalid partial range {:?}.", value)));
+    }
+    let start = parse_number::<u32>(start.strip_prefix('-').unwrap_or(start).as_bytes())?;
+    let end = parse_number::<u32>(end.strip_prefix('-').unwrap_or(end).as_bytes())?;
+    if start == 0 || end == 0 {
+        return Err(Cow::from("Partial range values must be non-zero."));
+    }
+
+    Ok(PartialRange {
+        start: std::cmp::min(start, end),
+        end: std::cmp::max(start, end),
+        from_end,
+    })
+}
+
 pub fn parse_sequence_set(value: &[u8]) -> Result<Sequence> {
     let mut sequence_set = Vec::new();
 
@@ -0,0 +1,381 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{borrow::Cow, iter::Peekable, vec::IntoIter};
+
+use crate::{
+    protocol::{
+        fetch::Attribute,
+        notify::{self, Event, EventGroup, Filter},
+        ProtocolVersion,
+    },
+    receiver::{Receiver, Request, Token},
+    utf7::utf7_maybe_decode,
+    Command,
+};
+
+impl Request<Command> {
+    #[allow(clippy::while_let_on_iterator)]
+    pub fn parse_notify(self, version: ProtocolVersion) -> crate::Result<notify::Arguments> {
+        let mut tokens = self.tokens.into_iter().peekable();
+
+        match tokens.next() {
+            Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NONE") => {
+                if tokens.next().is_none() {
+                    Ok(notify::Arguments::None { tag: self.tag })
+                } else {
+                    Err((self.tag.as_str(), "Unexpected arguments after NONE.").into())
+                }
+            }
+            Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"SET") => {
+                let mut status = false;
+                let mut groups = Vec::new();
+
+                if tokens
+                    .peek()
+                    .map_or(false, |token| token.eq_ignore_ascii_case(b"STATUS"))
+                {
+                    tokens.next();
+                    status = true;
+                }
+
+                while let Some(token) = tokens.next() {
+                    if !token.is_parenthesis_open() {
+                        return Err((self.tag.as_str(), "Expected event group.").into());
+                    }
+                    let group = parse_event_group(&mut tokens, version)
+                        .map_err(|v| (self.tag.as_str(), v))?;
+                    if group.filter.is_selected()
+                        && groups
+                            .iter()
+                            .any(|group: &EventGroup| group.filter.is_selected())
+                    {
+                        return Err((
+                            self.tag.as_str(),
+                            "Only one selected or selected-delayed filter is allowed.",
+                        )
+                            .into());
+                    }
+                    groups.push(group);
+                }
+
+                if !groups.is_empty() {
+                    Ok(notify::Arguments::Set {
+                        tag: self.tag,
+                        status,
+                        groups,
+                    })
+                } else {
+                    Err((self.tag, "At least one event group is required.").into())
+                }
+            }
+            _ => Err((self.tag.as_str(), "Expected SET or NONE.").into()),
+        }
+    }
+}
+
+#[allow(clippy::while_let_on_iterator)]
+fn parse_event_group(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    version: ProtocolVersion,
+) -> super::Result<EventGroup> {
+    let filter = tokens
+        .next()
+        .ok_or_else(|| Cow::from("Missing mailbox filter."))?
+        .unwrap_bytes();
+    let filter = if filter.eq_ignore_ascii_case(b"selected") {
+        Filter::Selected
+    } else if filter.eq_ignore_ascii_case(b"selected-delayed") {
+        Filter::SelectedDelayed
+    } else if filter.eq_ignore_ascii_case(b"inboxes") {
+        Filter::Inboxes
+    } else if filter.eq_ignore_ascii_case(b"personal") {
+        Filter::Personal
+    } else if filter.eq_ignore_ascii_case(b"subscribed") {
+        Filter::Subscribed
+    } else if filter.eq_ignore_ascii_case(b"subtree") {
+        Filter::Subtree(parse_mailboxes(tokens, version)?)
+    } else if filter.eq_ignore_ascii_case(b"mailboxes") {
+        Filter::Mailboxes(parse_mailboxes(tokens, version)?)
+    } else {
+        return Err(format!(
+            "Invalid mailbox filter '{}'.",
+            String::from_utf8_lossy(&filter)
+        )
+        .into());
+    };
+
+    let mut events = Vec::new();
+    match tokens.next() {
+        Some(Token::ParenthesisOpen) => {
+            while let Some(token) = tokens.next() {
+                match token {
+                    Token::ParenthesisClose => break,
+                    Token::Argument(value) => {
+                        let mut event = Event::parse(&value)?;
+                        if let Event::MessageNew(attributes) = &mut event {
+                            if tokens
+                                .peek()
+                                .map_or(false, |token| token.is_parenthesis_open())
+                            {
+                                tokens.next();
+                                if !filter.is_selected() {
+                                    return Err(Cow::from(
+                                        "Fetch attributes are only allowed for the selected mailbox.",
+                                    ));
+                                }
+                                *attributes = parse_fetch_attributes(tokens)?;
+                            }
+                        }
+                        events.push(event);
+                    }
+                    _ => return Err(Cow::from("Invalid event.")),
+                }
+            }
+            if events.is_empty() {
+                return Err(Cow::from("At least one event is required."));
+            }
+        }
+        Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NONE") => {}
+        _ => return Err(Cow::from("Expected event list or NONE.")),
+    }
+    if tokens
+        .next()
+        .map_or(true, |token| !token.is_parenthesis_close())
+    {
+        return Err(Cow::from("Expected parenthesis after event list."));
+    }
+
+    // MessageNew and MessageExpunge have to be requested together
+    let has_new = events
+        .iter()
+        .any(|event| matches!(event, Event::MessageNew(_)));
+    let has_expunge = events.contains(&Event::MessageExpunge);
+    if has_new != has_expunge {
+        Err(Cow::from(
+            "MessageNew and MessageExpunge must be specified together.",
+        ))
+    } else if !has_new && events.contains(&Event::FlagChange) {
+        Err(Cow::from(
+            "FlagChange requires MessageNew and MessageExpunge.",
+        ))
+    } else {
+        Ok(EventGroup { filter, events })
+    }
+}
+
+#[allow(clippy::while_let_on_iterator)]
+fn parse_mailboxes(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    version: ProtocolVersion,
+) -> super::Result<Vec<String>> {
+    let mut mailboxes = Vec::new();
+    match tokens.next() {
+        Some(Token::ParenthesisOpen) => {
+            while let Some(token) = tokens.next() {
+                match token {
+                    Token::ParenthesisClose => break,
+                    token => {
+                        mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, version));
+                    }
+                }
+            }
+        }
+        Some(token) => {
+            mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, version));
+        }
+        None => (),
+    }
+
+    if !mailboxes.is_empty() {
+        Ok(mailboxes)
+    } else {
+        Err(Cow::from("Expected one or more mailbox names."))
+    }
+}
+
+fn parse_fetch_attributes(tokens: &mut Peekable<IntoIter<Token>>) -> super::Result<Vec<Attribute>> {
+    // Rebuild the attribute list and parse it as a FETCH command, as
+    // NOTIFY arguments are tokenized without splitting sections.
+    let mut fetch = b"0 FETCH 1 (".to_vec();
+    let mut depth = 0;
+    let mut last_token = Token::ParenthesisOpen;
+    loop {
+        let token = tokens
+            .next()
+            .ok_or_else(|| Cow::from("Unterminated fetch attribute list."))?;
+        match &token {
+            Token::ParenthesisOpen => {
+                depth += 1;
+            }
+            Token::ParenthesisClose if depth == 0 => break,
+            Token::ParenthesisClose => {
+                depth -= 1;
+            }
+            _ => (),
+        }
+        if !last_token.is_parenthesis_open()
+            && !token.is_parenthesis_close()
+            && !matches!(&token, Token::Argument(value) if value.first() == Some(&b']'))
+        {
+            fetch.push(b' ');
+        }
+        fetch.extend_from_slice(token.to_string().as_bytes());
+        last_token = token;
+    }
+    fetch.extend_from_slice(b")\r\n");
+
+    match Receiver::<Command>::new().parse(&mut fetch.iter()) {
+        Ok(request) => request
+            .parse_fetch()
+            .map(|arguments| arguments.attributes)
+            .map_err(|response| response.message),
+        Err(_) => Err(Cow::from("Invalid fetch attributes.")),
+    }
+}
+
+impl Event {
+    pub fn parse(value: &[u8]) -> super::Result<Self> {
+        if value.eq_ignore_ascii_case(b"MessageNew") {
+            Ok(Self::MessageNew(Vec::new()))
+        } else if value.eq_ignore_ascii_case(b"MessageExpunge") {
+            Ok(Self::MessageExpunge)
+        } else if value.eq_ignore_ascii_case(b"FlagChange") {
+            Ok(Self::FlagChange)
+        } else if value.eq_ignore_ascii_case(b"AnnotationChange") {
+            Ok(Self::AnnotationChange)
+        } else if value.eq_ignore_ascii_case(b"MailboxName") {
+            Ok(Self::MailboxName)
+        } else if value.eq_ignore_ascii_case(b"SubscriptionChange") {
+            Ok(Self::SubscriptionChange)
+        } else if value.eq_ignore_ascii_case(b"MailboxMetadataChange") {
+            Ok(Self::MailboxMetadataChange)
+        } else if value.eq_ignore_ascii_case(b"ServerMetadataChange") {
+            Ok(Self::ServerMetadataChange)
+        } else {
+            Err(format!("Invalid event '{}'.", String::from_utf8_lossy(value)).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{
+            fetch::{Attribute, Section},
+            notify::{self, Event, EventGroup, Filter},
+            ProtocolVersion,
+        },
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_notify() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A1 NOTIFY NONE\r\n",
+                notify::Arguments::None {
+                    tag: "A1".to_string(),
+                },
+            ),
+            (
+                concat!(
+                    "A2 NOTIFY SET STATUS (selected (MessageNew (UID ",
+                    "BODY.PEEK[HEADER.FIELDS (From Subject)]) MessageExpunge FlagChange)) ",
+                    "(subtree (INBOX \"Lists\") (MessageNew MessageExpunge MailboxName)) ",
+                    "(personal (MailboxName SubscriptionChange)) (mailboxes Drafts NONE)\r\n"
+                ),
+                notify::Arguments::Set {
+                    tag: "A2".to_string(),
+                    status: true,
+                    groups: vec![
+                        EventGroup {
+                            filter: Filter::Selected,
+                            events: vec![
+                                Event::MessageNew(vec![
+                                    Attribute::Uid,
+                                    Attribute::BodySection {
+                                        peek: true,
+                                        sections: vec![Section::HeaderFields {
+                                            not: false,
+                                            fields: vec!["From".to_string(), "Subject".to_string()],
+                                        }],
+                                        partial: None,
+                                    },
+                                ]),
+                                Event::MessageExpunge,
+                                Event::FlagChange,
+                            ],
+                        },
+                        EventGroup {
+                            filter: Filter::Subtree(vec!["INBOX".to_string(), "Lists".to_string()]),
+                            events: vec![
+                                Event::MessageNew(vec![]),
+                                Event::MessageExpunge,
+                                Event::MailboxName,
+                            ],
+                        },
+                        EventGroup {
+                            filter: Filter::Personal,
+                            events: vec![Event::MailboxName, Event::SubscriptionChange],
+                        },
+                        EventGroup {
+                            filter: Filter::Mailboxes(vec!["Drafts".to_string()]),
+                            events: vec![],
+                        },
+                    ],
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(ProtocolVersion::Rev2)
+                    .unwrap(),
+                arguments,
+                "{command}"
+            );
+        }
+
+        for command in [
+            "A3 NOTIFY SET (selected (MessageNew))\r\n",
+            "A4 NOTIFY SET (personal (FlagChange))\r\n",
+            "A5 NOTIFY SET (personal (MessageNew (UID) MessageExpunge))\r\n",
+            "A6 NOTIFY SET (selected (MessageNew MessageExpunge)) (selected-delayed (MessageNew MessageExpunge))\r\n",
+            "A7 NOTIFY SET\r\n",
+            "A8 NOTIFY SET (unknown (MailboxName))\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{command}"
+            );
+        }
+    }
+}
@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::{
+    protocol::{replace, ProtocolVersion},
+    receiver::Request,
+    Command,
+};
+
+use super::parse_number;
+
+impl Request<Command> {
+    pub fn parse_replace(mut self, version: ProtocolVersion) -> crate::Result<replace::Arguments> {
+        if self.tokens.len() < 3 {
+            return Err(self.into_error("Missing arguments."));
+        }
+
+        let id = parse_number::<u32>(&self.tokens.remove(0).unwrap_bytes())
+            .map_err(|v| (self.tag.as_str(), v))?;
+        if id == 0 {
+            return Err((self.tag, "Invalid message number.").into());
+        }
+        let mut arguments = self.parse_append(version)?;
+        if arguments.messages.len() == 1 {
+            Ok(replace::Arguments {
+                tag: arguments.tag,
+                id,
+                mailbox_name: arguments.mailbox_name,
+                message: arguments.messages.pop().unwrap(),
+            })
+        } else {
+            Err((arguments.tag, "Expected exactly one message.").into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        protocol::{append::Message, replace, Flag, ProtocolVersion},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_replace() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A003 REPLACE 4 Drafts (\\Seen \\Draft) {1+}\r\na\r\n",
+                replace::Arguments {
+                    tag: "A003".to_string(),
+                    id: 4,
+                    mailbox_name: "Drafts".to_string(),
+                    message: Message {
+                        message: vec![b'a'],
+                        flags: vec![Flag::Seen, Flag::Draft],
+                        received_at: None,
+                        catenate: vec![],
+                    },
+                },
+            ),
+            (
+                "A004 UID REPLACE 2000 \"Other Drafts\" \"7-Feb-1994 21:52:25 -0800\" {3+}\r\nabc\r\n",
+                replace::Arguments {
+                    tag: "A004".to_string(),
+                    id: 2000,
+                    mailbox_name: "Other Drafts".to_string(),
+                    message: Message {
+                        message: vec![b'a', b'b', b'c'],
+                        flags: vec![],
+                        received_at: Some(760686745),
+                        catenate: vec![],
+                    },
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_replace(ProtocolVersion::Rev2)
+                    .unwrap(),
+                arguments,
+                "{:?}",
+                command
+            );
+        }
+
+        for command in [
+            "A005 REPLACE 4 Drafts\r\n",
+            "A006 REPLACE 0 Drafts {1+}\r\na\r\n",
+            "A007 REPLACE 4 Drafts {1+}\r\na {1+}\r\nb\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_replace(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{:?}",
+                command
+            );
+        }
+    }
+}
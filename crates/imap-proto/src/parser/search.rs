@@ -34,7 +34,7 @@ use crate::protocol::{Flag, ProtocolVersion};
 use crate::receiver::{Request, Token};
 use crate::Command;
 
-use super::{parse_date, parse_number, parse_sequence_set};
+use super::{parse_date, parse_number, parse_partial_range, parse_sequence_set};
 
 impl Request<Command> {
     #[allow(clippy::while_let_on_iterator)]
@@ -95,9 +95,17 @@ pub fn parse_result_options(
         return Err(Cow::from("Invalid result option, expected parenthesis."));
     }
 
-    for token in tokens {
+    while let Some(token) = tokens.next() {
         match token {
             Token::ParenthesisClose => break,
+            Token::Argument(value) if value.eq_ignore_ascii_case(b"partial") => {
+                result_options.push(ResultOption::Partial(parse_partial_range(
+                    &tokens
+                        .next()
+                        .ok_or_else(|| Cow::from("Missing PARTIAL range."))?
+                        .unwrap_bytes(),
+                )?));
+            }
             Token::Argument(value) => {
                 result_options.push(ResultOption::parse(&value)?);
             }
@@ -323,6 +331,29 @@ pub fn parse_filters(
                             .ok_or_else(|| Cow::from("Expected an THREADID value."))?
                             .unwrap_string()?,
                     ));
+                } else if value.eq_ignore_ascii_case(b"SAVEDBEFORE") {
+                    filters.push(Filter::SavedBefore(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDON") {
+                    filters.push(Filter::SavedOn(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDSINCE") {
+                    filters.push(Filter::SavedSince(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDATESUPPORTED") {
+                    filters.push(Filter::SaveDateSupported);
                 } else if value.eq_ignore_ascii_case(b"OR") {
                     if filters_stack.len() > 10 {
                         return Err(Cow::from("Too many nested filters"));
@@ -343,6 +374,16 @@ pub fn parse_filters(
                     filters = Vec::with_capacity(1);
                     operator = Filter::Not;
                     continue;
+                } else if value.eq_ignore_ascii_case(b"FUZZY") {
+                    if filters_stack.len() > 10 {
+                        return Err(Cow::from("Too many nested filters"));
+                    }
+
+                    filters_stack.push((filters, operator, filters_len));
+                    filters_len = 0;
+                    filters = Vec::with_capacity(1);
+                    operator = Filter::Fuzzy;
+                    continue;
                 } else {
                     filters.push(Filter::Sequence(parse_sequence_set(&value)?, false));
                 }
@@ -373,7 +414,7 @@ pub fn parse_filters(
         if !filters_stack.is_empty()
             && (found_parenthesis
                 || (operator == Filter::Or && filters_len == 2)
-                || (operator == Filter::Not && filters_len == 1))
+                || ((operator == Filter::Not || operator == Filter::Fuzzy) && filters_len == 1))
         {
             while let Some((mut prev_filters, prev_operator, prev_filters_len)) =
                 filters_stack.pop()
@@ -430,6 +471,8 @@ impl ResultOption {
             Ok(Self::Save)
         } else if value.eq_ignore_ascii_case(b"context") {
             Ok(Self::Context)
+        } else if value.eq_ignore_ascii_case(b"relevancy") {
+            Ok(Self::Relevancy)
         } else {
             Err(format!("Invalid result option {:?}", String::from_utf8_lossy(value)).into())
         }
@@ -441,7 +484,7 @@ mod tests {
     use crate::{
         protocol::{
             search::{self, Filter, ModSeqEntry, ResultOption},
-            Flag, ProtocolVersion, Sequence,
+            Flag, PartialRange, ProtocolVersion, Sequence,
         },
         receiver::Receiver,
     };
@@ -468,6 +511,24 @@ mod tests {
                     sort: None,
                 },
             ),
+            (
+                b"A284 SEARCH RETURN (COUNT PARTIAL -100:-1) SAVEDSINCE 1-Feb-1994 SAVEDATESUPPORTED\r\n"
+                    .to_vec(),
+                search::Arguments {
+                    tag: "A284".to_string(),
+                    result_options: vec![
+                        ResultOption::Count,
+                        ResultOption::Partial(PartialRange {
+                            start: 1,
+                            end: 100,
+                            from_end: true,
+                        }),
+                    ],
+                    filter: vec![Filter::SavedSince(760060800), Filter::SaveDateSupported],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
             (
                 b"A283 SEARCH RETURN () FLAGGED SINCE 1-Feb-1994 NOT FROM \"Smith\"\r\n".to_vec(),
                 search::Arguments {
@@ -742,6 +803,27 @@ mod tests {
                     sort: None,
                 },
             ),
+            (
+                b"X SEARCH RETURN (RELEVANCY) FUZZY (SUBJECT \"hello\") OR FUZZY BODY world SEEN\r\n"
+                    .to_vec(),
+                search::Arguments {
+                    tag: "X".to_string(),
+                    result_options: vec![ResultOption::Relevancy],
+                    filter: vec![
+                        Filter::Fuzzy,
+                        Filter::Subject("hello".to_string()),
+                        Filter::End,
+                        Filter::Or,
+                        Filter::Fuzzy,
+                        Filter::Body("world".to_string()),
+                        Filter::End,
+                        Filter::Seen,
+                        Filter::End,
+                    ],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
         ] {
             let command_str = String::from_utf8_lossy(&command).into_owned();
             assert_eq!(
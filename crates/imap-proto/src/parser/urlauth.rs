@@ -0,0 +1,201 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::{
+    protocol::{urlauth, ProtocolVersion},
+    receiver::Request,
+    utf7::utf7_maybe_decode,
+    Command,
+};
+
+impl Request<Command> {
+    pub fn parse_genurlauth(self) -> crate::Result<urlauth::Arguments> {
+        if self.tokens.is_empty() {
+            return Err(self.into_error("Missing arguments."));
+        } else if self.tokens.len() % 2 != 0 {
+            return Err(self.into_error("Missing URLAUTH mechanism."));
+        }
+
+        let mut urls = Vec::with_capacity(self.tokens.len() / 2);
+        let mut tokens = self.tokens.into_iter();
+        while let Some(url) = tokens.next() {
+            urls.push(url.unwrap_string().map_err(|v| (self.tag.as_str(), v))?);
+            if !tokens.next().unwrap().eq_ignore_ascii_case(b"INTERNAL") {
+                return Err((self.tag.as_str(), "Unsupported URLAUTH mechanism.").into());
+            }
+        }
+
+        Ok(urlauth::Arguments {
+            tag: self.tag,
+            mailbox_name: None,
+            urls,
+        })
+    }
+
+    pub fn parse_resetkey(self, version: ProtocolVersion) -> crate::Result<urlauth::Arguments> {
+        let mut tokens = self.tokens.into_iter();
+        let mailbox_name = if let Some(token) = tokens.next() {
+            utf7_maybe_decode(
+                token.unwrap_string().map_err(|v| (self.tag.as_str(), v))?,
+                version,
+            )
+            .into()
+        } else {
+            None
+        };
+        for token in tokens {
+            if !token.eq_ignore_ascii_case(b"INTERNAL") {
+                return Err((self.tag.as_str(), "Unsupported URLAUTH mechanism.").into());
+            }
+        }
+
+        Ok(urlauth::Arguments {
+            tag: self.tag,
+            mailbox_name,
+            urls: vec![],
+        })
+    }
+
+    pub fn parse_urlfetch(self) -> crate::Result<urlauth::Arguments> {
+        if self.tokens.is_empty() {
+            return Err(self.into_error("Missing URLs."));
+        }
+
+        let mut urls = Vec::with_capacity(self.tokens.len());
+        for token in self.tokens {
+            urls.push(token.unwrap_string().map_err(|v| (self.tag.as_str(), v))?);
+        }
+
+        Ok(urlauth::Arguments {
+            tag: self.tag,
+            mailbox_name: None,
+            urls,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{urlauth, ProtocolVersion},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_urlauth() {
+        let mut receiver = Receiver::new();
+
+        assert_eq!(
+            receiver
+                .parse(
+                    &mut concat!(
+                        "a002 GENURLAUTH \"imap://joe@example.com/INBOX/;uid=20/;",
+                        "section=1.2;urlauth=submit+fred\" INTERNAL\r\n"
+                    )
+                    .as_bytes()
+                    .iter()
+                )
+                .unwrap()
+                .parse_genurlauth()
+                .unwrap(),
+            urlauth::Arguments {
+                tag: "a002".to_string(),
+                mailbox_name: None,
+                urls: vec![
+                    "imap://joe@example.com/INBOX/;uid=20/;section=1.2;urlauth=submit+fred"
+                        .to_string()
+                ],
+            }
+        );
+
+        assert_eq!(
+            receiver
+                .parse(
+                    &mut concat!(
+                        "b120 URLFETCH \"imap://joe@example.com/INBOX/;uid=20/;",
+                        "section=1.2;urlauth=submit+fred:internal:91354a47\" ",
+                        "\"imap://joe@example.com/INBOX/;uid=21;urlauth=anonymous:internal:ab\"\r\n"
+                    )
+                    .as_bytes()
+                    .iter()
+                )
+                .unwrap()
+                .parse_urlfetch()
+                .unwrap(),
+            urlauth::Arguments {
+                tag: "b120".to_string(),
+                mailbox_name: None,
+                urls: vec![
+                    concat!(
+                        "imap://joe@example.com/INBOX/;uid=20/;",
+                        "section=1.2;urlauth=submit+fred:internal:91354a47"
+                    )
+                    .to_string(),
+                    "imap://joe@example.com/INBOX/;uid=21;urlauth=anonymous:internal:ab"
+                        .to_string()
+                ],
+            }
+        );
+
+        for (command, mailbox_name) in [
+            ("a001 RESETKEY\r\n", None),
+            (
+                "a001 RESETKEY INBOX INTERNAL\r\n",
+                Some("INBOX".to_string()),
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_resetkey(ProtocolVersion::Rev2)
+                    .unwrap(),
+                urlauth::Arguments {
+                    tag: "a001".to_string(),
+                    mailbox_name,
+                    urls: vec![],
+                },
+                "{command}"
+            );
+        }
+
+        for command in [
+            "a003 GENURLAUTH \"imap://joe@example.com/INBOX/;uid=20;urlauth=anonymous\"\r\n",
+            "a003 GENURLAUTH \"imap://joe@example.com/INBOX/;uid=20;urlauth=anonymous\" XSAMPLE\r\n",
+            "a004 RESETKEY INBOX XSAMPLE\r\n",
+            "a005 URLFETCH\r\n",
+        ] {
+            let request = receiver.parse(&mut command.as_bytes().iter()).unwrap();
+            assert!(
+                match request.command {
+                    crate::Command::GenUrlAuth => request.parse_genurlauth().is_err(),
+                    crate::Command::ResetKey => request
+                        .parse_resetkey(ProtocolVersion::Rev2)
+                        .is_err(),
+                    _ => request.parse_urlfetch().is_err(),
+                },
+                "{command}"
+            );
+        }
+    }
+}
@@ -64,6 +64,14 @@ pub enum Capability {
     ObjectId,
     Preview,
     Utf8Accept,
+    Notify,
+    Compress, //COMPRESS=DEFLATE
+    UrlAuth,
+    Catenate,
+    Replace,
+    SaveDate,
+    Partial,
+    SearchFuzzy, //SEARCH=FUZZY
     Auth(Mechanism),
 }
 
@@ -109,6 +117,14 @@ impl Capability {
             Capability::CreateSpecialUse => b"CREATE-SPECIAL-USE",
             Capability::Move => b"MOVE",
             Capability::Utf8Accept => b"UTF8=ACCEPT",
+            Capability::Notify => b"NOTIFY",
+            Capability::Compress => b"COMPRESS=DEFLATE",
+            Capability::UrlAuth => b"URLAUTH",
+            Capability::Catenate => b"CATENATE",
+            Capability::Replace => b"REPLACE",
+            Capability::SaveDate => b"SAVEDATE",
+            Capability::Partial => b"PARTIAL",
+            Capability::SearchFuzzy => b"SEARCH=FUZZY",
         });
     }
 
@@ -150,6 +166,14 @@ impl Capability {
                 Capability::StatusSize,
                 Capability::ObjectId,
                 Capability::Preview,
+                Capability::Notify,
+                Capability::Compress,
+                Capability::UrlAuth,
+                Capability::Catenate,
+                Capability::Replace,
+                Capability::SaveDate,
+                Capability::Partial,
+                Capability::SearchFuzzy,
             ]);
         } else {
             capabilties.extend([
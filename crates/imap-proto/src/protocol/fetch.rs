@@ -23,11 +23,11 @@
 
 use std::borrow::Cow;
 
-use mail_parser::DateTime;
+use mail_parser::{DateTime, Message, PartType};
 
 use super::{
     literal_string, quoted_rfc2822_or_nil, quoted_string, quoted_string_or_nil, quoted_timestamp,
-    Flag, ImapResponse, Sequence,
+    Flag, ImapResponse, PartialRange, Sequence,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -37,6 +37,7 @@ pub struct Arguments {
     pub attributes: Vec<Attribute>,
     pub changed_since: Option<u64>,
     pub include_vanished: bool,
+    pub partial: Option<PartialRange>,
 }
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Response<'x> {
@@ -81,6 +82,7 @@ pub enum Attribute {
     ModSeq,
     EmailId,
     ThreadId,
+    SaveDate,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -150,6 +152,9 @@ pub enum DataItem<'x> {
     ThreadId {
         thread_id: String,
     },
+    SaveDate {
+        date: Option<i64>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -841,6 +846,14 @@ impl<'x> DataItem<'x> {
                 buf.extend_from_slice(thread_id.as_bytes());
                 buf.push(b')');
             }
+            DataItem::SaveDate { date } => {
+                buf.extend_from_slice(b"SAVEDATE ");
+                if let Some(date) = date {
+                    quoted_timestamp(buf, *date);
+                } else {
+                    buf.extend_from_slice(b"NIL");
+                }
+            }
         }
     }
 }
@@ -910,10 +923,156 @@ impl<'x> ImapResponse for Response<'x> {
 
 */
 
+pub fn body_section<'x, 'z: 'x>(
+    mut message: &'z Message<'x>,
+    sections: &[Section],
+    partial: Option<(u32, u32)>,
+) -> Option<Cow<'x, [u8]>> {
+    let mut part = message.root_part();
+    if sections.is_empty() {
+        return Some(
+            get_partial_bytes(
+                message
+                    .raw_message
+                    .get(part.offset_header..part.offset_end)?,
+                partial,
+            )
+            .into(),
+        );
+    }
+
+    let mut sections_iter = sections.iter().enumerate().peekable();
+
+    while let Some((section_num, section)) = sections_iter.next() {
+        match section {
+            Section::Part { num } => {
+                part = if let Some(sub_part_ids) = part.sub_parts() {
+                    sub_part_ids
+                        .get((*num).saturating_sub(1) as usize)
+                        .and_then(|pos| message.parts.get(*pos))
+                } else if *num == 1 && (section_num == sections.len() - 1 || part.is_message()) {
+                    Some(part)
+                } else {
+                    None
+                }?;
+
+                if let (
+                    PartType::Message(nested_message),
+                    Some((
+                        _,
+                        Section::Part { .. }
+                        | Section::Header
+                        | Section::HeaderFields { .. }
+                        | Section::Text,
+                    )),
+                ) = (&part.body, sections_iter.peek())
+                {
+                    message = nested_message;
+                    part = message.root_part();
+                }
+            }
+            Section::Header => {
+                return Some(
+                    get_partial_bytes(
+                        message
+                            .raw_message
+                            .get(part.offset_header..part.offset_body)?,
+                        partial,
+                    )
+                    .into(),
+                );
+            }
+            Section::HeaderFields { not, fields } => {
+                let mut headers =
+                    Vec::with_capacity(part.offset_body.saturating_sub(part.offset_header));
+                for header in &part.headers {
+                    let header_name = header.name.as_str();
+                    if fields.iter().any(|f| header_name.eq_ignore_ascii_case(f)) != *not {
+                        headers.extend_from_slice(header_name.as_bytes());
+                        headers.push(b':');
+                        headers.extend_from_slice(
+                            message
+                                .raw_message
+                                .get(header.offset_start..header.offset_end)
+                                .unwrap_or(b""),
+                        );
+                    }
+                }
+
+                headers.extend_from_slice(b"\r\n");
+
+                return Some(if partial.is_none() {
+                    headers.into()
+                } else {
+                    get_partial_bytes(&headers, partial).to_vec().into()
+                });
+            }
+            Section::Text => {
+                return Some(
+                    get_partial_bytes(
+                        message.raw_message.get(part.offset_body..part.offset_end)?,
+                        partial,
+                    )
+                    .into(),
+                );
+            }
+            Section::Mime => {
+                let mut headers =
+                    Vec::with_capacity(part.offset_body.saturating_sub(part.offset_header));
+                for header in &part.headers {
+                    if header.name.is_mime_header() || header.name.as_str().starts_with("Content-")
+                    {
+                        headers.extend_from_slice(header.name.as_str().as_bytes());
+                        headers.extend_from_slice(b":");
+                        headers.extend_from_slice(
+                            message
+                                .raw_message
+                                .get(header.offset_start..header.offset_end)
+                                .unwrap_or(b""),
+                        );
+                    }
+                }
+                headers.extend_from_slice(b"\r\n");
+                return Some(if partial.is_none() {
+                    headers.into()
+                } else {
+                    get_partial_bytes(&headers, partial).to_vec().into()
+                });
+            }
+        }
+    }
+
+    // BODY[x] should return both headers and body, but most clients
+    // expect BODY[x] to return only the body, just like BOXY[x.TEXT] does.
+
+    Some(
+        get_partial_bytes(
+            message.raw_message.get(part.offset_body..part.offset_end)?,
+            partial,
+        )
+        .into(),
+    )
+}
+
+#[inline(always)]
+pub fn get_partial_bytes(bytes: &[u8], partial: Option<(u32, u32)>) -> &[u8] {
+    if let Some((start, end)) = partial {
+        if let Some(bytes) =
+            bytes.get(start as usize..std::cmp::min((start + end) as usize, bytes.len()))
+        {
+            bytes
+        } else {
+            &[]
+        }
+    } else {
+        bytes
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use mail_parser::DateTime;
+    use mail_parser::{DateTime, Message, PartType};
 
     use crate::protocol::{Flag, ImapResponse};
 
@@ -1355,6 +1514,13 @@ mod tests {
                 super::DataItem::InternalDate { date: 482374938 },
                 "INTERNALDATE \"15-Apr-1985 01:02:18 +0000\"",
             ),
+            (
+                super::DataItem::SaveDate {
+                    date: Some(482374938),
+                },
+                "SAVEDATE \"15-Apr-1985 01:02:18 +0000\"",
+            ),
+            (super::DataItem::SaveDate { date: None }, "SAVEDATE NIL"),
         ] {
             let mut buf = Vec::with_capacity(100);
 
@@ -33,6 +33,7 @@ pub mod acl;
 pub mod append;
 pub mod authenticate;
 pub mod capability;
+pub mod compress;
 pub mod copy_move;
 pub mod create;
 pub mod delete;
@@ -42,13 +43,16 @@ pub mod fetch;
 pub mod list;
 pub mod login;
 pub mod namespace;
+pub mod notify;
 pub mod rename;
+pub mod replace;
 pub mod search;
 pub mod select;
 pub mod status;
 pub mod store;
 pub mod subscribe;
 pub mod thread;
+pub mod urlauth;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProtocolVersion {
@@ -164,6 +168,42 @@ impl Sequence {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialRange {
+    pub start: u32,
+    pub end: u32,
+    pub from_end: bool,
+}
+
+impl PartialRange {
+    pub fn apply<T>(&self, items: &mut Vec<T>) {
+        let len = items.len();
+        let (from, to) = if !self.from_end {
+            ((self.start - 1) as usize, self.end as usize)
+        } else {
+            (
+                len.saturating_sub(self.end as usize),
+                len.saturating_sub((self.start - 1) as usize),
+            )
+        };
+        let to = std::cmp::min(to, len);
+        items.truncate(to);
+        items.drain(..std::cmp::min(from, to));
+    }
+
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        if self.from_end {
+            buf.push(b'-');
+        }
+        buf.extend_from_slice(self.start.to_string().as_bytes());
+        buf.push(b':');
+        if self.from_end {
+            buf.push(b'-');
+        }
+        buf.extend_from_slice(self.end.to_string().as_bytes());
+    }
+}
+
 pub trait ImapResponse {
     fn serialize(self) -> Vec<u8>;
 }
@@ -377,6 +417,17 @@ impl ResponseCode {
                 return;
             }
             ResponseCode::UseAttr => b"USEATTR",
+            ResponseCode::BadEvent => {
+                b"BADEVENT (MessageNew MessageExpunge FlagChange MailboxName SubscriptionChange)"
+            }
+            ResponseCode::NotificationOverflow => b"NOTIFICATIONOVERFLOW",
+            ResponseCode::CompressionActive => b"COMPRESSIONACTIVE",
+            ResponseCode::BadUrl { url } => {
+                buf.extend_from_slice(b"BADURL ");
+                buf.extend_from_slice(url.as_bytes());
+                return;
+            }
+            ResponseCode::TooBig => b"TOOBIG",
         });
     }
 }
@@ -505,13 +556,20 @@ impl Display for Command {
             Command::MyRights => write!(f, "MYRIGHTS"),
             Command::Unauthenticate => write!(f, "UNAUTHENTICATE"),
             Command::Id => write!(f, "ID"),
+            Command::Notify => write!(f, "NOTIFY"),
+            Command::Compress => write!(f, "COMPRESS"),
+            Command::GenUrlAuth => write!(f, "GENURLAUTH"),
+            Command::ResetKey => write!(f, "RESETKEY"),
+            Command::UrlFetch => write!(f, "URLFETCH"),
+            Command::Replace(false) => write!(f, "REPLACE"),
+            Command::Replace(true) => write!(f, "UID REPLACE"),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::parse_sequence_set;
+    use crate::parser::{parse_partial_range, parse_sequence_set};
 
     #[test]
     fn sequence_set_contains() {
@@ -531,4 +589,31 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn partial_range_apply() {
+        for (range, expected_result) in [
+            ("1:3", vec![1, 2, 3]),
+            ("3:1", vec![1, 2, 3]),
+            ("4:20", vec![4, 5, 6, 7, 8, 9, 10]),
+            ("-1:-3", vec![8, 9, 10]),
+            ("-3:-1", vec![8, 9, 10]),
+            ("-5:-20", vec![1, 2, 3, 4, 5, 6]),
+            ("11:20", vec![]),
+            ("-11:-20", vec![]),
+        ] {
+            let mut items = (1..=10).collect::<Vec<u32>>();
+            parse_partial_range(range.as_bytes())
+                .unwrap()
+                .apply(&mut items);
+            assert_eq!(items, expected_result, "{range}");
+        }
+
+        for invalid in ["0:5", "-1:5", "1", "a:b", "1:-5"] {
+            assert!(
+                parse_partial_range(invalid.as_bytes()).is_err(),
+                "{invalid}"
+            );
+        }
+    }
 }
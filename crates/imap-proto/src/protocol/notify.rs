@@ -0,0 +1,108 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use super::fetch::Attribute;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arguments {
+    Set {
+        tag: String,
+        status: bool,
+        groups: Vec<EventGroup>,
+    },
+    None {
+        tag: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventGroup {
+    pub filter: Filter,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Selected,
+    SelectedDelayed,
+    Inboxes,
+    Personal,
+    Subscribed,
+    Subtree(Vec<String>),
+    Mailboxes(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    MessageNew(Vec<Attribute>),
+    MessageExpunge,
+    FlagChange,
+    AnnotationChange,
+    MailboxName,
+    SubscriptionChange,
+    MailboxMetadataChange,
+    ServerMetadataChange,
+}
+
+impl Arguments {
+    pub fn unwrap_tag(self) -> String {
+        match self {
+            Arguments::Set { tag, .. } => tag,
+            Arguments::None { tag } => tag,
+        }
+    }
+}
+
+impl Filter {
+    pub fn is_selected(&self) -> bool {
+        matches!(self, Filter::Selected | Filter::SelectedDelayed)
+    }
+}
+
+impl EventGroup {
+    pub fn has_message_events(&self) -> bool {
+        self.events
+            .iter()
+            .any(|event| matches!(event, Event::MessageNew(_)))
+    }
+
+    pub fn has_event(&self, event: &Event) -> bool {
+        self.events.contains(event)
+    }
+
+    pub fn fetch_attributes(&self) -> Option<&[Attribute]> {
+        self.events.iter().find_map(|event| match event {
+            Event::MessageNew(attributes) if !attributes.is_empty() => Some(attributes.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+impl Event {
+    pub fn is_supported(&self) -> bool {
+        !matches!(
+            self,
+            Event::AnnotationChange | Event::MailboxMetadataChange | Event::ServerMetadataChange
+        )
+    }
+}
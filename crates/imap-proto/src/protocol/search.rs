@@ -21,7 +21,7 @@
  * for more details.
 */
 
-use super::{quoted_string, serialize_sequence, Flag, Sequence};
+use super::{quoted_string, serialize_sequence, Flag, PartialRange, Sequence};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Arguments {
@@ -43,6 +43,7 @@ pub enum Sort {
     Subject,
     To,
     DisplayTo,
+    Relevancy,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -60,6 +61,8 @@ pub struct Response {
     pub min: Option<u32>,
     pub max: Option<u32>,
     pub count: Option<u32>,
+    pub partial: Option<(PartialRange, Vec<u32>)>,
+    pub relevancy: Option<Vec<u32>>,
     pub highest_modseq: Option<u64>,
 }
 
@@ -71,6 +74,8 @@ pub enum ResultOption {
     Count,
     Save,
     Context,
+    Partial(PartialRange),
+    Relevancy,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -127,6 +132,15 @@ pub enum Filter {
     // RFC 8474 - ObjectID
     EmailId(String),
     ThreadId(String),
+
+    // RFC 8514 - SAVEDATE
+    SavedBefore(i64),
+    SavedOn(i64),
+    SavedSince(i64),
+    SaveDateSupported,
+
+    // RFC 6203 - FUZZY
+    Fuzzy,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -173,6 +187,27 @@ impl Response {
                 buf.extend_from_slice(b" ALL ");
                 serialize_sequence(&mut buf, &self.ids);
             }
+            if let Some((range, ids)) = &self.partial {
+                buf.extend_from_slice(b" PARTIAL (");
+                range.serialize(&mut buf);
+                buf.push(b' ');
+                if !ids.is_empty() {
+                    serialize_sequence(&mut buf, ids);
+                } else {
+                    buf.extend_from_slice(b"NIL");
+                }
+                buf.push(b')');
+            }
+            if let Some(relevancy) = &self.relevancy {
+                buf.extend_from_slice(b" RELEVANCY (");
+                for (pos, score) in relevancy.iter().enumerate() {
+                    if pos > 0 {
+                        buf.push(b' ');
+                    }
+                    buf.extend_from_slice(score.to_string().as_bytes());
+                }
+                buf.push(b')');
+            }
             if let Some(highest_modseq) = self.highest_modseq {
                 buf.extend_from_slice(b" MODSEQ ");
                 buf.extend_from_slice(highest_modseq.to_string().as_bytes());
@@ -215,6 +250,8 @@ mod tests {
                     min: 2.into(),
                     max: 11.into(),
                     count: 3.into(),
+                    partial: None,
+                    relevancy: None,
                     highest_modseq: None,
                 },
                 "A283",
@@ -232,6 +269,8 @@ mod tests {
                     min: None,
                     max: None,
                     count: None,
+                    partial: None,
+                    relevancy: None,
                     highest_modseq: None,
                 },
                 "A283",
@@ -247,6 +286,8 @@ mod tests {
                     min: None,
                     max: None,
                     count: None,
+                    partial: None,
+                    relevancy: None,
                     highest_modseq: None,
                 },
                 "A283",
@@ -262,12 +303,79 @@ mod tests {
                     min: None,
                     max: None,
                     count: None,
+                    partial: None,
+                    relevancy: None,
                     highest_modseq: 12345.into(),
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\") ALL 10:13,21 MODSEQ 12345\r\n",),
                 concat!("* SEARCH 10 11 12 13 21 (MODSEQ 12345)\r\n",),
             ),
+            (
+                super::Response {
+                    is_uid: true,
+                    is_esearch: true,
+                    is_sort: false,
+                    ids: vec![],
+                    min: None,
+                    max: None,
+                    count: None,
+                    partial: Some((
+                        super::PartialRange {
+                            start: 1,
+                            end: 5,
+                            from_end: true,
+                        },
+                        vec![8, 9, 10, 12],
+                    )),
+                    relevancy: None,
+                    highest_modseq: None,
+                },
+                "A284",
+                concat!("* ESEARCH (TAG \"A284\") UID PARTIAL (-1:-5 8:10,12)\r\n",),
+                concat!("* SEARCH\r\n"),
+            ),
+            (
+                super::Response {
+                    is_uid: false,
+                    is_esearch: true,
+                    is_sort: false,
+                    ids: vec![],
+                    min: None,
+                    max: None,
+                    count: 0.into(),
+                    partial: Some((
+                        super::PartialRange {
+                            start: 1,
+                            end: 100,
+                            from_end: false,
+                        },
+                        vec![],
+                    )),
+                    relevancy: None,
+                    highest_modseq: None,
+                },
+                "A285",
+                concat!("* ESEARCH (TAG \"A285\") COUNT 0 PARTIAL (1:100 NIL)\r\n",),
+                concat!("* SEARCH\r\n"),
+            ),
+            (
+                super::Response {
+                    is_uid: false,
+                    is_esearch: true,
+                    is_sort: false,
+                    ids: vec![2, 5, 7],
+                    min: None,
+                    max: None,
+                    count: None,
+                    partial: None,
+                    relevancy: vec![99, 3, 42].into(),
+                    highest_modseq: None,
+                },
+                "A286",
+                concat!("* ESEARCH (TAG \"A286\") ALL 2,5,7 RELEVANCY (99 3 42)\r\n",),
+                concat!("* SEARCH 2 5 7\r\n"),
+            ),
         ] {
             let response_v2 = String::from_utf8(response.clone().serialize(tag)).unwrap();
             response.is_esearch = false;
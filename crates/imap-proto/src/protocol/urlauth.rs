@@ -0,0 +1,348 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use chrono::DateTime;
+
+use super::{fetch::Section, literal_string, quoted_string};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arguments {
+    pub tag: String,
+    pub mailbox_name: Option<String>,
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenUrlAuthResponse {
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlFetchResponse {
+    pub items: Vec<(String, Option<Vec<u8>>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImapUrl {
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub mailbox_name: String,
+    pub uid_validity: Option<u32>,
+    pub uid: u32,
+    pub sections: Vec<Section>,
+    pub partial: Option<(u32, u32)>,
+    pub expire: Option<i64>,
+    pub access: Option<Access>,
+    pub token: Option<String>,
+    pub rump: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Access {
+    Submit(String),
+    User(String),
+    AuthUser,
+    Anonymous,
+}
+
+impl ImapUrl {
+    pub fn parse(url: &str) -> Option<Self> {
+        // Obtain user and host from absolute URLs, relative URLs
+        // start directly with the mailbox path.
+        let (user, host, path) = if url
+            .get(..7)
+            .map_or(false, |p| p.eq_ignore_ascii_case("imap://"))
+        {
+            let (authority, path) = url[7..].split_once('/')?;
+            if let Some((user, host)) = authority.rsplit_once('@') {
+                let user = user.split_once(';').map_or(user, |(user, _)| user);
+                (Some(url_decode(user)?), Some(host.to_string()), path)
+            } else {
+                (None, Some(authority.to_string()), path)
+            }
+        } else {
+            (None, None, url.strip_prefix('/')?)
+        };
+
+        // Split mailbox and message parts
+        let path_lower = path.to_ascii_lowercase();
+        let uid_pos = path_lower.find("/;uid=")?;
+        let mut mailbox_name = &path[..uid_pos];
+        let mut uid_validity = None;
+        if let Some(pos) = path_lower[..uid_pos].find(";uidvalidity=") {
+            uid_validity = path[pos + 13..uid_pos].parse::<u32>().ok()?.into();
+            mailbox_name = &path[..pos];
+        }
+        let mailbox_name = url_decode(mailbox_name)?;
+
+        // Parse message part
+        let mut params = path[uid_pos + 6..].split(';').peekable();
+        let uid = params
+            .next()?
+            .trim_end_matches('/')
+            .parse::<u32>()
+            .ok()
+            .filter(|uid| *uid > 0)?;
+        let mut sections = Vec::new();
+        let mut partial = None;
+        let mut expire = None;
+        let mut access = None;
+        let mut token = None;
+        let mut rump = url.to_string();
+
+        while let Some(param) = params.next() {
+            let (name, value) = param.split_once('=')?;
+            let value = value.trim_end_matches('/');
+            if name.eq_ignore_ascii_case("SECTION") {
+                sections = parse_section(&url_decode(value)?)?;
+            } else if name.eq_ignore_ascii_case("PARTIAL") {
+                partial = Some(if let Some((offset, length)) = value.split_once('.') {
+                    (offset.parse().ok()?, length.parse().ok()?)
+                } else {
+                    let offset = value.parse::<u32>().ok()?;
+                    (offset, u32::MAX - offset)
+                });
+            } else if name.eq_ignore_ascii_case("EXPIRE") {
+                expire = DateTime::parse_from_rfc3339(value).ok()?.timestamp().into();
+            } else if name.eq_ignore_ascii_case("URLAUTH") {
+                let mut parts = value.splitn(3, ':');
+                let access_id = parts.next()?;
+                access = Access::parse(access_id)?.into();
+
+                // URLAUTH has to be the last component, the rump URL is
+                // everything up to and including the access identifier.
+                if params.peek().is_some() {
+                    return None;
+                }
+                let rump_len = url.len() - param.len() + name.len() + 1 + access_id.len();
+                rump = url[..rump_len].to_string();
+
+                if let Some(mechanism) = parts.next() {
+                    if mechanism.eq_ignore_ascii_case("INTERNAL") {
+                        token = parts
+                            .next()
+                            .filter(|token| {
+                                !token.is_empty() && token.bytes().all(|c| c.is_ascii_hexdigit())
+                            })?
+                            .to_ascii_lowercase()
+                            .into();
+                    } else {
+                        return None;
+                    }
+                }
+            } else {
+                return None;
+            }
+        }
+
+        Some(ImapUrl {
+            user,
+            host,
+            mailbox_name,
+            uid_validity,
+            uid,
+            sections,
+            partial,
+            expire,
+            access,
+            token,
+            rump,
+        })
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.host.is_none()
+    }
+}
+
+impl Access {
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.eq_ignore_ascii_case("anonymous") {
+            Some(Access::Anonymous)
+        } else if value.eq_ignore_ascii_case("authuser") {
+            Some(Access::AuthUser)
+        } else {
+            let (application, user) = value.split_once('+')?;
+            let user = url_decode(user).filter(|user| !user.is_empty())?;
+            if application.eq_ignore_ascii_case("submit") {
+                Some(Access::Submit(user))
+            } else if application.eq_ignore_ascii_case("user") {
+                Some(Access::User(user))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl GenUrlAuthResponse {
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(b"* GENURLAUTH");
+        for url in &self.urls {
+            buf.push(b' ');
+            quoted_string(&mut buf, url);
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+}
+
+impl UrlFetchResponse {
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(
+            self.items
+                .iter()
+                .map(|(url, data)| url.len() + data.as_ref().map_or(3, |d| d.len() + 10))
+                .sum::<usize>()
+                + 16,
+        );
+        buf.extend_from_slice(b"* URLFETCH");
+        for (url, data) in &self.items {
+            buf.push(b' ');
+            quoted_string(&mut buf, url);
+            buf.push(b' ');
+            if let Some(data) = data {
+                literal_string(&mut buf, data);
+            } else {
+                buf.extend_from_slice(b"NIL");
+            }
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+}
+
+fn parse_section(value: &str) -> Option<Vec<Section>> {
+    let mut sections = Vec::new();
+    for part in value.split('.') {
+        sections.push(if let Ok(num) = part.parse::<u32>() {
+            if num == 0 {
+                return None;
+            }
+            Section::Part { num }
+        } else if part.eq_ignore_ascii_case("HEADER") {
+            Section::Header
+        } else if part.eq_ignore_ascii_case("TEXT") {
+            Section::Text
+        } else if part.eq_ignore_ascii_case("MIME") {
+            Section::Mime
+        } else {
+            return None;
+        });
+    }
+    Some(sections)
+}
+
+fn url_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(ch) = iter.next() {
+        if ch == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(ch);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::fetch::Section;
+
+    use super::{Access, ImapUrl};
+
+    #[test]
+    fn parse_imap_url() {
+        for (url, expected) in [
+            (
+                concat!(
+                    "imap://joe@example.com/INBOX/;uid=20/;section=1.2;urlauth=",
+                    "submit+fred:internal:91354a473744909de610943775f92038"
+                ),
+                Some(ImapUrl {
+                    user: "joe".to_string().into(),
+                    host: "example.com".to_string().into(),
+                    mailbox_name: "INBOX".to_string(),
+                    uid_validity: None,
+                    uid: 20,
+                    sections: vec![Section::Part { num: 1 }, Section::Part { num: 2 }],
+                    partial: None,
+                    expire: None,
+                    access: Access::Submit("fred".to_string()).into(),
+                    token: "91354a473744909de610943775f92038".to_string().into(),
+                    rump: "imap://joe@example.com/INBOX/;uid=20/;section=1.2;urlauth=submit+fred"
+                        .to_string(),
+                }),
+            ),
+            (
+                concat!(
+                    "imap://joe@example.com/Sent%20Items;UIDVALIDITY=385759045/;UID=20",
+                    "/;SECTION=TEXT/;PARTIAL=10.50;EXPIRE=2023-01-01T00:00:00Z;URLAUTH=anonymous"
+                ),
+                Some(ImapUrl {
+                    user: "joe".to_string().into(),
+                    host: "example.com".to_string().into(),
+                    mailbox_name: "Sent Items".to_string(),
+                    uid_validity: 385759045.into(),
+                    uid: 20,
+                    sections: vec![Section::Text],
+                    partial: (10, 50).into(),
+                    expire: 1672531200.into(),
+                    access: Access::Anonymous.into(),
+                    token: None,
+                    rump: concat!(
+                        "imap://joe@example.com/Sent%20Items;UIDVALIDITY=385759045/;UID=20",
+                        "/;SECTION=TEXT/;PARTIAL=10.50;EXPIRE=2023-01-01T00:00:00Z;URLAUTH=anonymous"
+                    )
+                    .to_string(),
+                }),
+            ),
+            (
+                "/Drafts/;UID=4/;SECTION=1.MIME",
+                Some(ImapUrl {
+                    user: None,
+                    host: None,
+                    mailbox_name: "Drafts".to_string(),
+                    uid_validity: None,
+                    uid: 4,
+                    sections: vec![Section::Part { num: 1 }, Section::Mime],
+                    partial: None,
+                    expire: None,
+                    access: None,
+                    token: None,
+                    rump: "/Drafts/;UID=4/;SECTION=1.MIME".to_string(),
+                }),
+            ),
+            ("imap://joe@example.com/INBOX", None),
+            ("imap://joe@example.com/INBOX/;UID=0", None),
+            ("imap://joe@example.com/INBOX/;UID=1;URLAUTH=other+joe", None),
+            ("imap://joe@example.com/INBOX/;UID=1;URLAUTH=anonymous:hmac:abcd", None),
+            ("/INBOX/;UID=1/;SECTION=HEADER.FIELDS%20(To)", None),
+        ] {
+            assert_eq!(ImapUrl::parse(url), expected, "{url}");
+        }
+    }
+}
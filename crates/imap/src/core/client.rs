@@ -30,7 +30,10 @@ use imap_proto::{
 use jmap::auth::rate_limit::AuthenticatedLimiter;
 use parking_lot::Mutex;
 use tokio::io::AsyncRead;
-use utils::listener::limiter::{ConcurrencyLimiter, RateLimiter};
+use utils::{
+    listener::limiter::{ConcurrencyLimiter, RateLimiter},
+    metrics,
+};
 
 use super::{SelectedMailbox, Session, SessionData, State, IMAP};
 
@@ -76,6 +79,12 @@ impl<T: AsyncRead> Session<T> {
 
         let mut requests = requests.into_iter().peekable();
         while let Some(request) = requests.next() {
+            // Commands that complete in a background task take over the timer
+            self.command_timer = metrics::IMAP_COMMAND_DURATION
+                .with_label_values(&[&request.command.to_string()])
+                .start_timer()
+                .into();
+
             match request.command {
                 Command::List | Command::Lsub => {
                     self.handle_list(request).await?;
@@ -124,6 +133,9 @@ impl<T: AsyncRead> Session<T> {
                 Command::Move(is_uid) => {
                     self.handle_copy_move(request, true, is_uid).await?;
                 }
+                Command::Replace(is_uid) => {
+                    self.handle_replace(request, is_uid).await?;
+                }
                 Command::Sort(is_uid) => {
                     self.handle_search(request, true, is_uid).await?;
                 }
@@ -154,6 +166,21 @@ impl<T: AsyncRead> Session<T> {
                 Command::Enable => {
                     self.handle_enable(request).await?;
                 }
+                Command::Notify => {
+                    self.handle_notify(request).await?;
+                }
+                Command::Compress => {
+                    self.handle_compress(request).await?;
+                }
+                Command::GenUrlAuth => {
+                    self.handle_genurlauth(request).await?;
+                }
+                Command::ResetKey => {
+                    self.handle_resetkey(request).await?;
+                }
+                Command::UrlFetch => {
+                    self.handle_urlfetch(request).await?;
+                }
                 Command::StartTls => {
                     return self
                         .write_bytes(
@@ -195,6 +222,8 @@ impl<T: AsyncRead> Session<T> {
                     self.handle_id(request).await?;
                 }
             }
+
+            self.command_timer = None;
         }
 
         if let Some(needs_literal) = needs_literal {
@@ -284,6 +313,11 @@ impl<T: AsyncRead> Session<T> {
             | Command::Status
             | Command::Append
             | Command::Idle
+            | Command::Notify
+            | Command::Compress
+            | Command::GenUrlAuth
+            | Command::ResetKey
+            | Command::UrlFetch
             | Command::SetAcl
             | Command::DeleteAcl
             | Command::GetAcl
@@ -304,6 +338,7 @@ impl<T: AsyncRead> Session<T> {
             | Command::Store(_)
             | Command::Copy(_)
             | Command::Move(_)
+            | Command::Replace(_)
             | Command::Check
             | Command::Sort(_)
             | Command::Thread(_) => match state {
@@ -311,7 +346,10 @@ impl<T: AsyncRead> Session<T> {
                     if mailbox.is_select
                         || !matches!(
                             request.command,
-                            Command::Store(_) | Command::Expunge(_) | Command::Move(_),
+                            Command::Store(_)
+                                | Command::Expunge(_)
+                                | Command::Move(_)
+                                | Command::Replace(_),
                         )
                     {
                         Ok(request)
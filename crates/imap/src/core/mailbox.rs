@@ -139,6 +139,7 @@ impl SessionData {
             .get_document_ids(account_id, Collection::Email)
             .await
             .map_err(|_| {})?;
+        let shared_seen = self.jmap.shared_seen_id(access_token, account_id);
 
         if let Some(mailbox_prefix) = &mailbox_prefix {
             path.push(mailbox_prefix.to_string());
@@ -216,7 +217,12 @@ impl SessionData {
                                 .into(),
                             total_unseen: self
                                 .jmap
-                                .mailbox_unread_tags(account_id, *mailbox_id, &message_ids)
+                                .mailbox_unread_tags(
+                                    account_id,
+                                    *mailbox_id,
+                                    &message_ids,
+                                    shared_seen,
+                                )
                                 .await
                                 .map_err(|_| {})?
                                 .map(|v| v.len() as u32)
@@ -451,6 +457,22 @@ impl SessionData {
                                     {
                                         changes.changed.push(mailbox_name.to_string());
                                     }
+                                    if mailbox.is_subscribed != old_mailbox.is_subscribed {
+                                        changes.subscriptions.push(mailbox_name.to_string());
+                                    }
+                                }
+
+                                // Add renamed mailboxes
+                                if let Some((old_name, _)) = old_account
+                                    .mailbox_names
+                                    .iter()
+                                    .find(|(_, old_id)| *old_id == mailbox_id)
+                                {
+                                    if old_name != mailbox_name {
+                                        changes
+                                            .renamed
+                                            .push((old_name.to_string(), mailbox_name.to_string()));
+                                    }
                                 }
                             } else {
                                 changes.added.push(mailbox_name.to_string());
@@ -30,7 +30,7 @@ use std::{
 use ahash::AHashMap;
 use dashmap::DashMap;
 use imap_proto::{
-    protocol::{list::Attribute, ProtocolVersion},
+    protocol::{list::Attribute, notify::EventGroup, ProtocolVersion},
     receiver::Receiver,
     Command, ResponseCode, StatusResponse,
 };
@@ -41,15 +41,20 @@ use jmap::{
     },
     JMAP,
 };
+use jmap_proto::types::{keyword::Keyword, state::StateChange};
 use parking_lot::Mutex;
+use store::roaring::RoaringBitmap;
 use tokio::{
     io::{AsyncRead, ReadHalf},
     sync::{mpsc, watch},
 };
 use utils::{
+    codec::deflate::Inflater,
     config::Rate,
     listener::{limiter::InFlight, ServerInstance},
     map::mutex_map::MutexMap,
+    metrics::HistogramTimer,
+    reload::Reloadable,
 };
 
 pub mod client;
@@ -60,13 +65,16 @@ pub mod writer;
 
 #[derive(Clone)]
 pub struct ImapSessionManager {
-    pub jmap: Arc<JMAP>,
-    pub imap: Arc<IMAP>,
+    pub jmap: Reloadable<JMAP>,
+    pub imap: Reloadable<IMAP>,
 }
 
 impl ImapSessionManager {
-    pub fn new(jmap: Arc<JMAP>, imap: Arc<IMAP>) -> Self {
-        Self { jmap, imap }
+    pub fn new(jmap: impl Into<Reloadable<JMAP>>, imap: impl Into<Reloadable<IMAP>>) -> Self {
+        Self {
+            jmap: jmap.into(),
+            imap: imap.into(),
+        }
     }
 }
 
@@ -105,9 +113,17 @@ pub struct Session<T: AsyncRead> {
     pub stream_rx: ReadHalf<T>,
     pub in_flight: InFlight,
     pub remote_addr: RemoteAddress,
+    pub notify: Option<Notifier>,
+    pub inflater: Option<Inflater>,
+    pub command_timer: Option<HistogramTimer>,
     pub span: tracing::Span,
 }
 
+pub struct Notifier {
+    pub groups: Vec<EventGroup>,
+    pub change_rx: mpsc::Receiver<StateChange>,
+}
+
 pub struct SessionData {
     pub account_id: u32,
     pub jmap: Arc<JMAP>,
@@ -180,6 +196,8 @@ pub struct MailboxSync {
     pub added: Vec<String>,
     pub changed: Vec<String>,
     pub deleted: Vec<String>,
+    pub renamed: Vec<(String, String)>,
+    pub subscriptions: Vec<String>,
 }
 
 pub enum SavedSearch {
@@ -221,4 +239,24 @@ impl SessionData {
                     .with_code(ResponseCode::ContactAdmin)
             })
     }
+
+    // Returns the \Seen state kept by a member of a shared account, or None
+    // if the account uses the message keywords.
+    pub async fn shared_seen(
+        &self,
+        account_id: u32,
+    ) -> crate::op::Result<Option<(u32, RoaringBitmap)>> {
+        let access_token = self.get_access_token().await?;
+        if let Some(user_id) = self.jmap.shared_seen_id(&access_token, account_id) {
+            Ok(Some((
+                user_id,
+                self.jmap
+                    .get_keyword_tag(account_id, Some(user_id), Keyword::Seen)
+                    .await?
+                    .unwrap_or_default(),
+            )))
+        } else {
+            Ok(None)
+        }
+    }
 }
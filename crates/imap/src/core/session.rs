@@ -23,6 +23,7 @@
 
 use imap_proto::{protocol::ProtocolVersion, receiver::Receiver};
 use jmap::auth::rate_limit::RemoteAddress;
+use jmap_proto::types::state::StateChange;
 use tokio::{
     io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
@@ -31,7 +32,7 @@ use tokio::{
 use tokio_rustls::server::TlsStream;
 use utils::listener::{SessionData, SessionManager};
 
-use super::{writer, ImapSessionManager, Session, State};
+use super::{writer, ImapSessionManager, Notifier, Session, State};
 
 impl SessionManager for ImapSessionManager {
     fn spawn(&self, session: SessionData<TcpStream>) {
@@ -70,7 +71,12 @@ impl<T: AsyncRead> Session<T> {
                     match result {
                         Ok(Ok(bytes_read)) => {
                             if bytes_read > 0 {
-                                match self.ingest(&buf[..bytes_read]).await {
+                                let bytes = if let Some(bytes) = self.inflate(&buf[..bytes_read]).await {
+                                    bytes
+                                } else {
+                                    break;
+                                };
+                                match self.ingest(&bytes).await {
                                     Ok(false) => (),
                                     Ok(true) => {
                                         return true;
@@ -96,6 +102,11 @@ impl<T: AsyncRead> Session<T> {
                         }
                     }
                 },
+                state_change = next_state_change(&mut self.notify) => {
+                    if self.handle_state_change(state_change).await.is_err() {
+                        break;
+                    }
+                },
                 _ = shutdown_rx.changed() => {
                     self.write_bytes(&b"* BYE Server shutting down.\r\n"[..]).await.ok();
                     tracing::debug!(parent: &self.span, event = "shutdown", "IMAP server shutting down.");
@@ -108,13 +119,24 @@ impl<T: AsyncRead> Session<T> {
     }
 }
 
+async fn next_state_change(notify: &mut Option<Notifier>) -> Option<StateChange> {
+    if let Some(notify) = notify {
+        notify.change_rx.recv().await
+    } else {
+        std::future::pending().await
+    }
+}
+
 impl Session<TcpStream> {
     pub async fn new(
         mut session: SessionData<TcpStream>,
         manager: ImapSessionManager,
     ) -> Result<Session<TcpStream>, ()> {
+        let imap = manager.imap.load();
+        let jmap = manager.jmap.load();
+
         // Write plain text greeting
-        if let Err(err) = session.stream.write_all(&manager.imap.greeting_plain).await {
+        if let Err(err) = session.stream.write_all(&imap.greeting_plain).await {
             tracing::debug!(parent: &session.span, event = "error", reason = %err, "Failed to write greeting.");
             return Err(());
         }
@@ -124,19 +146,22 @@ impl Session<TcpStream> {
         let (stream_rx, stream_tx) = tokio::io::split(session.stream);
 
         Ok(Session {
-            receiver: Receiver::with_max_request_size(manager.imap.max_request_size),
+            receiver: Receiver::with_max_request_size(imap.max_request_size),
             version: ProtocolVersion::Rev1,
             state: State::NotAuthenticated { auth_failures: 0 },
             writer: writer::spawn_writer(writer::Event::Stream(stream_tx), session.span.clone()),
             is_tls: false,
             is_condstore: false,
             is_qresync: false,
-            imap: manager.imap,
-            jmap: manager.jmap,
+            imap,
+            jmap,
             instance: session.instance,
             span: session.span,
             in_flight: session.in_flight,
             remote_addr: RemoteAddress::IpAddress(session.remote_ip),
+            notify: None,
+            inflater: None,
+            command_timer: None,
             stream_rx,
         })
     }
@@ -185,6 +210,9 @@ impl Session<TcpStream> {
             span: self.span,
             in_flight: self.in_flight,
             remote_addr: self.remote_addr,
+            notify: self.notify,
+            inflater: self.inflater,
+            command_timer: None,
             stream_rx,
         })
     }
@@ -195,6 +223,9 @@ impl Session<TlsStream<TcpStream>> {
         session: utils::listener::SessionData<TcpStream>,
         manager: ImapSessionManager,
     ) -> Result<Session<TlsStream<TcpStream>>, ()> {
+        let imap = manager.imap.load();
+        let jmap = manager.jmap.load();
+
         // Upgrade to TLS
         let mut stream = session
             .instance
@@ -203,7 +234,7 @@ impl Session<TlsStream<TcpStream>> {
 
         // Write TLS greeting
         let span = session.span;
-        if let Err(err) = stream.write_all(&manager.imap.greeting_tls).await {
+        if let Err(err) = stream.write_all(&imap.greeting_tls).await {
             tracing::debug!(parent: &span, event = "error", reason = %err, "Failed to write greeting.");
             return Err(());
         }
@@ -213,19 +244,22 @@ impl Session<TlsStream<TcpStream>> {
         let (stream_rx, stream_tx) = tokio::io::split(stream);
 
         Ok(Session {
-            receiver: Receiver::with_max_request_size(manager.imap.max_request_size),
+            receiver: Receiver::with_max_request_size(imap.max_request_size),
             version: ProtocolVersion::Rev1,
             state: State::NotAuthenticated { auth_failures: 0 },
             writer: writer::spawn_writer(writer::Event::StreamTls(stream_tx), span.clone()),
             is_tls: true,
             is_condstore: false,
             is_qresync: false,
-            imap: manager.imap,
-            jmap: manager.jmap,
+            imap,
+            jmap,
             instance: session.instance,
             span,
             in_flight: session.in_flight,
             remote_addr: RemoteAddress::IpAddress(session.remote_ip),
+            notify: None,
+            inflater: None,
+            command_timer: None,
             stream_rx,
         })
     }
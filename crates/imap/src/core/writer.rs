@@ -30,6 +30,7 @@ use tokio::{
 };
 use tokio_rustls::server::TlsStream;
 use tracing::debug;
+use utils::codec::deflate::Deflater;
 
 use super::{Session, SessionData};
 
@@ -40,11 +41,14 @@ pub enum Event {
     StreamTls(WriteHalf<TlsStream<TcpStream>>),
     Bytes(Cow<'static, [u8]>),
     Upgrade(oneshot::Sender<WriteHalf<TcpStream>>),
+    Compress,
 }
 
 pub fn spawn_writer(mut stream: Event, span: tracing::Span) -> mpsc::Sender<Event> {
     let (tx, mut rx) = mpsc::channel::<Event>(IPC_CHANNEL_BUFFER);
     tokio::spawn(async move {
+        let mut deflater: Option<Deflater> = None;
+
         'outer: loop {
             match stream {
                 Event::Stream(mut stream_tx) => {
@@ -60,6 +64,14 @@ pub fn spawn_writer(mut stream: Event, span: tracing::Span) -> mpsc::Sender<Even
 
                                 //let c = print!("{}", String::from_utf8_lossy(&bytes));
 
+                                let bytes = match deflate(&mut deflater, bytes) {
+                                    Ok(bytes) => bytes,
+                                    Err(err) => {
+                                        debug!("Failed to compress stream: {}", err);
+                                        break 'outer;
+                                    }
+                                };
+
                                 match stream_tx.write_all(bytes.as_ref()).await {
                                     Ok(_) => {
                                         let _ = stream_tx.flush().await;
@@ -82,6 +94,9 @@ pub fn spawn_writer(mut stream: Event, span: tracing::Span) -> mpsc::Sender<Even
                                     break 'outer;
                                 }
                             }
+                            Event::Compress => {
+                                deflater = Deflater::new().into();
+                            }
                             _ => {
                                 stream = event;
                                 continue 'outer;
@@ -103,6 +118,14 @@ pub fn spawn_writer(mut stream: Event, span: tracing::Span) -> mpsc::Sender<Even
 
                                 //let c = print!("{}", String::from_utf8_lossy(&bytes));
 
+                                let bytes = match deflate(&mut deflater, bytes) {
+                                    Ok(bytes) => bytes,
+                                    Err(err) => {
+                                        debug!("Failed to compress stream: {}", err);
+                                        break 'outer;
+                                    }
+                                };
+
                                 match stream_tx.write_all(bytes.as_ref()).await {
                                     Ok(_) => {
                                         let _ = stream_tx.flush().await;
@@ -113,6 +136,9 @@ pub fn spawn_writer(mut stream: Event, span: tracing::Span) -> mpsc::Sender<Even
                                     }
                                 }
                             }
+                            Event::Compress => {
+                                deflater = Deflater::new().into();
+                            }
                             _ => {
                                 stream = event;
                                 continue 'outer;
@@ -128,6 +154,17 @@ pub fn spawn_writer(mut stream: Event, span: tracing::Span) -> mpsc::Sender<Even
     tx
 }
 
+fn deflate(
+    deflater: &mut Option<Deflater>,
+    bytes: Cow<'static, [u8]>,
+) -> std::io::Result<Cow<'static, [u8]>> {
+    if let Some(deflater) = deflater {
+        deflater.deflate(bytes.as_ref()).map(Cow::Owned)
+    } else {
+        Ok(bytes)
+    }
+}
+
 impl<T: AsyncRead> Session<T> {
     pub async fn write_bytes(&self, bytes: impl Into<Cow<'static, [u8]>>) -> crate::OpResult {
         let bytes = bytes.into();
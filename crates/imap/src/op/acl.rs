@@ -56,7 +56,9 @@ impl<T: AsyncRead> Session<T> {
                 let data = self.state.session_data();
                 let is_rev2 = self.version.is_rev2();
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     match data.get_acl_mailbox(&arguments, true).await {
                         Ok(Some((_, values, _))) => {
                             let mut permissions = Vec::new();
@@ -114,6 +116,7 @@ impl<T: AsyncRead> Session<T> {
                                                     Acl::Submit => {
                                                         rights.push(Rights::Post);
                                                     }
+                                                    Acl::SendAs | Acl::SendOnBehalf => {}
                                                     Acl::None => (),
                                                 }
                                             }
@@ -170,7 +173,9 @@ impl<T: AsyncRead> Session<T> {
                 let data = self.state.session_data();
                 let is_rev2 = self.version.is_rev2();
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     match data.get_acl_mailbox(&arguments, false).await {
                         Ok(Some((mailbox, values, access_token))) => {
                             data.write_bytes(
@@ -269,7 +274,9 @@ impl<T: AsyncRead> Session<T> {
             Ok(arguments) => {
                 let data = self.state.session_data();
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     // Validate mailbox
                     let (mailbox, values, _) = match data.get_acl_mailbox(&arguments, true).await {
                         Ok(Some(result)) => result,
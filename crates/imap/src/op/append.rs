@@ -24,10 +24,12 @@
 use std::sync::Arc;
 
 use imap_proto::{
-    protocol::append::Arguments, receiver::Request, Command, ResponseCode, StatusResponse,
+    protocol::append::{Arguments, CatenatePart},
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
 };
 
-use jmap::email::ingest::IngestEmail;
+use jmap::email::{ingest::IngestEmail, seen::shared_seen_apply};
 use jmap_proto::types::{acl::Acl, keyword::Keyword, state::StateChange, type_state::DataType};
 use mail_parser::MessageParser;
 use tokio::io::AsyncRead;
@@ -76,7 +78,9 @@ impl<T: AsyncRead> Session<T> {
                     };
                 let is_qresync = self.is_qresync;
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     data.write_bytes(
                         match data
                             .append_messages(arguments, selected_mailbox, mailbox, is_qresync)
@@ -120,17 +124,44 @@ impl SessionData {
         }
 
         // Obtain quota
-        let account_quota = self
+        let access_token = self
             .get_access_token()
             .await
-            .map_err(|r| r.with_tag(&arguments.tag))?
-            .quota as i64;
+            .map_err(|r| r.with_tag(&arguments.tag))?;
+        let account_quota = access_token.quota as i64;
+
+        // Members of shared accounts keep their own \Seen state
+        let shared_seen = self.jmap.shared_seen_id(&access_token, account_id);
 
         // Append messages
         let mut response = StatusResponse::completed(Command::Append);
         let mut created_ids = Vec::with_capacity(arguments.messages.len());
         let mut last_change_id = None;
-        for message in arguments.messages {
+        for mut message in arguments.messages {
+            // Assemble CATENATE parts
+            if !message.catenate.is_empty() {
+                match self.catenate_message(message.catenate).await {
+                    Ok(raw_message) => {
+                        message.message = raw_message;
+                    }
+                    Err(err) => {
+                        response = err;
+                        break;
+                    }
+                }
+            }
+
+            let mut keywords = message
+                .flags
+                .into_iter()
+                .map(Keyword::from)
+                .collect::<Vec<_>>();
+            let mut is_seen = false;
+            if shared_seen.is_some() {
+                is_seen = keywords.contains(&Keyword::Seen);
+                shared_seen_apply(&mut keywords, false);
+            }
+
             match self
                 .jmap
                 .email_ingest(IngestEmail {
@@ -139,16 +170,29 @@ impl SessionData {
                     account_id,
                     account_quota,
                     mailbox_ids: vec![mailbox_id],
-                    keywords: message.flags.into_iter().map(Keyword::from).collect(),
+                    keywords,
                     received_at: message.received_at.map(|d| d as u64),
                     skip_duplicates: false,
                     encrypt: self.jmap.config.encrypt && self.jmap.config.encrypt_append,
+                    replace: None,
                 })
                 .await
             {
                 Ok(email) => {
                     created_ids.push(email.id.document_id());
                     last_change_id = Some(email.change_id);
+
+                    if let (true, Some(user_id)) = (is_seen, shared_seen) {
+                        if self
+                            .jmap
+                            .shared_seen_set(account_id, user_id, [email.id.document_id()], true)
+                            .await
+                            .is_err()
+                        {
+                            response = StatusResponse::database_failure();
+                            break;
+                        }
+                    }
                 }
                 Err(err) => {
                     match err {
@@ -219,4 +263,31 @@ impl SessionData {
 
         Ok(response.with_tag(arguments.tag))
     }
+
+    pub async fn catenate_message(&self, parts: Vec<CatenatePart>) -> crate::op::Result<Vec<u8>> {
+        let mut raw_message = Vec::new();
+        for part in parts {
+            match part {
+                CatenatePart::Text(text) => {
+                    raw_message.extend_from_slice(&text);
+                }
+                CatenatePart::Url(url) => {
+                    if let Some(contents) = self.fetch_url(&url).await? {
+                        raw_message.extend_from_slice(&contents);
+                    } else {
+                        return Err(StatusResponse::no("Unable to fetch URL.")
+                            .with_code(ResponseCode::BadUrl { url }));
+                    }
+                }
+            }
+
+            // Enforce the same limit as for APPEND literals
+            if raw_message.len() > self.imap.max_request_size {
+                return Err(
+                    StatusResponse::no("Message is too large.").with_code(ResponseCode::TooBig)
+                );
+            }
+        }
+        Ok(raw_message)
+    }
 }
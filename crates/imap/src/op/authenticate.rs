@@ -31,6 +31,7 @@ use imap_proto::{
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use tokio::io::AsyncRead;
+use utils::metrics;
 
 use crate::core::{Session, SessionData, State};
 
@@ -139,6 +140,7 @@ impl<T: AsyncRead> Session<T> {
                 }
             }
         };
+        metrics::authentication("imap", access_token.is_some());
 
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
@@ -210,6 +212,7 @@ impl<T: AsyncRead> Session<T> {
 
     pub async fn handle_unauthenticate(&mut self, request: Request<Command>) -> crate::OpResult {
         self.state = State::NotAuthenticated { auth_failures: 0 };
+        self.notify = None;
 
         self.write_bytes(
             StatusResponse::completed(Command::Unauthenticate)
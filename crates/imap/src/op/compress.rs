@@ -0,0 +1,97 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{borrow::Cow, io::ErrorKind};
+
+use imap_proto::{
+    receiver::{self, Request},
+    Command, ResponseCode, StatusResponse,
+};
+
+use tokio::io::AsyncRead;
+use utils::codec::deflate::Inflater;
+
+use crate::core::{writer, Session};
+
+impl<T: AsyncRead> Session<T> {
+    pub async fn handle_compress(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_compress() {
+            Ok(arguments) => {
+                if self.inflater.is_some() {
+                    return self
+                        .write_bytes(
+                            StatusResponse::no("Compression is already active.")
+                                .with_tag(arguments.tag)
+                                .with_code(ResponseCode::CompressionActive)
+                                .into_bytes(),
+                        )
+                        .await;
+                }
+
+                // The tagged response is sent uncompressed, everything
+                // written after it goes through the deflate layer.
+                self.write_bytes(
+                    StatusResponse::ok("DEFLATE active.")
+                        .with_tag(arguments.tag)
+                        .into_bytes(),
+                )
+                .await?;
+                if let Err(err) = self.writer.send(writer::Event::Compress).await {
+                    tracing::debug!(parent: &self.span, "Failed to write to channel: {}", err);
+                    return Err(());
+                }
+                self.inflater = Inflater::with_max_size(self.receiver.max_request_size).into();
+                tracing::debug!(parent: &self.span, event = "compress", "DEFLATE compression enabled.");
+
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+
+    pub async fn inflate<'x>(&mut self, bytes: &'x [u8]) -> Option<Cow<'x, [u8]>> {
+        if let Some(inflater) = &mut self.inflater {
+            match inflater.inflate(bytes) {
+                Ok(bytes) => Some(Cow::Owned(bytes)),
+                Err(err) if err.kind() == ErrorKind::InvalidInput => {
+                    // The stream is still in sync, fail the current command
+                    // and discard the data.
+                    tracing::debug!(parent: &self.span, event = "error", reason = %err, "Decompressed request too large.");
+                    if let receiver::Error::Error { response } = self.receiver.error_reset(format!(
+                        "Request exceeds maximum limit of {} bytes.",
+                        self.receiver.max_request_size
+                    )) {
+                        self.write_bytes(response.into_bytes()).await.ok()?;
+                    }
+                    Some(Cow::Borrowed(&[]))
+                }
+                Err(err) => {
+                    tracing::debug!(parent: &self.span, event = "error", reason = %err, "Failed to decompress stream.");
+                    None
+                }
+            }
+        } else {
+            Some(Cow::Borrowed(bytes))
+        }
+    }
+}
@@ -36,7 +36,9 @@ use jmap_proto::{
         type_state::DataType,
     },
 };
-use store::write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, F_VALUE};
+use store::write::{
+    assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder, F_CLEAR, F_INDEX, F_VALUE,
+};
 use tokio::io::AsyncRead;
 
 use crate::core::{MailboxId, SelectedMailbox, Session, SessionData};
@@ -53,7 +55,9 @@ impl<T: AsyncRead> Session<T> {
                 let (data, src_mailbox) = self.state.mailbox_state();
 
                 let is_qresync = self.is_qresync;
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     // Refresh mailboxes
                     if let Err(err) = data.synchronize_mailboxes(false).await {
                         return data
@@ -250,6 +254,17 @@ impl SessionData {
                     .with_collection(Collection::Email)
                     .update_document(id);
                 mailboxes.update_batch(&mut batch, Property::MailboxIds);
+
+                // Update save date
+                if let Some(saved_at) = self
+                    .jmap
+                    .get_property::<u64>(account_id, Collection::Email, id, Property::SavedAt)
+                    .await
+                    .map_err(|_| StatusResponse::database_failure().with_tag(&arguments.tag))?
+                {
+                    batch.value(Property::SavedAt, saved_at, F_INDEX | F_CLEAR);
+                }
+                batch.value(Property::SavedAt, now(), F_VALUE | F_INDEX);
                 if changelog.change_id == u64::MAX {
                     changelog.change_id =
                         self.jmap.assign_change_id(account_id).await.map_err(|_| {
@@ -293,6 +308,14 @@ impl SessionData {
                         .with_code(ResponseCode::ContactAdmin)
                 })?
                 .quota as i64;
+            let shared_seen_users = if is_move {
+                self.jmap
+                    .shared_seen_users(src_account_id)
+                    .await
+                    .map_err(|_| StatusResponse::database_failure().with_tag(&arguments.tag))?
+            } else {
+                Vec::new()
+            };
             for (id, imap_id) in ids {
                 match self
                     .jmap
@@ -346,7 +369,7 @@ impl SessionData {
                         // Delete message if it is no longer in any mailbox
                         if let Ok(changes) = self
                             .jmap
-                            .email_delete(src_account_id, id)
+                            .email_delete(src_account_id, id, &shared_seen_users)
                             .await
                             .map_err(|_| {
                                 StatusResponse::database_failure().with_tag(&arguments.tag)
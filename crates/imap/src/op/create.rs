@@ -54,7 +54,9 @@ impl<T: AsyncRead> Session<T> {
 
         if !arguments.is_empty() {
             let data = self.state.session_data();
+            let timer = self.command_timer.take();
             tokio::spawn(async move {
+                let _timer = timer;
                 for argument in arguments {
                     data.write_bytes(data.create_folder(argument).await.into_bytes())
                         .await;
@@ -166,6 +166,11 @@ impl SessionData {
 
         // Delete ids
         let mut changelog = ChangeLogBuilder::new();
+        let shared_seen_users = if !deleted_ids.is_empty() {
+            self.jmap.shared_seen_users(account_id).await?
+        } else {
+            Vec::new()
+        };
         for id in deleted_ids {
             if sequence
                 .as_ref()
@@ -229,13 +234,21 @@ impl SessionData {
                     }
                 } else {
                     // Delete message from all mailboxes
-                    if let Ok(changes) = self.jmap.email_delete(account_id, id).await? {
+                    if let Ok(changes) = self
+                        .jmap
+                        .email_delete(account_id, id, &shared_seen_users)
+                        .await?
+                    {
                         changelog.merge(changes);
                     }
                 }
             } else {
                 // Delete message from all mailboxes
-                if let Ok(changes) = self.jmap.email_delete(account_id, id).await? {
+                if let Ok(changes) = self
+                    .jmap
+                    .email_delete(account_id, id, &shared_seen_users)
+                    .await?
+                {
                     changelog.merge(changes);
                 }
             }
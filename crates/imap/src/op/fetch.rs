@@ -29,14 +29,15 @@ use imap_proto::{
     protocol::{
         expunge::Vanished,
         fetch::{
-            self, Arguments, Attribute, BodyContents, BodyPart, BodyPartExtension, BodyPartFields,
-            DataItem, Envelope, FetchItem, Section,
+            self, get_partial_bytes, Arguments, Attribute, BodyContents, BodyPart,
+            BodyPartExtension, BodyPartFields, DataItem, Envelope, FetchItem, Section,
         },
         Flag,
     },
     receiver::Request,
     Command, ResponseCode, StatusResponse,
 };
+use jmap::email::seen::shared_seen_apply;
 use jmap_proto::{
     error::method::MethodError,
     object::Object,
@@ -76,7 +77,9 @@ impl<T: AsyncRead> Session<T> {
                     false
                 };
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     data.write_bytes(
                         data.fetch(arguments, mailbox, is_uid, is_qresync, enabled_condstore)
                             .await
@@ -201,6 +204,14 @@ impl SessionData {
             arguments.attributes.push_unique(Attribute::ModSeq);
         }
 
+        // Apply PARTIAL range
+        if let Some(partial) = &arguments.partial {
+            let mut sorted_ids = ids.into_iter().collect::<Vec<_>>();
+            sorted_ids.sort_unstable_by_key(|(_, imap_id)| imap_id.uid);
+            partial.apply(&mut sorted_ids);
+            ids = sorted_ids.into_iter().collect();
+        }
+
         // Build properties list
         let mut set_seen_flags = false;
         let mut needs_thread_id = false;
@@ -258,6 +269,12 @@ impl SessionData {
             arguments.attributes.push_unique(Attribute::Uid);
         }
 
+        // Members of shared accounts keep their own \Seen state
+        let shared_seen = match self.shared_seen(account_id).await {
+            Ok(shared_seen) => shared_seen,
+            Err(response) => return response.with_tag(arguments.tag),
+        };
+
         let mut set_seen_ids = Vec::new();
 
         // Process each message
@@ -266,7 +283,7 @@ impl SessionData {
             let seqnum = imap_id.seqnum;
 
             // Obtain attributes and keywords
-            let (email, keywords) = if let (Ok(Some(email)), Ok(Some(keywords))) = (
+            let (email, mut keywords) = if let (Ok(Some(email)), Ok(Some(keywords))) = (
                 self.jmap
                     .get_property::<Object<Value>>(
                         account_id,
@@ -294,6 +311,9 @@ impl SessionData {
                     "Message metadata not found");
                 continue;
             };
+            if let Some((_, seen_ids)) = &shared_seen {
+                shared_seen_apply(&mut keywords.inner, seen_ids.contains(id));
+            }
 
             // Fetch and parse blob
             let raw_message = if needs_blobs {
@@ -377,6 +397,22 @@ impl SessionData {
                             });
                         }
                     }
+                    Attribute::SaveDate => {
+                        items.push(DataItem::SaveDate {
+                            date: self
+                                .jmap
+                                .get_property::<u64>(
+                                    account_id,
+                                    Collection::Email,
+                                    id,
+                                    Property::SavedAt,
+                                )
+                                .await
+                                .ok()
+                                .flatten()
+                                .map(|date| date as i64),
+                        });
+                    }
                     Attribute::Preview { .. } => {
                         items.push(DataItem::Preview {
                             contents: email
@@ -537,17 +573,32 @@ impl SessionData {
                     return StatusResponse::database_failure().with_tag(arguments.tag);
                 }
             };
+            if let Some((user_id, _)) = &shared_seen {
+                if self
+                    .jmap
+                    .shared_seen_register(account_id, *user_id)
+                    .await
+                    .is_err()
+                {
+                    return StatusResponse::database_failure().with_tag(arguments.tag);
+                }
+            }
             for (id, mut keywords) in set_seen_ids {
-                keywords.inner.push(Keyword::Seen);
                 let mut batch = BatchBuilder::new();
                 batch
                     .with_account_id(account_id)
                     .with_collection(Collection::Email)
-                    .update_document(id.document_id())
-                    .assert_value(Property::Keywords, &keywords)
-                    .value(Property::Keywords, keywords.inner, F_VALUE)
-                    .value(Property::Keywords, Keyword::Seen, F_BITMAP)
-                    .value(Property::Cid, changelog.change_id, F_VALUE);
+                    .update_document(id.document_id());
+                if let Some((user_id, _)) = &shared_seen {
+                    batch.bitmap(Property::Keywords, *user_id, 0);
+                } else {
+                    keywords.inner.push(Keyword::Seen);
+                    batch
+                        .assert_value(Property::Keywords, &keywords)
+                        .value(Property::Keywords, keywords.inner, F_VALUE)
+                        .value(Property::Keywords, Keyword::Seen, F_BITMAP);
+                }
+                batch.value(Property::Cid, changelog.change_id, F_VALUE);
                 match self.jmap.write_batch(batch).await {
                     Ok(_) => {
                         changelog.log_update(Collection::Email, id);
@@ -810,131 +861,7 @@ impl<'x> AsImapDataItem<'x> for Message<'x> {
         sections: &[Section],
         partial: Option<(u32, u32)>,
     ) -> Option<Cow<'x, [u8]>> {
-        let mut part = self.root_part();
-        if sections.is_empty() {
-            return Some(
-                get_partial_bytes(
-                    self.raw_message.get(part.offset_header..part.offset_end)?,
-                    partial,
-                )
-                .into(),
-            );
-        }
-
-        let mut message = self;
-        let mut sections_iter = sections.iter().enumerate().peekable();
-
-        while let Some((section_num, section)) = sections_iter.next() {
-            match section {
-                Section::Part { num } => {
-                    part = if let Some(sub_part_ids) = part.sub_parts() {
-                        sub_part_ids
-                            .get((*num).saturating_sub(1) as usize)
-                            .and_then(|pos| message.parts.get(*pos))
-                    } else if *num == 1 && (section_num == sections.len() - 1 || part.is_message())
-                    {
-                        Some(part)
-                    } else {
-                        None
-                    }?;
-
-                    if let (
-                        PartType::Message(nested_message),
-                        Some((
-                            _,
-                            Section::Part { .. }
-                            | Section::Header
-                            | Section::HeaderFields { .. }
-                            | Section::Text,
-                        )),
-                    ) = (&part.body, sections_iter.peek())
-                    {
-                        message = nested_message;
-                        part = message.root_part();
-                    }
-                }
-                Section::Header => {
-                    return Some(
-                        get_partial_bytes(
-                            message
-                                .raw_message
-                                .get(part.offset_header..part.offset_body)?,
-                            partial,
-                        )
-                        .into(),
-                    );
-                }
-                Section::HeaderFields { not, fields } => {
-                    let mut headers =
-                        Vec::with_capacity(part.offset_body.saturating_sub(part.offset_header));
-                    for header in &part.headers {
-                        let header_name = header.name.as_str();
-                        if fields.iter().any(|f| header_name.eq_ignore_ascii_case(f)) != *not {
-                            headers.extend_from_slice(header_name.as_bytes());
-                            headers.push(b':');
-                            headers.extend_from_slice(
-                                message
-                                    .raw_message
-                                    .get(header.offset_start..header.offset_end)
-                                    .unwrap_or(b""),
-                            );
-                        }
-                    }
-
-                    headers.extend_from_slice(b"\r\n");
-
-                    return Some(if partial.is_none() {
-                        headers.into()
-                    } else {
-                        get_partial_bytes(&headers, partial).to_vec().into()
-                    });
-                }
-                Section::Text => {
-                    return Some(
-                        get_partial_bytes(
-                            message.raw_message.get(part.offset_body..part.offset_end)?,
-                            partial,
-                        )
-                        .into(),
-                    );
-                }
-                Section::Mime => {
-                    let mut headers =
-                        Vec::with_capacity(part.offset_body.saturating_sub(part.offset_header));
-                    for header in &part.headers {
-                        if header.name.is_mime_header()
-                            || header.name.as_str().starts_with("Content-")
-                        {
-                            headers.extend_from_slice(header.name.as_str().as_bytes());
-                            headers.extend_from_slice(b":");
-                            headers.extend_from_slice(
-                                message
-                                    .raw_message
-                                    .get(header.offset_start..header.offset_end)
-                                    .unwrap_or(b""),
-                            );
-                        }
-                    }
-                    headers.extend_from_slice(b"\r\n");
-                    return Some(if partial.is_none() {
-                        headers.into()
-                    } else {
-                        get_partial_bytes(&headers, partial).to_vec().into()
-                    });
-                }
-            }
-        }
-
-        // BODY[x] should return both headers and body, but most clients
-        // expect BODY[x] to return only the body, just like BOXY[x.TEXT] does.
-
-        Some(
-            get_partial_bytes(
-                message.raw_message.get(part.offset_body..part.offset_end)?,
-                partial,
-            )
-            .into(),
-        )
+        fetch::body_section(self, sections, partial)
     }
 
     fn binary(
@@ -1086,21 +1013,6 @@ impl<'x> AsImapDataItem<'x> for Message<'x> {
     }
 }
 
-#[inline(always)]
-fn get_partial_bytes(bytes: &[u8], partial: Option<(u32, u32)>) -> &[u8] {
-    if let Some((start, end)) = partial {
-        if let Some(bytes) =
-            bytes.get(start as usize..std::cmp::min((start + end) as usize, bytes.len()))
-        {
-            bytes
-        } else {
-            &[]
-        }
-    } else {
-        bytes
-    }
-}
-
 trait AsImapAddress {
     fn as_imap_address(&self) -> Vec<fetch::Address>;
 }
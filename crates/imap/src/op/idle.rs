@@ -28,6 +28,7 @@ use imap_proto::{
     protocol::{
         fetch,
         list::{Attribute, ListItem},
+        notify::EventGroup,
         status::Status,
         Sequence,
     },
@@ -35,9 +36,12 @@ use imap_proto::{
     Command, ResponseCode, StatusResponse,
 };
 
-use jmap_proto::types::{collection::Collection, type_state::DataType};
+use jmap_proto::types::{collection::Collection, state::StateChange, type_state::DataType};
 use store::query::log::Query;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    sync::mpsc,
+};
 use utils::map::bitmap::Bitmap;
 
 use crate::core::{SelectedMailbox, Session, SessionData, State};
@@ -55,8 +59,22 @@ impl<T: AsyncRead> Session<T> {
             ),
             _ => unreachable!(),
         };
-        let is_rev2 = self.version.is_rev2();
-        let is_qresync = self.is_qresync;
+
+        // Reuse the NOTIFY subscription, if any, as the state manager
+        // only keeps one subscription per session.
+        if let Some(mut notifier) = self.notify.take() {
+            let result = self
+                .idle(
+                    request,
+                    data,
+                    mailbox,
+                    &mut notifier.change_rx,
+                    Some(&notifier.groups),
+                )
+                .await;
+            self.notify = notifier.into();
+            return result;
+        }
 
         // Register with state manager
         let mut change_rx = if let Some(change_rx) = self
@@ -76,6 +94,21 @@ impl<T: AsyncRead> Session<T> {
                 .await;
         };
 
+        self.idle(request, data, mailbox, &mut change_rx, None)
+            .await
+    }
+
+    async fn idle(
+        &mut self,
+        request: Request<Command>,
+        data: Arc<SessionData>,
+        mailbox: Option<Arc<SelectedMailbox>>,
+        change_rx: &mut mpsc::Receiver<StateChange>,
+        groups: Option<&[EventGroup]>,
+    ) -> crate::OpResult {
+        let is_rev2 = self.version.is_rev2();
+        let is_qresync = self.is_qresync;
+
         // Send continuation response
         self.write_bytes(b"+ Idling, send 'DONE' to stop.\r\n".to_vec())
             .await?;
@@ -87,7 +120,12 @@ impl<T: AsyncRead> Session<T> {
                     match result {
                         Ok(Ok(bytes_read)) => {
                             if bytes_read > 0 {
-                                if (buf[..bytes_read]).windows(4).any(|w| w == b"DONE") {
+                                let bytes = if let Some(bytes) = self.inflate(&buf[..bytes_read]).await {
+                                    bytes
+                                } else {
+                                    return Err(());
+                                };
+                                if bytes.windows(4).any(|w| w == b"DONE") {
                                     tracing::debug!(parent: &self.span, event = "stop", context = "idle", "Stopping IDLE.");
                                     return self.write_bytes(StatusResponse::completed(Command::Idle)
                                                                     .with_tag(request.tag)
@@ -111,6 +149,11 @@ impl<T: AsyncRead> Session<T> {
                 }
                 state_change = change_rx.recv() => {
                     if let Some(state_change) = state_change {
+                        if let Some(groups) = groups {
+                            data.write_notifications(&mailbox, groups, state_change, is_qresync, is_rev2).await;
+                            continue;
+                        }
+
                         let mut has_mailbox_changes = false;
                         let mut has_email_changes = false;
 
@@ -205,73 +248,82 @@ impl SessionData {
 
         // Fetch selected mailbox changes
         if check_emails {
-            // Synchronize emails
             if let Some(mailbox) = mailbox {
-                // Obtain changes since last sync
-                let modseq = mailbox.state.lock().modseq;
-                match self.write_mailbox_changes(mailbox, is_qresync).await {
-                    Ok(new_state) => {
-                        if new_state == modseq {
-                            return;
-                        }
-                    }
-                    Err(response) => {
-                        self.write_bytes(response.into_bytes()).await;
-                        return;
+                if let Some(changed_ids) = self.write_selected_changes(mailbox, is_qresync).await {
+                    if !changed_ids.is_empty() {
+                        self.fetch(
+                            fetch::Arguments {
+                                tag: String::new(),
+                                sequence_set: Sequence::List {
+                                    items: changed_ids
+                                        .into_iter()
+                                        .map(|uid| Sequence::Number { value: uid })
+                                        .collect(),
+                                },
+                                attributes: vec![fetch::Attribute::Flags, fetch::Attribute::Uid],
+                                changed_since: None,
+                                include_vanished: false,
+                                partial: None,
+                            },
+                            mailbox.clone(),
+                            true,
+                            is_qresync,
+                            false,
+                        )
+                        .await;
                     }
                 }
+            }
+        }
+    }
 
-                // Obtain changed messages
-                let changed_ids = match self
-                    .jmap
-                    .changes_(
-                        mailbox.id.account_id,
-                        Collection::Email,
-                        modseq.map(Query::Since).unwrap_or(Query::All),
-                    )
-                    .await
-                {
-                    Ok(changelog) => {
-                        let state = mailbox.state.lock();
-                        changelog
-                            .changes
-                            .into_iter()
-                            .filter_map(|change| {
-                                state
-                                    .id_to_imap
-                                    .get(&((change.unwrap_id() & u32::MAX as u64) as u32))
-                                    .map(|id| id.uid)
-                            })
-                            .collect::<AHashSet<_>>()
-                    }
-                    Err(_) => {
-                        self.write_bytes(StatusResponse::database_failure().into_bytes())
-                            .await;
-                        return;
-                    }
-                };
+    pub async fn write_selected_changes(
+        &self,
+        mailbox: &Arc<SelectedMailbox>,
+        is_qresync: bool,
+    ) -> Option<AHashSet<u32>> {
+        // Obtain changes since last sync
+        let modseq = mailbox.state.lock().modseq;
+        match self.write_mailbox_changes(mailbox, is_qresync).await {
+            Ok(new_state) => {
+                if new_state == modseq {
+                    return None;
+                }
+            }
+            Err(response) => {
+                self.write_bytes(response.into_bytes()).await;
+                return None;
+            }
+        }
 
-                if !changed_ids.is_empty() {
-                    self.fetch(
-                        fetch::Arguments {
-                            tag: String::new(),
-                            sequence_set: Sequence::List {
-                                items: changed_ids
-                                    .into_iter()
-                                    .map(|uid| Sequence::Number { value: uid })
-                                    .collect(),
-                            },
-                            attributes: vec![fetch::Attribute::Flags, fetch::Attribute::Uid],
-                            changed_since: None,
-                            include_vanished: false,
-                        },
-                        mailbox.clone(),
-                        true,
-                        is_qresync,
-                        false,
-                    )
+        // Obtain changed messages
+        match self
+            .jmap
+            .changes_(
+                mailbox.id.account_id,
+                Collection::Email,
+                modseq.map(Query::Since).unwrap_or(Query::All),
+            )
+            .await
+        {
+            Ok(changelog) => {
+                let state = mailbox.state.lock();
+                changelog
+                    .changes
+                    .into_iter()
+                    .filter_map(|change| {
+                        state
+                            .id_to_imap
+                            .get(&((change.unwrap_id() & u32::MAX as u64) as u32))
+                            .map(|id| id.uid)
+                    })
+                    .collect::<AHashSet<_>>()
+                    .into()
+            }
+            Err(_) => {
+                self.write_bytes(StatusResponse::database_failure().into_bytes())
                     .await;
-                }
+                None
             }
         }
     }
@@ -49,7 +49,9 @@ impl<T: AsyncRead> Session<T> {
                 if !arguments.is_separator_query() {
                     let data = self.state.session_data();
                     let version = self.version;
+                    let timer = self.command_timer.take();
                     tokio::spawn(async move {
+                        let _timer = timer;
                         data.list(arguments, is_lsub, version).await;
                     });
                     Ok(())
@@ -29,6 +29,7 @@ pub mod append;
 pub mod authenticate;
 pub mod capability;
 pub mod close;
+pub mod compress;
 pub mod copy_move;
 pub mod create;
 pub mod delete;
@@ -41,13 +42,16 @@ pub mod login;
 pub mod logout;
 pub mod namespace;
 pub mod noop;
+pub mod notify;
 pub mod rename;
+pub mod replace;
 pub mod search;
 pub mod select;
 pub mod status;
 pub mod store;
 pub mod subscribe;
 pub mod thread;
+pub mod urlauth;
 
 trait FromModSeq {
     fn from_modseq(modseq: u64) -> Self;
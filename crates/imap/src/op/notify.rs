@@ -0,0 +1,453 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use imap_proto::{
+    protocol::{
+        fetch,
+        list::{Attribute, ListItem, Tag},
+        notify::{Arguments, Event, EventGroup, Filter},
+        status::Status,
+        Sequence,
+    },
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
+};
+
+use jmap_proto::types::{state::StateChange, type_state::DataType};
+use tokio::io::AsyncRead;
+use utils::map::bitmap::Bitmap;
+
+use crate::core::{Notifier, SelectedMailbox, Session, SessionData, State};
+
+impl<T: AsyncRead> Session<T> {
+    pub async fn handle_notify(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_notify(self.version) {
+            Ok(Arguments::Set {
+                tag,
+                status,
+                groups,
+            }) => {
+                // Validate events
+                if groups
+                    .iter()
+                    .flat_map(|group| group.events.iter())
+                    .any(|event| !event.is_supported())
+                {
+                    return self
+                        .write_bytes(
+                            StatusResponse::no("One or more events are not supported.")
+                                .with_tag(tag)
+                                .with_code(ResponseCode::BadEvent)
+                                .into_bytes(),
+                        )
+                        .await;
+                }
+
+                let (data, mailbox) = match &self.state {
+                    State::Authenticated { data, .. } => (data.clone(), None),
+                    State::Selected { data, mailbox, .. } => (data.clone(), mailbox.clone().into()),
+                    _ => unreachable!(),
+                };
+
+                // Register with state manager, unless already registered
+                let change_rx = if let Some(notifier) = self.notify.take() {
+                    notifier.change_rx
+                } else if let Some(change_rx) = self
+                    .jmap
+                    .subscribe_state_manager(
+                        data.account_id,
+                        data.account_id,
+                        Bitmap::from_iter([
+                            DataType::Email,
+                            DataType::Mailbox,
+                            DataType::EmailDelivery,
+                        ]),
+                    )
+                    .await
+                {
+                    change_rx
+                } else {
+                    return self
+                        .write_bytes(
+                            StatusResponse::no("It was not possible to start NOTIFY.")
+                                .with_tag(tag)
+                                .with_code(ResponseCode::ContactAdmin)
+                                .into_bytes(),
+                        )
+                        .await;
+                };
+
+                // Send the status of all monitored mailboxes
+                if status {
+                    if let Err(response) = data.synchronize_mailboxes(false).await {
+                        self.notify = Notifier { groups, change_rx }.into();
+                        return self.write_bytes(response.with_tag(tag).into_bytes()).await;
+                    }
+                    let mailbox_names = data
+                        .mailboxes
+                        .lock()
+                        .iter()
+                        .flat_map(|account| account.mailbox_names.keys().cloned())
+                        .collect::<Vec<_>>();
+                    let is_rev2 = self.version.is_rev2();
+                    let mut buf = Vec::with_capacity(64);
+                    for mailbox_name in mailbox_names {
+                        if data
+                            .notify_group(&groups, &mailbox, &mailbox_name)
+                            .map_or(false, |group| group.has_message_events())
+                        {
+                            if let Ok(status) = data
+                                .status(
+                                    mailbox_name,
+                                    &[
+                                        Status::Messages,
+                                        Status::Unseen,
+                                        Status::UidNext,
+                                        Status::UidValidity,
+                                    ],
+                                )
+                                .await
+                            {
+                                status.serialize(&mut buf, is_rev2);
+                            }
+                        }
+                    }
+                    if !buf.is_empty() {
+                        self.write_bytes(buf).await?;
+                    }
+                }
+
+                self.notify = Notifier { groups, change_rx }.into();
+                self.write_bytes(
+                    StatusResponse::completed(Command::Notify)
+                        .with_tag(tag)
+                        .into_bytes(),
+                )
+                .await
+            }
+            Ok(Arguments::None { tag }) => {
+                self.notify = None;
+                self.write_bytes(
+                    StatusResponse::completed(Command::Notify)
+                        .with_tag(tag)
+                        .into_bytes(),
+                )
+                .await
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+
+    pub async fn handle_state_change(
+        &mut self,
+        state_change: Option<StateChange>,
+    ) -> crate::OpResult {
+        let (data, mailbox) = match &self.state {
+            State::Authenticated { data, .. } => (data.clone(), None),
+            State::Selected { data, mailbox, .. } => (data.clone(), mailbox.clone().into()),
+            _ => return Ok(()),
+        };
+
+        if let Some(state_change) = state_change {
+            if let Some(notifier) = &self.notify {
+                data.write_notifications(
+                    &mailbox,
+                    &notifier.groups,
+                    state_change,
+                    self.is_qresync,
+                    self.version.is_rev2(),
+                )
+                .await;
+            }
+            Ok(())
+        } else {
+            // The state manager dropped the subscription
+            tracing::debug!(parent: &self.span, "NOTIFY channel closed.");
+            self.notify = None;
+            self.write_bytes(
+                StatusResponse::ok("Notifications have been disabled.")
+                    .with_code(ResponseCode::NotificationOverflow)
+                    .into_bytes(),
+            )
+            .await
+        }
+    }
+}
+
+impl SessionData {
+    pub async fn write_notifications(
+        &self,
+        mailbox: &Option<Arc<SelectedMailbox>>,
+        groups: &[EventGroup],
+        state_change: StateChange,
+        is_qresync: bool,
+        is_rev2: bool,
+    ) {
+        let mut check_mailboxes = false;
+        let mut check_emails = false;
+        for (type_state, _) in state_change.types {
+            match type_state {
+                DataType::Email | DataType::EmailDelivery => {
+                    check_emails = true;
+                }
+                DataType::Mailbox => {
+                    check_mailboxes = true;
+                }
+                _ => {}
+            }
+        }
+
+        // Report changes on mailboxes other than the selected one
+        if check_mailboxes {
+            match self.synchronize_mailboxes(true).await {
+                Ok(Some(changes)) => {
+                    let mut buf = Vec::with_capacity(64);
+
+                    // List deleted mailboxes
+                    for mailbox_name in changes.deleted {
+                        if self.has_notify_event(
+                            groups,
+                            mailbox,
+                            &mailbox_name,
+                            &Event::MailboxName,
+                        ) {
+                            ListItem {
+                                mailbox_name,
+                                attributes: vec![Attribute::NonExistent],
+                                tags: vec![],
+                            }
+                            .serialize(&mut buf, is_rev2, false);
+                        }
+                    }
+
+                    // List added mailboxes
+                    for mailbox_name in changes.added {
+                        if self.has_notify_event(
+                            groups,
+                            mailbox,
+                            &mailbox_name,
+                            &Event::MailboxName,
+                        ) {
+                            ListItem {
+                                mailbox_name,
+                                attributes: vec![],
+                                tags: vec![],
+                            }
+                            .serialize(&mut buf, is_rev2, false);
+                        }
+                    }
+
+                    // List renamed mailboxes
+                    for (old_name, mailbox_name) in changes.renamed {
+                        if self.has_notify_event(
+                            groups,
+                            mailbox,
+                            &mailbox_name,
+                            &Event::MailboxName,
+                        ) || self.has_notify_event(
+                            groups,
+                            mailbox,
+                            &old_name,
+                            &Event::MailboxName,
+                        ) {
+                            ListItem {
+                                mailbox_name,
+                                attributes: vec![],
+                                tags: vec![Tag::OldName(old_name)],
+                            }
+                            .serialize(&mut buf, is_rev2, false);
+                        }
+                    }
+
+                    // List mailboxes with subscription changes
+                    for mailbox_name in changes.subscriptions {
+                        if self.has_notify_event(
+                            groups,
+                            mailbox,
+                            &mailbox_name,
+                            &Event::SubscriptionChange,
+                        ) {
+                            ListItem {
+                                attributes: if self.is_subscribed(&mailbox_name) {
+                                    vec![Attribute::Subscribed]
+                                } else {
+                                    vec![]
+                                },
+                                mailbox_name,
+                                tags: vec![],
+                            }
+                            .serialize(&mut buf, is_rev2, false);
+                        }
+                    }
+
+                    // Obtain status of changed mailboxes
+                    for mailbox_name in changes.changed {
+                        if self
+                            .notify_group(groups, mailbox, &mailbox_name)
+                            .map_or(false, |group| group.has_message_events())
+                        {
+                            if let Ok(status) = self
+                                .status(
+                                    mailbox_name,
+                                    &[
+                                        Status::Messages,
+                                        Status::Unseen,
+                                        Status::UidNext,
+                                        Status::UidValidity,
+                                    ],
+                                )
+                                .await
+                            {
+                                status.serialize(&mut buf, is_rev2);
+                            }
+                        }
+                    }
+
+                    if !buf.is_empty() {
+                        self.write_bytes(buf).await;
+                    }
+                }
+                Err(_) => {
+                    tracing::debug!(parent: &self.span, "Failed to refresh mailboxes.");
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // Report changes on the selected mailbox
+        let (mailbox, group) = match (mailbox, groups.iter().find(|g| g.filter.is_selected())) {
+            (Some(mailbox), Some(group)) if check_emails && group.has_message_events() => {
+                (mailbox, group)
+            }
+            _ => return,
+        };
+        let uid_next = mailbox.state.lock().uid_next;
+        if let Some(changed_ids) = self.write_selected_changes(mailbox, is_qresync).await {
+            // Fetch flags of changed messages, new messages are reported by MessageNew
+            let changed_flags = changed_ids
+                .iter()
+                .filter(|uid| **uid < uid_next)
+                .map(|uid| Sequence::Number { value: *uid })
+                .collect::<Vec<_>>();
+            if group.has_event(&Event::FlagChange) && !changed_flags.is_empty() {
+                self.fetch(
+                    fetch::Arguments {
+                        tag: String::new(),
+                        sequence_set: Sequence::List {
+                            items: changed_flags,
+                        },
+                        attributes: vec![fetch::Attribute::Flags, fetch::Attribute::Uid],
+                        changed_since: None,
+                        include_vanished: false,
+                        partial: None,
+                    },
+                    mailbox.clone(),
+                    true,
+                    is_qresync,
+                    false,
+                )
+                .await;
+            }
+
+            // Fetch the requested attributes of new messages
+            if let Some(attributes) = group.fetch_attributes() {
+                let new_ids = changed_ids
+                    .into_iter()
+                    .filter(|uid| *uid >= uid_next)
+                    .map(|uid| Sequence::Number { value: uid })
+                    .collect::<Vec<_>>();
+                if !new_ids.is_empty() {
+                    self.fetch(
+                        fetch::Arguments {
+                            tag: String::new(),
+                            sequence_set: Sequence::List { items: new_ids },
+                            attributes: attributes.to_vec(),
+                            changed_since: None,
+                            include_vanished: false,
+                            partial: None,
+                        },
+                        mailbox.clone(),
+                        true,
+                        is_qresync,
+                        false,
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+
+    pub fn notify_group<'x>(
+        &self,
+        groups: &'x [EventGroup],
+        mailbox: &Option<Arc<SelectedMailbox>>,
+        mailbox_name: &str,
+    ) -> Option<&'x EventGroup> {
+        // Events on the selected mailbox are reported by the selected filter
+        if let Some(mailbox) = mailbox {
+            if groups.iter().any(|group| group.filter.is_selected())
+                && self.get_mailbox_by_name(mailbox_name).as_ref() == Some(&mailbox.id)
+            {
+                return None;
+            }
+        }
+
+        groups.iter().find(|group| match &group.filter {
+            Filter::Selected | Filter::SelectedDelayed => false,
+            Filter::Inboxes => mailbox_name.eq_ignore_ascii_case("INBOX"),
+            Filter::Personal => mailbox_name
+                .strip_prefix(&self.imap.name_shared)
+                .map_or(true, |name| !name.starts_with('/')),
+            Filter::Subscribed => self.is_subscribed(mailbox_name),
+            Filter::Subtree(names) => names.iter().any(|name| {
+                mailbox_name
+                    .strip_prefix(name.as_str())
+                    .map_or(false, |child| child.is_empty() || child.starts_with('/'))
+            }),
+            Filter::Mailboxes(names) => names.iter().any(|name| name == mailbox_name),
+        })
+    }
+
+    fn has_notify_event(
+        &self,
+        groups: &[EventGroup],
+        mailbox: &Option<Arc<SelectedMailbox>>,
+        mailbox_name: &str,
+        event: &Event,
+    ) -> bool {
+        self.notify_group(groups, mailbox, mailbox_name)
+            .map_or(false, |group| group.has_event(event))
+    }
+
+    fn is_subscribed(&self, mailbox_name: &str) -> bool {
+        self.mailboxes.lock().iter().any(|account| {
+            account
+                .mailbox_names
+                .get(mailbox_name)
+                .and_then(|mailbox_id| account.mailbox_state.get(mailbox_id))
+                .map_or(false, |mailbox| mailbox.is_subscribed)
+        })
+    }
+}
@@ -45,7 +45,9 @@ impl<T: AsyncRead> Session<T> {
         match request.parse_rename(self.version) {
             Ok(arguments) => {
                 let data = self.state.session_data();
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     data.write_bytes(data.rename_folder(arguments).await.into_bytes())
                         .await;
                 });
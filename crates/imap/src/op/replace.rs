@@ -0,0 +1,247 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use imap_proto::{
+    protocol::{replace::Arguments, Sequence},
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
+};
+
+use jmap::email::{
+    ingest::{IngestEmail, ReplaceEmail},
+    seen::shared_seen_apply,
+};
+use jmap_proto::types::{acl::Acl, keyword::Keyword, state::StateChange, type_state::DataType};
+use mail_parser::MessageParser;
+use tokio::io::AsyncRead;
+
+use crate::core::{SavedSearch, SelectedMailbox, Session, SessionData};
+
+impl<T: AsyncRead> Session<T> {
+    pub async fn handle_replace(
+        &mut self,
+        request: Request<Command>,
+        is_uid: bool,
+    ) -> crate::OpResult {
+        match request.parse_replace(self.version) {
+            Ok(arguments) => {
+                let (data, mailbox) = self.state.select_data();
+                let is_qresync = self.is_qresync;
+
+                let timer = self.command_timer.take();
+                tokio::spawn(async move {
+                    let _timer = timer;
+                    let tag = arguments.tag.clone();
+                    data.write_bytes(
+                        match data
+                            .replace_message(arguments, mailbox, is_uid, is_qresync)
+                            .await
+                        {
+                            Ok(response) => response,
+                            Err(response) => response.with_tag(tag),
+                        }
+                        .into_bytes(),
+                    )
+                    .await;
+                });
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+}
+
+impl SessionData {
+    async fn replace_message(
+        &self,
+        arguments: Arguments,
+        src_mailbox: Arc<SelectedMailbox>,
+        is_uid: bool,
+        is_qresync: bool,
+    ) -> crate::op::Result<StatusResponse> {
+        // Refresh mailboxes
+        self.synchronize_mailboxes(false).await?;
+
+        // Obtain destination mailbox
+        let dest_mailbox = match self.get_mailbox_by_name(&arguments.mailbox_name) {
+            Some(mailbox) if mailbox.mailbox_id.is_some() => mailbox,
+            Some(_) => {
+                return Err(StatusResponse::no(
+                    "Appending messages to this mailbox is not allowed.",
+                )
+                .with_code(ResponseCode::Cannot));
+            }
+            None => {
+                return Err(StatusResponse::no("Mailbox does not exist.")
+                    .with_code(ResponseCode::TryCreate));
+            }
+        };
+        let src_mailbox_id = src_mailbox.id.mailbox_id.ok_or_else(|| {
+            StatusResponse::no("Messages cannot be replaced in this mailbox.")
+                .with_code(ResponseCode::Cannot)
+        })?;
+        if src_mailbox.id.account_id != dest_mailbox.account_id {
+            return Err(
+                StatusResponse::no("Replacing messages across accounts is not supported.")
+                    .with_code(ResponseCode::Cannot),
+            );
+        }
+        let account_id = dest_mailbox.account_id;
+        let dest_mailbox_id = dest_mailbox.mailbox_id.unwrap();
+
+        // Obtain the message to replace
+        let (document_id, _) = src_mailbox
+            .sequence_to_ids(&Sequence::number(arguments.id), is_uid)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| StatusResponse::no("Message does not exist."))?;
+
+        // Verify ACLs
+        if !self
+            .check_mailbox_acl(account_id, dest_mailbox_id, Acl::AddItems)
+            .await?
+        {
+            return Err(StatusResponse::no(
+                "You do not have the required permissions to append messages to this mailbox.",
+            )
+            .with_code(ResponseCode::NoPerm));
+        }
+        if !self
+            .check_mailbox_acl(account_id, src_mailbox_id, Acl::RemoveItems)
+            .await?
+        {
+            return Err(StatusResponse::no(
+                "You do not have the required permissions to remove messages from this mailbox.",
+            )
+            .with_code(ResponseCode::NoPerm));
+        }
+
+        // Assemble CATENATE parts
+        let mut message = arguments.message;
+        if !message.catenate.is_empty() {
+            message.message = self
+                .catenate_message(std::mem::take(&mut message.catenate))
+                .await?;
+        }
+
+        // Append the new message and remove the old one in a single batch
+        let access_token = self.get_access_token().await?;
+        let account_quota = access_token.quota as i64;
+
+        // Members of shared accounts keep their own \Seen state
+        let shared_seen = self.jmap.shared_seen_id(&access_token, account_id);
+        let mut keywords = message
+            .flags
+            .into_iter()
+            .map(Keyword::from)
+            .collect::<Vec<_>>();
+        let mut is_seen = false;
+        if shared_seen.is_some() {
+            is_seen = keywords.contains(&Keyword::Seen);
+            shared_seen_apply(&mut keywords, false);
+        }
+        let email =
+            self.jmap
+                .email_ingest(IngestEmail {
+                    raw_message: &message.message,
+                    message: MessageParser::new().parse(&message.message),
+                    account_id,
+                    account_quota,
+                    mailbox_ids: vec![dest_mailbox_id],
+                    keywords,
+                    received_at: message.received_at.map(|d| d as u64),
+                    skip_duplicates: false,
+                    encrypt: self.jmap.config.encrypt && self.jmap.config.encrypt_append,
+                    replace: ReplaceEmail {
+                        document_id,
+                        mailbox_id: src_mailbox_id,
+                    }
+                    .into(),
+                })
+                .await
+                .map_err(|err| match err {
+                    jmap::IngestError::Temporary => StatusResponse::database_failure(),
+                    jmap::IngestError::OverQuota => StatusResponse::no("Disk quota exceeded.")
+                        .with_code(ResponseCode::OverQuota),
+                    jmap::IngestError::Permanent { reason, .. } => StatusResponse::no(reason),
+                })?;
+        if let (true, Some(user_id)) = (is_seen, shared_seen) {
+            self.jmap
+                .shared_seen_set(account_id, user_id, [email.id.document_id()], true)
+                .await?;
+        }
+
+        // Broadcast changes
+        self.jmap
+            .broadcast_state_change(
+                StateChange::new(account_id)
+                    .with_change(DataType::Email, email.change_id)
+                    .with_change(DataType::Mailbox, email.change_id)
+                    .with_change(DataType::Thread, email.change_id),
+            )
+            .await;
+
+        // Send the UID of the replacement message
+        if self.imap.enable_uidplus {
+            let document_id = email.id.document_id();
+            let uid = if src_mailbox.id == dest_mailbox {
+                self.synchronize_messages(&src_mailbox).await?;
+                let current_state = src_mailbox.state.lock();
+                let state = current_state
+                    .next_state
+                    .as_ref()
+                    .map_or(&*current_state, |state| &state.next_state);
+                state
+                    .id_to_imap
+                    .get(&document_id)
+                    .map(|id| (id.uid, state.uid_validity))
+            } else {
+                let state = self.fetch_messages(&dest_mailbox).await?;
+                state
+                    .id_to_imap
+                    .get(&document_id)
+                    .map(|id| (id.uid, state.uid_validity))
+            };
+            if let Some((uid, uid_validity)) = uid {
+                self.write_bytes(
+                    StatusResponse::ok("Replacement Message ready")
+                        .with_code(ResponseCode::AppendUid {
+                            uid_validity,
+                            uids: vec![uid],
+                        })
+                        .into_bytes(),
+                )
+                .await;
+            }
+        }
+
+        // Clear saved searches and send the expunge response
+        *src_mailbox.saved_search.lock() = SavedSearch::None;
+        self.write_mailbox_changes(&src_mailbox, is_qresync).await?;
+
+        Ok(StatusResponse::completed(Command::Replace(is_uid)).with_tag(arguments.tag))
+    }
+}
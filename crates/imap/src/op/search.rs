@@ -32,6 +32,8 @@ use imap_proto::{
     Command, StatusResponse,
 };
 
+use ahash::AHashMap;
+use jmap::email::seen::keyword_filter;
 use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
 use mail_parser::HeaderName;
 use nlp::language::Language;
@@ -73,7 +75,9 @@ impl<T: AsyncRead> Session<T> {
                         (None, None)
                     };
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     let tag = std::mem::take(&mut arguments.tag);
                     let bytes = match data
                         .search(
@@ -124,10 +128,23 @@ impl SessionData {
         is_uid: bool,
     ) -> Result<search::Response, StatusResponse> {
         // Run query
-        let (result_set, include_highest_modseq) = self
+        let (result_set, include_highest_modseq, fuzzy_terms) = self
             .query(arguments.filter, &mailbox, &prev_saved_search, is_uid)
             .await?;
 
+        // Obtain relevancy scores
+        let return_relevancy = arguments.result_options.contains(&ResultOption::Relevancy);
+        let scores = if return_relevancy
+            || arguments.sort.as_ref().map_or(false, |sort| {
+                sort.iter().any(|item| item.sort == search::Sort::Relevancy)
+            }) {
+            self.jmap
+                .fuzzy_scores(mailbox.id.account_id, &fuzzy_terms)
+                .await?
+        } else {
+            AHashMap::new()
+        };
+
         // Obtain modseq
         let highest_modseq = if include_highest_modseq {
             self.synchronize_messages(&mailbox)
@@ -178,6 +195,10 @@ impl SessionData {
                                 search::Sort::To | search::Sort::DisplayTo => {
                                     query::Comparator::field(Property::To, item.ascending)
                                 }
+                                // Most relevant messages are returned first unless reversed
+                                search::Sort::Relevancy => {
+                                    query::Comparator::scores(scores.clone(), !item.ascending)
+                                }
                             })
                             .collect::<Vec<_>>(),
                         Pagination::new(results_len, 0, None, 0),
@@ -222,6 +243,26 @@ impl SessionData {
             results_tx.send(saved_results).ok();
         }
 
+        // Map relevancy scores, messages matched without fuzzy terms are exact matches
+        let relevancy = if return_relevancy {
+            let state = mailbox.state.lock();
+            let scores = scores
+                .into_iter()
+                .filter_map(|(document_id, score)| {
+                    state
+                        .map_result_id(document_id, is_uid)
+                        .map(|(id, _)| (id, score))
+                })
+                .collect::<AHashMap<_, _>>();
+            imap_ids
+                .iter()
+                .map(|id| scores.get(id).copied().unwrap_or(100))
+                .collect::<Vec<_>>()
+                .into()
+        } else {
+            None
+        };
+
         // Build response
         Ok(Response {
             is_uid,
@@ -232,6 +273,18 @@ impl SessionData {
             } else {
                 None
             },
+            partial: arguments
+                .result_options
+                .iter()
+                .find_map(|option| match option {
+                    ResultOption::Partial(range) => {
+                        let mut ids = imap_ids.clone();
+                        range.apply(&mut ids);
+                        Some((*range, ids))
+                    }
+                    _ => None,
+                }),
+            relevancy,
             ids: if arguments.result_options.is_empty()
                 || arguments.result_options.contains(&ResultOption::All)
             {
@@ -251,9 +304,14 @@ impl SessionData {
         mailbox: &SelectedMailbox,
         prev_saved_search: &Option<Option<Arc<Vec<ImapId>>>>,
         is_uid: bool,
-    ) -> Result<(ResultSet, bool), StatusResponse> {
+    ) -> Result<(ResultSet, bool, Vec<(Vec<u8>, String)>), StatusResponse> {
         // Obtain message ids
         let mut filters = Vec::with_capacity(imap_filter.len() + 1);
+        let mut fuzzy_terms = Vec::new();
+        let mut fuzzy_stack = Vec::new();
+        let shared_seen = self
+            .jmap
+            .shared_seen_id(&*self.get_access_token().await?, mailbox.id.account_id);
         let message_ids = if let Some(mailbox_id) = mailbox.id.mailbox_id {
             let ids = self
                 .jmap
@@ -277,6 +335,7 @@ impl SessionData {
         // Convert query
         let mut include_highest_modseq = false;
         for filter in imap_filter {
+            let is_fuzzy = fuzzy_stack.iter().any(|is_fuzzy| *is_fuzzy);
             match filter {
                 search::Filter::Sequence(sequence, uid_filter) => {
                     let mut set = RoaringBitmap::new();
@@ -320,11 +379,20 @@ impl SessionData {
                     filters.push(query::Filter::lt(Property::ReceivedAt, date as u64));
                 }
                 search::Filter::Body(text) => {
-                    filters.push(query::Filter::has_text_detect(
-                        Property::TextBody,
-                        text,
-                        self.jmap.config.default_language,
-                    ));
+                    if is_fuzzy {
+                        filters.push(query::Filter::has_fuzzy_text(
+                            Property::TextBody,
+                            &text,
+                            self.jmap.config.default_language,
+                        ));
+                        fuzzy_terms.push((vec![Property::TextBody.into()], text));
+                    } else {
+                        filters.push(query::Filter::has_text_detect(
+                            Property::TextBody,
+                            text,
+                            self.jmap.config.default_language,
+                        ));
+                    }
                 }
                 search::Filter::Cc(text) => {
                     filters.push(query::Filter::has_text(Property::Cc, text, Language::None));
@@ -415,10 +483,7 @@ impl SessionData {
                     }
                 },
                 search::Filter::Keyword(keyword) => {
-                    filters.push(query::Filter::is_in_bitmap(
-                        Property::Keywords,
-                        Keyword::from(keyword),
-                    ));
+                    filters.push(keyword_filter(shared_seen, Keyword::from(keyword)));
                 }
                 search::Filter::Larger(size) => {
                     filters.push(query::Filter::gt(Property::Size, size));
@@ -433,10 +498,7 @@ impl SessionData {
                     filters.push(query::Filter::End);
                 }
                 search::Filter::Seen => {
-                    filters.push(query::Filter::is_in_bitmap(
-                        Property::Keywords,
-                        Keyword::Seen,
-                    ));
+                    filters.push(keyword_filter(shared_seen, Keyword::Seen));
                 }
                 search::Filter::SentBefore(date) => {
                     filters.push(query::Filter::lt(Property::SentAt, date as u64));
@@ -457,13 +519,28 @@ impl SessionData {
                     filters.push(query::Filter::lt(Property::Size, size));
                 }
                 search::Filter::Subject(text) => {
-                    filters.push(query::Filter::has_text_detect(
-                        Property::Subject,
-                        text,
-                        self.jmap.config.default_language,
-                    ));
+                    if is_fuzzy {
+                        filters.push(query::Filter::has_fuzzy_text(
+                            Property::Subject,
+                            &text,
+                            self.jmap.config.default_language,
+                        ));
+                        fuzzy_terms.push((vec![Property::Subject.into()], text));
+                    } else {
+                        filters.push(query::Filter::has_text_detect(
+                            Property::Subject,
+                            text,
+                            self.jmap.config.default_language,
+                        ));
+                    }
                 }
                 search::Filter::Text(text) => {
+                    if !is_fuzzy {
+                        if let Some(text_filters) = self.jmap.parse_text_query(&text) {
+                            filters.extend(text_filters);
+                            continue;
+                        }
+                    }
                     filters.push(query::Filter::Or);
                     filters.push(query::Filter::has_text(
                         Property::From,
@@ -477,21 +554,37 @@ impl SessionData {
                         &text,
                         Language::None,
                     ));
-                    filters.push(query::Filter::has_text_detect(
-                        Property::Subject,
-                        &text,
-                        self.jmap.config.default_language,
-                    ));
-                    filters.push(query::Filter::has_text_detect(
-                        Property::TextBody,
-                        &text,
-                        self.jmap.config.default_language,
-                    ));
-                    filters.push(query::Filter::has_text_detect(
-                        Property::Attachments,
-                        text,
-                        self.jmap.config.default_language,
-                    ));
+                    if is_fuzzy {
+                        let fields: Vec<u8> = vec![
+                            Property::Subject.into(),
+                            Property::TextBody.into(),
+                            Property::Attachments.into(),
+                        ];
+                        for field in &fields {
+                            filters.push(query::Filter::has_fuzzy_text(
+                                *field,
+                                &text,
+                                self.jmap.config.default_language,
+                            ));
+                        }
+                        fuzzy_terms.push((fields, text));
+                    } else {
+                        filters.push(query::Filter::has_text_detect(
+                            Property::Subject,
+                            &text,
+                            self.jmap.config.default_language,
+                        ));
+                        filters.push(query::Filter::has_text_detect(
+                            Property::TextBody,
+                            &text,
+                            self.jmap.config.default_language,
+                        ));
+                        filters.push(query::Filter::has_text_detect(
+                            Property::Attachments,
+                            text,
+                            self.jmap.config.default_language,
+                        ));
+                    }
                     filters.push(query::Filter::End);
                 }
                 search::Filter::To(text) => {
@@ -531,31 +624,33 @@ impl SessionData {
                 }
                 search::Filter::Unkeyword(keyword) => {
                     filters.push(query::Filter::Not);
-                    filters.push(query::Filter::is_in_bitmap(
-                        Property::Keywords,
-                        Keyword::from(keyword),
-                    ));
+                    filters.push(keyword_filter(shared_seen, Keyword::from(keyword)));
                     filters.push(query::Filter::End);
                 }
                 search::Filter::Unseen => {
                     filters.push(query::Filter::Not);
-                    filters.push(query::Filter::is_in_bitmap(
-                        Property::Keywords,
-                        Keyword::Seen,
-                    ));
+                    filters.push(keyword_filter(shared_seen, Keyword::Seen));
                     filters.push(query::Filter::End);
                 }
                 search::Filter::And => {
                     filters.push(query::Filter::And);
+                    fuzzy_stack.push(false);
                 }
                 search::Filter::Or => {
                     filters.push(query::Filter::Or);
+                    fuzzy_stack.push(false);
                 }
                 search::Filter::Not => {
                     filters.push(query::Filter::Not);
+                    fuzzy_stack.push(false);
+                }
+                search::Filter::Fuzzy => {
+                    filters.push(query::Filter::And);
+                    fuzzy_stack.push(true);
                 }
                 search::Filter::End => {
                     filters.push(query::Filter::End);
+                    fuzzy_stack.pop();
                 }
                 search::Filter::Recent => {
                     filters.push(query::Filter::is_in_bitmap(
@@ -570,19 +665,13 @@ impl SessionData {
                         Keyword::Recent,
                     ));
                     filters.push(query::Filter::Not);
-                    filters.push(query::Filter::is_in_bitmap(
-                        Property::Keywords,
-                        Keyword::Seen,
-                    ));
+                    filters.push(keyword_filter(shared_seen, Keyword::Seen));
                     filters.push(query::Filter::End);
                     filters.push(query::Filter::End);
                 }
                 search::Filter::Old => {
                     filters.push(query::Filter::Not);
-                    filters.push(query::Filter::is_in_bitmap(
-                        Property::Keywords,
-                        Keyword::Seen,
-                    ));
+                    filters.push(keyword_filter(shared_seen, Keyword::Seen));
                     filters.push(query::Filter::End);
                 }
                 search::Filter::Older(secs) => {
@@ -640,6 +729,21 @@ impl SessionData {
                         )));
                     }
                 }
+                search::Filter::SavedBefore(date) => {
+                    filters.push(query::Filter::lt(Property::SavedAt, date as u64));
+                }
+                search::Filter::SavedOn(date) => {
+                    filters.push(query::Filter::And);
+                    filters.push(query::Filter::ge(Property::SavedAt, date as u64));
+                    filters.push(query::Filter::lt(Property::SavedAt, (date + 86400) as u64));
+                    filters.push(query::Filter::End);
+                }
+                search::Filter::SavedSince(date) => {
+                    filters.push(query::Filter::ge(Property::SavedAt, date as u64));
+                }
+                search::Filter::SaveDateSupported => {
+                    filters.push(query::Filter::is_in_set(message_ids.clone()));
+                }
             }
         }
 
@@ -647,7 +751,7 @@ impl SessionData {
         self.jmap
             .filter(mailbox.id.account_id, Collection::Email, filters)
             .await
-            .map(|res| (res, include_highest_modseq))
+            .map(|res| (res, include_highest_modseq, fuzzy_terms))
             .map_err(|err| err.into())
     }
 }
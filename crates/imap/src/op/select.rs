@@ -101,6 +101,7 @@ impl<T: AsyncRead> Session<T> {
                                             attributes: vec![fetch::Attribute::Flags],
                                             changed_since: qresync.modseq.into(),
                                             include_vanished: true,
+                                            partial: None,
                                         },
                                         mailbox.clone(),
                                         true,
@@ -44,7 +44,9 @@ impl<T: AsyncRead> Session<T> {
             Ok(arguments) => {
                 let version = self.version;
                 let data = self.state.session_data();
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     // Refresh mailboxes
                     if let Err(err) = data.synchronize_mailboxes(false).await {
                         data.write_bytes(err.with_tag(arguments.tag).into_bytes())
@@ -216,6 +218,9 @@ impl SessionData {
             } else {
                 None
             };
+            let shared_seen = self
+                .jmap
+                .shared_seen_id(&*self.get_access_token().await?, mailbox.account_id);
 
             if let Some(mailbox_id) = mailbox.mailbox_id {
                 let mailbox_message_ids = self
@@ -246,12 +251,7 @@ impl SessionData {
                             {
                                 if let Some(mut seen) = self
                                     .jmap
-                                    .get_tag(
-                                        mailbox.account_id,
-                                        Collection::Email,
-                                        Property::Keywords,
-                                        Keyword::Seen,
-                                    )
+                                    .get_keyword_tag(mailbox.account_id, shared_seen, Keyword::Seen)
                                     .await?
                                 {
                                     seen ^= message_ids;
@@ -312,12 +312,7 @@ impl SessionData {
                         Status::UidValidity => mailbox_state.as_ref().unwrap().uid_validity as u64,
                         Status::Unseen => self
                             .jmap
-                            .get_tag(
-                                mailbox.account_id,
-                                Collection::Email,
-                                Property::Keywords,
-                                Keyword::Seen,
-                            )
+                            .get_keyword_tag(mailbox.account_id, shared_seen, Keyword::Seen)
                             .await?
                             .map(|mut seen| {
                                 seen ^= message_ids.as_ref();
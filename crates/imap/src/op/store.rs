@@ -33,7 +33,7 @@ use imap_proto::{
     receiver::Request,
     Command, ResponseCode, ResponseType, StatusResponse,
 };
-use jmap::email::set::TagManager;
+use jmap::email::{seen::shared_seen_apply, set::TagManager};
 use jmap_proto::{
     error::method::MethodError,
     types::{
@@ -43,7 +43,7 @@ use jmap_proto::{
 };
 use store::{
     query::log::{Change, Query},
-    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, F_VALUE},
+    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, F_CLEAR, F_VALUE},
 };
 use tokio::io::AsyncRead;
 
@@ -62,7 +62,9 @@ impl<T: AsyncRead> Session<T> {
                 let (data, mailbox) = self.state.select_data();
                 let is_condstore = self.is_condstore || mailbox.is_condstore;
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     let bytes = match data.store(arguments, mailbox, is_uid, is_condstore).await {
                         Ok(response) => response,
                         Err(response) => response.into_bytes(),
@@ -190,11 +192,29 @@ impl SessionData {
         };
 
         // Process each change
-        let set_keywords = arguments
+        let mut set_keywords = arguments
             .keywords
             .into_iter()
             .map(Keyword::from)
             .collect::<Vec<_>>();
+
+        // Members of shared accounts only change their own \Seen state
+        let shared_seen = self.shared_seen(account_id).await.map_err(|_| {
+            StatusResponse::database_failure().with_tag(response.tag.as_ref().unwrap())
+        })?;
+        let mut set_seen = false;
+        if let Some((user_id, _)) = &shared_seen {
+            set_seen = set_keywords.contains(&Keyword::Seen);
+            if set_seen {
+                self.jmap
+                    .shared_seen_register(account_id, *user_id)
+                    .await
+                    .map_err(|_| {
+                        StatusResponse::database_failure().with_tag(response.tag.as_ref().unwrap())
+                    })?;
+            }
+            shared_seen_apply(&mut set_keywords, false);
+        }
         let mut changelog = ChangeLogBuilder::new();
         let mut changed_mailboxes = AHashSet::new();
         for (id, imap_id) in ids {
@@ -224,9 +244,18 @@ impl SessionData {
             };
 
             // Apply changes
+            let mut seen_update = None;
             match arguments.operation {
                 Operation::Set => {
-                    keywords.set(set_keywords.clone());
+                    let mut set_keywords = set_keywords.clone();
+                    if shared_seen.is_some() {
+                        seen_update = set_seen.into();
+                        shared_seen_apply(
+                            &mut set_keywords,
+                            keywords.current().contains(&Keyword::Seen),
+                        );
+                    }
+                    keywords.set(set_keywords);
                 }
                 Operation::Add => {
                     for keyword in &set_keywords {
@@ -239,19 +268,27 @@ impl SessionData {
                     }
                 }
             }
+            let mut is_seen = false;
+            if let Some((_, seen_ids)) = &shared_seen {
+                is_seen = seen_ids.contains(id);
+                if set_seen && !matches!(arguments.operation, Operation::Set) {
+                    seen_update = matches!(arguments.operation, Operation::Add).into();
+                }
+                seen_update = seen_update.filter(|seen| *seen != is_seen);
+            }
 
-            if keywords.has_changes() {
+            if keywords.has_changes() || seen_update.is_some() {
                 // Convert keywords to flags
-                let seen_changed = keywords
-                    .changed_tags()
-                    .any(|keyword| keyword == &Keyword::Seen);
+                let seen_changed = seen_update.is_some()
+                    || keywords
+                        .changed_tags()
+                        .any(|keyword| keyword == &Keyword::Seen);
                 let flags = if !arguments.is_silent {
-                    keywords
-                        .current()
-                        .iter()
-                        .cloned()
-                        .map(Flag::from)
-                        .collect::<Vec<_>>()
+                    let mut current = keywords.current().to_vec();
+                    if shared_seen.is_some() {
+                        shared_seen_apply(&mut current, seen_update.unwrap_or(is_seen));
+                    }
+                    current.into_iter().map(Flag::from).collect::<Vec<_>>()
                 } else {
                     vec![]
                 };
@@ -262,7 +299,12 @@ impl SessionData {
                     .with_account_id(account_id)
                     .with_collection(Collection::Email)
                     .update_document(id);
-                keywords.update_batch(&mut batch, Property::Keywords);
+                if let (Some(seen), Some((user_id, _))) = (seen_update, &shared_seen) {
+                    batch.bitmap(Property::Keywords, *user_id, if seen { 0 } else { F_CLEAR });
+                }
+                if keywords.has_changes() {
+                    keywords.update_batch(&mut batch, Property::Keywords);
+                }
                 if changelog.change_id == u64::MAX {
                     changelog.change_id =
                         self.jmap.assign_change_id(account_id).await.map_err(|_| {
@@ -45,7 +45,9 @@ impl<T: AsyncRead> Session<T> {
         match request.parse_subscribe(self.version) {
             Ok(arguments) => {
                 let data = self.state.session_data();
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     data.write_bytes(
                         data.subscribe_folder(arguments.tag, arguments.mailbox_name, is_subscribe)
                             .await
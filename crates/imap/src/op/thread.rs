@@ -50,7 +50,9 @@ impl<T: AsyncRead> Session<T> {
             Ok(mut arguments) => {
                 let (data, mailbox) = self.state.mailbox_state();
 
+                let timer = self.command_timer.take();
                 tokio::spawn(async move {
+                    let _timer = timer;
                     let tag = std::mem::take(&mut arguments.tag);
                     let bytes = match data.thread(arguments, mailbox, is_uid).await {
                         Ok(response) => StatusResponse::completed(command)
@@ -75,7 +77,7 @@ impl SessionData {
         is_uid: bool,
     ) -> Result<Response, StatusResponse> {
         // Run query
-        let (result_set, _) = self
+        let (result_set, _, _) = self
             .query(arguments.filter, &mailbox, &None, is_uid)
             .await?;
 
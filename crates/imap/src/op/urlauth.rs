@@ -0,0 +1,256 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use imap_proto::{
+    protocol::urlauth::{GenUrlAuthResponse, ImapUrl, UrlFetchResponse},
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
+};
+
+use jmap::email::urlauth::UrlRequester;
+use jmap_proto::types::acl::Acl;
+use tokio::io::AsyncRead;
+
+use crate::core::{MailboxId, Session, SessionData};
+
+impl<T: AsyncRead> Session<T> {
+    pub async fn handle_genurlauth(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_genurlauth() {
+            Ok(arguments) => {
+                let data = self.state.session_data();
+
+                let timer = self.command_timer.take();
+                tokio::spawn(async move {
+                    let _timer = timer;
+                    data.write_bytes(match data.genurlauth(arguments.urls).await {
+                        Ok(urls) => StatusResponse::completed(Command::GenUrlAuth)
+                            .with_tag(arguments.tag)
+                            .serialize(GenUrlAuthResponse { urls }.into_bytes()),
+                        Err(response) => response.with_tag(arguments.tag).into_bytes(),
+                    })
+                    .await;
+                });
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+
+    pub async fn handle_urlfetch(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_urlfetch() {
+            Ok(arguments) => {
+                let data = self.state.session_data();
+
+                let timer = self.command_timer.take();
+                tokio::spawn(async move {
+                    let _timer = timer;
+                    data.write_bytes(match data.urlfetch(arguments.urls).await {
+                        Ok(items) => StatusResponse::completed(Command::UrlFetch)
+                            .with_tag(arguments.tag)
+                            .serialize(UrlFetchResponse { items }.into_bytes()),
+                        Err(response) => response.with_tag(arguments.tag).into_bytes(),
+                    })
+                    .await;
+                });
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+
+    pub async fn handle_resetkey(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_resetkey(self.version) {
+            Ok(arguments) => {
+                let data = self.state.session_data();
+
+                let timer = self.command_timer.take();
+                tokio::spawn(async move {
+                    let _timer = timer;
+                    data.write_bytes(
+                        match data.resetkey(arguments.mailbox_name).await {
+                            Ok(response) => response,
+                            Err(response) => response,
+                        }
+                        .with_tag(arguments.tag)
+                        .into_bytes(),
+                    )
+                    .await;
+                });
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+}
+
+impl SessionData {
+    async fn genurlauth(&self, urls: Vec<String>) -> crate::op::Result<Vec<String>> {
+        let access_token = self.get_access_token().await?;
+        self.synchronize_mailboxes(false).await?;
+
+        let mut signed_urls = Vec::with_capacity(urls.len());
+        for url in urls {
+            // Only the mailbox owner can authorize access to its messages
+            let imap_url = if let Some(imap_url) = ImapUrl::parse(&url).filter(|imap_url| {
+                !imap_url.is_relative()
+                    && imap_url.access.is_some()
+                    && imap_url.token.is_none()
+                    && imap_url.user.as_ref() == Some(&access_token.name)
+            }) {
+                imap_url
+            } else {
+                return Err(StatusResponse::bad(format!("Invalid URLAUTH URL {url:?}.")));
+            };
+
+            match self.resolve_url(&imap_url).await? {
+                Some((mailbox, document_id)) if mailbox.account_id == self.account_id => {
+                    let token = self
+                        .jmap
+                        .urlauth_sign(
+                            mailbox.account_id,
+                            mailbox.mailbox_id.unwrap(),
+                            document_id,
+                            &imap_url.rump,
+                        )
+                        .await?;
+                    signed_urls.push(format!("{url}:INTERNAL:{token}"));
+                }
+                _ => {
+                    return Err(StatusResponse::no(format!(
+                        "Unable to generate URLAUTH for {url:?}."
+                    ))
+                    .with_code(ResponseCode::BadUrl { url }));
+                }
+            }
+        }
+
+        Ok(signed_urls)
+    }
+
+    async fn urlfetch(
+        &self,
+        urls: Vec<String>,
+    ) -> crate::op::Result<Vec<(String, Option<Vec<u8>>)>> {
+        let access_token = self.get_access_token().await?;
+        let mut items = Vec::with_capacity(urls.len());
+        for url in urls {
+            let contents = if let Some(imap_url) = ImapUrl::parse(&url) {
+                self.jmap
+                    .urlauth_fetch(&imap_url, UrlRequester::Imap(&access_token.name))
+                    .await?
+            } else {
+                None
+            };
+            items.push((url, contents));
+        }
+
+        Ok(items)
+    }
+
+    async fn resetkey(&self, mailbox_name: Option<String>) -> crate::op::Result<StatusResponse> {
+        let mailbox_id = if let Some(mailbox_name) = mailbox_name {
+            self.synchronize_mailboxes(false).await?;
+            match self.get_mailbox_by_name(&mailbox_name) {
+                Some(MailboxId {
+                    account_id,
+                    mailbox_id: Some(mailbox_id),
+                }) if account_id == self.account_id => Some(mailbox_id),
+                _ => {
+                    return Ok(StatusResponse::no("Mailbox does not exist.")
+                        .with_code(ResponseCode::NonExistent));
+                }
+            }
+        } else {
+            None
+        };
+
+        self.jmap
+            .urlauth_reset_keys(self.account_id, mailbox_id)
+            .await?;
+
+        Ok(StatusResponse::completed(Command::ResetKey))
+    }
+
+    pub async fn fetch_url(&self, url: &str) -> crate::op::Result<Option<Vec<u8>>> {
+        let imap_url = if let Some(imap_url) = ImapUrl::parse(url) {
+            imap_url
+        } else {
+            return Ok(None);
+        };
+        let access_token = self.get_access_token().await?;
+
+        if imap_url.token.is_some() {
+            self.jmap
+                .urlauth_fetch(&imap_url, UrlRequester::Imap(&access_token.name))
+                .await
+                .map_err(Into::into)
+        } else if imap_url.is_relative() || imap_url.user.as_ref() == Some(&access_token.name) {
+            self.synchronize_mailboxes(false).await?;
+            if let Some((mailbox, document_id)) = self.resolve_url(&imap_url).await? {
+                self.jmap
+                    .get_message_section(
+                        mailbox.account_id,
+                        document_id,
+                        &imap_url.sections,
+                        imap_url.partial,
+                    )
+                    .await
+                    .map_err(Into::into)
+            } else {
+                Ok(None)
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn resolve_url(&self, url: &ImapUrl) -> crate::op::Result<Option<(MailboxId, u32)>> {
+        let mailbox = match self.get_mailbox_by_name(&url.mailbox_name) {
+            Some(mailbox) if mailbox.mailbox_id.is_some() => mailbox,
+            _ => return Ok(None),
+        };
+        if !self
+            .check_mailbox_acl(
+                mailbox.account_id,
+                mailbox.mailbox_id.unwrap(),
+                Acl::ReadItems,
+            )
+            .await?
+        {
+            return Ok(None);
+        }
+
+        let state = self.fetch_messages(&mailbox).await?;
+        if url
+            .uid_validity
+            .map_or(false, |uid_validity| uid_validity != state.uid_validity)
+        {
+            return Ok(None);
+        }
+
+        Ok(state
+            .uid_to_id
+            .get(&url.uid)
+            .map(|document_id| (mailbox, *document_id)))
+    }
+}
@@ -99,6 +99,8 @@ pub enum SetErrorType {
     InvalidScript,
     #[serde(rename = "scriptIsActive")]
     ScriptIsActive,
+    #[serde(rename = "mdnAlreadySent")]
+    MdnAlreadySent,
 }
 
 impl SetErrorType {
@@ -129,6 +131,7 @@ impl SetErrorType {
             SetErrorType::AlreadyExists => "alreadyExists",
             SetErrorType::InvalidScript => "invalidScript",
             SetErrorType::ScriptIsActive => "scriptIsActive",
+            SetErrorType::MdnAlreadySent => "mdnAlreadySent",
         }
     }
 }
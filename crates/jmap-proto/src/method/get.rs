@@ -51,6 +51,7 @@ pub enum RequestArguments {
     PushSubscription,
     SieveScript,
     VacationResponse,
+    MailFilter,
     Principal,
     Quota,
     Blob(blob::GetArguments),
@@ -86,6 +87,7 @@ impl JsonObjectParser for GetRequest<RequestArguments> {
                 MethodObject::PushSubscription => RequestArguments::PushSubscription,
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::VacationResponse => RequestArguments::VacationResponse,
+                MethodObject::MailFilter => RequestArguments::MailFilter,
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Blob => RequestArguments::Blob(Default::default()),
                 MethodObject::Quota => RequestArguments::Quota,
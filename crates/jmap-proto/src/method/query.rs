@@ -108,6 +108,7 @@ pub enum Filter {
     SentBefore(UTCDate),
     SentAfter(UTCDate),
     InThread(Id),
+    Fuzzy(String),
     ParentId(Option<Id>),
     Role(Option<String>),
     HasAnyRole(bool),
@@ -152,6 +153,7 @@ pub enum SortProperty {
     AllInThreadHaveKeyword,
     SomeInThreadHaveKeyword,
     Used,
+    Relevance,
     _T(String),
 }
 
@@ -414,6 +416,9 @@ pub fn parse_filter(parser: &mut Parser) -> crate::parser::Result<Vec<Filter>> {
                         (0x6461_6572_6854_6e69, _) => {
                             Filter::InThread(parser.next_token::<Id>()?.unwrap_string("inThread")?)
                         }
+                        (0x0079_7a7a_7566, _) => {
+                            Filter::Fuzzy(parser.next_token::<String>()?.unwrap_string("fuzzy")?)
+                        }
                         (0x6449_746e_6572_6170, _) => Filter::ParentId(
                             parser
                                 .next_token::<Id>()?
@@ -583,6 +588,7 @@ impl JsonObjectParser for SortProperty {
             0x4b65_7661_4864_6165_7268_546e_496c_6c61 => Ok(SortProperty::AllInThreadHaveKeyword),
             0x6576_6148_6461_6572_6854_6e49_656d_6f73 => Ok(SortProperty::SomeInThreadHaveKeyword),
             0x6465_7375 => Ok(SortProperty::Used),
+            0x0065_636e_6176_656c_6572 => Ok(SortProperty::Relevance),
             _ => {
                 if parser.is_eof || parser.skip_string() {
                     Ok(SortProperty::_T(
@@ -638,6 +644,7 @@ impl Display for Filter {
             Filter::SentBefore(_) => "sentBefore",
             Filter::SentAfter(_) => "sentAfter",
             Filter::InThread(_) => "inThread",
+            Filter::Fuzzy(_) => "fuzzy",
             Filter::ParentId(_) => "parentId",
             Filter::Role(_) => "role",
             Filter::HasAnyRole(_) => "hasAnyRole",
@@ -676,6 +683,7 @@ impl Display for SortProperty {
             SortProperty::AllInThreadHaveKeyword => "allInThreadHaveKeyword",
             SortProperty::SomeInThreadHaveKeyword => "someInThreadHaveKeyword",
             SortProperty::Used => "used",
+            SortProperty::Relevance => "relevance",
             SortProperty::_T(s) => s,
         })
     }
@@ -715,6 +723,7 @@ impl Filter {
                 | Filter::Id(_)
                 | Filter::SentBefore(_)
                 | Filter::SentAfter(_)
+                | Filter::Fuzzy(_)
         )
     }
 }
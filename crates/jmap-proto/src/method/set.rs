@@ -71,6 +71,7 @@ pub enum RequestArguments {
     PushSubscription,
     SieveScript(sieve::SetArguments),
     VacationResponse,
+    MailFilter,
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize)]
@@ -130,6 +131,7 @@ impl JsonObjectParser for SetRequest<RequestArguments> {
                 }
                 MethodObject::PushSubscription => RequestArguments::PushSubscription,
                 MethodObject::VacationResponse => RequestArguments::VacationResponse,
+                MethodObject::MailFilter => RequestArguments::MailFilter,
                 MethodObject::SieveScript => RequestArguments::SieveScript(Default::default()),
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
@@ -233,12 +235,16 @@ impl JsonObjectParser for Object<SetValue> {
                     | Property::TextSignature
                     | Property::Type
                     | Property::Charset
-                    | Property::Disposition
                     | Property::Language
                     | Property::Location
                     | Property::Cid
                     | Property::Role
-                    | Property::PartId => parser
+                    | Property::PartId
+                    | Property::ReportingUA
+                    | Property::MdnGateway
+                    | Property::OriginalRecipient
+                    | Property::FinalRecipient
+                    | Property::OriginalMessageId => parser
                         .next_token::<String>()?
                         .unwrap_string_or_null("")?
                         .map(|text| SetValue::Value(Value::Text(text)))
@@ -257,20 +263,41 @@ impl JsonObjectParser for Object<SetValue> {
                                 .unwrap_or(SetValue::Value(Value::Null))
                         }
                     }
+                    Property::Disposition => {
+                        if let MethodObject::Mdn = &parser.ctx {
+                            SetValue::Value(Value::parse::<ObjectProperty, String>(
+                                parser.next_token()?,
+                                parser,
+                            )?)
+                        } else {
+                            parser
+                                .next_token::<String>()?
+                                .unwrap_string_or_null("")?
+                                .map(|text| SetValue::Value(Value::Text(text)))
+                                .unwrap_or(SetValue::Value(Value::Null))
+                        }
+                    }
                     Property::HasAttachment
                     | Property::IsSubscribed
                     | Property::IsEnabled
-                    | Property::IsActive => parser
+                    | Property::IsActive
+                    | Property::IncludeOriginalMessage => parser
                         .next_token::<String>()?
                         .unwrap_bool_or_null("")?
                         .map(|bool| SetValue::Value(Value::Bool(bool)))
                         .unwrap_or(SetValue::Value(Value::Null)),
-                    Property::Size | Property::SortOrder | Property::Quota => parser
+                    Property::Size
+                    | Property::SortOrder
+                    | Property::Quota
+                    | Property::Retention => parser
                         .next_token::<String>()?
                         .unwrap_uint_or_null("")?
                         .map(|uint| SetValue::Value(Value::UnsignedInt(uint)))
                         .unwrap_or(SetValue::Value(Value::Null)),
-                    Property::ParentId | Property::EmailId | Property::IdentityId => parser
+                    Property::ParentId
+                    | Property::EmailId
+                    | Property::IdentityId
+                    | Property::ForEmailId => parser
                         .next_token::<MaybeReference<Id, String>>()?
                         .unwrap_string_or_null("")?
                         .map(SetValue::from)
@@ -342,14 +369,15 @@ impl JsonObjectParser for Object<SetValue> {
                     | Property::SubParts
                     | Property::To
                     | Property::UndoStatus
-                    | Property::Types => SetValue::Value(Value::parse::<ObjectProperty, String>(
-                        parser.next_token()?,
-                        parser,
-                    )?),
-                    Property::Parameters => SetValue::Value(Value::parse::<String, String>(
+                    | Property::Types
+                    | Property::Rules
+                    | Property::Error => SetValue::Value(Value::parse::<ObjectProperty, String>(
                         parser.next_token()?,
                         parser,
                     )?),
+                    Property::Parameters | Property::ExtensionFields => SetValue::Value(
+                        Value::parse::<String, String>(parser.next_token()?, parser)?,
+                    ),
                     Property::Members => SetValue::Value(Value::parse::<ObjectProperty, Id>(
                         parser.next_token()?,
                         parser,
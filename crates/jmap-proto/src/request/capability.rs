@@ -48,6 +48,12 @@ pub enum Capability {
     Blob = 1 << 8,
     #[serde(rename(serialize = "urn:ietf:params:jmap:quota"))]
     Quota = 1 << 9,
+    #[serde(rename(serialize = "urn:ietf:params:jmap:mdn"))]
+    Mdn = 1 << 10,
+    #[serde(rename(serialize = "urn:ietf:params:jmap:smimeverify"))]
+    SmimeVerify = 1 << 11,
+    #[serde(rename(serialize = "urn:ietf:params:jmap:mailfilter"))]
+    MailFilter = 1 << 12,
 }
 
 impl JsonObjectParser for Capability {
@@ -77,6 +83,9 @@ impl JsonObjectParser for Capability {
                 0x0065_7665_6973 => Ok(Capability::Sieve),
                 0x626f_6c62 => Ok(Capability::Blob),
                 0x0061_746f_7571 => Ok(Capability::Quota),
+                0x006e_646d => Ok(Capability::Mdn),
+                0x0079_6669_7265_7665_6d69_6d73 => Ok(Capability::SmimeVerify),
+                0x7265_746c_6966_6c69_616d => Ok(Capability::MailFilter),
                 _ => Err(parser.error_capability()),
             },
             Err(Error::Method(_)) => Err(parser.error_capability()),
@@ -46,6 +46,8 @@ pub enum MethodObject {
     SieveScript,
     Principal,
     Quota,
+    Mdn,
+    MailFilter,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -61,6 +63,7 @@ pub enum MethodFunction {
     Validate,
     Lookup,
     Upload,
+    Send,
     Echo,
 }
 
@@ -114,6 +117,8 @@ impl JsonObjectParser for MethodName {
                 0x006c_6170_6963_6e69_7250 => MethodObject::Principal,
                 0x0061_746f_7551 => MethodObject::Quota,
                 0x6572_6f43 => MethodObject::Core,
+                0x004e_444d => MethodObject::Mdn,
+                0x7265_746c_6946_6c69_614d => MethodObject::MailFilter,
                 _ => return Err(parser.error_value()),
             },
             fnc: match fnc_hash {
@@ -128,6 +133,7 @@ impl JsonObjectParser for MethodName {
                 0x6574_6164_696c_6176 => MethodFunction::Validate,
                 0x7075_6b6f_6f6c => MethodFunction::Lookup,
                 0x6461_6f6c_7075 => MethodFunction::Upload,
+                0x646e_6573 => MethodFunction::Send,
                 0x6f68_6365 => MethodFunction::Echo,
                 _ => return Err(parser.error_value()),
             },
@@ -193,6 +199,9 @@ impl MethodName {
             (MethodFunction::Get, MethodObject::VacationResponse) => "VacationResponse/get",
             (MethodFunction::Set, MethodObject::VacationResponse) => "VacationResponse/set",
 
+            (MethodFunction::Get, MethodObject::MailFilter) => "MailFilter/get",
+            (MethodFunction::Set, MethodObject::MailFilter) => "MailFilter/set",
+
             (MethodFunction::Get, MethodObject::SieveScript) => "SieveScript/get",
             (MethodFunction::Set, MethodObject::SieveScript) => "SieveScript/set",
             (MethodFunction::Query, MethodObject::SieveScript) => "SieveScript/query",
@@ -212,6 +221,9 @@ impl MethodName {
             (MethodFunction::Lookup, MethodObject::Blob) => "Blob/lookup",
             (MethodFunction::Upload, MethodObject::Blob) => "Blob/upload",
 
+            (MethodFunction::Send, MethodObject::Mdn) => "MDN/send",
+            (MethodFunction::Parse, MethodObject::Mdn) => "MDN/parse",
+
             (MethodFunction::Echo, MethodObject::Core) => "Core/echo",
             _ => "error",
         }
@@ -234,6 +246,8 @@ impl Display for MethodObject {
             MethodObject::Thread => "Thread",
             MethodObject::Email => "Email",
             MethodObject::Quota => "Quota",
+            MethodObject::Mdn => "MDN",
+            MethodObject::MailFilter => "MailFilter",
         })
     }
 }
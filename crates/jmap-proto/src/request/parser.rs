@@ -34,6 +34,7 @@ use crate::{
         get::GetRequest,
         import::ImportEmailRequest,
         lookup::BlobLookupRequest,
+        mdn::{MdnParseRequest, MdnSendRequest},
         parse::ParseEmailRequest,
         query::QueryRequest,
         query_changes::QueryChangesRequest,
@@ -140,6 +141,7 @@ impl Request {
                                 | MethodObject::EmailSubmission
                                 | MethodObject::PushSubscription
                                 | MethodObject::VacationResponse
+                                | MethodObject::MailFilter
                                 | MethodObject::SieveScript
                                 | MethodObject::Principal
                                 | MethodObject::Quota
@@ -183,6 +185,12 @@ impl Request {
                                 ValidateSieveScriptRequest::parse(parser)
                                     .map(RequestMethod::ValidateScript)
                             }
+                            (MethodFunction::Send, MethodObject::Mdn) => {
+                                MdnSendRequest::parse(parser).map(RequestMethod::MdnSend)
+                            }
+                            (MethodFunction::Parse, MethodObject::Mdn) => {
+                                MdnParseRequest::parse(parser).map(RequestMethod::MdnParse)
+                            }
                             (MethodFunction::Echo, MethodObject::Core) => {
                                 Echo::parse(parser).map(RequestMethod::Echo)
                             }
@@ -249,7 +257,13 @@ impl From<Error> for RequestError {
 
 #[cfg(test)]
 mod tests {
-    use crate::request::Request;
+    use crate::{
+        request::{capability::Capability, reference::MaybeReference, Request, RequestMethod},
+        types::{
+            property::Property,
+            value::{SetValue, Value},
+        },
+    };
 
     const TEST: &str = r#"
     {
@@ -364,9 +378,251 @@ mod tests {
       }
     "##;
 
+    const TEST3: &str = r##"
+    {
+        "using": [
+          "urn:ietf:params:jmap:core",
+          "urn:ietf:params:jmap:mail",
+          "urn:ietf:params:jmap:mdn"
+        ],
+        "methodCalls": [
+          [
+            "MDN/send",
+            {
+              "accountId": "b",
+              "identityId": "a",
+              "send": {
+                "k1546": {
+                  "forEmailId": "c",
+                  "subject": "Read receipt for: World domination",
+                  "textBody": "This receipt shows that the email has been displayed on your recipient's computer.",
+                  "reportingUA": "joes-pc.cs.example.com; Foomail 97.1",
+                  "includeOriginalMessage": true,
+                  "disposition": {
+                    "actionMode": "manual-action",
+                    "sendingMode": "mdn-sent-manually",
+                    "type": "displayed"
+                  },
+                  "extensionFields": {
+                    "X-EXTENSION-EXAMPLE": "example.com"
+                  }
+                }
+              },
+              "onSuccessUpdateEmail": {
+                "#k1546": {
+                  "keywords/$mdnsent": true
+                }
+              }
+            },
+            "0"
+          ],
+          [
+            "MDN/parse",
+            {
+              "accountId": "b",
+              "blobIds": []
+            },
+            "1"
+          ]
+        ]
+      }
+    "##;
+
+    const TEST4: &str = r##"
+    {
+        "using": [
+          "urn:ietf:params:jmap:core",
+          "urn:ietf:params:jmap:mailfilter"
+        ],
+        "methodCalls": [
+          [
+            "MailFilter/set",
+            {
+              "accountId": "b",
+              "update": {
+                "singleton": {
+                  "isEnabled": true,
+                  "rules": [
+                    {
+                      "name": "Newsletters",
+                      "anyOf": false,
+                      "conditions": [
+                        {
+                          "field": "listId",
+                          "comparator": "contains",
+                          "value": "news.example.org"
+                        },
+                        {
+                          "field": "header",
+                          "header": "X-Priority",
+                          "comparator": "is",
+                          "not": true,
+                          "value": "1"
+                        }
+                      ],
+                      "actions": [
+                        {
+                          "type": "fileInto",
+                          "value": "Newsletters"
+                        },
+                        {
+                          "type": "stop"
+                        }
+                      ]
+                    }
+                  ]
+                }
+              }
+            },
+            "0"
+          ]
+        ]
+      }
+    "##;
+
     #[test]
     fn parse_request() {
         println!("{:?}", Request::parse(TEST.as_bytes(), 10, 10240));
         println!("{:?}", Request::parse(TEST2.as_bytes(), 10, 10240));
     }
+
+    #[test]
+    fn parse_mdn_request() {
+        let mut request = Request::parse(TEST3.as_bytes(), 10, 10240).unwrap();
+        assert_eq!(
+            request.using & Capability::Mdn as u32,
+            Capability::Mdn as u32
+        );
+        assert_eq!(request.method_calls.len(), 2);
+
+        let call = request.method_calls.remove(0);
+        assert_eq!(call.name.to_string(), "MDN/send");
+        if let RequestMethod::MdnSend(send) = call.method {
+            let (id, mdn) = send.send.into_iter().next().unwrap();
+            assert_eq!(id, "k1546");
+            assert!(matches!(
+                mdn.properties.get(&Property::IncludeOriginalMessage),
+                Some(SetValue::Value(Value::Bool(true)))
+            ));
+            assert!(matches!(
+                mdn.properties.get(&Property::ReportingUA),
+                Some(SetValue::Value(Value::Text(_)))
+            ));
+            match mdn.properties.get(&Property::Disposition) {
+                Some(SetValue::Value(Value::Object(disposition))) => {
+                    assert_eq!(
+                        disposition.get(&Property::ActionMode),
+                        &Value::Text("manual-action".to_string())
+                    );
+                    assert_eq!(
+                        disposition.get(&Property::SendingMode),
+                        &Value::Text("mdn-sent-manually".to_string())
+                    );
+                    assert_eq!(
+                        disposition.get(&Property::Type),
+                        &Value::Text("displayed".to_string())
+                    );
+                }
+                other => panic!("Unexpected disposition {other:?}"),
+            }
+            assert!(matches!(
+                mdn.properties.get(&Property::ExtensionFields),
+                Some(SetValue::Value(Value::Object(_)))
+            ));
+            let (email_id, update) = send
+                .on_success_update_email
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap();
+            assert_eq!(email_id, MaybeReference::Reference("k1546".to_string()));
+            assert!(matches!(
+                update.properties.get(&Property::Keywords),
+                Some(SetValue::Patch(_))
+            ));
+        } else {
+            panic!("Expected MDN/send request, got {:?}", call.method);
+        }
+
+        let call = request.method_calls.remove(0);
+        assert_eq!(call.name.to_string(), "MDN/parse");
+        assert!(matches!(call.method, RequestMethod::MdnParse(parse) if parse.blob_ids.is_empty()));
+    }
+
+    #[test]
+    fn parse_mail_filter_request() {
+        let mut request = Request::parse(TEST4.as_bytes(), 10, 10240).unwrap();
+        assert_eq!(
+            request.using & Capability::MailFilter as u32,
+            Capability::MailFilter as u32
+        );
+
+        let call = request.method_calls.remove(0);
+        assert_eq!(call.name.to_string(), "MailFilter/set");
+        if let RequestMethod::Set(set) = call.method {
+            let (id, update) = set.update.unwrap().into_iter().next().unwrap();
+            assert!(id.is_singleton());
+            assert!(matches!(
+                update.properties.get(&Property::IsEnabled),
+                Some(SetValue::Value(Value::Bool(true)))
+            ));
+            match update.properties.get(&Property::Rules) {
+                Some(SetValue::Value(Value::List(rules))) => {
+                    let rule = match &rules[0] {
+                        Value::Object(rule) => rule,
+                        other => panic!("Unexpected rule {other:?}"),
+                    };
+                    assert_eq!(
+                        rule.get(&Property::Name),
+                        &Value::Text("Newsletters".to_string())
+                    );
+                    assert_eq!(
+                        rule.get(&Property::_T("anyOf".to_string())),
+                        &Value::Bool(false)
+                    );
+                    match rule.get(&Property::_T("conditions".to_string())) {
+                        Value::List(conditions) => {
+                            assert_eq!(conditions.len(), 2);
+                            match &conditions[1] {
+                                Value::Object(condition) => {
+                                    assert_eq!(
+                                        condition.get(&Property::_T("header".to_string())),
+                                        &Value::Text("X-Priority".to_string())
+                                    );
+                                    assert_eq!(
+                                        condition.get(&Property::_T("not".to_string())),
+                                        &Value::Bool(true)
+                                    );
+                                    assert_eq!(
+                                        condition.get(&Property::Value),
+                                        &Value::Text("1".to_string())
+                                    );
+                                }
+                                other => panic!("Unexpected condition {other:?}"),
+                            }
+                        }
+                        other => panic!("Unexpected conditions {other:?}"),
+                    }
+                    match rule.get(&Property::_T("actions".to_string())) {
+                        Value::List(actions) => {
+                            assert_eq!(actions.len(), 2);
+                            match &actions[1] {
+                                Value::Object(action) => {
+                                    assert_eq!(
+                                        action.get(&Property::Type),
+                                        &Value::Text("stop".to_string())
+                                    );
+                                }
+                                other => panic!("Unexpected action {other:?}"),
+                            }
+                        }
+                        other => panic!("Unexpected actions {other:?}"),
+                    }
+                }
+                other => panic!("Unexpected rules {other:?}"),
+            }
+        } else {
+            panic!("Expected MailFilter/set request, got {:?}", call.method);
+        }
+    }
 }
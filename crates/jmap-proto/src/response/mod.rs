@@ -34,6 +34,7 @@ use crate::{
         get::GetResponse,
         import::ImportEmailResponse,
         lookup::BlobLookupResponse,
+        mdn::{MdnParseResponse, MdnSendResponse},
         parse::ParseEmailResponse,
         query::QueryResponse,
         query_changes::QueryChangesResponse,
@@ -64,6 +65,8 @@ pub enum ResponseMethod {
     ValidateScript(ValidateSieveScriptResponse),
     LookupBlob(BlobLookupResponse),
     UploadBlob(BlobUploadResponse),
+    MdnSend(MdnSendResponse),
+    MdnParse(MdnParseResponse),
     Echo(Echo),
     Error(MethodError),
 }
@@ -207,6 +210,18 @@ impl From<BlobLookupResponse> for ResponseMethod {
     }
 }
 
+impl From<MdnSendResponse> for ResponseMethod {
+    fn from(mdn_send: MdnSendResponse) -> Self {
+        ResponseMethod::MdnSend(mdn_send)
+    }
+}
+
+impl From<MdnParseResponse> for ResponseMethod {
+    fn from(mdn_parse: MdnParseResponse) -> Self {
+        ResponseMethod::MdnParse(mdn_parse)
+    }
+}
+
 impl<T: Into<ResponseMethod>> From<Result<T, MethodError>> for ResponseMethod {
     fn from(result: Result<T, MethodError>) -> Self {
         match result {
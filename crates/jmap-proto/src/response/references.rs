@@ -27,7 +27,9 @@ use utils::map::vec_map::VecMap;
 
 use crate::{
     error::{method::MethodError, set::SetError},
-    method::{copy::CopyResponse, set::SetResponse, upload::DataSourceObject},
+    method::{
+        copy::CopyResponse, mdn::MdnSendResponse, set::SetResponse, upload::DataSourceObject,
+    },
     object::Object,
     request::{
         reference::{MaybeReference, ResultReference},
@@ -155,6 +157,12 @@ impl Response {
                     );
                 }
             }
+            RequestMethod::MdnSend(request) => {
+                // Resolve MDN references
+                for obj in request.send.values_mut() {
+                    self.eval_object_references(obj, None)?;
+                }
+            }
             RequestMethod::UploadBlob(request) => {
                 let mut graph = HashMap::with_capacity(request.create.len());
                 for (create_id, object) in request.create.iter_mut() {
@@ -453,6 +461,12 @@ impl EvalObjectReferences for CopyResponse {
     }
 }
 
+impl EvalObjectReferences for MdnSendResponse {
+    fn get_id(&self, _id_ref: &str) -> Option<Value> {
+        None
+    }
+}
+
 impl EvalResult {
     pub fn unwrap_ids(self, rr: &ResultReference) -> Result<Vec<Id>, MethodError> {
         if let EvalResult::Values(values) = self {
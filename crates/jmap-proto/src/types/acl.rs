@@ -40,7 +40,9 @@ pub enum Acl {
     CreateChild = 7,
     Administer = 8,
     Submit = 9,
-    None = 10,
+    SendAs = 10,
+    SendOnBehalf = 11,
+    None = 12,
 }
 
 impl JsonObjectParser for Acl {
@@ -71,6 +73,7 @@ impl JsonObjectParser for Acl {
             0x0064_6c69_6843_6574_6165_7263 => Ok(Acl::CreateChild),
             0x7265_7473_696e_696d_6461 => Ok(Acl::Administer),
             0x7469_6d62_7573 => Ok(Acl::Submit),
+            // Sending rights are delegated on principals and cannot be shared on mailboxes
             _ => Err(parser.error_value()),
         }
     }
@@ -89,6 +92,8 @@ impl Acl {
             Acl::CreateChild => "createChild",
             Acl::Administer => "administer",
             Acl::Submit => "submit",
+            Acl::SendAs => "sendAs",
+            Acl::SendOnBehalf => "sendOnBehalf",
             Acl::None => "",
         }
     }
@@ -138,6 +143,8 @@ impl From<u64> for Acl {
             7 => Acl::CreateChild,
             8 => Acl::Administer,
             9 => Acl::Submit,
+            10 => Acl::SendAs,
+            11 => Acl::SendOnBehalf,
             _ => Acl::None,
         }
     }
@@ -166,3 +173,31 @@ impl DeserializeFrom for Acl {
         }
     }
 }*/
+
+#[cfg(test)]
+mod tests {
+    use utils::map::bitmap::Bitmap;
+
+    use crate::parser::{json::Parser, JsonObjectParser};
+
+    use super::Acl;
+
+    #[test]
+    fn acl_parse() {
+        assert_eq!(
+            Bitmap::<Acl>::parse(&mut Parser::new(br#"["read", "readItems", "submit"]"#)).unwrap(),
+            Bitmap::from_iter([Acl::Read, Acl::ReadItems, Acl::Submit])
+        );
+
+        for input in [
+            r#"["read", "sendAs"]"#,
+            r#"["sendOnBehalf"]"#,
+            r#"["readItems", "unknown"]"#,
+        ] {
+            assert!(
+                Bitmap::<Acl>::parse(&mut Parser::new(input.as_bytes())).is_err(),
+                "{input}"
+            );
+        }
+    }
+}
@@ -136,6 +136,28 @@ pub enum Property {
     WarnLimit,
     SoftLimit,
     Scope,
+    SavedAt,
+    ForEmailId,
+    IncludeOriginalMessage,
+    ReportingUA,
+    MdnGateway,
+    OriginalRecipient,
+    FinalRecipient,
+    OriginalMessageId,
+    Error,
+    ExtensionFields,
+    ActionMode,
+    SendingMode,
+    SmimeStatus,
+    SmimeErrors,
+    SmimeSignatures,
+    SmimeStatusAtDelivery,
+    Issuer,
+    NotBefore,
+    NotAfter,
+    IsVerified,
+    Retention,
+    Rules,
     Digest(DigestProperty),
     Data(DataProperty),
     _T(String),
@@ -383,11 +405,15 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0073_6449_6c69_616d => Property::EmailIds,
             0x0065_706f_6c65_766e => Property::Envelope,
             0x7365_7269_7078 => Property::Expires,
+            0x726f_7272 => Property::Error,
+            0x7364_6c65_6946_6e6f_6973_6e65_7478 => Property::ExtensionFields,
             _ => return None,
         },
         b'f' => match hash {
             0x006d_6f72 => Property::From,
             0x0065_7461_446d_6f72 => Property::FromDate,
+            0x0064_496c_6961_6d45_726f => Property::ForEmailId,
+            0x0074_6e65_6970_6963_6552_6c61_6e69 => Property::FinalRecipient,
             _ => return None,
         },
         b'h' => match hash {
@@ -423,12 +449,18 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x7372_6562_6d65 => Property::Members,
             0x6449_6567_6173_7365 => Property::MessageId,
             0x0073_7468_6769_5279 => Property::MyRights,
+            0x0079_6177_6574_6147_6e64 => Property::MdnGateway,
             _ => return None,
         },
         b'n' => match hash {
             0x0065_6d61 => Property::Name,
             _ => return None,
         },
+        b'o' => match hash {
+            0x746e_6569_7069_6365_526c_616e_6967_6972 => Property::OriginalRecipient,
+            0x6449_6567_6173_7365_4d6c_616e_6967_6972 => Property::OriginalMessageId,
+            _ => return None,
+        },
         b'p' => match hash {
             0x0064_4974_6e65_7261 => Property::ParentId,
             0x0064_4974_7261 => Property::PartId,
@@ -445,6 +477,9 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0073_6563_6e65_7265_6665 => Property::References,
             0x6f54_796c_7065 => Property::ReplyTo,
             0x0065_6c6f => Property::Role,
+            0x4155_676e_6974_726f_7065 => Property::ReportingUA,
+            0x6e6f_6974_6e65_7465 => Property::Retention,
+            0x7365_6c75 => Property::Rules,
             _ => return None,
         },
         b's' => match hash {
@@ -456,6 +491,9 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x7265_6472_4f74_726f => Property::SortOrder,
             0x7463_656a_6275 => Property::Subject,
             0x7374_7261_5062_7573 => Property::SubParts,
+            0x7375_7461_7453_656d_696d => Property::SmimeStatus,
+            0x7372_6f72_7245_656d_696d => Property::SmimeErrors,
+            0x7365_7275_7461_6e67_6953_656d_696d => Property::SmimeSignatures,
             _ => return None,
         },
         b't' => match hash {
@@ -630,6 +668,7 @@ impl JsonObjectParser for ObjectProperty {
             b'a' => match hash {
                 0x7365_7373_6572_6464 => Property::Addresses,
                 0x0068_7475 => Property::Auth,
+                0x0065_646f_4d6e_6f69_7463 => Property::ActionMode,
                 _ => parser.invalid_property()?,
             },
             b'b' => match hash {
@@ -700,6 +739,7 @@ impl JsonObjectParser for ObjectProperty {
                 0x796c_7065_5270_746d => Property::SmtpReply,
                 0x7469_6d69_4c74_666f => Property::SoftLimit,
                 0x6570_6f63 => Property::Scope,
+                0x6564_6f4d_676e_6964_6e65 => Property::SendingMode,
                 _ => parser.invalid_property()?,
             },
             b't' => match hash {
@@ -726,16 +766,24 @@ impl JsonObjectParser for ObjectProperty {
 impl<'x> Parser<'x> {
     fn invalid_property(&mut self) -> crate::parser::Result<Property> {
         if self.is_eof || self.skip_string() {
-            Ok(Property::_T(
-                String::from_utf8_lossy(self.bytes[self.pos_marker..self.pos - 1].as_ref())
-                    .into_owned(),
-            ))
+            let property = &self.bytes[self.pos_marker..self.pos - 1];
+            Ok(parse_long_property(property)
+                .unwrap_or_else(|| Property::_T(String::from_utf8_lossy(property).into_owned())))
         } else {
             Err(self.error_unterminated())
         }
     }
 }
 
+// Property names that do not fit in a 128-bit hash
+fn parse_long_property(property: &[u8]) -> Option<Property> {
+    match property {
+        b"includeOriginalMessage" => Some(Property::IncludeOriginalMessage),
+        b"smimeStatusAtDelivery" => Some(Property::SmimeStatusAtDelivery),
+        _ => None,
+    }
+}
+
 impl Property {
     pub fn parse(value: &str) -> Property {
         let mut first_char = 0;
@@ -749,7 +797,8 @@ impl Property {
                         hash |= (ch as u128) << shift;
                         shift += 8;
                     } else {
-                        return Property::_T(value.to_string());
+                        return parse_long_property(value.as_bytes())
+                            .unwrap_or_else(|| Property::_T(value.to_string()));
                     }
                 } else {
                     first_char = ch;
@@ -900,6 +949,28 @@ impl Display for Property {
             Property::Scope => write!(f, "scope"),
             Property::WarnLimit => write!(f, "warnLimit"),
             Property::SoftLimit => write!(f, "softLimit"),
+            Property::SavedAt => write!(f, "savedAt"),
+            Property::ForEmailId => write!(f, "forEmailId"),
+            Property::IncludeOriginalMessage => write!(f, "includeOriginalMessage"),
+            Property::ReportingUA => write!(f, "reportingUA"),
+            Property::MdnGateway => write!(f, "mdnGateway"),
+            Property::OriginalRecipient => write!(f, "originalRecipient"),
+            Property::FinalRecipient => write!(f, "finalRecipient"),
+            Property::OriginalMessageId => write!(f, "originalMessageId"),
+            Property::Error => write!(f, "error"),
+            Property::ExtensionFields => write!(f, "extensionFields"),
+            Property::ActionMode => write!(f, "actionMode"),
+            Property::SendingMode => write!(f, "sendingMode"),
+            Property::SmimeStatus => write!(f, "smimeStatus"),
+            Property::SmimeErrors => write!(f, "smimeErrors"),
+            Property::SmimeSignatures => write!(f, "smimeSignatures"),
+            Property::SmimeStatusAtDelivery => write!(f, "smimeStatusAtDelivery"),
+            Property::Issuer => write!(f, "issuer"),
+            Property::NotBefore => write!(f, "notBefore"),
+            Property::NotAfter => write!(f, "notAfter"),
+            Property::IsVerified => write!(f, "isVerified"),
+            Property::Retention => write!(f, "retention"),
+            Property::Rules => write!(f, "rules"),
             Property::_T(s) => write!(f, "{s}"),
         }
     }
@@ -1080,6 +1151,28 @@ impl From<&Property> for u8 {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::SavedAt => 104,
+            Property::ForEmailId => 105,
+            Property::IncludeOriginalMessage => 106,
+            Property::ReportingUA => 107,
+            Property::MdnGateway => 108,
+            Property::OriginalRecipient => 109,
+            Property::FinalRecipient => 110,
+            Property::OriginalMessageId => 111,
+            Property::Error => 112,
+            Property::ExtensionFields => 113,
+            Property::ActionMode => 114,
+            Property::SendingMode => 115,
+            Property::SmimeStatus => 116,
+            Property::SmimeErrors => 117,
+            Property::SmimeSignatures => 118,
+            Property::SmimeStatusAtDelivery => 119,
+            Property::Issuer => 120,
+            Property::NotBefore => 121,
+            Property::NotAfter => 122,
+            Property::IsVerified => 123,
+            Property::Retention => 124,
+            Property::Rules => 125,
             Property::Digest(_) | Property::Data(_) => unreachable!("invalid property"),
         }
     }
@@ -1222,6 +1315,28 @@ impl SerializeInto for Property {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::SavedAt => 104,
+            Property::ForEmailId => 105,
+            Property::IncludeOriginalMessage => 106,
+            Property::ReportingUA => 107,
+            Property::MdnGateway => 108,
+            Property::OriginalRecipient => 109,
+            Property::FinalRecipient => 110,
+            Property::OriginalMessageId => 111,
+            Property::Error => 112,
+            Property::ExtensionFields => 113,
+            Property::ActionMode => 114,
+            Property::SendingMode => 115,
+            Property::SmimeStatus => 116,
+            Property::SmimeErrors => 117,
+            Property::SmimeSignatures => 118,
+            Property::SmimeStatusAtDelivery => 119,
+            Property::Issuer => 120,
+            Property::NotBefore => 121,
+            Property::NotAfter => 122,
+            Property::IsVerified => 123,
+            Property::Retention => 124,
+            Property::Rules => 125,
             Property::Digest(_) | Property::Data(_) => {
                 unreachable!("Property::Digest and Property::Data are not serializable")
             }
@@ -1340,6 +1455,28 @@ impl DeserializeFrom for Property {
             101 => Some(Property::WarnLimit),
             102 => Some(Property::SoftLimit),
             103 => Some(Property::Scope),
+            104 => Some(Property::SavedAt),
+            105 => Some(Property::ForEmailId),
+            106 => Some(Property::IncludeOriginalMessage),
+            107 => Some(Property::ReportingUA),
+            108 => Some(Property::MdnGateway),
+            109 => Some(Property::OriginalRecipient),
+            110 => Some(Property::FinalRecipient),
+            111 => Some(Property::OriginalMessageId),
+            112 => Some(Property::Error),
+            113 => Some(Property::ExtensionFields),
+            114 => Some(Property::ActionMode),
+            115 => Some(Property::SendingMode),
+            116 => Some(Property::SmimeStatus),
+            117 => Some(Property::SmimeErrors),
+            118 => Some(Property::SmimeSignatures),
+            119 => Some(Property::SmimeStatusAtDelivery),
+            120 => Some(Property::Issuer),
+            121 => Some(Property::NotBefore),
+            122 => Some(Property::NotAfter),
+            123 => Some(Property::IsVerified),
+            124 => Some(Property::Retention),
+            125 => Some(Property::Rules),
             _ => None,
         }
     }
@@ -82,6 +82,9 @@ impl JMAP {
             self.store.write(batch.build()).await?;
         }
 
+        // Revoke sending rights
+        self.delegation_revoke_all(account_id).await?;
+
         // Delete account
         self.store.purge_account(account_id).await?;
 
@@ -24,7 +24,13 @@
 use std::{str::FromStr, time::Duration};
 
 use nlp::language::Language;
-use store::rand::{distributions::Alphanumeric, thread_rng, Rng};
+use store::{
+    ahash::AHashMap,
+    fts::extract::ExtractLimits,
+    rand::{distributions::Alphanumeric, thread_rng, Rng},
+};
+
+use crate::email::crypto::{try_parse_certs, EncryptionMethod};
 
 use super::session::BaseCapabilities;
 
@@ -35,6 +41,34 @@ impl crate::Config {
                 settings.value("jmap.fts.default-language").unwrap_or("en"),
             )
             .unwrap_or(Language::English),
+            fts_extract: ExtractLimits {
+                max_size: settings
+                    .property("jmap.fts.extract.max-size")?
+                    .unwrap_or(10000000),
+                max_text_length: settings
+                    .property("jmap.fts.extract.max-text-length")?
+                    .unwrap_or(1000000),
+                max_archive_entries: settings
+                    .property("jmap.fts.extract.max-archive-entries")?
+                    .unwrap_or(100),
+                timeout: settings.property_or_static("jmap.fts.extract.timeout", "2s")?,
+            },
+            fts_index_background: settings
+                .property("jmap.fts.index.background")?
+                .unwrap_or(true),
+            fts_index_workers: settings
+                .property("jmap.fts.index.workers")?
+                .unwrap_or_else(|| {
+                    std::thread::available_parallelism()
+                        .map(usize::from)
+                        .unwrap_or(2)
+                })
+                .max(1),
+            fts_index_max_pending: settings
+                .property("jmap.fts.index.max-pending")?
+                .unwrap_or(10000),
+            fts_index_poll_interval: settings
+                .property_or_static("jmap.fts.index.poll-interval", "30s")?,
             query_max_results: settings
                 .property("jmap.protocol.query.max-results")?
                 .unwrap_or(5000),
@@ -75,6 +109,19 @@ impl crate::Config {
             mailbox_name_max_len: settings
                 .property("jmap.mailbox.max-name-length")?
                 .unwrap_or(255),
+            mailbox_retention: settings
+                .properties::<Duration>("jmap.mailbox.retention")
+                .map(|result| {
+                    result.map(|(key, retention)| {
+                        (
+                            key.rsplit_once('.')
+                                .map_or(key, |(_, role)| role)
+                                .to_string(),
+                            retention.as_secs(),
+                        )
+                    })
+                })
+                .collect::<Result<AHashMap<_, _>, String>>()?,
             mail_attachments_max_size: settings
                 .property("jmap.email.max-attachment-size")?
                 .unwrap_or(50000000),
@@ -138,6 +185,24 @@ impl crate::Config {
                 .unwrap_or(true),
             encrypt: settings.property_or_static("jmap.encryption.enable", "true")?,
             encrypt_append: settings.property_or_static("jmap.encryption.append", "false")?,
+            smime_trust_store: settings
+                .values("jmap.smime.trust-store")
+                .map(|(key, _)| {
+                    settings
+                        .file_contents(key)
+                        .and_then(|bytes| match try_parse_certs(bytes) {
+                            Ok((EncryptionMethod::SMIME, certs)) => Ok(certs),
+                            Ok(_) => Err("Expected X.509 certificates".to_string()),
+                            Err(err) => Err(err),
+                        })
+                        .map_err(|err| {
+                            format!("Failed to load trust store from property {key:?}: {err}")
+                        })
+                })
+                .collect::<Result<Vec<_>, String>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
             http_headers: settings
                 .values("jmap.http.headers")
                 .map(|(_, v)| {
@@ -164,6 +229,12 @@ impl crate::Config {
                     }
                 })
                 .collect::<Result<Vec<_>, String>>()?,
+            metrics_enable: settings.property_or_static("metrics.prometheus.enable", "false")?,
+            metrics_auth: settings
+                .value("metrics.prometheus.auth.username")
+                .zip(settings.value("metrics.prometheus.auth.secret"))
+                .map(|(username, secret)| (username.to_string(), secret.to_string())),
+            metrics_public: settings.property_or_static("metrics.prometheus.public", "false")?,
         };
         config.add_capabilites(settings);
         Ok(config)
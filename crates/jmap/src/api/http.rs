@@ -21,7 +21,11 @@
  * for more details.
 */
 
-use std::{net::IpAddr, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    net::IpAddr,
+    sync::{atomic::Ordering, Arc},
+};
 
 use http_body_util::{BodyExt, Full};
 use hyper::{
@@ -36,14 +40,22 @@ use jmap_proto::{
     error::request::{RequestError, RequestLimitError},
     request::Request,
     response::Response,
-    types::{blob::BlobId, id::Id},
+    types::{acl::Acl, blob::BlobId, id::Id},
 };
+use mail_parser::decoders::base64::base64_decode;
 use serde_json::Value;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
+    sync::oneshot,
+};
+use utils::{
+    config::Config,
+    ipc::ReloadEvent,
+    listener::{ServerInstance, SessionData, SessionManager},
+    map::bitmap::Bitmap,
+    metrics,
 };
-use utils::listener::{ServerInstance, SessionData, SessionManager};
 
 use crate::{
     auth::{oauth::OAuthMetadata, AccessToken},
@@ -277,11 +289,11 @@ pub async fn parse_jmap_request(
 
         "admin" => {
             // Make sure the user is a superuser
-            match jmap.authenticate_headers(&req, remote_ip).await {
-                Ok(Some((_, access_token))) if access_token.is_super_user() => (),
+            let access_token = match jmap.authenticate_headers(&req, remote_ip).await {
+                Ok(Some((_, access_token))) if access_token.is_super_user() => access_token,
                 Ok(_) => return RequestError::unauthorized().into_http_response(),
                 Err(err) => return err.into_http_response(),
-            }
+            };
 
             match (
                 path.next().unwrap_or(""),
@@ -291,6 +303,14 @@ pub async fn parse_jmap_request(
                 ("account", "delete", &Method::GET) => {
                     return if let Some(account_name) = path.next() {
                         if let Ok(Some(account_id)) = jmap.try_get_account_id(account_name).await {
+                            if !matches!(jmap.legal_hold_since(account_id).await, Ok(None)) {
+                                return RequestError::blank(
+                                    StatusCode::CONFLICT.as_u16(),
+                                    "Account on legal hold",
+                                    "Accounts on legal hold cannot be deleted.",
+                                )
+                                .into_http_response();
+                            }
                             match jmap.delete_account(account_name, account_id).await {
                                 Ok(_) => JsonResponse::new(Value::String("success".into()))
                                     .into_http_response(),
@@ -377,7 +397,323 @@ pub async fn parse_jmap_request(
                         .into_http_response(),
                     };
                 }
-                (path_1 @ ("queue" | "report"), path_2, &Method::GET) => {
+                ("fts", "reindex", &Method::GET) => {
+                    let account_id = if let Some(account_name) = path.next() {
+                        match jmap.try_get_account_id(account_name).await {
+                            Ok(Some(account_id)) => Some(account_id),
+                            Ok(None) => {
+                                return RequestError::blank(
+                                    StatusCode::NOT_FOUND.as_u16(),
+                                    "Not found",
+                                    "Account not found.",
+                                )
+                                .into_http_response();
+                            }
+                            Err(_) => {
+                                return RequestError::internal_server_error().into_http_response()
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
+                    return match jmap.fts_reindex(account_id).await {
+                        Ok(num_queued) => {
+                            JsonResponse::new(Value::Number(num_queued.into())).into_http_response()
+                        }
+                        Err(err) => RequestError::blank(
+                            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                            "Reindex failed",
+                            err.to_string(),
+                        )
+                        .into_http_response(),
+                    };
+                }
+                ("retention", "purge", &Method::GET) => {
+                    let account_id = if let Some(account_name) = path.next() {
+                        match jmap.try_get_account_id(account_name).await {
+                            Ok(Some(account_id)) => Some(account_id),
+                            Ok(None) => {
+                                return RequestError::blank(
+                                    StatusCode::NOT_FOUND.as_u16(),
+                                    "Not found",
+                                    "Account not found.",
+                                )
+                                .into_http_response();
+                            }
+                            Err(_) => {
+                                return RequestError::internal_server_error().into_http_response()
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
+                    let num_expunged = jmap.purge_expired_messages(account_id).await;
+                    return JsonResponse::new(Value::Number(num_expunged.into()))
+                        .into_http_response();
+                }
+                ("hold", action @ ("enable" | "release" | "status"), &Method::GET) => {
+                    let account_id = match path.next() {
+                        Some(account_name) => match jmap.try_get_account_id(account_name).await {
+                            Ok(Some(account_id)) => account_id,
+                            Ok(None) => {
+                                return RequestError::blank(
+                                    StatusCode::NOT_FOUND.as_u16(),
+                                    "Not found",
+                                    "Account not found.",
+                                )
+                                .into_http_response();
+                            }
+                            Err(_) => {
+                                return RequestError::internal_server_error().into_http_response()
+                            }
+                        },
+                        None => {
+                            return RequestError::blank(
+                                StatusCode::BAD_REQUEST.as_u16(),
+                                "Invalid parameters",
+                                "Expected account name",
+                            )
+                            .into_http_response();
+                        }
+                    };
+
+                    let result = match action {
+                        "enable" => jmap
+                            .legal_hold_enable(account_id)
+                            .await
+                            .map(|_| Value::String("success".into())),
+                        "release" => jmap
+                            .legal_hold_release(account_id)
+                            .await
+                            .map(|num_removed| Value::Number(num_removed.into())),
+                        _ => match jmap.legal_hold_since(account_id).await {
+                            Ok(since) => jmap.legal_hold_list(account_id).await.map(|messages| {
+                                serde_json::json!({
+                                    "since": since,
+                                    "messages": messages,
+                                })
+                            }),
+                            Err(_) => {
+                                return RequestError::internal_server_error().into_http_response()
+                            }
+                        },
+                    };
+                    return match result {
+                        Ok(response) => JsonResponse::new(response).into_http_response(),
+                        Err(err) => RequestError::blank(
+                            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                            "Legal hold request failed",
+                            err.to_string(),
+                        )
+                        .into_http_response(),
+                    };
+                }
+                ("delegate", action @ ("grant" | "revoke" | "list"), &Method::GET) => {
+                    let mut account_ids = Vec::with_capacity(2);
+                    for account_name in path.by_ref().take(if action == "list" { 1 } else { 2 }) {
+                        match jmap.directory.principal(account_name).await {
+                            Ok(Some(_)) => match jmap.get_account_id(account_name).await {
+                                Ok(account_id) => account_ids.push(account_id),
+                                Err(_) => {
+                                    return RequestError::internal_server_error()
+                                        .into_http_response()
+                                }
+                            },
+                            Ok(None) => {
+                                return RequestError::blank(
+                                    StatusCode::NOT_FOUND.as_u16(),
+                                    "Not found",
+                                    format!("Account {account_name} not found."),
+                                )
+                                .into_http_response();
+                            }
+                            Err(_) => {
+                                return RequestError::internal_server_error().into_http_response()
+                            }
+                        }
+                    }
+
+                    let result = match (action, account_ids.as_slice(), path.next()) {
+                        ("grant", &[owner_id, delegate_id], Some(right)) => {
+                            let right = match right {
+                                "send-as" => Acl::SendAs,
+                                "send-on-behalf" => Acl::SendOnBehalf,
+                                _ => {
+                                    return RequestError::blank(
+                                        StatusCode::BAD_REQUEST.as_u16(),
+                                        "Invalid parameters",
+                                        "Expected send-as or send-on-behalf",
+                                    )
+                                    .into_http_response();
+                                }
+                            };
+                            jmap.delegation_set(
+                                owner_id,
+                                delegate_id,
+                                Bitmap::new().with_item(right),
+                            )
+                            .await
+                            .map(|_| Value::String("success".into()))
+                        }
+                        ("revoke", &[owner_id, delegate_id], None) => jmap
+                            .delegation_set(owner_id, delegate_id, Bitmap::new())
+                            .await
+                            .map(|_| Value::String("success".into())),
+                        ("list", &[owner_id], None) => match jmap.delegation_list(owner_id).await {
+                            Ok(delegates) => {
+                                let mut response = serde_json::Map::new();
+                                for (delegate_id, rights) in delegates {
+                                    if let Ok(Some(delegate_name)) =
+                                        jmap.get_account_name(delegate_id).await
+                                    {
+                                        response.insert(
+                                            delegate_name,
+                                            rights.map(|right| right.to_string()).collect(),
+                                        );
+                                    }
+                                }
+                                Ok(Value::Object(response))
+                            }
+                            Err(err) => Err(err),
+                        },
+                        _ => {
+                            return RequestError::blank(
+                                StatusCode::BAD_REQUEST.as_u16(),
+                                "Invalid parameters",
+                                "Expected owner and delegate account names",
+                            )
+                            .into_http_response();
+                        }
+                    };
+                    return match result {
+                        Ok(response) => JsonResponse::new(response).into_http_response(),
+                        Err(err) => RequestError::blank(
+                            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                            "Delegation request failed",
+                            err.to_string(),
+                        )
+                        .into_http_response(),
+                    };
+                }
+                ("fts", "status", &Method::GET) => {
+                    let stats = &jmap.fts_index_stats;
+                    return JsonResponse::new(Value::Object(
+                        [
+                            ("pending", stats.pending.load(Ordering::Relaxed) as u64),
+                            ("indexed", stats.indexed.load(Ordering::Relaxed)),
+                            ("failed", stats.failed.load(Ordering::Relaxed)),
+                        ]
+                        .into_iter()
+                        .map(|(key, value)| (key.to_string(), Value::Number(value.into())))
+                        .collect(),
+                    ))
+                    .into_http_response();
+                }
+                ("config", "reload", &Method::GET) => {
+                    return reload_config(&jmap, Vec::new(), "Configuration reload failed").await;
+                }
+                ("settings", "list", &Method::GET) => {
+                    return match jmap.store.settings_list(path.next().unwrap_or("")).await {
+                        Ok(settings) => JsonResponse::new(Value::Object(
+                            settings
+                                .into_iter()
+                                .map(|(key, value)| (key, Value::String(value)))
+                                .collect(),
+                        ))
+                        .into_http_response(),
+                        Err(err) => RequestError::blank(
+                            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                            "Failed to list settings",
+                            err.to_string(),
+                        )
+                        .into_http_response(),
+                    };
+                }
+                ("settings", "get", &Method::GET) => {
+                    return match path.next() {
+                        Some(key) => match jmap.store.settings_get(key).await {
+                            Ok(Some(value)) => {
+                                JsonResponse::new(Value::String(value)).into_http_response()
+                            }
+                            Ok(None) => RequestError::blank(
+                                StatusCode::NOT_FOUND.as_u16(),
+                                "Not found",
+                                "Setting not found.",
+                            )
+                            .into_http_response(),
+                            Err(err) => RequestError::blank(
+                                StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                                "Failed to retrieve setting",
+                                err.to_string(),
+                            )
+                            .into_http_response(),
+                        },
+                        None => RequestError::blank(
+                            StatusCode::BAD_REQUEST.as_u16(),
+                            "Invalid parameters",
+                            "Expected setting key",
+                        )
+                        .into_http_response(),
+                    };
+                }
+                ("settings", "set", &Method::POST) => {
+                    let settings =
+                        match fetch_body(&mut req, jmap.config.request_max_size, &access_token)
+                            .await
+                            .and_then(|bytes| {
+                                serde_json::from_slice::<BTreeMap<String, String>>(&bytes).ok()
+                            }) {
+                            Some(settings) if !settings.is_empty() => settings,
+                            _ => {
+                                return RequestError::blank(
+                                    StatusCode::BAD_REQUEST.as_u16(),
+                                    "Invalid parameters",
+                                    "Expected a JSON object of setting keys and values",
+                                )
+                                .into_http_response();
+                            }
+                        };
+                    if let Some(key) = settings.keys().find(|key| Config::is_local_key(key)) {
+                        return RequestError::blank(
+                            StatusCode::BAD_REQUEST.as_u16(),
+                            "Invalid parameters",
+                            format!(
+                                "Setting {key:?} can only be changed in the configuration file."
+                            ),
+                        )
+                        .into_http_response();
+                    }
+
+                    return reload_config(
+                        &jmap,
+                        settings
+                            .into_iter()
+                            .map(|(key, value)| (key, Some(value)))
+                            .collect(),
+                        "Invalid settings",
+                    )
+                    .await;
+                }
+                ("settings", "delete", &Method::GET) => {
+                    let keys = path.map(|key| (key.to_string(), None)).collect::<Vec<_>>();
+                    return if !keys.is_empty() {
+                        reload_config(&jmap, keys, "Invalid settings").await
+                    } else {
+                        RequestError::blank(
+                            StatusCode::BAD_REQUEST.as_u16(),
+                            "Invalid parameters",
+                            "Expected setting key",
+                        )
+                        .into_http_response()
+                    };
+                }
+                (
+                    path_1 @ ("queue" | "report" | "quarantine" | "incoming"),
+                    path_2,
+                    &Method::GET,
+                ) => {
                     return jmap
                         .smtp
                         .handle_manage_request(req.uri(), req.method(), path_1, path_2)
@@ -386,14 +722,52 @@ pub async fn parse_jmap_request(
                 _ => (),
             }
         }
+        "metrics" if jmap.config.metrics_enable && req.method() == Method::GET => {
+            return if is_metrics_authorized(&jmap, &req, remote_ip) {
+                hyper::Response::builder()
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, metrics::CONTENT_TYPE)
+                    .body(
+                        Full::new(Bytes::from(metrics::encode()))
+                            .map_err(|never| match never {})
+                            .boxed(),
+                    )
+                    .unwrap()
+            } else {
+                RequestError::unauthorized().into_http_response()
+            };
+        }
         _ => (),
     }
     RequestError::not_found().into_http_response()
 }
 
+// Scrapers authenticate with the credentials configured for the metrics endpoint,
+// which are independent from the directory.
+fn is_metrics_authorized(jmap: &JMAP, req: &HttpRequest, remote_ip: IpAddr) -> bool {
+    let basic_auth = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.split_once(' '))
+        .filter(|(mechanism, _)| mechanism.eq_ignore_ascii_case("basic"))
+        .and_then(|(_, token)| base64_decode(token.trim().as_bytes()))
+        .and_then(|token| String::from_utf8(token).ok());
+
+    metrics::is_scrape_authorized(
+        jmap.config
+            .metrics_auth
+            .as_ref()
+            .map(|(username, secret)| (username.as_str(), secret.as_str())),
+        jmap.config.metrics_public,
+        remote_ip,
+        basic_auth.as_deref(),
+    )
+}
+
 impl SessionManager for JmapSessionManager {
     fn spawn(&self, session: SessionData<TcpStream>) {
-        let jmap = self.inner.clone();
+        let jmap = self.inner.load();
 
         tokio::spawn(async move {
             if let Some(tls_acceptor) = &session.instance.tls_acceptor {
@@ -431,7 +805,7 @@ impl SessionManager for JmapSessionManager {
     }
 
     fn shutdown(&self) {
-        let jmap = self.inner.clone();
+        let jmap = self.inner.load();
         tokio::spawn(async move {
             let _ = jmap.state_tx.send(state::Event::Stop).await;
         });
@@ -490,6 +864,34 @@ async fn handle_request<T: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
     }
 }
 
+// Validates the configuration, including any changes to the database settings,
+// and reloads it on success.
+async fn reload_config(
+    jmap: &JMAP,
+    settings: Vec<(String, Option<String>)>,
+    error_title: &'static str,
+) -> HttpResponse {
+    let (result_tx, result_rx) = oneshot::channel();
+    if jmap
+        .reload_tx
+        .send(ReloadEvent {
+            settings,
+            result_tx: result_tx.into(),
+        })
+        .await
+        .is_err()
+    {
+        return RequestError::internal_server_error().into_http_response();
+    }
+
+    match result_rx.await {
+        Ok(Ok(())) => JsonResponse::new(Value::String("success".into())).into_http_response(),
+        Ok(Err(err)) => RequestError::blank(StatusCode::BAD_REQUEST.as_u16(), error_title, err)
+            .into_http_response(),
+        Err(_) => RequestError::internal_server_error().into_http_response(),
+    }
+}
+
 pub async fn fetch_body(
     req: &mut HttpRequest,
     max_size: usize,
@@ -21,12 +21,10 @@
  * for more details.
 */
 
-use std::sync::Arc;
-
 use hyper::StatusCode;
 use jmap_proto::types::{id::Id, state::State, type_state::DataType};
 use serde::Serialize;
-use utils::map::vec_map::VecMap;
+use utils::{map::vec_map::VecMap, reload::Reloadable};
 
 use crate::JMAP;
 
@@ -39,12 +37,14 @@ pub mod session;
 
 #[derive(Clone)]
 pub struct JmapSessionManager {
-    pub inner: Arc<JMAP>,
+    pub inner: Reloadable<JMAP>,
 }
 
 impl JmapSessionManager {
-    pub fn new(inner: Arc<JMAP>) -> Self {
-        Self { inner }
+    pub fn new(inner: impl Into<Reloadable<JMAP>>) -> Self {
+        Self {
+            inner: inner.into(),
+        }
     }
 }
 
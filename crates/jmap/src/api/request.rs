@@ -33,7 +33,7 @@ use jmap_proto::{
     response::{Response, ResponseMethod},
     types::collection::Collection,
 };
-use utils::listener::ServerInstance;
+use utils::{listener::ServerInstance, metrics};
 
 use crate::{auth::AccessToken, JMAP};
 
@@ -62,10 +62,15 @@ impl JMAP {
                 let mut next_call = None;
 
                 // Add response
-                match self
+                let timer = metrics::JMAP_METHOD_DURATION
+                    .with_label_values(&[call.name.as_str()])
+                    .start_timer();
+                let result = self
                     .handle_method_call(call.method, &access_token, &mut next_call, instance)
-                    .await
-                {
+                    .await;
+                timer.observe_duration();
+
+                match result {
                     Ok(mut method_response) => {
                         match &mut method_response {
                             ResponseMethod::Set(set_response) => {
@@ -172,6 +177,11 @@ impl JMAP {
 
                     self.vacation_response_get(req).await?.into()
                 }
+                get::RequestArguments::MailFilter => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.mail_filter_get(req).await?.into()
+                }
                 get::RequestArguments::Principal => {
                     if self.config.principal_allow_lookups || access_token.is_super_user() {
                         self.principal_get(req).await?.into()
@@ -274,6 +284,11 @@ impl JMAP {
 
                     self.vacation_response_set(req).await?.into()
                 }
+                set::RequestArguments::MailFilter => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.mail_filter_set(req, access_token).await?.into()
+                }
             },
             RequestMethod::Changes(req) => self.changes(req, access_token).await?.into(),
             RequestMethod::Copy(req) => {
@@ -293,6 +308,16 @@ impl JMAP {
 
                 self.email_parse(req, access_token).await?.into()
             }
+            RequestMethod::MdnSend(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.mdn_send(req, instance, next_call).await?.into()
+            }
+            RequestMethod::MdnParse(req) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.mdn_parse(req, access_token).await?.into()
+            }
             RequestMethod::QueryChanges(req) => self.query_changes(req, access_token).await?.into(),
             RequestMethod::SearchSnippet(req) => {
                 access_token.assert_has_access(req.account_id, Collection::Email)?;
@@ -203,8 +203,7 @@ impl JMAP {
 
         // Add secondary accounts
         for id in access_token.secondary_ids() {
-            let is_personal = !access_token.is_member(*id);
-            let is_readonly = is_personal
+            let is_readonly = !access_token.is_member(*id)
                 && self
                     .shared_documents(&access_token, *id, Collection::Mailbox, Acl::AddItems)
                     .await
@@ -216,7 +215,7 @@ impl JMAP {
                     .await
                     .unwrap_or_default()
                     .unwrap_or_else(|| Id::from(*id).to_string()),
-                is_personal,
+                false,
                 is_readonly,
                 Some(&[Capability::Mail, Capability::Quota, Capability::Blob]),
                 &self.config.capabilities.account,
@@ -265,6 +264,26 @@ impl crate::Config {
             }),
         );
 
+        // Add MDN capabilities
+        self.capabilities.session.append(
+            Capability::Mdn,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::Mdn,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+
+        // Add S/MIME verification capabilities
+        self.capabilities.session.append(
+            Capability::SmimeVerify,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::SmimeVerify,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+
         // Add vacation response capabilities
         self.capabilities.session.append(
             Capability::VacationResponse,
@@ -275,6 +294,16 @@ impl crate::Config {
             Capabilities::Empty(EmptyCapabilities::default()),
         );
 
+        // Add mail filter capabilities
+        self.capabilities.session.append(
+            Capability::MailFilter,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::MailFilter,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+
         // Add Sieve capabilities
         self.capabilities.session.append(
             Capability::Sieve,
@@ -38,7 +38,7 @@ use store::{
     write::{key::KeySerializer, BatchBuilder, Operation, ValueClass},
     CustomValueKey, Serialize,
 };
-use utils::{listener::limiter::InFlight, map::ttl_dashmap::TtlMap};
+use utils::{listener::limiter::InFlight, map::ttl_dashmap::TtlMap, metrics};
 
 use crate::JMAP;
 
@@ -73,7 +73,9 @@ impl JMAP {
                             })
                         })
                     {
-                        self.authenticate_plain(&account, &secret, &addr).await
+                        let access_token = self.authenticate_plain(&account, &secret, &addr).await;
+                        metrics::authentication("jmap", access_token.is_some());
+                        access_token
                     } else {
                         tracing::debug!(
                             context = "authenticate_headers",
@@ -86,17 +88,20 @@ impl JMAP {
                     // Enforce anonymous rate limit for bearer auth requests
                     self.is_anonymous_allowed(&addr)?;
 
-                    match self.validate_access_token("access_token", &token).await {
-                        Ok((account_id, _, _)) => self.get_access_token(account_id).await,
-                        Err(err) => {
-                            tracing::debug!(
-                                context = "authenticate_headers",
-                                err = err,
-                                "Failed to validate access token."
-                            );
-                            None
-                        }
-                    }
+                    let access_token =
+                        match self.validate_access_token("access_token", &token).await {
+                            Ok((account_id, _, _)) => self.get_access_token(account_id).await,
+                            Err(err) => {
+                                tracing::debug!(
+                                    context = "authenticate_headers",
+                                    err = err,
+                                    "Failed to validate access token."
+                                );
+                                None
+                            }
+                        };
+                    metrics::authentication("jmap", access_token.is_some());
+                    access_token
                 } else {
                     // Enforce anonymous rate limit
                     self.is_anonymous_allowed(&addr)?;
@@ -0,0 +1,248 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    types::{acl::Acl, collection::Collection},
+};
+use store::{
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        BatchBuilder, Operation, ValueClass,
+    },
+    AclKey, CustomValueKey, Deserialize, Serialize,
+};
+use utils::{ipc::SendPermission, map::bitmap::Bitmap};
+
+use crate::JMAP;
+
+// Custom keys start with u32::MAX followed by the key type, types 0 to 8 are
+// already in use. Delegations are indexed by owner so they can be listed
+// without scanning the ACLs of every account.
+const DELEGATION_KEY: u8 = 9;
+
+impl JMAP {
+    pub async fn sender_permission(
+        &self,
+        account_id: u32,
+        account_name: &str,
+        address: &str,
+    ) -> Result<SendPermission, MethodError> {
+        // Addresses owned by the account
+        let emails = self
+            .directory
+            .emails_by_name(account_name)
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                            context = "sender_permission",
+                            account_name = account_name,
+                            error = ?err,
+                            "Failed to obtain account addresses");
+                MethodError::ServerPartialFail
+            })?;
+        if emails
+            .iter()
+            .any(|email| email.eq_ignore_ascii_case(address))
+        {
+            return Ok(SendPermission::Owner);
+        }
+
+        // Addresses of accounts that delegated sending rights
+        let owners = self
+            .directory
+            .names_by_email(address)
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                            context = "sender_permission",
+                            address = address,
+                            error = ?err,
+                            "Failed to obtain address owners");
+                MethodError::ServerPartialFail
+            })?;
+        let mut permission = SendPermission::Denied;
+        for owner_name in owners {
+            let owner_id = if let Some(owner_id) = self.try_get_account_id(&owner_name).await? {
+                owner_id
+            } else {
+                continue;
+            };
+            let rights = self
+                .delegation_get(owner_id, account_id)
+                .await
+                .map_err(|err| {
+                    tracing::error!(event = "error",
+                            context = "sender_permission",
+                            account_id = account_id,
+                            error = ?err,
+                            "Failed to obtain delegated rights");
+                    MethodError::ServerPartialFail
+                })?;
+
+            if rights.contains(Acl::SendAs) {
+                return Ok(SendPermission::SendAs);
+            } else if rights.contains(Acl::SendOnBehalf) {
+                if let Some(sender) = emails.first() {
+                    permission = SendPermission::SendOnBehalf {
+                        sender: sender.to_string(),
+                    };
+                }
+            }
+        }
+
+        Ok(permission)
+    }
+
+    pub async fn verify_sender(&self, address: &str, submitter: &str) -> Option<SendPermission> {
+        let account_id = self.get_account_id(submitter).await.ok()?;
+        self.sender_permission(account_id, submitter, address)
+            .await
+            .ok()
+    }
+
+    pub async fn delegation_get(
+        &self,
+        owner_id: u32,
+        delegate_id: u32,
+    ) -> store::Result<Bitmap<Acl>> {
+        self.store
+            .get_value::<u64>(AclKey {
+                grant_account_id: delegate_id,
+                to_account_id: owner_id,
+                to_collection: Collection::Principal.into(),
+                to_document_id: owner_id,
+            })
+            .await
+            .map(|rights| Bitmap::from(rights.unwrap_or_default()))
+    }
+
+    pub async fn delegation_set(
+        &self,
+        owner_id: u32,
+        delegate_id: u32,
+        mut rights: Bitmap<Acl>,
+    ) -> store::Result<()> {
+        // Only sending rights can be delegated on principals
+        rights.intersection(&Bitmap::from_iter([Acl::SendAs, Acl::SendOnBehalf]));
+
+        let rights = if !rights.is_empty() {
+            u64::from(rights).serialize().into()
+        } else {
+            None
+        };
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(owner_id)
+            .with_collection(Collection::Principal)
+            .update_document(owner_id)
+            .op(Operation::acl(delegate_id, rights.clone()))
+            .op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: delegation_key(owner_id, delegate_id),
+                },
+                set: rights,
+            });
+        self.store.write(batch.build()).await
+    }
+
+    pub async fn delegation_list(&self, owner_id: u32) -> store::Result<Vec<(u32, Bitmap<Acl>)>> {
+        self.store
+            .iterate(
+                Vec::new(),
+                CustomValueKey {
+                    value: delegation_key(owner_id, 0),
+                },
+                CustomValueKey {
+                    value: delegation_key(owner_id, u32::MAX),
+                },
+                false,
+                true,
+                move |delegates, key, value| {
+                    delegates.push((
+                        key.deserialize_be_u32(key.len() - std::mem::size_of::<u32>())?,
+                        Bitmap::from(u64::deserialize(value)?),
+                    ));
+
+                    Ok(true)
+                },
+            )
+            .await
+    }
+
+    pub async fn delegation_revoke_all(&self, account_id: u32) -> store::Result<()> {
+        // Rights delegated by the account
+        for (delegate_id, _) in self.delegation_list(account_id).await? {
+            self.delegation_set(account_id, delegate_id, Bitmap::new())
+                .await?;
+        }
+
+        // Rights delegated to the account
+        let to_collection = u8::from(Collection::Principal);
+        for owner_id in self
+            .store
+            .iterate(
+                Vec::new(),
+                AclKey {
+                    grant_account_id: account_id,
+                    to_account_id: 0,
+                    to_collection: 0,
+                    to_document_id: 0,
+                },
+                AclKey {
+                    grant_account_id: account_id,
+                    to_account_id: u32::MAX,
+                    to_collection: u8::MAX,
+                    to_document_id: u32::MAX,
+                },
+                false,
+                true,
+                move |owner_ids, key, _| {
+                    let acl_key = AclKey::deserialize(key)?;
+                    if acl_key.to_collection == to_collection
+                        && acl_key.to_document_id == acl_key.to_account_id
+                    {
+                        owner_ids.push(acl_key.to_account_id);
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await?
+        {
+            self.delegation_set(owner_id, account_id, Bitmap::new())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn delegation_key(owner_id: u32, delegate_id: u32) -> Vec<u8> {
+    KeySerializer::new(std::mem::size_of::<u32>() * 3 + 1)
+        .write(u32::MAX)
+        .write(DELEGATION_KEY)
+        .write(owner_id)
+        .write(delegate_id)
+        .finalize()
+}
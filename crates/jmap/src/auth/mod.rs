@@ -41,6 +41,7 @@ use utils::map::bitmap::Bitmap;
 
 pub mod acl;
 pub mod authenticate;
+pub mod delegate;
 pub mod oauth;
 pub mod rate_limit;
 
@@ -89,9 +90,12 @@ impl AccessToken {
     }
 
     pub fn secondary_ids(&self) -> impl Iterator<Item = &u32> {
-        self.member_of
-            .iter()
-            .chain(self.access_to.iter().map(|(id, _)| id))
+        self.member_of.iter().chain(
+            self.access_to
+                .iter()
+                .map(|(id, _)| id)
+                .filter(|id| !self.member_of.contains(id)),
+        )
     }
 
     pub fn is_member(&self, account_id: u32) -> bool {
@@ -115,7 +119,7 @@ impl AccessToken {
         self.member_of
             .iter()
             .chain(self.access_to.iter().filter_map(move |(id, cols)| {
-                if cols.contains(collection) {
+                if cols.contains(collection) && !self.member_of.contains(id) {
                     id.into()
                 } else {
                     None
@@ -49,9 +49,9 @@ use jmap_proto::{
 };
 use mail_parser::parsers::fields::thread::thread_name;
 use store::{
-    fts::term_index::TokenIndex,
+    fts::{queue::FtsQueueEntry, term_index::TokenIndex},
     query::RawValue,
-    write::{BatchBuilder, F_BITMAP, F_VALUE},
+    write::{now, BatchBuilder, F_BITMAP, F_INDEX, F_VALUE},
     BlobKind,
 };
 use utils::map::vec_map::VecMap;
@@ -294,22 +294,24 @@ impl JMAP {
         received_at: Option<UTCDate>,
     ) -> Result<Result<IngestedEmail, SetError>, MethodError> {
         // Obtain term index and metadata
-        let (mut metadata, token_index) = if let (Some(metadata), Some(token_index)) = (
-            self.get_property::<Object<Value>>(
+        let (mut metadata, token_index) = if let Some(metadata) = self
+            .get_property::<Object<Value>>(
                 from_account_id,
                 Collection::Email,
                 from_message_id,
                 Property::BodyStructure,
             )
-            .await?,
-            self.get_term_index::<RawValue<TokenIndex>>(
-                from_account_id,
-                Collection::Email,
-                from_message_id,
+            .await?
+        {
+            (
+                metadata,
+                self.get_term_index::<RawValue<TokenIndex>>(
+                    from_account_id,
+                    Collection::Email,
+                    from_message_id,
+                )
+                .await?,
             )
-            .await?,
-        ) {
-            (metadata, token_index)
         } else {
             return Ok(Err(SetError::not_found().with_description(format!(
                 "Message not found not found in account {}.",
@@ -433,10 +435,19 @@ impl JMAP {
             .value(Property::MailboxIds, mailboxes, F_VALUE | F_BITMAP)
             .value(Property::Keywords, keywords, F_VALUE | F_BITMAP)
             .value(Property::Cid, changes.change_id, F_VALUE)
+            .value(Property::SavedAt, now(), F_VALUE | F_INDEX)
             .custom(EmailIndexBuilder::set(metadata))
-            .custom(token_index)
             .custom(changes);
 
+        // Copy the term index, or queue the message for indexing if the
+        // original has not been indexed yet
+        let token_index_missing = token_index.is_none();
+        if let Some(token_index) = token_index {
+            batch.custom(token_index);
+        } else {
+            batch.op(FtsQueueEntry::new(account_id, Collection::Email, message_id).insert());
+        }
+
         self.store.write(batch.build()).await.map_err(|err| {
             tracing::error!(
                     event = "error",
@@ -445,6 +456,9 @@ impl JMAP {
                     "Failed to write message to database.");
             MethodError::ServerPartialFail
         })?;
+        if token_index_missing {
+            self.fts_queue_notify(1);
+        }
 
         Ok(Ok(email))
     }
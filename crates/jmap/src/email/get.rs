@@ -31,10 +31,15 @@ use jmap_proto::{
     },
 };
 use mail_parser::MessageParser;
+use store::write::now;
 
 use crate::{auth::AccessToken, email::headers::HeaderToValue, JMAP};
 
-use super::body::{ToBodyPart, TruncateBody};
+use super::{
+    body::{ToBodyPart, TruncateBody},
+    seen::shared_seen_apply,
+    smime::VerifySmime,
+};
 
 impl JMAP {
     pub async fn email_get(
@@ -92,6 +97,15 @@ impl JMAP {
         let message_ids = self
             .owned_or_shared_messages(access_token, account_id, Acl::ReadItems)
             .await?;
+        let shared_seen = self.shared_seen_id(access_token, account_id);
+        let seen_ids = if shared_seen.is_some() && properties.contains(&Property::Keywords) {
+            self.get_keyword_tag(account_id, shared_seen, Keyword::Seen)
+                .await?
+                .unwrap_or_default()
+                .into()
+        } else {
+            None
+        };
         let ids = if let Some(ids) = ids {
             ids
         } else {
@@ -121,6 +135,12 @@ impl JMAP {
         // Check if we need to fetch the raw headers or body
         let mut needs_headers = false;
         let mut needs_body = false;
+        let needs_smime = properties.iter().any(|property| {
+            matches!(
+                property,
+                Property::SmimeStatus | Property::SmimeErrors | Property::SmimeSignatures
+            )
+        });
         for property in &properties {
             match property {
                 Property::Header(_) | Property::Headers => {
@@ -130,7 +150,10 @@ impl JMAP {
                 | Property::TextBody
                 | Property::HtmlBody
                 | Property::Attachments
-                | Property::BodyStructure => {
+                | Property::BodyStructure
+                | Property::SmimeStatus
+                | Property::SmimeErrors
+                | Property::SmimeSignatures => {
                     needs_body = true;
                 }
                 _ => (),
@@ -207,6 +230,15 @@ impl JMAP {
                 None
             };
 
+            // Verify S/MIME signatures
+            let smime = if needs_smime {
+                message.as_ref().and_then(|message| {
+                    message.verify_smime(&self.config.smime_trust_store, now() as i64)
+                })
+            } else {
+                None
+            };
+
             // Prepare response
             let mut email = Object::with_capacity(properties.len());
             for property in &properties {
@@ -257,7 +289,13 @@ impl JMAP {
                                 &Property::Keywords,
                             )
                             .await?
-                            .map(|keywords| {
+                            .map(|mut keywords| {
+                                if let Some(seen_ids) = &seen_ids {
+                                    shared_seen_apply(
+                                        &mut keywords,
+                                        seen_ids.contains(id.document_id()),
+                                    );
+                                }
                                 let mut obj = Object::with_capacity(keywords.len());
                                 for keyword in keywords {
                                     obj.append(Property::_T(keyword.to_string()), true);
@@ -290,9 +328,31 @@ impl JMAP {
                     | Property::Subject
                     | Property::SentAt
                     | Property::HasAttachment
-                    | Property::Preview => {
+                    | Property::Preview
+                    | Property::SmimeStatusAtDelivery => {
                         email.append(property.clone(), values.remove(property));
                     }
+                    Property::SmimeStatus => {
+                        email.append(
+                            Property::SmimeStatus,
+                            smime.as_ref().map(|smime| smime.status.as_str()),
+                        );
+                    }
+                    Property::SmimeErrors => {
+                        email.append(
+                            Property::SmimeErrors,
+                            smime
+                                .as_ref()
+                                .filter(|smime| !smime.errors.is_empty())
+                                .map(|smime| smime.errors.clone()),
+                        );
+                    }
+                    Property::SmimeSignatures => {
+                        email.append(
+                            Property::SmimeSignatures,
+                            smime.as_ref().map(|smime| smime.signatures.clone()),
+                        );
+                    }
                     Property::Header(_) => {
                         if let Some(message) = &message {
                             email.append(
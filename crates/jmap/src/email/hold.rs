@@ -0,0 +1,282 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    object::Object,
+    types::{collection::Collection, keyword::Keyword, property::Property, value::Value},
+};
+use store::{
+    write::{key::KeySerializer, now, BatchBuilder, Operation, ValueClass, F_CLEAR, F_VALUE},
+    BitmapKey, BlobKind, CustomValueKey, Serialize, ValueKey,
+};
+
+use crate::{Bincode, JMAP};
+
+// Custom keys start with u32::MAX followed by the key type, types 0 to 5 are
+// used by the account name mappings, URLAUTH, full-text index queue and settings keys.
+const LEGAL_HOLD_KEY: u8 = 6;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeldMessage {
+    pub id: u32,
+    pub document_id: u32,
+    pub mailboxes: Vec<String>,
+    pub keywords: Vec<String>,
+    pub deleted_at: u64,
+}
+
+impl JMAP {
+    pub async fn legal_hold_since(&self, account_id: u32) -> Result<Option<u64>, MethodError> {
+        self.store
+            .get_value::<u64>(CustomValueKey {
+                value: legal_hold_key(account_id),
+            })
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                                context = "legal_hold",
+                                account_id = account_id,
+                                error = ?err,
+                                "Failed to retrieve legal hold status");
+                MethodError::ServerPartialFail
+            })
+    }
+
+    pub async fn legal_hold_enable(&self, account_id: u32) -> store::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: legal_hold_key(account_id),
+            },
+            set: now().serialize().into(),
+        });
+        self.store.write(batch.build()).await
+    }
+
+    // Lifts the hold and permanently removes the messages retained while it was
+    // active. Returns the number of messages removed.
+    pub async fn legal_hold_release(&self, account_id: u32) -> store::Result<usize> {
+        let held_ids = self
+            .store
+            .get_bitmap(BitmapKey::document_ids(account_id, Collection::LegalHold))
+            .await?
+            .unwrap_or_default();
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::LegalHold);
+        for held_id in &held_ids {
+            self.store
+                .delete_blob(&held_blob_kind(account_id, held_id))
+                .await?;
+            batch
+                .delete_document(held_id)
+                .value(Property::Value, (), F_VALUE | F_CLEAR);
+        }
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: legal_hold_key(account_id),
+            },
+            set: None,
+        });
+        self.store.write(batch.build()).await?;
+
+        Ok(held_ids.len() as usize)
+    }
+
+    pub async fn legal_hold_list(&self, account_id: u32) -> store::Result<Vec<HeldMessage>> {
+        let held_ids = self
+            .store
+            .get_bitmap(BitmapKey::document_ids(account_id, Collection::LegalHold))
+            .await?
+            .unwrap_or_default();
+        let mut messages = Vec::with_capacity(held_ids.len() as usize);
+        for held_id in held_ids {
+            if let Some(message) = self
+                .store
+                .get_value::<Bincode<HeldMessage>>(ValueKey::new(
+                    account_id,
+                    Collection::LegalHold,
+                    held_id,
+                    Property::Value,
+                ))
+                .await?
+            {
+                messages.push(message.inner);
+            }
+        }
+
+        Ok(messages)
+    }
+
+    // Retains a copy of a message that is about to be deleted if its account
+    // is on legal hold. Must be called before the message is removed.
+    pub async fn email_legal_hold(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> Result<(), MethodError> {
+        if self.legal_hold_since(account_id).await?.is_none() {
+            return Ok(());
+        }
+
+        // Obtain mailbox names and keywords
+        let mailbox_ids = if let Some(mailbox_ids) = self
+            .get_property::<Vec<u32>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::MailboxIds,
+            )
+            .await?
+        {
+            mailbox_ids
+        } else {
+            return Ok(());
+        };
+        let mut mailboxes = Vec::with_capacity(mailbox_ids.len());
+        for mailbox_id in mailbox_ids {
+            if let Some(mut mailbox) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    &Property::Value,
+                )
+                .await?
+            {
+                if let Value::Text(name) = mailbox.remove(&Property::Name) {
+                    mailboxes.push(name);
+                }
+            }
+        }
+        let keywords = self
+            .get_property::<Vec<Keyword>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::Keywords,
+            )
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|keyword| keyword.to_string())
+            .collect();
+
+        // Copy the message blob
+        let held_id = self
+            .store
+            .assign_document_id(account_id, Collection::LegalHold)
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                                context = "legal_hold",
+                                account_id = account_id,
+                                error = ?err,
+                                "Failed to assign documentId.");
+                MethodError::ServerPartialFail
+            })?;
+        let held_blob = held_blob_kind(account_id, held_id);
+        match self
+            .store
+            .copy_blob(
+                &BlobKind::LinkedMaildir {
+                    account_id,
+                    document_id,
+                },
+                &held_blob,
+                None,
+            )
+            .await
+        {
+            Ok(true) => (),
+            Ok(false) => {
+                tracing::warn!(
+                    event = "error",
+                    context = "legal_hold",
+                    account_id = account_id,
+                    document_id = document_id,
+                    "Message blob not found, only its metadata will be retained."
+                );
+            }
+            Err(err) => {
+                tracing::error!(event = "error",
+                                context = "legal_hold",
+                                account_id = account_id,
+                                document_id = document_id,
+                                error = ?err,
+                                "Failed to copy message blob.");
+                return Err(MethodError::ServerPartialFail);
+            }
+        }
+
+        // Write metadata
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::LegalHold)
+            .create_document(held_id)
+            .value(
+                Property::Value,
+                Bincode::new(HeldMessage {
+                    id: held_id,
+                    document_id,
+                    mailboxes,
+                    keywords,
+                    deleted_at: now(),
+                }),
+                F_VALUE,
+            );
+        if let Err(err) = self.store.write(batch.build()).await {
+            tracing::error!(event = "error",
+                            context = "legal_hold",
+                            account_id = account_id,
+                            document_id = document_id,
+                            error = ?err,
+                            "Failed to write held message.");
+            let _ = self.store.delete_blob(&held_blob).await;
+            return Err(MethodError::ServerPartialFail);
+        }
+
+        Ok(())
+    }
+}
+
+fn held_blob_kind(account_id: u32, held_id: u32) -> BlobKind {
+    BlobKind::Linked {
+        account_id,
+        collection: Collection::LegalHold.into(),
+        document_id: held_id,
+    }
+}
+
+fn legal_hold_key(account_id: u32) -> Vec<u8> {
+    KeySerializer::new(std::mem::size_of::<u32>() * 2 + 1)
+        .write(u32::MAX)
+        .write(LEGAL_HOLD_KEY)
+        .write(account_id)
+        .finalize()
+}
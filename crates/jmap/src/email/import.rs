@@ -31,6 +31,7 @@ use jmap_proto::{
         acl::Acl,
         collection::Collection,
         id::Id,
+        keyword::Keyword,
         property::Property,
         state::{State, StateChange},
         type_state::DataType,
@@ -41,7 +42,7 @@ use utils::map::vec_map::VecMap;
 
 use crate::{auth::AccessToken, IngestError, JMAP};
 
-use super::ingest::IngestEmail;
+use super::{ingest::IngestEmail, seen::shared_seen_apply};
 
 impl JMAP {
     pub async fn email_import(
@@ -67,6 +68,9 @@ impl JMAP {
         // Obtain quota
         let account_quota = self.get_quota(access_token, account_id).await?;
 
+        // Members of shared accounts keep their own \Seen state
+        let shared_seen = self.shared_seen_id(access_token, account_id);
+
         let mut response = ImportEmailResponse {
             account_id: request.account_id,
             new_state: old_state.clone(),
@@ -131,6 +135,12 @@ impl JMAP {
             };
 
             // Import message
+            let mut keywords = email.keywords;
+            let mut is_seen = false;
+            if shared_seen.is_some() {
+                is_seen = keywords.contains(&Keyword::Seen);
+                shared_seen_apply(&mut keywords, false);
+            }
             match self
                 .email_ingest(IngestEmail {
                     raw_message: &raw_message,
@@ -138,14 +148,19 @@ impl JMAP {
                     account_id,
                     account_quota,
                     mailbox_ids,
-                    keywords: email.keywords,
+                    keywords,
                     received_at: email.received_at.map(|r| r.into()),
                     skip_duplicates: false,
                     encrypt: self.config.encrypt && self.config.encrypt_append,
+                    replace: None,
                 })
                 .await
             {
                 Ok(email) => {
+                    if let (true, Some(user_id)) = (is_seen, shared_seen) {
+                        self.shared_seen_set(account_id, user_id, [email.id.document_id()], true)
+                            .await?;
+                    }
                     response.created.append(id, email.into());
                 }
                 Err(IngestError::Permanent { reason, .. }) => {
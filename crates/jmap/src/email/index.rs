@@ -35,15 +35,20 @@ use jmap_proto::{
 use mail_parser::{
     decoders::html::html_to_text,
     parsers::{fields::thread::thread_name, preview::preview_text},
-    Addr, Address, GetHeader, Group, HeaderName, HeaderValue, Message, MessagePart, PartType,
+    Addr, Address, GetHeader, Group, HeaderName, HeaderValue, Message, MessagePart, MimeHeaders,
+    PartType,
 };
 use nlp::language::Language;
 use store::{
-    fts::builder::{FtsIndexBuilder, MAX_TOKEN_LENGTH},
-    write::{BatchBuilder, IntoOperations, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE},
+    ahash::AHashMap,
+    fts::{
+        builder::{FtsIndexBuilder, MAX_TOKEN_LENGTH},
+        extract::{extract_text, DocumentType, ExtractLimits},
+    },
+    write::{now, BatchBuilder, IntoOperations, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE},
 };
 
-use crate::email::headers::IntoForm;
+use crate::email::{headers::IntoForm, smime::SmimeStatus};
 
 pub const MAX_MESSAGE_PARTS: usize = 1000;
 pub const MAX_ID_LENGTH: usize = 100;
@@ -64,7 +69,7 @@ pub(super) trait IndexMessage {
         keywords: Vec<Keyword>,
         mailbox_ids: Vec<u32>,
         received_at: u64,
-        default_language: Language,
+        smime_status: Option<SmimeStatus>,
     ) -> store::Result<&mut Self>;
 }
 
@@ -75,7 +80,7 @@ impl IndexMessage for BatchBuilder {
         keywords: Vec<Keyword>,
         mailbox_ids: Vec<u32>,
         received_at: u64,
-        default_language: Language,
+        smime_status: Option<SmimeStatus>,
     ) -> store::Result<&mut Self> {
         let mut metadata = Object::with_capacity(15);
 
@@ -97,9 +102,10 @@ impl IndexMessage for BatchBuilder {
         );
         self.value(Property::ReceivedAt, received_at, F_INDEX);
 
-        let mut fts = FtsIndexBuilder::with_default_language(default_language);
+        // Index savedAt
+        self.value(Property::SavedAt, now(), F_VALUE | F_INDEX);
+
         let mut seen_headers = [false; 40];
-        let mut language = Language::Unknown;
         let mut has_attachments = false;
         let preview_part_id = message
             .text_body
@@ -114,17 +120,12 @@ impl IndexMessage for BatchBuilder {
             .take(MAX_MESSAGE_PARTS)
             .enumerate()
         {
-            let part_language = part.language().unwrap_or(language);
             if part_id == 0 {
-                language = part_language;
                 let mut extra_ids = Vec::new();
                 for header in part.headers.into_iter().rev() {
                     if matches!(header.name, HeaderName::Other(_)) {
                         continue;
                     }
-                    // Index hasHeader property
-                    let header_num = header.name.id().to_string();
-                    fts.index_raw_token(Property::Headers, &header_num);
 
                     match header.name {
                         HeaderName::MessageId
@@ -136,14 +137,6 @@ impl IndexMessage for BatchBuilder {
                                 if id.len() < MAX_ID_LENGTH {
                                     self.value(Property::MessageId, id, F_INDEX);
                                 }
-
-                                // Index ids without stemming
-                                if id.len() < MAX_TOKEN_LENGTH {
-                                    fts.index_raw_token(
-                                        Property::Headers,
-                                        format!("{header_num}{id}"),
-                                    );
-                                }
                             });
 
                             if matches!(
@@ -198,9 +191,6 @@ impl IndexMessage for BatchBuilder {
                                             AddressElement::GroupName => (),
                                         }
                                     }
-
-                                    // Index an address name or email without stemming
-                                    fts.index_raw(u8::from(&property), value);
                                 });
 
                                 if !seen_header {
@@ -238,16 +228,15 @@ impl IndexMessage for BatchBuilder {
                             }
                         }
                         HeaderName::Subject => {
-                            // Index subject
-                            let subject = match &header.value {
-                                HeaderValue::Text(text) => text.clone(),
-                                HeaderValue::TextList(list) if !list.is_empty() => {
-                                    list.first().unwrap().clone()
-                                }
-                                _ => "".into(),
-                            };
-
                             if !seen_headers[header.name.id() as usize] {
+                                let subject = match &header.value {
+                                    HeaderValue::Text(text) => text.clone(),
+                                    HeaderValue::TextList(list) if !list.is_empty() => {
+                                        list.first().unwrap().clone()
+                                    }
+                                    _ => "".into(),
+                                };
+
                                 // Add to metadata
                                 metadata.append(
                                     Property::Subject,
@@ -271,23 +260,6 @@ impl IndexMessage for BatchBuilder {
 
                                 seen_headers[header.name.id() as usize] = true;
                             }
-
-                            // Index subject for FTS
-                            fts.index(Property::Subject, subject, language);
-                        }
-
-                        HeaderName::Comments | HeaderName::Keywords | HeaderName::ListId => {
-                            // Index headers
-                            header.value.visit_text(|text| {
-                                for token in text.split_ascii_whitespace() {
-                                    if token.len() < MAX_TOKEN_LENGTH {
-                                        fts.index_raw_token(
-                                            Property::Headers,
-                                            format!("{header_num}{}", token.to_lowercase()),
-                                        );
-                                    }
-                                }
-                            });
                         }
                         _ => (),
                     }
@@ -313,83 +285,213 @@ impl IndexMessage for BatchBuilder {
                         );
                     }
 
-                    if message.text_body.contains(&part_id) || message.html_body.contains(&part_id)
+                    if !message.text_body.contains(&part_id)
+                        && !message.html_body.contains(&part_id)
                     {
-                        fts.index(Property::TextBody, text, part_language);
-                    } else {
-                        fts.index(Property::Attachments, text, part_language);
                         has_attachments = true;
                     }
                 }
                 PartType::Html(html) => {
-                    let text = html_to_text(&html);
                     if part_id == preview_part_id {
                         metadata.append(
                             Property::Preview,
-                            preview_text(text.replace('\r', "").into(), PREVIEW_LENGTH),
+                            preview_text(
+                                html_to_text(&html).replace('\r', "").into(),
+                                PREVIEW_LENGTH,
+                            ),
                         );
                     }
 
-                    if message.text_body.contains(&part_id) || message.html_body.contains(&part_id)
+                    if !message.text_body.contains(&part_id)
+                        && !message.html_body.contains(&part_id)
                     {
-                        fts.index(Property::TextBody, text, part_language);
-                    } else {
-                        fts.index(Property::Attachments, text, part_language);
                         has_attachments = true;
                     }
                 }
-                PartType::Binary(_) if !has_attachments => {
+                PartType::Binary(_) | PartType::Message(_) if !has_attachments => {
                     has_attachments = true;
                 }
-                PartType::Message(mut nested_message) => {
+                _ => {}
+            }
+        }
+
+        // Store and index hasAttachment property
+        metadata.append(Property::HasAttachment, has_attachments);
+        if has_attachments {
+            self.bitmap(Property::HasAttachment, (), 0);
+        }
+
+        // Store the S/MIME status at delivery time
+        if let Some(smime_status) = smime_status {
+            metadata.append(Property::SmimeStatusAtDelivery, smime_status.as_str());
+        }
+
+        // Store properties
+        self.value(Property::BodyStructure, metadata, F_VALUE);
+
+        Ok(self)
+    }
+}
+
+pub trait IndexMessageText<'x>: Sized {
+    fn index_message(self, message: &'x Message<'x>, attachments: AttachmentText) -> Self;
+}
+
+// Text extracted from the attachments of a message, by part id
+pub type AttachmentText = AHashMap<usize, String>;
+
+// Returns the attachments in a supported document format, along with their part id
+pub fn attachment_documents<'x>(
+    message: &'x Message<'x>,
+) -> impl Iterator<Item = (usize, DocumentType, &'x [u8])> {
+    message
+        .parts
+        .iter()
+        .take(MAX_MESSAGE_PARTS)
+        .enumerate()
+        .filter_map(|(part_id, part)| match &part.body {
+            PartType::Binary(bytes) | PartType::InlineBinary(bytes) => DocumentType::detect(
+                part.content_type()
+                    .map(|content_type| (content_type.ctype(), content_type.subtype())),
+                part.attachment_name(),
+            )
+            .map(|document_type| (part_id, document_type, bytes.as_ref())),
+            _ => None,
+        })
+}
+
+pub fn extract_attachments(message: &Message, extract_limits: &ExtractLimits) -> AttachmentText {
+    attachment_documents(message)
+        .filter_map(|(part_id, document_type, bytes)| {
+            extract_text(document_type, bytes, extract_limits).map(|text| (part_id, text))
+        })
+        .collect()
+}
+
+impl<'x> IndexMessageText<'x> for FtsIndexBuilder<'x> {
+    fn index_message(mut self, message: &'x Message<'x>, mut attachments: AttachmentText) -> Self {
+        let mut language = Language::Unknown;
+
+        for (part_id, part) in message.parts.iter().take(MAX_MESSAGE_PARTS).enumerate() {
+            let part_language = part.language().unwrap_or(language);
+
+            // Index attachment file names
+            if let Some(name) = part.attachment_name() {
+                self.index(Property::Name, name, Language::None);
+            }
+
+            if part_id == 0 {
+                language = part_language;
+                for header in part.headers.iter().rev() {
+                    if matches!(header.name, HeaderName::Other(_)) {
+                        continue;
+                    }
+                    // Index hasHeader property
+                    let header_num = header.name.id().to_string();
+                    self.index_raw_token(Property::Headers, &header_num);
+
+                    match header.name {
+                        HeaderName::MessageId
+                        | HeaderName::InReplyTo
+                        | HeaderName::References
+                        | HeaderName::ResentMessageId => {
+                            header.value.visit_text(|id| {
+                                // Index ids without stemming
+                                if id.len() < MAX_TOKEN_LENGTH {
+                                    self.index_raw_token(
+                                        Property::Headers,
+                                        format!("{header_num}{id}"),
+                                    );
+                                }
+                            });
+                        }
+                        HeaderName::From | HeaderName::To | HeaderName::Cc | HeaderName::Bcc => {
+                            let property = Property::from_header(&header.name);
+                            header.value.visit_addresses(|_, value| {
+                                // Index an address name or email without stemming
+                                self.index_raw(u8::from(&property), value);
+                            });
+                        }
+                        HeaderName::Subject => {
+                            // Index subject
+                            let subject = match &header.value {
+                                HeaderValue::Text(text) => text.as_ref(),
+                                HeaderValue::TextList(list) if !list.is_empty() => {
+                                    list.first().unwrap().as_ref()
+                                }
+                                _ => "",
+                            };
+                            self.index(Property::Subject, subject, language);
+                        }
+                        HeaderName::Comments | HeaderName::Keywords | HeaderName::ListId => {
+                            // Index headers
+                            header.value.visit_text(|text| {
+                                for token in text.split_ascii_whitespace() {
+                                    if token.len() < MAX_TOKEN_LENGTH {
+                                        self.index_raw_token(
+                                            Property::Headers,
+                                            format!("{header_num}{}", token.to_lowercase()),
+                                        );
+                                    }
+                                }
+                            });
+                        }
+                        _ => (),
+                    }
+                }
+            }
+
+            match &part.body {
+                PartType::Text(text) => {
+                    if message.text_body.contains(&part_id) || message.html_body.contains(&part_id)
+                    {
+                        self.index(Property::TextBody, text.as_ref(), part_language);
+                    } else {
+                        self.index(Property::Attachments, text.as_ref(), part_language);
+                    }
+                }
+                PartType::Html(html) => {
+                    let text = html_to_text(html);
+                    if message.text_body.contains(&part_id) || message.html_body.contains(&part_id)
+                    {
+                        self.index(Property::TextBody, text, part_language);
+                    } else {
+                        self.index(Property::Attachments, text, part_language);
+                    }
+                }
+                PartType::Binary(_) | PartType::InlineBinary(_) => {
+                    // Index the text of supported document formats
+                    if let Some(text) = attachments.remove(&part_id) {
+                        self.index(Property::Attachments, text, part_language);
+                    }
+                }
+                PartType::Message(nested_message) => {
                     let nested_message_language = nested_message
                         .root_part()
                         .language()
                         .unwrap_or(Language::Unknown);
-                    if let Some(HeaderValue::Text(subject)) =
-                        nested_message.remove_header(HeaderName::Subject)
-                    {
-                        fts.index(
-                            Property::Attachments,
-                            subject.into_owned(),
-                            nested_message_language,
-                        );
+                    if let Some(subject) = nested_message.subject() {
+                        self.index(Property::Attachments, subject, nested_message_language);
                     }
 
-                    for sub_part in nested_message.parts.into_iter().take(MAX_MESSAGE_PARTS) {
+                    for sub_part in nested_message.parts.iter().take(MAX_MESSAGE_PARTS) {
                         let language = sub_part.language().unwrap_or(nested_message_language);
-                        match sub_part.body {
+                        match &sub_part.body {
                             PartType::Text(text) => {
-                                fts.index(Property::Attachments, text, language);
+                                self.index(Property::Attachments, text.as_ref(), language);
                             }
                             PartType::Html(html) => {
-                                fts.index(Property::Attachments, html_to_text(&html), language);
+                                self.index(Property::Attachments, html_to_text(html), language);
                             }
                             _ => (),
                         }
                     }
-
-                    if !has_attachments {
-                        has_attachments = true;
-                    }
                 }
-                _ => {}
+                PartType::Multipart(_) => (),
             }
         }
 
-        // Store and index hasAttachment property
-        metadata.append(Property::HasAttachment, has_attachments);
-        if has_attachments {
-            self.bitmap(Property::HasAttachment, (), 0);
-        }
-
-        // Store properties
-        self.value(Property::BodyStructure, metadata, F_VALUE);
-
-        // Store full text index
-        self.custom(fts);
-
-        Ok(self)
+        self
     }
 }
 
@@ -35,20 +35,25 @@ use mail_parser::{
 };
 use store::{
     ahash::AHashSet,
+    fts::{builder::FtsIndexBuilder, queue::FtsQueueEntry},
     query::Filter,
-    write::{log::ChangeLogBuilder, now, BatchBuilder, F_BITMAP, F_CLEAR, F_VALUE},
-    BitmapKey, ValueKey,
+    write::{
+        assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder, F_BITMAP, F_CLEAR, F_VALUE,
+    },
+    BitmapKey, BlobKind, ValueKey,
 };
 use utils::map::vec_map::VecMap;
 
 use crate::{
-    email::index::{IndexMessage, MAX_ID_LENGTH},
+    email::index::{IndexMessage, IndexMessageText, MAX_ID_LENGTH},
     IngestError, JMAP,
 };
 
 use super::{
     crypto::{EncryptMessage, EncryptMessageError, EncryptionParams},
     index::{TrimTextValue, MAX_SORT_FIELD_LENGTH},
+    set::TagManager,
+    smime::VerifySmime,
 };
 
 #[derive(Default)]
@@ -69,6 +74,13 @@ pub struct IngestEmail<'x> {
     pub received_at: Option<u64>,
     pub skip_duplicates: bool,
     pub encrypt: bool,
+    pub replace: Option<ReplaceEmail>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReplaceEmail {
+    pub document_id: u32,
+    pub mailbox_id: u32,
 }
 
 impl JMAP {
@@ -174,6 +186,11 @@ impl JMAP {
             }
         };
 
+        // Verify S/MIME signatures at delivery time
+        let smime_status = message
+            .verify_smime(&self.config.smime_trust_store, now() as i64)
+            .map(|verification| verification.status);
+
         // Encrypt message
         if params.encrypt && !message.is_encrypted() {
             if let Some(encrypt_params) = self
@@ -294,16 +311,45 @@ impl JMAP {
             changes.log_child_update(Collection::Mailbox, *mailbox_id);
         }
 
+        // Remove the message being replaced in the same batch
+        let delete_blob = if let Some(replace) = &params.replace {
+            self.email_replace_batch(
+                &mut batch,
+                &mut changes,
+                params.account_id,
+                replace,
+                thread_id,
+            )
+            .await?
+        } else {
+            false
+        };
+
         // Build write batch
         batch
             .with_collection(Collection::Email)
-            .create_document(document_id)
+            .create_document(document_id);
+
+        // Index the message text now or queue it for background indexing
+        let fts_queued = self.fts_queue_has_capacity();
+        if fts_queued {
+            batch
+                .op(FtsQueueEntry::new(params.account_id, Collection::Email, document_id).insert());
+        } else {
+            let attachments = self.fts_extract_attachments(&message).await;
+            batch.custom(
+                FtsIndexBuilder::with_default_language(self.config.default_language)
+                    .index_message(&message, attachments),
+            );
+        }
+
+        batch
             .index_message(
                 message,
                 params.keywords,
                 params.mailbox_ids,
                 params.received_at.unwrap_or_else(now),
-                self.config.default_language,
+                smime_status,
             )
             .map_err(|err| {
                 tracing::error!(
@@ -324,6 +370,27 @@ impl JMAP {
                 "Failed to write message to database.");
             IngestError::Temporary
         })?;
+        if fts_queued {
+            self.fts_queue_notify(1);
+        }
+
+        // Delete the blob of the replaced message
+        if let (true, Some(replace)) = (delete_blob, params.replace) {
+            if let Err(err) = self
+                .store
+                .delete_blob(&BlobKind::LinkedMaildir {
+                    account_id: params.account_id,
+                    document_id: replace.document_id,
+                })
+                .await
+            {
+                tracing::warn!(
+                    event = "error",
+                    context = "email_ingest",
+                    error = ?err,
+                    "Failed to delete blob of replaced message.");
+            }
+        }
 
         Ok(IngestedEmail {
             id,
@@ -333,6 +400,92 @@ impl JMAP {
         })
     }
 
+    async fn email_replace_batch(
+        &self,
+        batch: &mut BatchBuilder,
+        changes: &mut ChangeLogBuilder,
+        account_id: u32,
+        replace: &ReplaceEmail,
+        thread_id: u32,
+    ) -> Result<bool, IngestError> {
+        let mailboxes = self
+            .get_property::<HashedValue<Vec<u32>>>(
+                account_id,
+                Collection::Email,
+                replace.document_id,
+                Property::MailboxIds,
+            )
+            .await
+            .map_err(|_| IngestError::Temporary)?
+            .filter(|mailboxes| mailboxes.inner.contains(&replace.mailbox_id))
+            .ok_or_else(|| IngestError::Permanent {
+                code: [5, 5, 0],
+                reason: "The message to replace no longer exists.".to_string(),
+            })?;
+
+        if mailboxes.inner.len() > 1 {
+            // The message belongs to other mailboxes, untag it from this one
+            let prev_thread_id = self
+                .get_property::<u32>(
+                    account_id,
+                    Collection::Email,
+                    replace.document_id,
+                    Property::ThreadId,
+                )
+                .await
+                .map_err(|_| IngestError::Temporary)?
+                .ok_or(IngestError::Temporary)?;
+            let mut mailboxes = TagManager::new(mailboxes);
+            mailboxes.update(replace.mailbox_id, false);
+            batch
+                .with_collection(Collection::Email)
+                .update_document(replace.document_id);
+            mailboxes.update_batch(batch, Property::MailboxIds);
+            batch.value(Property::Cid, changes.change_id, F_VALUE);
+            changes.log_update(
+                Collection::Email,
+                Id::from_parts(prev_thread_id, replace.document_id),
+            );
+            changes.log_child_update(Collection::Mailbox, replace.mailbox_id);
+
+            Ok(false)
+        } else {
+            self.email_legal_hold(account_id, replace.document_id)
+                .await
+                .map_err(|_| IngestError::Temporary)?;
+            let shared_seen_users = self
+                .shared_seen_users(account_id)
+                .await
+                .map_err(|_| IngestError::Temporary)?;
+            match self
+                .email_delete_batch(
+                    batch,
+                    changes,
+                    account_id,
+                    replace.document_id,
+                    &shared_seen_users,
+                )
+                .await
+            {
+                Ok(Ok(delete_thread_id)) => {
+                    // Keep the thread if the new message was added to it
+                    if let Some(delete_thread_id) = delete_thread_id.filter(|id| *id != thread_id) {
+                        batch
+                            .with_collection(Collection::Thread)
+                            .delete_document(delete_thread_id);
+                        changes.log_delete(Collection::Thread, delete_thread_id);
+                    }
+                    Ok(true)
+                }
+                Ok(Err(_)) => Err(IngestError::Permanent {
+                    code: [5, 5, 0],
+                    reason: "The message to replace no longer exists.".to_string(),
+                }),
+                Err(_) => Err(IngestError::Temporary),
+            }
+        }
+    }
+
     pub async fn find_or_merge_thread(
         &self,
         account_id: u32,
@@ -26,10 +26,15 @@ pub mod copy;
 pub mod crypto;
 pub mod get;
 pub mod headers;
+pub mod hold;
 pub mod import;
 pub mod index;
 pub mod ingest;
 pub mod parse;
 pub mod query;
+pub mod seen;
 pub mod set;
+pub mod smime;
 pub mod snippet;
+pub mod text_query;
+pub mod urlauth;
@@ -30,6 +30,7 @@ use jmap_proto::{
 use mail_parser::HeaderName;
 use nlp::language::Language;
 use store::{
+    ahash::AHashMap,
     fts::builder::MAX_TOKEN_LENGTH,
     query::{self},
     roaring::RoaringBitmap,
@@ -38,6 +39,8 @@ use store::{
 
 use crate::{auth::AccessToken, JMAP};
 
+use super::seen::keyword_filter;
+
 impl JMAP {
     pub async fn email_query(
         &self,
@@ -45,7 +48,9 @@ impl JMAP {
         access_token: &AccessToken,
     ) -> Result<QueryResponse, MethodError> {
         let account_id = request.account_id.document_id();
+        let shared_seen = self.shared_seen_id(access_token, account_id);
         let mut filters = Vec::with_capacity(request.filter.len());
+        let mut fuzzy_terms = Vec::new();
 
         for cond in std::mem::take(&mut request.filter) {
             match cond {
@@ -70,24 +75,25 @@ impl JMAP {
                 Filter::MinSize(size) => filters.push(query::Filter::ge(Property::Size, size)),
                 Filter::MaxSize(size) => filters.push(query::Filter::lt(Property::Size, size)),
                 Filter::AllInThreadHaveKeyword(keyword) => filters.push(query::Filter::is_in_set(
-                    self.thread_keywords(account_id, keyword, true).await?,
+                    self.thread_keywords(account_id, shared_seen, keyword, true)
+                        .await?,
                 )),
                 Filter::SomeInThreadHaveKeyword(keyword) => filters.push(query::Filter::is_in_set(
-                    self.thread_keywords(account_id, keyword, false).await?,
+                    self.thread_keywords(account_id, shared_seen, keyword, false)
+                        .await?,
                 )),
                 Filter::NoneInThreadHaveKeyword(keyword) => {
                     filters.push(query::Filter::Not);
                     filters.push(query::Filter::is_in_set(
-                        self.thread_keywords(account_id, keyword, false).await?,
+                        self.thread_keywords(account_id, shared_seen, keyword, false)
+                            .await?,
                     ));
                     filters.push(query::Filter::End);
                 }
-                Filter::HasKeyword(keyword) => {
-                    filters.push(query::Filter::is_in_bitmap(Property::Keywords, keyword))
-                }
+                Filter::HasKeyword(keyword) => filters.push(keyword_filter(shared_seen, keyword)),
                 Filter::NotKeyword(keyword) => {
                     filters.push(query::Filter::Not);
-                    filters.push(query::Filter::is_in_bitmap(Property::Keywords, keyword));
+                    filters.push(keyword_filter(shared_seen, keyword));
                     filters.push(query::Filter::End);
                 }
                 Filter::HasAttachment(has_attach) => {
@@ -100,6 +106,10 @@ impl JMAP {
                     }
                 }
                 Filter::Text(text) => {
+                    if let Some(text_filters) = self.parse_text_query(&text) {
+                        filters.extend(text_filters);
+                        continue;
+                    }
                     filters.push(query::Filter::Or);
                     filters.push(query::Filter::has_text(
                         Property::From,
@@ -236,6 +246,23 @@ impl JMAP {
                     Property::ThreadId,
                     id.document_id(),
                 )),
+                Filter::Fuzzy(text) => {
+                    let fields: Vec<u8> = vec![
+                        Property::Subject.into(),
+                        Property::TextBody.into(),
+                        Property::Attachments.into(),
+                    ];
+                    filters.push(query::Filter::Or);
+                    for field in &fields {
+                        filters.push(query::Filter::has_fuzzy_text(
+                            *field,
+                            &text,
+                            self.config.default_language,
+                        ));
+                    }
+                    filters.push(query::Filter::End);
+                    fuzzy_terms.push((fields, text));
+                }
                 Filter::And | Filter::Or | Filter::Not | Filter::Close => {
                     filters.push(cond.into());
                 }
@@ -281,10 +308,9 @@ impl JMAP {
                         query::Comparator::field(Property::SentAt, comparator.is_ascending)
                     }
                     SortProperty::HasKeyword => query::Comparator::set(
-                        self.get_tag(
+                        self.get_keyword_tag(
                             account_id,
-                            Collection::Email,
-                            Property::Keywords,
+                            shared_seen,
                             comparator.keyword.unwrap_or(Keyword::Seen),
                         )
                         .await?
@@ -294,6 +320,7 @@ impl JMAP {
                     SortProperty::AllInThreadHaveKeyword => query::Comparator::set(
                         self.thread_keywords(
                             account_id,
+                            shared_seen,
                             comparator.keyword.unwrap_or(Keyword::Seen),
                             true,
                         )
@@ -303,6 +330,7 @@ impl JMAP {
                     SortProperty::SomeInThreadHaveKeyword => query::Comparator::set(
                         self.thread_keywords(
                             account_id,
+                            shared_seen,
                             comparator.keyword.unwrap_or(Keyword::Seen),
                             false,
                         )
@@ -313,6 +341,10 @@ impl JMAP {
                     SortProperty::Cc => {
                         query::Comparator::field(Property::Cc, comparator.is_ascending)
                     }
+                    SortProperty::Relevance => query::Comparator::scores(
+                        self.fuzzy_scores(account_id, &fuzzy_terms).await?,
+                        comparator.is_ascending,
+                    ),
 
                     other => return Err(MethodError::UnsupportedSort(other.to_string())),
                 });
@@ -338,14 +370,58 @@ impl JMAP {
         }
     }
 
+    pub async fn fuzzy_scores(
+        &self,
+        account_id: u32,
+        fuzzy_terms: &[(Vec<u8>, String)],
+    ) -> Result<AHashMap<u32, u32>, MethodError> {
+        // The relevancy of a message is the average score of all fuzzy terms
+        let mut scores = AHashMap::new();
+        for (fields, text) in fuzzy_terms {
+            for (document_id, score) in self
+                .store
+                .fuzzy_scores(
+                    account_id,
+                    Collection::Email,
+                    fields
+                        .iter()
+                        .map(|field| (*field, fuzzy_field_weight(*field)))
+                        .collect(),
+                    text.clone(),
+                    self.config.default_language,
+                )
+                .await
+                .map_err(|err| {
+                    tracing::error!(event = "error",
+                                    context = "email_query",
+                                    account_id = account_id,
+                                    error = ?err,
+                                    "Failed to obtain fuzzy scores.");
+
+                    MethodError::ServerPartialFail
+                })?
+            {
+                *scores.entry(document_id).or_insert(0) += score;
+            }
+        }
+        if fuzzy_terms.len() > 1 {
+            for score in scores.values_mut() {
+                *score = (*score / fuzzy_terms.len() as u32).max(1);
+            }
+        }
+
+        Ok(scores)
+    }
+
     async fn thread_keywords(
         &self,
         account_id: u32,
+        shared_seen: Option<u32>,
         keyword: Keyword,
         match_all: bool,
     ) -> Result<RoaringBitmap, MethodError> {
         let keyword_doc_ids = self
-            .get_tag(account_id, Collection::Email, Property::Keywords, keyword)
+            .get_keyword_tag(account_id, shared_seen, keyword)
             .await?
             .unwrap_or_default();
 
@@ -386,3 +462,15 @@ impl JMAP {
         Ok(matched_ids)
     }
 }
+
+// Matches in the subject or sender rank above matches in the body or attachments
+fn fuzzy_field_weight(field: u8) -> u32 {
+    [
+        (Property::Subject, 100),
+        (Property::From, 100),
+        (Property::TextBody, 60),
+    ]
+    .into_iter()
+    .find_map(|(property, weight)| (u8::from(property) == field).then_some(weight))
+    .unwrap_or(40)
+}
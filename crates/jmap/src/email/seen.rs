@@ -0,0 +1,187 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    types::{collection::Collection, keyword::Keyword, property::Property},
+};
+use store::{
+    query::Filter,
+    roaring::RoaringBitmap,
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        BatchBuilder, Operation, ValueClass, F_CLEAR,
+    },
+    CustomValueKey,
+};
+
+use crate::{auth::AccessToken, JMAP};
+
+// Members of a shared account keep their own \Seen state, which is stored as a
+// tag on the Keywords property keyed by the member's account id. Each member
+// with seen state is registered under this custom key so that their tags can
+// be cleared when a message is deleted.
+const SHARED_SEEN_KEY: u8 = 7;
+
+impl JMAP {
+    // Returns the id under which the \Seen state of a shared account member is
+    // stored, or None if the account uses the message keywords.
+    pub fn shared_seen_id(&self, access_token: &AccessToken, account_id: u32) -> Option<u32> {
+        if access_token.primary_id() != account_id && access_token.member_of.contains(&account_id) {
+            Some(access_token.primary_id())
+        } else {
+            None
+        }
+    }
+
+    pub async fn get_keyword_tag(
+        &self,
+        account_id: u32,
+        shared_seen: Option<u32>,
+        keyword: Keyword,
+    ) -> Result<Option<RoaringBitmap>, MethodError> {
+        match (shared_seen, keyword) {
+            (Some(user_id), Keyword::Seen) => {
+                self.get_tag(account_id, Collection::Email, Property::Keywords, user_id)
+                    .await
+            }
+            (_, keyword) => {
+                self.get_tag(account_id, Collection::Email, Property::Keywords, keyword)
+                    .await
+            }
+        }
+    }
+
+    pub async fn shared_seen_set(
+        &self,
+        account_id: u32,
+        user_id: u32,
+        document_ids: impl IntoIterator<Item = u32>,
+        seen: bool,
+    ) -> Result<(), MethodError> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Email);
+        for document_id in document_ids {
+            batch.update_document(document_id).bitmap(
+                Property::Keywords,
+                user_id,
+                if seen { 0 } else { F_CLEAR },
+            );
+        }
+        if !batch.is_empty() {
+            shared_seen_register_op(&mut batch, account_id, user_id);
+            self.write_batch(batch).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn shared_seen_register(
+        &self,
+        account_id: u32,
+        user_id: u32,
+    ) -> Result<(), MethodError> {
+        let mut batch = BatchBuilder::new();
+        shared_seen_register_op(&mut batch, account_id, user_id);
+        self.write_batch(batch).await
+    }
+
+    // Returns the members of a shared account that have their own \Seen state.
+    // Callers deleting messages obtain them once and pass them to email_delete.
+    pub async fn shared_seen_users(&self, account_id: u32) -> Result<Vec<u32>, MethodError> {
+        self.store
+            .iterate(
+                Vec::new(),
+                CustomValueKey {
+                    value: shared_seen_key(account_id, 0),
+                },
+                CustomValueKey {
+                    value: shared_seen_key(account_id, u32::MAX),
+                },
+                false,
+                true,
+                |user_ids, key, _| {
+                    user_ids.push(key.deserialize_be_u32(key.len() - std::mem::size_of::<u32>())?);
+
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                                context = "shared_seen",
+                                account_id = account_id,
+                                error = ?err,
+                                "Failed to retrieve shared seen users");
+                MethodError::ServerPartialFail
+            })
+    }
+}
+
+// Adds to the batch of a message being deleted the operations that clear
+// its per-user \Seen state, as document ids are reused.
+pub fn shared_seen_delete(batch: &mut BatchBuilder, shared_seen_users: &[u32]) {
+    for user_id in shared_seen_users {
+        batch.bitmap(Property::Keywords, *user_id, F_CLEAR);
+    }
+}
+
+// Replaces the \Seen keyword of a message with the per-user state.
+pub fn shared_seen_apply(keywords: &mut Vec<Keyword>, is_seen: bool) {
+    if let Some(pos) = keywords.iter().position(|k| k == &Keyword::Seen) {
+        if !is_seen {
+            keywords.swap_remove(pos);
+        }
+    } else if is_seen {
+        keywords.push(Keyword::Seen);
+    }
+}
+
+pub fn keyword_filter(shared_seen: Option<u32>, keyword: Keyword) -> Filter {
+    match (shared_seen, keyword) {
+        (Some(user_id), Keyword::Seen) => Filter::is_in_bitmap(Property::Keywords, user_id),
+        (_, keyword) => Filter::is_in_bitmap(Property::Keywords, keyword),
+    }
+}
+
+// Registrations are written without reading them first, setting an existing
+// key again has no effect.
+fn shared_seen_register_op(batch: &mut BatchBuilder, account_id: u32, user_id: u32) {
+    batch.op(Operation::Value {
+        class: ValueClass::Custom {
+            bytes: shared_seen_key(account_id, user_id),
+        },
+        set: Vec::new().into(),
+    });
+}
+
+fn shared_seen_key(account_id: u32, user_id: u32) -> Vec<u8> {
+    KeySerializer::new(std::mem::size_of::<u32>() * 3 + 1)
+        .write(u32::MAX)
+        .write(SHARED_SEEN_KEY)
+        .write(account_id)
+        .write(user_id)
+        .finalize()
+}
@@ -56,7 +56,7 @@ use store::{
     fts::term_index::TokenIndex,
     write::{
         assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, DeserializeFrom, SerializeInto,
-        ToBitmaps, ValueClass, F_BITMAP, F_CLEAR, F_VALUE,
+        ToBitmaps, ValueClass, F_BITMAP, F_CLEAR, F_INDEX, F_VALUE,
     },
     BlobKind, Serialize, ValueKey,
 };
@@ -67,6 +67,7 @@ use super::{
     headers::{BuildHeader, ValueToHeader},
     index::EmailIndexBuilder,
     ingest::IngestEmail,
+    seen::{shared_seen_apply, shared_seen_delete},
 };
 
 impl JMAP {
@@ -111,6 +112,9 @@ impl JMAP {
         // Obtain quota
         let account_quota = self.get_quota(access_token, account_id).await?;
 
+        // Members of shared accounts keep their own \Seen state
+        let shared_seen = self.shared_seen_id(access_token, account_id);
+
         // Process creates
         'create: for (id, mut object) in request.unwrap_create() {
             let has_body_structure = object
@@ -730,6 +734,11 @@ impl JMAP {
             builder.write_to(&mut raw_message).unwrap_or_default();
 
             // Ingest message
+            let mut is_seen = false;
+            if shared_seen.is_some() {
+                is_seen = keywords.contains(&Keyword::Seen);
+                shared_seen_apply(&mut keywords, false);
+            }
             match self
                 .email_ingest(IngestEmail {
                     raw_message: &raw_message,
@@ -741,10 +750,15 @@ impl JMAP {
                     received_at,
                     skip_duplicates: false,
                     encrypt: self.config.encrypt && self.config.encrypt_append,
+                    replace: None,
                 })
                 .await
             {
                 Ok(message) => {
+                    if let (true, Some(user_id)) = (is_seen, shared_seen) {
+                        self.shared_seen_set(account_id, user_id, [message.id.document_id()], true)
+                            .await?;
+                    }
                     response.created.insert(id, message.into());
                 }
                 Err(IngestError::OverQuota) => {
@@ -760,6 +774,15 @@ impl JMAP {
 
         // Process updates
         let mut changes = ChangeLogBuilder::new();
+        let mut seen_ids = if shared_seen.is_some() {
+            self.get_keyword_tag(account_id, shared_seen, Keyword::Seen)
+                .await?
+                .unwrap_or_default()
+                .into()
+        } else {
+            None
+        };
+        let mut shared_seen_registered = false;
         'update: for (id, object) in request.unwrap_update() {
             // Make sure id won't be destroyed
             if will_destroy.contains(&id) {
@@ -797,6 +820,7 @@ impl JMAP {
                 .with_account_id(account_id)
                 .with_collection(Collection::Email);
 
+            let mut seen_update = None;
             for (property, value) in object.properties {
                 let value = match response.eval_object_references(value) {
                     Ok(value) => value,
@@ -823,20 +847,29 @@ impl JMAP {
                         }
                     }
                     (Property::Keywords, MaybePatchValue::Value(Value::List(keywords_))) => {
-                        keywords.set(
-                            keywords_
-                                .into_iter()
-                                .filter_map(|keyword| keyword.try_unwrap_keyword())
-                                .collect(),
-                        );
+                        let mut keywords_ = keywords_
+                            .into_iter()
+                            .filter_map(|keyword| keyword.try_unwrap_keyword())
+                            .collect::<Vec<_>>();
+                        if shared_seen.is_some() {
+                            // Members of shared accounts only change their own \Seen state
+                            seen_update = keywords_.contains(&Keyword::Seen).into();
+                            shared_seen_apply(
+                                &mut keywords_,
+                                keywords.current().contains(&Keyword::Seen),
+                            );
+                        }
+                        keywords.set(keywords_);
                     }
                     (Property::Keywords, MaybePatchValue::Patch(patch)) => {
                         let mut patch = patch.into_iter();
                         if let Some(keyword) = patch.next().unwrap().try_unwrap_keyword() {
-                            keywords.update(
-                                keyword,
-                                patch.next().unwrap().try_unwrap_bool().unwrap_or_default(),
-                            );
+                            let set = patch.next().unwrap().try_unwrap_bool().unwrap_or_default();
+                            if shared_seen.is_some() && keyword == Keyword::Seen {
+                                seen_update = set.into();
+                            } else {
+                                keywords.update(keyword, set);
+                            }
                         }
                     }
                     (property, _) => {
@@ -846,7 +879,13 @@ impl JMAP {
                 }
             }
 
-            if !mailboxes.has_changes() && !keywords.has_changes() {
+            let seen_update = seen_update.filter(|seen| {
+                *seen
+                    != seen_ids
+                        .as_ref()
+                        .map_or(false, |ids| ids.contains(document_id))
+            });
+            if !mailboxes.has_changes() && !keywords.has_changes() && seen_update.is_none() {
                 response.not_updated.append(
                     id,
                     SetError::invalid_properties()
@@ -861,7 +900,7 @@ impl JMAP {
             changes.log_update(Collection::Email, id);
 
             // Process keywords
-            if keywords.has_changes() {
+            if keywords.has_changes() || seen_update.is_some() {
                 // Verify permissions on shared accounts
                 if matches!(&can_modify_message_ids, Some(ids) if !ids.contains(document_id)) {
                     response.not_updated.append(
@@ -873,17 +912,36 @@ impl JMAP {
                 }
 
                 // Set all current mailboxes as changed if the Seen tag changed
-                if keywords
-                    .changed_tags()
-                    .any(|keyword| keyword == &Keyword::Seen)
+                if seen_update.is_some()
+                    || keywords
+                        .changed_tags()
+                        .any(|keyword| keyword == &Keyword::Seen)
                 {
                     for mailbox_id in mailboxes.current() {
                         changed_mailboxes.insert(*mailbox_id);
                     }
                 }
 
+                // Update the member's \Seen state
+                if let (Some(seen), Some(user_id)) = (seen_update, shared_seen) {
+                    if !shared_seen_registered {
+                        self.shared_seen_register(account_id, user_id).await?;
+                        shared_seen_registered = true;
+                    }
+                    batch.bitmap(Property::Keywords, user_id, if seen { 0 } else { F_CLEAR });
+                    if let Some(seen_ids) = &mut seen_ids {
+                        if seen {
+                            seen_ids.insert(document_id);
+                        } else {
+                            seen_ids.remove(document_id);
+                        }
+                    }
+                }
+
                 // Update keywords property
-                keywords.update_batch(&mut batch, Property::Keywords);
+                if keywords.has_changes() {
+                    keywords.update_batch(&mut batch, Property::Keywords);
+                }
 
                 // Update last change id
                 if changes.change_id == u64::MAX {
@@ -999,13 +1057,17 @@ impl JMAP {
             } else {
                 None
             };
+            let shared_seen_users = self.shared_seen_users(account_id).await?;
             for destroy_id in will_destroy {
                 let document_id = destroy_id.document_id();
 
                 if email_ids.contains(document_id) {
                     if !matches!(&can_destroy_message_ids, Some(ids) if !ids.contains(document_id))
                     {
-                        match self.email_delete(account_id, document_id).await? {
+                        match self
+                            .email_delete(account_id, document_id, &shared_seen_users)
+                            .await?
+                        {
                             Ok(change) => {
                                 changes.merge(change);
                                 response.destroyed.push(destroy_id);
@@ -1050,15 +1112,90 @@ impl JMAP {
         Ok(response)
     }
 
+    // Shared seen users are obtained by the caller with shared_seen_users, once
+    // for all the messages it deletes.
     pub async fn email_delete(
         &self,
         account_id: u32,
         document_id: u32,
+        shared_seen_users: &[u32],
     ) -> Result<Result<ChangeLogBuilder, SetError>, MethodError> {
+        // Retain a copy if the account is on legal hold
+        self.email_legal_hold(account_id, document_id).await?;
+
         // Create batch
         let mut batch = BatchBuilder::new();
         let mut changes = ChangeLogBuilder::with_change_id(0);
 
+        // Remove message
+        match self
+            .email_delete_batch(
+                &mut batch,
+                &mut changes,
+                account_id,
+                document_id,
+                shared_seen_users,
+            )
+            .await?
+        {
+            Ok(Some(thread_id)) => {
+                // Thread is empty, delete it
+                batch
+                    .with_collection(Collection::Thread)
+                    .delete_document(thread_id);
+                changes.log_delete(Collection::Thread, thread_id);
+            }
+            Ok(None) => (),
+            Err(err) => return Ok(Err(err)),
+        }
+
+        // Commit batch
+        match self.store.write(batch.build()).await {
+            Ok(_) => (),
+            Err(store::Error::AssertValueFailed) => {
+                return Ok(Err(SetError::forbidden().with_description(
+                    "Another process modified this message, please try again.",
+                )));
+            }
+            Err(err) => {
+                tracing::error!(
+                    event = "error",
+                    context = "email_delete",
+                    error = ?err,
+                    "Failed to commit batch.");
+                return Err(MethodError::ServerPartialFail);
+            }
+        }
+
+        // Delete blob
+        self.store
+            .delete_blob(&BlobKind::LinkedMaildir {
+                account_id,
+                document_id,
+            })
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                event = "error",
+                context = "email_delete",
+                error = ?err,
+                "Failed to delete blob.");
+                MethodError::ServerPartialFail
+            })?;
+
+        Ok(Ok(changes))
+    }
+
+    // Removes a message as part of an existing batch. Returns the threadId if the
+    // thread became empty, the caller is responsible for deleting it and the blob.
+    pub async fn email_delete_batch(
+        &self,
+        batch: &mut BatchBuilder,
+        changes: &mut ChangeLogBuilder,
+        account_id: u32,
+        document_id: u32,
+        shared_seen_users: &[u32],
+    ) -> Result<Result<Option<u32>, SetError>, MethodError> {
         // Delete document
         batch
             .with_account_id(account_id)
@@ -1123,6 +1260,7 @@ impl JMAP {
             );
             return Ok(Err(SetError::not_found()));
         };
+        shared_seen_delete(batch, shared_seen_users);
 
         // Remove threadIds
         let mut delete_thread_id = None;
@@ -1144,9 +1282,8 @@ impl JMAP {
                     // Thread has other document ids, remove this one
                     changes.log_child_update(Collection::Thread, thread_id);
                 } else {
-                    // Thread is empty, delete it
+                    // Thread is empty
                     delete_thread_id = thread_id.into();
-                    changes.log_delete(Collection::Thread, thread_id);
                 }
 
                 // Remove threadId value and tag
@@ -1222,58 +1359,29 @@ impl JMAP {
         {
             batch.custom(token_index);
         } else {
+            // Messages waiting in the indexing queue have no term index yet
             tracing::debug!(
-                event = "error",
                 context = "email_delete",
                 account_id = account_id,
                 document_id = document_id,
-                "Failed to fetch term index.",
+                "Message has no term index.",
             );
-            return Ok(Err(SetError::not_found()));
         }
 
-        // Delete threadId
-        if let Some(thread_id) = delete_thread_id {
-            batch
-                .with_collection(Collection::Thread)
-                .delete_document(thread_id);
-        }
-
-        // Commit batch
-        match self.store.write(batch.build()).await {
-            Ok(_) => (),
-            Err(store::Error::AssertValueFailed) => {
-                return Ok(Err(SetError::forbidden().with_description(
-                    "Another process modified this message, please try again.",
-                )));
-            }
-            Err(err) => {
-                tracing::error!(
-                    event = "error",
-                    context = "email_delete",
-                    error = ?err,
-                    "Failed to commit batch.");
-                return Err(MethodError::ServerPartialFail);
-            }
-        }
-
-        // Delete blob
-        self.store
-            .delete_blob(&BlobKind::LinkedMaildir {
+        // Remove save date
+        if let Some(saved_at) = self
+            .get_property::<u64>(
                 account_id,
+                Collection::Email,
                 document_id,
-            })
-            .await
-            .map_err(|err| {
-                tracing::error!(
-                event = "error",
-                context = "email_delete",
-                error = ?err,
-                "Failed to delete blob.");
-                MethodError::ServerPartialFail
-            })?;
+                Property::SavedAt,
+            )
+            .await?
+        {
+            batch.value(Property::SavedAt, saved_at, F_VALUE | F_INDEX | F_CLEAR);
+        }
 
-        Ok(Ok(changes))
+        Ok(Ok(delete_thread_id))
     }
 }
 
@@ -0,0 +1,749 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::borrow::Cow;
+
+use jmap_proto::{
+    object::Object,
+    types::{date::UTCDate, property::Property, value::Value},
+};
+use mail_parser::{Message, MimeHeaders, PartType};
+use p256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
+use rasn::types::{Any, BitString, ObjectIdentifier, OctetString, Oid};
+use rasn_cms::{
+    CertificateChoices, ContentInfo, SignedData, SignerIdentifier, SignerInfo, CONTENT_SIGNED_DATA,
+};
+use rasn_pkix::{BasicConstraints, Certificate, Name, SubjectPublicKeyInfo, Time};
+use rsa::{pkcs1::DecodeRsaPublicKey, Pkcs1v15Sign, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+const MAX_CHAIN_LENGTH: usize = 10;
+
+// ASN.1 string tags
+const UTF8_STRING: u8 = 0x0c;
+const PRINTABLE_STRING: u8 = 0x13;
+const TELETEX_STRING: u8 = 0x14;
+const IA5_STRING: u8 = 0x16;
+const BMP_STRING: u8 = 0x1e;
+const RFC822_NAME: u8 = 0x81;
+
+// Object identifiers
+const OID_MESSAGE_DIGEST: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 9, 4]);
+const OID_EMAIL_ADDRESS: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 9, 1]);
+const OID_COMMON_NAME: &Oid = Oid::const_new(&[2, 5, 4, 3]);
+const OID_ORGANIZATION: &Oid = Oid::const_new(&[2, 5, 4, 10]);
+const OID_SUBJECT_KEY_ID: &Oid = Oid::const_new(&[2, 5, 29, 14]);
+const OID_KEY_USAGE: &Oid = Oid::const_new(&[2, 5, 29, 15]);
+const OID_SUBJECT_ALT_NAME: &Oid = Oid::const_new(&[2, 5, 29, 17]);
+const OID_BASIC_CONSTRAINTS: &Oid = Oid::const_new(&[2, 5, 29, 19]);
+const OID_EXT_KEY_USAGE: &Oid = Oid::const_new(&[2, 5, 29, 37]);
+const OID_ANY_EXT_KEY_USAGE: &Oid = Oid::const_new(&[2, 5, 29, 37, 0]);
+const OID_EMAIL_PROTECTION: &Oid = Oid::const_new(&[1, 3, 6, 1, 5, 5, 7, 3, 4]);
+const OID_SHA1: &Oid = Oid::const_new(&[1, 3, 14, 3, 2, 26]);
+const OID_SHA256: &Oid = Oid::const_new(&[2, 16, 840, 1, 101, 3, 4, 2, 1]);
+const OID_SHA384: &Oid = Oid::const_new(&[2, 16, 840, 1, 101, 3, 4, 2, 2]);
+const OID_SHA512: &Oid = Oid::const_new(&[2, 16, 840, 1, 101, 3, 4, 2, 3]);
+const OID_RSA_ENCRYPTION: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 1, 1]);
+const OID_SHA1_WITH_RSA: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 1, 5]);
+const OID_SHA256_WITH_RSA: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 1, 11]);
+const OID_SHA384_WITH_RSA: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 1, 12]);
+const OID_SHA512_WITH_RSA: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 1, 13]);
+const OID_EC_PUBLIC_KEY: &Oid = Oid::const_new(&[1, 2, 840, 10045, 2, 1]);
+const OID_PRIME256V1: &Oid = Oid::const_new(&[1, 2, 840, 10045, 3, 1, 7]);
+const OID_ECDSA_WITH_SHA1: &Oid = Oid::const_new(&[1, 2, 840, 10045, 4, 1]);
+const OID_ECDSA_WITH_SHA256: &Oid = Oid::const_new(&[1, 2, 840, 10045, 4, 3, 2]);
+const OID_ECDSA_WITH_SHA384: &Oid = Oid::const_new(&[1, 2, 840, 10045, 4, 3, 3]);
+const OID_ECDSA_WITH_SHA512: &Oid = Oid::const_new(&[1, 2, 840, 10045, 4, 3, 4]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmimeStatus {
+    Unknown,
+    Encrypted,
+    SignedVerified,
+    SignedFailed,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmimeVerification {
+    pub status: SmimeStatus,
+    pub errors: Vec<String>,
+    pub signatures: Vec<SmimeSignature>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SmimeSignature {
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub issuer: Option<String>,
+    pub not_before: Option<UTCDate>,
+    pub not_after: Option<UTCDate>,
+    pub is_verified: bool,
+}
+
+pub trait VerifySmime {
+    fn verify_smime(&self, trust_store: &[Vec<u8>], now: i64) -> Option<SmimeVerification>;
+}
+
+impl VerifySmime for Message<'_> {
+    fn verify_smime(&self, trust_store: &[Vec<u8>], now: i64) -> Option<SmimeVerification> {
+        let root = self.root_part();
+        let content_type = root.content_type()?;
+        let main_type = content_type.c_type.as_ref();
+        let sub_type = content_type
+            .c_subtype
+            .as_ref()
+            .map(|s| s.as_ref())
+            .unwrap_or_default();
+        let from = self.from().and_then(|a| a.last()).and_then(|a| a.address());
+
+        if main_type.eq_ignore_ascii_case("multipart") && sub_type.eq_ignore_ascii_case("signed") {
+            // Detached signature (RFC 8551 - Section 3.5.3)
+            let (content, signature) = match &root.body {
+                PartType::Multipart(parts) if parts.len() >= 2 => {
+                    (&self.parts[parts[0]], &self.parts[parts[1]])
+                }
+                _ => return None,
+            };
+            if !content_type
+                .attribute("protocol")
+                .map_or(false, is_pkcs7_signature)
+                && !signature.content_type().map_or(false, |ct| {
+                    ct.c_type.eq_ignore_ascii_case("application")
+                        && ct
+                            .c_subtype
+                            .as_ref()
+                            .map_or(false, |st| is_pkcs7_signature(st))
+                })
+            {
+                // Not an S/MIME signature (i.e. OpenPGP)
+                return None;
+            }
+
+            let signed_content = canonicalize(
+                self.raw_message()
+                    .get(content.raw_header_offset()..content.raw_end_offset())
+                    .unwrap_or_default(),
+            );
+            Some(verify_signed_data(
+                signature.contents(),
+                Some(signed_content.as_ref()),
+                from,
+                trust_store,
+                now,
+            ))
+        } else if main_type.eq_ignore_ascii_case("application")
+            && (sub_type.eq_ignore_ascii_case("pkcs7-mime")
+                || sub_type.eq_ignore_ascii_case("x-pkcs7-mime"))
+        {
+            // Opaque signature or encrypted message (RFC 8551 - Section 3.2)
+            match content_type
+                .attribute("smime-type")
+                .map(|t| t.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("signed-data") => Some(verify_signed_data(
+                    root.contents(),
+                    None,
+                    from,
+                    trust_store,
+                    now,
+                )),
+                Some("enveloped-data" | "authenveloped-data") => {
+                    Some(SmimeVerification::new(SmimeStatus::Encrypted))
+                }
+                _ => Some(SmimeVerification::new(SmimeStatus::Unknown)),
+            }
+        } else {
+            None
+        }
+    }
+}
+
+fn verify_signed_data(
+    bytes: &[u8],
+    detached_content: Option<&[u8]>,
+    from: Option<&str>,
+    trust_store: &[Vec<u8>],
+    now: i64,
+) -> SmimeVerification {
+    let mut result = SmimeVerification::new(SmimeStatus::SignedFailed);
+    let signed_data = if let Some(signed_data) = parse_signed_data(bytes) {
+        signed_data
+    } else {
+        result
+            .errors
+            .push("Failed to parse S/MIME signature.".to_string());
+        return result;
+    };
+    let content = if let Some(content) =
+        detached_content.or(signed_data.encap_content_info.content.as_deref())
+    {
+        content
+    } else {
+        result
+            .errors
+            .push("S/MIME signature does not include the signed content.".to_string());
+        return result;
+    };
+    if signed_data.signer_infos.is_empty() {
+        result
+            .errors
+            .push("S/MIME signature does not contain any signers.".to_string());
+        return result;
+    }
+
+    let certificates = signed_data
+        .certificates
+        .iter()
+        .flatten()
+        .filter_map(|cert| match cert {
+            CertificateChoices::Certificate(cert) => {
+                ParsedCertificate::new(Certificate::clone(cert))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let trust_anchors = trust_store
+        .iter()
+        .filter_map(|cert| ParsedCertificate::parse(cert))
+        .collect::<Vec<_>>();
+
+    for signer in &signed_data.signer_infos {
+        let cert = if let Some(cert) = certificates
+            .iter()
+            .find(|cert| signer_matches(&signer.sid, cert))
+        {
+            cert
+        } else {
+            result
+                .errors
+                .push("Signer certificate not found in S/MIME signature.".to_string());
+            continue;
+        };
+        let signer_name = cert
+            .emails
+            .first()
+            .or(cert.subject_name.as_ref())
+            .map(|name| name.as_str())
+            .unwrap_or("unknown signer");
+        let num_errors = result.errors.len();
+
+        // Verify signature
+        if let Err(err) = verify_signer(signer, content, &cert.public_key) {
+            result.errors.push(format!("{err} ({signer_name})."));
+        }
+
+        // Verify certificate validity and trust chain
+        if now < cert.not_before.timestamp() {
+            result.errors.push(format!(
+                "Signer certificate is not yet valid ({signer_name})."
+            ));
+        } else if now > cert.not_after.timestamp() {
+            result
+                .errors
+                .push(format!("Signer certificate has expired ({signer_name})."));
+        }
+        if let Err(err) = cert.verify_chain(&certificates, &trust_anchors, now) {
+            result.errors.push(format!("{err} ({signer_name})."));
+        }
+
+        // Verify that the signer matches the sender
+        if !from.map_or(false, |from| {
+            cert.emails
+                .iter()
+                .any(|email| email.eq_ignore_ascii_case(from))
+        }) {
+            result.errors.push(format!(
+                "Signer address does not match the From header ({signer_name})."
+            ));
+        }
+
+        result.signatures.push(SmimeSignature {
+            email: cert.emails.first().cloned(),
+            name: cert.subject_name.clone(),
+            issuer: cert.issuer_name.clone(),
+            not_before: cert.not_before.clone().into(),
+            not_after: cert.not_after.clone().into(),
+            is_verified: result.errors.len() == num_errors,
+        });
+    }
+
+    if result.errors.is_empty() {
+        result.status = SmimeStatus::SignedVerified;
+    }
+
+    result
+}
+
+impl SmimeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmimeStatus::Unknown => "unknown",
+            SmimeStatus::Encrypted => "encrypted",
+            SmimeStatus::SignedVerified => "signed/verified",
+            SmimeStatus::SignedFailed => "signed/failed",
+        }
+    }
+}
+
+impl SmimeVerification {
+    fn new(status: SmimeStatus) -> Self {
+        SmimeVerification {
+            status,
+            errors: Vec::new(),
+            signatures: Vec::new(),
+        }
+    }
+}
+
+impl From<SmimeSignature> for Value {
+    fn from(signature: SmimeSignature) -> Self {
+        Value::Object(
+            Object::with_capacity(6)
+                .with_property(Property::Email, signature.email)
+                .with_property(Property::Name, signature.name)
+                .with_property(Property::Issuer, signature.issuer)
+                .with_property(Property::NotBefore, signature.not_before)
+                .with_property(Property::NotAfter, signature.not_after)
+                .with_property(Property::IsVerified, signature.is_verified),
+        )
+    }
+}
+
+fn is_pkcs7_signature(sub_type: &str) -> bool {
+    let sub_type = sub_type
+        .rsplit_once('/')
+        .map(|(_, sub_type)| sub_type)
+        .unwrap_or(sub_type);
+    sub_type.eq_ignore_ascii_case("pkcs7-signature")
+        || sub_type.eq_ignore_ascii_case("x-pkcs7-signature")
+}
+
+// Signed MIME entities are hashed using CRLF line endings
+fn canonicalize(bytes: &[u8]) -> Cow<'_, [u8]> {
+    if bytes
+        .iter()
+        .enumerate()
+        .any(|(pos, &ch)| ch == b'\n' && (pos == 0 || bytes[pos - 1] != b'\r'))
+    {
+        let mut result = Vec::with_capacity(bytes.len() + 128);
+        let mut last_ch = 0;
+        for &ch in bytes {
+            if ch == b'\n' && last_ch != b'\r' {
+                result.push(b'\r');
+            }
+            result.push(ch);
+            last_ch = ch;
+        }
+        Cow::Owned(result)
+    } else {
+        Cow::Borrowed(bytes)
+    }
+}
+
+struct ParsedCertificate {
+    cert: Certificate,
+    tbs: Vec<u8>,
+    issuer_name: Option<String>,
+    subject_name: Option<String>,
+    emails: Vec<String>,
+    not_before: UTCDate,
+    not_after: UTCDate,
+    public_key: PublicKey,
+    key_id: Option<OctetString>,
+    is_ca: bool,
+    key_usage: Option<BitString>,
+    ext_key_usage: Option<Vec<ObjectIdentifier>>,
+}
+
+enum PublicKey {
+    Rsa(Vec<u8>),
+    P256(Vec<u8>),
+    Unsupported,
+}
+
+#[derive(Clone, Copy)]
+enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+fn parse_signed_data(bytes: &[u8]) -> Option<SignedData> {
+    let content_info = rasn::ber::decode::<ContentInfo>(bytes).ok()?;
+    if &*content_info.content_type == CONTENT_SIGNED_DATA {
+        rasn::ber::decode::<SignedData>(content_info.content.as_bytes()).ok()
+    } else {
+        None
+    }
+}
+
+fn verify_signer(
+    signer: &SignerInfo,
+    content: &[u8],
+    public_key: &PublicKey,
+) -> Result<(), &'static str> {
+    let digest_algorithm = DigestAlgorithm::parse(&signer.digest_algorithm.algorithm)
+        .ok_or("Unsupported S/MIME digest algorithm")?;
+
+    let signed_bytes = if let Some(signed_attrs) = &signer.signed_attrs {
+        // Make sure the content matches the signed message digest
+        let message_digest = signed_attrs
+            .iter()
+            .find(|attr| &*attr.r#type == OID_MESSAGE_DIGEST)
+            .and_then(|attr| attr.value.iter().next())
+            .and_then(|value| rasn::der::decode::<OctetString>(value.as_bytes()).ok())
+            .ok_or("S/MIME signature is missing the message digest")?;
+        if message_digest.as_ref() != digest_algorithm.digest(content).as_slice() {
+            return Err("Message content does not match the S/MIME signature");
+        }
+
+        // Signed attributes are signed using their explicit SET OF encoding
+        Cow::Owned(
+            rasn::der::encode(signed_attrs)
+                .map_err(|_| "Failed to encode S/MIME signed attributes")?,
+        )
+    } else {
+        Cow::Borrowed(content)
+    };
+
+    public_key.verify(
+        &signer.signature_algorithm.algorithm,
+        digest_algorithm.into(),
+        &signed_bytes,
+        signer.signature.as_ref(),
+    )
+}
+
+fn signer_matches(sid: &SignerIdentifier, cert: &ParsedCertificate) -> bool {
+    match sid {
+        SignerIdentifier::IssuerAndSerialNumber(sid) => {
+            sid.issuer == cert.cert.tbs_certificate.issuer
+                && sid.serial_number == cert.cert.tbs_certificate.serial_number
+        }
+        SignerIdentifier::SubjectKeyIdentifier(key_id) => cert.key_id.as_ref() == Some(key_id),
+    }
+}
+
+impl ParsedCertificate {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        rasn::der::decode::<Certificate>(bytes)
+            .ok()
+            .and_then(Self::new)
+    }
+
+    fn new(cert: Certificate) -> Option<Self> {
+        let tbs_certificate = &cert.tbs_certificate;
+        let tbs = rasn::der::encode(tbs_certificate).ok()?;
+        let issuer_name = name_attribute(&tbs_certificate.issuer, OID_COMMON_NAME)
+            .or_else(|| name_attribute(&tbs_certificate.issuer, OID_ORGANIZATION));
+        let subject_name = name_attribute(&tbs_certificate.subject, OID_COMMON_NAME);
+        let mut emails = name_attribute(&tbs_certificate.subject, OID_EMAIL_ADDRESS)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let not_before = time_to_date(&tbs_certificate.validity.not_before);
+        let not_after = time_to_date(&tbs_certificate.validity.not_after);
+        let public_key = PublicKey::parse(&tbs_certificate.subject_public_key_info);
+        let mut key_id = None;
+        let mut is_ca = false;
+        let mut key_usage = None;
+        let mut ext_key_usage = None;
+
+        for extension in tbs_certificate.extensions.iter().flatten() {
+            let extn_id: &Oid = &extension.extn_id;
+            let value = extension.extn_value.as_ref();
+
+            if extn_id == OID_SUBJECT_ALT_NAME {
+                for name in rasn::der::decode::<Vec<Any>>(value).ok()? {
+                    if let Some(email) = any_string(&name, &[RFC822_NAME]) {
+                        if !emails.iter().any(|e| e.eq_ignore_ascii_case(&email)) {
+                            emails.push(email);
+                        }
+                    }
+                }
+            } else if extn_id == OID_SUBJECT_KEY_ID {
+                key_id = rasn::der::decode::<OctetString>(value).ok()?.into();
+            } else if extn_id == OID_BASIC_CONSTRAINTS {
+                is_ca = rasn::der::decode::<BasicConstraints>(value).ok()?.ca;
+            } else if extn_id == OID_KEY_USAGE {
+                key_usage = rasn::der::decode::<BitString>(value).ok()?.into();
+            } else if extn_id == OID_EXT_KEY_USAGE {
+                ext_key_usage = rasn::der::decode::<Vec<ObjectIdentifier>>(value)
+                    .ok()?
+                    .into();
+            }
+        }
+
+        Some(ParsedCertificate {
+            cert,
+            tbs,
+            issuer_name,
+            subject_name,
+            emails,
+            not_before,
+            not_after,
+            public_key,
+            key_id,
+            is_ca,
+            key_usage,
+            ext_key_usage,
+        })
+    }
+
+    fn verify_chain(
+        &self,
+        intermediates: &[ParsedCertificate],
+        trust_anchors: &[ParsedCertificate],
+        now: i64,
+    ) -> Result<(), &'static str> {
+        if !self.allows_email_signing() {
+            return Err("Signer certificate is not valid for email protection");
+        }
+
+        let mut cert = self;
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            if trust_anchors.iter().any(|anchor| {
+                anchor.cert == cert.cert
+                    || (anchor.cert.tbs_certificate.subject == cert.cert.tbs_certificate.issuer
+                        && anchor.has_issued(cert))
+            }) {
+                return Ok(());
+            }
+
+            cert = intermediates
+                .iter()
+                .find(|issuer| {
+                    issuer.cert != cert.cert
+                        && issuer.is_ca
+                        && issuer.cert.tbs_certificate.subject == cert.cert.tbs_certificate.issuer
+                        && issuer.has_issued(cert)
+                })
+                .ok_or("Signer certificate was not issued by a trusted authority")?;
+            if now < cert.not_before.timestamp() || now > cert.not_after.timestamp() {
+                return Err("Issuer certificate is expired or not yet valid");
+            }
+        }
+
+        Err("Signer certificate chain is too long")
+    }
+
+    // RFC 8550 - Section 4.4.2 and 4.4.4
+    fn allows_email_signing(&self) -> bool {
+        self.key_usage.as_ref().map_or(true, |key_usage| {
+            // digitalSignature or nonRepudiation
+            key_usage.get(0).map_or(false, |bit| *bit) || key_usage.get(1).map_or(false, |bit| *bit)
+        }) && self.ext_key_usage.as_ref().map_or(true, |ext_key_usage| {
+            ext_key_usage.iter().any(|usage| {
+                let usage: &Oid = usage;
+                usage == OID_EMAIL_PROTECTION || usage == OID_ANY_EXT_KEY_USAGE
+            })
+        })
+    }
+
+    fn has_issued(&self, cert: &ParsedCertificate) -> bool {
+        self.public_key
+            .verify(
+                &cert.cert.signature_algorithm.algorithm,
+                None,
+                &cert.tbs,
+                cert.cert.signature_value.as_raw_slice(),
+            )
+            .is_ok()
+    }
+}
+
+impl PublicKey {
+    fn parse(spki: &SubjectPublicKeyInfo) -> Self {
+        let algorithm: &Oid = &spki.algorithm.algorithm;
+        let key = spki.subject_public_key.as_raw_slice().to_vec();
+
+        if algorithm == OID_RSA_ENCRYPTION {
+            PublicKey::Rsa(key)
+        } else if algorithm == OID_EC_PUBLIC_KEY
+            && spki
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|curve| rasn::der::decode::<ObjectIdentifier>(curve.as_bytes()).ok())
+                .map_or(false, |curve| &*curve == OID_PRIME256V1)
+        {
+            PublicKey::P256(key)
+        } else {
+            PublicKey::Unsupported
+        }
+    }
+
+    fn verify(
+        &self,
+        signature_algorithm: &ObjectIdentifier,
+        digest_algorithm: Option<DigestAlgorithm>,
+        data: &[u8],
+        signature: &[u8],
+    ) -> Result<(), &'static str> {
+        let (is_rsa, digest_algorithm) = [
+            (OID_RSA_ENCRYPTION, true, digest_algorithm),
+            (OID_SHA1_WITH_RSA, true, DigestAlgorithm::Sha1.into()),
+            (OID_SHA256_WITH_RSA, true, DigestAlgorithm::Sha256.into()),
+            (OID_SHA384_WITH_RSA, true, DigestAlgorithm::Sha384.into()),
+            (OID_SHA512_WITH_RSA, true, DigestAlgorithm::Sha512.into()),
+            (OID_EC_PUBLIC_KEY, false, digest_algorithm),
+            (OID_ECDSA_WITH_SHA1, false, DigestAlgorithm::Sha1.into()),
+            (OID_ECDSA_WITH_SHA256, false, DigestAlgorithm::Sha256.into()),
+            (OID_ECDSA_WITH_SHA384, false, DigestAlgorithm::Sha384.into()),
+            (OID_ECDSA_WITH_SHA512, false, DigestAlgorithm::Sha512.into()),
+        ]
+        .into_iter()
+        .find(|(oid, _, _)| &**signature_algorithm == *oid)
+        .map_or((false, None), |(_, is_rsa, digest_algorithm)| {
+            (is_rsa, digest_algorithm)
+        });
+        let digest_algorithm = digest_algorithm.ok_or("Unsupported S/MIME signature algorithm")?;
+        let digest = digest_algorithm.digest(data);
+
+        let is_valid = match self {
+            PublicKey::Rsa(key) if is_rsa => {
+                let mut digest_info = digest_algorithm.digest_info_prefix().to_vec();
+                digest_info.extend_from_slice(&digest);
+                RsaPublicKey::from_pkcs1_der(key).map_or(false, |key| {
+                    key.verify(Pkcs1v15Sign::new_unprefixed(), &digest_info, signature)
+                        .is_ok()
+                })
+            }
+            PublicKey::P256(key) if !is_rsa => VerifyingKey::from_sec1_bytes(key)
+                .ok()
+                .zip(Signature::from_der(signature).ok())
+                .map_or(false, |(key, signature)| {
+                    key.verify_prehash(&digest, &signature).is_ok()
+                }),
+            _ => return Err("Unsupported S/MIME signature algorithm"),
+        };
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err("S/MIME signature verification failed")
+        }
+    }
+}
+
+impl DigestAlgorithm {
+    fn parse(oid: &ObjectIdentifier) -> Option<Self> {
+        let oid: &Oid = oid;
+        if oid == OID_SHA1 {
+            Some(DigestAlgorithm::Sha1)
+        } else if oid == OID_SHA256 {
+            Some(DigestAlgorithm::Sha256)
+        } else if oid == OID_SHA384 {
+            Some(DigestAlgorithm::Sha384)
+        } else if oid == OID_SHA512 {
+            Some(DigestAlgorithm::Sha512)
+        } else {
+            None
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha1 => Sha1::digest(data).to_vec(),
+            DigestAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            DigestAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+            DigestAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    // DER encoded DigestInfo header used by PKCS#1 v1.5 signatures
+    fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            DigestAlgorithm::Sha1 => &[
+                0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04,
+                0x14,
+            ],
+            DigestAlgorithm::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x01, 0x05, 0x00, 0x04, 0x20,
+            ],
+            DigestAlgorithm::Sha384 => &[
+                0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x02, 0x05, 0x00, 0x04, 0x30,
+            ],
+            DigestAlgorithm::Sha512 => &[
+                0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x03, 0x05, 0x00, 0x04, 0x40,
+            ],
+        }
+    }
+}
+
+fn time_to_date(time: &Time) -> UTCDate {
+    UTCDate::from_timestamp(match time {
+        Time::Utc(time) => time.timestamp(),
+        Time::General(time) => time.timestamp(),
+    })
+}
+
+fn name_attribute(name: &Name, oid: &Oid) -> Option<String> {
+    let Name::RdnSequence(rdns) = name;
+    rdns.iter().flatten().find_map(|attr| {
+        if &*attr.r#type == oid {
+            any_string(
+                &attr.value,
+                &[
+                    UTF8_STRING,
+                    PRINTABLE_STRING,
+                    TELETEX_STRING,
+                    IA5_STRING,
+                    BMP_STRING,
+                ],
+            )
+        } else {
+            None
+        }
+    })
+}
+
+// Directory strings and general names are kept by rasn as raw DER values
+fn any_string(value: &Any, tags: &[u8]) -> Option<String> {
+    let bytes = value.as_bytes();
+    let tag = *bytes.first()?;
+    let header_len = match *bytes.get(1)? {
+        len if len < 0x80 => 2,
+        len => 2 + (len & 0x7f) as usize,
+    };
+    let contents = bytes.get(header_len..)?;
+
+    if !tags.contains(&tag) {
+        None
+    } else if tag == BMP_STRING {
+        Some(String::from_utf16_lossy(
+            &contents
+                .chunks_exact(2)
+                .map(|ch| u16::from_be_bytes([ch[0], ch[1]]))
+                .collect::<Vec<_>>(),
+        ))
+    } else {
+        Some(String::from_utf8_lossy(contents).into_owned())
+    }
+}
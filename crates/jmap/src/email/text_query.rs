@@ -0,0 +1,278 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::types::property::Property;
+use nlp::language::Language;
+use store::query::{self, TextMatch};
+
+use crate::JMAP;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Any,
+    From,
+    To,
+    Cc,
+    Bcc,
+    Subject,
+    Body,
+    Attachment,
+    Filename,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Term {
+    Text { field: Field, text: String },
+    Prefix { field: Field, prefix: String },
+    HasAttachment,
+}
+
+const ADDRESS_FIELDS: [Property; 4] = [Property::From, Property::To, Property::Cc, Property::Bcc];
+const TEXT_FIELDS: [Property; 3] = [Property::Subject, Property::TextBody, Property::Attachments];
+
+impl JMAP {
+    // Parses search expressions such as 'from:john invoic* -has:attachment'
+    // into store filters. Returns None when the text contains no query syntax,
+    // in which case callers should search it as plain text.
+    pub fn parse_text_query(&self, text: &str) -> Option<Vec<query::Filter>> {
+        let mut groups: Vec<Vec<(bool, Term)>> = Vec::new();
+        let mut has_syntax = false;
+        let mut is_or = false;
+
+        for token in split_query(text) {
+            if token == "OR" {
+                is_or = !groups.is_empty();
+                has_syntax = true;
+                continue;
+            }
+
+            let (is_negated, token) = match token.strip_prefix('-') {
+                Some(token) if !token.is_empty() => {
+                    has_syntax = true;
+                    (true, token)
+                }
+                _ => (false, token.as_str()),
+            };
+            let (field, value) = match token.split_once(':') {
+                Some((name, value)) if !value.is_empty() => {
+                    match name.to_ascii_lowercase().as_str() {
+                        "from" => (Field::From, value),
+                        "to" => (Field::To, value),
+                        "cc" => (Field::Cc, value),
+                        "bcc" => (Field::Bcc, value),
+                        "subject" => (Field::Subject, value),
+                        "body" => (Field::Body, value),
+                        "attachment" => (Field::Attachment, value),
+                        "filename" | "attach" => (Field::Filename, value),
+                        "has" if value.eq_ignore_ascii_case("attachment") => {
+                            has_syntax = true;
+                            add_term(&mut groups, &mut is_or, is_negated, Term::HasAttachment);
+                            continue;
+                        }
+                        _ => (Field::Any, token),
+                    }
+                }
+                _ => (Field::Any, token),
+            };
+            if field != Field::Any {
+                has_syntax = true;
+            }
+
+            let term = if value.len() > 1 && value.starts_with('"') && value.ends_with('"') {
+                Term::Text {
+                    field,
+                    text: value.to_string(),
+                }
+            } else {
+                // Leading wildcards match whole tokens, as tokens never contain punctuation
+                let value = if value.starts_with('*') {
+                    has_syntax = true;
+                    value.trim_start_matches(|ch: char| !ch.is_alphanumeric())
+                } else {
+                    value
+                };
+                if let Some(prefix) = value.strip_suffix('*') {
+                    has_syntax = true;
+                    let prefix = prefix.trim_end_matches('*').to_lowercase();
+                    if prefix.is_empty() {
+                        continue;
+                    }
+                    Term::Prefix { field, prefix }
+                } else if !value.is_empty() {
+                    Term::Text {
+                        field,
+                        text: value.to_string(),
+                    }
+                } else {
+                    continue;
+                }
+            };
+            add_term(&mut groups, &mut is_or, is_negated, term);
+        }
+
+        if !has_syntax || groups.is_empty() {
+            return None;
+        }
+
+        let mut filters = vec![query::Filter::And];
+        for group in groups {
+            let is_or = group.len() > 1;
+            if is_or {
+                filters.push(query::Filter::Or);
+            }
+            for (is_negated, term) in group {
+                if is_negated {
+                    filters.push(query::Filter::Not);
+                }
+                self.term_filters(term, &mut filters);
+                if is_negated {
+                    filters.push(query::Filter::End);
+                }
+            }
+            if is_or {
+                filters.push(query::Filter::End);
+            }
+        }
+        filters.push(query::Filter::End);
+
+        Some(filters)
+    }
+
+    fn term_filters(&self, term: Term, filters: &mut Vec<query::Filter>) {
+        match term {
+            Term::Text { field, text } => match field {
+                Field::Any => {
+                    filters.push(query::Filter::Or);
+                    for property in ADDRESS_FIELDS {
+                        filters.push(query::Filter::has_text(property, &text, Language::None));
+                    }
+                    for property in TEXT_FIELDS {
+                        filters.push(query::Filter::has_text_detect(
+                            property,
+                            &text,
+                            self.config.default_language,
+                        ));
+                    }
+                    filters.push(query::Filter::End);
+                }
+                Field::From | Field::To | Field::Cc | Field::Bcc => {
+                    filters.push(query::Filter::has_text(
+                        field.property(),
+                        text,
+                        Language::None,
+                    ));
+                }
+                Field::Subject | Field::Body | Field::Attachment => {
+                    filters.push(query::Filter::has_text_detect(
+                        field.property(),
+                        text,
+                        self.config.default_language,
+                    ));
+                }
+                Field::Filename => {
+                    // File names are indexed without stemming
+                    let op = if text.starts_with('"') {
+                        TextMatch::Exact(Language::None)
+                    } else {
+                        TextMatch::Stemmed(Language::None)
+                    };
+                    filters.push(query::Filter::HasText {
+                        field: Property::Name.into(),
+                        text,
+                        op,
+                    });
+                }
+            },
+            Term::Prefix { field, prefix } => {
+                if field == Field::Any {
+                    filters.push(query::Filter::Or);
+                    for property in ADDRESS_FIELDS.into_iter().chain(TEXT_FIELDS) {
+                        filters.push(query::Filter::has_prefix_text(property, &prefix));
+                    }
+                    filters.push(query::Filter::End);
+                } else {
+                    filters.push(query::Filter::has_prefix_text(field.property(), prefix));
+                }
+            }
+            Term::HasAttachment => {
+                filters.push(query::Filter::is_in_bitmap(Property::HasAttachment, ()));
+            }
+        }
+    }
+}
+
+impl Field {
+    fn property(&self) -> Property {
+        match self {
+            Field::From => Property::From,
+            Field::To => Property::To,
+            Field::Cc => Property::Cc,
+            Field::Bcc => Property::Bcc,
+            Field::Subject => Property::Subject,
+            Field::Body | Field::Any => Property::TextBody,
+            Field::Attachment => Property::Attachments,
+            Field::Filename => Property::Name,
+        }
+    }
+}
+
+fn add_term(groups: &mut Vec<Vec<(bool, Term)>>, is_or: &mut bool, is_negated: bool, term: Term) {
+    match groups.last_mut() {
+        Some(group) if *is_or => {
+            group.push((is_negated, term));
+        }
+        _ => {
+            groups.push(vec![(is_negated, term)]);
+        }
+    }
+    *is_or = false;
+}
+
+// Splits a query on whitespace, keeping quoted phrases together.
+fn split_query(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_quotes = false;
+
+    for ch in text.chars() {
+        match ch {
+            '"' => {
+                in_quotes = !in_quotes;
+                token.push(ch);
+            }
+            _ if ch.is_whitespace() && !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            _ => {
+                token.push(ch);
+            }
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
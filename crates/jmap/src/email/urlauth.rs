@@ -0,0 +1,313 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use hmac::{Hmac, Mac};
+use imap_proto::protocol::{
+    fetch::{body_section, Section},
+    urlauth::{Access, ImapUrl},
+};
+use jmap_proto::{
+    error::method::MethodError,
+    types::{blob::BlobId, collection::Collection, property::Property},
+};
+use mail_parser::MessageParser;
+use rand::{thread_rng, Rng};
+use sha2::Sha256;
+use store::{
+    write::{key::KeySerializer, now, BatchBuilder, Operation, ValueClass},
+    CustomValueKey, Deserialize,
+};
+
+use crate::JMAP;
+
+const URLAUTH_KEY_LEN: usize = 32;
+const URLAUTH_MAC_LEN: usize = 16;
+const URLAUTH_TOKEN_LEN: usize = 3 * std::mem::size_of::<u32>() + URLAUTH_MAC_LEN;
+
+#[derive(Debug, Clone, Copy)]
+pub enum UrlRequester<'x> {
+    Imap(&'x str),
+    Submission(&'x str),
+}
+
+struct UrlAuthKey(Vec<u8>);
+
+impl JMAP {
+    pub async fn urlauth_sign(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        document_id: u32,
+        rump: &str,
+    ) -> Result<String, MethodError> {
+        let key = self.urlauth_key(account_id, mailbox_id).await?;
+        let mut token = Vec::with_capacity(URLAUTH_TOKEN_LEN);
+        token.extend_from_slice(&account_id.to_be_bytes());
+        token.extend_from_slice(&mailbox_id.to_be_bytes());
+        token.extend_from_slice(&document_id.to_be_bytes());
+        let mac = urlauth_mac(&key, rump, &token).finalize().into_bytes();
+        token.extend_from_slice(&mac[..URLAUTH_MAC_LEN]);
+
+        Ok(token
+            .iter()
+            .fold(String::with_capacity(URLAUTH_TOKEN_LEN * 2), |mut s, b| {
+                use std::fmt::Write;
+                let _ = write!(s, "{b:02x}");
+                s
+            }))
+    }
+
+    pub async fn urlauth_reset_keys(
+        &self,
+        account_id: u32,
+        mailbox_id: Option<u32>,
+    ) -> Result<(), MethodError> {
+        let mailbox_ids = if let Some(mailbox_id) = mailbox_id {
+            vec![mailbox_id]
+        } else {
+            self.get_document_ids(account_id, Collection::Mailbox)
+                .await?
+                .unwrap_or_default()
+                .into_iter()
+                .collect()
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox);
+        for mailbox_id in mailbox_ids {
+            batch.op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: urlauth_key_id(account_id, mailbox_id),
+                },
+                set: thread_rng().gen::<[u8; URLAUTH_KEY_LEN]>().to_vec().into(),
+            });
+        }
+
+        if !batch.is_empty() {
+            self.write_batch(batch).await
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn urlauth_fetch(
+        &self,
+        url: &ImapUrl,
+        requester: UrlRequester<'_>,
+    ) -> Result<Option<Vec<u8>>, MethodError> {
+        // Validate access identifier and expiration
+        let (access, token) = match (&url.access, &url.token) {
+            (Some(access), Some(token)) => (access, token),
+            _ => return Ok(None),
+        };
+        if !requester.has_access(access) || url.expire.map_or(false, |expire| expire < now() as i64)
+        {
+            return Ok(None);
+        }
+
+        // Decode token
+        let token = match decode_hex(token) {
+            Some(token) if token.len() == URLAUTH_TOKEN_LEN => token,
+            _ => return Ok(None),
+        };
+        let account_id = u32::from_be_bytes(token[0..4].try_into().unwrap());
+        let mailbox_id = u32::from_be_bytes(token[4..8].try_into().unwrap());
+        let document_id = u32::from_be_bytes(token[8..12].try_into().unwrap());
+
+        // Make sure the URL was issued by the mailbox owner
+        if self.get_account_name(account_id).await? != url.user {
+            return Ok(None);
+        }
+
+        // Verify MAC
+        let key = if let Some(key) = self
+            .store
+            .get_value::<UrlAuthKey>(CustomValueKey {
+                value: urlauth_key_id(account_id, mailbox_id),
+            })
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                            context = "store",
+                            account_id = account_id,
+                            error = ?err,
+                            "Failed to retrieve URLAUTH key");
+                MethodError::ServerPartialFail
+            })? {
+            key.0
+        } else {
+            return Ok(None);
+        };
+        if urlauth_mac(&key, &url.rump, &token[..12])
+            .verify_truncated_left(&token[12..])
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        // Make sure the message is still in the mailbox
+        if !self
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::MailboxIds,
+                mailbox_id,
+            )
+            .await?
+            .map_or(false, |ids| ids.contains(document_id))
+        {
+            return Ok(None);
+        }
+
+        self.get_message_section(account_id, document_id, &url.sections, url.partial)
+            .await
+    }
+
+    pub async fn fetch_submission_url(&self, url: &str, submitter: &str) -> Option<Vec<u8>> {
+        let url = ImapUrl::parse(url).filter(|url| !url.is_relative())?;
+        self.urlauth_fetch(&url, UrlRequester::Submission(submitter))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    pub async fn get_message_section(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        sections: &[Section],
+        partial: Option<(u32, u32)>,
+    ) -> Result<Option<Vec<u8>>, MethodError> {
+        Ok(self
+            .get_blob(&BlobId::maildir(account_id, document_id).kind, 0..u32::MAX)
+            .await?
+            .and_then(|raw_message| {
+                MessageParser::new()
+                    .parse(&raw_message)
+                    .and_then(|message| {
+                        body_section(&message, sections, partial).map(|bytes| bytes.into_owned())
+                    })
+            }))
+    }
+
+    async fn urlauth_key(&self, account_id: u32, mailbox_id: u32) -> Result<Vec<u8>, MethodError> {
+        let key_id = urlauth_key_id(account_id, mailbox_id);
+        let mut try_count = 0;
+
+        loop {
+            if let Some(key) = self
+                .store
+                .get_value::<UrlAuthKey>(CustomValueKey {
+                    value: key_id.clone(),
+                })
+                .await
+                .map_err(|err| {
+                    tracing::error!(event = "error",
+                                context = "store",
+                                account_id = account_id,
+                                error = ?err,
+                                "Failed to retrieve URLAUTH key");
+                    MethodError::ServerPartialFail
+                })?
+            {
+                return Ok(key.0);
+            }
+
+            // Generate a new key for this mailbox
+            let key = thread_rng().gen::<[u8; URLAUTH_KEY_LEN]>().to_vec();
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Mailbox)
+                .assert_value(
+                    ValueClass::Custom {
+                        bytes: key_id.clone(),
+                    },
+                    (),
+                )
+                .op(Operation::Value {
+                    class: ValueClass::Custom {
+                        bytes: key_id.clone(),
+                    },
+                    set: key.clone().into(),
+                });
+
+            match self.store.write(batch.build()).await {
+                Ok(_) => return Ok(key),
+                Err(store::Error::AssertValueFailed) if try_count < 3 => {
+                    try_count += 1;
+                }
+                Err(err) => {
+                    tracing::error!(event = "error",
+                                    context = "store",
+                                    account_id = account_id,
+                                    error = ?err,
+                                    "Failed to write URLAUTH key");
+                    return Err(MethodError::ServerPartialFail);
+                }
+            }
+        }
+    }
+}
+
+impl UrlRequester<'_> {
+    pub fn has_access(&self, access: &Access) -> bool {
+        match (access, self) {
+            (Access::Anonymous, _) | (Access::AuthUser, UrlRequester::Imap(_)) => true,
+            (Access::User(user), UrlRequester::Imap(name))
+            | (Access::Submit(user), UrlRequester::Submission(name)) => user == name,
+            _ => false,
+        }
+    }
+}
+
+impl Deserialize for UrlAuthKey {
+    fn deserialize(bytes: &[u8]) -> store::Result<Self> {
+        Ok(UrlAuthKey(bytes.to_vec()))
+    }
+}
+
+fn urlauth_key_id(account_id: u32, mailbox_id: u32) -> Vec<u8> {
+    KeySerializer::new(std::mem::size_of::<u32>() * 3 + 1)
+        .write(u32::MAX)
+        .write(2u8)
+        .write(account_id)
+        .write(mailbox_id)
+        .finalize()
+}
+
+fn urlauth_mac(key: &[u8], rump: &str, ids: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(rump.as_bytes());
+    mac.update(ids);
+    mac
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    (0..value.len())
+        .step_by(2)
+        .map(|pos| u8::from_str_radix(value.get(pos..pos + 2)?, 16).ok())
+        .collect()
+}
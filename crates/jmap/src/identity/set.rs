@@ -33,6 +33,7 @@ use jmap_proto::{
     },
 };
 use store::write::{log::ChangeLogBuilder, BatchBuilder, F_CLEAR, F_VALUE};
+use utils::ipc::SendPermission;
 
 use crate::{auth::AccessToken, JMAP};
 
@@ -79,12 +80,10 @@ impl JMAP {
                     self.get_account_name(account_id).await?.unwrap_or_default()
                 };
 
-                if !self
-                    .directory
-                    .emails_by_name(&account_name)
-                    .await
-                    .unwrap_or_default()
-                    .contains(email)
+                if self
+                    .sender_permission(account_id, &account_name, email)
+                    .await?
+                    == SendPermission::Denied
                 {
                     response.not_created.append(
                         id,
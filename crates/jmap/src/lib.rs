@@ -44,10 +44,13 @@ use nlp::language::Language;
 use services::{
     delivery::spawn_delivery_manager,
     housekeeper::{self, init_housekeeper, spawn_housekeeper},
+    index::{self, init_index_manager, spawn_index_manager, IndexStats},
     state::{self, init_state_manager, spawn_state_manager},
 };
 use smtp::core::SMTP;
 use store::{
+    ahash::AHashMap,
+    fts::extract::ExtractLimits,
     parking_lot::Mutex,
     query::{sort::Pagination, Comparator, Filter, ResultSet, SortedResultSet},
     roaring::RoaringBitmap,
@@ -57,9 +60,9 @@ use store::{
 use tokio::sync::mpsc;
 use utils::{
     config::Rate,
-    ipc::DeliveryEvent,
+    ipc::{DeliveryEvent, ReloadEvent},
     map::ttl_dashmap::{TtlDashMap, TtlMap},
-    UnwrapFailure,
+    reload::Reloadable,
 };
 
 pub mod api;
@@ -69,9 +72,11 @@ pub mod changes;
 pub mod email;
 pub mod identity;
 pub mod mailbox;
+pub mod mdn;
 pub mod principal;
 pub mod push;
 pub mod quota;
+pub mod rules;
 pub mod services;
 pub mod sieve;
 pub mod submission;
@@ -82,21 +87,24 @@ pub mod websocket;
 pub const LONG_SLUMBER: Duration = Duration::from_secs(60 * 60 * 24);
 
 pub struct JMAP {
-    pub store: Store,
+    pub store: Arc<Store>,
     pub config: Config,
     pub directory: Arc<dyn Directory>,
 
-    pub sessions: TtlDashMap<String, u32>,
-    pub access_tokens: TtlDashMap<u32, Arc<AccessToken>>,
+    pub sessions: Arc<TtlDashMap<String, u32>>,
+    pub access_tokens: Arc<TtlDashMap<u32, Arc<AccessToken>>>,
 
-    pub rate_limit_auth: DashMap<u32, Arc<Mutex<AuthenticatedLimiter>>>,
-    pub rate_limit_unauth: DashMap<RemoteAddress, Arc<Mutex<AnonymousLimiter>>>,
+    pub rate_limit_auth: Arc<DashMap<u32, Arc<Mutex<AuthenticatedLimiter>>>>,
+    pub rate_limit_unauth: Arc<DashMap<RemoteAddress, Arc<Mutex<AnonymousLimiter>>>>,
 
-    pub oauth_codes: TtlDashMap<String, Arc<OAuthCode>>,
+    pub oauth_codes: Arc<TtlDashMap<String, Arc<OAuthCode>>>,
 
     pub state_tx: mpsc::Sender<state::Event>,
     pub housekeeper_tx: mpsc::Sender<housekeeper::Event>,
+    pub fts_index_tx: mpsc::Sender<index::Event>,
+    pub fts_index_stats: Arc<IndexStats>,
     pub smtp: Arc<SMTP>,
+    pub reload_tx: mpsc::Sender<ReloadEvent>,
 
     pub sieve_compiler: Compiler,
     pub sieve_runtime: Runtime<()>,
@@ -104,6 +112,11 @@ pub struct JMAP {
 
 pub struct Config {
     pub default_language: Language,
+    pub fts_extract: ExtractLimits,
+    pub fts_index_background: bool,
+    pub fts_index_workers: usize,
+    pub fts_index_max_pending: usize,
+    pub fts_index_poll_interval: Duration,
     pub query_max_results: usize,
     pub changes_max_results: usize,
 
@@ -123,6 +136,7 @@ pub struct Config {
 
     pub mailbox_max_depth: usize,
     pub mailbox_name_max_len: usize,
+    pub mailbox_retention: AHashMap<String, u64>,
     pub mail_attachments_max_size: usize,
     pub mail_parse_max_items: usize,
     pub mail_max_size: usize,
@@ -153,9 +167,15 @@ pub struct Config {
 
     pub http_headers: Vec<(hyper::header::HeaderName, hyper::header::HeaderValue)>,
 
+    pub metrics_enable: bool,
+    pub metrics_auth: Option<(String, String)>,
+    pub metrics_public: bool,
+
     pub encrypt: bool,
     pub encrypt_append: bool,
 
+    pub smime_trust_store: Vec<Vec<u8>>,
+
     pub principal_allow_lookups: bool,
 
     pub capabilities: BaseCapabilities,
@@ -175,57 +195,135 @@ impl JMAP {
     pub async fn init(
         config: &utils::config::Config,
         directory_config: &DirectoryConfig,
+        store: Arc<Store>,
         delivery_rx: mpsc::Receiver<DeliveryEvent>,
+        reload_tx: mpsc::Sender<ReloadEvent>,
         smtp: Arc<SMTP>,
-    ) -> Result<Arc<Self>, String> {
+    ) -> Result<Reloadable<Self>, String> {
         // Init state manager and housekeeper
         let (state_tx, state_rx) = init_state_manager();
         let (housekeeper_tx, housekeeper_rx) = init_housekeeper();
+        let (fts_index_tx, fts_index_rx) = init_index_manager();
+        let core = Reloadable::new(JMAP::build(
+            config,
+            directory_config,
+            store,
+            state_tx,
+            housekeeper_tx,
+            fts_index_tx,
+            Default::default(),
+            reload_tx,
+            smtp,
+        )?);
+
+        // Spawn delivery manager
+        spawn_delivery_manager(core.clone(), delivery_rx);
+
+        // Spawn state manager
+        spawn_state_manager(core.clone(), config, state_rx);
+
+        // Spawn housekeeper
+        spawn_housekeeper(core.clone(), config, housekeeper_rx);
+
+        // Spawn full-text index manager
+        spawn_index_manager(core.clone(), fts_index_rx);
+
+        Ok(core)
+    }
+
+    /// Builds a new core from an updated configuration, sharing the store, the
+    /// background service channels and the session, access token, rate limiter
+    /// and OAuth code caches with the running core.
+    pub fn reload(
+        &self,
+        config: &utils::config::Config,
+        directory_config: &DirectoryConfig,
+        smtp: Arc<SMTP>,
+    ) -> Result<Self, String> {
+        let mut core = JMAP::build(
+            config,
+            directory_config,
+            self.store.clone(),
+            self.state_tx.clone(),
+            self.housekeeper_tx.clone(),
+            self.fts_index_tx.clone(),
+            self.fts_index_stats.clone(),
+            self.reload_tx.clone(),
+            smtp,
+        )?;
+        core.sessions = self.sessions.clone();
+        core.access_tokens = self.access_tokens.clone();
+        core.rate_limit_auth = self.rate_limit_auth.clone();
+        core.rate_limit_unauth = self.rate_limit_unauth.clone();
+        core.oauth_codes = self.oauth_codes.clone();
+
+        // Keep issued OAuth tokens valid when the signing key is auto-generated
+        if config.value("oauth.key").is_none() {
+            core.config.oauth_key = self.config.oauth_key.clone();
+        }
+
+        Ok(core)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        config: &utils::config::Config,
+        directory_config: &DirectoryConfig,
+        store: Arc<Store>,
+        state_tx: mpsc::Sender<state::Event>,
+        housekeeper_tx: mpsc::Sender<housekeeper::Event>,
+        fts_index_tx: mpsc::Sender<index::Event>,
+        fts_index_stats: Arc<IndexStats>,
+        reload_tx: mpsc::Sender<ReloadEvent>,
+        smtp: Arc<SMTP>,
+    ) -> Result<Self, String> {
         let shard_amount = config
             .property::<u64>("global.shared-map.shard")?
             .unwrap_or(32)
             .next_power_of_two() as usize;
 
-        let jmap_server = Arc::new(JMAP {
+        let directory = config.value_require("jmap.directory")?;
+
+        Ok(JMAP {
             directory: directory_config
                 .directories
-                .get(config.value_require("jmap.directory")?)
-                .failed(&format!(
-                    "Unable to find directory '{}'",
-                    config.value_require("jmap.directory")?
-                ))
+                .get(directory)
+                .ok_or_else(|| format!("Unable to find directory '{directory}'"))?
                 .clone(),
-            store: Store::open(config).await.failed("Unable to open database"),
-            config: Config::new(config).failed("Invalid configuration file"),
-            sessions: TtlDashMap::with_capacity(
+            store,
+            config: Config::new(config)?,
+            sessions: Arc::new(TtlDashMap::with_capacity(
                 config.property("jmap.session.cache.size")?.unwrap_or(100),
                 shard_amount,
-            ),
-            access_tokens: TtlDashMap::with_capacity(
+            )),
+            access_tokens: Arc::new(TtlDashMap::with_capacity(
                 config.property("jmap.session.cache.size")?.unwrap_or(100),
                 shard_amount,
-            ),
-            rate_limit_auth: DashMap::with_capacity_and_hasher_and_shard_amount(
+            )),
+            rate_limit_auth: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                 config
                     .property("jmap.rate-limit.cache.size")?
                     .unwrap_or(1024),
                 RandomState::default(),
                 shard_amount,
-            ),
-            rate_limit_unauth: DashMap::with_capacity_and_hasher_and_shard_amount(
+            )),
+            rate_limit_unauth: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                 config
                     .property("jmap.rate-limit.cache.size")?
                     .unwrap_or(1024),
                 RandomState::default(),
                 shard_amount,
-            ),
-            oauth_codes: TtlDashMap::with_capacity(
+            )),
+            oauth_codes: Arc::new(TtlDashMap::with_capacity(
                 config.property("oauth.cache.size")?.unwrap_or(128),
                 shard_amount,
-            ),
+            )),
             state_tx,
             housekeeper_tx,
+            fts_index_tx,
+            fts_index_stats,
             smtp,
+            reload_tx,
             sieve_compiler: Compiler::new()
                 .with_max_script_size(
                     config
@@ -373,18 +471,7 @@ impl JMAP {
                 .with_env_variable("version", env!("CARGO_PKG_VERSION"))
                 .with_env_variable("location", "MS")
                 .with_env_variable("phase", "during"),
-        });
-
-        // Spawn delivery manager
-        spawn_delivery_manager(jmap_server.clone(), delivery_rx);
-
-        // Spawn state manager
-        spawn_state_manager(jmap_server.clone(), config, state_rx);
-
-        // Spawn housekeeper
-        spawn_housekeeper(jmap_server.clone(), config, housekeeper_rx);
-
-        Ok(jmap_server)
+        })
     }
 
     pub async fn assign_document_id(
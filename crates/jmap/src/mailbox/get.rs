@@ -53,6 +53,7 @@ impl JMAP {
             Property::TotalThreads,
             Property::UnreadThreads,
             Property::MyRights,
+            Property::Retention,
         ]);
         let account_id = request.account_id.document_id();
         let mut mailbox_ids = self.mailbox_get_or_create(account_id).await?;
@@ -62,6 +63,7 @@ impl JMAP {
                 .await?;
         }
         let message_ids = self.get_document_ids(account_id, Collection::Email).await?;
+        let shared_seen = self.shared_seen_id(access_token, account_id);
         let ids = if let Some(ids) = ids {
             ids
         } else {
@@ -78,6 +80,7 @@ impl JMAP {
                     | Property::ParentId
                     | Property::Role
                     | Property::SortOrder
+                    | Property::Retention
                     | Property::Acl
                     | Property::MyRights
             )
@@ -125,7 +128,9 @@ impl JMAP {
             for property in &properties {
                 let value = match property {
                     Property::Id => Value::Id(id),
-                    Property::Name | Property::Role => values.remove(property),
+                    Property::Name | Property::Role | Property::Retention => {
+                        values.remove(property)
+                    }
                     Property::SortOrder => values
                         .properties
                         .remove(property)
@@ -152,10 +157,15 @@ impl JMAP {
                         .unwrap_or(0),
                     ),
                     Property::UnreadEmails => Value::UnsignedInt(
-                        self.mailbox_unread_tags(account_id, document_id, &message_ids)
-                            .await?
-                            .map(|v| v.len())
-                            .unwrap_or(0),
+                        self.mailbox_unread_tags(
+                            account_id,
+                            document_id,
+                            &message_ids,
+                            shared_seen,
+                        )
+                        .await?
+                        .map(|v| v.len())
+                        .unwrap_or(0),
                     ),
                     Property::TotalThreads => Value::UnsignedInt(
                         self.mailbox_count_threads(
@@ -173,8 +183,13 @@ impl JMAP {
                     Property::UnreadThreads => Value::UnsignedInt(
                         self.mailbox_count_threads(
                             account_id,
-                            self.mailbox_unread_tags(account_id, document_id, &message_ids)
-                                .await?,
+                            self.mailbox_unread_tags(
+                                account_id,
+                                document_id,
+                                &message_ids,
+                                shared_seen,
+                            )
+                            .await?,
                         )
                         .await? as u64,
                     ),
@@ -284,6 +299,7 @@ impl JMAP {
         account_id: u32,
         document_id: u32,
         message_ids: &Option<RoaringBitmap>,
+        shared_seen: Option<u32>,
     ) -> Result<Option<RoaringBitmap>, MethodError> {
         if let (Some(message_ids), Some(mailbox_message_ids)) = (
             message_ids,
@@ -296,12 +312,7 @@ impl JMAP {
             .await?,
         ) {
             if let Some(mut seen) = self
-                .get_tag(
-                    account_id,
-                    Collection::Email,
-                    Property::Keywords,
-                    Keyword::Seen,
-                )
+                .get_keyword_tag(account_id, shared_seen, Keyword::Seen)
                 .await?
             {
                 seen ^= message_ids;
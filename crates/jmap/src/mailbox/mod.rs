@@ -23,6 +23,7 @@
 
 pub mod get;
 pub mod query;
+pub mod retention;
 pub mod set;
 
 pub const INBOX_ID: u32 = 0;
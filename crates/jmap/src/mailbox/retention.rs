@@ -0,0 +1,276 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    object::Object,
+    types::{
+        collection::Collection, id::Id, property::Property, state::StateChange,
+        type_state::DataType, value::Value,
+    },
+};
+use store::{
+    query::Filter,
+    write::{
+        assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder, F_BITMAP, F_CLEAR, F_VALUE,
+    },
+};
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn purge_expired_messages(&self, account_id: Option<u32>) -> usize {
+        let account_ids = if let Some(account_id) = account_id {
+            vec![account_id]
+        } else {
+            match self.get_document_ids(u32::MAX, Collection::Principal).await {
+                Ok(account_ids) => account_ids.unwrap_or_default().into_iter().collect(),
+                Err(_) => return 0,
+            }
+        };
+
+        let mut num_expunged = 0;
+        for account_id in account_ids {
+            match self.mailbox_expunge_expired(account_id).await {
+                Ok(0) => (),
+                Ok(num_messages) => {
+                    tracing::debug!(
+                        context = "retention",
+                        event = "expunge",
+                        account_id = account_id,
+                        num_messages = num_messages,
+                        "Expunged messages past their retention period."
+                    );
+                    num_expunged += num_messages;
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        context = "retention",
+                        event = "error",
+                        account_id = account_id,
+                        "Failed to expunge messages past their retention period."
+                    );
+                }
+            }
+        }
+
+        num_expunged
+    }
+
+    pub async fn mailbox_expunge_expired(&self, account_id: u32) -> Result<usize, MethodError> {
+        let mailbox_ids = if let Some(mailbox_ids) = self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await?
+        {
+            mailbox_ids
+        } else {
+            return Ok(0);
+        };
+        let now = now();
+        let mut changes = ChangeLogBuilder::new();
+        let mut num_expunged = 0;
+        let mut shared_seen_users = None;
+
+        for mailbox_id in mailbox_ids {
+            // A retention period set on the mailbox overrides the default for its role,
+            // a period of zero days keeps messages forever.
+            let mut mailbox = if let Some(mailbox) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    &Property::Value,
+                )
+                .await?
+            {
+                mailbox
+            } else {
+                continue;
+            };
+            let retention = match mailbox.remove(&Property::Retention) {
+                Value::UnsignedInt(days) => days.saturating_mul(86400),
+                _ => match mailbox.get(&Property::Role) {
+                    Value::Text(role) => self
+                        .config
+                        .mailbox_retention
+                        .get(role)
+                        .copied()
+                        .unwrap_or(0),
+                    _ => 0,
+                },
+            };
+            if retention == 0 || retention >= now {
+                continue;
+            }
+
+            let message_ids = self
+                .filter(
+                    account_id,
+                    Collection::Email,
+                    vec![
+                        Filter::is_in_bitmap(Property::MailboxIds, mailbox_id),
+                        Filter::lt(Property::ReceivedAt, now - retention),
+                    ],
+                )
+                .await?
+                .results;
+            if message_ids.is_empty() {
+                continue;
+            }
+            if shared_seen_users.is_none() {
+                shared_seen_users = Some(self.shared_seen_users(account_id).await?);
+            }
+
+            for message_id in message_ids {
+                if self
+                    .email_expunge(
+                        account_id,
+                        mailbox_id,
+                        message_id,
+                        shared_seen_users.as_deref().unwrap_or_default(),
+                        &mut changes,
+                    )
+                    .await?
+                {
+                    num_expunged += 1;
+                }
+            }
+        }
+
+        // Commit changes and notify IMAP and JMAP clients
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(account_id)
+                    .with_change(DataType::Email, change_id)
+                    .with_change(DataType::Mailbox, change_id)
+                    .with_change(DataType::Thread, change_id),
+            )
+            .await;
+        }
+
+        Ok(num_expunged)
+    }
+
+    // Removes a message from a mailbox. Messages that do not belong to any other
+    // mailbox are destroyed, otherwise they are only untagged from this mailbox.
+    async fn email_expunge(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        message_id: u32,
+        shared_seen_users: &[u32],
+        changes: &mut ChangeLogBuilder,
+    ) -> Result<bool, MethodError> {
+        let mailbox_ids = if let Some(mailbox_ids) = self
+            .get_property::<HashedValue<Vec<u32>>>(
+                account_id,
+                Collection::Email,
+                message_id,
+                Property::MailboxIds,
+            )
+            .await?
+            .and_then(|mut ids| {
+                let idx = ids.inner.iter().position(|&id| id == mailbox_id)?;
+                ids.inner.swap_remove(idx);
+                Some(ids)
+            }) {
+            mailbox_ids
+        } else {
+            return Ok(false);
+        };
+
+        if mailbox_ids.inner.is_empty() {
+            return match self
+                .email_delete(account_id, message_id, shared_seen_users)
+                .await?
+            {
+                Ok(change) => {
+                    changes.merge(change);
+                    Ok(true)
+                }
+                Err(err) => {
+                    tracing::debug!(
+                        context = "retention",
+                        event = "error",
+                        account_id = account_id,
+                        message_id = message_id,
+                        reason = ?err,
+                        "Failed to delete expired message."
+                    );
+                    Ok(false)
+                }
+            };
+        }
+
+        let thread_id = if let Some(thread_id) = self
+            .get_property::<u32>(
+                account_id,
+                Collection::Email,
+                message_id,
+                Property::ThreadId,
+            )
+            .await?
+        {
+            thread_id
+        } else {
+            return Ok(false);
+        };
+
+        // Untag message from mailbox
+        if changes.change_id == u64::MAX {
+            changes.change_id = self.assign_change_id(account_id).await?;
+        }
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Email)
+            .update_document(message_id)
+            .assert_value(Property::MailboxIds, &mailbox_ids)
+            .value(Property::MailboxIds, mailbox_ids.inner, F_VALUE)
+            .value(Property::MailboxIds, mailbox_id, F_BITMAP | F_CLEAR)
+            .value(Property::Cid, changes.change_id, F_VALUE);
+        match self.store.write(batch.build()).await {
+            Ok(_) => {
+                changes.log_update(Collection::Email, Id::from_parts(thread_id, message_id));
+                changes.log_child_update(Collection::Mailbox, mailbox_id);
+                Ok(true)
+            }
+            Err(store::Error::AssertValueFailed) => {
+                // The message was modified concurrently, try again on the next run
+                Ok(false)
+            }
+            Err(err) => {
+                tracing::error!(
+                    event = "error",
+                    context = "retention",
+                    account_id = account_id,
+                    mailbox_id = mailbox_id,
+                    message_id = message_id,
+                    error = ?err,
+                    "Failed to untag expired message from mailbox.");
+                Err(MethodError::ServerPartialFail)
+            }
+        }
+    }
+}
@@ -307,6 +307,7 @@ impl JMAP {
 
                 // If the message is in multiple mailboxes, untag it from the current mailbox,
                 // otherwise delete it.
+                let shared_seen_users = self.shared_seen_users(account_id).await?;
                 for message_id in message_ids {
                     // Obtain mailboxIds
                     if let Some(mailbox_ids) = self
@@ -380,8 +381,9 @@ impl JMAP {
                             }
                         } else {
                             // Delete message
-                            if let Ok(mut change) =
-                                self.email_delete(account_id, message_id).await?
+                            if let Ok(mut change) = self
+                                .email_delete(account_id, message_id, &shared_seen_users)
+                                .await?
                             {
                                 change.changes.remove(&(Collection::Mailbox as u8));
                                 changes.merge(change);
@@ -544,6 +546,10 @@ impl JMAP {
                 (Property::SortOrder, MaybePatchValue::Value(Value::UnsignedInt(value))) => {
                     Value::UnsignedInt(value)
                 }
+                (Property::Retention, MaybePatchValue::Value(Value::UnsignedInt(value))) => {
+                    Value::UnsignedInt(value)
+                }
+                (Property::Retention, MaybePatchValue::Value(Value::Null)) => Value::Null,
                 (Property::Acl, value) => {
                     match self
                         .acl_set(&mut changes, update.as_ref().map(|(_, obj)| obj), value)
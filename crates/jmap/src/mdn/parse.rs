@@ -0,0 +1,260 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::mdn::{MdnParseRequest, MdnParseResponse},
+    object::Object,
+    types::{collection::Collection, id::Id, property::Property, value::Value},
+};
+use mail_parser::{MessageParser, PartType};
+use store::query::Filter;
+use utils::map::vec_map::VecMap;
+
+use crate::{auth::AccessToken, email::index::MAX_ID_LENGTH, JMAP};
+
+impl JMAP {
+    pub async fn mdn_parse(
+        &self,
+        request: MdnParseRequest,
+        access_token: &AccessToken,
+    ) -> Result<MdnParseResponse, MethodError> {
+        if request.blob_ids.len() > self.config.mail_parse_max_items {
+            return Err(MethodError::RequestTooLarge);
+        }
+        let account_id = request.account_id.document_id();
+        let mut response = MdnParseResponse {
+            account_id: request.account_id,
+            parsed: VecMap::with_capacity(request.blob_ids.len()),
+            not_parsable: vec![],
+            not_found: vec![],
+        };
+
+        for blob_id in request.blob_ids {
+            // Fetch raw message to parse
+            let raw_message = match self.blob_download(&blob_id, access_token).await? {
+                Some(raw_message) => raw_message,
+                None => {
+                    response.not_found.push(blob_id);
+                    continue;
+                }
+            };
+            let message = if let Some(message) = MessageParser::new().parse(&raw_message) {
+                message
+            } else {
+                response.not_parsable.push(blob_id);
+                continue;
+            };
+
+            // Find the disposition notification part
+            let fields = if let Some(part) = message
+                .parts
+                .iter()
+                .find(|part| part.is_content_type("message", "disposition-notification"))
+            {
+                parse_fields(part.contents())
+            } else {
+                response.not_parsable.push(blob_id);
+                continue;
+            };
+
+            let mut reporting_ua = Value::Null;
+            let mut mdn_gateway = Value::Null;
+            let mut original_recipient = Value::Null;
+            let mut final_recipient = Value::Null;
+            let mut original_message_id = None;
+            let mut disposition = None;
+            let mut errors = Vec::new();
+            let mut extension_fields = Object::with_capacity(0);
+
+            for (name, value) in fields {
+                match name.to_ascii_lowercase().as_str() {
+                    "reporting-ua" => {
+                        reporting_ua = Value::Text(value);
+                    }
+                    "mdn-gateway" => {
+                        mdn_gateway = Value::Text(value);
+                    }
+                    "original-recipient" => {
+                        original_recipient = Value::Text(value);
+                    }
+                    "final-recipient" => {
+                        final_recipient = Value::Text(value);
+                    }
+                    "original-message-id" => {
+                        original_message_id = value.into();
+                    }
+                    "disposition" => {
+                        disposition = parse_disposition(&value);
+                    }
+                    "error" => {
+                        errors.push(Value::Text(value));
+                    }
+                    _ => {
+                        extension_fields.append(Property::_T(name), value);
+                    }
+                }
+            }
+
+            // A disposition is required
+            let disposition = if let Some(disposition) = disposition {
+                disposition
+            } else {
+                response.not_parsable.push(blob_id);
+                continue;
+            };
+
+            // Find the original message in the account
+            let mut for_email_id = Value::Null;
+            if let Some(message_id) = original_message_id
+                .as_deref()
+                .map(|id| id.trim_start_matches('<').trim_end_matches('>'))
+                .filter(|id| !id.is_empty() && id.len() < MAX_ID_LENGTH)
+            {
+                for document_id in self
+                    .filter(
+                        account_id,
+                        Collection::Email,
+                        vec![Filter::eq(Property::MessageId, message_id)],
+                    )
+                    .await?
+                    .results
+                {
+                    // The index also contains In-Reply-To and References ids
+                    let is_original = self
+                        .get_property::<Object<Value>>(
+                            account_id,
+                            Collection::Email,
+                            document_id,
+                            Property::BodyStructure,
+                        )
+                        .await?
+                        .map_or(false, |metadata| {
+                            matches!(metadata.get(&Property::MessageId), Value::List(ids)
+                                if ids.iter().any(|id| id.as_string() == Some(message_id)))
+                        });
+                    if is_original {
+                        if let Some(thread_id) = self
+                            .get_property::<u32>(
+                                account_id,
+                                Collection::Email,
+                                document_id,
+                                Property::ThreadId,
+                            )
+                            .await?
+                        {
+                            for_email_id = Value::Id(Id::from_parts(thread_id, document_id));
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // Prepare response
+            response.parsed.append(
+                blob_id,
+                Object::with_capacity(12)
+                    .with_property(Property::ForEmailId, for_email_id)
+                    .with_property(
+                        Property::Subject,
+                        message
+                            .subject()
+                            .map_or(Value::Null, |subject| Value::Text(subject.to_string())),
+                    )
+                    .with_property(
+                        Property::TextBody,
+                        message
+                            .body_text(0)
+                            .map_or(Value::Null, |text| Value::Text(text.into_owned())),
+                    )
+                    .with_property(
+                        Property::IncludeOriginalMessage,
+                        message
+                            .parts
+                            .iter()
+                            .any(|part| matches!(part.body, PartType::Message(_))),
+                    )
+                    .with_property(Property::ReportingUA, reporting_ua)
+                    .with_property(Property::Disposition, disposition)
+                    .with_property(Property::MdnGateway, mdn_gateway)
+                    .with_property(Property::OriginalRecipient, original_recipient)
+                    .with_property(Property::FinalRecipient, final_recipient)
+                    .with_property(
+                        Property::OriginalMessageId,
+                        original_message_id.map_or(Value::Null, Value::Text),
+                    )
+                    .with_property(
+                        Property::Error,
+                        if !errors.is_empty() {
+                            Value::List(errors)
+                        } else {
+                            Value::Null
+                        },
+                    )
+                    .with_property(
+                        Property::ExtensionFields,
+                        if !extension_fields.properties.is_empty() {
+                            Value::Object(extension_fields)
+                        } else {
+                            Value::Null
+                        },
+                    ),
+            );
+        }
+
+        Ok(response)
+    }
+}
+
+fn parse_fields(data: &[u8]) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in String::from_utf8_lossy(data).split('\n') {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with([' ', '\t']) {
+            // Unfold continuation lines
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim();
+            if !name.is_empty() {
+                fields.push((name.to_string(), value.trim().to_string()));
+            }
+        }
+    }
+    fields
+}
+
+fn parse_disposition(value: &str) -> Option<Object<Value>> {
+    let (modes, type_) = value.split_once(';')?;
+    let (action_mode, sending_mode) = modes.split_once('/')?;
+    let type_ = type_.split_once('/').map_or(type_, |(type_, _)| type_);
+
+    Some(
+        Object::with_capacity(3)
+            .with_property(Property::ActionMode, action_mode.trim().to_lowercase())
+            .with_property(Property::SendingMode, sending_mode.trim().to_lowercase())
+            .with_property(Property::Type, type_.trim().to_lowercase()),
+    )
+}
@@ -0,0 +1,571 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{collections::HashMap, fmt::Write, sync::Arc};
+
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::{
+        mdn::{MdnSendRequest, MdnSendResponse},
+        set::{self, SetRequest},
+    },
+    object::Object,
+    request::{
+        method::{MethodFunction, MethodName, MethodObject},
+        reference::MaybeReference,
+        Call, RequestMethod,
+    },
+    response::references::EvalObjectReferences,
+    types::{
+        collection::Collection,
+        id::Id,
+        keyword::Keyword,
+        property::{HeaderForm, HeaderProperty, Property},
+        value::{MaybePatchValue, SetValue, Value},
+    },
+};
+use mail_builder::{
+    headers::{
+        address::{Address, EmailAddress},
+        content_type::ContentType,
+        message_id::MessageId,
+        HeaderType,
+    },
+    mime::{make_boundary, BodyPart, MimePart},
+    MessageBuilder,
+};
+use mail_parser::MessageParser;
+use smtp_proto::{MailFrom, RcptTo};
+use store::BlobKind;
+use utils::{listener::ServerInstance, map::vec_map::VecMap};
+
+use crate::{email::headers::HeaderToValue, identity::set::sanitize_email, JMAP};
+
+struct Identity {
+    name: Option<String>,
+    email: String,
+}
+
+impl JMAP {
+    pub async fn mdn_send(
+        &self,
+        request: MdnSendRequest,
+        instance: &Arc<ServerInstance>,
+        next_call: &mut Option<Call<RequestMethod>>,
+    ) -> Result<MdnSendResponse, MethodError> {
+        if request.send.len() > self.config.set_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+        let account_id = request.account_id.document_id();
+        let mut response = MdnSendResponse {
+            account_id: request.account_id,
+            sent: VecMap::with_capacity(request.send.len()),
+            not_sent: VecMap::new(),
+        };
+
+        // Obtain identity
+        let identity = if let Some(mut identity) = self
+            .get_property::<Object<Value>>(
+                account_id,
+                Collection::Identity,
+                request.identity_id.document_id(),
+                Property::Value,
+            )
+            .await?
+        {
+            if let Some(email) = identity.remove(&Property::Email).try_unwrap_string() {
+                Identity {
+                    name: identity
+                        .remove(&Property::Name)
+                        .try_unwrap_string()
+                        .filter(|name| !name.is_empty()),
+                    email,
+                }
+            } else {
+                return Err(MethodError::InvalidArguments(
+                    "Identity has no email address.".to_string(),
+                ));
+            }
+        } else {
+            return Err(MethodError::InvalidArguments(
+                "Identity not found.".to_string(),
+            ));
+        };
+
+        // Send MDNs
+        let mut sent_email_ids = HashMap::new();
+        for (id, mdn) in request.send {
+            match self
+                .send_mdn(account_id, &response, instance, &identity, mdn)
+                .await?
+            {
+                Ok((email_id, mdn)) => {
+                    sent_email_ids.insert(id.clone(), email_id);
+                    response.sent.append(id, mdn);
+                }
+                Err(err) => {
+                    response.not_sent.append(id, err);
+                }
+            }
+        }
+
+        // Flag emails as $mdnsent and apply onSuccessUpdateEmail
+        if !sent_email_ids.is_empty() {
+            let mut update: VecMap<Id, Object<SetValue>> = VecMap::new();
+            for (id, value) in request.on_success_update_email.unwrap_or_default() {
+                let id = match id {
+                    MaybeReference::Value(id) => id,
+                    MaybeReference::Reference(id_ref) => {
+                        if let Some(id) = sent_email_ids.get(&id_ref) {
+                            *id
+                        } else {
+                            continue;
+                        }
+                    }
+                };
+                update.append(id, value);
+            }
+            for email_id in sent_email_ids.into_values() {
+                let email = update.get_mut_or_insert_with(email_id, || Object {
+                    properties: VecMap::with_capacity(1),
+                });
+                if !email.properties.iter().any(|(property, value)| {
+                    matches!((property, value), (Property::Keywords, SetValue::Patch(patch))
+                        if matches!(patch.first(), Some(Value::Keyword(Keyword::MdnSent))))
+                }) {
+                    email.properties.append(
+                        Property::Keywords,
+                        SetValue::Patch(vec![Value::Keyword(Keyword::MdnSent), Value::Bool(true)]),
+                    );
+                }
+            }
+
+            *next_call = Call {
+                id: String::new(),
+                name: MethodName::new(MethodObject::Email, MethodFunction::Set),
+                method: RequestMethod::Set(SetRequest {
+                    account_id: request.account_id,
+                    if_in_state: None,
+                    create: None,
+                    update: Some(update),
+                    destroy: None,
+                    arguments: set::RequestArguments::Email,
+                }),
+            }
+            .into();
+        }
+
+        Ok(response)
+    }
+
+    async fn send_mdn(
+        &self,
+        account_id: u32,
+        response: &MdnSendResponse,
+        instance: &Arc<ServerInstance>,
+        identity: &Identity,
+        mdn: Object<SetValue>,
+    ) -> Result<Result<(Id, Object<Value>), SetError>, MethodError> {
+        let mut email_id = None;
+        let mut subject = None;
+        let mut text_body = None;
+        let mut include_original = false;
+        let mut reporting_ua = None;
+        let mut final_recipient = None;
+        let mut disposition = None;
+        let mut extension_fields = Vec::new();
+
+        for (property, value) in mdn.properties {
+            let value = match response.eval_object_references(value) {
+                Ok(value) => value,
+                Err(err) => {
+                    return Ok(Err(err));
+                }
+            };
+
+            match (&property, value) {
+                (Property::ForEmailId, MaybePatchValue::Value(Value::Id(value))) => {
+                    email_id = value.into();
+                }
+                (Property::Subject, MaybePatchValue::Value(Value::Text(value))) => {
+                    subject = value.into();
+                }
+                (Property::TextBody, MaybePatchValue::Value(Value::Text(value))) => {
+                    text_body = value.into();
+                }
+                (Property::IncludeOriginalMessage, MaybePatchValue::Value(Value::Bool(value))) => {
+                    include_original = value;
+                }
+                (
+                    Property::ReportingUA | Property::FinalRecipient,
+                    MaybePatchValue::Value(Value::Text(value)),
+                ) if is_valid_field_value(&value) => {
+                    if let Property::ReportingUA = property {
+                        reporting_ua = value.into();
+                    } else {
+                        final_recipient = value.into();
+                    }
+                }
+                (Property::Disposition, MaybePatchValue::Value(Value::Object(value))) => {
+                    let mut action_mode = None;
+                    let mut sending_mode = None;
+                    let mut type_ = None;
+                    for (property, value) in value.properties {
+                        match (property, value) {
+                            (Property::ActionMode, Value::Text(value))
+                                if ["manual-action", "automatic-action"]
+                                    .contains(&value.as_str()) =>
+                            {
+                                action_mode = value.into();
+                            }
+                            (Property::SendingMode, Value::Text(value))
+                                if ["mdn-sent-manually", "mdn-sent-automatically"]
+                                    .contains(&value.as_str()) =>
+                            {
+                                sending_mode = value.into();
+                            }
+                            (Property::Type, Value::Text(value))
+                                if ["deleted", "dispatched", "displayed", "processed"]
+                                    .contains(&value.as_str()) =>
+                            {
+                                type_ = value.into();
+                            }
+                            (property, _) => {
+                                return Ok(Err(SetError::invalid_properties()
+                                    .with_property((Property::Disposition, property))
+                                    .with_description("Invalid disposition value.")));
+                            }
+                        }
+                    }
+                    if let (Some(action_mode), Some(sending_mode), Some(type_)) =
+                        (action_mode, sending_mode, type_)
+                    {
+                        disposition = (action_mode, sending_mode, type_).into();
+                    } else {
+                        return Ok(Err(SetError::invalid_properties()
+                            .with_property(Property::Disposition)
+                            .with_description(
+                                "Disposition requires actionMode, sendingMode and type.",
+                            )));
+                    }
+                }
+                (Property::ExtensionFields, MaybePatchValue::Value(Value::Object(value))) => {
+                    for (name, value) in value.properties {
+                        match (name, value) {
+                            (Property::_T(name), Value::Text(value))
+                                if !name.is_empty()
+                                    && name
+                                        .chars()
+                                        .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+                                    && is_valid_field_value(&value) =>
+                            {
+                                extension_fields.push((name, value));
+                            }
+                            _ => {
+                                return Ok(Err(SetError::invalid_properties()
+                                    .with_property(Property::ExtensionFields)
+                                    .with_description("Invalid extension field.")));
+                            }
+                        }
+                    }
+                }
+                (
+                    Property::Subject
+                    | Property::TextBody
+                    | Property::ReportingUA
+                    | Property::FinalRecipient
+                    | Property::ExtensionFields,
+                    MaybePatchValue::Value(Value::Null),
+                ) => (),
+                _ => {
+                    return Ok(Err(SetError::invalid_properties()
+                        .with_property(property)
+                        .with_description("Field could not be set.")));
+                }
+            }
+        }
+
+        // Make sure we have all required fields.
+        let (email_id, (action_mode, sending_mode, type_)) =
+            if let (Some(email_id), Some(disposition)) = (email_id, disposition) {
+                (email_id, disposition)
+            } else {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_properties([Property::ForEmailId, Property::Disposition])
+                    .with_description(
+                        "forEmailId and disposition properties are required.",
+                    )));
+            };
+
+        // Make sure an MDN was not sent already
+        let document_id = email_id.document_id();
+        match self
+            .get_property::<Vec<Keyword>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::Keywords,
+            )
+            .await?
+        {
+            Some(keywords) if keywords.contains(&Keyword::MdnSent) => {
+                return Ok(Err(SetError::new(SetErrorType::MdnAlreadySent)
+                    .with_description("An MDN was already sent for this email.")));
+            }
+            Some(_) => (),
+            None => {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_property(Property::ForEmailId)
+                    .with_description("Email not found.")));
+            }
+        }
+
+        // Obtain original message
+        let raw_message = if let Some(raw_message) = self
+            .get_blob(
+                &BlobKind::LinkedMaildir {
+                    account_id,
+                    document_id,
+                },
+                0..u32::MAX,
+            )
+            .await?
+        {
+            raw_message
+        } else {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::ForEmailId)
+                .with_description("Blob for email not found.")));
+        };
+        let message = if let Some(message) = MessageParser::new().parse(&raw_message) {
+            message
+        } else {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::ForEmailId)
+                .with_description("Failed to parse email.")));
+        };
+
+        // Obtain notification recipients
+        let mut rcpt_to: Vec<RcptTo<String>> = Vec::new();
+        if let Value::List(addresses) = message.root_part().header_to_value(
+            &Property::Header(HeaderProperty {
+                form: HeaderForm::Addresses,
+                header: "Disposition-Notification-To".to_string(),
+                all: false,
+            }),
+            &raw_message,
+        ) {
+            for address in addresses {
+                if let Some(address) = address
+                    .as_obj()
+                    .and_then(|obj| obj.properties.get(&Property::Email))
+                    .and_then(|value| value.as_string())
+                    .and_then(sanitize_email)
+                {
+                    if !rcpt_to.iter().any(|rcpt| rcpt.address == address) {
+                        rcpt_to.push(RcptTo {
+                            address,
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+        }
+        if rcpt_to.is_empty() {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::ForEmailId)
+                .with_description(
+                    "Email does not request a disposition notification.",
+                )));
+        }
+        let original_recipient = message
+            .root_part()
+            .header_to_value(
+                &Property::Header(HeaderProperty {
+                    form: HeaderForm::Text,
+                    header: "Original-Recipient".to_string(),
+                    all: false,
+                }),
+                &raw_message,
+            )
+            .try_unwrap_string()
+            .filter(|value| is_valid_field_value(value));
+        let original_message_id = message
+            .message_id()
+            .filter(|id| is_valid_field_value(id))
+            .map(|id| id.to_string());
+
+        // Build report
+        let mut created = Object::with_capacity(5);
+        let final_recipient = final_recipient.unwrap_or_else(|| {
+            let final_recipient = format!("rfc822; {}", identity.email);
+            created.append(Property::FinalRecipient, final_recipient.clone());
+            final_recipient
+        });
+        let mut report = String::with_capacity(128);
+        if let Some(reporting_ua) = &reporting_ua {
+            let _ = write!(report, "Reporting-UA: {reporting_ua}\r\n");
+        }
+        if let Some(original_recipient) = &original_recipient {
+            let _ = write!(report, "Original-Recipient: {original_recipient}\r\n");
+            created.append(Property::OriginalRecipient, original_recipient.clone());
+        }
+        let _ = write!(report, "Final-Recipient: {final_recipient}\r\n");
+        if let Some(original_message_id) = &original_message_id {
+            let original_message_id = format!("<{original_message_id}>");
+            let _ = write!(report, "Original-Message-ID: {original_message_id}\r\n");
+            created.append(Property::OriginalMessageId, original_message_id);
+        }
+        let _ = write!(
+            report,
+            "Disposition: {action_mode}/{sending_mode}; {type_}\r\n"
+        );
+        for (name, value) in &extension_fields {
+            let _ = write!(report, "{name}: {value}\r\n");
+        }
+
+        let subject = subject.unwrap_or_else(|| {
+            let subject = if let Some(subject) = message.subject() {
+                format!("Return Receipt ({type_}): {subject}")
+            } else {
+                format!("Return Receipt ({type_})")
+            };
+            created.append(Property::Subject, subject.clone());
+            subject
+        });
+        let text_body = text_body.unwrap_or_else(|| {
+            let text_body = format!(
+                concat!(
+                    "This is a Return Receipt for the message sent to {}.\r\n\r\n",
+                    "Note: This Return Receipt only acknowledges that the message ",
+                    "was {} on the recipient's computer. There is no guarantee that ",
+                    "the content has been read or understood.\r\n"
+                ),
+                identity.email, type_
+            );
+            created.append(Property::TextBody, text_body.clone());
+            text_body
+        });
+
+        // Build message
+        let domain = identity
+            .email
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain);
+        let mut builder = MessageBuilder::new()
+            .from(Address::Address(EmailAddress {
+                name: identity.name.as_deref().map(Into::into),
+                email: identity.email.as_str().into(),
+            }))
+            .to(Address::new_list(
+                rcpt_to
+                    .iter()
+                    .map(|rcpt| {
+                        Address::Address(EmailAddress {
+                            name: None,
+                            email: rcpt.address.as_str().into(),
+                        })
+                    })
+                    .collect(),
+            ))
+            .message_id(format!("{}@{}", make_boundary("."), domain))
+            .subject(subject);
+        if let Some(original_message_id) = &original_message_id {
+            builder = builder
+                .header("In-Reply-To", MessageId::new(original_message_id.as_str()))
+                .header("References", MessageId::new(original_message_id.as_str()));
+        }
+        if sending_mode == "mdn-sent-automatically" {
+            builder = builder.header("Auto-Submitted", HeaderType::Text("auto-replied".into()));
+        }
+        let message = builder
+            .body(MimePart::new(
+                ContentType::new("multipart/report")
+                    .attribute("report-type", "disposition-notification"),
+                BodyPart::Multipart(vec![
+                    MimePart::new(
+                        ContentType::new("text/plain"),
+                        BodyPart::Text(text_body.into()),
+                    ),
+                    MimePart::new(
+                        ContentType::new("message/disposition-notification"),
+                        BodyPart::Text(report.into()),
+                    ),
+                    if include_original {
+                        MimePart::new(
+                            ContentType::new("message/rfc822"),
+                            BodyPart::Text(String::from_utf8_lossy(&raw_message)),
+                        )
+                    } else {
+                        MimePart::new(
+                            ContentType::new("text/rfc822-headers"),
+                            BodyPart::Text(String::from_utf8_lossy(message.raw_headers())),
+                        )
+                    },
+                ]),
+            ))
+            .write_to_vec()
+            .unwrap_or_default();
+        if message.len() > self.config.mail_max_size {
+            return Ok(Err(SetError::new(SetErrorType::InvalidEmail)
+                .with_description(format!(
+                    "Message exceeds maximum size of {} bytes.",
+                    self.config.mail_max_size
+                ))));
+        }
+
+        // Submit message
+        match self
+            .submit_message(
+                instance,
+                MailFrom {
+                    address: identity.email.clone(),
+                    ..Default::default()
+                },
+                rcpt_to,
+                message,
+            )
+            .await
+        {
+            Ok((Some(_), _)) => Ok(Ok((email_id, created))),
+            Ok((None, responses)) => Ok(Err(SetError::new(SetErrorType::ForbiddenToSend)
+                .with_description(format!(
+                    "Server rejected all recipients: {}",
+                    responses
+                        .into_iter()
+                        .filter_map(|(_, response)| response)
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                )))),
+            Err(err) => Ok(Err(err)),
+        }
+    }
+}
+
+fn is_valid_field_value(value: &str) -> bool {
+    !value.is_empty() && !value.contains(['\r', '\n'])
+}
@@ -0,0 +1,168 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    request::reference::MaybeReference,
+    types::{any_id::AnyId, collection::Collection, id::Id, property::Property, value::Value},
+};
+use store::{query::Filter, BlobKind};
+
+use crate::JMAP;
+
+use super::{build_script, parse_rules, RULES_SCRIPT_NAME};
+
+impl JMAP {
+    pub async fn mail_filter_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+    ) -> Result<GetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let properties =
+            request.unwrap_properties(&[Property::Id, Property::IsEnabled, Property::Rules]);
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self
+                .get_state(account_id, Collection::SieveScript)
+                .await?
+                .into(),
+            list: Vec::with_capacity(1),
+            not_found: vec![],
+        };
+
+        let do_get = if let Some(MaybeReference::Value(ids)) = request.ids {
+            let mut do_get = false;
+            for id in ids {
+                match id.try_unwrap() {
+                    Some(AnyId::Id(id)) if id.is_singleton() => {
+                        do_get = true;
+                    }
+                    Some(id) => {
+                        response.not_found.push(id);
+                    }
+                    _ => {}
+                }
+            }
+            do_get
+        } else {
+            true
+        };
+        if do_get {
+            if let Some(document_id) = self.get_mail_filter_script_id(account_id).await? {
+                if let Some(mut obj) = self
+                    .get_property::<Object<Value>>(
+                        account_id,
+                        Collection::SieveScript,
+                        document_id,
+                        Property::Value,
+                    )
+                    .await?
+                {
+                    let mut result = Object::with_capacity(properties.len());
+                    for property in &properties {
+                        match property {
+                            Property::Id => {
+                                result.append(Property::Id, Value::Id(Id::singleton()));
+                            }
+                            Property::IsEnabled => {
+                                result.append(Property::IsEnabled, obj.remove(&Property::IsActive));
+                            }
+                            Property::Rules => {
+                                let rules = obj.remove(&Property::Rules);
+                                let value = if self
+                                    .mail_filter_is_managed(account_id, document_id, &obj, &rules)
+                                    .await?
+                                {
+                                    rules
+                                } else {
+                                    Value::Null
+                                };
+                                result.append(Property::Rules, value);
+                            }
+                            property => {
+                                result.append(property.clone(), Value::Null);
+                            }
+                        }
+                    }
+                    response.list.push(result);
+                } else {
+                    response.not_found.push(Id::singleton().into());
+                }
+            } else {
+                response.not_found.push(Id::singleton().into());
+            }
+        }
+
+        Ok(response)
+    }
+
+    pub async fn get_mail_filter_script_id(
+        &self,
+        account_id: u32,
+    ) -> Result<Option<u32>, MethodError> {
+        self.filter(
+            account_id,
+            Collection::SieveScript,
+            vec![Filter::eq(Property::Name, RULES_SCRIPT_NAME)],
+        )
+        .await
+        .map(|r| r.results.min())
+    }
+
+    // Rules can only be returned while the stored script is the one generated
+    // from them, a script edited through SieveScript/set or ManageSieve is
+    // reported with null rules.
+    async fn mail_filter_is_managed(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        obj: &Object<Value>,
+        rules: &Value,
+    ) -> Result<bool, MethodError> {
+        let rules = match (rules, parse_rules(rules)) {
+            (Value::List(_), Ok(rules)) => rules,
+            _ => return Ok(false),
+        };
+        let script_size = if let Some(script_size) = obj.get(&Property::Size).as_uint() {
+            script_size as usize
+        } else {
+            return Ok(false);
+        };
+        let script = build_script(&rules);
+
+        Ok(script.len() == script_size
+            && self
+                .get_blob(
+                    &BlobKind::Linked {
+                        account_id,
+                        collection: Collection::SieveScript.into(),
+                        document_id,
+                    },
+                    0..script_size as u32,
+                )
+                .await?
+                .map_or(false, |bytes| bytes == script))
+    }
+}
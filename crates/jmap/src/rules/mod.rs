@@ -0,0 +1,489 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::borrow::Cow;
+
+use jmap_proto::{
+    error::set::SetError,
+    object::Object,
+    types::{property::Property, value::Value},
+};
+
+pub mod get;
+pub mod set;
+
+// Name of the Sieve script managed by MailFilter/set
+pub const RULES_SCRIPT_NAME: &str = "mailfilter";
+
+const MAX_VALUE_LEN: usize = 1024;
+
+pub struct Rule {
+    pub name: String,
+    pub any_of: bool,
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+
+pub struct Condition {
+    pub field: Field,
+    pub comparator: Comparator,
+    pub not: bool,
+    pub value: String,
+}
+
+pub enum Field {
+    From,
+    To,
+    Subject,
+    ListId,
+    Header(String),
+    Size,
+}
+
+pub enum Comparator {
+    Contains,
+    Is,
+    Matches,
+    Over,
+    Under,
+}
+
+pub enum Action {
+    FileInto(String),
+    Flag(String),
+    Forward(String),
+    Reject(String),
+    Vacation {
+        subject: Option<String>,
+        text: String,
+    },
+    Stop,
+}
+
+impl Rule {
+    pub fn parse(value: &Value) -> Result<Self, SetError> {
+        let mut rule = Rule {
+            name: String::new(),
+            any_of: false,
+            conditions: Vec::new(),
+            actions: Vec::new(),
+        };
+
+        for (property, value) in &as_object(value, "Rule")?.properties {
+            match (property, value) {
+                (Property::Name, Value::Text(value)) => {
+                    rule.name = check_len(value)?.to_string();
+                }
+                (Property::_T(name), Value::Bool(value)) if name == "anyOf" => {
+                    rule.any_of = *value;
+                }
+                (Property::_T(name), Value::List(values)) if name == "conditions" => {
+                    for value in values {
+                        rule.conditions.push(Condition::parse(value)?);
+                    }
+                }
+                (Property::_T(name), Value::List(values)) if name == "actions" => {
+                    for value in values {
+                        rule.actions.push(Action::parse(value)?);
+                    }
+                }
+                (_, Value::Null) => (),
+                (property, _) => {
+                    return Err(invalid_rule(format!("Invalid rule property '{property}'.")));
+                }
+            }
+        }
+
+        if rule.actions.is_empty() {
+            Err(invalid_rule("Rule has no actions."))
+        } else if rule
+            .actions
+            .iter()
+            .any(|action| matches!(action, Action::Reject(_)))
+            && rule
+                .actions
+                .iter()
+                .any(|action| !matches!(action, Action::Reject(_) | Action::Stop))
+        {
+            Err(invalid_rule(
+                "The reject action can only be combined with stop.",
+            ))
+        } else {
+            Ok(rule)
+        }
+    }
+}
+
+impl Condition {
+    fn parse(value: &Value) -> Result<Self, SetError> {
+        let obj = as_object(value, "Condition")?;
+        let mut field = None;
+        let mut header = None;
+        let mut comparator = None;
+        let mut not = false;
+        let mut value = None;
+
+        for (property, item) in &obj.properties {
+            match (property, item) {
+                (Property::_T(name), Value::Text(item)) if name == "field" => {
+                    field = item.as_str().into();
+                }
+                (Property::_T(name), Value::Text(item)) if name == "header" => {
+                    header = item.as_str().into();
+                }
+                (Property::_T(name), Value::Text(item)) if name == "comparator" => {
+                    comparator = match item.as_str() {
+                        "contains" => Comparator::Contains,
+                        "is" => Comparator::Is,
+                        "matches" => Comparator::Matches,
+                        "over" => Comparator::Over,
+                        "under" => Comparator::Under,
+                        _ => {
+                            return Err(invalid_rule(format!("Invalid comparator {item:?}.")));
+                        }
+                    }
+                    .into();
+                }
+                (Property::_T(name), Value::Bool(item)) if name == "not" => {
+                    not = *item;
+                }
+                (Property::Value, Value::Text(item)) => {
+                    value = check_len(item)?.into();
+                }
+                (_, Value::Null) => (),
+                (property, _) => {
+                    return Err(invalid_rule(format!(
+                        "Invalid condition property '{property}'."
+                    )));
+                }
+            }
+        }
+
+        let field = match (field, header) {
+            (Some("from"), None) => Field::From,
+            (Some("to"), None) => Field::To,
+            (Some("subject"), None) => Field::Subject,
+            (Some("listId"), None) => Field::ListId,
+            (Some("size"), None) => Field::Size,
+            (Some("header"), Some(header))
+                if !header.is_empty()
+                    && header.len() <= 255
+                    && header.bytes().all(|ch| ch.is_ascii_graphic() && ch != b':') =>
+            {
+                Field::Header(header.to_string())
+            }
+            (Some("header"), _) => {
+                return Err(invalid_rule("Missing or invalid header name."));
+            }
+            (Some(field), None) => {
+                return Err(invalid_rule(format!("Invalid field {field:?}.")));
+            }
+            (_, _) => {
+                return Err(invalid_rule("Missing or invalid condition field."));
+            }
+        };
+        let comparator = comparator.ok_or_else(|| invalid_rule("Missing condition comparator."))?;
+        let value = value.ok_or_else(|| invalid_rule("Missing condition value."))?;
+
+        if let Field::Size = &field {
+            // Sizes are expressed as a number with an optional K, M or G suffix
+            let value = value
+                .strip_suffix(&['K', 'M', 'G', 'k', 'm', 'g'][..])
+                .unwrap_or(value);
+            if !matches!(comparator, Comparator::Over | Comparator::Under) {
+                return Err(invalid_rule(
+                    "Size conditions only support the over and under comparators.",
+                ));
+            } else if value.is_empty()
+                || value.len() > 10
+                || !value.bytes().all(|ch| ch.is_ascii_digit())
+            {
+                return Err(invalid_rule("Invalid size value."));
+            }
+        } else if matches!(comparator, Comparator::Over | Comparator::Under) {
+            return Err(invalid_rule(
+                "The over and under comparators are only supported by size conditions.",
+            ));
+        }
+
+        Ok(Condition {
+            field,
+            comparator,
+            not,
+            value: value.to_string(),
+        })
+    }
+}
+
+impl Action {
+    fn parse(value: &Value) -> Result<Self, SetError> {
+        let obj = as_object(value, "Action")?;
+        let mut action_type = None;
+        let mut value = None;
+        let mut subject = None;
+
+        for (property, item) in &obj.properties {
+            match (property, item) {
+                (Property::Type, Value::Text(item)) => {
+                    action_type = item.as_str().into();
+                }
+                (Property::Value, Value::Text(item)) => {
+                    value = check_len(item)?.to_string().into();
+                }
+                (Property::_T(name), Value::Text(item)) if name == "subject" => {
+                    subject = check_len(item)?.to_string().into();
+                }
+                (_, Value::Null) => (),
+                (property, _) => {
+                    return Err(invalid_rule(format!(
+                        "Invalid action property '{property}'."
+                    )));
+                }
+            }
+        }
+
+        match (action_type, value) {
+            (Some("fileInto"), Some(value)) if !value.trim().is_empty() => {
+                Ok(Action::FileInto(value))
+            }
+            (Some("flag"), Some(value))
+                if !value.is_empty() && !value.contains(|ch: char| ch.is_whitespace()) =>
+            {
+                Ok(Action::Flag(value))
+            }
+            (Some("forward"), Some(value))
+                if value.contains('@')
+                    && !value.contains(|ch: char| ch.is_whitespace() || "<>\"".contains(ch)) =>
+            {
+                Ok(Action::Forward(value))
+            }
+            (Some("reject"), value) => Ok(Action::Reject(value.unwrap_or_default())),
+            (Some("vacation"), Some(text)) => Ok(Action::Vacation { subject, text }),
+            (Some("stop"), None) => Ok(Action::Stop),
+            (Some(action_type), _) => Err(invalid_rule(format!(
+                "Missing or invalid value for action {action_type:?}."
+            ))),
+            (None, _) => Err(invalid_rule("Missing action type.")),
+        }
+    }
+}
+
+// Builds the Sieve script for a list of rules. The output has to be stable, as
+// it is compared against the stored script to detect changes made by hand.
+pub fn build_script(rules: &[Rule]) -> Vec<u8> {
+    let mut script = Vec::with_capacity(1024);
+    script.extend_from_slice(
+        b"# Generated by MailFilter/set, manual changes disable the rules editor.\r\n",
+    );
+
+    // Add required extensions
+    let mut requires: Vec<&str> = Vec::new();
+    for action in rules.iter().flat_map(|rule| rule.actions.iter()) {
+        let extensions: &[&str] = match action {
+            Action::FileInto(_) => &["fileinto", "mailbox"],
+            Action::Flag(_) => &["imap4flags"],
+            Action::Forward(_) => &["copy"],
+            Action::Reject(_) => &["reject"],
+            Action::Vacation { .. } => &["vacation"],
+            Action::Stop => &[],
+        };
+        for &extension in extensions {
+            if !requires.contains(&extension) {
+                requires.push(extension);
+            }
+        }
+    }
+    if !requires.is_empty() {
+        script.extend_from_slice(b"require [");
+        for (pos, extension) in requires.iter().enumerate() {
+            if pos > 0 {
+                script.extend_from_slice(b", ");
+            }
+            write_string(&mut script, extension, false);
+        }
+        script.extend_from_slice(b"];\r\n");
+    }
+
+    for rule in rules {
+        // Add rule name as a comment
+        script.extend_from_slice(b"\r\n# ");
+        script.extend(rule.name.bytes().filter(|ch| ![b'\r', b'\n'].contains(ch)));
+        script.extend_from_slice(b"\r\n");
+
+        // Add conditions
+        script.extend_from_slice(b"if ");
+        match rule.conditions.len() {
+            0 => {
+                script.extend_from_slice(b"true");
+            }
+            1 => {
+                rule.conditions[0].write(&mut script);
+            }
+            _ => {
+                script.extend_from_slice(if rule.any_of { b"anyof (" } else { b"allof (" });
+                for (pos, condition) in rule.conditions.iter().enumerate() {
+                    if pos > 0 {
+                        script.extend_from_slice(b", ");
+                    }
+                    condition.write(&mut script);
+                }
+                script.push(b')');
+            }
+        }
+        script.extend_from_slice(b" {\r\n");
+
+        // Add actions
+        for action in &rule.actions {
+            script.extend_from_slice(b"    ");
+            match action {
+                Action::FileInto(mailbox) => {
+                    script.extend_from_slice(b"fileinto :create ");
+                    write_string(&mut script, mailbox, false);
+                }
+                Action::Flag(flag) => {
+                    script.extend_from_slice(b"addflag ");
+                    write_string(&mut script, flag, false);
+                }
+                Action::Forward(address) => {
+                    script.extend_from_slice(b"redirect :copy ");
+                    write_string(&mut script, address, false);
+                }
+                Action::Reject(reason) => {
+                    script.extend_from_slice(b"reject ");
+                    write_string(&mut script, reason, true);
+                }
+                Action::Vacation { subject, text } => {
+                    script.extend_from_slice(b"vacation ");
+                    if let Some(subject) = subject {
+                        script.extend_from_slice(b":subject ");
+                        write_string(&mut script, subject, false);
+                        script.push(b' ');
+                    }
+                    write_string(&mut script, text, true);
+                }
+                Action::Stop => {
+                    script.extend_from_slice(b"stop");
+                }
+            }
+            script.extend_from_slice(b";\r\n");
+        }
+        script.extend_from_slice(b"}\r\n");
+    }
+
+    script
+}
+
+impl Condition {
+    fn write(&self, script: &mut Vec<u8>) {
+        if self.not {
+            script.extend_from_slice(b"not ");
+        }
+        let comparator: &[u8] = match self.comparator {
+            Comparator::Contains => b":contains ",
+            Comparator::Is => b":is ",
+            Comparator::Matches => b":matches ",
+            Comparator::Over => b":over ",
+            Comparator::Under => b":under ",
+        };
+        match &self.field {
+            Field::From => {
+                script.extend_from_slice(b"address ");
+                script.extend_from_slice(comparator);
+                script.extend_from_slice(b"\"from\" ");
+            }
+            Field::To => {
+                script.extend_from_slice(b"address ");
+                script.extend_from_slice(comparator);
+                script.extend_from_slice(b"[\"to\", \"cc\"] ");
+            }
+            Field::Subject | Field::ListId | Field::Header(_) => {
+                script.extend_from_slice(b"header ");
+                script.extend_from_slice(comparator);
+                write_string(
+                    script,
+                    match &self.field {
+                        Field::Subject => "subject",
+                        Field::ListId => "list-id",
+                        Field::Header(header) => header.as_str(),
+                        _ => unreachable!(),
+                    },
+                    false,
+                );
+                script.push(b' ');
+            }
+            Field::Size => {
+                script.extend_from_slice(b"size ");
+                script.extend_from_slice(comparator);
+                script.extend_from_slice(self.value.to_ascii_uppercase().as_bytes());
+                return;
+            }
+        }
+        write_string(script, &self.value, false);
+    }
+}
+
+pub fn parse_rules(value: &Value) -> Result<Vec<Rule>, SetError> {
+    match value {
+        Value::List(values) => values.iter().map(Rule::parse).collect(),
+        Value::Null => Ok(Vec::new()),
+        _ => Err(invalid_rule("Rules must be a list.")),
+    }
+}
+
+fn write_string(script: &mut Vec<u8>, value: &str, multiline: bool) {
+    script.push(b'\"');
+    for &ch in value.as_bytes() {
+        match ch {
+            b'\\' | b'\"' => {
+                script.push(b'\\');
+            }
+            b'\r' | b'\n' if !multiline => {
+                continue;
+            }
+            _ => (),
+        }
+        script.push(ch);
+    }
+    script.push(b'\"');
+}
+
+fn as_object<'x>(value: &'x Value, name: &str) -> Result<&'x Object<Value>, SetError> {
+    match value {
+        Value::Object(obj) => Ok(obj),
+        _ => Err(invalid_rule(format!("{name} must be an object."))),
+    }
+}
+
+fn check_len(value: &str) -> Result<&str, SetError> {
+    if value.len() <= MAX_VALUE_LEN {
+        Ok(value)
+    } else {
+        Err(invalid_rule("Value is too long."))
+    }
+}
+
+fn invalid_rule(description: impl Into<Cow<'static, str>>) -> SetError {
+    SetError::invalid_properties()
+        .with_property(Property::Rules)
+        .with_description(description)
+}
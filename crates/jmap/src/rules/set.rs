@@ -0,0 +1,360 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::set::{RequestArguments, SetRequest, SetResponse},
+    object::{index::ObjectIndexBuilder, Object},
+    response::references::EvalObjectReferences,
+    types::{
+        collection::Collection,
+        id::Id,
+        property::Property,
+        value::{MaybePatchValue, Value},
+    },
+};
+use sieve::compiler::ErrorType;
+use store::{
+    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, F_CLEAR, F_VALUE},
+    BlobKind,
+};
+
+use crate::{auth::AccessToken, sieve::set::SCHEMA, JMAP};
+
+use super::{build_script, parse_rules, RULES_SCRIPT_NAME};
+
+impl JMAP {
+    // Only one Sieve script can be active at a time, so enabling the mail
+    // filter deactivates any other script, including the vacation response.
+    pub async fn mail_filter_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> Result<SetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut response = self
+            .prepare_set_response(&request, Collection::SieveScript)
+            .await?;
+        let will_destroy = request.unwrap_destroy();
+
+        // Process set or update requests
+        let mut create_id = None;
+        let mut changes = None;
+        match (request.create, request.update) {
+            (Some(create), Some(update)) if !create.is_empty() && !update.is_empty() => {
+                return Err(MethodError::InvalidArguments(
+                    "Creating and updating on the same request is not allowed.".into(),
+                ));
+            }
+            (Some(create), _) if !create.is_empty() => {
+                for (id, obj) in create {
+                    if will_destroy.contains(&Id::singleton()) {
+                        response.not_created.append(
+                            id,
+                            SetError::new(SetErrorType::WillDestroy)
+                                .with_description("ID will be destroyed."),
+                        );
+                    } else if create_id.is_some() {
+                        response.not_created.append(
+                            id,
+                            SetError::forbidden()
+                                .with_description("Only one object can be created."),
+                        );
+                    } else {
+                        create_id = Some(id);
+                        changes = Some(obj);
+                    }
+                }
+            }
+            (_, Some(update)) if !update.is_empty() => {
+                for (id, obj) in update {
+                    if id.is_singleton() {
+                        if !will_destroy.contains(&id) {
+                            changes = Some(obj);
+                        } else {
+                            response.not_updated.append(
+                                id,
+                                SetError::new(SetErrorType::WillDestroy)
+                                    .with_description("ID will be destroyed."),
+                            );
+                        }
+                    } else {
+                        response.not_updated.append(
+                            id,
+                            SetError::new(SetErrorType::NotFound).with_description("ID not found."),
+                        );
+                    }
+                }
+            }
+            _ => {
+                if will_destroy.is_empty() {
+                    return Ok(response);
+                }
+            }
+        }
+
+        // Process changes
+        let mut change_log = ChangeLogBuilder::new();
+        if let Some(changes_) = changes {
+            // Parse properties
+            let mut changes = Object::with_capacity(changes_.properties.len());
+            let mut is_active = false;
+            let mut rules = None;
+
+            for (property, value) in changes_.properties {
+                let value = match response.eval_object_references(value) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        return Ok(set_error(response, create_id, err));
+                    }
+                };
+                match (&property, value) {
+                    (Property::Rules, MaybePatchValue::Value(value)) => {
+                        match parse_rules(&value) {
+                            Ok(rules_) => {
+                                rules = Some(rules_);
+                            }
+                            Err(err) => {
+                                return Ok(set_error(response, create_id, err));
+                            }
+                        }
+                        changes.append(
+                            Property::Rules,
+                            if let Value::Null = value {
+                                Value::List(vec![])
+                            } else {
+                                value
+                            },
+                        );
+                    }
+                    (Property::IsEnabled, MaybePatchValue::Value(Value::Bool(value))) => {
+                        is_active = value;
+                        changes.append(Property::IsActive, value);
+                    }
+                    (Property::IsEnabled, MaybePatchValue::Value(Value::Null)) => {
+                        changes.append(Property::IsActive, Value::Bool(false));
+                    }
+                    _ => {
+                        return Ok(set_error(
+                            response,
+                            create_id,
+                            SetError::invalid_properties()
+                                .with_property(property)
+                                .with_description("Field could not be set."),
+                        ));
+                    }
+                }
+            }
+
+            // Obtain current script
+            let document_id = self.get_mail_filter_script_id(account_id).await?;
+
+            // Add name, isActive and rules
+            if document_id.is_none() {
+                if create_id.is_none() {
+                    response
+                        .not_updated
+                        .append(Id::singleton(), SetError::not_found());
+                    return Ok(response);
+                } else if self
+                    .get_document_ids(account_id, Collection::SieveScript)
+                    .await?
+                    .map_or(0, |ids| ids.len()) as usize
+                    > self.config.sieve_max_scripts
+                {
+                    return Ok(set_error(
+                        response,
+                        create_id,
+                        SetError::new(SetErrorType::OverQuota).with_description(concat!(
+                            "There are too many sieve scripts, ",
+                            "please delete some before adding a new one."
+                        )),
+                    ));
+                }
+
+                changes.append(Property::Name, Value::Text(RULES_SCRIPT_NAME.into()));
+                if !changes.properties.contains_key(&Property::IsActive) {
+                    changes.append(Property::IsActive, Value::Bool(false));
+                }
+                if rules.is_none() {
+                    rules = Some(vec![]);
+                    changes.append(Property::Rules, Value::List(vec![]));
+                }
+            }
+
+            // Build and compile script
+            let script_blob = if let Some(rules) = rules {
+                let mut script = build_script(&rules);
+
+                // Check quota
+                let account_quota = self.get_quota(access_token, account_id).await?;
+                if account_quota > 0
+                    && script.len() as i64 + self.get_used_quota(account_id).await? > account_quota
+                {
+                    return Ok(set_error(response, create_id, SetError::over_quota()));
+                }
+
+                match self.sieve_compiler.compile(&script) {
+                    Ok(compiled_script) => {
+                        changes.append(Property::Size, Value::UnsignedInt(script.len() as u64));
+                        script.extend(bincode::serialize(&compiled_script).unwrap_or_default());
+                        Some(script)
+                    }
+                    Err(err) => {
+                        return Ok(set_error(
+                            response,
+                            create_id,
+                            SetError::new(if let ErrorType::ScriptTooLong = &err.error_type() {
+                                SetErrorType::TooLarge
+                            } else {
+                                SetErrorType::InvalidScript
+                            })
+                            .with_property(Property::Rules)
+                            .with_description(err.to_string()),
+                        ));
+                    }
+                }
+            } else {
+                None
+            };
+
+            // Prepare write batch
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::SieveScript);
+
+            // Validate current script and changes
+            let mut was_active = false;
+            let obj = match ObjectIndexBuilder::new(SCHEMA)
+                .with_current_opt(if let Some(document_id) = document_id {
+                    self.get_property::<HashedValue<Object<Value>>>(
+                        account_id,
+                        Collection::SieveScript,
+                        document_id,
+                        Property::Value,
+                    )
+                    .await?
+                    .map(|value| {
+                        was_active = value.inner.properties.get(&Property::IsActive)
+                            == Some(&Value::Bool(true));
+                        value
+                    })
+                    .ok_or(MethodError::ServerPartialFail)?
+                    .into()
+                } else {
+                    None
+                })
+                .with_changes(changes)
+                .validate()
+            {
+                Ok(obj) => obj,
+                Err(err) => {
+                    return Ok(set_error(response, create_id, err));
+                }
+            };
+
+            // Write changes
+            let document_id = if let Some(document_id) = document_id {
+                batch
+                    .update_document(document_id)
+                    .value(Property::EmailIds, (), F_VALUE | F_CLEAR)
+                    .custom(obj);
+                change_log.log_update(Collection::SieveScript, document_id);
+                document_id
+            } else {
+                let document_id = self
+                    .assign_document_id(account_id, Collection::SieveScript)
+                    .await?;
+                batch.create_document(document_id).custom(obj);
+                change_log.log_insert(Collection::SieveScript, document_id);
+                document_id
+            };
+            if !batch.is_empty() {
+                self.write_batch(batch).await?;
+            }
+
+            // Write blob
+            if let Some(script_blob) = script_blob {
+                self.put_blob(
+                    &BlobKind::Linked {
+                        account_id,
+                        collection: Collection::SieveScript.into(),
+                        document_id,
+                    },
+                    &script_blob,
+                )
+                .await?;
+            }
+
+            // Deactivate other sieve scripts
+            if !was_active && is_active {
+                self.sieve_activate_script(account_id, document_id.into())
+                    .await?;
+            }
+
+            // Add result
+            if let Some(create_id) = create_id {
+                response.created.insert(
+                    create_id,
+                    Object::with_capacity(1).with_property(Property::Id, Id::singleton()),
+                );
+            } else {
+                response.updated.append(Id::singleton(), None);
+            }
+        } else if !will_destroy.is_empty() {
+            for id in will_destroy {
+                if id.is_singleton() {
+                    if let Some(document_id) = self.get_mail_filter_script_id(account_id).await? {
+                        self.sieve_script_delete(account_id, document_id, false)
+                            .await?;
+                        change_log.log_delete(Collection::SieveScript, document_id);
+                        response.destroyed.push(id);
+                        continue;
+                    }
+                }
+
+                response.not_destroyed.append(id, SetError::not_found());
+            }
+        }
+
+        // Write changes
+        if !change_log.is_empty() {
+            response.new_state = Some(self.commit_changes(account_id, change_log).await?.into());
+        }
+
+        Ok(response)
+    }
+}
+
+fn set_error(mut response: SetResponse, id: Option<String>, err: SetError) -> SetResponse {
+    if let Some(id) = id {
+        response.not_created.append(id, err);
+    } else {
+        response.not_updated.append(Id::singleton(), err);
+    }
+    response
+}
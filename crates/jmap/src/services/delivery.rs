@@ -21,19 +21,40 @@
  * for more details.
 */
 
-use std::sync::Arc;
-
 use tokio::sync::mpsc;
-use utils::ipc::DeliveryEvent;
+use utils::{ipc::DeliveryEvent, reload::Reloadable};
 
 use crate::JMAP;
 
-pub fn spawn_delivery_manager(core: Arc<JMAP>, mut delivery_rx: mpsc::Receiver<DeliveryEvent>) {
+pub fn spawn_delivery_manager(
+    core: Reloadable<JMAP>,
+    mut delivery_rx: mpsc::Receiver<DeliveryEvent>,
+) {
     tokio::spawn(async move {
         while let Some(event) = delivery_rx.recv().await {
             match event {
                 DeliveryEvent::Ingest { message, result_tx } => {
-                    result_tx.send(core.deliver_message(message).await).ok();
+                    result_tx
+                        .send(core.load().deliver_message(message).await)
+                        .ok();
+                }
+                DeliveryEvent::FetchUrl {
+                    url,
+                    submitter,
+                    result_tx,
+                } => {
+                    result_tx
+                        .send(core.load().fetch_submission_url(&url, &submitter).await)
+                        .ok();
+                }
+                DeliveryEvent::VerifySender {
+                    address,
+                    submitter,
+                    result_tx,
+                } => {
+                    result_tx
+                        .send(core.load().verify_sender(&address, &submitter).await)
+                        .ok();
                 }
                 DeliveryEvent::Stop => break,
             }
@@ -21,12 +21,13 @@
  * for more details.
 */
 
-use std::{sync::Arc, time::Instant};
+use std::time::Instant;
 
 use tokio::sync::mpsc;
 use utils::{
     config::{cron::SimpleCron, Config},
     map::ttl_dashmap::TtlMap,
+    reload::Reloadable,
     UnwrapFailure,
 };
 
@@ -44,8 +45,9 @@ pub enum Event {
 const TASK_PURGE_DB: usize = 0;
 const TASK_PURGE_BLOBS: usize = 1;
 const TASK_PURGE_SESSIONS: usize = 2;
+const TASK_PURGE_RETENTION: usize = 3;
 
-pub fn spawn_housekeeper(core: Arc<JMAP>, settings: &Config, mut rx: mpsc::Receiver<Event>) {
+pub fn spawn_housekeeper(core: Reloadable<JMAP>, settings: &Config, mut rx: mpsc::Receiver<Event>) {
     let purge_db_at = settings
         .property_or_static::<SimpleCron>("jmap.purge.schedule.db", "0 3 *")
         .failed("Initialize housekeeper");
@@ -55,6 +57,9 @@ pub fn spawn_housekeeper(core: Arc<JMAP>, settings: &Config, mut rx: mpsc::Recei
     let purge_cache = settings
         .property_or_static::<SimpleCron>("jmap.purge.schedule.sessions", "15 * *")
         .failed("Initialize housekeeper");
+    let purge_retention_at = settings
+        .property_or_static::<SimpleCron>("jmap.purge.schedule.retention", "0 4 *")
+        .failed("Initialize housekeeper");
 
     tokio::spawn(async move {
         tracing::debug!("Housekeeper task started.");
@@ -63,8 +68,9 @@ pub fn spawn_housekeeper(core: Arc<JMAP>, settings: &Config, mut rx: mpsc::Recei
                 purge_db_at.time_to_next(),
                 purge_blobs_at.time_to_next(),
                 purge_cache.time_to_next(),
+                purge_retention_at.time_to_next(),
             ];
-            let mut tasks_to_run = [false, false, false];
+            let mut tasks_to_run = [false, false, false, false];
             let start_time = Instant::now();
 
             match tokio::time::timeout(time_to_next.iter().min().copied().unwrap(), rx.recv()).await
@@ -99,7 +105,7 @@ pub fn spawn_housekeeper(core: Arc<JMAP>, settings: &Config, mut rx: mpsc::Recei
                     continue;
                 }
 
-                let core = core.clone();
+                let core = core.load();
 
                 tokio::spawn(async move {
                     match task_id {
@@ -127,6 +133,10 @@ pub fn spawn_housekeeper(core: Arc<JMAP>, settings: &Config, mut rx: mpsc::Recei
                             core.rate_limit_unauth
                                 .retain(|_, limiter| limiter.lock().is_active());
                         }
+                        TASK_PURGE_RETENTION => {
+                            tracing::info!("Purging messages past their retention period.");
+                            core.purge_expired_messages(None).await;
+                        }
                         _ => unreachable!(),
                     }
                 });
@@ -0,0 +1,378 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_parser::{Message, MessageParser};
+use store::{
+    fts::{
+        builder::FtsIndexBuilder, extract::extract_text, queue::FtsQueueEntry,
+        term_index::TokenIndex,
+    },
+    write::{key::DeserializeBigEndian, now, BatchBuilder},
+    BitmapKey, BlobKind, CustomValueKey, ValueKey,
+};
+use tokio::{
+    sync::mpsc::{self, error::TryRecvError},
+    task::JoinSet,
+};
+use utils::reload::Reloadable;
+
+use crate::{
+    auth::authenticate::AccountKey,
+    email::index::{attachment_documents, extract_attachments, AttachmentText, IndexMessageText},
+    JMAP,
+};
+
+use super::IPC_CHANNEL_BUFFER;
+
+pub enum Event {
+    Index,
+    Exit,
+}
+
+#[derive(Default)]
+pub struct IndexStats {
+    pub pending: AtomicUsize,
+    pub indexed: AtomicU64,
+    pub failed: AtomicU64,
+}
+
+const MAX_ATTEMPTS: u32 = 5;
+const REINDEX_BATCH_SIZE: usize = 1000;
+
+pub fn spawn_index_manager(handle: Reloadable<JMAP>, mut rx: mpsc::Receiver<Event>) {
+    tokio::spawn(async move {
+        tracing::debug!("Full-text index manager started.");
+
+        // Obtain the number of messages waiting to be indexed
+        let core = handle.load();
+        match core.store.fts_queue_size().await {
+            Ok(size) => {
+                core.fts_index_stats.pending.store(size, Ordering::Relaxed);
+            }
+            Err(err) => {
+                tracing::error!(
+                    context = "fts_index",
+                    event = "error",
+                    "Failed to obtain indexing queue size: {}",
+                    err
+                );
+            }
+        }
+
+        loop {
+            let core = handle.load();
+            let num_workers = core.config.fts_index_workers;
+            let batch_size = num_workers * 10;
+
+            // Index the messages that are due, using up to `num_workers` tasks
+            let mut has_more = false;
+            match core.store.fts_queue_next(now(), batch_size).await {
+                Ok(entries) => {
+                    has_more = entries.len() == batch_size;
+                    let mut entries = entries.into_iter();
+                    let mut tasks = JoinSet::new();
+                    for entry in entries.by_ref().take(num_workers) {
+                        let core = core.clone();
+                        tasks.spawn(async move { core.fts_index_queued(entry).await });
+                    }
+                    while tasks.join_next().await.is_some() {
+                        if let Some(entry) = entries.next() {
+                            let core = core.clone();
+                            tasks.spawn(async move { core.fts_index_queued(entry).await });
+                        }
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(
+                        context = "fts_index",
+                        event = "error",
+                        "Failed to read indexing queue: {}",
+                        err
+                    );
+                }
+            }
+
+            // Wait for new messages unless there are more due
+            let event = if has_more {
+                match rx.try_recv() {
+                    Ok(event) => Some(event),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => Some(Event::Exit),
+                }
+            } else {
+                match tokio::time::timeout(core.config.fts_index_poll_interval, rx.recv()).await {
+                    Ok(Some(event)) => Some(event),
+                    Ok(None) => Some(Event::Exit),
+                    Err(_) => None,
+                }
+            };
+
+            // Drain pending notifications, they are all handled by the next batch
+            let mut do_exit = matches!(event, Some(Event::Exit));
+            while !do_exit {
+                match rx.try_recv() {
+                    Ok(Event::Index) => (),
+                    Ok(Event::Exit) | Err(TryRecvError::Disconnected) => do_exit = true,
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
+            if do_exit {
+                tracing::debug!("Full-text index manager exiting.");
+                return;
+            }
+        }
+    });
+}
+
+pub fn init_index_manager() -> (mpsc::Sender<Event>, mpsc::Receiver<Event>) {
+    mpsc::channel::<Event>(IPC_CHANNEL_BUFFER)
+}
+
+impl JMAP {
+    pub fn fts_queue_has_capacity(&self) -> bool {
+        self.config.fts_index_background
+            && self.fts_index_stats.pending.load(Ordering::Relaxed)
+                < self.config.fts_index_max_pending
+    }
+
+    pub fn fts_queue_notify(&self, num_queued: usize) {
+        self.fts_index_stats
+            .pending
+            .fetch_add(num_queued, Ordering::Relaxed);
+        let _ = self.fts_index_tx.try_send(Event::Index);
+    }
+
+    async fn fts_index_queued(&self, entry: FtsQueueEntry) {
+        let mut batch = BatchBuilder::new();
+        batch.op(entry.remove());
+
+        match self
+            .fts_index_message(entry.account_id, entry.document_id)
+            .await
+        {
+            Ok(_) => {
+                self.fts_index_stats.indexed.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) if entry.attempts + 1 < MAX_ATTEMPTS => {
+                tracing::debug!(
+                    context = "fts_index",
+                    event = "retry",
+                    account_id = entry.account_id,
+                    document_id = entry.document_id,
+                    attempts = entry.attempts + 1,
+                    "Failed to index message, will retry later: {}",
+                    err
+                );
+                batch.op(entry.retry().insert());
+            }
+            Err(err) => {
+                tracing::warn!(
+                    context = "fts_index",
+                    event = "error",
+                    account_id = entry.account_id,
+                    document_id = entry.document_id,
+                    "Failed to index message: {}",
+                    err
+                );
+                self.fts_index_stats.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        match self.store.write(batch.build()).await {
+            Ok(_) => {
+                let _ = self.fts_index_stats.pending.fetch_update(
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                    |pending| Some(pending.saturating_sub(1)),
+                );
+            }
+            Err(err) => {
+                tracing::error!(
+                    context = "fts_index",
+                    event = "error",
+                    "Failed to update indexing queue: {}",
+                    err
+                );
+            }
+        }
+    }
+
+    pub async fn fts_index_message(&self, account_id: u32, document_id: u32) -> store::Result<()> {
+        // Skip messages that were deleted while queued
+        let change_id = if let Some(change_id) = self
+            .store
+            .get_value::<u64>(ValueKey::new(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::Cid,
+            ))
+            .await?
+        {
+            change_id
+        } else {
+            return Ok(());
+        };
+        let raw_message = if let Some(raw_message) = self
+            .store
+            .get_blob(
+                &BlobKind::LinkedMaildir {
+                    account_id,
+                    document_id,
+                },
+                0..u32::MAX,
+            )
+            .await?
+        {
+            raw_message
+        } else {
+            return Ok(());
+        };
+
+        // Any existing index is replaced in the same batch
+        let token_index = self
+            .store
+            .get_value::<TokenIndex>(ValueKey::term_index(
+                account_id,
+                Collection::Email,
+                document_id,
+            ))
+            .await?;
+
+        // Parsing and text extraction are CPU bound, run them on a blocking thread
+        let default_language = self.config.default_language;
+        let extract_limits = self.config.fts_extract.clone();
+        let batch = tokio::task::spawn_blocking(move || {
+            let message = MessageParser::new().parse(&raw_message)?;
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email)
+                .update_document(document_id)
+                .assert_value(Property::Cid, change_id);
+            if let Some(token_index) = token_index {
+                batch.custom(token_index);
+            }
+            batch.custom(
+                FtsIndexBuilder::with_default_language(default_language)
+                    .index_message(&message, extract_attachments(&message, &extract_limits)),
+            );
+            Some(batch.build())
+        })
+        .await
+        .map_err(|err| store::Error::InternalError(format!("Indexing task failed: {err}")))?;
+
+        if let Some(batch) = batch {
+            self.store.write(batch).await
+        } else {
+            Err(store::Error::InternalError(
+                "Failed to parse message.".to_string(),
+            ))
+        }
+    }
+
+    // Attachment text extraction is CPU bound, run it on a blocking thread
+    pub async fn fts_extract_attachments(&self, message: &Message<'_>) -> AttachmentText {
+        let extract_limits = self.config.fts_extract.clone();
+        let documents = attachment_documents(message)
+            .filter(|(_, _, bytes)| bytes.len() <= extract_limits.max_size)
+            .map(|(part_id, document_type, bytes)| (part_id, document_type, bytes.to_vec()))
+            .collect::<Vec<_>>();
+        if documents.is_empty() {
+            return AttachmentText::default();
+        }
+
+        tokio::task::spawn_blocking(move || {
+            documents
+                .into_iter()
+                .filter_map(|(part_id, document_type, bytes)| {
+                    extract_text(document_type, &bytes, &extract_limits).map(|text| (part_id, text))
+                })
+                .collect()
+        })
+        .await
+        .unwrap_or_else(|err| {
+            tracing::error!(
+                context = "fts_index",
+                event = "error",
+                "Attachment text extraction failed: {}",
+                err
+            );
+            AttachmentText::default()
+        })
+    }
+
+    // Queues the messages of an account, or of all accounts, for reindexing
+    pub async fn fts_reindex(&self, account_id: Option<u32>) -> store::Result<usize> {
+        let account_ids = if let Some(account_id) = account_id {
+            vec![account_id]
+        } else {
+            self.store
+                .iterate(
+                    Vec::new(),
+                    CustomValueKey {
+                        value: AccountKey::id_to_name(0),
+                    },
+                    CustomValueKey {
+                        value: AccountKey::id_to_name(u32::MAX),
+                    },
+                    false,
+                    true,
+                    |account_ids, key, _| {
+                        account_ids
+                            .push(key.deserialize_be_u32(key.len() - std::mem::size_of::<u32>())?);
+                        Ok(true)
+                    },
+                )
+                .await?
+        };
+
+        let mut num_queued = 0;
+        for account_id in account_ids {
+            let document_ids = self
+                .store
+                .get_bitmap(BitmapKey::document_ids(account_id, Collection::Email))
+                .await?
+                .unwrap_or_default();
+            let document_ids = document_ids.into_iter().collect::<Vec<_>>();
+
+            for chunk in document_ids.chunks(REINDEX_BATCH_SIZE) {
+                let mut batch = BatchBuilder::new();
+                for document_id in chunk {
+                    batch.op(
+                        FtsQueueEntry::new(account_id, Collection::Email, *document_id).insert(),
+                    );
+                }
+                self.store.write(batch.build()).await?;
+                num_queued += chunk.len();
+                self.fts_queue_notify(chunk.len());
+            }
+        }
+
+        Ok(num_queued)
+    }
+}
@@ -105,6 +105,7 @@ impl JMAP {
                         received_at: None,
                         skip_duplicates: true,
                         encrypt: self.config.encrypt,
+                        replace: None,
                     })
                     .await
                 }
@@ -23,6 +23,7 @@
 
 pub mod delivery;
 pub mod housekeeper;
+pub mod index;
 pub mod ingest;
 pub mod state;
 
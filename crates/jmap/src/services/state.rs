@@ -21,15 +21,12 @@
  * for more details.
 */
 
-use std::{
-    sync::Arc,
-    time::{Duration, Instant, SystemTime},
-};
+use std::time::{Duration, Instant, SystemTime};
 
 use jmap_proto::types::{id::Id, state::StateChange, type_state::DataType};
 use store::ahash::AHashMap;
 use tokio::sync::mpsc;
-use utils::{config::Config, map::bitmap::Bitmap};
+use utils::{config::Config, map::bitmap::Bitmap, reload::Reloadable};
 
 use crate::{
     push::{manager::spawn_push_manager, UpdateSubscription},
@@ -88,7 +85,7 @@ pub fn init_state_manager() -> (mpsc::Sender<Event>, mpsc::Receiver<Event>) {
 }
 
 pub fn spawn_state_manager(
-    core: Arc<JMAP>,
+    core: Reloadable<JMAP>,
     settings: &Config,
     mut change_rx: mpsc::Receiver<Event>,
 ) {
@@ -114,7 +111,7 @@ pub fn spawn_state_manager(
                 }
                 Event::UpdateSharedAccounts { account_id } => {
                     // Obtain account membership and shared mailboxes
-                    let acl = match core.get_access_token(account_id).await {
+                    let acl = match core.load().get_access_token(account_id).await {
                         Some(result) => result,
                         None => {
                             continue;
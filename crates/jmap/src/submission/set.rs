@@ -47,6 +47,7 @@ use jmap_proto::{
         value::{MaybePatchValue, SetValue, Value},
     },
 };
+use mail_parser::MessageParser;
 use smtp::{
     core::{management::QueueRequest, NullIo, Session, SessionData, State},
     queue,
@@ -57,7 +58,7 @@ use store::{
     BlobKind,
 };
 use tokio::sync::oneshot;
-use utils::{listener::ServerInstance, map::vec_map::VecMap};
+use utils::{ipc::SendPermission, listener::ServerInstance, map::vec_map::VecMap};
 
 use crate::{identity::set::sanitize_email, JMAP};
 
@@ -475,6 +476,18 @@ impl JMAP {
                 .with_description("Identity not found.")));
         };
 
+        // Make sure the account is still allowed to send from the identity address
+        let account_name = self.get_account_name(account_id).await?.unwrap_or_default();
+        let permission = self
+            .sender_permission(account_id, &account_name, &identity_mail_from)
+            .await?;
+        if permission == SendPermission::Denied {
+            return Ok(Err(SetError::new(SetErrorType::ForbiddenFrom)
+                .with_description(
+                    "You are not allowed to send from this address.",
+                )));
+        }
+
         // Make sure the envelope address matches the identity email address
         let mail_from = if let Some(mail_from) = mail_from {
             if !mail_from.address.eq_ignore_ascii_case(&identity_mail_from) {
@@ -571,7 +584,7 @@ impl JMAP {
         );
 
         // Obtain raw message
-        let message = if let Some(message) = self
+        let mut message = if let Some(message) = self
             .get_blob(
                 &BlobKind::LinkedMaildir {
                     account_id,
@@ -596,46 +609,31 @@ impl JMAP {
                 .with_description("Blob for email not found.")));
         };
 
-        // Begin local SMTP session
-        let mut session =
-            Session::<NullIo>::local(self.smtp.clone(), instance.clone(), SessionData::default());
-
-        // MAIL FROM
-        let _ = session.handle_mail_from(mail_from).await;
-        if let Some(error) = session.has_failed() {
-            return Ok(Err(SetError::new(SetErrorType::ForbiddenMailFrom)
-                .with_description(format!(
-                    "Server rejected MAIL-FROM: {}",
-                    error.trim()
-                ))));
-        }
-
-        // RCPT TO
-        let mut responses = Vec::new();
-        let mut has_success = false;
-        for rcpt in rcpt_to {
-            let addr = rcpt.address.clone();
-            let _ = session.handle_rcpt_to(rcpt).await;
-            let response = session.has_failed();
-            if response.is_none() {
-                has_success = true;
+        // Add Sender header when sending on behalf of another account
+        if let SendPermission::SendOnBehalf { sender } = permission {
+            if MessageParser::new()
+                .parse(&message)
+                .map_or(true, |message| message.sender().is_none())
+            {
+                let mut sender = format!("Sender: <{sender}>\r\n").into_bytes();
+                sender.extend_from_slice(&message);
+                message = sender;
             }
-            responses.push((addr, response));
         }
 
-        // DATA
-        if has_success {
-            session.data.message = message;
-            let response = session.queue_message().await;
-            if let State::Accepted(queue_id) = session.state {
-                submission.append(Property::MessageId, queue_id);
-            } else {
-                return Ok(Err(SetError::new(SetErrorType::ForbiddenToSend)
-                    .with_description(format!(
-                        "Server rejected DATA: {}",
-                        std::str::from_utf8(&response).unwrap().trim()
-                    ))));
+        // Submit message
+        let (queue_id, responses) = match self
+            .submit_message(instance, mail_from, rcpt_to, message)
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => {
+                return Ok(Err(err));
             }
+        };
+        let has_success = queue_id.is_some();
+        if let Some(queue_id) = queue_id {
+            submission.append(Property::MessageId, queue_id);
         }
 
         // Set responses
@@ -671,6 +669,56 @@ impl JMAP {
 
         Ok(Ok(submission))
     }
+
+    pub(crate) async fn submit_message(
+        &self,
+        instance: &Arc<ServerInstance>,
+        mail_from: MailFrom<String>,
+        rcpt_to: Vec<RcptTo<String>>,
+        message: Vec<u8>,
+    ) -> Result<(Option<u64>, Vec<(String, Option<String>)>), SetError> {
+        // Begin local SMTP session
+        let mut session =
+            Session::<NullIo>::local(self.smtp.clone(), instance.clone(), SessionData::default());
+
+        // MAIL FROM
+        let _ = session.handle_mail_from(mail_from).await;
+        if let Some(error) = session.has_failed() {
+            return Err(SetError::new(SetErrorType::ForbiddenMailFrom)
+                .with_description(format!("Server rejected MAIL-FROM: {}", error.trim())));
+        }
+
+        // RCPT TO
+        let mut responses = Vec::new();
+        let mut has_success = false;
+        for rcpt in rcpt_to {
+            let addr = rcpt.address.clone();
+            let _ = session.handle_rcpt_to(rcpt).await;
+            let response = session.has_failed();
+            if response.is_none() {
+                has_success = true;
+            }
+            responses.push((addr, response));
+        }
+
+        // DATA
+        if has_success {
+            session.data.message = message;
+            let response = session.queue_message().await;
+            if let State::Accepted(queue_id) = session.state {
+                Ok((Some(queue_id), responses))
+            } else {
+                Err(
+                    SetError::new(SetErrorType::ForbiddenToSend).with_description(format!(
+                        "Server rejected DATA: {}",
+                        std::str::from_utf8(&response).unwrap().trim()
+                    )),
+                )
+            }
+        } else {
+            Ok((None, responses))
+        }
+    }
 }
 
 fn parse_envelope_address(envelope: &Value) -> Result<(String, Option<String>), SetError> {
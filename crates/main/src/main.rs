@@ -21,17 +21,28 @@
  * for more details.
 */
 
-use std::time::Duration;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use directory::config::ConfigDirectory;
+use directory::{config::ConfigDirectory, DirectoryConfig};
 use imap::core::{ImapSessionManager, IMAP};
 use jmap::{api::JmapSessionManager, services::IPC_CHANNEL_BUFFER, JMAP};
 use managesieve::core::ManageSieveSessionManager;
 use smtp::core::{SmtpSessionManager, SMTP};
-use tokio::sync::mpsc;
+use store::Store;
+use tokio::sync::{mpsc, watch};
 use utils::{
-    config::{Config, ServerProtocol},
-    enable_tracing, wait_for_shutdown, UnwrapFailure,
+    config::{certificate::CertificateResolver, Config, ServerProtocol},
+    enable_tracing,
+    ipc::ReloadEvent,
+    reload::Reloadable,
+    spawn_reload_handler, wait_for_shutdown, UnwrapFailure,
 };
 
 #[cfg(not(target_env = "msvc"))]
@@ -43,9 +54,8 @@ static GLOBAL: Jemalloc = Jemalloc;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
-    let config = Config::init();
+    let mut config = Config::init();
     let servers = config.parse_servers().failed("Invalid configuration");
-    let directory = config.parse_directory().failed("Invalid configuration");
 
     // Bind ports and drop privileges
     servers.bind(&config);
@@ -60,20 +70,59 @@ async fn main() -> std::io::Result<()> {
     )
     .failed("Failed to enable tracing");
 
+    // Open the store and layer the settings kept in the database
+    let store = Arc::new(Store::open(&config).await.failed("Unable to open database"));
+    let settings_version = Arc::new(AtomicU64::new(
+        store
+            .settings_version()
+            .await
+            .failed("Failed to read settings from database"),
+    ));
+    config.apply_settings(
+        store
+            .settings_list("")
+            .await
+            .failed("Failed to read settings from database"),
+    );
+    let directory = config.parse_directory().failed("Invalid configuration");
+
     // Init servers
     let (delivery_tx, delivery_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
-    let smtp = SMTP::init(&config, &servers, &directory, delivery_tx)
-        .await
-        .failed("Invalid configuration file");
-    let jmap = JMAP::init(&config, &directory, delivery_rx, smtp.clone())
-        .await
-        .failed("Invalid configuration file");
-    let imap = IMAP::init(&config)
+    let (reload_tx, mut reload_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
+    let smtp = SMTP::init(&config, &servers, &directory, store.clone(), delivery_tx)
         .await
         .failed("Invalid configuration file");
+    let jmap = JMAP::init(
+        &config,
+        &directory,
+        store.clone(),
+        delivery_rx,
+        reload_tx.clone(),
+        smtp.load(),
+    )
+    .await
+    .failed("Invalid configuration file");
+    let imap = Reloadable::new(
+        IMAP::init(&config)
+            .await
+            .failed("Invalid configuration file"),
+    );
+
+    // Keep track of the listeners, their settings can only be changed by restarting
+    let listeners = servers
+        .inner
+        .iter()
+        .map(|server| {
+            (
+                server.id.clone(),
+                server.protocol,
+                server.tls_certificates.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
 
     // Spawn servers
-    let (shutdown_tx, shutdown_rx) = servers.spawn(|server, shutdown_rx| {
+    let (shutdown_tx, _) = servers.spawn(|server, shutdown_rx| {
         match &server.protocol {
             ServerProtocol::Smtp | ServerProtocol::Lmtp => {
                 server.spawn(SmtpSessionManager::new(smtp.clone()), shutdown_rx)
@@ -96,22 +145,207 @@ async fn main() -> std::io::Result<()> {
     });
 
     // Spawn scheduled directory queries
-    for schedule in directory.schedules {
-        schedule.spawn(shutdown_rx.clone());
-    }
+    let mut schedules_tx = spawn_schedules(directory);
+
+    // Reload configuration on SIGHUP or when the database settings change
+    spawn_settings_poller(
+        store.clone(),
+        settings_version.clone(),
+        config
+            .property_or_static::<Duration>("settings.poll-interval", "30s")
+            .failed("Invalid configuration"),
+        reload_tx.clone(),
+    );
+    spawn_reload_handler(reload_tx);
 
     // Wait for shutdown signal
-    wait_for_shutdown(&format!(
+    let shutdown_message = format!(
         "Shutting down Stalwart Mail Server v{}...",
         env!("CARGO_PKG_VERSION")
-    ))
-    .await;
+    );
+    let shutdown = wait_for_shutdown(&shutdown_message);
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            Some(event) = reload_rx.recv() => {
+                let result = match reload(
+                    event.settings,
+                    &store,
+                    &settings_version,
+                    &smtp,
+                    &jmap,
+                    &imap,
+                    &listeners,
+                )
+                .await
+                {
+                    Ok(directory) => {
+                        // Replace scheduled directory queries
+                        let _ = schedules_tx.send(true);
+                        schedules_tx = spawn_schedules(directory);
+
+                        tracing::info!(
+                            context = "config",
+                            event = "reload",
+                            "Configuration reloaded."
+                        );
+                        Ok(())
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            context = "config",
+                            event = "error",
+                            "Failed to reload configuration: {}",
+                            err
+                        );
+                        Err(err)
+                    }
+                };
+
+                if let Some(result_tx) = event.result_tx {
+                    let _ = result_tx.send(result);
+                }
+            }
+        }
+    }
 
     // Stop services
     let _ = shutdown_tx.send(true);
+    let _ = schedules_tx.send(true);
 
     // Wait for services to finish
     tokio::time::sleep(Duration::from_secs(1)).await;
 
     Ok(())
 }
+
+#[allow(clippy::too_many_arguments)]
+async fn reload(
+    settings: Vec<(String, Option<String>)>,
+    store: &Store,
+    settings_version: &AtomicU64,
+    smtp: &Reloadable<SMTP>,
+    jmap: &Reloadable<JMAP>,
+    imap: &Reloadable<IMAP>,
+    listeners: &[(
+        String,
+        ServerProtocol,
+        Option<Reloadable<CertificateResolver>>,
+    )],
+) -> utils::config::Result<DirectoryConfig> {
+    // Parse and validate the new configuration, including any pending changes
+    // to the database settings
+    let mut config = Config::read_file(
+        &Config::config_path().ok_or("Missing parameter --config=<path-to-config>.")?,
+    )?;
+    let mut stored_settings = store
+        .settings_list("")
+        .await
+        .map_err(|err| format!("Failed to read settings from database: {err}"))?
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    for (key, value) in &settings {
+        if let Some(value) = value {
+            stored_settings.insert(key.clone(), value.clone());
+        } else {
+            stored_settings.remove(key);
+        }
+    }
+    config.apply_settings(stored_settings);
+    let servers = config.parse_servers()?;
+    if servers.inner.len() != listeners.len()
+        || servers
+            .inner
+            .iter()
+            .zip(listeners)
+            .any(|(server, (id, protocol, certificates))| {
+                &server.id != id
+                    || &server.protocol != protocol
+                    || server.tls_certificates.is_some() != certificates.is_some()
+            })
+    {
+        return Err("Changes to listeners require a restart.".to_string());
+    }
+    let directory = config.parse_directory()?;
+    let new_smtp = Arc::new(smtp.load().reload(&config, &servers, &directory)?);
+    let new_jmap = jmap.load().reload(&config, &directory, new_smtp.clone())?;
+    let new_imap = IMAP::init(&config).await?;
+
+    // Commit the changes to the database settings
+    if !settings.is_empty() {
+        settings_version.store(
+            store
+                .settings_update(settings)
+                .await
+                .map_err(|err| format!("Failed to write settings to database: {err}"))?,
+            Ordering::Relaxed,
+        );
+    }
+
+    // Swap TLS certificates and cores, existing sessions keep their snapshot
+    for (server, (_, _, certificates)) in servers.inner.iter().zip(listeners) {
+        if let (Some(new_certificates), Some(certificates)) =
+            (&server.tls_certificates, certificates)
+        {
+            certificates.store(new_certificates.load());
+        }
+    }
+    smtp.store(new_smtp);
+    jmap.store(new_jmap);
+    imap.store(new_imap);
+
+    Ok(directory)
+}
+
+fn spawn_schedules(directory: DirectoryConfig) -> watch::Sender<bool> {
+    let (schedules_tx, schedules_rx) = watch::channel(false);
+    for schedule in directory.schedules {
+        schedule.spawn(schedules_rx.clone());
+    }
+    schedules_tx
+}
+
+fn spawn_settings_poller(
+    store: Arc<Store>,
+    settings_version: Arc<AtomicU64>,
+    poll_interval: Duration,
+    reload_tx: mpsc::Sender<ReloadEvent>,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(poll_interval).await;
+
+            match store.settings_version().await {
+                Ok(version) if version != settings_version.load(Ordering::Relaxed) => {
+                    tracing::debug!(
+                        context = "config",
+                        event = "changed",
+                        "Database settings changed, reloading configuration."
+                    );
+                    settings_version.store(version, Ordering::Relaxed);
+                    if reload_tx
+                        .send(ReloadEvent {
+                            settings: Vec::new(),
+                            result_tx: None,
+                        })
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Ok(_) => (),
+                Err(err) => {
+                    tracing::warn!(
+                        context = "config",
+                        event = "error",
+                        "Failed to read settings version from database: {}",
+                        err
+                    );
+                }
+            }
+        }
+    });
+}
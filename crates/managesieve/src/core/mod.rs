@@ -37,7 +37,10 @@ use tokio::{
     net::TcpStream,
 };
 use tokio_rustls::server::TlsStream;
-use utils::listener::{limiter::InFlight, ServerInstance};
+use utils::{
+    listener::{limiter::InFlight, ServerInstance},
+    reload::Reloadable,
+};
 
 pub struct Session<T: AsyncRead + AsyncWrite> {
     pub jmap: Arc<JMAP>,
@@ -72,13 +75,16 @@ impl State {
 
 #[derive(Clone)]
 pub struct ManageSieveSessionManager {
-    pub jmap: Arc<JMAP>,
-    pub imap: Arc<IMAP>,
+    pub jmap: Reloadable<JMAP>,
+    pub imap: Reloadable<IMAP>,
 }
 
 impl ManageSieveSessionManager {
-    pub fn new(jmap: Arc<JMAP>, imap: Arc<IMAP>) -> Self {
-        Self { jmap, imap }
+    pub fn new(jmap: impl Into<Reloadable<JMAP>>, imap: impl Into<Reloadable<IMAP>>) -> Self {
+        Self {
+            jmap: jmap.into(),
+            imap: imap.into(),
+        }
     }
 }
 
@@ -37,17 +37,18 @@ use super::{IsTls, ManageSieveSessionManager, Session, State};
 impl SessionManager for ManageSieveSessionManager {
     fn spawn(&self, session: utils::listener::SessionData<TcpStream>) {
         // Create session
+        let imap = self.imap.load();
         let mut session = Session {
-            jmap: self.jmap.clone(),
-            imap: self.imap.clone(),
+            jmap: self.jmap.load(),
+            receiver: Receiver::with_max_request_size(imap.max_request_size)
+                .with_start_state(receiver::State::Command { is_uid: false }),
+            imap,
             instance: session.instance,
             state: State::NotAuthenticated { auth_failures: 0 },
             span: session.span,
             stream: session.stream,
             in_flight: session.in_flight,
             remote_addr: RemoteAddress::IpAddress(session.remote_ip),
-            receiver: Receiver::with_max_request_size(self.imap.max_request_size)
-                .with_start_state(receiver::State::Command { is_uid: false }),
         };
 
         tokio::spawn(async move {
@@ -31,6 +31,7 @@ use imap_proto::{
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use tokio::io::{AsyncRead, AsyncWrite};
+use utils::metrics;
 
 use crate::core::{Command, IsTls, Session, State, StatusResponse};
 
@@ -112,6 +113,7 @@ impl<T: AsyncRead + AsyncWrite + IsTls> Session<T> {
                 }
             }
         };
+        metrics::authentication("managesieve", access_token.is_some());
 
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
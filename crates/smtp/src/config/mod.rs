@@ -31,9 +31,10 @@ pub mod resolver;
 pub mod scripts;
 pub mod session;
 pub mod throttle;
+pub mod webhook;
 
 use std::{
-    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::PathBuf,
     sync::{atomic::AtomicU64, Arc},
     time::Duration,
@@ -211,6 +212,7 @@ pub struct Ehlo {
 pub struct Extensions {
     pub pipelining: IfBlock<bool>,
     pub chunking: IfBlock<bool>,
+    pub burl: IfBlock<bool>,
     pub requiretls: IfBlock<bool>,
     pub dsn: IfBlock<bool>,
     pub vrfy: IfBlock<bool>,
@@ -226,6 +228,7 @@ pub struct Auth {
     pub mechanisms: IfBlock<u64>,
     pub require: IfBlock<bool>,
     pub allow_plain_text: IfBlock<bool>,
+    pub must_match_sender: IfBlock<bool>,
     pub errors_max: IfBlock<usize>,
     pub errors_wait: IfBlock<Duration>,
 }
@@ -345,13 +348,69 @@ pub struct QueueConfig {
 
     // Throttle and Quotas
     pub throttle: QueueThrottle,
+    pub adaptive: QueueAdaptiveThrottle,
     pub quota: QueueQuotas,
+    pub quarantine: QueueQuarantine,
+    pub journal: QueueJournal,
     pub management_lookup: Arc<dyn Directory>,
+
+    // Metrics
+    pub metrics_max_domains: usize,
+}
+
+pub struct QueueQuarantine {
+    pub path: Option<PathBuf>,
+    pub retention: Duration,
+}
+
+pub struct QueueJournal {
+    pub address: IfBlock<Option<String>>,
+    pub path: IfBlock<Option<PathBuf>>,
 }
 
 pub struct QueueOutboundSourceIp {
     pub ipv4: IfBlock<Vec<Ipv4Addr>>,
     pub ipv6: IfBlock<Vec<Ipv6Addr>>,
+    pub pool: IfBlock<Option<Arc<IpPool>>>,
+    pub pool_header: IfBlock<Option<String>>,
+    pub pools: AHashMap<String, Arc<IpPool>>,
+}
+
+#[derive(Debug)]
+pub struct IpPool {
+    pub name: String,
+    pub ips: Vec<PoolIp>,
+    pub fallback: Option<String>,
+    pub dnsbl: Vec<String>,
+    pub dnsbl_ttl: Duration,
+}
+
+#[derive(Debug)]
+pub struct PoolIp {
+    pub addr: IpAddr,
+    pub warmup: Option<IpWarmup>,
+}
+
+#[derive(Debug)]
+pub struct IpWarmup {
+    pub start: u64,
+    pub schedule: Vec<u64>,
+}
+
+pub struct WebhookConfig {
+    pub path: PathBuf,
+    pub endpoints: Vec<Arc<Webhook>>,
+    pub events: u32,
+}
+
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub events: u32,
+    pub signature_key: Option<Vec<u8>>,
+    pub retry: Vec<Duration>,
+    pub headers: Vec<(String, String)>,
+    pub client: reqwest::Client,
 }
 
 pub struct ReportConfig {
@@ -438,6 +497,24 @@ pub struct QueueThrottle {
     pub host: Vec<Throttle>,
 }
 
+pub struct QueueAdaptiveThrottle {
+    pub enable: IfBlock<bool>,
+    pub key: AdaptiveThrottleKey,
+    pub concurrency_min: u64,
+    pub concurrency_max: u64,
+    pub rate_min: u64,
+    pub rate_max: Rate,
+    pub backoff: f64,
+    pub recover_after: u32,
+    pub jitter: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdaptiveThrottleKey {
+    RecipientDomain,
+    Mx,
+}
+
 pub struct QueueQuotas {
     pub sender: Vec<QueueQuota>,
     pub rcpt: Vec<QueueQuota>,
@@ -34,12 +34,22 @@ use super::{
 };
 use utils::config::{
     utils::{AsKey, ParseValue},
-    Config, DynValue,
+    Config, DynValue, Rate,
 };
 
 pub trait ConfigQueue {
     fn parse_queue(&self, ctx: &ConfigContext) -> super::Result<QueueConfig>;
     fn parse_queue_throttle(&self, ctx: &ConfigContext) -> super::Result<QueueThrottle>;
+    fn parse_queue_adaptive_throttle(
+        &self,
+        ctx: &ConfigContext,
+    ) -> super::Result<QueueAdaptiveThrottle>;
+    fn parse_queue_source_ip(
+        &self,
+        ctx: &ConfigContext,
+        available_keys: &[EnvelopeKey],
+    ) -> super::Result<QueueOutboundSourceIp>;
+    fn parse_ip_pool(&self, id: &str) -> super::Result<IpPool>;
     fn parse_queue_quota(&self, ctx: &ConfigContext) -> super::Result<QueueQuotas>;
     fn parse_queue_quota_item(
         &self,
@@ -68,6 +78,13 @@ impl ConfigQueue for Config {
             EnvelopeKey::Priority,
             EnvelopeKey::Mx,
         ];
+        let journal_envelope_keys = [
+            EnvelopeKey::Recipient,
+            EnvelopeKey::RecipientDomain,
+            EnvelopeKey::Sender,
+            EnvelopeKey::SenderDomain,
+            EnvelopeKey::Priority,
+        ];
         let host_envelope_keys = [
             EnvelopeKey::RecipientDomain,
             EnvelopeKey::Sender,
@@ -129,14 +146,7 @@ impl ConfigQueue for Config {
             ip_strategy: self
                 .parse_if_block("queue.outbound.ip-strategy", ctx, &sender_envelope_keys)?
                 .unwrap_or_else(|| IfBlock::new(IpLookupStrategy::Ipv4thenIpv6)),
-            source_ip: QueueOutboundSourceIp {
-                ipv4: self
-                    .parse_if_block("queue.outbound.source-ip.v4", ctx, &mx_envelope_keys)?
-                    .unwrap_or_else(|| IfBlock::new(Vec::new())),
-                ipv6: self
-                    .parse_if_block("queue.outbound.source-ip.v6", ctx, &mx_envelope_keys)?
-                    .unwrap_or_else(|| IfBlock::new(Vec::new())),
-            },
+            source_ip: self.parse_queue_source_ip(ctx, &mx_envelope_keys)?,
             next_hop: next_hop.into_relay_host(ctx)?,
             tls: QueueOutboundTls {
                 dane: self
@@ -157,6 +167,7 @@ impl ConfigQueue for Config {
                     .unwrap_or_else(|| IfBlock::new(false)),
             },
             throttle: self.parse_queue_throttle(ctx)?,
+            adaptive: self.parse_queue_adaptive_throttle(ctx)?,
             quota: self.parse_queue_quota(ctx)?,
             timeout: QueueOutboundTimeout {
                 connect: self
@@ -204,6 +215,20 @@ impl ConfigQueue for Config {
                     .unwrap_or_default()
                     .map_if_block(&ctx.signers, "report.dsn.sign", "signature")?,
             },
+            quarantine: QueueQuarantine {
+                path: self.property("queue.quarantine.path")?,
+                retention: self
+                    .property("queue.quarantine.retention")?
+                    .unwrap_or(Duration::from_secs(30 * 86400)),
+            },
+            journal: QueueJournal {
+                address: self
+                    .parse_if_block("queue.journal.address", ctx, &journal_envelope_keys)?
+                    .unwrap_or_else(|| IfBlock::new(None)),
+                path: self
+                    .parse_if_block("queue.journal.path", ctx, &journal_envelope_keys)?
+                    .unwrap_or_else(|| IfBlock::new(None)),
+            },
             management_lookup: if let Some(id) = self.value("management.directory") {
                 ctx.directory
                     .directories
@@ -215,6 +240,9 @@ impl ConfigQueue for Config {
             } else {
                 Arc::new(MemoryDirectory::default())
             },
+            metrics_max_domains: self
+                .property("metrics.prometheus.queue.max-domains")?
+                .unwrap_or(25),
         };
 
         if config.retry.has_empty_list() {
@@ -286,6 +314,174 @@ impl ConfigQueue for Config {
         Ok(throttle)
     }
 
+    fn parse_queue_adaptive_throttle(
+        &self,
+        ctx: &ConfigContext,
+    ) -> super::Result<QueueAdaptiveThrottle> {
+        let prefix = "queue.outbound.adaptive";
+        let adaptive = QueueAdaptiveThrottle {
+            enable: self
+                .parse_if_block(
+                    (prefix, "enable"),
+                    ctx,
+                    &[
+                        EnvelopeKey::RecipientDomain,
+                        EnvelopeKey::Sender,
+                        EnvelopeKey::SenderDomain,
+                        EnvelopeKey::Priority,
+                        EnvelopeKey::Mx,
+                    ],
+                )?
+                .unwrap_or_else(|| IfBlock::new(false)),
+            key: self.property_or_static::<AdaptiveThrottleKey>((prefix, "key"), "mx")?,
+            concurrency_min: self
+                .property_or_static::<u64>((prefix, "concurrency.min"), "1")?
+                .max(1),
+            concurrency_max: self.property_or_static::<u64>((prefix, "concurrency.max"), "10")?,
+            rate_min: self
+                .property_or_static::<u64>((prefix, "rate.min"), "1")?
+                .max(1),
+            rate_max: self.property_or_static::<Rate>((prefix, "rate.max"), "100/1m")?,
+            backoff: self.property_or_static::<f64>((prefix, "backoff"), "0.5")?,
+            recover_after: self
+                .property_or_static::<u32>((prefix, "recover-after"), "10")?
+                .max(1),
+            jitter: self.property_or_static::<f64>((prefix, "jitter"), "0.25")?,
+        };
+
+        // Validate
+        if adaptive.concurrency_max < adaptive.concurrency_min {
+            Err(format!(
+                "Property \"{prefix}.concurrency.max\" cannot be lower than \"{prefix}.concurrency.min\"."
+            ))
+        } else if adaptive.rate_max.requests < adaptive.rate_min {
+            Err(format!(
+                "Property \"{prefix}.rate.max\" cannot be lower than \"{prefix}.rate.min\"."
+            ))
+        } else if !(f64::EPSILON..1.0).contains(&adaptive.backoff) {
+            Err(format!(
+                "Property \"{prefix}.backoff\" must be a value between 0 and 1."
+            ))
+        } else if !(0.0..=1.0).contains(&adaptive.jitter) {
+            Err(format!(
+                "Property \"{prefix}.jitter\" must be a value between 0 and 1."
+            ))
+        } else {
+            Ok(adaptive)
+        }
+    }
+
+    fn parse_queue_source_ip(
+        &self,
+        ctx: &ConfigContext,
+        available_keys: &[EnvelopeKey],
+    ) -> super::Result<QueueOutboundSourceIp> {
+        // Parse IP pools
+        let mut pools = AHashMap::new();
+        for id in self.sub_keys("queue.outbound.ip-pool") {
+            pools.insert(id.to_string(), Arc::new(self.parse_ip_pool(id)?));
+        }
+        for pool in pools.values() {
+            if let Some(fallback) = &pool.fallback {
+                if fallback == &pool.name || !pools.contains_key(fallback) {
+                    return Err(format!(
+                        "Invalid fallback pool {fallback:?} for IP pool {:?}.",
+                        pool.name
+                    ));
+                }
+            }
+        }
+
+        // The pool is selected when the message is queued, which means
+        // that session properties are available rather than delivery ones.
+        let session_keys = [
+            EnvelopeKey::Sender,
+            EnvelopeKey::SenderDomain,
+            EnvelopeKey::AuthenticatedAs,
+            EnvelopeKey::Listener,
+            EnvelopeKey::RemoteIp,
+            EnvelopeKey::LocalIp,
+            EnvelopeKey::Priority,
+        ];
+
+        Ok(QueueOutboundSourceIp {
+            ipv4: self
+                .parse_if_block("queue.outbound.source-ip.v4", ctx, available_keys)?
+                .unwrap_or_else(|| IfBlock::new(Vec::new())),
+            ipv6: self
+                .parse_if_block("queue.outbound.source-ip.v6", ctx, available_keys)?
+                .unwrap_or_else(|| IfBlock::new(Vec::new())),
+            pool: self
+                .parse_if_block::<Option<String>>(
+                    "queue.outbound.source-ip.pool",
+                    ctx,
+                    &session_keys,
+                )?
+                .unwrap_or_default()
+                .map_if_block(&pools, "queue.outbound.source-ip.pool", "IP pool")?,
+            pool_header: self
+                .parse_if_block("queue.outbound.source-ip.pool-header", ctx, &session_keys)?
+                .unwrap_or_default(),
+            pools,
+        })
+    }
+
+    fn parse_ip_pool(&self, id: &str) -> super::Result<IpPool> {
+        let prefix = "queue.outbound.ip-pool";
+        let mut pool = IpPool {
+            name: id.to_string(),
+            ips: Vec::new(),
+            fallback: self.property((prefix, id, "fallback"))?,
+            dnsbl: self
+                .values((prefix, id, "dnsbl"))
+                .map(|(_, zone)| zone.trim_end_matches('.').to_lowercase())
+                .collect(),
+            dnsbl_ttl: self.property_or_static((prefix, id, "dnsbl-ttl"), "1h")?,
+        };
+        for (key, is_ipv4) in [("v4", true), ("v6", false)] {
+            for value in self.properties::<IpAddr>((prefix, id, key)) {
+                let (key, addr) = value?;
+                if addr.is_ipv4() != is_ipv4 {
+                    return Err(format!(
+                        "Invalid address {addr} for property {key:?}: address family mismatch."
+                    ));
+                }
+                pool.ips.push(PoolIp { addr, warmup: None });
+            }
+        }
+        if pool.ips.is_empty() {
+            return Err(format!("IP pool {id:?} does not contain any addresses."));
+        }
+
+        // Parse warm-up schedules
+        for array_pos in self.sub_keys((prefix, id, "warmup")) {
+            let prefix = format!("{prefix}.{id}.warmup.{array_pos}");
+            let addr = self.property_require::<IpAddr>((prefix.as_str(), "ip"))?;
+            let start = self.value_require((prefix.as_str(), "start"))?;
+            let start = mail_parser::DateTime::parse_rfc3339(start)
+                .filter(|dt| dt.is_valid())
+                .ok_or_else(|| {
+                    format!("Invalid RFC3339 date {start:?} for property \"{prefix}.start\".")
+                })?
+                .to_timestamp() as u64;
+            let mut schedule = Vec::new();
+            for value in self.properties::<u64>((prefix.as_str(), "schedule")) {
+                schedule.push(value?.1);
+            }
+            if schedule.is_empty() {
+                return Err(format!("Missing property \"{prefix}.schedule\"."));
+            }
+
+            pool.ips
+                .iter_mut()
+                .find(|ip| ip.addr == addr)
+                .ok_or_else(|| format!("Warm-up address {addr} not found in IP pool {id:?}."))?
+                .warmup = IpWarmup { start, schedule }.into();
+        }
+
+        Ok(pool)
+    }
+
     fn parse_queue_quota(&self, ctx: &ConfigContext) -> super::Result<QueueQuotas> {
         let mut capacities = QueueQuotas {
             sender: Vec::new(),
@@ -454,6 +650,20 @@ impl From<&Host> for RelayHost {
     }
 }
 
+impl ParseValue for AdaptiveThrottleKey {
+    fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
+        match value {
+            "mx" => Ok(AdaptiveThrottleKey::Mx),
+            "rcpt-domain" => Ok(AdaptiveThrottleKey::RecipientDomain),
+            _ => Err(format!(
+                "Invalid adaptive throttle key {:?} for key {:?}.",
+                value,
+                key.as_key()
+            )),
+        }
+    }
+}
+
 impl ParseValue for RequireOptional {
     fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
         match value {
@@ -197,6 +197,9 @@ impl ConfigSession for Config {
             chunking: self
                 .parse_if_block("session.extensions.chunking", ctx, &available_keys)?
                 .unwrap_or_else(|| IfBlock::new(true)),
+            burl: self
+                .parse_if_block("session.extensions.burl", ctx, &available_keys)?
+                .unwrap_or_default(),
             requiretls: self
                 .parse_if_block("session.extensions.requiretls", ctx, &available_keys)?
                 .unwrap_or_default(),
@@ -287,6 +290,9 @@ impl ConfigSession for Config {
             allow_plain_text: self
                 .parse_if_block("session.auth.allow-plain-text", ctx, &available_keys)?
                 .unwrap_or_else(|| IfBlock::new(false)),
+            must_match_sender: self
+                .parse_if_block("session.auth.must-match-sender", ctx, &available_keys)?
+                .unwrap_or_else(|| IfBlock::new(false)),
         })
     }
 
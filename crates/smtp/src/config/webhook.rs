@@ -0,0 +1,138 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{sync::Arc, time::Duration};
+
+use utils::config::{
+    utils::{AsKey, ParseValue},
+    Config,
+};
+
+use crate::{webhook::WebhookType, USER_AGENT};
+
+use super::{Webhook, WebhookConfig};
+
+pub trait ConfigWebhook {
+    fn parse_webhooks(&self) -> super::Result<WebhookConfig>;
+    fn parse_webhook(&self, id: &str) -> super::Result<Webhook>;
+}
+
+impl ConfigWebhook for Config {
+    fn parse_webhooks(&self) -> super::Result<WebhookConfig> {
+        let mut endpoints = Vec::new();
+        let mut events = 0;
+
+        for id in self.sub_keys("webhook.endpoint") {
+            let webhook = self.parse_webhook(id)?;
+            events |= webhook.events;
+            endpoints.push(Arc::new(webhook));
+        }
+
+        Ok(WebhookConfig {
+            path: if !endpoints.is_empty() {
+                self.property_require("webhook.path")?
+            } else {
+                self.property("webhook.path")?.unwrap_or_default()
+            },
+            endpoints,
+            events,
+        })
+    }
+
+    fn parse_webhook(&self, id: &str) -> super::Result<Webhook> {
+        let mut events = 0;
+        for event in self.properties::<WebhookType>(("webhook.endpoint", id, "events")) {
+            events |= event?.1.mask();
+        }
+        if events == 0 {
+            return Err(format!(
+                "No events configured for webhook {id:?}, set \"webhook.endpoint.{id}.events\"."
+            ));
+        }
+
+        let mut retry = Vec::new();
+        for duration in self.properties::<Duration>(("webhook.endpoint", id, "retry")) {
+            retry.push(duration?.1);
+        }
+        if retry.is_empty() && !self.contains_key(("webhook.endpoint", id, "retry")) {
+            retry = [60, 5 * 60, 15 * 60, 3600, 6 * 3600, 86400]
+                .into_iter()
+                .map(Duration::from_secs)
+                .collect();
+        }
+
+        let mut headers = Vec::new();
+        for (key, value) in self.values(("webhook.endpoint", id, "headers")) {
+            if let Some((name, value)) = value.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            } else {
+                return Err(format!(
+                    "Invalid header {value:?} for property {key:?}, expected \"Name: value\"."
+                ));
+            }
+        }
+
+        Ok(Webhook {
+            id: id.to_string(),
+            url: self.property_require(("webhook.endpoint", id, "url"))?,
+            events,
+            signature_key: self
+                .value(("webhook.endpoint", id, "signature-key"))
+                .map(|key| key.as_bytes().to_vec()),
+            retry,
+            headers,
+            client: reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(
+                    self.property(("webhook.endpoint", id, "timeout"))?
+                        .unwrap_or(Duration::from_secs(30)),
+                )
+                .danger_accept_invalid_certs(
+                    self.property(("webhook.endpoint", id, "tls.allow-invalid-certs"))?
+                        .unwrap_or(false),
+                )
+                .build()
+                .map_err(|err| format!("Failed to build HTTP client for webhook {id:?}: {err}"))?,
+        })
+    }
+}
+
+impl ParseValue for WebhookType {
+    fn parse_value(key: impl AsKey, value: &str) -> super::Result<Self> {
+        match value {
+            "message.accepted" => Ok(WebhookType::MessageAccepted),
+            "message.delivered" => Ok(WebhookType::MessageDelivered),
+            "message.deferred" => Ok(WebhookType::MessageDeferred),
+            "message.bounced" => Ok(WebhookType::MessageBounced),
+            "message.expired" => Ok(WebhookType::MessageExpired),
+            "message.quarantined" => Ok(WebhookType::MessageQuarantined),
+            "rcpt.rejected" => Ok(WebhookType::RcptRejected),
+            "auth.failed" => Ok(WebhookType::AuthFailed),
+            _ => Err(format!(
+                "Invalid webhook event {:?} for key {:?}.",
+                value,
+                key.as_key()
+            )),
+        }
+    }
+}
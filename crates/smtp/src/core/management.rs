@@ -21,7 +21,13 @@
  * for more details.
 */
 
-use std::{borrow::Cow, fmt::Display, net::IpAddr, sync::Arc, time::Instant};
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    net::IpAddr,
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
 
 use directory::Type;
 use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full};
@@ -44,9 +50,13 @@ use tokio::{
 use utils::listener::{limiter::InFlight, SessionManager};
 
 use crate::{
-    queue::{self, instant_to_timestamp, InstantFromTimestamp, QueueId, Status},
+    queue::{
+        self, instant_to_timestamp, quarantine::QuarantineItem, InstantFromTimestamp, QueueId,
+        Status,
+    },
     reporting::{
         self,
+        inbox::{IncomingReportFilter, IncomingReportType, SourceSummary},
         scheduler::{ReportKey, ReportPolicy, ReportType, ReportValue},
     },
 };
@@ -142,6 +152,19 @@ pub struct Recipient {
     pub orcpt: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AdaptiveLimit {
+    pub key: String,
+    pub concurrency: u64,
+    pub in_flight: u64,
+    pub rate: u64,
+    pub rate_period: u64,
+    pub deferrals: u64,
+    #[serde(deserialize_with = "deserialize_maybe_datetime")]
+    #[serde(serialize_with = "serialize_maybe_datetime")]
+    pub last_deferral: Option<DateTime>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Report {
     pub domain: String,
@@ -158,7 +181,7 @@ pub struct Report {
 
 impl SessionManager for SmtpAdminSessionManager {
     fn spawn(&self, session: utils::listener::SessionData<tokio::net::TcpStream>) {
-        let core = self.inner.clone();
+        let core = self.inner.load();
         tokio::spawn(async move {
             if let Some(tls_acceptor) = &session.instance.tls_acceptor {
                 match tls_acceptor.accept(session.stream).await {
@@ -235,6 +258,7 @@ impl SMTP {
     ) -> Result<hyper::Response<BoxBody<Bytes, hyper::Error>>, hyper::Error> {
         // Authenticate request
         let mut is_authenticated = false;
+        let mut owner: Option<Vec<String>> = None;
         if let Some((mechanism, payload)) = req
             .headers()
             .get(AUTHORIZATION)
@@ -261,6 +285,35 @@ impl SMTP {
                         Ok(Some(principal)) if principal.typ == Type::Superuser => {
                             is_authenticated = true;
                         }
+                        Ok(Some(principal))
+                            if self.queue.config.quarantine.path.is_some()
+                                && req.uri().path().starts_with("/admin/quarantine/") =>
+                        {
+                            // Users can only access their own quarantined messages
+                            match self
+                                .queue
+                                .config
+                                .management_lookup
+                                .emails_by_name(&principal.name)
+                                .await
+                            {
+                                Ok(emails) => {
+                                    owner = emails
+                                        .into_iter()
+                                        .map(|email| email.to_lowercase())
+                                        .collect::<Vec<_>>()
+                                        .into();
+                                    is_authenticated = true;
+                                }
+                                Err(_) => {
+                                    tracing::debug!(
+                                        context = "management",
+                                        event = "auth-error",
+                                        "Temporary authentication failure."
+                                    );
+                                }
+                            }
+                        }
                         Ok(Some(_)) => {
                             tracing::debug!(
                                 context = "management",
@@ -314,6 +367,13 @@ impl SMTP {
         let mut path = req.uri().path().split('/');
         path.next();
         path.next(); // Skip the leading /admin
+        if let Some(owner) = owner {
+            path.next(); // Skip quarantine
+            let (status, response) = self
+                .handle_quarantine_request(req.uri(), path.next().unwrap_or_default(), Some(&owner))
+                .await;
+            return Ok(json_response(status, response));
+        }
         Ok(self
             .handle_manage_request(
                 req.uri(),
@@ -530,6 +590,69 @@ impl SMTP {
                     Some(error) => error.into_bad_request(),
                 }
             }
+            (&Method::GET, "queue", "limits") => {
+                let mut key = None;
+                let mut error = None;
+
+                if let Some(query) = uri.query() {
+                    for (param, value) in form_urlencoded::parse(query.as_bytes()) {
+                        match param.as_ref() {
+                            "key" | "domain" | "mx" => {
+                                key = value.to_lowercase().into();
+                            }
+                            _ => {
+                                error = format!("Invalid parameter {param:?}.").into();
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                match error {
+                    None => {
+                        let now = Instant::now();
+                        let now_timestamp = SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .map_or(0, |d| d.as_secs());
+                        let mut result = self
+                            .queue
+                            .adaptive
+                            .iter()
+                            .filter(|entry| {
+                                key.as_ref()
+                                    .map_or(true, |key| entry.key().contains(key.as_str()))
+                            })
+                            .map(|entry| {
+                                let limiter = entry.value();
+                                AdaptiveLimit {
+                                    key: entry.key().clone(),
+                                    concurrency: limiter.concurrency.max_concurrent,
+                                    in_flight: limiter
+                                        .concurrency
+                                        .concurrent
+                                        .load(std::sync::atomic::Ordering::Relaxed),
+                                    rate: limiter.rate.max_requests,
+                                    rate_period: limiter.rate.max_interval.as_secs(),
+                                    deferrals: limiter.deferrals,
+                                    last_deferral: limiter.last_deferral.map(|last_deferral| {
+                                        DateTime::from_timestamp(now_timestamp.saturating_sub(
+                                            now.duration_since(last_deferral).as_secs(),
+                                        )
+                                            as i64)
+                                    }),
+                                }
+                            })
+                            .collect::<Vec<_>>();
+                        result.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+
+                        (
+                            StatusCode::OK,
+                            serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                        )
+                    }
+                    Some(error) => error.into_bad_request(),
+                }
+            }
             (&Method::GET, "report", "list") => {
                 let mut domain = None;
                 let mut type_ = None;
@@ -655,24 +778,242 @@ impl SMTP {
                     Some(error) => error.into_bad_request(),
                 }
             }
-            _ => (
-                StatusCode::NOT_FOUND,
-                format!(
-                    "{{\"error\": \"not-found\", \"details\": \"URL {} does not exist.\"}}",
-                    uri.path()
-                ),
-            ),
+            (&Method::GET, "quarantine", action) => {
+                self.handle_quarantine_request(uri, action, None).await
+            }
+            (&Method::GET, "incoming", action) => self.handle_incoming_request(uri, action).await,
+            _ => uri.into_not_found(),
         };
 
-        hyper::Response::builder()
-            .status(status)
-            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
-            .body(
-                Full::new(Bytes::from(response))
-                    .map_err(|never| match never {})
-                    .boxed(),
-            )
-            .unwrap()
+        json_response(status, response)
+    }
+
+    async fn handle_quarantine_request(
+        &self,
+        uri: &Uri,
+        action: &str,
+        owner: Option<&[String]>,
+    ) -> (StatusCode, String) {
+        let mut ids = Vec::new();
+        let mut rcpt = None;
+        let mut error = None;
+
+        if let Some(query) = uri.query() {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                match key.as_ref() {
+                    "id" | "ids" => match value.parse_queue_ids() {
+                        Ok(ids_) => {
+                            ids = ids_;
+                        }
+                        Err(reason) => {
+                            error = reason.into();
+                            break;
+                        }
+                    },
+                    "rcpt" | "to" => {
+                        rcpt = value.to_lowercase().into();
+                    }
+                    _ => {
+                        error = format!("Invalid parameter {key:?}.").into();
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some(error) = error {
+            return error.into_bad_request();
+        }
+
+        // Recipients that the request applies to, None means all recipients
+        let rcpts = match (owner, rcpt) {
+            (Some(owner), Some(rcpt)) => Some(if owner.contains(&rcpt) {
+                vec![rcpt]
+            } else {
+                vec![]
+            }),
+            (Some(owner), None) => Some(owner.to_vec()),
+            (None, Some(rcpt)) => Some(vec![rcpt]),
+            (None, None) => None,
+        };
+        let is_visible = |item: &QuarantineItem| {
+            rcpts.as_ref().map_or(true, |rcpts| {
+                item.recipients.iter().any(|rcpt| rcpts.contains(rcpt))
+            })
+        };
+        let span = tracing::info_span!("management", context = "quarantine");
+
+        match action {
+            "list" => {
+                let result = self
+                    .queue
+                    .quarantine_list()
+                    .await
+                    .into_iter()
+                    .filter(is_visible)
+                    .map(|item| item.id)
+                    .collect::<Vec<_>>();
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                )
+            }
+            "status" => {
+                let mut result = Vec::with_capacity(ids.len());
+                for id in ids {
+                    result.push(self.queue.quarantine_get(id).await.filter(is_visible).map(
+                        |mut item| {
+                            // Hide other recipients from users
+                            if let Some(owner) = owner {
+                                item.recipients.retain(|rcpt| owner.contains(rcpt));
+                            }
+                            item
+                        },
+                    ));
+                }
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                )
+            }
+            "preview" => {
+                if ids.len() != 1 {
+                    return "Expected one id.".to_string().into_bad_request();
+                }
+                let result = if let Some(item) =
+                    self.queue.quarantine_get(ids[0]).await.filter(is_visible)
+                {
+                    self.queue
+                        .quarantine_preview(&item)
+                        .await
+                        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                } else {
+                    None
+                };
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                )
+            }
+            "release" | "delete" => {
+                let mut result = Vec::with_capacity(ids.len());
+                for id in ids {
+                    result.push(
+                        if let Some(item) = self.queue.quarantine_get(id).await.filter(is_visible) {
+                            if action == "release" {
+                                self.queue
+                                    .quarantine_release(item, rcpts.as_deref(), &span)
+                                    .await
+                            } else {
+                                self.queue
+                                    .quarantine_delete(item, rcpts.as_deref(), &span)
+                                    .await
+                            }
+                        } else {
+                            false
+                        },
+                    );
+                }
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                )
+            }
+            _ => uri.into_not_found(),
+        }
+    }
+
+    async fn handle_incoming_request(&self, uri: &Uri, action: &str) -> (StatusCode, String) {
+        let mut filter = IncomingReportFilter::default();
+        let mut ids = Vec::new();
+        let mut error = None;
+
+        if let Some(query) = uri.query() {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                match key.as_ref() {
+                    "type" => match value.as_ref() {
+                        "dmarc" => {
+                            filter.type_ = IncomingReportType::Dmarc.into();
+                        }
+                        "tls" => {
+                            filter.type_ = IncomingReportType::Tls.into();
+                        }
+                        "arf" => {
+                            filter.type_ = IncomingReportType::Arf.into();
+                        }
+                        _ => {
+                            error = format!("Invalid report type {value:?}.").into();
+                            break;
+                        }
+                    },
+                    "domain" => {
+                        filter.domain = value.to_lowercase().into();
+                    }
+                    "reporter" => {
+                        filter.reporter = value.to_lowercase().into();
+                    }
+                    "from" | "to" => match DateTime::parse_rfc3339(value.as_ref()) {
+                        Some(dt) if key == "from" => {
+                            filter.from = (dt.to_timestamp() as u64).into();
+                        }
+                        Some(dt) => {
+                            filter.to = (dt.to_timestamp() as u64).into();
+                        }
+                        None => {
+                            error = format!("Invalid timestamp {value:?}.").into();
+                            break;
+                        }
+                    },
+                    "id" | "ids" => {
+                        ids = value
+                            .split(',')
+                            .filter(|id| !id.is_empty())
+                            .map(|id| id.to_string())
+                            .collect();
+                    }
+                    _ => {
+                        error = format!("Invalid parameter {key:?}.").into();
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some(error) = error {
+            return error.into_bad_request();
+        }
+
+        match action {
+            "list" => {
+                let result = self
+                    .report
+                    .incoming_list(&filter)
+                    .await
+                    .into_iter()
+                    .map(|report| report.id)
+                    .collect::<Vec<_>>();
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                )
+            }
+            "status" => {
+                let mut result = Vec::with_capacity(ids.len());
+                for id in &ids {
+                    result.push(self.report.incoming_get(id).await);
+                }
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                )
+            }
+            "summary" => {
+                let result = SourceSummary::aggregate(&self.report.incoming_list(&filter).await);
+                (
+                    StatusCode::OK,
+                    serde_json::to_string(&Response { data: result }).unwrap_or_default(),
+                )
+            }
+            _ => uri.into_not_found(),
+        }
     }
 
     async fn send_queue_event<T: Serialize>(
@@ -928,6 +1269,37 @@ trait BadRequest {
     fn into_bad_request(self) -> (StatusCode, String);
 }
 
+trait NotFound {
+    fn into_not_found(self) -> (StatusCode, String);
+}
+
+impl NotFound for &Uri {
+    fn into_not_found(self) -> (StatusCode, String) {
+        (
+            StatusCode::NOT_FOUND,
+            format!(
+                "{{\"error\": \"not-found\", \"details\": \"URL {} does not exist.\"}}",
+                self.path()
+            ),
+        )
+    }
+}
+
+fn json_response(
+    status: StatusCode,
+    response: String,
+) -> hyper::Response<BoxBody<Bytes, hyper::Error>> {
+    hyper::Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+        .body(
+            Full::new(Bytes::from(response))
+                .map_err(|never| match never {})
+                .boxed(),
+        )
+        .unwrap()
+}
+
 impl BadRequest for String {
     fn into_bad_request(self) -> (StatusCode, String) {
         (
@@ -36,6 +36,7 @@ use sieve::{Runtime, Sieve};
 use smtp_proto::request::receiver::{
     BdatReceiver, DataReceiver, DummyDataReceiver, DummyLineReceiver, LineReceiver, RequestReceiver,
 };
+use store::Store;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     sync::mpsc,
@@ -45,20 +46,22 @@ use tracing::Span;
 use utils::{
     ipc::DeliveryEvent,
     listener::{limiter::InFlight, ServerInstance},
+    reload::Reloadable,
 };
 
 use crate::{
     config::{
         scripts::SieveContext, DkimSigner, MailAuthConfig, QueueConfig, ReportConfig,
-        SessionConfig, VerifyStrategy,
+        SessionConfig, VerifyStrategy, WebhookConfig,
     },
     inbound::auth::SaslToken,
     outbound::{
         dane::{DnssecResolver, Tlsa},
         mta_sts,
+        pool::{DnsblStatus, WarmupCounter, WarmupKey},
     },
-    queue::{self, DomainPart, QueueId, QuotaLimiter},
-    reporting,
+    queue::{self, adaptive::AdaptiveLimiter, DomainPart, QueueId, QuotaLimiter},
+    reporting, webhook,
 };
 
 use self::throttle::{Limiter, ThrottleKey, ThrottleKeyHasherBuilder};
@@ -71,34 +74,39 @@ pub mod worker;
 
 #[derive(Clone)]
 pub struct SmtpSessionManager {
-    pub inner: Arc<SMTP>,
+    pub inner: Reloadable<SMTP>,
 }
 
 #[derive(Clone)]
 pub struct SmtpAdminSessionManager {
-    pub inner: Arc<SMTP>,
+    pub inner: Reloadable<SMTP>,
 }
 
 impl SmtpSessionManager {
-    pub fn new(inner: Arc<SMTP>) -> Self {
-        Self { inner }
+    pub fn new(inner: impl Into<Reloadable<SMTP>>) -> Self {
+        Self {
+            inner: inner.into(),
+        }
     }
 }
 
 impl SmtpAdminSessionManager {
-    pub fn new(inner: Arc<SMTP>) -> Self {
-        Self { inner }
+    pub fn new(inner: impl Into<Reloadable<SMTP>>) -> Self {
+        Self {
+            inner: inner.into(),
+        }
     }
 }
 
 pub struct SMTP {
-    pub worker_pool: rayon::ThreadPool,
+    pub worker_pool: Arc<rayon::ThreadPool>,
     pub session: SessionCore,
     pub queue: QueueCore,
     pub resolvers: Resolvers,
     pub mail_auth: MailAuthConfig,
     pub report: ReportCore,
     pub sieve: SieveCore,
+    pub webhook: WebhookCore,
     #[cfg(feature = "local_delivery")]
     pub delivery_tx: mpsc::Sender<DeliveryEvent>,
 }
@@ -131,21 +139,30 @@ pub struct DnsCache {
 
 pub struct SessionCore {
     pub config: SessionConfig,
-    pub throttle: DashMap<ThrottleKey, Limiter, ThrottleKeyHasherBuilder>,
+    pub throttle: Arc<DashMap<ThrottleKey, Limiter, ThrottleKeyHasherBuilder>>,
 }
 
 pub struct QueueCore {
     pub config: QueueConfig,
-    pub throttle: DashMap<ThrottleKey, Limiter, ThrottleKeyHasherBuilder>,
-    pub quota: DashMap<ThrottleKey, Arc<QuotaLimiter>, ThrottleKeyHasherBuilder>,
+    pub throttle: Arc<DashMap<ThrottleKey, Limiter, ThrottleKeyHasherBuilder>>,
+    pub adaptive: Arc<DashMap<String, AdaptiveLimiter>>,
+    pub warmup: Arc<DashMap<WarmupKey, WarmupCounter>>,
+    pub dnsbl: Arc<DashMap<IpAddr, DnsblStatus>>,
+    pub quota: Arc<DashMap<ThrottleKey, Arc<QuotaLimiter>, ThrottleKeyHasherBuilder>>,
     pub tx: mpsc::Sender<queue::Event>,
-    pub id_seq: AtomicU32,
+    pub id_seq: Arc<AtomicU32>,
     pub connectors: TlsConnectors,
 }
 
 pub struct ReportCore {
     pub config: ReportConfig,
     pub tx: mpsc::Sender<reporting::Event>,
+    pub store: Option<Arc<Store>>,
+}
+
+pub struct WebhookCore {
+    pub config: WebhookConfig,
+    pub tx: mpsc::Sender<webhook::Event>,
 }
 
 pub struct TlsConnectors {
@@ -227,6 +244,7 @@ pub struct SessionParameters {
     pub auth_errors_max: usize,
     pub auth_errors_wait: Duration,
     pub auth_plain_text: bool,
+    pub auth_match_sender: bool,
 
     // Rcpt parameters
     pub rcpt_errors_max: usize,
@@ -428,6 +446,7 @@ impl Session<NullIo> {
                 auth_errors_max: Default::default(),
                 auth_errors_wait: Default::default(),
                 auth_plain_text: false,
+                auth_match_sender: false,
                 rcpt_errors_max: Default::default(),
                 rcpt_errors_wait: Default::default(),
                 rcpt_max: Default::default(),
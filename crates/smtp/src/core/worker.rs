@@ -21,10 +21,15 @@
  * for more details.
 */
 
-use std::sync::{atomic::Ordering, Arc};
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
 
 use tokio::sync::oneshot;
 
+use crate::outbound::pool::today;
+
 use super::SMTP;
 
 impl SMTP {
@@ -63,6 +68,13 @@ impl SMTP {
                         .map_or(false, |r| r.elapsed() < r.max_interval)
             });
         }
+        self.queue
+            .adaptive
+            .retain(|_, v| v.is_active(&self.queue.config.adaptive));
+        let today = today();
+        self.queue.warmup.retain(|_, v| v.day == today);
+        let now = Instant::now();
+        self.queue.dnsbl.retain(|_, v| v.expires > now);
         self.queue.quota.retain(|_, v| {
             v.messages.load(Ordering::Relaxed) > 0 || v.size.load(Ordering::Relaxed) > 0
         });
@@ -79,5 +91,11 @@ impl SpawnCleanup for Arc<SMTP> {
         self.worker_pool.spawn(move || {
             core.cleanup();
         });
+
+        // Purge expired quarantined messages
+        let core = self.clone();
+        tokio::spawn(async move {
+            core.queue.purge_quarantine().await;
+        });
     }
 }
@@ -25,8 +25,12 @@ use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use smtp_proto::{IntoString, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_XOAUTH2};
 use tokio::io::{AsyncRead, AsyncWrite};
+use utils::metrics;
 
-use crate::core::Session;
+use crate::{
+    core::Session,
+    webhook::{WebhookEvent, WebhookType},
+};
 
 pub struct SaslToken {
     mechanism: u64,
@@ -189,6 +193,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
                     event = "authenticate",
                     result = if is_authenticated {"success"} else {"failed"}
                 );
+                metrics::authentication("smtp", is_authenticated);
                 return if is_authenticated {
                     self.data.authenticated_as = authenticated_as;
                     self.eval_post_auth_params().await;
@@ -196,6 +201,18 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
                         .await?;
                     Ok(false)
                 } else {
+                    if self.core.webhook.is_enabled(WebhookType::AuthFailed) {
+                        let mut event = WebhookEvent::new(WebhookType::AuthFailed)
+                            .with_remote_ip(self.data.remote_ip)
+                            .with_response("535 5.7.8 Authentication credentials invalid.");
+                        // Bearer tokens are never included in the event
+                        if let Credentials::Plain { username, .. }
+                        | Credentials::XOauth2 { username, .. } = &credentials
+                        {
+                            event = event.with_authenticated_as(username);
+                        }
+                        self.core.webhook.send(event).await;
+                    }
                     self.auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
                         .await
                 };
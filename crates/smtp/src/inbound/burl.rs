@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use utils::config::ServerProtocol;
+
+use crate::core::Session;
+
+use super::IsTls;
+
+impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
+    pub async fn handle_burl(&mut self, uri: String, is_last: bool) -> Result<(), ()> {
+        if !*self.core.session.config.extensions.burl.eval(self).await {
+            return self.write(b"502 5.5.1 BURL not enabled.\r\n").await;
+        } else if self.data.authenticated_as.is_empty() {
+            return self.write(b"530 5.7.0 Authentication required.\r\n").await;
+        } else if !self.can_send_data().await? {
+            return Ok(());
+        }
+
+        match self.fetch_url(&uri).await {
+            Some(contents)
+                if contents.len() + self.data.message.len() < self.params.max_message_size =>
+            {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "burl",
+                    event = "fetch",
+                    url = uri,
+                    size = contents.len(),
+                );
+
+                self.data.message.extend_from_slice(&contents);
+                if is_last {
+                    let num_rcpts = self.data.rcpt_to.len();
+                    let message = self.queue_message().await;
+                    if !message.is_empty() {
+                        if self.instance.protocol == ServerProtocol::Smtp {
+                            self.write(message.as_ref()).await?;
+                        } else {
+                            for _ in 0..num_rcpts {
+                                self.write(message.as_ref()).await?;
+                            }
+                        }
+                        self.reset();
+                        Ok(())
+                    } else {
+                        // Disconnect requested
+                        Err(())
+                    }
+                } else {
+                    self.write(b"250 2.5.0 URL resolved.\r\n").await
+                }
+            }
+            Some(_) => {
+                self.data.message = Vec::with_capacity(0);
+                self.write(b"552 5.3.4 Message too big for system.\r\n")
+                    .await
+            }
+            None => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "burl",
+                    event = "error",
+                    url = uri,
+                    "Failed to resolve URL."
+                );
+
+                self.data.message = Vec::with_capacity(0);
+                self.write(b"554 5.6.6 IMAP URL resolution failed.\r\n")
+                    .await
+            }
+        }
+    }
+
+    #[cfg(feature = "local_delivery")]
+    async fn fetch_url(&self, uri: &str) -> Option<Vec<u8>> {
+        let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+        if self
+            .core
+            .delivery_tx
+            .send(utils::ipc::DeliveryEvent::FetchUrl {
+                url: uri.to_string(),
+                submitter: self.data.authenticated_as.clone(),
+                result_tx,
+            })
+            .await
+            .is_ok()
+        {
+            result_rx.await.ok().flatten()
+        } else {
+            tracing::warn!(
+                parent: &self.span,
+                context = "burl",
+                event = "error",
+                reason = "tx channel closed",
+            );
+            None
+        }
+    }
+
+    #[cfg(not(feature = "local_delivery"))]
+    async fn fetch_url(&self, _uri: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
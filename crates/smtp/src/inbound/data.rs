@@ -34,6 +34,7 @@ use mail_auth::{
     dmarc, AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult, ReceivedSpf,
 };
 use mail_builder::headers::{date::Date, message_id::generate_message_id_header};
+use mail_parser::MessageParser;
 use sieve::runtime::Variable;
 use smtp_proto::{
     MAIL_BY_RETURN, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
@@ -42,15 +43,17 @@ use tokio::{
     io::{AsyncRead, AsyncWrite, AsyncWriteExt},
     process::Command,
 };
+use utils::{ipc::SendPermission, metrics};
 
 use crate::{
     core::{Session, SessionAddress, State},
-    queue::{self, Message, SimpleEnvelope},
+    queue::{self, journal::JournalEnvelope, quarantine::QuarantineItem, Message, SimpleEnvelope},
     reporting::analysis::AnalyzeReport,
     scripts::{ScriptModification, ScriptResult},
+    webhook::{WebhookEvent, WebhookType},
 };
 
-use super::{AuthResult, IsTls};
+use super::{milter::Modification, AuthResult, IsTls};
 
 impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
     pub async fn queue_message(&mut self) -> Cow<'static, [u8]> {
@@ -63,6 +66,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                     context = "data",
                     event = "parse-failed",
                     size = raw_message.len());
+            metrics::smtp_rejected("parse");
 
             return (&b"550 5.7.7 Failed to parse message.\r\n"[..]).into();
         };
@@ -78,15 +82,47 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                 return_path = self.data.mail_from.as_ref().unwrap().address,
                 from = auth_message.from(),
                 received_headers = auth_message.received_headers_count());
+            metrics::smtp_rejected("loop");
             return (&b"450 4.4.6 Too many Received headers. Possible loop detected.\r\n"[..])
                 .into();
         }
 
+        // Make sure the authenticated user is allowed to use the From address
+        let on_behalf_of = if self.params.auth_match_sender
+            && !self.data.authenticated_as.is_empty()
+            && !auth_message.from().is_empty()
+        {
+            match self.sender_permission(auth_message.from()).await {
+                Some(SendPermission::SendOnBehalf { sender }) => Some(sender),
+                Some(SendPermission::Denied) => {
+                    tracing::info!(parent: &self.span,
+                        context = "data",
+                        event = "forbidden",
+                        from = auth_message.from(),
+                        "Authenticated user is not allowed to send from this address.");
+                    metrics::smtp_rejected("sender");
+
+                    return (&b"550 5.7.1 You are not allowed to send from this address.\r\n"[..])
+                        .into();
+                }
+                Some(_) => None,
+                None => {
+                    return (&b"451 4.4.3 Unable to verify sender address at this time.\r\n"[..])
+                        .into();
+                }
+            }
+        } else {
+            None
+        };
+
         // Verify DKIM
         let dkim = *ac.dkim.verify.eval(self).await;
         let dmarc = *ac.dmarc.verify.eval(self).await;
         let dkim_output = if dkim.verify() || dmarc.verify() {
             let dkim_output = self.core.resolvers.dns.verify_dkim(&auth_message).await;
+            for output in &dkim_output {
+                metrics::smtp_auth_result("dkim", output.result().as_str());
+            }
             let rejected = dkim.is_strict()
                 && !dkim_output
                     .iter()
@@ -110,6 +146,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                     from = auth_message.from(),
                     result = ?dkim_output.iter().map(|d| d.result().to_string()).collect::<Vec<_>>(),
                     "No passing DKIM signatures found.");
+                metrics::smtp_rejected("dkim");
 
                 // 'Strict' mode violates the advice of Section 6.1 of RFC6376
                 return if dkim_output
@@ -138,6 +175,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
         let arc_sealer = ac.arc.seal.eval_and_capture(self).await.into_value(self);
         let arc_output = if arc.verify() || arc_sealer.is_some() {
             let arc_output = self.core.resolvers.dns.verify_arc(&auth_message).await;
+            metrics::smtp_auth_result("arc", arc_output.result().as_str());
 
             if arc.is_strict()
                 && !matches!(arc_output.result(), DkimResult::Pass | DkimResult::None)
@@ -149,6 +187,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                     from = auth_message.from(),
                     result = %arc_output.result(),
                     "ARC validation failed.");
+                metrics::smtp_rejected("arc");
 
                 return if matches!(arc_output.result(), DkimResult::TempError(_)) {
                     (&b"451 4.7.29 ARC validation failed.\r\n"[..]).into()
@@ -234,6 +273,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                     DmarcResult::None
                 };
                 let dmarc_policy = dmarc_output.policy();
+                metrics::smtp_auth_result("dmarc", dmarc_result.as_str());
 
                 if !rejected {
                     tracing::debug!(parent: &self.span,
@@ -267,6 +307,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                 }
 
                 if rejected {
+                    metrics::smtp_rejected("dmarc");
                     return if is_temp_fail {
                         (&b"451 4.7.1 Email temporarily rejected per DMARC policy.\r\n"[..]).into()
                     } else {
@@ -289,9 +330,17 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
         }
 
         // Run Milter filters
+        let mut quarantine = None;
         let mut edited_message = match self.run_milters(&auth_message).await {
             Ok(modifications) => {
                 if !modifications.is_empty() {
+                    quarantine = modifications.iter().find_map(|m| {
+                        if let Modification::Quarantine { reason } = m {
+                            Some((reason.clone(), None))
+                        } else {
+                            None
+                        }
+                    });
                     tracing::debug!(
                     parent: &self.span,
                     context = "milter",
@@ -313,7 +362,10 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                     None
                 }
             }
-            Err(response) => return response,
+            Err(response) => {
+                metrics::smtp_rejected("milter");
+                return response;
+            }
         };
 
         // Pipe message
@@ -468,6 +520,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                         context = "sieve",
                         event = "reject",
                         reason = message);
+                    metrics::smtp_rejected("sieve");
 
                     return message.into_bytes().into();
                 }
@@ -490,6 +543,9 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                     ScriptModification::SetEnvelope { name, value } => {
                         self.data.apply_envelope_modification(name, value);
                     }
+                    ScriptModification::Quarantine { reason, score } => {
+                        quarantine = Some((reason, score));
+                    }
                 }
             }
         }
@@ -499,6 +555,37 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
         let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
         let mut message = self.build_message(mail_from, rcpt_to).await;
 
+        // Select source IP pool, the header override takes precedence
+        let source_ip = &self.core.queue.config.source_ip;
+        message.pool = if let Some(pool) = source_ip
+            .pool_header
+            .eval(self)
+            .await
+            .as_ref()
+            .and_then(|header| {
+                auth_message
+                    .raw_parsed_headers()
+                    .iter()
+                    .find_map(|(name, value)| {
+                        if name.eq_ignore_ascii_case(header.as_bytes()) {
+                            std::str::from_utf8(value).ok().map(|value| value.trim())
+                        } else {
+                            None
+                        }
+                    })
+            })
+            .and_then(|name| source_ip.pools.get(name))
+        {
+            pool.name.clone().into()
+        } else {
+            source_ip
+                .pool
+                .eval(self)
+                .await
+                .as_ref()
+                .map(|pool| pool.name.clone())
+        };
+
         // Add Received header
         if *dc.add_received.eval(self).await {
             self.write_received(&mut headers, message.id)
@@ -554,6 +641,17 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
             headers.extend_from_slice(b"\r\n");
         }
 
+        if let Some(sender) = on_behalf_of {
+            if MessageParser::default()
+                .parse(&edited_message.as_ref().unwrap_or(&raw_message)[..])
+                .map_or(true, |message| message.sender().is_none())
+            {
+                headers.extend_from_slice(b"Sender: <");
+                headers.extend_from_slice(sender.as_bytes());
+                headers.extend_from_slice(b">\r\n");
+            }
+        }
+
         // Add Return-Path
         if *dc.add_return_path.eval(self).await {
             headers.extend_from_slice(b"Return-Path: <");
@@ -581,9 +679,82 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
         // Update size
         message.size = raw_message.len() + headers.len();
 
+        // Journal message
+        if !self
+            .core
+            .queue
+            .journal_message(
+                &message,
+                JournalEnvelope {
+                    remote_ip: self.data.remote_ip,
+                    authenticated_as: &self.data.authenticated_as,
+                },
+                &headers,
+                &raw_message,
+                &self.span,
+            )
+            .await
+        {
+            return (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into();
+        }
+
+        // Quarantine message
+        if let Some((reason, score)) = quarantine {
+            if self.core.queue.config.quarantine.path.is_some() {
+                let item = QuarantineItem::new(&message, reason)
+                    .with_score(score)
+                    .with_remote_ip(self.data.remote_ip)
+                    .with_subject(
+                        MessageParser::default()
+                            .parse(&raw_message[..])
+                            .as_ref()
+                            .and_then(|m| m.subject()),
+                    );
+                return if let Some(item) = self
+                    .core
+                    .queue
+                    .quarantine_message(message, item, &headers, &raw_message, &self.span)
+                    .await
+                {
+                    let event = WebhookEvent::new(WebhookType::MessageQuarantined)
+                        .with_quarantine(&item)
+                        .with_remote_ip(self.data.remote_ip)
+                        .with_authenticated_as(&self.data.authenticated_as);
+                    self.core.webhook.send(event).await;
+                    self.state = State::Accepted(item.id);
+                    self.data.messages_sent += 1;
+                    metrics::SMTP_ACCEPTED.inc();
+                    (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
+                } else {
+                    (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
+                };
+            } else {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "quarantine",
+                    event = "disabled",
+                    reason = reason,
+                    "Quarantine path not configured, queueing message."
+                );
+            }
+        }
+
         // Verify queue quota
         if self.core.queue.has_quota(&mut message).await {
             let queue_id = message.id;
+            let event = self
+                .core
+                .webhook
+                .is_enabled(WebhookType::MessageAccepted)
+                .then(|| {
+                    message.recipients.iter().fold(
+                        WebhookEvent::new(WebhookType::MessageAccepted)
+                            .with_message(&message)
+                            .with_remote_ip(self.data.remote_ip)
+                            .with_authenticated_as(&self.data.authenticated_as),
+                        |event, rcpt| event.with_recipient(rcpt),
+                    )
+                });
             if self
                 .core
                 .queue
@@ -592,6 +763,10 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
             {
                 self.state = State::Accepted(queue_id);
                 self.data.messages_sent += 1;
+                metrics::SMTP_ACCEPTED.inc();
+                if let Some(event) = event {
+                    self.core.webhook.send(event).await;
+                }
                 (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
             } else {
                 (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
@@ -604,6 +779,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                 from = message.return_path,
                 "Queue quota exceeded, rejecting message."
             );
+            metrics::smtp_rejected("quota");
             (b"452 4.3.1 Mail system full, try again later.\r\n"[..]).into()
         }
     }
@@ -627,6 +803,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
             domains: Vec::with_capacity(3),
             flags: mail_from.flags,
             priority: self.data.priority,
+            pool: None,
             size: 0,
             env_id: mail_from.dsn_info,
             queue_refs: Vec::with_capacity(0),
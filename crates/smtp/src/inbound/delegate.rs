@@ -0,0 +1,91 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use utils::ipc::SendPermission;
+
+use crate::core::Session;
+
+use super::IsTls;
+
+impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
+    pub async fn sender_permission(&self, address: &str) -> Option<SendPermission> {
+        // Addresses owned by the authenticated account
+        if let Some(directory) = &self.params.auth_directory {
+            match directory.emails_by_name(&self.data.authenticated_as).await {
+                Ok(emails) => {
+                    if emails
+                        .iter()
+                        .any(|email| email.eq_ignore_ascii_case(address))
+                    {
+                        return Some(SendPermission::Owner);
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        parent: &self.span,
+                        context = "sender",
+                        event = "error",
+                        reason = ?err,
+                        "Failed to obtain account addresses."
+                    );
+                    return None;
+                }
+            }
+        }
+
+        // Addresses delegated by other accounts
+        self.delegated_permission(address).await
+    }
+
+    #[cfg(feature = "local_delivery")]
+    async fn delegated_permission(&self, address: &str) -> Option<SendPermission> {
+        let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+        if self
+            .core
+            .delivery_tx
+            .send(utils::ipc::DeliveryEvent::VerifySender {
+                address: address.to_string(),
+                submitter: self.data.authenticated_as.clone(),
+                result_tx,
+            })
+            .await
+            .is_ok()
+        {
+            result_rx.await.ok().flatten()
+        } else {
+            tracing::warn!(
+                parent: &self.span,
+                context = "sender",
+                event = "error",
+                reason = "tx channel closed",
+            );
+            None
+        }
+    }
+
+    #[cfg(not(feature = "local_delivery"))]
+    async fn delegated_permission(&self, _address: &str) -> Option<SendPermission> {
+        Some(SendPermission::Denied)
+    }
+}
@@ -132,6 +132,11 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
             response.capabilities |= EXT_CHUNKING;
         }
 
+        // BURL
+        if *ec.burl.eval(self).await {
+            response.capabilities |= EXT_BURL;
+        }
+
         // Address Expansion
         if *ec.expn.eval(self).await {
             response.capabilities |= EXT_EXPN;
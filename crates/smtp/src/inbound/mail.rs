@@ -26,6 +26,7 @@ use std::time::SystemTime;
 use mail_auth::{IprevOutput, IprevResult, SpfOutput, SpfResult};
 use smtp_proto::{MailFrom, MAIL_BY_NOTIFY, MAIL_BY_RETURN, MAIL_REQUIRETLS};
 use tokio::io::{AsyncRead, AsyncWrite};
+use utils::{ipc::SendPermission, metrics};
 
 use crate::{
     core::{Session, SessionAddress},
@@ -67,6 +68,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
                     result = %iprev.result,
                     ptr = iprev.ptr.as_ref().and_then(|p| p.first()).map(|p| p.as_str()).unwrap_or_default()
             );
+            metrics::smtp_auth_result("iprev", iprev.result().as_str());
 
             self.data.iprev = iprev.into();
         }
@@ -92,10 +94,38 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
             } else {
                 &b"550 5.7.25 Reverse DNS validation failed.\r\n"[..]
             };
+            metrics::smtp_rejected("iprev");
 
             return self.write(message).await;
         }
 
+        // Make sure the authenticated user is allowed to use this address
+        if self.params.auth_match_sender
+            && !self.data.authenticated_as.is_empty()
+            && !from.address.is_empty()
+        {
+            match self.sender_permission(&from.address).await {
+                Some(SendPermission::Denied) => {
+                    tracing::info!(parent: &self.span,
+                        context = "mail-from",
+                        event = "forbidden",
+                        address = &from.address,
+                        "Authenticated user is not allowed to send from this address.");
+                    metrics::smtp_rejected("sender");
+
+                    return self
+                        .write(b"550 5.7.1 You are not allowed to send from this address.\r\n")
+                        .await;
+                }
+                Some(_) => (),
+                None => {
+                    return self
+                        .write(b"451 4.4.3 Unable to verify sender address at this time.\r\n")
+                        .await;
+                }
+            }
+        }
+
         let (address, address_lcase, domain) = if !from.address.is_empty() {
             let address_lcase = from.address.to_lowercase();
             let domain = address_lcase.domain_part().to_string();
@@ -221,6 +251,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
             }
         }
         if from.size > 0 && from.size > *config_data.max_message_size.eval(self).await {
+            metrics::smtp_rejected("size");
             self.data.mail_from = None;
             return self
                 .write(b"552 5.3.4 Message too big for system.\r\n")
@@ -326,6 +357,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
             self.eval_rcpt_params().await;
             self.write(b"250 2.1.0 OK\r\n").await
         } else {
+            metrics::smtp_rejected("rate-limit");
             self.data.mail_from = None;
             self.write(b"451 4.4.5 Rate limit exceeded, try again later.\r\n")
                 .await
@@ -333,6 +365,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
     }
 
     pub async fn handle_spf(&mut self, spf_output: &SpfOutput, strict: bool) -> Result<bool, ()> {
+        metrics::smtp_auth_result("spf", spf_output.result().as_str());
         let result = match spf_output.result() {
             SpfResult::Pass => true,
             SpfResult::TempError if strict => {
@@ -354,6 +387,10 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
             }
         };
 
+        if !result {
+            metrics::smtp_rejected("spf");
+        }
+
         // Send report
         if let (Some(recipient), Some(rate)) = (
             spf_output.report_address(),
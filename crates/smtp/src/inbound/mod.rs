@@ -31,7 +31,9 @@ use tokio_rustls::server::TlsStream;
 use crate::config::{ArcSealer, DkimSigner};
 
 pub mod auth;
+pub mod burl;
 pub mod data;
+pub mod delegate;
 pub mod ehlo;
 pub mod mail;
 pub mod milter;
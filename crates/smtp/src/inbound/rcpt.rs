@@ -25,11 +25,13 @@ use smtp_proto::{
     RcptTo, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
 };
 use tokio::io::{AsyncRead, AsyncWrite};
+use utils::metrics;
 
 use crate::{
     core::{Session, SessionAddress},
     queue::DomainPart,
     scripts::{ScriptModification, ScriptResult},
+    webhook::{WebhookEvent, WebhookType},
 };
 
 use super::IsTls;
@@ -48,6 +50,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
         if self.data.mail_from.is_none() {
             return self.write(b"503 5.5.1 MAIL is required first.\r\n").await;
         } else if self.data.rcpt_to.len() >= self.params.rcpt_max {
+            metrics::smtp_rejected("too-many-recipients");
             return self.write(b"451 4.5.3 Too many recipients.\r\n").await;
         }
 
@@ -117,7 +120,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
                         event = "reject",
                         address = self.data.rcpt_to.last().unwrap().address,
                         reason = message);
-                        self.data.rcpt_to.pop();
+                        self.reject_rcpt(message.as_bytes());
                         return self.write(message.as_bytes()).await;
                     }
                     _ => (),
@@ -174,7 +177,8 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
                                             address = &rcpt.address_lcase,
                                             "Mailbox does not exist.");
 
-                            self.data.rcpt_to.pop();
+                            metrics::smtp_rejected("unknown-recipient");
+                            self.reject_rcpt(b"550 5.1.2 Mailbox does not exist.\r\n");
                             return self
                                 .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n")
                                 .await;
@@ -198,7 +202,8 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
                         address = &rcpt.address_lcase,
                         "Relay not allowed.");
 
-                    self.data.rcpt_to.pop();
+                    metrics::smtp_rejected("relay");
+                    self.reject_rcpt(b"550 5.1.2 Relay not allowed.\r\n");
                     return self.rcpt_error(b"550 5.1.2 Relay not allowed.\r\n").await;
                 }
             } else {
@@ -220,7 +225,8 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
                 address = &rcpt.address_lcase,
                 "Relay not allowed.");
 
-            self.data.rcpt_to.pop();
+            metrics::smtp_rejected("relay");
+            self.reject_rcpt(b"550 5.1.2 Relay not allowed.\r\n");
             return self.rcpt_error(b"550 5.1.2 Relay not allowed.\r\n").await;
         }
 
@@ -230,6 +236,7 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
                     event = "success",
                     address = &self.data.rcpt_to.last().unwrap().address);
         } else {
+            metrics::smtp_rejected("rate-limit");
             self.data.rcpt_to.pop();
             return self
                 .write(b"451 4.4.5 Rate limit exceeded, try again later.\r\n")
@@ -239,6 +246,22 @@ impl<T: AsyncWrite + AsyncRead + Unpin + IsTls> Session<T> {
         self.write(b"250 2.1.5 OK\r\n").await
     }
 
+    fn reject_rcpt(&mut self, response: &[u8]) {
+        if let Some(rcpt) = self.data.rcpt_to.pop() {
+            if self.core.webhook.is_enabled(WebhookType::RcptRejected) {
+                let mut event = WebhookEvent::new(WebhookType::RcptRejected)
+                    .with_to(rcpt.address)
+                    .with_remote_ip(self.data.remote_ip)
+                    .with_authenticated_as(&self.data.authenticated_as)
+                    .with_response(String::from_utf8_lossy(response));
+                if let Some(mail_from) = &self.data.mail_from {
+                    event = event.with_from(&mail_from.address);
+                }
+                self.core.webhook.send(event).await;
+            }
+        }
+    }
+
     async fn rcpt_error(&mut self, response: &[u8]) -> Result<(), ()> {
         tokio::time::sleep(self.params.rcpt_errors_wait).await;
         self.data.rcpt_errors += 1;
@@ -31,7 +31,10 @@ use smtp_proto::{
     *,
 };
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use utils::config::{KeyLookup, ServerProtocol};
+use utils::{
+    config::{KeyLookup, ServerProtocol},
+    metrics,
+};
 
 use crate::{
     config::EnvelopeKey,
@@ -183,7 +186,10 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                                     self.write(b"502 5.5.1 Invalid command.\r\n").await?;
                                 }
                             }
-                            Request::Etrn { .. } | Request::Atrn { .. } | Request::Burl { .. } => {
+                            Request::Burl { uri, is_last } => {
+                                self.handle_burl(uri, is_last).await?;
+                            }
+                            Request::Etrn { .. } | Request::Atrn { .. } => {
                                 self.write(b"502 5.5.1 Command not implemented.\r\n")
                                     .await?;
                             }
@@ -318,6 +324,7 @@ impl<T: AsyncWrite + AsyncRead + IsTls + Unpin> Session<T> {
                         );
 
                         self.data.message = Vec::with_capacity(0);
+                        metrics::smtp_rejected("size");
                         self.write(b"552 5.3.4 Message too big for system.\r\n")
                             .await?;
                         state = State::default();
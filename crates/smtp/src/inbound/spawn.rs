@@ -42,7 +42,7 @@ impl SessionManager for SmtpSessionManager {
     fn spawn(&self, session: utils::listener::SessionData<TcpStream>) {
         // Create session
         let mut session = Session {
-            core: self.inner.clone(),
+            core: self.inner.load(),
             instance: session.instance,
             state: State::default(),
             span: session.span,
@@ -70,7 +70,7 @@ impl SessionManager for SmtpSessionManager {
 
     fn shutdown(&self) {
         // We spawn to avoid using async_trait
-        let core = self.inner.clone();
+        let core = self.inner.load();
         tokio::spawn(async move {
             let _ = core.queue.tx.send(queue::Event::Stop).await;
             let _ = core.report.tx.send(reporting::Event::Stop).await;
@@ -22,24 +22,28 @@
 */
 
 use crate::core::{
-    throttle::ThrottleKeyHasherBuilder, QueueCore, ReportCore, SessionCore, TlsConnectors, SMTP,
+    throttle::ThrottleKeyHasherBuilder, QueueCore, ReportCore, SessionCore, TlsConnectors,
+    WebhookCore, SMTP,
 };
 use std::sync::Arc;
 
 use config::{
     auth::ConfigAuth, queue::ConfigQueue, remote::ConfigHost, report::ConfigReport,
-    resolver::ConfigResolver, scripts::ConfigSieve, session::ConfigSession, ConfigContext, Host,
+    resolver::ConfigResolver, scripts::ConfigSieve, session::ConfigSession, webhook::ConfigWebhook,
+    ConfigContext, Host,
 };
 use dashmap::DashMap;
 use directory::DirectoryConfig;
 use mail_send::smtp::tls::build_tls_connector;
 use queue::manager::SpawnQueue;
 use reporting::scheduler::SpawnReport;
+use store::Store;
 use tokio::sync::mpsc;
 use utils::{
     config::{Config, ServerProtocol, Servers},
-    UnwrapFailure,
+    reload::Reloadable,
 };
+use webhook::outbox::SpawnWebhook;
 
 pub mod config;
 pub mod core;
@@ -48,6 +52,7 @@ pub mod outbound;
 pub mod queue;
 pub mod reporting;
 pub mod scripts;
+pub mod webhook;
 
 pub static USER_AGENT: &str = concat!("StalwartSMTP/", env!("CARGO_PKG_VERSION"),);
 pub static DAEMON_NAME: &str = concat!("Stalwart SMTP v", env!("CARGO_PKG_VERSION"),);
@@ -57,8 +62,93 @@ impl SMTP {
         config: &Config,
         servers: &Servers,
         directory: &DirectoryConfig,
+        store: Arc<Store>,
         #[cfg(feature = "local_delivery")] delivery_tx: mpsc::Sender<utils::ipc::DeliveryEvent>,
-    ) -> Result<Arc<Self>, String> {
+    ) -> Result<Reloadable<Self>, String> {
+        let (queue_tx, queue_rx) = mpsc::channel(1024);
+        let (report_tx, report_rx) = mpsc::channel(1024);
+        let (webhook_tx, webhook_rx) = mpsc::channel(1024);
+        let worker_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(
+                    config
+                        .property::<usize>("global.thread-pool")?
+                        .filter(|v| *v > 0)
+                        .unwrap_or_else(num_cpus::get),
+                )
+                .build()
+                .map_err(|err| format!("Failed to build worker pool: {err}"))?,
+        );
+        let core = Reloadable::new(SMTP::build(
+            config,
+            servers,
+            directory,
+            worker_pool,
+            store.into(),
+            queue_tx,
+            report_tx,
+            webhook_tx,
+            #[cfg(feature = "local_delivery")]
+            delivery_tx,
+        )?);
+        let inner = core.load();
+
+        // Spawn queue manager
+        queue_rx.spawn(core.clone(), inner.queue.read_queue().await);
+
+        // Spawn report manager
+        report_rx.spawn(core.clone(), inner.report.read_reports().await);
+
+        // Spawn webhook manager
+        webhook_rx.spawn(core.clone(), inner.webhook.read_outbox().await);
+
+        Ok(core)
+    }
+
+    /// Builds a new core from an updated configuration. The worker pool, the
+    /// channels to the background managers and the runtime state (throttle
+    /// counters, adaptive rates, IP warm-up, DNSBL status, queue quotas and the
+    /// queue id sequence) are shared with the running core.
+    pub fn reload(
+        &self,
+        config: &Config,
+        servers: &Servers,
+        directory: &DirectoryConfig,
+    ) -> Result<Self, String> {
+        let mut core = SMTP::build(
+            config,
+            servers,
+            directory,
+            self.worker_pool.clone(),
+            self.report.store.clone(),
+            self.queue.tx.clone(),
+            self.report.tx.clone(),
+            self.webhook.tx.clone(),
+            #[cfg(feature = "local_delivery")]
+            self.delivery_tx.clone(),
+        )?;
+        core.session.throttle = self.session.throttle.clone();
+        core.queue.throttle = self.queue.throttle.clone();
+        core.queue.adaptive = self.queue.adaptive.clone();
+        core.queue.warmup = self.queue.warmup.clone();
+        core.queue.dnsbl = self.queue.dnsbl.clone();
+        core.queue.quota = self.queue.quota.clone();
+        core.queue.id_seq = self.queue.id_seq.clone();
+
+        Ok(core)
+    }
+
+    fn build(
+        config: &Config,
+        servers: &Servers,
+        directory: &DirectoryConfig,
+        worker_pool: Arc<rayon::ThreadPool>,
+        store: Option<Arc<Store>>,
+        queue_tx: mpsc::Sender<queue::Event>,
+        report_tx: mpsc::Sender<reporting::Event>,
+        webhook_tx: mpsc::Sender<webhook::Event>,
+        #[cfg(feature = "local_delivery")] delivery_tx: mpsc::Sender<utils::ipc::DeliveryEvent>,
+    ) -> Result<Self, String> {
         // Read configuration parameters
         let mut config_ctx = ConfigContext::new(&servers.inner);
         config_ctx.directory = directory.clone();
@@ -92,51 +182,65 @@ impl SMTP {
         let queue_config = config.parse_queue(&config_ctx)?;
         let mail_auth_config = config.parse_mail_auth(&config_ctx)?;
         let report_config = config.parse_reports(&config_ctx)?;
+        let webhook_config = config.parse_webhooks()?;
 
         // Build core
-        let (queue_tx, queue_rx) = mpsc::channel(1024);
-        let (report_tx, report_rx) = mpsc::channel(1024);
-        let core = Arc::new(SMTP {
-            worker_pool: rayon::ThreadPoolBuilder::new()
-                .num_threads(
-                    config
-                        .property::<usize>("global.thread-pool")?
-                        .filter(|v| *v > 0)
-                        .unwrap_or_else(num_cpus::get),
-                )
-                .build()
-                .unwrap(),
-            resolvers: config.build_resolvers().failed("Failed to build resolvers"),
+        Ok(SMTP {
+            worker_pool,
+            resolvers: config
+                .build_resolvers()
+                .map_err(|err| format!("Failed to build resolvers: {err}"))?,
             session: SessionCore {
                 config: session_config,
-                throttle: DashMap::with_capacity_and_hasher_and_shard_amount(
+                throttle: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                     config.property("global.shared-map.capacity")?.unwrap_or(2),
                     ThrottleKeyHasherBuilder::default(),
                     config
                         .property::<u64>("global.shared-map.shard")?
                         .unwrap_or(32)
                         .next_power_of_two() as usize,
-                ),
+                )),
             },
             queue: QueueCore {
                 config: queue_config,
-                throttle: DashMap::with_capacity_and_hasher_and_shard_amount(
+                throttle: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                     config.property("global.shared-map.capacity")?.unwrap_or(2),
                     ThrottleKeyHasherBuilder::default(),
                     config
                         .property::<u64>("global.shared-map.shard")?
                         .unwrap_or(32)
                         .next_power_of_two() as usize,
-                ),
-                id_seq: 0.into(),
-                quota: DashMap::with_capacity_and_hasher_and_shard_amount(
+                )),
+                adaptive: Arc::new(DashMap::with_capacity_and_shard_amount(
+                    config.property("global.shared-map.capacity")?.unwrap_or(2),
+                    config
+                        .property::<u64>("global.shared-map.shard")?
+                        .unwrap_or(32)
+                        .next_power_of_two() as usize,
+                )),
+                warmup: Arc::new(DashMap::with_capacity_and_shard_amount(
+                    config.property("global.shared-map.capacity")?.unwrap_or(2),
+                    config
+                        .property::<u64>("global.shared-map.shard")?
+                        .unwrap_or(32)
+                        .next_power_of_two() as usize,
+                )),
+                dnsbl: Arc::new(DashMap::with_capacity_and_shard_amount(
+                    config.property("global.shared-map.capacity")?.unwrap_or(2),
+                    config
+                        .property::<u64>("global.shared-map.shard")?
+                        .unwrap_or(32)
+                        .next_power_of_two() as usize,
+                )),
+                id_seq: Arc::new(0.into()),
+                quota: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                     config.property("global.shared-map.capacity")?.unwrap_or(2),
                     ThrottleKeyHasherBuilder::default(),
                     config
                         .property::<u64>("global.shared-map.shard")?
                         .unwrap_or(32)
                         .next_power_of_two() as usize,
-                ),
+                )),
                 tx: queue_tx,
                 connectors: TlsConnectors {
                     pki_verify: build_tls_connector(false),
@@ -146,19 +250,16 @@ impl SMTP {
             report: ReportCore {
                 tx: report_tx,
                 config: report_config,
+                store,
             },
             mail_auth: mail_auth_config,
             sieve: sieve_config,
+            webhook: WebhookCore {
+                tx: webhook_tx,
+                config: webhook_config,
+            },
             #[cfg(feature = "local_delivery")]
             delivery_tx,
-        });
-
-        // Spawn queue manager
-        queue_rx.spawn(core.clone(), core.queue.read_queue().await);
-
-        // Spawn report manager
-        report_rx.spawn(core.clone(), core.report.read_reports().await);
-
-        Ok(core)
+        })
     }
 }
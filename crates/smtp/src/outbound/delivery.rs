@@ -33,13 +33,17 @@ use mail_auth::{
 };
 use mail_send::SmtpClient;
 use smtp_proto::MAIL_REQUIRETLS;
-use utils::config::ServerProtocol;
+use utils::{
+    config::ServerProtocol,
+    metrics::{self, ObserveDuration},
+};
 
 use crate::{
     config::{AggregateFrequency, TlsStrategy},
-    core::SMTP,
+    core::{WebhookCore, SMTP},
     queue::ErrorDetails,
     reporting::{tls::TlsRptOptions, PolicyType, TlsEvent},
+    webhook::{WebhookEvent, WebhookType},
 };
 
 use super::{
@@ -49,14 +53,14 @@ use super::{
     NextHop,
 };
 use crate::queue::{
-    manager::Queue, throttle, DeliveryAttempt, Domain, Error, Event, OnHold, QueueEnvelope,
-    Schedule, Status, WorkerResult,
+    adaptive::Feedback, manager::Queue, throttle, DeliveryAttempt, Domain, Error, Event, OnHold,
+    QueueEnvelope, Schedule, Status, WorkerResult,
 };
 
 impl DeliveryAttempt {
     pub async fn try_deliver(mut self, core: Arc<SMTP>, queue: &mut Queue) {
         // Check that the message still has recipients to be delivered
-        let has_pending_delivery = self.has_pending_delivery();
+        let has_pending_delivery = self.has_pending_delivery(&core.webhook).await;
 
         // Send any due Delivery Status Notifications
         core.queue.send_dsn(&mut self).await;
@@ -121,6 +125,7 @@ impl DeliveryAttempt {
 
             let mut domains = std::mem::take(&mut self.message.domains);
             let mut recipients = std::mem::take(&mut self.message.recipients);
+            let mut attempted = Vec::new();
             'next_domain: for (domain_idx, domain) in domains.iter_mut().enumerate() {
                 // Only process domains due for delivery
                 if !matches!(&domain.status, Status::Scheduled | Status::TemporaryFailure(_)
@@ -128,6 +133,8 @@ impl DeliveryAttempt {
                 {
                     continue;
                 }
+                attempted.push(domain_idx);
+                let attempt_start = Instant::now();
 
                 // Create new span for domain
                 let span = tracing::info_span!(
@@ -175,8 +182,11 @@ impl DeliveryAttempt {
                             .await;
 
                         // Update status for the current domain and continue with the next one
-                        domain
-                            .set_status(delivery_result, queue_config.retry.eval(&envelope).await);
+                        domain.set_attempt_status(
+                            delivery_result,
+                            queue_config.retry.eval(&envelope).await,
+                            attempt_start,
+                        );
                         continue 'next_domain;
                     }
                     Some(next_hop) => (
@@ -294,7 +304,11 @@ impl DeliveryAttempt {
                                     "Failed to retrieve MTA-STS policy: {}",
                                     err
                                 );
-                                domain.set_status(err, queue_config.retry.eval(&envelope).await);
+                                domain.set_attempt_status(
+                                    err,
+                                    queue_config.retry.eval(&envelope).await,
+                                    attempt_start,
+                                );
                                 continue 'next_domain;
                             } else {
                                 tracing::debug!(
@@ -326,7 +340,11 @@ impl DeliveryAttempt {
                                 event = "mx-lookup-failed",
                                 reason = %err,
                             );
-                            domain.set_status(err, queue_config.retry.eval(&envelope).await);
+                            domain.set_attempt_status(
+                                err,
+                                queue_config.retry.eval(&envelope).await,
+                                attempt_start,
+                            );
                             continue 'next_domain;
                         }
                     };
@@ -342,11 +360,12 @@ impl DeliveryAttempt {
                             event = "null-mx",
                             reason = "Domain does not accept messages (mull MX)",
                         );
-                        domain.set_status(
+                        domain.set_attempt_status(
                             Status::PermanentFailure(Error::DnsError(
                                 "Domain does not accept messages (null MX)".to_string(),
                             )),
                             queue_config.retry.eval(&envelope).await,
+                            attempt_start,
                         );
                         continue 'next_domain;
                     }
@@ -355,6 +374,7 @@ impl DeliveryAttempt {
                 // Try delivering message
                 let max_multihomed = *queue_config.max_multihomed.eval(&envelope).await;
                 let mut last_status = Status::Scheduled;
+                let mut is_deferred = false;
                 'next_host: for remote_host in &remote_hosts {
                     // Validate MTA-STS
                     envelope.mx = remote_host.hostname();
@@ -394,7 +414,12 @@ impl DeliveryAttempt {
 
                     // Obtain source and remote IPs
                     let resolve_result = match core
-                        .resolve_host(remote_host, &envelope, max_multihomed)
+                        .resolve_host(
+                            remote_host,
+                            &envelope,
+                            self.message.pool.as_deref(),
+                            max_multihomed,
+                        )
                         .await
                     {
                         Ok(result) => result,
@@ -579,6 +604,14 @@ impl DeliveryAttempt {
                                 continue 'next_domain;
                             }
                         }
+                        if let Err(err) = core
+                            .queue
+                            .is_adaptive_allowed(&envelope, &mut in_flight_host, &span)
+                            .await
+                        {
+                            domain.set_throttle_error(err, &mut on_hold);
+                            continue 'next_domain;
+                        }
 
                         // Connect
                         let mut smtp_client = match if let Some(ip_addr) = source_ip {
@@ -606,6 +639,13 @@ impl DeliveryAttempt {
                                     remote_port = remote_host.port(),
                                 );
 
+                                // Update IP warm-up volume
+                                if let (Some(ip_addr), Some(provider)) =
+                                    (source_ip, &resolve_result.provider)
+                                {
+                                    core.queue.record_warmup(ip_addr, provider);
+                                }
+
                                 smtp_client
                             }
                             Err(err) => {
@@ -660,6 +700,19 @@ impl DeliveryAttempt {
                                     status = %status,
                                 );
 
+                                if core
+                                    .queue
+                                    .adaptive_feedback(
+                                        &envelope,
+                                        &status,
+                                        std::iter::empty(),
+                                        &span,
+                                    )
+                                    .await
+                                    == Feedback::Deferred
+                                {
+                                    is_deferred = true;
+                                }
                                 last_status = status;
                                 continue 'next_host;
                             }
@@ -904,6 +957,19 @@ impl DeliveryAttempt {
                                     status = %status,
                                 );
 
+                                if core
+                                    .queue
+                                    .adaptive_feedback(
+                                        &envelope,
+                                        &status,
+                                        std::iter::empty(),
+                                        &span,
+                                    )
+                                    .await
+                                    == Feedback::Deferred
+                                {
+                                    is_deferred = true;
+                                }
                                 last_status = status;
                                 continue 'next_host;
                             }
@@ -918,20 +984,49 @@ impl DeliveryAttempt {
                                 .await
                         };
 
+                        // Update adaptive limits
+                        let feedback = core
+                            .queue
+                            .adaptive_feedback(
+                                &envelope,
+                                &delivery_result,
+                                recipients.iter().filter(|r| r.domain_idx == domain_idx),
+                                &span,
+                            )
+                            .await;
+
                         // Update status for the current domain and continue with the next one
-                        domain
-                            .set_status(delivery_result, queue_config.retry.eval(&envelope).await);
+                        domain.set_attempt_status(
+                            delivery_result,
+                            queue_config.retry.eval(&envelope).await,
+                            attempt_start,
+                        );
+                        if feedback == Feedback::Deferred {
+                            domain.add_retry_jitter(queue_config.adaptive.jitter);
+                        }
                         continue 'next_domain;
                     }
                 }
 
                 // Update status
                 domain.disable_tls = disable_tls;
-                domain.set_status(last_status, queue_config.retry.eval(&envelope).await);
+                domain.set_attempt_status(
+                    last_status,
+                    queue_config.retry.eval(&envelope).await,
+                    attempt_start,
+                );
+                if is_deferred {
+                    domain.add_retry_jitter(queue_config.adaptive.jitter);
+                }
             }
             self.message.domains = domains;
             self.message.recipients = recipients;
 
+            // Notify webhooks
+            core.webhook
+                .send_delivery_events(&self.message, &attempted)
+                .await;
+
             // Send Delivery Status Notifications
             core.queue.send_dsn(&mut self).await;
 
@@ -999,9 +1094,10 @@ impl DeliveryAttempt {
     }
 
     /// Marks as failed all domains that reached their expiration time
-    pub fn has_pending_delivery(&mut self) -> bool {
+    pub async fn has_pending_delivery(&mut self, webhook: &WebhookCore) -> bool {
         let now = Instant::now();
         let mut has_pending_delivery = false;
+        let mut expired = Vec::new();
         let span = self.span.clone();
 
         for (idx, domain) in self.message.domains.iter_mut().enumerate() {
@@ -1014,8 +1110,14 @@ impl DeliveryAttempt {
                         reason = %err,
                     );
 
-                    for rcpt in &mut self.message.recipients {
+                    for (rcpt_idx, rcpt) in self.message.recipients.iter_mut().enumerate() {
                         if rcpt.domain_idx == idx {
+                            if matches!(
+                                rcpt.status,
+                                Status::Scheduled | Status::TemporaryFailure(_)
+                            ) {
+                                expired.push(rcpt_idx);
+                            }
                             rcpt.status = std::mem::replace(&mut rcpt.status, Status::Scheduled)
                                 .into_permanent();
                         }
@@ -1033,8 +1135,14 @@ impl DeliveryAttempt {
                         reason = "Queue rate limit exceeded.",
                     );
 
-                    for rcpt in &mut self.message.recipients {
+                    for (rcpt_idx, rcpt) in self.message.recipients.iter_mut().enumerate() {
                         if rcpt.domain_idx == idx {
+                            if matches!(
+                                rcpt.status,
+                                Status::Scheduled | Status::TemporaryFailure(_)
+                            ) {
+                                expired.push(rcpt_idx);
+                            }
                             rcpt.status = std::mem::replace(&mut rcpt.status, Status::Scheduled)
                                 .into_permanent();
                         }
@@ -1052,6 +1160,17 @@ impl DeliveryAttempt {
             }
         }
 
+        if !expired.is_empty() && webhook.is_enabled(WebhookType::MessageExpired) {
+            for rcpt_idx in expired {
+                let rcpt = &self.message.recipients[rcpt_idx];
+                let event = WebhookEvent::new(WebhookType::MessageExpired)
+                    .with_message(&self.message)
+                    .with_recipient(rcpt)
+                    .with_reason(self.message.domains[rcpt.domain_idx].status.to_string());
+                webhook.send(event).await;
+            }
+        }
+
         has_pending_delivery
     }
 }
@@ -1068,6 +1187,24 @@ impl Domain {
         }
     }
 
+    // Updates the status after a delivery attempt and records its duration
+    pub fn set_attempt_status(
+        &mut self,
+        status: impl Into<Status<(), Error>>,
+        schedule: &[Duration],
+        attempt_start: Instant,
+    ) {
+        self.set_status(status, schedule);
+        metrics::DELIVERY_DURATION.observe_since(
+            &[match &self.status {
+                Status::Completed(_) => "delivered",
+                Status::TemporaryFailure(_) | Status::Scheduled => "deferred",
+                Status::PermanentFailure(_) => "failed",
+            }],
+            attempt_start,
+        );
+    }
+
     pub fn retry(&mut self, schedule: &[Duration]) {
         self.retry.due =
             Instant::now() + schedule[std::cmp::min(self.retry.inner as usize, schedule.len() - 1)];
@@ -39,6 +39,7 @@ pub struct IpLookupResult {
     pub source_ipv4: Option<IpAddr>,
     pub source_ipv6: Option<IpAddr>,
     pub remote_ips: Vec<IpAddr>,
+    pub provider: Option<String>,
 }
 
 impl SMTP {
@@ -101,9 +102,10 @@ impl SMTP {
         &self,
         remote_host: &NextHop<'_>,
         envelope: &impl KeyLookup<Key = EnvelopeKey>,
+        pool: Option<&str>,
         max_multihomed: usize,
     ) -> Result<IpLookupResult, Status<(), Error>> {
-        let remote_ips = self
+        let mut remote_ips = self
             .ip_lookup(
                 remote_host.fqdn_hostname().as_ref(),
                 *self.queue.config.ip_strategy.eval(envelope).await,
@@ -125,10 +127,48 @@ impl SMTP {
             })?;
 
         if !remote_ips.is_empty() {
+            // Obtain source IP addresses from the message's IP pool
+            if let Some(pool) = pool.and_then(|pool| self.queue.config.source_ip.pools.get(pool)) {
+                let selection = self
+                    .select_pool_ips(
+                        pool,
+                        remote_host.hostname(),
+                        remote_ips.iter().any(|ip| ip.is_ipv4()),
+                        remote_ips.iter().any(|ip| ip.is_ipv6()),
+                    )
+                    .await
+                    .ok_or_else(|| {
+                        Status::TemporaryFailure(Error::ConnectionError(ErrorDetails {
+                            entity: remote_host.hostname().to_string(),
+                            details: format!(
+                                "no source addresses available in IP pool {:?}",
+                                pool.name
+                            ),
+                        }))
+                    })?;
+
+                // Never fall back to the default source address
+                remote_ips.retain(|ip| {
+                    if ip.is_ipv4() {
+                        selection.source_ipv4.is_some()
+                    } else {
+                        selection.source_ipv6.is_some()
+                    }
+                });
+
+                return Ok(IpLookupResult {
+                    source_ipv4: selection.source_ipv4,
+                    source_ipv6: selection.source_ipv6,
+                    remote_ips,
+                    provider: selection.provider.into(),
+                });
+            }
+
             let mut result = IpLookupResult {
                 source_ipv4: None,
                 source_ipv6: None,
                 remote_ips,
+                provider: None,
             };
 
             // Obtain source IPv4 address
@@ -0,0 +1,280 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
+
+use rand::seq::SliceRandom;
+use utils::suffixlist::PublicSuffix;
+
+use crate::{
+    config::{IpPool, PoolIp},
+    core::{QueueCore, SMTP},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WarmupKey {
+    pub addr: IpAddr,
+    pub provider: String,
+}
+
+#[derive(Debug)]
+pub struct WarmupCounter {
+    pub day: u64,
+    pub count: u64,
+}
+
+#[derive(Debug)]
+pub struct DnsblStatus {
+    pub listed_on: Option<String>,
+    pub expires: Instant,
+}
+
+pub struct PoolSelection {
+    pub source_ipv4: Option<IpAddr>,
+    pub source_ipv6: Option<IpAddr>,
+    pub provider: String,
+}
+
+impl SMTP {
+    pub async fn select_pool_ips(
+        &self,
+        pool: &Arc<IpPool>,
+        remote_host: &str,
+        has_ipv4: bool,
+        has_ipv6: bool,
+    ) -> Option<PoolSelection> {
+        let pools = &self.queue.config.source_ip.pools;
+        let provider = remote_host
+            .trim_end_matches('.')
+            .to_lowercase()
+            .provider(&self.sieve.runtime.context().psl)
+            .to_string();
+        let today = today();
+
+        let mut pool = pool;
+        for _ in 0..pools.len() {
+            // Discard addresses that reached their daily warm-up volume
+            // for this provider or that are listed on a DNSBL.
+            let mut ipv4 = Vec::new();
+            let mut ipv6 = Vec::new();
+            for ip in &pool.ips {
+                if (if ip.addr.is_ipv4() {
+                    has_ipv4
+                } else {
+                    has_ipv6
+                }) && self.queue.is_warmup_allowed(ip, &provider, today)
+                    && !self.is_dnsbl_listed(pool, ip.addr).await
+                {
+                    if ip.addr.is_ipv4() {
+                        ipv4.push(ip.addr);
+                    } else {
+                        ipv6.push(ip.addr);
+                    }
+                }
+            }
+
+            if !ipv4.is_empty() || !ipv6.is_empty() {
+                let mut rng = rand::thread_rng();
+                return PoolSelection {
+                    source_ipv4: ipv4.choose(&mut rng).copied(),
+                    source_ipv6: ipv6.choose(&mut rng).copied(),
+                    provider,
+                }
+                .into();
+            }
+
+            match pool.fallback.as_ref().and_then(|name| pools.get(name)) {
+                Some(fallback) => {
+                    tracing::info!(
+                        context = "ip-pool",
+                        event = "failover",
+                        pool = pool.name,
+                        fallback = fallback.name,
+                        provider = provider,
+                        "No usable addresses left in IP pool, failing over."
+                    );
+                    pool = fallback;
+                }
+                None => break,
+            }
+        }
+
+        tracing::info!(
+            context = "ip-pool",
+            event = "exhausted",
+            pool = pool.name,
+            provider = provider,
+            "No usable addresses left in IP pool."
+        );
+
+        None
+    }
+
+    async fn is_dnsbl_listed(&self, pool: &IpPool, addr: IpAddr) -> bool {
+        if pool.dnsbl.is_empty() {
+            return false;
+        }
+
+        if let Some(status) = self.queue.dnsbl.get(&addr) {
+            if status.expires > Instant::now() {
+                return status.listed_on.is_some();
+            }
+        }
+
+        let reversed = addr.to_reverse_name();
+        let mut listed_on = None;
+        for zone in &pool.dnsbl {
+            if let Ok(result) = self
+                .resolvers
+                .dns
+                .ipv4_lookup(&format!("{reversed}.{zone}."))
+                .await
+            {
+                // Codes in 127.255.255.0/24 are errors (i.e. queries blocked by the list)
+                if result.iter().any(|ip| {
+                    let octets = ip.octets();
+                    octets[0] == 127 && !(octets[1] == 255 && octets[2] == 255)
+                }) {
+                    tracing::warn!(
+                        context = "ip-pool",
+                        event = "dnsbl-listed",
+                        pool = pool.name,
+                        ip = %addr,
+                        zone = zone,
+                        "Source IP address is listed on a DNSBL."
+                    );
+                    listed_on = zone.to_string().into();
+                    break;
+                }
+            }
+        }
+
+        let is_listed = listed_on.is_some();
+        self.queue.dnsbl.insert(
+            addr,
+            DnsblStatus {
+                listed_on,
+                expires: Instant::now() + pool.dnsbl_ttl,
+            },
+        );
+        is_listed
+    }
+}
+
+impl QueueCore {
+    pub fn is_warmup_allowed(&self, ip: &PoolIp, provider: &str, today: u64) -> bool {
+        if let Some(warmup) = &ip.warmup {
+            let day = today.saturating_sub(warmup.start / 86400) as usize;
+            if let Some(max_messages) = warmup.schedule.get(day) {
+                return self
+                    .warmup
+                    .get(&WarmupKey {
+                        addr: ip.addr,
+                        provider: provider.to_string(),
+                    })
+                    .map_or(true, |c| c.day != today || c.count < *max_messages);
+            }
+        }
+        true
+    }
+
+    pub fn record_warmup(&self, addr: IpAddr, provider: &str) {
+        let today = today();
+        let mut counter = self
+            .warmup
+            .entry(WarmupKey {
+                addr,
+                provider: provider.to_string(),
+            })
+            .or_insert(WarmupCounter {
+                day: today,
+                count: 0,
+            });
+        if counter.day != today {
+            counter.day = today;
+            counter.count = 0;
+        }
+        counter.count += 1;
+    }
+}
+
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+        / 86400
+}
+
+trait Provider {
+    fn provider(&self, psl: &PublicSuffix) -> &str;
+}
+
+impl Provider for String {
+    // Destination providers are identified by the registrable domain of their MX
+    // hosts, so that "gmail-smtp-in.l.google.com" and "alt1.aspmx.l.google.com"
+    // share the same warm-up volume.
+    fn provider(&self, psl: &PublicSuffix) -> &str {
+        let mut host = self.as_str();
+        let mut last_two = self.as_str();
+        while let Some((_, suffix)) = host.split_once('.') {
+            if psl.contains(suffix) {
+                return host;
+            } else if suffix.contains('.') {
+                last_two = suffix;
+            }
+            host = suffix;
+        }
+        last_two
+    }
+}
+
+trait ToReverseName {
+    fn to_reverse_name(&self) -> String;
+}
+
+impl ToReverseName for IpAddr {
+    fn to_reverse_name(&self) -> String {
+        use std::fmt::Write;
+
+        match self {
+            IpAddr::V4(ip) => {
+                let o = ip.octets();
+                format!("{}.{}.{}.{}", o[3], o[2], o[1], o[0])
+            }
+            IpAddr::V6(ip) => {
+                let mut name = String::with_capacity(63);
+                for ch in ip.octets().iter().rev() {
+                    if !name.is_empty() {
+                        name.push('.');
+                    }
+                    let _ = write!(name, "{:x}.{:x}", ch & 0xf, ch >> 4);
+                }
+                name
+            }
+        }
+    }
+}
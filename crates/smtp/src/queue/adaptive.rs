@@ -0,0 +1,283 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::{Duration, Instant};
+
+use dashmap::mapref::entry::Entry;
+use rand::Rng;
+use smtp_proto::Response;
+use utils::listener::limiter::{ConcurrencyLimiter, InFlight, RateLimiter};
+
+use crate::{
+    config::{AdaptiveThrottleKey, QueueAdaptiveThrottle},
+    core::QueueCore,
+};
+
+use super::{
+    throttle, Domain, Error, HostResponse, QueueEnvelope, Recipient, Status, RCPT_STATUS_CHANGED,
+};
+
+#[derive(Debug)]
+pub struct AdaptiveLimiter {
+    pub concurrency: ConcurrencyLimiter,
+    pub rate: RateLimiter,
+    pub successes: u32,
+    pub deferrals: u64,
+    pub last_deferral: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    Success,
+    Deferred,
+    None,
+}
+
+impl QueueCore {
+    pub async fn is_adaptive_allowed(
+        &self,
+        envelope: &QueueEnvelope<'_>,
+        in_flight: &mut Vec<InFlight>,
+        span: &tracing::Span,
+    ) -> Result<(), throttle::Error> {
+        let config = &self.config.adaptive;
+        if !*config.enable.eval(envelope).await {
+            return Ok(());
+        }
+
+        let key = config.key(envelope);
+        match self.adaptive.entry(key.to_string()) {
+            Entry::Occupied(mut e) => {
+                let limiter = e.get_mut();
+                if let Some(inflight) = limiter.concurrency.is_allowed() {
+                    in_flight.push(inflight);
+                } else {
+                    tracing::info!(
+                        parent: span,
+                        context = "throttle",
+                        event = "adaptive-too-many-requests",
+                        key = key,
+                        max_concurrent = limiter.concurrency.max_concurrent,
+                        "Adaptive concurrency limit exceeded."
+                    );
+                    return Err(throttle::Error::Concurrency {
+                        limiter: limiter.concurrency.clone(),
+                    });
+                }
+                if !limiter.rate.is_allowed() {
+                    tracing::info!(
+                        parent: span,
+                        context = "throttle",
+                        event = "adaptive-rate-limit-exceeded",
+                        key = key,
+                        max_requests = limiter.rate.max_requests,
+                        max_interval = limiter.rate.max_interval.as_secs(),
+                        "Adaptive rate limit exceeded."
+                    );
+                    return Err(throttle::Error::Rate {
+                        retry_at: limiter.rate.retry_at(),
+                    });
+                }
+            }
+            Entry::Vacant(e) => {
+                let mut limiter = AdaptiveLimiter::new(config);
+                if let Some(inflight) = limiter.concurrency.is_allowed() {
+                    in_flight.push(inflight);
+                }
+                limiter.rate.is_allowed();
+                e.insert(limiter);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn adaptive_feedback<'x>(
+        &self,
+        envelope: &QueueEnvelope<'_>,
+        status: &Status<(), Error>,
+        recipients: impl Iterator<Item = &'x Recipient>,
+        span: &tracing::Span,
+    ) -> Feedback {
+        let config = &self.config.adaptive;
+        if !*config.enable.eval(envelope).await {
+            return Feedback::None;
+        }
+
+        // Deferrals can be issued either at the session level (greeting, MAIL FROM, DATA)
+        // or for each individual recipient.
+        let feedback = match status {
+            Status::TemporaryFailure(Error::UnexpectedResponse(HostResponse {
+                response, ..
+            })) if response.is_throttle_response() => Feedback::Deferred,
+            Status::Completed(_) | Status::Scheduled => {
+                let mut feedback = if matches!(status, Status::Completed(_)) {
+                    Feedback::Success
+                } else {
+                    Feedback::None
+                };
+                for rcpt in recipients {
+                    if (rcpt.flags & RCPT_STATUS_CHANGED) != 0 {
+                        match &rcpt.status {
+                            Status::TemporaryFailure(HostResponse { response, .. })
+                                if response.is_throttle_response() =>
+                            {
+                                feedback = Feedback::Deferred;
+                                break;
+                            }
+                            Status::Completed(_) => {
+                                feedback = Feedback::Success;
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                feedback
+            }
+            _ => Feedback::None,
+        };
+
+        if feedback != Feedback::None {
+            let key = config.key(envelope);
+            if let Some(mut limiter) = self.adaptive.get_mut(key) {
+                let limiter = limiter.value_mut();
+                if feedback == Feedback::Deferred {
+                    limiter.decrease(config);
+
+                    tracing::info!(
+                        parent: span,
+                        context = "throttle",
+                        event = "adaptive-decrease",
+                        key = key,
+                        max_concurrent = limiter.concurrency.max_concurrent,
+                        max_requests = limiter.rate.max_requests,
+                        max_interval = limiter.rate.max_interval.as_secs(),
+                        "Remote host is deferring messages, lowering outbound limits."
+                    );
+                } else if limiter.increase(config) {
+                    tracing::debug!(
+                        parent: span,
+                        context = "throttle",
+                        event = "adaptive-increase",
+                        key = key,
+                        max_concurrent = limiter.concurrency.max_concurrent,
+                        max_requests = limiter.rate.max_requests,
+                        max_interval = limiter.rate.max_interval.as_secs(),
+                        "Raising outbound limits."
+                    );
+                }
+            }
+        }
+
+        feedback
+    }
+}
+
+impl QueueAdaptiveThrottle {
+    pub fn key<'x>(&self, envelope: &QueueEnvelope<'x>) -> &'x str {
+        match self.key {
+            AdaptiveThrottleKey::Mx if !envelope.mx.is_empty() => envelope.mx,
+            _ => envelope.domain,
+        }
+    }
+}
+
+impl AdaptiveLimiter {
+    pub fn new(config: &QueueAdaptiveThrottle) -> Self {
+        AdaptiveLimiter {
+            concurrency: ConcurrencyLimiter::new(config.concurrency_max),
+            rate: RateLimiter::new(config.rate_max.requests, config.rate_max.period),
+            successes: 0,
+            deferrals: 0,
+            last_deferral: None,
+        }
+    }
+
+    pub fn decrease(&mut self, config: &QueueAdaptiveThrottle) {
+        self.concurrency.max_concurrent = std::cmp::max(
+            (self.concurrency.max_concurrent as f64 * config.backoff) as u64,
+            config.concurrency_min,
+        );
+        self.rate.set_max_requests(std::cmp::max(
+            (self.rate.max_requests as f64 * config.backoff) as u64,
+            config.rate_min,
+        ));
+        self.successes = 0;
+        self.deferrals += 1;
+        self.last_deferral = Instant::now().into();
+    }
+
+    pub fn increase(&mut self, config: &QueueAdaptiveThrottle) -> bool {
+        if self.is_at_max(config) {
+            return false;
+        }
+
+        self.successes += 1;
+        if self.successes >= config.recover_after {
+            self.successes = 0;
+            self.concurrency.max_concurrent =
+                std::cmp::min(self.concurrency.max_concurrent + 1, config.concurrency_max);
+            self.rate.set_max_requests(std::cmp::min(
+                self.rate.max_requests + std::cmp::max(config.rate_max.requests / 10, 1),
+                config.rate_max.requests,
+            ));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_at_max(&self, config: &QueueAdaptiveThrottle) -> bool {
+        self.concurrency.max_concurrent >= config.concurrency_max
+            && self.rate.max_requests >= config.rate_max.requests
+    }
+
+    pub fn is_active(&self, config: &QueueAdaptiveThrottle) -> bool {
+        self.concurrency.is_active() || !self.is_at_max(config)
+    }
+}
+
+impl Domain {
+    pub fn add_retry_jitter(&mut self, jitter: f64) {
+        if jitter > 0.0 {
+            let now = Instant::now();
+            if let Some(delay) = self.retry.due.checked_duration_since(now) {
+                self.retry.due += Duration::from_secs_f64(
+                    delay.as_secs_f64() * rand::thread_rng().gen_range(0.0..=jitter),
+                );
+            }
+        }
+    }
+}
+
+pub trait ThrottleResponse {
+    fn is_throttle_response(&self) -> bool;
+}
+
+impl ThrottleResponse for Response<String> {
+    fn is_throttle_response(&self) -> bool {
+        // 421 Service not available or any 4.7.x policy deferral
+        // (e.g. Gmail's 4.7.28 or Outlook's 4.7.500 "server busy")
+        self.code == 421 || (self.code / 100 == 4 && self.esc[0] == 4 && self.esc[1] == 7)
+    }
+}
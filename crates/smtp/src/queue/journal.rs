@@ -0,0 +1,221 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    fmt::Write,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use mail_builder::{headers::date::Date, mime::make_boundary};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::core::QueueCore;
+
+use super::{Message, SimpleEnvelope};
+
+pub struct JournalEnvelope<'x> {
+    pub remote_ip: IpAddr,
+    pub authenticated_as: &'x str,
+}
+
+impl QueueCore {
+    // Sends a copy of the message and its envelope to the journal address and
+    // writes it to the journal location configured for each of its recipients.
+    // Returns false if any of the copies could not be made.
+    pub async fn journal_message(
+        &self,
+        message: &Message,
+        envelope: JournalEnvelope<'_>,
+        raw_headers: &[u8],
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) -> bool {
+        // Group recipients by journal address and location
+        let mut addresses: Vec<(&String, Vec<&str>)> = Vec::new();
+        let mut paths: Vec<(&PathBuf, Vec<&str>)> = Vec::new();
+        for rcpt in &message.recipients {
+            let envelope = SimpleEnvelope::new_rcpt(
+                message,
+                &message.domains[rcpt.domain_idx].domain,
+                &rcpt.address_lcase,
+            );
+            if let Some(address) = self.config.journal.address.eval(&envelope).await {
+                if let Some((_, rcpts)) = addresses.iter_mut().find(|(a, _)| *a == address) {
+                    rcpts.push(&rcpt.address);
+                } else {
+                    addresses.push((address, vec![&rcpt.address]));
+                }
+            }
+            if let Some(path) = self.config.journal.path.eval(&envelope).await {
+                if let Some((_, rcpts)) = paths.iter_mut().find(|(p, _)| *p == path) {
+                    rcpts.push(&rcpt.address);
+                } else {
+                    paths.push((path, vec![&rcpt.address]));
+                }
+            }
+        }
+
+        let mut success = true;
+        for (path, rcpts) in paths {
+            let report = self
+                .build_journal_report(message, &envelope, None, &rcpts, raw_headers, raw_message)
+                .await;
+            if let Err(err) = write_once(path, message, &report).await {
+                tracing::error!(
+                    parent: span,
+                    context = "journal",
+                    event = "error",
+                    "Failed to write journal copy to {}: {}",
+                    path.display(),
+                    err
+                );
+                success = false;
+            }
+        }
+
+        for (address, rcpts) in addresses {
+            let report = self
+                .build_journal_report(
+                    message,
+                    &envelope,
+                    Some(address),
+                    &rcpts,
+                    raw_headers,
+                    raw_message,
+                )
+                .await;
+            let mut journal_message = Message::new_boxed("", "", "");
+            journal_message
+                .add_recipient(address.as_str(), &self.config)
+                .await;
+            if !self
+                .queue_message(journal_message, None, &report, span)
+                .await
+            {
+                success = false;
+            }
+        }
+
+        success
+    }
+
+    async fn build_journal_report(
+        &self,
+        message: &Message,
+        envelope: &JournalEnvelope<'_>,
+        to: Option<&str>,
+        rcpts: &[&str],
+        raw_headers: &[u8],
+        raw_message: &[u8],
+    ) -> Vec<u8> {
+        let from_name = self.config.dsn.name.eval(message).await;
+        let from_addr = self.config.dsn.address.eval(message).await;
+        let hostname = self.config.hostname.eval(message).await;
+        let boundary = make_boundary("_");
+
+        // Envelope
+        let mut txt = String::with_capacity(128);
+        let _ = write!(txt, "Sender: {}\r\n", message.return_path);
+        for rcpt in rcpts {
+            let _ = write!(txt, "Recipient: {rcpt}\r\n");
+        }
+        if let Some(env_id) = &message.env_id {
+            let _ = write!(txt, "Envelope-Id: {env_id}\r\n");
+        }
+        let _ = write!(txt, "Queue-Id: {:x}\r\n", message.id);
+        let _ = write!(txt, "Remote-IP: {}\r\n", envelope.remote_ip);
+        if !envelope.authenticated_as.is_empty() {
+            let _ = write!(txt, "Authenticated-As: {}\r\n", envelope.authenticated_as);
+        }
+        let _ = write!(
+            txt,
+            "Arrival-Date: {}\r\n",
+            Date::new(message.created as i64).to_rfc822()
+        );
+
+        // Headers
+        let mut headers = String::with_capacity(512);
+        let _ = write!(headers, "From: \"{from_name}\" <{from_addr}>\r\n");
+        if let Some(to) = to {
+            let _ = write!(headers, "To: <{to}>\r\n");
+        }
+        let _ = write!(
+            headers,
+            concat!(
+                "Subject: Journal report for message {:x}\r\n",
+                "Date: {}\r\n",
+                "Message-ID: <{}@{}>\r\n",
+                "Auto-Submitted: auto-generated\r\n",
+                "MIME-Version: 1.0\r\n",
+                "Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n",
+                "--{}\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "Content-Transfer-Encoding: 8bit\r\n\r\n",
+                "{}\r\n",
+                "--{}\r\n",
+                "Content-Type: message/rfc822\r\n",
+                "Content-Disposition: attachment\r\n\r\n",
+            ),
+            message.id,
+            Date::now().to_rfc822(),
+            make_boundary("."),
+            hostname,
+            boundary,
+            boundary,
+            txt,
+            boundary,
+        );
+
+        // Original message
+        let mut report =
+            Vec::with_capacity(headers.len() + raw_headers.len() + raw_message.len() + 64);
+        report.extend_from_slice(headers.as_bytes());
+        report.extend_from_slice(raw_headers);
+        report.extend_from_slice(raw_message);
+        if !report.ends_with(b"\r\n") {
+            report.extend_from_slice(b"\r\n");
+        }
+        report.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+        report
+    }
+}
+
+// Journal copies are created once and never overwritten.
+async fn write_once(path: &Path, message: &Message, report: &[u8]) -> std::io::Result<()> {
+    let _ = fs::create_dir_all(path).await;
+    let path = path.join(format!("{}_{:x}.eml", message.created, message.id));
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .await?;
+    file.write_all(report).await?;
+    file.sync_all().await?;
+
+    let mut permissions = file.metadata().await?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&path, permissions).await
+}
@@ -23,14 +23,16 @@
 
 use std::{
     collections::BinaryHeap,
-    sync::{atomic::Ordering, Arc},
-    time::{Duration, Instant},
+    sync::atomic::Ordering,
+    time::{Duration, Instant, SystemTime},
 };
 
 use ahash::AHashMap;
 use smtp_proto::Response;
 use tokio::sync::mpsc;
 
+use utils::{metrics, reload::Reloadable};
+
 use crate::core::{
     management::{self},
     QueueCore, SMTP,
@@ -48,13 +50,23 @@ pub struct Queue {
     pub scheduled: BinaryHeap<Schedule<QueueId>>,
     pub on_hold: Vec<OnHold<QueueId>>,
     pub messages: AHashMap<QueueId, Box<Message>>,
+    metrics_due: Instant,
 }
 
+const METRICS_INTERVAL: Duration = Duration::from_secs(15);
+
 impl SpawnQueue for mpsc::Receiver<Event> {
-    fn spawn(mut self, core: Arc<SMTP>, mut queue: Queue) {
+    fn spawn(mut self, core: impl Into<Reloadable<SMTP>>, mut queue: Queue) {
+        let handle = core.into();
         tokio::spawn(async move {
             loop {
-                let result = tokio::time::timeout(queue.wake_up_time(), self.recv()).await;
+                let result = tokio::time::timeout(
+                    std::cmp::min(queue.wake_up_time(), METRICS_INTERVAL),
+                    self.recv(),
+                )
+                .await;
+                let core = handle.load();
+                queue.update_metrics(core.queue.config.metrics_max_domains);
 
                 // Deliver scheduled messages
                 while let Some(message) = queue.next_due() {
@@ -348,6 +360,42 @@ impl Queue {
             })
             .unwrap_or(self.long_wait)
     }
+
+    // Updates the per-domain queue size and age gauges
+    pub fn update_metrics(&mut self, max_domains: usize) {
+        let now = Instant::now();
+        if self.metrics_due > now {
+            return;
+        }
+        self.metrics_due = now + METRICS_INTERVAL;
+
+        let mut domains: AHashMap<&str, (i64, u64)> = AHashMap::new();
+        for message in self.messages.values() {
+            for domain in &message.domains {
+                if matches!(
+                    domain.status,
+                    Status::Scheduled | Status::TemporaryFailure(_)
+                ) {
+                    let (count, oldest) = domains
+                        .entry(domain.domain.as_str())
+                        .or_insert((0, message.created));
+                    *count += 1;
+                    *oldest = std::cmp::min(*oldest, message.created);
+                }
+            }
+        }
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        metrics::set_queue_status(
+            domains
+                .into_iter()
+                .map(|(domain, (count, oldest))| (domain, count, oldest)),
+            now,
+            max_domains,
+        );
+    }
 }
 
 impl Message {
@@ -552,10 +600,11 @@ impl Default for Queue {
             scheduled: BinaryHeap::with_capacity(128),
             on_hold: Vec::with_capacity(128),
             messages: AHashMap::with_capacity(128),
+            metrics_due: Instant::now(),
         }
     }
 }
 
 pub trait SpawnQueue {
-    fn spawn(self, core: Arc<SMTP>, queue: Queue);
+    fn spawn(self, core: impl Into<Reloadable<SMTP>>, queue: Queue);
 }
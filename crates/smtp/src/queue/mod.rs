@@ -38,8 +38,11 @@ use utils::{
 
 use crate::{config::EnvelopeKey, core::management};
 
+pub mod adaptive;
 pub mod dsn;
+pub mod journal;
 pub mod manager;
+pub mod quarantine;
 pub mod quota;
 pub mod serialize;
 pub mod spool;
@@ -90,6 +93,7 @@ pub struct Message {
     pub flags: u64,
     pub env_id: Option<String>,
     pub priority: i16,
+    pub pool: Option<String>,
 
     pub size: usize,
     pub queue_refs: Vec<UsedQuota>,
@@ -0,0 +1,450 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    net::IpAddr,
+    path::Path,
+    time::{Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::core::QueueCore;
+
+use super::{Message, QueueId, Schedule, SimpleEnvelope};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineItem {
+    pub id: QueueId,
+    pub created: u64,
+    pub expires: u64,
+    pub return_path: String,
+    pub recipients: Vec<String>,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub score: Option<f64>,
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub remote_ip: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub subject: Option<String>,
+}
+
+impl QueueCore {
+    pub async fn quarantine_message(
+        &self,
+        mut message: Box<Message>,
+        mut item: QuarantineItem,
+        raw_headers: &[u8],
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) -> Option<QuarantineItem> {
+        let path = self.config.quarantine.path.as_ref()?;
+
+        // Generate id
+        if message.id == 0 {
+            message.id = self.queue_id();
+        }
+        message.size = raw_headers.len() + raw_message.len();
+        item.id = message.id;
+        item.size = message.size;
+        item.created = now();
+        item.expires = item.created + self.config.quarantine.retention.as_secs();
+
+        // Save message followed by its queue metadata, so it can be queued on release
+        let _ = fs::create_dir_all(path).await;
+        let msg_path = path.join(format!("{:016x}.msg", item.id));
+        let metadata = message.serialize();
+        let result = match fs::File::create(&msg_path).await {
+            Ok(mut file) => {
+                let mut result = Ok(());
+                for bytes in [raw_headers, raw_message, &metadata] {
+                    if let Err(err) = file.write_all(bytes).await {
+                        result = Err(err);
+                        break;
+                    }
+                }
+                match result {
+                    Ok(_) => file.flush().await,
+                    Err(err) => Err(err),
+                }
+            }
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "error",
+                "Failed to write to file {}: {}",
+                msg_path.display(),
+                err
+            );
+            return None;
+        }
+        if !item.save(path, span).await {
+            let _ = fs::remove_file(&msg_path).await;
+            return None;
+        }
+
+        tracing::info!(
+            parent: span,
+            context = "quarantine",
+            event = "quarantined",
+            id = item.id,
+            from = if !item.return_path.is_empty() {
+                item.return_path.as_str()
+            } else {
+                "<>"
+            },
+            nrcpts = item.recipients.len(),
+            size = item.size,
+            reason = item.reason,
+            "Message quarantined."
+        );
+
+        Some(item)
+    }
+
+    pub async fn quarantine_list(&self) -> Vec<QuarantineItem> {
+        let mut items = Vec::new();
+        if let Some(path) = &self.config.quarantine.path {
+            if let Ok(mut dir) = fs::read_dir(path).await {
+                let now = now();
+                while let Ok(Some(entry)) = dir.next_entry().await {
+                    let path = entry.path();
+                    if path.extension().map_or(false, |ext| ext == "json") {
+                        if let Some(item) = QuarantineItem::read(&path).await {
+                            if item.expires > now {
+                                items.push(item);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        items.sort_unstable_by_key(|item| item.id);
+        items
+    }
+
+    pub async fn quarantine_get(&self, id: QueueId) -> Option<QuarantineItem> {
+        QuarantineItem::read(
+            &self
+                .config
+                .quarantine
+                .path
+                .as_ref()?
+                .join(format!("{id:016x}.json")),
+        )
+        .await
+        .filter(|item| item.expires > now())
+    }
+
+    pub async fn quarantine_preview(&self, item: &QuarantineItem) -> Option<Vec<u8>> {
+        let path = self.config.quarantine.path.as_ref()?;
+        let mut bytes = fs::read(path.join(format!("{:016x}.msg", item.id)))
+            .await
+            .ok()?;
+        if bytes.len() > item.size {
+            bytes.truncate(item.size);
+            Some(bytes)
+        } else {
+            None
+        }
+    }
+
+    /// Queues a quarantined message for delivery to the specified recipients,
+    /// or to all of them when no recipients are provided.
+    pub async fn quarantine_release(
+        &self,
+        mut item: QuarantineItem,
+        rcpts: Option<&[String]>,
+        span: &tracing::Span,
+    ) -> bool {
+        let path = if let Some(path) = &self.config.quarantine.path {
+            path
+        } else {
+            return false;
+        };
+        let released = item
+            .recipients
+            .iter()
+            .filter(|rcpt| rcpts.map_or(true, |rcpts| rcpts.contains(rcpt)))
+            .cloned()
+            .collect::<Vec<_>>();
+        if released.is_empty() {
+            return false;
+        }
+
+        // Rebuild message from the stored metadata
+        let msg_path = path.join(format!("{:016x}.msg", item.id));
+        let bytes = match fs::read(&msg_path).await {
+            Ok(bytes) if bytes.len() > item.size => bytes,
+            Ok(_) => {
+                tracing::error!(
+                    parent: span,
+                    context = "quarantine",
+                    event = "error",
+                    "Invalid quarantine file {}.",
+                    msg_path.display(),
+                );
+                return false;
+            }
+            Err(err) => {
+                tracing::error!(
+                    parent: span,
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to read file {}: {}",
+                    msg_path.display(),
+                    err
+                );
+                return false;
+            }
+        };
+        let (raw_message, metadata) = bytes.split_at(item.size);
+        let mut message = if let Some(message) = Message::deserialize(metadata) {
+            Box::new(message)
+        } else {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "error",
+                "Failed to deserialize metadata for file {}.",
+                msg_path.display(),
+            );
+            return false;
+        };
+        message.size = item.size;
+        message.retain_recipients(|rcpt| released.contains(&rcpt.address_lcase));
+
+        // Each release is queued as a new message, as recipients can be
+        // released separately and the queue id names the spool file
+        message.id = 0;
+
+        // Time spent in quarantine does not count towards expiration
+        for domain_idx in 0..message.domains.len() {
+            let envelope =
+                SimpleEnvelope::new(message.as_ref(), &message.domains[domain_idx].domain);
+            let expires = *self.config.expire.eval(&envelope).await;
+            let notify = *self
+                .config
+                .notify
+                .eval(&envelope)
+                .await
+                .first()
+                .unwrap_or(&expires);
+            let domain = &mut message.domains[domain_idx];
+            domain.retry = Schedule::now();
+            domain.notify = Schedule::later(notify);
+            domain.expires = Instant::now() + expires;
+        }
+
+        if !self.has_quota(&mut message).await
+            || !self.queue_message(message, None, raw_message, span).await
+        {
+            return false;
+        }
+
+        tracing::info!(
+            parent: span,
+            context = "quarantine",
+            event = "released",
+            id = item.id,
+            nrcpts = released.len(),
+            "Quarantined message released."
+        );
+
+        item.recipients.retain(|rcpt| !released.contains(rcpt));
+        item.update(path, span).await;
+        true
+    }
+
+    /// Removes the specified recipients from a quarantined message, deleting it
+    /// once no recipients are left.
+    pub async fn quarantine_delete(
+        &self,
+        mut item: QuarantineItem,
+        rcpts: Option<&[String]>,
+        span: &tracing::Span,
+    ) -> bool {
+        if let Some(path) = &self.config.quarantine.path {
+            let num_rcpts = item.recipients.len();
+            if let Some(rcpts) = rcpts {
+                item.recipients.retain(|rcpt| !rcpts.contains(rcpt));
+            } else {
+                item.recipients.clear();
+            }
+            if item.recipients.len() != num_rcpts {
+                tracing::info!(
+                    parent: span,
+                    context = "quarantine",
+                    event = "deleted",
+                    id = item.id,
+                    nrcpts = num_rcpts - item.recipients.len(),
+                    "Quarantined message deleted."
+                );
+                item.update(path, span).await;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub async fn purge_quarantine(&self) {
+        let path = if let Some(path) = &self.config.quarantine.path {
+            path
+        } else {
+            return;
+        };
+        let mut dir = if let Ok(dir) = fs::read_dir(path).await {
+            dir
+        } else {
+            return;
+        };
+        let now = now();
+        while let Ok(Some(entry)) = dir.next_entry().await {
+            let path = entry.path();
+            if path.extension().map_or(false, |ext| ext == "json")
+                && QuarantineItem::read(&path)
+                    .await
+                    .map_or(false, |item| item.expires <= now)
+            {
+                let _ = fs::remove_file(path.with_extension("msg")).await;
+                let _ = fs::remove_file(&path).await;
+            }
+        }
+    }
+}
+
+impl QuarantineItem {
+    pub fn new(message: &Message, reason: impl Into<String>) -> Self {
+        QuarantineItem {
+            id: message.id,
+            created: 0,
+            expires: 0,
+            return_path: message.return_path.clone(),
+            recipients: message
+                .recipients
+                .iter()
+                .map(|rcpt| rcpt.address_lcase.clone())
+                .collect(),
+            reason: reason.into(),
+            score: None,
+            size: 0,
+            remote_ip: None,
+            subject: None,
+        }
+    }
+
+    pub fn with_score(mut self, score: Option<f64>) -> Self {
+        self.score = score;
+        self
+    }
+
+    pub fn with_remote_ip(mut self, remote_ip: IpAddr) -> Self {
+        self.remote_ip = remote_ip.into();
+        self
+    }
+
+    pub fn with_subject(mut self, subject: Option<&str>) -> Self {
+        self.subject = subject.map(|s| s.to_string());
+        self
+    }
+
+    async fn read(path: &Path) -> Option<Self> {
+        serde_json::from_slice(&fs::read(path).await.ok()?).ok()
+    }
+
+    async fn save(&self, path: &Path, span: &tracing::Span) -> bool {
+        let item_path = path.join(format!("{:016x}.json", self.id));
+        match fs::write(&item_path, serde_json::to_vec(self).unwrap_or_default()).await {
+            Ok(_) => true,
+            Err(err) => {
+                tracing::error!(
+                    parent: span,
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to write to file {}: {}",
+                    item_path.display(),
+                    err
+                );
+                false
+            }
+        }
+    }
+
+    async fn update(&self, path: &Path, span: &tracing::Span) {
+        if !self.recipients.is_empty() {
+            self.save(path, span).await;
+        } else {
+            for ext in ["json", "msg"] {
+                let item_path = path.join(format!("{:016x}.{ext}", self.id));
+                if let Err(err) = fs::remove_file(&item_path).await {
+                    tracing::error!(
+                        parent: span,
+                        context = "quarantine",
+                        event = "error",
+                        "Failed to delete file {}: {}",
+                        item_path.display(),
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Message {
+    pub fn retain_recipients(&mut self, f: impl Fn(&super::Recipient) -> bool) {
+        let mut domains = Vec::with_capacity(self.domains.len());
+        let mut domain_map = vec![usize::MAX; self.domains.len()];
+        let old_domains = std::mem::take(&mut self.domains);
+
+        self.recipients.retain(|rcpt| f(rcpt));
+        for rcpt in &mut self.recipients {
+            if domain_map[rcpt.domain_idx] == usize::MAX {
+                domain_map[rcpt.domain_idx] = domains.len();
+                domains.push(rcpt.domain_idx);
+            }
+            rcpt.domain_idx = domain_map[rcpt.domain_idx];
+        }
+        let mut old_domains = old_domains.into_iter().map(Some).collect::<Vec<_>>();
+        self.domains = domains
+            .into_iter()
+            .filter_map(|idx| old_domains[idx].take())
+            .collect();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -84,6 +84,13 @@ impl Message {
             rcpt.serialize(idx, &mut buf);
         }
 
+        // Serialize IP pool
+        if let Some(pool) = &self.pool {
+            buf.push('P');
+            0usize.serialize(&mut buf);
+            pool.serialize(&mut buf);
+        }
+
         buf.into_bytes()
     }
 
@@ -195,6 +202,7 @@ impl Message {
             },
             flags: usize::deserialize(&mut bytes)? as u64,
             priority: i16::deserialize(&mut bytes)?,
+            pool: None,
             size: 0,
             recipients: vec![],
             domains: vec![],
@@ -269,6 +277,13 @@ impl Message {
                         break;
                     }
                 }
+                b'P' => {
+                    if let Some(pool) = String::deserialize(&mut bytes) {
+                        message.pool = pool.into();
+                    } else {
+                        break;
+                    }
+                }
                 _ => break,
             }
         }
@@ -187,6 +187,7 @@ impl Message {
             flags: 0,
             env_id: None,
             priority: 0,
+            pool: None,
             size: 0,
             queue_refs: vec![],
         })
@@ -36,9 +36,12 @@ use mail_auth::{
     zip,
 };
 use mail_parser::{DateTime, MessageParser, MimeHeaders, PartType};
+use tokio::runtime::Handle;
 
 use crate::core::SMTP;
 
+use super::inbox::IncomingReport;
+
 enum Compression {
     None,
     Gzip,
@@ -64,6 +67,7 @@ pub trait AnalyzeReport {
 impl AnalyzeReport for Arc<SMTP> {
     fn analyze_report(&self, message: Arc<Vec<u8>>) {
         let core = self.clone();
+        let handle = Handle::current();
         self.worker_pool.spawn(move || {
             let message = if let Some(message) = MessageParser::default().parse(message.as_ref()) {
                 message
@@ -213,10 +217,14 @@ impl AnalyzeReport for Arc<SMTP> {
                     }
                 };
 
-                match report.format {
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                let mut incoming = match report.format {
                     Format::Dmarc => match Report::parse_xml(&data) {
                         Ok(report) => {
                             report.log();
+                            IncomingReport::from_dmarc(&report, from)
                         }
                         Err(err) => {
                             tracing::debug!(
@@ -231,6 +239,7 @@ impl AnalyzeReport for Arc<SMTP> {
                     Format::Tls => match TlsReport::parse_json(&data) {
                         Ok(report) => {
                             report.log();
+                            IncomingReport::from_tls(&report, from)
                         }
                         Err(err) => {
                             tracing::debug!(
@@ -245,6 +254,7 @@ impl AnalyzeReport for Arc<SMTP> {
                     Format::Arf => match Feedback::parse_arf(&data) {
                         Some(report) => {
                             report.log();
+                            IncomingReport::from_arf(&report, from, now)
                         }
                         None => {
                             tracing::debug!(
@@ -255,35 +265,32 @@ impl AnalyzeReport for Arc<SMTP> {
                             continue;
                         }
                     },
-                }
+                };
 
                 // Save report
+                let id = core
+                    .report
+                    .config
+                    .analysis
+                    .report_id
+                    .fetch_add(1, Ordering::Relaxed);
+                incoming.id = format!("{}_{now}_{id}", incoming.type_.as_str());
+                incoming.received = now;
                 if let Some(report_path) = &core.report.config.analysis.store {
-                    let (report_format, extension) = match report.format {
-                        Format::Dmarc => ("dmarc", "xml"),
-                        Format::Tls => ("tlsrpt", "json"),
-                        Format::Arf => ("arf", "txt"),
+                    let extension = match report.format {
+                        Format::Dmarc => "xml",
+                        Format::Tls => "json",
+                        Format::Arf => "txt",
                     };
                     let c_extension = match report.compression {
                         Compression::None => "",
                         Compression::Gzip => ".gz",
                         Compression::Zip => ".zip",
                     };
-                    let now = SystemTime::now()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .map_or(0, |d| d.as_secs());
-                    let id = core
-                        .report
-                        .config
-                        .analysis
-                        .report_id
-                        .fetch_add(1, Ordering::Relaxed);
 
                     // Build path
                     let mut report_path = report_path.clone();
-                    report_path.push(format!(
-                        "{report_format}_{now}_{id}.{extension}{c_extension}"
-                    ));
+                    report_path.push(format!("{}.{extension}{c_extension}", incoming.id));
                     if let Err(err) = std::fs::write(&report_path, report.data) {
                         tracing::warn!(
                             context = "report",
@@ -295,6 +302,17 @@ impl AnalyzeReport for Arc<SMTP> {
                         );
                     }
                 }
+
+                // Store summary
+                if let Err(err) = handle.block_on(core.report.incoming_store(&incoming)) {
+                    tracing::warn!(
+                        context = "report",
+                        event = "error",
+                        from = from,
+                        "Failed to store incoming report summary: {}",
+                        err
+                    );
+                }
                 break;
             }
         });
@@ -0,0 +1,487 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::net::IpAddr;
+
+use ahash::AHashMap;
+use mail_auth::report::{tlsrpt::TlsReport, DmarcResult, Feedback, Report};
+use serde::{Deserialize, Serialize};
+use store::{
+    write::{key::KeySerializer, BatchBuilder, Operation, ValueClass},
+    CustomValueKey,
+};
+
+use crate::core::ReportCore;
+
+// Custom keys start with u32::MAX followed by the key type, types 0 to 7 are
+// used by the store, legal hold and shared seen keys.
+const INCOMING_REPORT_KEY: u8 = 8;
+
+// Summaries are keyed by the date they were received, the index keys end with
+// the same reference (received date, sequence number and report type).
+const SUMMARY: u8 = 0;
+const BY_DOMAIN: u8 = 1;
+const BY_REPORTER: u8 = 2;
+const BY_DATE: u8 = 3;
+const REF_LEN: usize = std::mem::size_of::<u64>() * 2 + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IncomingReportType {
+    Dmarc,
+    Tls,
+    Arf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncomingReport {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: IncomingReportType,
+    pub domains: Vec<String>,
+    pub reporter: String,
+    pub report_id: String,
+    pub range_from: u64,
+    pub range_to: u64,
+    pub received: u64,
+    pub sources: Vec<SourceSummary>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceSummary {
+    pub ip: Option<IpAddr>,
+    pub total: u64,
+    pub pass: u64,
+    pub fail: u64,
+    pub dkim_fail: u64,
+    pub spf_fail: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct IncomingReportFilter {
+    pub type_: Option<IncomingReportType>,
+    pub domain: Option<String>,
+    pub reporter: Option<String>,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+impl ReportCore {
+    /// Returns the stored incoming reports that match the filter, sorted by
+    /// the date they were received.
+    pub async fn incoming_list(&self, filter: &IncomingReportFilter) -> Vec<IncomingReport> {
+        let store = if let Some(store) = &self.store {
+            store
+        } else {
+            return vec![];
+        };
+
+        // Use the most selective index available
+        let from = filter.from.unwrap_or(0);
+        let (index, from_key, to_key) = if let Some(domain) = &filter.domain {
+            (
+                BY_DOMAIN,
+                index_key(BY_DOMAIN, domain.as_bytes())
+                    .write(0u8)
+                    .write(from)
+                    .finalize(),
+                index_key(BY_DOMAIN, domain.as_bytes())
+                    .write(0u8)
+                    .write(&[u8::MAX; REF_LEN + std::mem::size_of::<u64>()][..])
+                    .finalize(),
+            )
+        } else if let Some(reporter) = &filter.reporter {
+            (
+                BY_REPORTER,
+                index_key(BY_REPORTER, reporter.as_bytes()).finalize(),
+                index_key(BY_REPORTER, reporter.as_bytes())
+                    .write(u8::MAX)
+                    .finalize(),
+            )
+        } else if filter.from.is_some() {
+            (
+                BY_DATE,
+                index_key(BY_DATE, &[]).write(from).finalize(),
+                index_key(BY_DATE, &[u8::MAX; REF_LEN + std::mem::size_of::<u64>()]).finalize(),
+            )
+        } else {
+            (
+                SUMMARY,
+                index_key(SUMMARY, &[]).finalize(),
+                index_key(SUMMARY, &[u8::MAX; REF_LEN]).finalize(),
+            )
+        };
+        let result = store
+            .iterate(
+                Vec::new(),
+                CustomValueKey { value: from_key },
+                CustomValueKey { value: to_key },
+                false,
+                true,
+                move |refs: &mut Vec<(Vec<u8>, Option<IncomingReport>)>, key, value| {
+                    let report_ref = key
+                        .get(key.len().saturating_sub(REF_LEN)..)
+                        .unwrap_or_default()
+                        .to_vec();
+                    refs.push(if index == SUMMARY {
+                        (
+                            report_ref,
+                            <IncomingReport as store::Deserialize>::deserialize(value)?.into(),
+                        )
+                    } else {
+                        (report_ref, None)
+                    });
+                    Ok(true)
+                },
+            )
+            .await;
+
+        let mut reports = Vec::new();
+        match result {
+            Ok(refs) => {
+                for (report_ref, report) in refs {
+                    let report = if let Some(report) = report {
+                        report
+                    } else if let Some(report) = self.incoming_read(report_ref).await {
+                        report
+                    } else {
+                        continue;
+                    };
+                    if filter.matches(&report) {
+                        reports.push(report);
+                    }
+                }
+            }
+            Err(err) => {
+                tracing::error!(
+                    context = "report",
+                    event = "error",
+                    "Failed to list incoming reports: {}",
+                    err
+                );
+            }
+        }
+        reports.sort_unstable_by(|a, b| a.received.cmp(&b.received).then(a.id.cmp(&b.id)));
+        reports
+    }
+
+    pub async fn incoming_get(&self, id: &str) -> Option<IncomingReport> {
+        self.incoming_read(report_ref(id)?).await
+    }
+
+    /// Stores the summary of an incoming report along with its domain,
+    /// reporter and date index keys.
+    pub async fn incoming_store(&self, report: &IncomingReport) -> store::Result<()> {
+        let store = if let Some(store) = &self.store {
+            store
+        } else {
+            return Ok(());
+        };
+        let report_ref = report_ref(&report.id).ok_or_else(|| {
+            store::Error::InternalError(format!("Invalid report id {:?}", report.id))
+        })?;
+
+        let mut batch = BatchBuilder::new();
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: index_key(SUMMARY, &report_ref).finalize(),
+            },
+            set: serde_json::to_vec(report).unwrap_or_default().into(),
+        });
+        let reporter = report.reporter.to_lowercase();
+        for (index, value) in report
+            .domains
+            .iter()
+            .map(|domain| (BY_DOMAIN, domain.as_bytes()))
+            .chain([(BY_REPORTER, reporter.as_bytes()), (BY_DATE, &[][..])])
+        {
+            let mut key = index_key(index, value);
+            if index != BY_DATE {
+                key = key.write(0u8);
+            }
+            batch.op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: key.write(report.range_to).write(&report_ref[..]).finalize(),
+                },
+                set: Vec::new().into(),
+            });
+        }
+        store.write(batch.build()).await
+    }
+
+    async fn incoming_read(&self, report_ref: Vec<u8>) -> Option<IncomingReport> {
+        match self
+            .store
+            .as_ref()?
+            .get_value::<IncomingReport>(CustomValueKey {
+                value: index_key(SUMMARY, &report_ref).finalize(),
+            })
+            .await
+        {
+            Ok(report) => report,
+            Err(err) => {
+                tracing::error!(
+                    context = "report",
+                    event = "error",
+                    "Failed to read incoming report: {}",
+                    err
+                );
+                None
+            }
+        }
+    }
+}
+
+impl IncomingReport {
+    pub fn new(type_: IncomingReportType, reporter: impl Into<String>) -> Self {
+        IncomingReport {
+            id: String::new(),
+            type_,
+            domains: vec![],
+            reporter: reporter.into(),
+            report_id: String::new(),
+            range_from: 0,
+            range_to: 0,
+            received: 0,
+            sources: vec![],
+        }
+    }
+
+    pub fn from_dmarc(report: &Report, from: &str) -> Self {
+        let mut sources: AHashMap<Option<IpAddr>, SourceSummary> = AHashMap::new();
+        for record in report.records() {
+            let count = record.count() as u64;
+            let dkim_pass = matches!(record.dmarc_dkim_result(), DmarcResult::Pass);
+            let spf_pass = matches!(record.dmarc_spf_result(), DmarcResult::Pass);
+            let source = sources.entry(record.source_ip()).or_default();
+            source.total += count;
+            if dkim_pass || spf_pass {
+                source.pass += count;
+            } else {
+                source.fail += count;
+            }
+            if !dkim_pass {
+                source.dkim_fail += count;
+            }
+            if !spf_pass {
+                source.spf_fail += count;
+            }
+        }
+
+        IncomingReport {
+            domains: vec![report.domain().to_lowercase()],
+            report_id: report.report_id().to_string(),
+            range_from: report.date_range_begin(),
+            range_to: report.date_range_end(),
+            sources: sources
+                .into_iter()
+                .map(|(ip, source)| SourceSummary { ip, ..source })
+                .collect(),
+            ..IncomingReport::new(
+                IncomingReportType::Dmarc,
+                if !report.org_name().is_empty() {
+                    report.org_name()
+                } else {
+                    from
+                },
+            )
+        }
+        .with_sorted_sources()
+    }
+
+    pub fn from_tls(report: &TlsReport, from: &str) -> Self {
+        let mut sources: AHashMap<Option<IpAddr>, SourceSummary> = AHashMap::new();
+        let mut domains = Vec::with_capacity(report.policies.len());
+        for policy in &report.policies {
+            let domain = policy.policy.policy_domain.to_lowercase();
+            if !domains.contains(&domain) {
+                domains.push(domain);
+            }
+
+            // Failures are attributed to the sending MTA when available
+            let mut total_failure = policy.summary.total_failure as u64;
+            for failure in &policy.failure_details {
+                let count = std::cmp::min(failure.failed_session_count as u64, total_failure);
+                if count > 0 {
+                    let source = sources.entry(failure.sending_mta_ip).or_default();
+                    source.total += count;
+                    source.fail += count;
+                    total_failure -= count;
+                }
+            }
+            let source = sources.entry(None).or_default();
+            source.total += policy.summary.total_success as u64 + total_failure;
+            source.pass += policy.summary.total_success as u64;
+            source.fail += total_failure;
+        }
+
+        IncomingReport {
+            domains,
+            report_id: report.report_id.clone(),
+            range_from: report.date_range.start_datetime.to_timestamp() as u64,
+            range_to: report.date_range.end_datetime.to_timestamp() as u64,
+            sources: sources
+                .into_iter()
+                .filter(|(_, source)| source.total > 0)
+                .map(|(ip, source)| SourceSummary { ip, ..source })
+                .collect(),
+            ..IncomingReport::new(
+                IncomingReportType::Tls,
+                report
+                    .organization_name
+                    .as_deref()
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or(from),
+            )
+        }
+        .with_sorted_sources()
+    }
+
+    pub fn from_arf(report: &Feedback<'_>, from: &str, received: u64) -> Self {
+        let arrival_date = report
+            .arrival_date()
+            .map(|date| date as u64)
+            .unwrap_or(received);
+        let count = std::cmp::max(report.incidents(), 1) as u64;
+
+        IncomingReport {
+            domains: report
+                .reported_domain()
+                .iter()
+                .map(|domain| domain.to_lowercase())
+                .collect(),
+            range_from: arrival_date,
+            range_to: arrival_date,
+            sources: vec![SourceSummary {
+                ip: report.source_ip(),
+                total: count,
+                pass: 0,
+                fail: count,
+                dkim_fail: 0,
+                spf_fail: 0,
+            }],
+            ..IncomingReport::new(
+                IncomingReportType::Arf,
+                report
+                    .reporting_mta()
+                    .filter(|mta| !mta.is_empty())
+                    .unwrap_or(from),
+            )
+        }
+    }
+
+    fn with_sorted_sources(mut self) -> Self {
+        self.sources
+            .sort_unstable_by(|a, b| b.fail.cmp(&a.fail).then(b.total.cmp(&a.total)));
+        self
+    }
+}
+
+impl IncomingReportFilter {
+    pub fn matches(&self, report: &IncomingReport) -> bool {
+        self.type_.map_or(true, |type_| type_ == report.type_)
+            && self
+                .domain
+                .as_ref()
+                .map_or(true, |domain| report.domains.iter().any(|d| d == domain))
+            && self.reporter.as_ref().map_or(true, |reporter| {
+                report.reporter.to_lowercase().starts_with(reporter)
+            })
+            && self.from.map_or(true, |from| report.range_to >= from)
+            && self.to.map_or(true, |to| report.range_from <= to)
+    }
+}
+
+impl SourceSummary {
+    /// Aggregates the results of multiple reports by source IP address.
+    pub fn aggregate<'x>(reports: impl IntoIterator<Item = &'x IncomingReport>) -> Vec<Self> {
+        let mut sources: AHashMap<Option<IpAddr>, SourceSummary> = AHashMap::new();
+        for source in reports.into_iter().flat_map(|report| report.sources.iter()) {
+            let entry = sources.entry(source.ip).or_default();
+            entry.total += source.total;
+            entry.pass += source.pass;
+            entry.fail += source.fail;
+            entry.dkim_fail += source.dkim_fail;
+            entry.spf_fail += source.spf_fail;
+        }
+        let mut sources = sources
+            .into_iter()
+            .map(|(ip, source)| SourceSummary { ip, ..source })
+            .collect::<Vec<_>>();
+        sources.sort_unstable_by(|a, b| b.fail.cmp(&a.fail).then(b.total.cmp(&a.total)));
+        sources
+    }
+}
+
+impl store::Deserialize for IncomingReport {
+    fn deserialize(bytes: &[u8]) -> store::Result<Self> {
+        serde_json::from_slice(bytes).map_err(|err| {
+            store::Error::InternalError(format!("Failed to deserialize report: {err}"))
+        })
+    }
+}
+
+impl IncomingReportType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IncomingReportType::Dmarc => "dmarc",
+            IncomingReportType::Tls => "tlsrpt",
+            IncomingReportType::Arf => "arf",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "dmarc" => IncomingReportType::Dmarc.into(),
+            "tlsrpt" => IncomingReportType::Tls.into(),
+            "arf" => IncomingReportType::Arf.into(),
+            _ => None,
+        }
+    }
+}
+
+fn index_key(index: u8, value: &[u8]) -> KeySerializer {
+    KeySerializer::new(value.len() + REF_LEN + std::mem::size_of::<u64>() + 7)
+        .write(u32::MAX)
+        .write(INCOMING_REPORT_KEY)
+        .write(index)
+        .write(value)
+}
+
+// Report ids have the format <type>_<received>_<sequence number>.
+fn report_ref(id: &str) -> Option<Vec<u8>> {
+    let mut parts = id.splitn(3, '_');
+    let type_ = IncomingReportType::parse(parts.next()?)?;
+    let received = parts.next()?.parse::<u64>().ok()?;
+    let seq = parts.next()?.parse::<u64>().ok()?;
+
+    Some(
+        KeySerializer::new(REF_LEN)
+            .write(received)
+            .write(seq)
+            .write(type_ as u8)
+            .finalize(),
+    )
+}
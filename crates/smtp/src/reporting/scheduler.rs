@@ -35,7 +35,6 @@ use std::{
     collections::{hash_map::Entry, BinaryHeap},
     hash::Hash,
     path::PathBuf,
-    sync::Arc,
     time::{Duration, Instant, SystemTime},
 };
 use tokio::{
@@ -44,6 +43,8 @@ use tokio::{
     sync::mpsc,
 };
 
+use utils::reload::Reloadable;
+
 use crate::{
     config::AggregateFrequency,
     core::{management::ReportRequest, worker::SpawnCleanup, ReportCore, SMTP},
@@ -83,12 +84,15 @@ pub struct ReportPolicy<T> {
 }
 
 impl SpawnReport for mpsc::Receiver<Event> {
-    fn spawn(mut self, core: Arc<SMTP>, mut scheduler: Scheduler) {
+    fn spawn(mut self, core: impl Into<Reloadable<SMTP>>, mut scheduler: Scheduler) {
+        let handle = core.into();
         tokio::spawn(async move {
             let mut last_cleanup = Instant::now();
 
             loop {
-                match tokio::time::timeout(scheduler.wake_up_time(), self.recv()).await {
+                let result = tokio::time::timeout(scheduler.wake_up_time(), self.recv()).await;
+                let core = handle.load();
+                match result {
                     Ok(Some(event)) => match event {
                         Event::Dmarc(event) => {
                             scheduler.schedule_dmarc(event, &core).await;
@@ -659,5 +663,5 @@ impl ToTimestamp for Duration {
 }
 
 pub trait SpawnReport {
-    fn spawn(self, core: Arc<SMTP>, scheduler: Scheduler);
+    fn spawn(self, core: impl Into<Reloadable<SMTP>>, scheduler: Scheduler);
 }
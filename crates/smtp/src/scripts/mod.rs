@@ -55,6 +55,10 @@ pub enum ScriptModification {
         name: Arc<String>,
         value: Arc<String>,
     },
+    Quarantine {
+        reason: String,
+        score: Option<f64>,
+    },
 }
 
 pub struct ScriptParameters {
@@ -28,6 +28,7 @@ pub mod headers;
 pub mod http;
 pub mod lookup;
 pub mod pyzor;
+pub mod quarantine;
 pub mod query;
 
 use mail_parser::Message;
@@ -50,7 +51,7 @@ pub struct PluginContext<'x> {
     pub arguments: Vec<Variable>,
 }
 
-const PLUGINS_EXEC: [ExecPluginFnc; 15] = [
+const PLUGINS_EXEC: [ExecPluginFnc; 16] = [
     query::exec,
     exec::exec,
     lookup::exec,
@@ -66,8 +67,9 @@ const PLUGINS_EXEC: [ExecPluginFnc; 15] = [
     bayes::exec_is_balanced,
     pyzor::exec,
     headers::exec,
+    quarantine::exec,
 ];
-const PLUGINS_REGISTER: [RegisterPluginFnc; 15] = [
+const PLUGINS_REGISTER: [RegisterPluginFnc; 16] = [
     query::register,
     exec::register,
     lookup::register,
@@ -83,6 +85,7 @@ const PLUGINS_REGISTER: [RegisterPluginFnc; 15] = [
     bayes::register_is_balanced,
     pyzor::register,
     headers::register,
+    quarantine::register,
 ];
 
 pub trait RegisterSievePlugins {
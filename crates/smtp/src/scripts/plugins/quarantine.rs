@@ -0,0 +1,49 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use sieve::{runtime::Variable, FunctionMap};
+
+use crate::{config::scripts::SieveContext, scripts::ScriptModification};
+
+use super::PluginContext;
+
+pub fn register(plugin_id: u32, fnc_map: &mut FunctionMap<SieveContext>) {
+    fnc_map.set_external_function("quarantine", plugin_id, 2);
+}
+
+pub fn exec(ctx: PluginContext<'_>) -> Variable {
+    let reason = ctx.arguments[0].to_string();
+    if reason.is_empty() {
+        return false.into();
+    }
+
+    ctx.modifications.push(ScriptModification::Quarantine {
+        reason: reason.into_owned(),
+        score: match &ctx.arguments[1] {
+            Variable::Float(n) => Some(*n),
+            Variable::Integer(n) => Some(*n as f64),
+            _ => None,
+        },
+    });
+    true.into()
+}
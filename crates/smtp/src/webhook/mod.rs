@@ -0,0 +1,289 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{net::IpAddr, time::SystemTime};
+
+use crate::{
+    core::WebhookCore,
+    queue::{
+        quarantine::QuarantineItem, Error, HostResponse, Message, Recipient, Status,
+        RCPT_STATUS_CHANGED,
+    },
+};
+use mail_parser::DateTime;
+use serde::{Deserialize, Serialize};
+
+pub mod outbox;
+
+#[derive(Debug)]
+pub enum Event {
+    Send(Box<WebhookEvent>),
+    Done(outbox::DeliveryResult),
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookType {
+    #[serde(rename = "message.accepted")]
+    MessageAccepted,
+    #[serde(rename = "message.delivered")]
+    MessageDelivered,
+    #[serde(rename = "message.deferred")]
+    MessageDeferred,
+    #[serde(rename = "message.bounced")]
+    MessageBounced,
+    #[serde(rename = "message.expired")]
+    MessageExpired,
+    #[serde(rename = "message.quarantined")]
+    MessageQuarantined,
+    #[serde(rename = "rcpt.rejected")]
+    RcptRejected,
+    #[serde(rename = "auth.failed")]
+    AuthFailed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookEvent {
+    #[serde(rename = "type")]
+    pub type_: WebhookType,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub queue_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub to: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub remote_host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub remote_ip: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub authenticated_as: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl WebhookCore {
+    pub fn is_enabled(&self, type_: WebhookType) -> bool {
+        (self.config.events & type_.mask()) != 0
+    }
+
+    pub async fn send(&self, event: WebhookEvent) {
+        // Wait for capacity rather than dropping events when the channel is full
+        if self.is_enabled(event.type_) {
+            if let Err(err) = self.tx.send(Event::Send(Box::new(event))).await {
+                tracing::warn!(
+                    context = "webhook",
+                    event = "error",
+                    reason = %err,
+                    "Failed to send event to webhook manager."
+                );
+            }
+        }
+    }
+
+    pub async fn send_delivery_events(&self, message: &Message, attempted: &[usize]) {
+        let types = WebhookType::MessageDelivered.mask()
+            | WebhookType::MessageDeferred.mask()
+            | WebhookType::MessageBounced.mask();
+        if (self.config.events & types) == 0 {
+            return;
+        }
+
+        for rcpt in &message.recipients {
+            if !attempted.contains(&rcpt.domain_idx) {
+                continue;
+            }
+            let domain = &message.domains[rcpt.domain_idx];
+            let event = if rcpt.has_flag(RCPT_STATUS_CHANGED) {
+                match &rcpt.status {
+                    Status::Completed(response) => WebhookEvent::new(WebhookType::MessageDelivered)
+                        .with_remote_host(&response.hostname)
+                        .with_response(&response.response),
+                    Status::TemporaryFailure(response) => {
+                        WebhookEvent::new(WebhookType::MessageDeferred)
+                            .with_remote_host(&response.hostname.entity)
+                            .with_response(&response.response)
+                    }
+                    Status::PermanentFailure(response) => {
+                        WebhookEvent::new(WebhookType::MessageBounced)
+                            .with_remote_host(&response.hostname.entity)
+                            .with_response(&response.response)
+                    }
+                    Status::Scheduled => continue,
+                }
+            } else if !matches!(
+                &rcpt.status,
+                Status::Completed(_) | Status::PermanentFailure(_)
+            ) {
+                // There is no status for this address, use the domain's status.
+                match &domain.status {
+                    // Local throttling is not reported as a deferral
+                    Status::TemporaryFailure(Error::RateLimited | Error::ConcurrencyLimited)
+                    | Status::Scheduled
+                    | Status::Completed(_) => continue,
+                    Status::TemporaryFailure(err) => {
+                        WebhookEvent::new(WebhookType::MessageDeferred).with_error(err)
+                    }
+                    Status::PermanentFailure(err) => {
+                        WebhookEvent::new(WebhookType::MessageBounced).with_error(err)
+                    }
+                }
+            } else {
+                continue;
+            };
+
+            self.send(event.with_message(message).with_recipient(rcpt))
+                .await;
+        }
+    }
+}
+
+impl WebhookEvent {
+    pub fn new(type_: WebhookType) -> Self {
+        WebhookEvent {
+            type_,
+            created_at: DateTime::from_timestamp(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs()) as i64,
+            )
+            .to_rfc3339(),
+            queue_id: None,
+            from: None,
+            to: Vec::new(),
+            size: None,
+            remote_host: None,
+            remote_ip: None,
+            authenticated_as: None,
+            response: None,
+            reason: None,
+        }
+    }
+
+    pub fn with_message(mut self, message: &Message) -> Self {
+        self.queue_id = message.id.into();
+        self.from = message.return_path.clone().into();
+        self.size = message.size.into();
+        self
+    }
+
+    pub fn with_quarantine(mut self, item: &QuarantineItem) -> Self {
+        self.queue_id = item.id.into();
+        self.from = item.return_path.clone().into();
+        self.to = item.recipients.clone();
+        self.size = item.size.into();
+        self.reason = item.reason.clone().into();
+        self
+    }
+
+    pub fn with_recipient(mut self, rcpt: &Recipient) -> Self {
+        self.to.push(rcpt.address.clone());
+        self
+    }
+
+    pub fn with_from(mut self, from: impl Into<String>) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    pub fn with_to(mut self, to: impl Into<String>) -> Self {
+        self.to.push(to.into());
+        self
+    }
+
+    pub fn with_remote_host(mut self, remote_host: impl Into<String>) -> Self {
+        self.remote_host = Some(remote_host.into());
+        self
+    }
+
+    pub fn with_remote_ip(mut self, remote_ip: IpAddr) -> Self {
+        self.remote_ip = Some(remote_ip);
+        self
+    }
+
+    pub fn with_authenticated_as(mut self, authenticated_as: impl Into<String>) -> Self {
+        let authenticated_as = authenticated_as.into();
+        if !authenticated_as.is_empty() {
+            self.authenticated_as = Some(authenticated_as);
+        }
+        self
+    }
+
+    pub fn with_response(mut self, response: impl ToString) -> Self {
+        self.response = Some(response.to_string().trim_end().to_string());
+        self
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_error(self, err: &Error) -> Self {
+        match err {
+            Error::UnexpectedResponse(HostResponse { hostname, response }) => self
+                .with_remote_host(&hostname.entity)
+                .with_response(response),
+            Error::ConnectionError(details)
+            | Error::TlsError(details)
+            | Error::DaneError(details) => self
+                .with_remote_host(&details.entity)
+                .with_reason(err.to_string()),
+            _ => self.with_reason(err.to_string()),
+        }
+    }
+}
+
+impl WebhookType {
+    pub fn mask(&self) -> u32 {
+        1 << (*self as u32)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookType::MessageAccepted => "message.accepted",
+            WebhookType::MessageDelivered => "message.delivered",
+            WebhookType::MessageDeferred => "message.deferred",
+            WebhookType::MessageBounced => "message.bounced",
+            WebhookType::MessageExpired => "message.expired",
+            WebhookType::MessageQuarantined => "message.quarantined",
+            WebhookType::RcptRejected => "rcpt.rejected",
+            WebhookType::AuthFailed => "auth.failed",
+        }
+    }
+}
@@ -0,0 +1,460 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    collections::BinaryHeap,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use ahash::AHashMap;
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::{fs, sync::mpsc};
+
+use utils::reload::Reloadable;
+
+use crate::{
+    config::Webhook,
+    core::{WebhookCore, SMTP},
+    queue::{InstantFromTimestamp, Schedule},
+};
+
+use super::{Event, WebhookEvent};
+
+#[derive(Debug)]
+pub struct Outbox {
+    short_wait: Duration,
+    long_wait: Duration,
+    last_id: u64,
+    pub scheduled: BinaryHeap<Schedule<u64>>,
+    pub entries: AHashMap<u64, OutboxEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub webhook: String,
+    pub attempt: u32,
+    pub due: u64,
+    pub body: String,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct DeliveryResult {
+    pub id: u64,
+    pub success: bool,
+}
+
+#[cfg(feature = "test_mode")]
+pub static WEBHOOK_TEST_REQUESTS: parking_lot::Mutex<Vec<(String, String)>> =
+    parking_lot::Mutex::new(Vec::new());
+
+impl SpawnWebhook for mpsc::Receiver<Event> {
+    fn spawn(mut self, core: impl Into<Reloadable<SMTP>>, mut outbox: Outbox) {
+        let handle = core.into();
+        tokio::spawn(async move {
+            loop {
+                let result = tokio::time::timeout(outbox.wake_up_time(), self.recv()).await;
+                let core = handle.load();
+
+                // Deliver due events
+                while let Some(id) = outbox.next_due() {
+                    outbox.deliver(&core, id).await;
+                }
+
+                match result {
+                    Ok(Some(Event::Send(event))) => {
+                        outbox.enqueue(&core.webhook, *event).await;
+                    }
+                    Ok(Some(Event::Done(result))) => {
+                        outbox.done(&core.webhook, result).await;
+                    }
+                    Ok(Some(Event::Stop)) | Ok(None) => break,
+                    Err(_) => (),
+                }
+            }
+        });
+    }
+}
+
+impl Outbox {
+    pub async fn enqueue(&mut self, core: &WebhookCore, event: WebhookEvent) {
+        let body = match serde_json::to_string(&event) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::error!(
+                    context = "webhook",
+                    event = "error",
+                    "Failed to serialize webhook event: {}",
+                    err
+                );
+                return;
+            }
+        };
+
+        for webhook in &core.config.endpoints {
+            if (webhook.events & event.type_.mask()) == 0 {
+                continue;
+            }
+
+            let id = self.next_id();
+            let entry = OutboxEntry {
+                webhook: webhook.id.clone(),
+                attempt: 0,
+                due: now(),
+                body: body.clone(),
+                path: core.config.path.join(format!("{id:016x}.json")),
+            };
+            entry.save().await;
+            self.scheduled.push(Schedule {
+                due: Instant::now(),
+                inner: id,
+            });
+            self.entries.insert(id, entry);
+        }
+    }
+
+    pub async fn deliver(&mut self, core: &Arc<SMTP>, id: u64) {
+        let entry = if let Some(entry) = self.entries.get(&id) {
+            entry
+        } else {
+            return;
+        };
+        let webhook = if let Some(webhook) = core
+            .webhook
+            .config
+            .endpoints
+            .iter()
+            .find(|w| w.id == entry.webhook)
+        {
+            webhook.clone()
+        } else {
+            tracing::info!(
+                context = "webhook",
+                event = "discard",
+                webhook = entry.webhook,
+                "Discarding event for webhook no longer configured."
+            );
+            if let Some(entry) = self.entries.remove(&id) {
+                entry.remove().await;
+            }
+            return;
+        };
+
+        let tx = core.webhook.tx.clone();
+        let body = entry.body.clone();
+        tokio::spawn(async move {
+            let success = match webhook.post(id, &body).await {
+                Ok(_) => {
+                    tracing::debug!(
+                        context = "webhook",
+                        event = "success",
+                        webhook = webhook.id,
+                        url = webhook.url,
+                    );
+                    true
+                }
+                Err(err) => {
+                    tracing::info!(
+                        context = "webhook",
+                        event = "failed",
+                        webhook = webhook.id,
+                        url = webhook.url,
+                        reason = %err,
+                    );
+                    false
+                }
+            };
+            if tx
+                .send(Event::Done(DeliveryResult { id, success }))
+                .await
+                .is_err()
+            {
+                tracing::warn!("Channel closed while trying to notify webhook manager.");
+            }
+        });
+    }
+
+    pub async fn done(&mut self, core: &WebhookCore, result: DeliveryResult) {
+        let retry = match self
+            .entries
+            .get(&result.id)
+            .and_then(|entry| core.config.endpoints.iter().find(|w| w.id == entry.webhook))
+        {
+            Some(webhook) if !result.success => webhook.retry.as_slice(),
+            _ => &[],
+        };
+
+        if let Some(entry) = self.entries.get_mut(&result.id) {
+            if !result.success {
+                if let Some(wait) = retry.get(entry.attempt as usize) {
+                    entry.attempt += 1;
+                    entry.due = now() + wait.as_secs();
+                    entry.save().await;
+                    self.scheduled.push(Schedule {
+                        due: Instant::now() + *wait,
+                        inner: result.id,
+                    });
+                    return;
+                }
+
+                tracing::warn!(
+                    context = "webhook",
+                    event = "discard",
+                    webhook = entry.webhook,
+                    attempts = entry.attempt + 1,
+                    "Webhook delivery failed too many times, discarding event."
+                );
+            }
+
+            if let Some(entry) = self.entries.remove(&result.id) {
+                entry.remove().await;
+            }
+        }
+    }
+
+    pub fn next_due(&mut self) -> Option<u64> {
+        let item = self.scheduled.peek()?;
+        if item.due <= Instant::now() {
+            self.scheduled.pop().map(|i| i.inner)
+        } else {
+            None
+        }
+    }
+
+    pub fn wake_up_time(&self) -> Duration {
+        self.scheduled
+            .peek()
+            .map(|item| {
+                item.due
+                    .checked_duration_since(Instant::now())
+                    .unwrap_or(self.short_wait)
+            })
+            .unwrap_or(self.long_wait)
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = std::cmp::max(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64),
+            self.last_id + 1,
+        );
+        self.last_id = id;
+        id
+    }
+}
+
+impl OutboxEntry {
+    pub async fn save(&self) {
+        match serde_json::to_vec(self) {
+            Ok(bytes) => {
+                let mut result = fs::write(&self.path, &bytes).await;
+                if matches!(&result, Err(err) if err.kind() == ErrorKind::NotFound) {
+                    // Create the outbox directory on first write
+                    if let Some(parent) = self.path.parent() {
+                        result = match fs::create_dir_all(parent).await {
+                            Ok(_) => fs::write(&self.path, &bytes).await,
+                            Err(err) => Err(err),
+                        };
+                    }
+                }
+                if let Err(err) = result {
+                    tracing::error!(
+                        context = "webhook",
+                        event = "error",
+                        "Failed to write to {}: {}",
+                        self.path.display(),
+                        err
+                    );
+                }
+            }
+            Err(err) => {
+                tracing::error!(
+                    context = "webhook",
+                    event = "error",
+                    "Failed to serialize outbox entry: {}",
+                    err
+                );
+            }
+        }
+    }
+
+    pub async fn remove(&self) {
+        if let Err(err) = fs::remove_file(&self.path).await {
+            tracing::error!(
+                context = "webhook",
+                event = "error",
+                "Failed to delete {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+impl Webhook {
+    pub async fn post(&self, id: u64, body: &str) -> Result<(), String> {
+        let signature = self
+            .signature_key
+            .as_ref()
+            .map(|key| format!("sha256={}", sign(key, body.as_bytes())));
+
+        #[cfg(feature = "test_mode")]
+        if self.url.starts_with("https://127.0.0.1/") {
+            WEBHOOK_TEST_REQUESTS
+                .lock()
+                .push((signature.unwrap_or_default(), body.to_string()));
+            return Ok(());
+        }
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Webhook-Id", format!("{id:016x}"));
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(signature) = signature {
+            request = request.header("X-Signature", signature);
+        }
+
+        let response = request
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Unexpected HTTP status {}", response.status()))
+        }
+    }
+}
+
+impl WebhookCore {
+    pub async fn read_outbox(&self) -> Outbox {
+        let mut outbox = Outbox::default();
+        let mut dir = match fs::read_dir(&self.config.path).await {
+            Ok(dir) => dir,
+            Err(_) => return outbox,
+        };
+
+        loop {
+            match dir.next_entry().await {
+                Ok(Some(file)) => {
+                    let path = file.path();
+                    let id = match path
+                        .file_stem()
+                        .and_then(|f| f.to_str())
+                        .filter(|_| path.extension().map_or(false, |e| e == "json"))
+                        .and_then(|f| u64::from_str_radix(f, 16).ok())
+                    {
+                        Some(id) => id,
+                        None => continue,
+                    };
+
+                    match fs::read(&path)
+                        .await
+                        .map_err(|err| err.to_string())
+                        .and_then(|bytes| {
+                            serde_json::from_slice::<OutboxEntry>(&bytes)
+                                .map_err(|err| err.to_string())
+                        }) {
+                        Ok(mut entry) => {
+                            entry.path = path;
+                            outbox.last_id = std::cmp::max(outbox.last_id, id);
+                            outbox.scheduled.push(Schedule {
+                                due: entry.due.to_instant(),
+                                inner: id,
+                            });
+                            outbox.entries.insert(id, entry);
+                        }
+                        Err(err) => {
+                            tracing::warn!(
+                                context = "webhook",
+                                event = "error",
+                                "Failed to read outbox entry {}: {}",
+                                path.display(),
+                                err
+                            );
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to read webhook outbox {}: {}",
+                        self.config.path.display(),
+                        err
+                    );
+                    break;
+                }
+            }
+        }
+
+        outbox
+    }
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Outbox {
+            short_wait: Duration::from_millis(1),
+            long_wait: Duration::from_secs(86400 * 365),
+            last_id: 0,
+            scheduled: BinaryHeap::with_capacity(128),
+            entries: AHashMap::with_capacity(128),
+        }
+    }
+}
+
+pub fn sign(key: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::with_capacity(64), |mut s, b| {
+            use std::fmt::Write;
+            let _ = write!(s, "{b:02x}");
+            s
+        })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub trait SpawnWebhook {
+    fn spawn(self, core: impl Into<Reloadable<SMTP>>, outbox: Outbox);
+}
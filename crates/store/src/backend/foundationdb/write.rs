@@ -30,6 +30,7 @@ use foundationdb::{
 };
 use futures::StreamExt;
 use rand::Rng;
+use utils::metrics;
 
 use crate::{
     write::{
@@ -65,6 +66,9 @@ pub static ref BITMAPS: std::sync::Arc<parking_lot::Mutex<std::collections::Hash
 
 impl Store {
     pub async fn write(&self, batch: Batch) -> crate::Result<()> {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["write"])
+            .start_timer();
         let start = Instant::now();
         let mut retry_count = 0;
         let mut set_bitmaps = AHashMap::new();
@@ -22,6 +22,7 @@
 */
 
 use rusqlite::{params, OptionalExtension, TransactionBehavior};
+use utils::metrics;
 
 use crate::{
     write::{Batch, Operation, ValueClass},
@@ -87,6 +88,9 @@ const CLEAR_QUERIES: &[&str] = &[
 
 impl Store {
     pub async fn write(&self, batch: Batch) -> crate::Result<()> {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["write"])
+            .start_timer();
         let mut conn = self.conn_pool.get()?;
         self.spawn_worker(move || {
             let mut account_id = u32::MAX;
@@ -27,6 +27,7 @@ use tokio::{
     fs::{self, File},
     io::{AsyncReadExt, AsyncSeekExt},
 };
+use utils::metrics;
 
 use crate::{BlobKind, Store};
 
@@ -38,7 +39,10 @@ impl Store {
         kind: &BlobKind,
         range: Range<u32>,
     ) -> crate::Result<Option<Vec<u8>>> {
-        match &self.blob {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["get_blob"])
+            .start_timer();
+        let result = match &self.blob {
             BlobStore::Local(base_path) => {
                 let blob_path = get_local_path(base_path, kind);
                 let blob_size = match fs::metadata(&blob_path).await {
@@ -96,6 +100,14 @@ impl Store {
                     Err(err) => Err(err.into()),
                 }
             }
+        };
+
+        if let Ok(Some(blob)) = &result {
+            metrics::BLOB_BYTES
+                .with_label_values(&["read"])
+                .inc_by(blob.len() as u64);
         }
+
+        result
     }
 }
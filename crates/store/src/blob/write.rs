@@ -27,6 +27,7 @@ use tokio::{
     fs::{self, File},
     io::AsyncWriteExt,
 };
+use utils::metrics;
 
 use crate::{write::now, BlobKind, Store};
 
@@ -34,6 +35,13 @@ use super::{get_local_path, get_s3_path, BlobStore};
 
 impl Store {
     pub async fn put_blob(&self, kind: &BlobKind, data: &[u8]) -> crate::Result<()> {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["put_blob"])
+            .start_timer();
+        metrics::BLOB_BYTES
+            .with_label_values(&["write"])
+            .inc_by(data.len() as u64);
+
         match &self.blob {
             BlobStore::Local(base_path) => {
                 let blob_path = get_local_path(base_path, kind);
@@ -37,13 +37,17 @@ use utils::map::vec_map::VecMap;
 use crate::{
     query::RawValue,
     write::{BatchBuilder, IntoOperations, Operation, ValueClass},
-    Serialize, HASH_EXACT, HASH_STEMMED,
+    Serialize, HASH_EXACT, HASH_NGRAM, HASH_STEMMED,
 };
 
-use super::term_index::{TermIndexBuilder, TokenIndex};
+use super::{
+    fuzzy::ngrams,
+    term_index::{TermIndexBuilder, TokenIndex},
+};
 
 pub const MAX_TOKEN_LENGTH: usize = (u8::MAX >> 2) as usize;
 pub const MAX_TOKEN_MASK: usize = MAX_TOKEN_LENGTH - 1;
+pub const TERM_DICTIONARY_FIELD: u8 = u8::MAX - 1;
 
 struct Text<'x> {
     field: u8,
@@ -54,6 +58,7 @@ struct Text<'x> {
 pub struct FtsIndexBuilder<'x> {
     parts: Vec<Text<'x>>,
     tokens: VecMap<u8, AHashSet<String>>,
+    dictionary_fields: AHashSet<u8>,
     detect: LanguageDetector,
     default_language: Language,
 }
@@ -64,6 +69,7 @@ impl<'x> FtsIndexBuilder<'x> {
             parts: vec![],
             detect: LanguageDetector::new(),
             tokens: VecMap::new(),
+            dictionary_fields: AHashSet::new(),
             default_language,
         }
     }
@@ -86,7 +92,9 @@ impl<'x> FtsIndexBuilder<'x> {
     }
 
     pub fn index_raw(&mut self, field: impl Into<u8>, text: &str) {
-        let tokens = self.tokens.get_mut_or_insert(field.into());
+        let field = field.into();
+        self.dictionary_fields.insert(field);
+        let tokens = self.tokens.get_mut_or_insert(field);
         for token in SpaceTokenizer::new(text, MAX_TOKEN_LENGTH) {
             tokens.insert(token);
         }
@@ -118,6 +126,10 @@ impl<'x> IntoOperations for FtsIndexBuilder<'x> {
 
             for token in Stemmer::new(&part.text, language, MAX_TOKEN_LENGTH).collect::<Vec<_>>() {
                 ops.insert(Operation::hash(&token.word, HASH_EXACT, part.field, true));
+                ops.insert(Operation::term(&token.word, part.field, true));
+                for ngram in ngrams(&token.word) {
+                    ops.insert(Operation::hash(&ngram, HASH_NGRAM, part.field, true));
+                }
                 if let Some(stemmed_word) = &token.stemmed_word {
                     ops.insert(Operation::hash(
                         stemmed_word,
@@ -136,8 +148,12 @@ impl<'x> IntoOperations for FtsIndexBuilder<'x> {
 
         for (field, tokens) in self.tokens {
             let mut terms = Vec::with_capacity(tokens.len());
+            let add_term = self.dictionary_fields.contains(&field);
             for token in tokens {
                 ops.insert(Operation::hash(&token, HASH_EXACT, field, true));
+                if add_term {
+                    ops.insert(Operation::term(&token, field, true));
+                }
                 terms.push(term_index.add_token(Token {
                     word: token.into(),
                     from: 0,
@@ -174,6 +190,12 @@ impl TokenIndex {
                             term.field_id,
                             set,
                         ));
+                        if is_exact {
+                            ops.insert(Operation::term(word, term.field_id, set));
+                            for ngram in ngrams(word) {
+                                ops.insert(Operation::hash(&ngram, HASH_NGRAM, term.field_id, set));
+                            }
+                        }
                     }
                 }
             }
@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::io::Cursor;
+
+use zip::ZipArchive;
+
+use super::{extract_into, office::read_file, DocumentType, ExtractLimits, TextWriter};
+
+pub(crate) fn extract_zip(
+    bytes: &[u8],
+    writer: &mut TextWriter,
+    limits: &ExtractLimits,
+) -> Option<()> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).ok()?;
+
+    for index in 0..archive.len().min(limits.max_archive_entries) {
+        if writer.is_done() {
+            break;
+        }
+
+        let file = match archive.by_index(index) {
+            Ok(file) if !file.is_dir() => file,
+            _ => continue,
+        };
+
+        // Index the names of archived files along with their contents
+        let name = file.name().to_string();
+        writer.separator();
+        writer.push(name.rsplit('/').next().unwrap_or_default());
+        writer.separator();
+
+        // Nested archives are not unpacked
+        let document_type = match name
+            .rsplit_once('.')
+            .and_then(|(_, extension)| DocumentType::from_extension(extension))
+        {
+            Some(DocumentType::Zip) | None => continue,
+            Some(document_type) => document_type,
+        };
+        if let Some(contents) = read_file(file, limits) {
+            extract_into(document_type, &contents, writer, limits);
+        }
+    }
+
+    Some(())
+}
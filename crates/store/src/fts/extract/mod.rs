@@ -0,0 +1,368 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    panic,
+    time::{Duration, Instant},
+};
+
+use mail_parser::decoders::html::html_to_text;
+
+pub mod archive;
+pub mod office;
+pub mod rtf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentType {
+    Docx,
+    Xlsx,
+    Pptx,
+    OpenDocument,
+    Rtf,
+    Html,
+    Text,
+    Zip,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractLimits {
+    // Maximum size of an attachment and of each file unpacked from it
+    pub max_size: usize,
+    pub max_text_length: usize,
+    pub max_archive_entries: usize,
+    pub timeout: Duration,
+}
+
+pub(crate) struct TextWriter {
+    text: String,
+    max_length: usize,
+    deadline: Instant,
+}
+
+impl DocumentType {
+    pub fn detect(
+        content_type: Option<(&str, Option<&str>)>,
+        file_name: Option<&str>,
+    ) -> Option<Self> {
+        let from_mime = content_type
+            .and_then(|(ctype, subtype)| Self::from_mime_type(ctype, subtype.unwrap_or_default()));
+        from_mime.or_else(|| {
+            // Fall back to the file extension for generic content types
+            file_name
+                .and_then(|name| name.rsplit_once('.'))
+                .and_then(|(_, extension)| Self::from_extension(extension))
+        })
+    }
+
+    pub fn from_mime_type(ctype: &str, subtype: &str) -> Option<Self> {
+        let subtype = subtype.to_ascii_lowercase();
+        if ctype.eq_ignore_ascii_case("application") {
+            match subtype.as_str() {
+                "vnd.openxmlformats-officedocument.wordprocessingml.document"
+                | "vnd.openxmlformats-officedocument.wordprocessingml.template"
+                | "vnd.ms-word.document.macroenabled.12" => Some(DocumentType::Docx),
+                "vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                | "vnd.openxmlformats-officedocument.spreadsheetml.template"
+                | "vnd.ms-excel.sheet.macroenabled.12" => Some(DocumentType::Xlsx),
+                "vnd.openxmlformats-officedocument.presentationml.presentation"
+                | "vnd.openxmlformats-officedocument.presentationml.slideshow"
+                | "vnd.ms-powerpoint.presentation.macroenabled.12" => Some(DocumentType::Pptx),
+                "vnd.oasis.opendocument.text"
+                | "vnd.oasis.opendocument.spreadsheet"
+                | "vnd.oasis.opendocument.presentation" => Some(DocumentType::OpenDocument),
+                "rtf" | "x-rtf" => Some(DocumentType::Rtf),
+                "zip" | "x-zip" | "x-zip-compressed" => Some(DocumentType::Zip),
+                _ => None,
+            }
+        } else if ctype.eq_ignore_ascii_case("text") {
+            match subtype.as_str() {
+                "html" => Some(DocumentType::Html),
+                "rtf" | "richtext" => Some(DocumentType::Rtf),
+                "plain" | "csv" | "markdown" => Some(DocumentType::Text),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "docx" | "docm" | "dotx" => Some(DocumentType::Docx),
+            "xlsx" | "xlsm" | "xltx" => Some(DocumentType::Xlsx),
+            "pptx" | "pptm" | "ppsx" => Some(DocumentType::Pptx),
+            "odt" | "ods" | "odp" => Some(DocumentType::OpenDocument),
+            "rtf" => Some(DocumentType::Rtf),
+            "html" | "htm" => Some(DocumentType::Html),
+            "txt" | "csv" | "md" | "log" => Some(DocumentType::Text),
+            "zip" => Some(DocumentType::Zip),
+            _ => None,
+        }
+    }
+}
+
+pub fn extract_text(
+    document_type: DocumentType,
+    bytes: &[u8],
+    limits: &ExtractLimits,
+) -> Option<String> {
+    if bytes.len() > limits.max_size {
+        return None;
+    }
+
+    // Parsers for hostile documents may panic, treat these as unsupported
+    let text = panic::catch_unwind(|| {
+        let mut writer = TextWriter::new(limits);
+        extract_into(document_type, bytes, &mut writer, limits);
+        writer.text
+    })
+    .ok()?;
+
+    if !text.trim().is_empty() {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn extract_into(
+    document_type: DocumentType,
+    bytes: &[u8],
+    writer: &mut TextWriter,
+    limits: &ExtractLimits,
+) -> Option<()> {
+    match document_type {
+        DocumentType::Docx
+        | DocumentType::Xlsx
+        | DocumentType::Pptx
+        | DocumentType::OpenDocument => {
+            office::extract_office(document_type, bytes, writer, limits)
+        }
+        DocumentType::Rtf => rtf::extract_rtf(bytes, writer),
+        DocumentType::Html => {
+            writer.push(&html_to_text(&String::from_utf8_lossy(bytes)));
+            Some(())
+        }
+        DocumentType::Text => {
+            writer.push(&String::from_utf8_lossy(bytes));
+            Some(())
+        }
+        DocumentType::Zip => archive::extract_zip(bytes, writer, limits),
+    }
+}
+
+impl TextWriter {
+    pub fn new(limits: &ExtractLimits) -> Self {
+        TextWriter {
+            text: String::new(),
+            max_length: limits.max_text_length,
+            deadline: Instant::now() + limits.timeout,
+        }
+    }
+
+    pub fn push(&mut self, text: &str) {
+        let remaining = self.max_length.saturating_sub(self.text.len());
+        if text.len() <= remaining {
+            self.text.push_str(text);
+        } else {
+            let mut end = remaining;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.text.push_str(&text[..end]);
+        }
+    }
+
+    pub fn separator(&mut self) {
+        if self
+            .text
+            .chars()
+            .last()
+            .map_or(false, |ch| !ch.is_whitespace())
+        {
+            self.push("\n");
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.text.len() >= self.max_length || Instant::now() >= self.deadline
+    }
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        ExtractLimits {
+            max_size: 10_000_000,
+            max_text_length: 1_000_000,
+            max_archive_entries: 100,
+            timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    use super::{extract_text, DocumentType, ExtractLimits};
+
+    fn build_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            zip.start_file(
+                *name,
+                FileOptions::default().compression_method(CompressionMethod::Stored),
+            )
+            .unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn detect_document_type() {
+        for (content_type, file_name, expected) in [
+            (
+                Some((
+                    "application",
+                    Some("vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+                )),
+                None,
+                Some(DocumentType::Xlsx),
+            ),
+            (
+                Some(("application", Some("octet-stream"))),
+                Some("Report.DOCX"),
+                Some(DocumentType::Docx),
+            ),
+            (Some(("text", Some("rtf"))), None, Some(DocumentType::Rtf)),
+            (None, Some("notes.zip"), Some(DocumentType::Zip)),
+            (None, Some("image.png"), None),
+        ] {
+            assert_eq!(
+                DocumentType::detect(content_type, file_name),
+                expected,
+                "{content_type:?} {file_name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn extract_documents() {
+        let limits = ExtractLimits::default();
+
+        let docx = build_zip(&[(
+            "word/document.xml",
+            concat!(
+                "<w:document><w:body><w:p><w:r><w:t>Quarterly</w:t></w:r>",
+                "<w:r><w:t xml:space=\"preserve\"> report</w:t></w:r></w:p>",
+                "<w:p><w:r><w:t>Q&amp;A</w:t></w:r></w:p></w:body></w:document>"
+            )
+            .as_bytes(),
+        )]);
+        assert_eq!(
+            extract_text(DocumentType::Docx, &docx, &limits).unwrap(),
+            "Quarterly report\nQ&A\n"
+        );
+
+        let xlsx = build_zip(&[
+            (
+                "xl/worksheets/sheet1.xml",
+                concat!(
+                    "<worksheet><sheetData><row><c t=\"s\"><v>0</v></c><c><v>1234.5</v></c>",
+                    "<c t=\"inlineStr\"><is><t>inline</t></is></c></row></sheetData></worksheet>"
+                )
+                .as_bytes(),
+            ),
+            (
+                "xl/sharedStrings.xml",
+                b"<sst><si><t>Invoice</t></si><si><t>Total</t></si></sst>",
+            ),
+        ]);
+        let text = extract_text(DocumentType::Xlsx, &xlsx, &limits).unwrap();
+        for word in ["Invoice", "Total", "1234.5", "inline"] {
+            assert!(text.contains(word), "{word} not found in {text:?}");
+        }
+        assert!(!text.contains('0'), "{text:?}");
+
+        let odt = build_zip(&[(
+            "content.xml",
+            b"<office:text><text:h>Title</text:h><text:p>Some<text:s/>text</text:p></office:text>",
+        )]);
+        assert_eq!(
+            extract_text(DocumentType::OpenDocument, &odt, &limits).unwrap(),
+            "Title\nSome\ntext\n"
+        );
+
+        let rtf = concat!(
+            "{\\rtf1\\ansi{\\fonttbl{\\f0 Times;}}{\\*\\generator Writer;}",
+            "\\f0 Hello {\\b world}\\par Caf\\'e9 na\\u239?ve \\{ok\\}}"
+        );
+        assert_eq!(
+            extract_text(DocumentType::Rtf, rtf.as_bytes(), &limits).unwrap(),
+            "Hello world\nCafé naïve {ok}"
+        );
+
+        let zip = build_zip(&[
+            ("docs/readme.txt", b"Archived notes"),
+            (
+                "docs/page.html",
+                b"<html><body><p>Archived page</p></body></html>",
+            ),
+            ("docs/image.png", b"\x89PNG"),
+            ("docs/nested.zip", &docx),
+        ]);
+        let text = extract_text(DocumentType::Zip, &zip, &limits).unwrap();
+        for word in ["readme.txt", "Archived notes", "Archived page", "image.png"] {
+            assert!(text.contains(word), "{word} not found in {text:?}");
+        }
+        assert!(!text.contains("Quarterly"), "{text:?}");
+
+        // Limits
+        assert_eq!(
+            extract_text(
+                DocumentType::Text,
+                b"too large",
+                &ExtractLimits {
+                    max_size: 3,
+                    ..Default::default()
+                }
+            ),
+            None
+        );
+        assert_eq!(
+            extract_text(
+                DocumentType::Text,
+                "truncated ñ text".as_bytes(),
+                &ExtractLimits {
+                    max_text_length: 11,
+                    ..Default::default()
+                }
+            )
+            .unwrap(),
+            "truncated "
+        );
+    }
+}
@@ -0,0 +1,215 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::io::{Cursor, Read};
+
+use quick_xml::{events::Event, Reader};
+use zip::ZipArchive;
+
+use super::{DocumentType, ExtractLimits, TextWriter};
+
+// Elements after which a line break is added
+const OOXML_BREAKS: &[&[u8]] = &[b"p", b"br", b"cr", b"tab", b"si", b"row"];
+const ODF_BREAKS: &[&[u8]] = &[b"p", b"h", b"s", b"tab", b"line-break", b"table-cell"];
+
+pub(crate) fn extract_office(
+    document_type: DocumentType,
+    bytes: &[u8],
+    writer: &mut TextWriter,
+    limits: &ExtractLimits,
+) -> Option<()> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).ok()?;
+
+    // Obtain the parts containing text, in document order
+    let mut parts = archive
+        .file_names()
+        .filter(|name| match document_type {
+            DocumentType::Docx => {
+                *name == "word/document.xml"
+                    || *name == "word/footnotes.xml"
+                    || *name == "word/endnotes.xml"
+                    || is_numbered_part(name, "word/header")
+                    || is_numbered_part(name, "word/footer")
+            }
+            DocumentType::Xlsx => {
+                *name == "xl/sharedStrings.xml" || is_numbered_part(name, "xl/worksheets/sheet")
+            }
+            DocumentType::Pptx => {
+                is_numbered_part(name, "ppt/slides/slide")
+                    || is_numbered_part(name, "ppt/notesSlides/notesSlide")
+            }
+            _ => *name == "content.xml",
+        })
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    parts.sort_unstable_by(|a, b| (a.len(), a).cmp(&(b.len(), b)));
+    if parts.is_empty() {
+        return None;
+    }
+
+    for part in parts.into_iter().take(limits.max_archive_entries) {
+        if writer.is_done() {
+            break;
+        }
+        let xml = match read_entry(&mut archive, &part, limits) {
+            Some(xml) => xml,
+            None => continue,
+        };
+        match document_type {
+            DocumentType::Xlsx if part.starts_with("xl/worksheets/") => extract_sheet(&xml, writer),
+            DocumentType::OpenDocument => extract_xml(&xml, None, ODF_BREAKS, writer),
+            _ => extract_xml(&xml, Some(b"t".as_slice()), OOXML_BREAKS, writer),
+        }
+        writer.separator();
+    }
+
+    Some(())
+}
+
+pub(crate) fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    limits: &ExtractLimits,
+) -> Option<Vec<u8>> {
+    let file = archive.by_name(name).ok()?;
+    read_file(file, limits)
+}
+
+pub(crate) fn read_file(file: impl Read, limits: &ExtractLimits) -> Option<Vec<u8>> {
+    // The declared size cannot be trusted, limit the amount of data unpacked
+    let mut buf = Vec::new();
+    file.take(limits.max_size as u64 + 1)
+        .read_to_end(&mut buf)
+        .ok()?;
+    if buf.len() <= limits.max_size {
+        Some(buf)
+    } else {
+        None
+    }
+}
+
+fn is_numbered_part(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .and_then(|name| name.strip_suffix(".xml"))
+        .map_or(false, |num| num.bytes().all(|ch| ch.is_ascii_digit()))
+}
+
+// Extracts the text nodes of an XML document. When a text element is given,
+// only text inside elements with that local name is returned.
+fn extract_xml(
+    xml: &[u8],
+    text_element: Option<&[u8]>,
+    break_elements: &[&[u8]],
+    writer: &mut TextWriter,
+) {
+    let mut reader = Reader::from_reader(xml);
+    let mut text_depth = 0;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                if text_element == Some(element.local_name().as_ref()) {
+                    text_depth += 1;
+                }
+            }
+            Ok(Event::End(element)) => {
+                let name = element.local_name();
+                if text_element == Some(name.as_ref()) {
+                    text_depth -= 1;
+                } else if break_elements.contains(&name.as_ref()) {
+                    writer.separator();
+                }
+            }
+            Ok(Event::Empty(element)) => {
+                if break_elements.contains(&element.local_name().as_ref()) {
+                    writer.separator();
+                }
+            }
+            Ok(Event::Text(text)) if text_element.is_none() || text_depth > 0 => {
+                if let Ok(text) = text.unescape() {
+                    writer.push(&text);
+                }
+            }
+            Ok(Event::CData(text)) if text_element.is_none() || text_depth > 0 => {
+                writer.push(&String::from_utf8_lossy(&text));
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+
+        if writer.is_done() {
+            break;
+        }
+    }
+}
+
+// Extracts inline strings and values from a worksheet, shared strings are
+// obtained from the shared strings part.
+fn extract_sheet(xml: &[u8], writer: &mut TextWriter) {
+    let mut reader = Reader::from_reader(xml);
+    let mut is_shared = false;
+    let mut in_value = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => match element.local_name().as_ref() {
+                b"c" => {
+                    is_shared = matches!(
+                        element.try_get_attribute("t"),
+                        Ok(Some(attr)) if attr.value.as_ref() == b"s"
+                    );
+                }
+                b"v" => {
+                    in_value = !is_shared;
+                }
+                b"t" => {
+                    in_value = true;
+                }
+                _ => (),
+            },
+            Ok(Event::End(element)) => match element.local_name().as_ref() {
+                b"v" | b"t" => {
+                    if in_value {
+                        writer.push(" ");
+                    }
+                    in_value = false;
+                }
+                b"row" => {
+                    writer.separator();
+                }
+                _ => (),
+            },
+            Ok(Event::Text(text)) if in_value => {
+                if let Ok(text) = text.unescape() {
+                    writer.push(&text);
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+
+        if writer.is_done() {
+            break;
+        }
+    }
+}
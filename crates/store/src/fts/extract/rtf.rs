@@ -0,0 +1,201 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use super::TextWriter;
+
+// Destinations that do not contain document text
+const SKIP_DESTINATIONS: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "themedata",
+    "datastore",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "generator",
+    "xmlnstbl",
+    "latentstyles",
+    "fldinst",
+    "filetbl",
+    "revtbl",
+];
+const MAX_GROUP_DEPTH: usize = 1024;
+
+#[derive(Clone, Copy)]
+struct Group {
+    skip: bool,
+    unicode_skip: usize,
+}
+
+pub(crate) fn extract_rtf(bytes: &[u8], writer: &mut TextWriter) -> Option<()> {
+    if !bytes.starts_with(b"{\\rtf") {
+        return None;
+    }
+
+    let mut stack = Vec::new();
+    let mut group = Group {
+        skip: false,
+        unicode_skip: 1,
+    };
+    let mut pending_skip = 0;
+    let mut text = String::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let ch = bytes[pos];
+        pos += 1;
+
+        match ch {
+            b'{' => {
+                if stack.len() == MAX_GROUP_DEPTH {
+                    break;
+                }
+                stack.push(group);
+            }
+            b'}' => {
+                group = match stack.pop() {
+                    Some(group) => group,
+                    None => break,
+                };
+                pending_skip = 0;
+            }
+            b'\\' => {
+                let ch = *bytes.get(pos)?;
+                pos += 1;
+
+                match ch {
+                    b'\\' | b'{' | b'}' => {
+                        push_char(&mut text, ch as char, &group, &mut pending_skip);
+                    }
+                    b'\'' => {
+                        let hex = std::str::from_utf8(bytes.get(pos..pos + 2)?).ok()?;
+                        pos += 2;
+                        if let Ok(ch) = u8::from_str_radix(hex, 16) {
+                            push_char(&mut text, ch as char, &group, &mut pending_skip);
+                        }
+                    }
+                    b'*' => {
+                        group.skip = true;
+                    }
+                    b'~' => {
+                        push_char(&mut text, ' ', &group, &mut pending_skip);
+                    }
+                    b'_' => {
+                        push_char(&mut text, '-', &group, &mut pending_skip);
+                    }
+                    b'\r' | b'\n' => {
+                        push_char(&mut text, '\n', &group, &mut pending_skip);
+                    }
+                    b'a'..=b'z' | b'A'..=b'Z' => {
+                        // Parse control word and its optional numeric parameter
+                        let word_start = pos - 1;
+                        while bytes.get(pos).map_or(false, |ch| ch.is_ascii_alphabetic()) {
+                            pos += 1;
+                        }
+                        let word = std::str::from_utf8(&bytes[word_start..pos]).ok()?;
+                        let param_start = pos;
+                        if bytes.get(pos) == Some(&b'-') {
+                            pos += 1;
+                        }
+                        while bytes.get(pos).map_or(false, |ch| ch.is_ascii_digit()) {
+                            pos += 1;
+                        }
+                        let param = std::str::from_utf8(&bytes[param_start..pos])
+                            .ok()
+                            .and_then(|param| param.parse::<i64>().ok());
+                        if bytes.get(pos) == Some(&b' ') {
+                            pos += 1;
+                        }
+
+                        match word {
+                            "par" | "line" | "sect" | "page" | "row" | "cell" => {
+                                push_char(&mut text, '\n', &group, &mut 0);
+                            }
+                            "tab" => {
+                                push_char(&mut text, ' ', &group, &mut 0);
+                            }
+                            "emdash" | "endash" => {
+                                push_char(&mut text, '-', &group, &mut pending_skip);
+                            }
+                            "lquote" | "rquote" => {
+                                push_char(&mut text, '\'', &group, &mut pending_skip);
+                            }
+                            "ldblquote" | "rdblquote" => {
+                                push_char(&mut text, '"', &group, &mut pending_skip);
+                            }
+                            "u" => {
+                                if let Some(ch) = param
+                                    .map(|param| if param < 0 { param + 65536 } else { param })
+                                    .and_then(|param| char::from_u32(param as u32))
+                                {
+                                    push_char(&mut text, ch, &group, &mut 0);
+                                }
+                                pending_skip = group.unicode_skip;
+                            }
+                            "uc" => {
+                                group.unicode_skip = param.unwrap_or(1).clamp(0, 8) as usize;
+                            }
+                            "bin" => {
+                                pos = pos.saturating_add(param.unwrap_or(0).max(0) as usize);
+                            }
+                            _ if SKIP_DESTINATIONS.contains(&word) => {
+                                group.skip = true;
+                            }
+                            _ => (),
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            b'\r' | b'\n' => (),
+            _ => {
+                push_char(&mut text, ch as char, &group, &mut pending_skip);
+            }
+        }
+
+        if text.len() >= 4096 {
+            writer.push(&text);
+            text.clear();
+            if writer.is_done() {
+                return Some(());
+            }
+        }
+    }
+
+    writer.push(&text);
+
+    Some(())
+}
+
+fn push_char(text: &mut String, ch: char, group: &Group, pending_skip: &mut usize) {
+    if *pending_skip > 0 {
+        // Skip the fallback characters that follow an Unicode escape
+        *pending_skip -= 1;
+    } else if !group.skip {
+        text.push(ch);
+    }
+}
@@ -0,0 +1,288 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use ahash::AHashMap;
+use nlp::language::Language;
+use roaring::RoaringBitmap;
+
+use crate::{
+    fts::builder::MAX_TOKEN_LENGTH, BitmapKey, ReadTransaction, Store, ValueKey, HASH_NGRAM,
+};
+
+use super::term_index::TokenIndex;
+
+pub const NGRAM_LENGTH: usize = 3;
+
+// Returns the trigrams of a word, padded at both ends so that
+// the first and last characters carry the same weight as the rest.
+pub fn ngrams(word: &str) -> Vec<String> {
+    let chars = std::iter::once('^')
+        .chain(word.chars())
+        .chain(std::iter::once('$'))
+        .collect::<Vec<_>>();
+    if chars.len() > NGRAM_LENGTH {
+        let mut ngrams = chars
+            .windows(NGRAM_LENGTH)
+            .map(|ngram| ngram.iter().collect::<String>())
+            .collect::<Vec<_>>();
+        ngrams.sort_unstable();
+        ngrams.dedup();
+        ngrams
+    } else {
+        vec![chars.into_iter().collect()]
+    }
+}
+
+pub fn max_edit_distance(word_len: usize) -> usize {
+    match word_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+// Levenshtein distance between two words, or None if it exceeds max_distance.
+pub fn edit_distance(a: &str, b: &str, max_distance: usize) -> Option<usize> {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    if a.len().abs_diff(b.len()) > max_distance {
+        return None;
+    }
+
+    let mut prev_row = (0..=b.len()).collect::<Vec<_>>();
+    let mut row = vec![0; b.len() + 1];
+    for (i, ch_a) in a.iter().enumerate() {
+        row[0] = i + 1;
+        let mut row_min = row[0];
+        for (j, ch_b) in b.iter().enumerate() {
+            row[j + 1] = if ch_a == ch_b {
+                prev_row[j]
+            } else {
+                1 + prev_row[j].min(prev_row[j + 1]).min(row[j])
+            };
+            row_min = row_min.min(row[j + 1]);
+        }
+        if row_min > max_distance {
+            return None;
+        }
+        std::mem::swap(&mut prev_row, &mut row);
+    }
+
+    Some(prev_row[b.len()]).filter(|distance| *distance <= max_distance)
+}
+
+impl ReadTransaction<'_> {
+    #[maybe_async::maybe_async]
+    pub(crate) async fn fts_fuzzy_query(
+        &mut self,
+        account_id: u32,
+        collection: u8,
+        field: u8,
+        text: &str,
+        language: Language,
+    ) -> crate::Result<AHashMap<u32, u32>> {
+        let mut words = Vec::new();
+        for token in language.tokenize_text(text, MAX_TOKEN_LENGTH) {
+            let word = token.word.into_owned();
+            if !words.contains(&word) {
+                words.push(word);
+            }
+        }
+        if words.is_empty() {
+            return Ok(AHashMap::new());
+        }
+
+        // Obtain candidates sharing enough trigrams with each word, every edit
+        // operation can remove at most NGRAM_LENGTH trigrams from a word.
+        let mut candidates: Option<RoaringBitmap> = None;
+        for word in &words {
+            let ngrams = ngrams(word);
+            let min_matches = ngrams
+                .len()
+                .saturating_sub(NGRAM_LENGTH * max_edit_distance(word.chars().count()))
+                .max(1) as u32;
+            let mut matches: AHashMap<u32, u32> = AHashMap::new();
+
+            for ngram in ngrams {
+                self.refresh_if_old().await?;
+                if let Some(bitmap) = self
+                    .get_bitmap(BitmapKey::hash(
+                        &ngram, account_id, collection, HASH_NGRAM, field,
+                    ))
+                    .await?
+                {
+                    for document_id in bitmap {
+                        if candidates
+                            .as_ref()
+                            .map_or(true, |candidates| candidates.contains(document_id))
+                        {
+                            *matches.entry(document_id).or_insert(0) += 1;
+                        }
+                    }
+                }
+            }
+
+            let word_candidates = matches
+                .into_iter()
+                .filter_map(|(document_id, count)| {
+                    if count >= min_matches {
+                        Some(document_id)
+                    } else {
+                        None
+                    }
+                })
+                .collect::<RoaringBitmap>();
+            if word_candidates.is_empty() {
+                return Ok(AHashMap::new());
+            }
+            candidates = Some(word_candidates);
+        }
+
+        // Verify candidates against the document's tokens and score them
+        let mut results = AHashMap::new();
+        'outer: for document_id in candidates.unwrap_or_default() {
+            self.refresh_if_old().await?;
+            let token_index = if let Some(token_index) = self
+                .get_value::<TokenIndex>(ValueKey::term_index(account_id, collection, document_id))
+                .await?
+            {
+                token_index
+            } else {
+                tracing::debug!(
+                    event = "error",
+                    context = "fts_fuzzy_query",
+                    account_id = account_id,
+                    collection = collection,
+                    document_id = document_id,
+                    "Document is missing a term index",
+                );
+                continue;
+            };
+            let tokens = token_index
+                .terms
+                .iter()
+                .filter(|terms| terms.field_id == field)
+                .flat_map(|terms| terms.exact_terms.iter())
+                .filter_map(|term_id| token_index.tokens.get(*term_id as usize))
+                .collect::<Vec<_>>();
+
+            let mut score = 0;
+            for word in &words {
+                let word_len = word.chars().count();
+                let max_distance = max_edit_distance(word_len);
+                if let Some(distance) = tokens
+                    .iter()
+                    .filter_map(|token| edit_distance(word, token, max_distance))
+                    .min()
+                {
+                    score += 100 * (word_len - distance) / word_len;
+                } else {
+                    continue 'outer;
+                }
+            }
+
+            results.insert(document_id, ((score / words.len()) as u32).max(1));
+        }
+
+        Ok(results)
+    }
+}
+
+impl Store {
+    // Returns the relevancy score (1-100) of each document matching the text
+    // fuzzily in any of the fields, scaled by the weight (1-100) of the field.
+    pub async fn fuzzy_scores(
+        &self,
+        account_id: u32,
+        collection: impl Into<u8>,
+        fields: Vec<(u8, u32)>,
+        text: String,
+        language: Language,
+    ) -> crate::Result<AHashMap<u32, u32>> {
+        let collection = collection.into();
+
+        #[cfg(not(feature = "is_sync"))]
+        {
+            let mut trx = self.read_transaction().await?;
+            let mut scores = AHashMap::new();
+            for (field, weight) in fields {
+                for (document_id, score) in trx
+                    .fts_fuzzy_query(account_id, collection, field, &text, language)
+                    .await?
+                {
+                    let score = (score * weight / 100).max(1);
+                    let entry = scores.entry(document_id).or_insert(score);
+                    *entry = (*entry).max(score);
+                }
+            }
+            Ok(scores)
+        }
+
+        #[cfg(feature = "is_sync")]
+        {
+            let mut trx = self.read_transaction()?;
+            self.spawn_worker(move || {
+                let mut scores = AHashMap::new();
+                for (field, weight) in fields {
+                    for (document_id, score) in
+                        trx.fts_fuzzy_query(account_id, collection, field, &text, language)?
+                    {
+                        let score = (score * weight / 100).max(1);
+                        let entry = scores.entry(document_id).or_insert(score);
+                        *entry = (*entry).max(score);
+                    }
+                }
+                Ok(scores)
+            })
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, ngrams};
+
+    #[test]
+    fn fuzzy_ngrams() {
+        assert_eq!(ngrams("cat"), vec!["^ca", "at$", "cat"]);
+        assert_eq!(ngrams("a"), vec!["^a$"]);
+        assert_eq!(ngrams("aaaa"), vec!["^aa", "aa$", "aaa"]);
+    }
+
+    #[test]
+    fn fuzzy_edit_distance() {
+        for (a, b, max_distance, expected) in [
+            ("kitten", "sitting", 3, Some(3)),
+            ("kitten", "sitting", 2, None),
+            ("hello", "hello", 0, Some(0)),
+            ("hello", "helo", 1, Some(1)),
+            ("hello", "hallo", 1, Some(1)),
+            ("hello", "world", 2, None),
+            ("résumé", "resume", 2, Some(2)),
+            ("", "abc", 3, Some(3)),
+        ] {
+            assert_eq!(edit_distance(a, b, max_distance), expected, "{a} {b}");
+        }
+    }
+}
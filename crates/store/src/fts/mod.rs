@@ -26,11 +26,17 @@ use crate::{
     BitmapKey, Serialize, BM_HASH,
 };
 
-use self::{bloom::hash_token, builder::MAX_TOKEN_MASK};
+use self::{
+    bloom::hash_token,
+    builder::{MAX_TOKEN_MASK, TERM_DICTIONARY_FIELD},
+};
 
 pub mod bloom;
 pub mod builder;
+pub mod extract;
+pub mod fuzzy;
 pub mod query;
+pub mod queue;
 pub mod search_snippet;
 pub mod term_index;
 
@@ -72,4 +78,15 @@ impl Operation {
             set,
         }
     }
+
+    pub fn term(word: &str, field: u8, set: bool) -> Self {
+        let mut key = Vec::with_capacity(word.len() + 1);
+        key.push(field);
+        key.extend_from_slice(word.as_bytes());
+        Operation::Index {
+            field: TERM_DICTIONARY_FIELD,
+            key,
+            set,
+        }
+    }
 }
@@ -25,11 +25,15 @@ use nlp::language::{stemmer::Stemmer, Language};
 use roaring::RoaringBitmap;
 
 use crate::{
-    fts::builder::MAX_TOKEN_LENGTH, BitmapKey, ReadTransaction, ValueKey, HASH_EXACT, HASH_STEMMED,
+    fts::builder::{MAX_TOKEN_LENGTH, TERM_DICTIONARY_FIELD},
+    write::key::DeserializeBigEndian,
+    BitmapKey, IndexKey, ReadTransaction, ValueKey, HASH_EXACT, HASH_STEMMED,
 };
 
 use super::term_index::TermIndex;
 
+pub const MAX_PREFIX_EXPANSIONS: usize = 128;
+
 impl ReadTransaction<'_> {
     #[maybe_async::maybe_async]
     pub(crate) async fn fts_query(
@@ -152,4 +156,72 @@ impl ReadTransaction<'_> {
             Ok(Some(bitmaps))
         }
     }
+
+    #[maybe_async::maybe_async]
+    pub(crate) async fn fts_prefix_query(
+        &mut self,
+        account_id: u32,
+        collection: u8,
+        field: u8,
+        prefix: &str,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        let mut key = Vec::with_capacity(prefix.len() + 2);
+        key.push(field);
+        key.extend_from_slice(prefix.as_bytes());
+        let begin = IndexKey {
+            account_id,
+            collection,
+            document_id: 0,
+            field: TERM_DICTIONARY_FIELD,
+            key: key.clone(),
+        };
+        key.push(u8::MAX);
+        let end = IndexKey {
+            account_id,
+            collection,
+            document_id: u32::MAX,
+            field: TERM_DICTIONARY_FIELD,
+            key,
+        };
+
+        // Keys are sorted by term, stop once too many distinct terms were expanded
+        let term_offset = std::mem::size_of::<u32>() + 3;
+        let (_, _, results) = self
+            .iterate(
+                (Vec::new(), 0usize, RoaringBitmap::new()),
+                begin,
+                end,
+                false,
+                true,
+                move |(last_term, num_terms, results), key, _| {
+                    let id_pos = key.len().saturating_sub(std::mem::size_of::<u32>());
+                    let term = key.get(term_offset..id_pos).ok_or_else(|| {
+                        crate::Error::InternalError(
+                            "Invalid key found in term dictionary".to_string(),
+                        )
+                    })?;
+                    if term != last_term.as_slice() {
+                        if *num_terms == MAX_PREFIX_EXPANSIONS {
+                            tracing::debug!(
+                                event = "truncated",
+                                context = "fts_prefix_query",
+                                "Prefix query expanded to too many terms."
+                            );
+                            return Ok(false);
+                        }
+                        *num_terms += 1;
+                        *last_term = term.to_vec();
+                    }
+                    results.insert(key.deserialize_be_u32(id_pos)?);
+                    Ok(true)
+                },
+            )
+            .await?;
+
+        if !results.is_empty() {
+            Ok(Some(results))
+        } else {
+            Ok(None)
+        }
+    }
 }
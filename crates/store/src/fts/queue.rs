@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::{
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        now, Operation, ValueClass,
+    },
+    CustomValueKey, Deserialize, Serialize, Store,
+};
+
+// Custom keys start with u32::MAX followed by the key type, types 0 to 2 are
+// used by the account name mappings and URLAUTH keys.
+const FTS_QUEUE_KEY: u8 = 3;
+const KEY_LEN: usize = std::mem::size_of::<u32>() * 3 + std::mem::size_of::<u64>() + 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FtsQueueEntry {
+    pub due: u64,
+    pub account_id: u32,
+    pub collection: u8,
+    pub document_id: u32,
+    pub attempts: u32,
+}
+
+impl FtsQueueEntry {
+    pub fn new(account_id: u32, collection: impl Into<u8>, document_id: u32) -> Self {
+        FtsQueueEntry {
+            due: now(),
+            account_id,
+            collection: collection.into(),
+            document_id,
+            attempts: 0,
+        }
+    }
+
+    pub fn insert(&self) -> Operation {
+        Operation::Value {
+            class: ValueClass::Custom { bytes: self.key() },
+            set: self.attempts.serialize().into(),
+        }
+    }
+
+    pub fn remove(&self) -> Operation {
+        Operation::Value {
+            class: ValueClass::Custom { bytes: self.key() },
+            set: None,
+        }
+    }
+
+    // Returns the entry to retry after a failure, with an exponential backoff
+    pub fn retry(&self) -> Self {
+        FtsQueueEntry {
+            due: now() + (60 << self.attempts.min(10)),
+            attempts: self.attempts + 1,
+            ..*self
+        }
+    }
+
+    fn key(&self) -> Vec<u8> {
+        KeySerializer::new(KEY_LEN)
+            .write(u32::MAX)
+            .write(FTS_QUEUE_KEY)
+            .write(self.due)
+            .write(self.account_id)
+            .write(self.collection)
+            .write(self.document_id)
+            .finalize()
+    }
+
+    fn deserialize(key: &[u8], value: &[u8]) -> crate::Result<Self> {
+        if key.len() == KEY_LEN {
+            Ok(FtsQueueEntry {
+                due: key.deserialize_be_u64(5)?,
+                account_id: key.deserialize_be_u32(13)?,
+                collection: key[17],
+                document_id: key.deserialize_be_u32(18)?,
+                attempts: u32::deserialize(value)?,
+            })
+        } else {
+            Err(crate::Error::InternalError(
+                "Invalid full-text index queue key.".to_string(),
+            ))
+        }
+    }
+}
+
+impl Store {
+    pub async fn fts_queue_next(
+        &self,
+        max_due: u64,
+        limit: usize,
+    ) -> crate::Result<Vec<FtsQueueEntry>> {
+        let (from_key, to_key) = queue_range();
+        self.iterate(
+            Vec::new(),
+            from_key,
+            to_key,
+            false,
+            true,
+            move |entries, key, value| {
+                let entry = FtsQueueEntry::deserialize(key, value)?;
+                if entry.due <= max_due {
+                    entries.push(entry);
+                    Ok(entries.len() < limit)
+                } else {
+                    Ok(false)
+                }
+            },
+        )
+        .await
+    }
+
+    pub async fn fts_queue_size(&self) -> crate::Result<usize> {
+        let (from_key, to_key) = queue_range();
+        self.iterate(0, from_key, to_key, false, true, |size, _, _| {
+            *size += 1;
+            Ok(true)
+        })
+        .await
+    }
+}
+
+fn queue_range() -> (CustomValueKey, CustomValueKey) {
+    (
+        CustomValueKey {
+            value: KeySerializer::new(5)
+                .write(u32::MAX)
+                .write(FTS_QUEUE_KEY)
+                .finalize(),
+        },
+        CustomValueKey {
+            value: KeySerializer::new(KEY_LEN)
+                .write(u32::MAX)
+                .write(FTS_QUEUE_KEY)
+                .write(u64::MAX)
+                .write(u32::MAX)
+                .write(u8::MAX)
+                .write(u32::MAX)
+                .finalize(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FtsQueueEntry;
+
+    #[test]
+    fn fts_queue_key() {
+        let entry = FtsQueueEntry {
+            due: 1_700_000_000,
+            account_id: 7,
+            collection: 1,
+            document_id: 12345,
+            attempts: 2,
+        };
+        let key = entry.key();
+        assert_eq!(key.len(), super::KEY_LEN);
+        assert_eq!(
+            FtsQueueEntry::deserialize(&key, &2u32.to_be_bytes()).unwrap(),
+            entry
+        );
+
+        // Entries are sorted by due time
+        let retry = entry.retry();
+        assert_eq!(retry.attempts, 3);
+        assert!(retry.key() > key);
+    }
+}
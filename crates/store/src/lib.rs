@@ -29,6 +29,7 @@ pub mod backend;
 pub mod blob;
 pub mod fts;
 pub mod query;
+pub mod settings;
 pub mod write;
 
 pub use ahash;
@@ -220,6 +221,7 @@ pub const BM_HASH: u8 = 1 << 6;
 
 pub const HASH_EXACT: u8 = 0;
 pub const HASH_STEMMED: u8 = 1 << 6;
+pub const HASH_NGRAM: u8 = 1 << 7;
 
 pub const BLOOM_BIGRAM: u8 = 1 << 0;
 pub const BLOOM_TRIGRAM: u8 = 1 << 1;
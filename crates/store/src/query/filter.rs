@@ -78,6 +78,20 @@ impl ReadTransaction<'_> {
                         self.fts_query(account_id, collection, field, &text, language, false)
                             .await?
                     }
+                    TextMatch::Fuzzy(language) => {
+                        let results = self
+                            .fts_fuzzy_query(account_id, collection, field, &text, language)
+                            .await?;
+                        if !results.is_empty() {
+                            Some(results.into_keys().collect())
+                        } else {
+                            None
+                        }
+                    }
+                    TextMatch::Prefix => {
+                        self.fts_prefix_query(account_id, collection, field, &text)
+                            .await?
+                    }
                     TextMatch::Tokenized => {
                         self.get_bitmaps_intersection(
                             SpaceTokenizer::new(&text, MAX_TOKEN_LENGTH)
@@ -22,6 +22,7 @@
 */
 
 use roaring::RoaringBitmap;
+use utils::metrics;
 
 use crate::{BitmapKey, Deserialize, Key, Store};
 
@@ -30,6 +31,9 @@ impl Store {
     where
         U: Deserialize + 'static,
     {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["get_value"])
+            .start_timer();
         #[cfg(not(feature = "is_sync"))]
         {
             self.read_transaction().await?.get_value(key).await
@@ -46,6 +50,9 @@ impl Store {
     where
         U: Deserialize + 'static,
     {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["get_values"])
+            .start_timer();
         #[cfg(not(feature = "is_sync"))]
         {
             let mut trx = self.read_transaction().await?;
@@ -114,6 +121,9 @@ impl Store {
         &self,
         key: BitmapKey<T>,
     ) -> crate::Result<Option<RoaringBitmap>> {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["get_bitmap"])
+            .start_timer();
         #[cfg(not(feature = "is_sync"))]
         {
             self.read_transaction().await?.get_bitmap(key).await
@@ -135,6 +145,9 @@ impl Store {
         ascending: bool,
         cb: impl Fn(&mut T, &[u8], &[u8]) -> crate::Result<bool> + Sync + Send + 'static,
     ) -> crate::Result<T> {
+        let _timer = metrics::STORE_DURATION
+            .with_label_values(&["iterate"])
+            .start_timer();
         #[cfg(not(feature = "is_sync"))]
         {
             self.read_transaction()
@@ -26,6 +26,7 @@ pub mod get;
 pub mod log;
 pub mod sort;
 
+use ahash::AHashMap;
 use nlp::language::Language;
 use roaring::RoaringBitmap;
 
@@ -68,14 +69,26 @@ pub enum Filter {
 pub enum TextMatch {
     Exact(Language),
     Stemmed(Language),
+    Fuzzy(Language),
+    Prefix,
     Tokenized,
     Raw,
 }
 
 #[derive(Debug)]
 pub enum Comparator {
-    Field { field: u8, ascending: bool },
-    DocumentSet { set: RoaringBitmap, ascending: bool },
+    Field {
+        field: u8,
+        ascending: bool,
+    },
+    DocumentSet {
+        set: RoaringBitmap,
+        ascending: bool,
+    },
+    Scores {
+        scores: AHashMap<u32, u32>,
+        ascending: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -184,6 +197,26 @@ impl Filter {
         }
     }
 
+    pub fn has_fuzzy_text(
+        field: impl Into<u8>,
+        text: impl Into<String>,
+        language: Language,
+    ) -> Self {
+        Filter::HasText {
+            field: field.into(),
+            text: text.into(),
+            op: TextMatch::Fuzzy(language),
+        }
+    }
+
+    pub fn has_prefix_text(field: impl Into<u8>, prefix: impl Into<String>) -> Self {
+        Filter::HasText {
+            field: field.into(),
+            text: prefix.into(),
+            op: TextMatch::Prefix,
+        }
+    }
+
     pub fn has_raw_text(field: impl Into<u8>, text: impl Into<String>) -> Self {
         Filter::HasText {
             field: field.into(),
@@ -221,6 +254,10 @@ impl Comparator {
         Self::DocumentSet { set, ascending }
     }
 
+    pub fn scores(scores: AHashMap<u32, u32>, ascending: bool) -> Self {
+        Self::Scores { scores, ascending }
+    }
+
     pub fn ascending(field: impl Into<u8>) -> Self {
         Self::Field {
             field: field.into(),
@@ -91,6 +91,28 @@ impl ReadTransaction<'_> {
                         }
                     }
                 }
+                Comparator::Scores { scores, ascending } => {
+                    let mut results = result_set
+                        .results
+                        .into_iter()
+                        .map(|document_id| {
+                            (document_id, scores.get(&document_id).copied().unwrap_or(0))
+                        })
+                        .collect::<Vec<_>>();
+                    results.sort_by(|a, b| {
+                        let ordering = if ascending {
+                            a.1.cmp(&b.1)
+                        } else {
+                            b.1.cmp(&a.1)
+                        };
+                        ordering.then_with(|| a.0.cmp(&b.0))
+                    });
+                    for (document_id, _) in results {
+                        if !paginate.add(0, document_id) {
+                            break;
+                        }
+                    }
+                }
             }
 
             // Obtain prefixes
@@ -174,6 +196,13 @@ impl ReadTransaction<'_> {
                             }
                         }
                     }
+                    Comparator::Scores { scores, ascending } => {
+                        for document_id in &result_set.results {
+                            let score = scores.get(&document_id).copied().unwrap_or(0);
+                            sorted_ids.entry(document_id).or_insert([0u32; 4])[pos] =
+                                if ascending { score } else { u32::MAX - score };
+                        }
+                    }
                 }
             }
 
@@ -0,0 +1,121 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::{
+    write::{key::KeySerializer, BatchBuilder, Operation, ValueClass},
+    CustomValueKey, Serialize, Store,
+};
+
+// Custom keys start with u32::MAX followed by the key type, types 0 to 3 are
+// used by the account name mappings, URLAUTH and full-text index queue keys.
+const SETTINGS_KEY: u8 = 4;
+const SETTINGS_VERSION_KEY: u8 = 5;
+
+impl Store {
+    pub async fn settings_list(&self, prefix: &str) -> crate::Result<Vec<(String, String)>> {
+        let from_key = CustomValueKey {
+            value: settings_key(prefix),
+        };
+        let to_key = CustomValueKey {
+            value: KeySerializer::new(prefix.len() + 6)
+                .write(u32::MAX)
+                .write(SETTINGS_KEY)
+                .write(prefix)
+                .write(u8::MAX)
+                .finalize(),
+        };
+        self.iterate(
+            Vec::new(),
+            from_key,
+            to_key,
+            false,
+            true,
+            |settings, key, value| {
+                settings.push((
+                    String::from_utf8_lossy(key.get(5..).unwrap_or_default()).into_owned(),
+                    String::from_utf8_lossy(value).into_owned(),
+                ));
+                Ok(true)
+            },
+        )
+        .await
+    }
+
+    pub async fn settings_get(&self, key: &str) -> crate::Result<Option<String>> {
+        self.get_value::<String>(CustomValueKey {
+            value: settings_key(key),
+        })
+        .await
+    }
+
+    // Applies the changes (a value of None deletes the key) and returns the
+    // new settings version.
+    pub async fn settings_update(
+        &self,
+        changes: Vec<(String, Option<String>)>,
+    ) -> crate::Result<u64> {
+        let version = rand::random::<u64>();
+        let mut batch = BatchBuilder::new();
+        for (key, value) in changes {
+            batch.op(Operation::Value {
+                class: ValueClass::Custom {
+                    bytes: settings_key(&key),
+                },
+                set: value.map(|v| v.serialize()),
+            });
+        }
+        batch.op(Operation::Value {
+            class: ValueClass::Custom {
+                bytes: settings_version_key(),
+            },
+            set: version.serialize().into(),
+        });
+        self.write(batch.build()).await?;
+        Ok(version)
+    }
+
+    // The version changes on every update, which allows other nodes sharing
+    // the same store to detect that their settings are out of date.
+    pub async fn settings_version(&self) -> crate::Result<u64> {
+        self.get_value::<u64>(CustomValueKey {
+            value: settings_version_key(),
+        })
+        .await
+        .map(|v| v.unwrap_or_default())
+    }
+}
+
+fn settings_key(key: &str) -> Vec<u8> {
+    KeySerializer::new(key.len() + 5)
+        .write(u32::MAX)
+        .write(SETTINGS_KEY)
+        .write(key)
+        .finalize()
+}
+
+fn settings_version_key() -> Vec<u8> {
+    KeySerializer::new(5)
+        .write(u32::MAX)
+        .write(SETTINGS_VERSION_KEY)
+        .finalize()
+}
@@ -0,0 +1,182 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::io;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+const BUFFER_SIZE: usize = 1024;
+
+/// Raw DEFLATE stream compressor (no zlib header) that flushes
+/// at the end of every write, as required by streaming protocols.
+pub struct Deflater {
+    inner: Compress,
+}
+
+/// Raw DEFLATE stream decompressor.
+pub struct Inflater {
+    inner: Decompress,
+    max_size: usize,
+}
+
+impl Deflater {
+    pub fn new() -> Self {
+        Deflater {
+            inner: Compress::new(Compression::default(), false),
+        }
+    }
+
+    pub fn deflate(&mut self, mut bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(bytes.len() / 2 + BUFFER_SIZE);
+
+        loop {
+            let total_in = self.inner.total_in();
+            self.inner
+                .compress_vec(bytes, &mut output, FlushCompress::Sync)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            bytes = &bytes[(self.inner.total_in() - total_in) as usize..];
+
+            // The sync flush is complete once all input has been consumed
+            // and the compressor did not fill the output buffer.
+            if bytes.is_empty() && output.len() < output.capacity() {
+                return Ok(output);
+            }
+            output.reserve(BUFFER_SIZE);
+        }
+    }
+}
+
+impl Inflater {
+    pub fn new() -> Self {
+        Self::with_max_size(usize::MAX)
+    }
+
+    pub fn with_max_size(max_size: usize) -> Self {
+        Inflater {
+            inner: Decompress::new(false),
+            max_size,
+        }
+    }
+
+    /// Inflates the input, failing with `InvalidInput` when the output
+    /// exceeds the maximum size. The input is fully consumed in that case,
+    /// so the stream can still be used afterwards.
+    pub fn inflate(&mut self, mut bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(
+            std::cmp::min(bytes.len().saturating_mul(4), self.max_size).saturating_add(BUFFER_SIZE),
+        );
+        let mut is_too_large = false;
+
+        loop {
+            let total_in = self.inner.total_in();
+            let status = self
+                .inner
+                .decompress_vec(bytes, &mut output, FlushDecompress::None)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            bytes = &bytes[(self.inner.total_in() - total_in) as usize..];
+
+            if matches!(status, Status::StreamEnd)
+                || (bytes.is_empty() && output.len() < output.capacity())
+            {
+                return if !is_too_large && output.len() <= self.max_size {
+                    Ok(output)
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Inflated data exceeds {} bytes", self.max_size),
+                    ))
+                };
+            } else if output.len() >= self.max_size {
+                // Keep inflating to stay in sync with the stream, discarding
+                // the output.
+                is_too_large = true;
+                output.clear();
+            } else {
+                output.reserve(std::cmp::min(
+                    output.capacity(),
+                    (self.max_size - output.len()).saturating_add(BUFFER_SIZE),
+                ));
+            }
+        }
+    }
+}
+
+impl Default for Deflater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for Inflater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Deflater, Inflater};
+
+    #[test]
+    fn deflate_stream() {
+        let mut deflater = Deflater::new();
+        let mut inflater = Inflater::new();
+
+        for chunk in [
+            b"A001 OK COMPRESS completed.\r\n".to_vec(),
+            b"* 1 FETCH (FLAGS (\\Seen))\r\n".repeat(5000),
+            Vec::new(),
+            b"A002 OK NOOP completed.\r\n".to_vec(),
+        ] {
+            // Split the compressed data to simulate partial reads
+            let compressed = deflater.deflate(&chunk).unwrap();
+            let (first, second) = compressed.split_at(compressed.len() / 2);
+            let mut decompressed = inflater.inflate(first).unwrap();
+            decompressed.extend(inflater.inflate(second).unwrap());
+            assert_eq!(decompressed, chunk);
+        }
+    }
+
+    #[test]
+    fn inflate_max_size() {
+        let mut deflater = Deflater::new();
+        let mut inflater = Inflater::with_max_size(1024);
+
+        // Output larger than the limit is rejected
+        let compressed = deflater.deflate(&b"A".repeat(256 * 1024)).unwrap();
+        assert!(compressed.len() < 1024);
+        assert_eq!(
+            inflater.inflate(&compressed).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+
+        // The stream is still usable afterwards
+        let chunk = b"A001 NOOP\r\n".to_vec();
+        assert_eq!(
+            inflater
+                .inflate(&deflater.deflate(&chunk).unwrap())
+                .unwrap(),
+            chunk
+        );
+    }
+}
@@ -22,4 +22,5 @@
 */
 
 pub mod base32_custom;
+pub mod deflate;
 pub mod leb128;
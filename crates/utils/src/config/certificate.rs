@@ -25,12 +25,14 @@ use std::{io::Cursor, sync::Arc};
 
 use rustls::{
     server::{ClientHello, ResolvesServerCert, ResolvesServerCertUsingSni},
-    sign::CertifiedKey,
+    sign::{any_supported_type, CertifiedKey},
     version::{TLS12, TLS13},
     Certificate, PrivateKey, SupportedProtocolVersion,
 };
 use rustls_pemfile::{certs, read_one, Item};
 
+use crate::reload::Reloadable;
+
 use super::Config;
 
 pub static TLS13_VERSION: &[&SupportedProtocolVersion] = &[&TLS13];
@@ -50,7 +52,77 @@ impl ResolvesServerCert for CertificateResolver {
     }
 }
 
+impl ResolvesServerCert for Reloadable<CertificateResolver> {
+    fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.load().resolve(hello)
+    }
+}
+
 impl Config {
+    pub fn parse_certificates(&self, id: &str) -> super::Result<CertificateResolver> {
+        // Obtain default certificate
+        let cert_id = self
+            .value_or_default(
+                ("server.listener", id, "tls.certificate"),
+                "server.tls.certificate",
+            )
+            .ok_or_else(|| format!("Undefined certificate id for listener {id:?}."))?;
+        let cert = self.rustls_certificate(cert_id)?;
+        let pki = self.rustls_private_key(cert_id)?;
+
+        // Add SNI certificates
+        let mut resolver = ResolvesServerCertUsingSni::new();
+        let mut has_sni = false;
+        for (key, value) in
+            self.values_or_default(("server.listener", id, "tls.sni"), "server.tls.sni")
+        {
+            if let Some(prefix) = key.strip_suffix(".subject") {
+                has_sni = true;
+                resolver
+                    .add(
+                        value,
+                        match self.value((prefix, "certificate")) {
+                            Some(sni_cert_id) if sni_cert_id != cert_id => CertifiedKey {
+                                cert: self.rustls_certificate(sni_cert_id)?,
+                                key:
+                                    any_supported_type(&self.rustls_private_key(sni_cert_id)?)
+                                        .map_err(|err| {
+                                            format!(
+                                                "Failed to sign SNI certificate for {key:?}: {err}",
+                                            )
+                                        })?,
+                                ocsp: None,
+                                sct_list: None,
+                            },
+                            _ => CertifiedKey {
+                                cert: cert.clone(),
+                                key: any_supported_type(&pki).map_err(|err| {
+                                    format!("Failed to sign SNI certificate for {key:?}: {err}",)
+                                })?,
+                                ocsp: None,
+                                sct_list: None,
+                            },
+                        },
+                    )
+                    .map_err(|err| format!("Failed to add SNI certificate for {key:?}: {err}"))?;
+            }
+        }
+
+        // Add default certificate
+        let default_cert = Some(Arc::new(CertifiedKey {
+            cert,
+            key: any_supported_type(&pki)
+                .map_err(|err| format!("Failed to sign certificate id {cert_id:?}: {err}"))?,
+            ocsp: None,
+            sct_list: None,
+        }));
+
+        Ok(CertificateResolver {
+            resolver: if has_sni { resolver.into() } else { None },
+            default_cert,
+        })
+    }
+
     pub fn rustls_certificate(&self, cert_id: &str) -> super::Result<Vec<Certificate>> {
         let certs = certs(&mut Cursor::new(self.file_contents((
             "certificate",
@@ -30,16 +30,15 @@ use rustls::{
         TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256, TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
         TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384, TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256,
     },
-    server::{NoClientAuth, ResolvesServerCertUsingSni},
-    sign::{any_supported_type, CertifiedKey},
+    server::NoClientAuth,
     ServerConfig, SupportedCipherSuite, ALL_CIPHER_SUITES, ALL_KX_GROUPS, ALL_VERSIONS,
 };
 use tokio::net::TcpSocket;
 
-use crate::UnwrapFailure;
+use crate::{reload::Reloadable, UnwrapFailure};
 
 use super::{
-    certificate::{CertificateResolver, TLS12_VERSION, TLS13_VERSION},
+    certificate::{TLS12_VERSION, TLS13_VERSION},
     utils::{AsKey, ParseKey, ParseValue},
     Config, Listener, Server, ServerProtocol, Servers,
 };
@@ -66,7 +65,7 @@ impl Config {
 
     fn parse_server(&self, id: &str) -> super::Result<Server> {
         // Build TLS config
-        let (tls, tls_implicit) = if self
+        let (tls, tls_certificates, tls_implicit) = if self
             .property_or_default(("server.listener", id, "tls.enable"), "server.tls.enable")?
             .unwrap_or(false)
         {
@@ -96,66 +95,8 @@ impl Config {
                 ciphers.push(protocol.parse_key(key)?);
             }
 
-            // Obtain default certificate
-            let cert_id = self
-                .value_or_default(
-                    ("server.listener", id, "tls.certificate"),
-                    "server.tls.certificate",
-                )
-                .ok_or_else(|| format!("Undefined certificate id for listener {id:?}."))?;
-            let cert = self.rustls_certificate(cert_id)?;
-            let pki = self.rustls_private_key(cert_id)?;
-
-            // Add SNI certificates
-            let mut resolver = ResolvesServerCertUsingSni::new();
-            let mut has_sni = false;
-            for (key, value) in
-                self.values_or_default(("server.listener", id, "tls.sni"), "server.tls.sni")
-            {
-                if let Some(prefix) = key.strip_suffix(".subject") {
-                    has_sni = true;
-                    resolver
-                        .add(
-                            value,
-                            match self.value((prefix, "certificate")) {
-                                Some(sni_cert_id) if sni_cert_id != cert_id => CertifiedKey {
-                                    cert: self.rustls_certificate(sni_cert_id)?,
-                                    key: any_supported_type(&self.rustls_private_key(sni_cert_id)?)
-                                        .map_err(|err| {
-                                            format!(
-                                                "Failed to sign SNI certificate for {key:?}: {err}",
-                                            )
-                                        })?,
-                                    ocsp: None,
-                                    sct_list: None,
-                                },
-                                _ => CertifiedKey {
-                                    cert: cert.clone(),
-                                    key:
-                                        any_supported_type(&pki).map_err(|err| {
-                                            format!(
-                                                "Failed to sign SNI certificate for {key:?}: {err}",
-                                            )
-                                        })?,
-                                    ocsp: None,
-                                    sct_list: None,
-                                },
-                            },
-                        )
-                        .map_err(|err| {
-                            format!("Failed to add SNI certificate for {key:?}: {err}")
-                        })?;
-                }
-            }
-
-            // Add default certificate
-            let default_cert = Some(Arc::new(CertifiedKey {
-                cert,
-                key: any_supported_type(&pki)
-                    .map_err(|err| format!("Failed to sign certificate id {cert_id:?}: {err}"))?,
-                ocsp: None,
-                sct_list: None,
-            }));
+            // Parse certificates
+            let certificates = Reloadable::new(self.parse_certificates(id)?);
 
             // Build server config
             let mut config = ServerConfig::builder()
@@ -174,10 +115,7 @@ impl Config {
                 })
                 .map_err(|err| format!("Failed to build TLS config: {err}"))?
                 .with_client_cert_verifier(NoClientAuth::boxed())
-                .with_cert_resolver(Arc::new(CertificateResolver {
-                    resolver: if has_sni { resolver.into() } else { None },
-                    default_cert,
-                }));
+                .with_cert_resolver(Arc::new(certificates.clone()));
 
             //config.key_log = Arc::new(KeyLogger::default());
             config.ignore_client_order = self
@@ -188,6 +126,7 @@ impl Config {
                 .unwrap_or(true);
             (
                 config.into(),
+                certificates.into(),
                 self.property_or_default(
                     ("server.listener", id, "tls.implicit"),
                     "server.tls.implicit",
@@ -195,7 +134,7 @@ impl Config {
                 .unwrap_or(true),
             )
         } else {
-            (None, false)
+            (None, None, false)
         };
 
         // Build listeners
@@ -303,6 +242,7 @@ impl Config {
             protocol,
             listeners,
             tls,
+            tls_certificates,
             tls_implicit,
         })
     }
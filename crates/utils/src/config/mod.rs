@@ -40,9 +40,9 @@ use ahash::{AHashMap, AHashSet};
 use rustls::ServerConfig;
 use tokio::net::TcpSocket;
 
-use crate::{failed, UnwrapFailure};
+use crate::{failed, reload::Reloadable, UnwrapFailure};
 
-use self::utils::ParseValue;
+use self::{certificate::CertificateResolver, utils::ParseValue};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Config {
@@ -58,6 +58,7 @@ pub struct Server {
     pub protocol: ServerProtocol,
     pub listeners: Vec<Listener>,
     pub tls: Option<ServerConfig>,
+    pub tls_certificates: Option<Reloadable<CertificateResolver>>,
     pub tls_implicit: bool,
     pub max_connections: u64,
 }
@@ -144,20 +145,24 @@ pub type Result<T> = std::result::Result<T, String>;
 
 impl Config {
     pub fn init() -> Self {
-        let mut config_path = None;
+        Config::read_file(
+            &Config::config_path().failed("Missing parameter --config=<path-to-config>."),
+        )
+        .failed("Invalid configuration file")
+    }
+
+    pub fn config_path() -> Option<String> {
         let mut found_param = false;
 
         for arg in std::env::args().skip(1) {
             if let Some((key, value)) = arg.split_once('=') {
                 if key.starts_with("--config") {
-                    config_path = value.trim().to_string().into();
-                    break;
+                    return value.trim().to_string().into();
                 } else {
                     failed(&format!("Invalid command line argument: {key}"));
                 }
             } else if found_param {
-                config_path = arg.into();
-                break;
+                return arg.into();
             } else if arg.starts_with("--config") {
                 found_param = true;
             } else {
@@ -165,16 +170,16 @@ impl Config {
             }
         }
 
+        None
+    }
+
+    pub fn read_file(path: &str) -> Result<Self> {
         // Read main configuration file
         let mut config = Config::default();
-        config
-            .parse(
-                &std::fs::read_to_string(
-                    config_path.failed("Missing parameter --config=<path-to-config>."),
-                )
-                .failed("Could not read configuration file"),
-            )
-            .failed("Invalid configuration file");
+        config.parse(
+            &std::fs::read_to_string(path)
+                .map_err(|err| format!("Could not read configuration file {path:?}: {err}"))?,
+        )?;
 
         // Extract macros and includes
         let mut keys = BTreeMap::new();
@@ -194,29 +199,45 @@ impl Config {
         // Include files
         config.keys = keys;
         for mut include in includes {
-            include.replace_macros("include.files", &macros);
+            include.replace_macros("include.files", &macros)?;
             config
-                .parse(&std::fs::read_to_string(&include).failed(&format!(
-                    "Could not read included configuration file {include:?}"
-                )))
-                .failed(&format!("Invalid included configuration file {include:?}"));
+                .parse(&std::fs::read_to_string(&include).map_err(|err| {
+                    format!("Could not read included configuration file {include:?}: {err}")
+                })?)
+                .map_err(|err| format!("Invalid included configuration file {include:?}: {err}"))?;
         }
 
         // Replace macros
         for (key, value) in &mut config.keys {
-            value.replace_macros(key, &macros);
+            value.replace_macros(key, &macros)?;
         }
 
-        config
+        Ok(config)
+    }
+
+    // Settings kept in the database are layered over the local configuration,
+    // except for node specific keys and those needed to open the database.
+    pub fn apply_settings(&mut self, settings: impl IntoIterator<Item = (String, String)>) {
+        for (key, value) in settings {
+            if !Config::is_local_key(&key) {
+                self.keys.insert(key, value);
+            }
+        }
+    }
+
+    pub fn is_local_key(key: &str) -> bool {
+        LOCAL_KEYS.iter().any(|prefix| key.starts_with(prefix))
     }
 }
 
+const LOCAL_KEYS: &[&str] = &["server.", "certificate.", "store.", "global.", "settings."];
+
 trait ReplaceMacros: Sized {
-    fn replace_macros(&mut self, key: &str, macros: &AHashMap<String, String>);
+    fn replace_macros(&mut self, key: &str, macros: &AHashMap<String, String>) -> Result<()>;
 }
 
 impl ReplaceMacros for String {
-    fn replace_macros(&mut self, key: &str, macros: &AHashMap<String, String>) {
+    fn replace_macros(&mut self, key: &str, macros: &AHashMap<String, String>) -> Result<()> {
         if self.contains("%{") {
             let mut result = String::with_capacity(self.len());
             let mut value = self.as_str();
@@ -231,12 +252,10 @@ impl ReplaceMacros for String {
                             result.push_str(macro_value);
                             value = rest;
                         } else {
-                            failed(&format!("Unknown macro {macro_name:?} for key {key:?}"));
+                            return Err(format!("Unknown macro {macro_name:?} for key {key:?}"));
                         }
                     } else {
-                        failed(&format!(
-                            "Unterminated macro name {value:?} for key {key:?}"
-                        ));
+                        return Err(format!("Unterminated macro name {value:?} for key {key:?}"));
                     }
                 } else {
                     result.push_str(value);
@@ -246,5 +265,7 @@ impl ReplaceMacros for String {
 
             *self = result;
         }
+
+        Ok(())
     }
 }
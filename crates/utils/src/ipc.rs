@@ -31,9 +31,25 @@ pub enum DeliveryEvent {
         message: IngestMessage,
         result_tx: oneshot::Sender<Vec<DeliveryResult>>,
     },
+    FetchUrl {
+        url: String,
+        submitter: String,
+        result_tx: oneshot::Sender<Option<Vec<u8>>>,
+    },
+    VerifySender {
+        address: String,
+        submitter: String,
+        result_tx: oneshot::Sender<Option<SendPermission>>,
+    },
     Stop,
 }
 
+#[derive(Debug)]
+pub struct ReloadEvent {
+    pub settings: Vec<(String, Option<String>)>,
+    pub result_tx: Option<oneshot::Sender<Result<(), String>>>,
+}
+
 #[derive(Debug)]
 pub struct IngestMessage {
     pub sender_address: String,
@@ -54,6 +70,14 @@ pub enum DeliveryResult {
     },
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendPermission {
+    Owner,
+    SendAs,
+    SendOnBehalf { sender: String },
+    Denied,
+}
+
 impl IngestMessage {
     pub async fn read_message(&self) -> Result<Vec<u8>, ()> {
         let mut raw_message = vec![0u8; self.message_size];
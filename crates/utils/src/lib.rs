@@ -24,12 +24,16 @@
 use std::collections::HashMap;
 
 use config::Config;
+use ipc::ReloadEvent;
+use tokio::sync::mpsc;
 
 pub mod codec;
 pub mod config;
 pub mod ipc;
 pub mod listener;
 pub mod map;
+pub mod metrics;
+pub mod reload;
 pub mod suffixlist;
 
 use opentelemetry::{
@@ -227,3 +231,33 @@ pub async fn wait_for_shutdown(message: &str) {
 
     tracing::info!(message);
 }
+
+pub fn spawn_reload_handler(reload_tx: mpsc::Sender<ReloadEvent>) {
+    #[cfg(not(target_env = "msvc"))]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut h_hup = signal(SignalKind::hangup()).failed("start signal handler");
+
+        tokio::spawn(async move {
+            while h_hup.recv().await.is_some() {
+                tracing::debug!("Received SIGHUP.");
+                if reload_tx
+                    .send(ReloadEvent {
+                        settings: Vec::new(),
+                        result_tx: None,
+                    })
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+
+    #[cfg(target_env = "msvc")]
+    {
+        let _ = reload_tx;
+    }
+}
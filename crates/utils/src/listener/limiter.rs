@@ -29,6 +29,8 @@ use std::{
     time::{Duration, Instant},
 };
 
+use prometheus::IntGauge;
+
 #[derive(Debug)]
 pub struct RateLimiter {
     pub max_requests: u64,
@@ -46,11 +48,15 @@ pub struct ConcurrencyLimiter {
 #[derive(Default)]
 pub struct InFlight {
     concurrent: Arc<AtomicU64>,
+    active: Option<IntGauge>,
 }
 
 impl Drop for InFlight {
     fn drop(&mut self) {
         self.concurrent.fetch_sub(1, Ordering::Relaxed);
+        if let Some(active) = &self.active {
+            active.dec();
+        }
     }
 }
 
@@ -100,6 +106,11 @@ impl RateLimiter {
         self.tokens = self.max_requests;
     }
 
+    pub fn set_max_requests(&mut self, max_requests: u64) {
+        self.max_requests = max_requests;
+        self.tokens = std::cmp::min(self.tokens, max_requests);
+    }
+
     pub fn is_active(&self) -> bool {
         self.tokens < self.max_requests || self.last_refill.elapsed() < self.max_interval
     }
@@ -119,6 +130,7 @@ impl ConcurrencyLimiter {
             self.concurrent.fetch_add(1, Ordering::Relaxed);
             Some(InFlight {
                 concurrent: self.concurrent.clone(),
+                active: None,
             })
         } else {
             None
@@ -138,4 +150,11 @@ impl InFlight {
     pub fn num_concurrent(&self) -> u64 {
         self.concurrent.load(Ordering::Relaxed)
     }
+
+    // Keeps the gauge incremented for as long as the request is in flight
+    pub fn with_gauge(mut self, gauge: IntGauge) -> Self {
+        gauge.inc();
+        self.active = Some(gauge);
+        self
+    }
 }
@@ -34,7 +34,7 @@ use crate::{
     config::{Config, Listener, Server, ServerProtocol, Servers},
     failed,
     listener::SessionData,
-    UnwrapFailure,
+    metrics, UnwrapFailure,
 };
 
 use super::{limiter::ConcurrencyLimiter, ServerInstance, SessionManager};
@@ -82,6 +82,7 @@ impl Server {
             let mut shutdown_rx = instance.shutdown_rx.clone();
             let manager = manager.clone();
             let instance = instance.clone();
+            let protocol = instance.protocol.to_string();
             tokio::spawn(async move {
                 loop {
                     tokio::select! {
@@ -101,6 +102,10 @@ impl Server {
 
                                     // Enforce concurrency
                                     if let Some(in_flight) = instance.limiter.is_allowed() {
+                                        metrics::CONNECTIONS.with_label_values(&[&protocol]).inc();
+                                        let in_flight = in_flight.with_gauge(
+                                            metrics::CONNECTIONS_ACTIVE.with_label_values(&[&protocol]),
+                                        );
                                         let span = tracing::info_span!(
                                             "session",
                                             instance = instance.id,
@@ -150,6 +155,7 @@ impl Server {
                                             instance: instance.clone(),
                                         });
                                     } else {
+                                        metrics::CONNECTIONS_REJECTED.with_label_values(&[&protocol]).inc();
                                         tracing::info!(
                                             context = "throttle",
                                             event = "too-many-requests",
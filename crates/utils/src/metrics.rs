@@ -0,0 +1,335 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{net::IpAddr, time::Instant};
+
+use prometheus::{
+    exponential_buckets, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge_vec, Encoder, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec,
+    TextEncoder,
+};
+
+pub use prometheus::HistogramTimer;
+
+lazy_static::lazy_static! {
+    pub static ref CONNECTIONS: IntCounterVec = register_int_counter_vec!(
+        "connections_total",
+        "Number of connections accepted",
+        &["protocol"]
+    )
+    .unwrap();
+    pub static ref CONNECTIONS_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "connections_rejected_total",
+        "Number of connections rejected because of the concurrency limit",
+        &["protocol"]
+    )
+    .unwrap();
+    pub static ref CONNECTIONS_ACTIVE: IntGaugeVec = register_int_gauge_vec!(
+        "connections_active",
+        "Number of active connections",
+        &["protocol"]
+    )
+    .unwrap();
+    pub static ref AUTHENTICATIONS: IntCounterVec = register_int_counter_vec!(
+        "authentications_total",
+        "Number of authentication attempts",
+        &["protocol", "result"]
+    )
+    .unwrap();
+    pub static ref SMTP_ACCEPTED: IntCounter = register_int_counter!(
+        "smtp_messages_accepted_total",
+        "Number of messages accepted over SMTP or LMTP"
+    )
+    .unwrap();
+    pub static ref SMTP_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "smtp_rejected_total",
+        "Number of SMTP commands or messages rejected",
+        &["reason"]
+    )
+    .unwrap();
+    pub static ref SMTP_AUTH_RESULTS: IntCounterVec = register_int_counter_vec!(
+        "smtp_auth_results_total",
+        "Results of SPF, DKIM, ARC, DMARC and reverse IP verifications",
+        &["method", "result"]
+    )
+    .unwrap();
+    pub static ref QUEUE_MESSAGES: IntGaugeVec = register_int_gauge_vec!(
+        "queue_messages",
+        "Number of messages pending delivery",
+        &["domain"]
+    )
+    .unwrap();
+    pub static ref QUEUE_AGE: IntGaugeVec = register_int_gauge_vec!(
+        "queue_oldest_message_age_seconds",
+        "Age of the oldest message pending delivery",
+        &["domain"]
+    )
+    .unwrap();
+    pub static ref DELIVERY_DURATION: HistogramVec = register_histogram_vec!(
+        "delivery_duration_seconds",
+        "Time taken to deliver a message to a remote domain",
+        &["result"],
+        exponential_buckets(0.1, 2.0, 12).unwrap()
+    )
+    .unwrap();
+    pub static ref STORE_DURATION: HistogramVec = register_histogram_vec!(
+        "store_operation_duration_seconds",
+        "Time taken to complete a store operation",
+        &["operation"],
+        exponential_buckets(0.0001, 4.0, 10).unwrap()
+    )
+    .unwrap();
+    pub static ref BLOB_BYTES: IntCounterVec = register_int_counter_vec!(
+        "blob_bytes_total",
+        "Number of bytes read from and written to the blob store",
+        &["operation"]
+    )
+    .unwrap();
+    pub static ref IMAP_COMMAND_DURATION: HistogramVec = register_histogram_vec!(
+        "imap_command_duration_seconds",
+        "Time taken to execute an IMAP command",
+        &["command"]
+    )
+    .unwrap();
+    pub static ref JMAP_METHOD_DURATION: HistogramVec = register_histogram_vec!(
+        "jmap_method_duration_seconds",
+        "Time taken to execute a JMAP method call",
+        &["method"]
+    )
+    .unwrap();
+}
+
+pub fn authentication(protocol: &str, is_success: bool) {
+    AUTHENTICATIONS
+        .with_label_values(&[protocol, if is_success { "success" } else { "failure" }])
+        .inc();
+}
+
+pub fn smtp_rejected(reason: &str) {
+    SMTP_REJECTED.with_label_values(&[reason]).inc();
+}
+
+pub fn smtp_auth_result(method: &str, result: &str) {
+    SMTP_AUTH_RESULTS.with_label_values(&[method, result]).inc();
+}
+
+pub const CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+/// Label used for the queue gauges of the domains beyond the reporting limit.
+pub const OTHER_DOMAINS: &str = "other";
+
+/// Publishes the number of queued messages and the age of the oldest one per
+/// destination domain. Only the `max_domains` domains with the most messages get
+/// their own label, the remaining ones are aggregated under [`OTHER_DOMAINS`].
+pub fn set_queue_status<'x>(
+    domains: impl IntoIterator<Item = (&'x str, i64, u64)>,
+    now: u64,
+    max_domains: usize,
+) {
+    let mut domains = domains.into_iter().collect::<Vec<_>>();
+    domains.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    QUEUE_MESSAGES.reset();
+    QUEUE_AGE.reset();
+    let mut other: Option<(i64, u64)> = None;
+    for (pos, (domain, count, oldest)) in domains.into_iter().enumerate() {
+        if pos < max_domains {
+            QUEUE_MESSAGES.with_label_values(&[domain]).set(count);
+            QUEUE_AGE
+                .with_label_values(&[domain])
+                .set(now.saturating_sub(oldest) as i64);
+        } else {
+            let other = other.get_or_insert((0, oldest));
+            other.0 += count;
+            other.1 = std::cmp::min(other.1, oldest);
+        }
+    }
+    if let Some((count, oldest)) = other {
+        QUEUE_MESSAGES
+            .with_label_values(&[OTHER_DOMAINS])
+            .set(count);
+        QUEUE_AGE
+            .with_label_values(&[OTHER_DOMAINS])
+            .set(now.saturating_sub(oldest) as i64);
+    }
+}
+
+/// Decides whether a scraper may read the metrics. When credentials are configured
+/// the request has to present them (`basic_auth` is the decoded `user:secret` pair),
+/// otherwise metrics are only served to local scrapers unless they are public.
+pub fn is_scrape_authorized(
+    credentials: Option<(&str, &str)>,
+    is_public: bool,
+    remote_ip: IpAddr,
+    basic_auth: Option<&str>,
+) -> bool {
+    if let Some((username, secret)) = credentials {
+        basic_auth
+            .and_then(|token| token.split_once(':'))
+            .map_or(false, |(u, s)| {
+                // Evaluate both comparisons so the response time does not reveal
+                // which one failed
+                constant_time_eq(u.as_bytes(), username.as_bytes())
+                    & constant_time_eq(s.as_bytes(), secret.as_bytes())
+            })
+    } else {
+        is_public
+            || match remote_ip {
+                IpAddr::V6(ip) => ip
+                    .to_ipv4_mapped()
+                    .map_or_else(|| ip.is_loopback(), |ip| ip.is_loopback()),
+                IpAddr::V4(ip) => ip.is_loopback(),
+            }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = a.len() ^ b.len();
+    for pos in 0..std::cmp::max(a.len(), b.len()) {
+        diff |= (a.get(pos).copied().unwrap_or(0) ^ b.get(pos).copied().unwrap_or(0)) as usize;
+    }
+    diff == 0
+}
+
+pub trait ObserveDuration {
+    fn observe_since(&self, labels: &[&str], start: Instant);
+}
+
+impl ObserveDuration for HistogramVec {
+    fn observe_since(&self, labels: &[&str], start: Instant) {
+        self.with_label_values(labels)
+            .observe(start.elapsed().as_secs_f64());
+    }
+}
+
+/// Encodes all registered metrics in the Prometheus text exposition format.
+pub fn encode() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(4096);
+    if let Err(err) = TextEncoder::new().encode(&prometheus::gather(), &mut buf) {
+        tracing::warn!(
+            context = "metrics",
+            event = "error",
+            "Failed to encode metrics: {}",
+            err
+        );
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use prometheus::core::Collector;
+
+    use super::{is_scrape_authorized, set_queue_status, OTHER_DOMAINS, QUEUE_AGE, QUEUE_MESSAGES};
+
+    #[test]
+    fn scrape_authorization() {
+        let local: IpAddr = "127.0.0.1".parse().unwrap();
+        let local_v6: IpAddr = "::1".parse().unwrap();
+        let local_mapped: IpAddr = "::ffff:127.0.0.1".parse().unwrap();
+        let remote: IpAddr = "192.0.2.1".parse().unwrap();
+        let credentials = Some(("prometheus", "secret"));
+
+        // Without credentials only local scrapers are allowed unless public
+        for (ip, expected) in [
+            (local, true),
+            (local_v6, true),
+            (local_mapped, true),
+            (remote, false),
+        ] {
+            assert_eq!(
+                is_scrape_authorized(None, false, ip, None),
+                expected,
+                "{ip}"
+            );
+            assert!(is_scrape_authorized(None, true, ip, None), "{ip}");
+        }
+
+        // Credentials are required from any address once configured
+        for ip in [local, remote] {
+            for (basic_auth, expected) in [
+                (Some("prometheus:secret"), true),
+                (Some("prometheus:secreT"), false),
+                (Some("prometheus:secret1"), false),
+                (Some("prometheus:"), false),
+                (Some("prometheu:secret"), false),
+                (Some("prometheus"), false),
+                (Some(""), false),
+                (None, false),
+            ] {
+                assert_eq!(
+                    is_scrape_authorized(credentials, true, ip, basic_auth),
+                    expected,
+                    "{ip} {basic_auth:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn queue_status_cardinality() {
+        set_queue_status(
+            [
+                ("a.org", 1, 900),
+                ("b.org", 10, 500),
+                ("c.org", 5, 400),
+                ("d.org", 2, 100),
+                ("e.org", 1, 800),
+            ],
+            1000,
+            2,
+        );
+        let mut labels = QUEUE_MESSAGES
+            .collect()
+            .into_iter()
+            .flat_map(|family| family.get_metric().to_vec())
+            .map(|metric| {
+                (
+                    metric.get_label()[0].get_value().to_string(),
+                    metric.get_gauge().get_value() as i64,
+                )
+            })
+            .collect::<Vec<_>>();
+        labels.sort_unstable();
+        assert_eq!(
+            labels,
+            vec![
+                ("b.org".to_string(), 10),
+                ("c.org".to_string(), 5),
+                (OTHER_DOMAINS.to_string(), 4),
+            ]
+        );
+        assert_eq!(QUEUE_AGE.with_label_values(&["b.org"]).get(), 500);
+        assert_eq!(QUEUE_AGE.with_label_values(&[OTHER_DOMAINS]).get(), 900);
+
+        // Domains below the limit are reported individually
+        set_queue_status([("a.org", 3, 990)], 1000, 2);
+        assert_eq!(QUEUE_MESSAGES.with_label_values(&["a.org"]).get(), 3);
+        assert_eq!(QUEUE_AGE.with_label_values(&["a.org"]).get(), 10);
+        assert!(!String::from_utf8(super::encode())
+            .unwrap()
+            .contains("domain=\"other\""));
+    }
+}
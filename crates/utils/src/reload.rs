@@ -0,0 +1,73 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::{Arc, RwLock};
+
+/// Shared handle to a value that can be replaced at runtime. Callers obtain a
+/// snapshot with `load`, which remains valid until dropped even if a newer
+/// value is stored in the meantime.
+pub struct Reloadable<T> {
+    inner: Arc<RwLock<Arc<T>>>,
+}
+
+impl<T> Reloadable<T> {
+    pub fn new(value: impl Into<Arc<T>>) -> Self {
+        Reloadable {
+            inner: Arc::new(RwLock::new(value.into())),
+        }
+    }
+
+    pub fn load(&self) -> Arc<T> {
+        self.inner
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    pub fn store(&self, value: impl Into<Arc<T>>) -> Arc<T> {
+        std::mem::replace(
+            &mut *self.inner.write().unwrap_or_else(|err| err.into_inner()),
+            value.into(),
+        )
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Reloadable {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> From<Arc<T>> for Reloadable<T> {
+    fn from(value: Arc<T>) -> Self {
+        Reloadable::new(value)
+    }
+}
+
+impl<T> std::fmt::Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reloadable").finish_non_exhaustive()
+    }
+}
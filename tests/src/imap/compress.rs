@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use utils::codec::deflate::{Deflater, Inflater};
+
+pub async fn test() {
+    let mut stream = TcpStream::connect("127.0.0.1:9991").await.unwrap();
+    let mut buf = vec![0u8; 8192];
+
+    // Authenticate and enable compression
+    read_until(&mut stream, &mut buf, None, "* OK").await;
+    stream
+        .write_all(b"C1 AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0\r\n")
+        .await
+        .unwrap();
+    read_until(&mut stream, &mut buf, None, "C1 OK").await;
+    stream.write_all(b"C2 COMPRESS DEFLATE\r\n").await.unwrap();
+    read_until(&mut stream, &mut buf, None, "C2 OK").await;
+
+    // All further traffic is compressed in both directions
+    let mut deflater = Deflater::new();
+    let mut inflater = Inflater::new();
+    for (command, expected) in [
+        ("C3 NOOP\r\n", "C3 OK"),
+        ("C4 COMPRESS DEFLATE\r\n", "C4 NO [COMPRESSIONACTIVE]"),
+        ("C5 COMPRESS GZIP\r\n", "C5 BAD"),
+        ("C6 SELECT INBOX\r\n", "C6 OK"),
+        ("C7 UID FETCH 1:* (FLAGS)\r\n", "C7 OK"),
+        ("C8 CREATE Compressed\r\n", "C8 OK"),
+    ] {
+        stream
+            .write_all(&deflater.deflate(command.as_bytes()).unwrap())
+            .await
+            .unwrap();
+        read_until(&mut stream, &mut buf, Some(&mut inflater), expected).await;
+    }
+
+    // Literals are split across several compressed writes
+    let message = "From: test@domain.com\r\nSubject: Compressed\r\n\r\nTest message\r\n";
+    for chunk in [
+        format!("C9 APPEND Compressed {{{}+}}\r\n", message.len()),
+        message.to_string(),
+        "\r\n".to_string(),
+    ] {
+        stream
+            .write_all(&deflater.deflate(chunk.as_bytes()).unwrap())
+            .await
+            .unwrap();
+    }
+    read_until(&mut stream, &mut buf, Some(&mut inflater), "C9 OK").await;
+
+    stream
+        .write_all(&deflater.deflate(b"C10 DELETE Compressed\r\n").unwrap())
+        .await
+        .unwrap();
+    read_until(&mut stream, &mut buf, Some(&mut inflater), "C10 OK").await;
+
+    stream
+        .write_all(&deflater.deflate(b"C11 LOGOUT\r\n").unwrap())
+        .await
+        .unwrap();
+    read_until(&mut stream, &mut buf, Some(&mut inflater), "* BYE").await;
+}
+
+async fn read_until(
+    stream: &mut TcpStream,
+    buf: &mut [u8],
+    mut inflater: Option<&mut Inflater>,
+    expected: &str,
+) {
+    let mut response = String::new();
+    loop {
+        let bytes_read = tokio::time::timeout(Duration::from_millis(1500), stream.read(buf))
+            .await
+            .unwrap_or_else(|_| panic!("Timeout waiting for {expected:?}, got {response:?}"))
+            .unwrap();
+        assert_ne!(bytes_read, 0, "Connection closed, got {response:?}");
+        let bytes = if let Some(inflater) = &mut inflater {
+            inflater.inflate(&buf[..bytes_read]).unwrap()
+        } else {
+            buf[..bytes_read].to_vec()
+        };
+        response.push_str(std::str::from_utf8(&bytes).unwrap());
+        if response.lines().any(|line| line.starts_with(expected)) {
+            return;
+        }
+    }
+}
@@ -25,19 +25,25 @@ pub mod acl;
 pub mod append;
 pub mod basic;
 pub mod body_structure;
+pub mod compress;
 pub mod condstore;
 pub mod copy_move;
 pub mod fetch;
 pub mod idle;
 pub mod mailbox;
 pub mod managesieve;
+pub mod notify;
+pub mod replace;
 pub mod search;
+pub mod shared_seen;
 pub mod store;
 pub mod thread;
+pub mod urlauth;
 
 use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use ::managesieve::core::ManageSieveSessionManager;
+use ::store::Store;
 use directory::config::ConfigDirectory;
 use imap::core::{ImapSessionManager, IMAP};
 use imap_proto::ResponseType;
@@ -80,6 +86,11 @@ protocol = "managesieve"
 max-connections = 81920
 tls.implicit = true
 
+[server.listener.submission]
+bind = ['127.0.0.1:11587']
+protocol = 'smtp'
+tls.implicit = false
+
 [server.listener.lmtp-debug]
 bind = ['127.0.0.1:11201']
 greeting = 'Test LMTP instance'
@@ -106,6 +117,13 @@ directory = "sql"
 total = 5
 wait = "1ms"
 
+[session.auth]
+mechanisms = [ { if = "listener", eq = "submission", then = ["plain"] },
+               { else = [] } ]
+directory = [ { if = "listener", eq = "submission", then = "sql" },
+              { else = false } ]
+allow-plain-text = true
+
 [queue]
 path = "{TMP}"
 hash = 64
@@ -134,6 +152,7 @@ allow-invalid-certs = true
 [session.extensions]
 future-release = [ { if = "authenticated-as", ne = "", then = "99999999d"},
                    { else = false } ]
+burl = true
 
 [store]
 db.path = "{TMP}/sqlite.db"
@@ -151,6 +170,9 @@ private-key = "file://{PK}"
 [jmap]
 directory = "sql"
 
+[jmap.fts.index]
+background = false
+
 [jmap.protocol]
 set.max-objects = 100000
 
@@ -245,12 +267,22 @@ async fn init_imap_tests(delete_if_exists: bool) -> IMAPTest {
     // Start JMAP and SMTP servers
     servers.bind(&config);
     let (delivery_tx, delivery_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
-    let smtp = SMTP::init(&config, &servers, &directory, delivery_tx)
+    let store = Arc::new(Store::open(&config).await.failed("Unable to open database"));
+    let smtp = SMTP::init(&config, &servers, &directory, store.clone(), delivery_tx)
         .await
-        .failed("Invalid configuration file");
-    let jmap = JMAP::init(&config, &directory, delivery_rx, smtp.clone())
-        .await
-        .failed("Invalid configuration file");
+        .failed("Invalid configuration file")
+        .load();
+    let jmap = JMAP::init(
+        &config,
+        &directory,
+        store,
+        delivery_rx,
+        mpsc::channel(1).0,
+        smtp.clone(),
+    )
+    .await
+    .failed("Invalid configuration file")
+    .load();
     let imap: Arc<IMAP> = IMAP::init(&config)
         .await
         .failed("Invalid configuration file");
@@ -299,18 +331,22 @@ async fn init_imap_tests(delete_if_exists: bool) -> IMAPTest {
         "Bill Foobar",
     )
     .await;
-    create_test_group_with_email(
+    create_test_user_with_email(
         jmap.directory.as_ref(),
-        "support@example.com",
-        "Support Group",
+        "mike@example.com",
+        "secret",
+        "Mike Foobar",
     )
     .await;
-    add_to_group(
+    create_test_group_with_email(
         jmap.directory.as_ref(),
-        "jane.smith@example.com",
         "support@example.com",
+        "Support Group",
     )
     .await;
+    for member in ["jane.smith@example.com", "mike@example.com"] {
+        add_to_group(jmap.directory.as_ref(), member, "support@example.com").await;
+    }
 
     if delete_if_exists {
         jmap.store.destroy().await;
@@ -371,8 +407,13 @@ pub async fn imap_tests() {
     copy_move::test(&mut imap, &mut imap_check).await;
     thread::test(&mut imap, &mut imap_check).await;
     idle::test(&mut imap, &mut imap_check).await;
+    notify::test(&mut imap, &mut imap_check).await;
+    compress::test().await;
+    urlauth::test(&mut imap, &mut imap_check).await;
+    replace::test(&mut imap, &mut imap_check).await;
     condstore::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    shared_seen::test().await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
@@ -0,0 +1,173 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, _imap_check: &mut ImapConnection) {
+    // Create test mailboxes
+    for mailbox in ["Replace", "Replace Sent"] {
+        imap.send(&format!("CREATE \"{mailbox}\"")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+
+    // Add test messages
+    for num in 1..=5 {
+        let message = draft(num);
+        imap.send(&format!(
+            "APPEND Replace (\\Draft) {{{}+}}\r\n{}",
+            message.len(),
+            message
+        ))
+        .await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+    imap.send("SELECT Replace").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Save dates are returned and searchable
+    imap.send("UID FETCH 1 (SAVEDATE)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("SAVEDATE \"");
+    imap.send("UID SEARCH RETURN (COUNT) SAVEDSINCE 1-Jan-2000")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COUNT 5");
+    imap.send("UID SEARCH RETURN (COUNT) SAVEDBEFORE 1-Jan-2000")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COUNT 0");
+    imap.send("UID SEARCH RETURN (COUNT) SAVEDATESUPPORTED")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COUNT 5");
+
+    // Paged search results
+    for (range, expected) in [
+        ("1:2", "PARTIAL (1:2 1:2)"),
+        ("-1:-2", "PARTIAL (-1:-2 4:5)"),
+        ("3:100", "PARTIAL (3:100 3:5)"),
+        ("10:20", "PARTIAL (10:20 NIL)"),
+    ] {
+        imap.send(&format!("UID SEARCH RETURN (PARTIAL {range}) ALL"))
+            .await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok)
+            .await
+            .assert_contains(expected);
+    }
+
+    // Paged fetch results
+    imap.send("UID FETCH 1:* (FLAGS) (PARTIAL -1:-2)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("FETCH (", 2)
+        .assert_contains("UID 4")
+        .assert_contains("UID 5");
+    imap.send("FETCH 1:* (FLAGS) (PARTIAL 1:1)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("FETCH (", 1)
+        .assert_contains("* 1 FETCH");
+
+    // Replace a message in the same mailbox
+    let message = draft(6);
+    imap.send(&format!(
+        "UID REPLACE 3 Replace (\\Draft \\Seen) {{{}+}}\r\n{}",
+        message.len(),
+        message
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("[APPENDUID ")
+        .assert_contains("* 3 EXPUNGE");
+    imap.send("UID SEARCH RETURN (ALL) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("ALL 1:2,4:6");
+    imap.send("UID FETCH 6 (FLAGS BODY.PEEK[HEADER.FIELDS (SUBJECT)])")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Draft 6")
+        .assert_contains("\\Seen");
+
+    // Replace a message into a different mailbox
+    let message = draft(7);
+    imap.send(&format!(
+        "REPLACE 1 \"Replace Sent\" {{{}+}}\r\n{}",
+        message.len(),
+        message
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXPUNGE");
+    imap.send("STATUS \"Replace Sent\" (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MESSAGES 1");
+    imap.send("UID SEARCH RETURN (COUNT) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COUNT 3");
+
+    // Replacing a message that does not exist should fail
+    imap.send(&format!(
+        "UID REPLACE 99 Replace {{{}+}}\r\n{}",
+        message.len(),
+        message
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+    imap.send("STATUS Replace (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MESSAGES 3");
+
+    // Delete test mailboxes
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for mailbox in ["Replace", "Replace Sent"] {
+        imap.send(&format!("DELETE \"{mailbox}\"")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+}
+
+fn draft(num: u32) -> String {
+    format!(
+        concat!(
+            "From: test@domain.com\r\n",
+            "Subject: Draft {}\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Draft number {}.\r\n"
+        ),
+        num, num
+    )
+}
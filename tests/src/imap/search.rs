@@ -23,7 +23,7 @@
 
 use imap_proto::ResponseType;
 
-use super::{AssertResult, ImapConnection, Type};
+use super::{append::assert_append_message, AssertResult, ImapConnection, Type};
 
 pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
     // Searches without selecting a mailbox should fail.
@@ -91,6 +91,62 @@ pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
         .await
         .assert_equals("* SEARCH 1 2");
 
+    // Fuzzy search
+    imap_check.send("UID SEARCH SUBJECT argentinna").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH");
+    imap_check.send("UID SEARCH SUBJECT argentina").await;
+    let exact_results = imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .next()
+        .unwrap();
+    imap_check.send("UID SEARCH FUZZY SUBJECT argentinna").await;
+    let fuzzy_results = imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .next()
+        .unwrap();
+    assert_eq!(exact_results, fuzzy_results);
+    imap_check
+        .send("UID SEARCH RETURN (COUNT RELEVANCY) FUZZY (SUBJECT argentinna)")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("RELEVANCY (");
+
+    // Prefix and field queries
+    imap_check.send("UID SEARCH TEXT \"subject:argent*\"").await;
+    let prefix_results = imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .next()
+        .unwrap();
+    assert_eq!(exact_results, prefix_results);
+    imap_check
+        .send("UID SEARCH TEXT \"subject:argentina -subject:argentina\"")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH");
+    imap_check
+        .send("UID SEARCH TEXT \"subject:argentinna OR subject:argentina\"")
+        .await;
+    let or_results = imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .next()
+        .unwrap();
+    assert_eq!(exact_results, or_results);
+
     // Saved search
     imap_check.send(
         "UID SEARCH RETURN (SAVE ALL) OR OR FROM nathaniel FROM vandelay OR SUBJECT rfc FROM gore",
@@ -135,4 +191,25 @@ pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
     imap.assert_read(Type::Tagged, ResponseType::Ok)
         .await
         .assert_contains("COUNT 10 ALL 6,4:5,1,10,9,3,7:8,2");
+
+    // Subject matches should rank above body matches
+    imap.send("CREATE Relevancy").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for message in [
+        "Subject: Travel plans\r\n\r\nOur flights to Argentina are booked.\r\n",
+        "Subject: Argentina travel plans\r\n\r\nOur flights are booked.\r\n",
+    ] {
+        assert_append_message(imap, "Relevancy", message, ResponseType::Ok).await;
+    }
+    imap.send("SELECT Relevancy").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("UID SORT (RELEVANCY) UTF-8 FUZZY TEXT argentinna")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SORT 2 1");
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Relevancy").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
 }
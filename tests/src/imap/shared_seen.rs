@@ -0,0 +1,150 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use imap_proto::ResponseType;
+
+use crate::jmap::delivery::SmtpConnection;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test() {
+    // Delivery to support account
+    let mut lmtp = SmtpConnection::connect_port(11201).await;
+    lmtp.ingest(
+        "bill@example.com",
+        &["support@example.com"],
+        concat!(
+            "From: bill@example.com\r\n",
+            "To: support@example.com\r\n",
+            "Subject: Shared seen\r\n",
+            "\r\n",
+            "Each member keeps their own seen state."
+        ),
+    )
+    .await;
+
+    // Connect with two members of the support group
+    let mut imap_jane = ImapConnection::connect(b"_j ").await;
+    let mut imap_mike = ImapConnection::connect(b"_m ").await;
+    for (imap, secret) in [
+        (&mut imap_jane, "AGphbmUuc21pdGhAZXhhbXBsZS5jb20Ac2VjcmV0"),
+        (&mut imap_mike, "AG1pa2VAZXhhbXBsZS5jb20Ac2VjcmV0"),
+    ] {
+        imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+        imap.send(&format!(
+            "AUTHENTICATE PLAIN {{{}+}}\r\n{}",
+            secret.len(),
+            secret
+        ))
+        .await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+        imap.send("SELECT \"Shared Folders/support@example.com/Inbox\"")
+            .await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok)
+            .await
+            .assert_contains("* 2 EXISTS");
+    }
+
+    // STORE sets \Seen only for the member that issued it
+    imap_jane.send("STORE 1:* +FLAGS (\\Seen)").await;
+    imap_jane
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("\\Seen", 2);
+    imap_jane.send("FETCH 1:* (FLAGS)").await;
+    imap_jane
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("\\Seen", 2);
+    imap_mike.send("NOOP").await;
+    imap_mike.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_mike.send("FETCH 1:* (FLAGS)").await;
+    imap_mike
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("\\Seen", 0);
+    imap_mike.send("SEARCH UNSEEN").await;
+    imap_mike
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH 1 2");
+    imap_jane.send("SEARCH UNSEEN").await;
+    imap_jane
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH");
+
+    // FETCH of the message body sets \Seen only for the member that issued it
+    imap_mike.send("FETCH 2 (BODY[])").await;
+    imap_mike
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\\Seen");
+    imap_jane.send("STORE 1:* -FLAGS (\\Seen)").await;
+    imap_jane
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("\\Seen", 0);
+    imap_mike.send("NOOP").await;
+    imap_mike.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_mike.send("FETCH 1:* (FLAGS)").await;
+    imap_mike
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("\\Seen", 1)
+        .assert_contains("* 2 FETCH (FLAGS (\\Seen))");
+
+    // APPEND with \Seen sets it only for the member that issued it
+    let message = "From: jane.smith@example.com\r\nSubject: Appended\r\n\r\nSeen by Jane.\r\n";
+    imap_jane
+        .send(&format!(
+            "APPEND \"Shared Folders/support@example.com/Inbox\" (\\Seen) {{{}}}",
+            message.len()
+        ))
+        .await;
+    imap_jane
+        .assert_read(Type::Continuation, ResponseType::Ok)
+        .await;
+    imap_jane.send_untagged(message).await;
+    imap_jane.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for imap in [&mut imap_jane, &mut imap_mike] {
+        imap.send("NOOP").await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+    imap_jane.send("FETCH 3 (FLAGS)").await;
+    imap_jane
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 3 FETCH (FLAGS (\\Seen))");
+    imap_mike.send("FETCH 3 (FLAGS)").await;
+    imap_mike
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("\\Seen", 0);
+
+    // Logout
+    for imap in [&mut imap_jane, &mut imap_mike] {
+        imap.send("LOGOUT").await;
+        imap.assert_read(Type::Untagged, ResponseType::Bye).await;
+    }
+}
@@ -0,0 +1,194 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use imap_proto::ResponseType;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf},
+    net::TcpStream,
+};
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    // Add a test message
+    imap.send("CREATE Lemonade").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let message = concat!(
+        "From: test@domain.com\r\n",
+        "Subject: Lemonade\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Forward without download.\r\n"
+    );
+    imap.send(&format!("APPEND Lemonade {{{}}}", message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // URLs can only be generated by the mailbox owner
+    imap.send(
+        "GENURLAUTH \"imap://jane.smith%40example.com@localhost/Lemonade/;UID=1;URLAUTH=authuser\" INTERNAL",
+    )
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Bad).await;
+    imap.send(
+        "GENURLAUTH \"imap://jdoe%40example.com@localhost/Lemonade/;UID=99;URLAUTH=authuser\" INTERNAL",
+    )
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("BADURL");
+
+    // Generate URLAUTH
+    let url = generate_url(imap, "authuser").await;
+    assert!(url.contains(";URLAUTH=authuser:INTERNAL:"), "{url}");
+
+    // Fetch URL from another session
+    imap_check.send(&format!("URLFETCH \"{url}\"")).await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Forward without download.");
+
+    // Tampered URLs should not resolve
+    imap_check
+        .send(&format!("URLFETCH \"{}\"", url.replace("UID=1;", "UID=2;")))
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains(" NIL");
+
+    // Submit the message by reference using BURL
+    let submit_url = generate_url(imap, "submit+jdoe%40example.com").await;
+    let (reader, mut writer) =
+        tokio::io::split(TcpStream::connect("127.0.0.1:11587").await.unwrap());
+    let mut reader = BufReader::new(reader).lines();
+    smtp_read(&mut reader, "220").await;
+    for (command, code) in [
+        ("EHLO localhost".to_string(), "250"),
+        (
+            "AUTH PLAIN AGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0".to_string(),
+            "235",
+        ),
+        ("MAIL FROM:<jdoe@example.com>".to_string(), "250"),
+        ("RCPT TO:<jdoe@example.com>".to_string(), "250"),
+        (
+            format!("BURL {} LAST", submit_url.replace("UID=1;", "UID=2;")),
+            "554",
+        ),
+        (format!("BURL {submit_url} LAST"), "250"),
+        ("QUIT".to_string(), "221"),
+    ] {
+        writer
+            .write_all(format!("{command}\r\n").as_bytes())
+            .await
+            .unwrap();
+        smtp_read(&mut reader, code).await;
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SEARCH RETURN (SAVE) SUBJECT Lemonade").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("FETCH $ BODY[TEXT]").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Forward without download.");
+    imap.send("STORE $ +FLAGS.SILENT (\\Deleted)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("EXPUNGE").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Build a new message using CATENATE
+    let header = "From: test@domain.com\r\nSubject: Catenated\r\n\r\n";
+    imap.send(&format!(
+        "APPEND Lemonade CATENATE (TEXT {{{}+}}\r\n{} URL \"/Lemonade/;UID=1/;SECTION=TEXT\")",
+        header.len(),
+        header
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SELECT Lemonade").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("UID FETCH 2 BODY[]").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Subject: Catenated")
+        .assert_contains("Forward without download.");
+
+    // Unresolvable URLs should be rejected
+    imap.send("APPEND Lemonade CATENATE (URL \"/Lemonade/;UID=99\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("BADURL");
+
+    // Resetting the mailbox key invalidates existing URLs
+    imap.send("RESETKEY Lemonade").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send(&format!("URLFETCH \"{url}\"")).await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains(" NIL");
+
+    // Delete test mailbox
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Lemonade").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
+
+async fn generate_url(imap: &mut ImapConnection, access: &str) -> String {
+    imap.send(&format!(
+        "GENURLAUTH \"imap://jdoe%40example.com@localhost/Lemonade/;UID=1;URLAUTH={access}\" INTERNAL"
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .find_map(|line| {
+            line.strip_prefix("* GENURLAUTH \"")
+                .and_then(|line| line.strip_suffix('"'))
+                .map(|url| url.to_string())
+        })
+        .expect("Missing GENURLAUTH response")
+}
+
+async fn smtp_read(reader: &mut Lines<BufReader<ReadHalf<TcpStream>>>, code: &str) {
+    loop {
+        let line = tokio::time::timeout(Duration::from_millis(1500), reader.next_line())
+            .await
+            .expect("Timeout while waiting for SMTP response")
+            .unwrap()
+            .expect("Connection closed");
+        assert!(line.starts_with(code), "Expected {code}, got {line:?}");
+        if line.as_bytes().get(3) == Some(&b' ') {
+            break;
+        }
+    }
+}
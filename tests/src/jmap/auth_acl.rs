@@ -755,6 +755,35 @@ pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
             .unwrap(),
         "Created by john in sales"
     );
+
+    // Members of the group have their own \Seen state
+    john_client
+        .set_default_account_id(&sales_id.to_string())
+        .email_set_keyword(&email_id, "$seen", true)
+        .await
+        .unwrap();
+    for (client, is_seen, unread) in [(&mut john_client, true, 0), (&mut jane_client, false, 1)] {
+        assert_eq!(
+            client
+                .set_default_account_id(&sales_id.to_string())
+                .email_get(&email_id, [Property::Keywords].into())
+                .await
+                .unwrap()
+                .unwrap()
+                .keywords()
+                .contains(&"$seen"),
+            is_seen
+        );
+        assert_eq!(
+            client
+                .mailbox_get(&inbox_id, [mailbox::Property::UnreadEmails].into())
+                .await
+                .unwrap()
+                .unwrap()
+                .unread_emails(),
+            unread
+        );
+    }
     assert_forbidden(
         bill_client
             .set_default_account_id(&sales_id.to_string())
@@ -25,8 +25,9 @@ use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use ahash::AHashMap;
 use jmap::{
-    email::crypto::{
-        try_parse_certs, Algorithm, EncryptMessage, EncryptionMethod, EncryptionParams,
+    email::{
+        crypto::{try_parse_certs, Algorithm, EncryptMessage, EncryptionMethod, EncryptionParams},
+        smime::{SmimeStatus, VerifySmime},
     },
     JMAP,
 };
@@ -273,6 +274,83 @@ pub fn check_is_encrypted() {
     }
 }
 
+#[test]
+pub fn check_smime_verify() {
+    let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join("crypto");
+    let (_, trust_store) =
+        try_parse_certs(std::fs::read(resources.join("smime_ca.pem")).unwrap()).unwrap();
+    let now = store::write::now() as i64;
+
+    for (file_name, expected_status, num_errors) in [
+        ("smime_signed.eml", SmimeStatus::SignedVerified, 0),
+        ("smime_opaque.eml", SmimeStatus::SignedVerified, 0),
+        ("smime_tampered.eml", SmimeStatus::SignedFailed, 1),
+        ("smime_sender.eml", SmimeStatus::SignedFailed, 1),
+    ] {
+        let raw_message = std::fs::read(resources.join(file_name)).unwrap();
+        let message = MessageParser::new().parse(&raw_message).unwrap();
+
+        // Verify against the test trust store
+        let result = message.verify_smime(&trust_store, now).unwrap();
+        assert_eq!(result.status, expected_status, "failed for {file_name}");
+        assert_eq!(
+            result.errors.len(),
+            num_errors,
+            "failed for {file_name}: {result:?}"
+        );
+        assert_eq!(result.signatures.len(), 1, "failed for {file_name}");
+        let signature = &result.signatures[0];
+        assert_eq!(signature.email.as_deref(), Some("alice@example.org"));
+        assert_eq!(signature.name.as_deref(), Some("Alice Doe"));
+        assert_eq!(
+            signature.issuer.as_deref(),
+            Some("Stalwart Test Intermediate CA")
+        );
+        assert_eq!(signature.is_verified, num_errors == 0);
+
+        // Signers are not trusted without a trust anchor
+        let result = message.verify_smime(&[], now).unwrap();
+        assert_eq!(
+            result.status,
+            SmimeStatus::SignedFailed,
+            "failed for {file_name}"
+        );
+        assert!(!result.signatures[0].is_verified);
+    }
+
+    // Signer certificates must be allowed to protect email
+    for file_name in ["smime_eku", "smime_key_usage"] {
+        let (_, trust_store) =
+            try_parse_certs(std::fs::read(resources.join(format!("{file_name}.pem"))).unwrap())
+                .unwrap();
+        let raw_message = std::fs::read(resources.join(format!("{file_name}.eml"))).unwrap();
+        let message = MessageParser::new().parse(&raw_message).unwrap();
+        let result = message.verify_smime(&trust_store, now).unwrap();
+        assert_eq!(
+            result.status,
+            SmimeStatus::SignedFailed,
+            "failed for {file_name}"
+        );
+        assert_eq!(
+            result.errors,
+            vec![
+                "Signer certificate is not valid for email protection (alice@example.org)."
+                    .to_string()
+            ],
+            "failed for {file_name}"
+        );
+        assert!(!result.signatures[0].is_verified);
+    }
+
+    // Messages without S/MIME signatures
+    let message = MessageParser::new()
+        .parse(b"From: alice@example.org\r\nSubject: test\r\n\r\ntest\r\n")
+        .unwrap();
+    assert!(message.verify_smime(&trust_store, now).is_none());
+}
+
 async fn post(params: &AHashMap<String, Vec<u8>>) -> String {
     let mut form = reqwest::multipart::Form::new();
     for (key, value) in params {
@@ -21,7 +21,12 @@
  * for more details.
 */
 
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
 
 use jmap::{mailbox::INBOX_ID, JMAP};
 use jmap_client::{client::Client, core::query, email::query::Filter};
@@ -66,6 +71,19 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         email_ids.insert(email_name, email_id);
     }
 
+    // Rebuild the index in the background, results should not change
+    assert_eq!(server.fts_reindex(Some(1)).await.unwrap(), email_ids.len());
+    let mut pending = usize::MAX;
+    for _ in 0..100 {
+        pending = server.fts_index_stats.pending.load(Ordering::Relaxed);
+        if pending == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(pending, 0, "Indexing queue did not drain.");
+    assert_eq!(server.fts_index_stats.failed.load(Ordering::Relaxed), 0);
+
     // Run tests
     for (filter, email_name, snippet_subject, snippet_preview) in [
         (
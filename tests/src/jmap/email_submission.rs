@@ -31,11 +31,12 @@ use jmap::JMAP;
 use jmap_client::{
     client::Client,
     core::set::{SetError, SetErrorType, SetObject},
+    email::Property,
     email_submission::{query::Filter, Address, Delivered, DeliveryStatus, Displayed, UndoStatus},
     mailbox::Role,
     Error,
 };
-use jmap_proto::types::id::Id;
+use jmap_proto::types::{acl::Acl, id::Id};
 use mail_parser::DateTime;
 use store::parking_lot::Mutex;
 use tokio::{
@@ -43,10 +44,11 @@ use tokio::{
     net::TcpListener,
     sync::mpsc,
 };
+use utils::map::bitmap::Bitmap;
 
 use crate::{
     directory::sql::create_test_user_with_email,
-    jmap::{email_set::assert_email_properties, mailbox::destroy_all_mailboxes},
+    jmap::{email_set::assert_email_properties, jmap_json_request, mailbox::destroy_all_mailboxes},
 };
 
 #[derive(Default, Debug, PartialEq, Eq)]
@@ -458,6 +460,233 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         .await
         .unwrap()
         .is_none());
+    // Send a disposition notification (RFC 9007)
+    let email_body = concat!(
+        "From: Jane Smith <jane_smith@remote.org>\r\n",
+        "To: jdoe@example.com\r\n",
+        "Subject: Please confirm\r\n",
+        "Message-ID: <mdn-request@remote.org>\r\n",
+        "Disposition-Notification-To: Jane Smith <jane_smith@remote.org>\r\n",
+        "\r\n",
+        "Let me know when you read this.\r\n"
+    );
+    let email_id = client
+        .email_import(
+            email_body.as_bytes().to_vec(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let mdn_request = r#"[[ "MDN/send", {
+            "accountId": "$$",
+            "identityId": "%%",
+            "send": {
+                "k1": {
+                    "forEmailId": "@@",
+                    "subject": "Read receipt",
+                    "textBody": "Your message was displayed.",
+                    "reportingUA": "example.com; Stalwart JMAP",
+                    "disposition": {
+                        "actionMode": "manual-action",
+                        "sendingMode": "mdn-sent-manually",
+                        "type": "displayed"
+                    }
+                }
+            }
+          }, "0" ]]"#
+        .replace("$$", &account_id)
+        .replace("%%", &identity_id)
+        .replace("@@", &email_id);
+    let response = jmap_json_request(&mdn_request, "jdoe@example.com", "12345").await;
+    assert_eq!(
+        response["methodResponses"][0][1]["sent"]["k1"]["finalRecipient"],
+        "rfc822; jdoe@example.com",
+        "{response}"
+    );
+    assert_eq!(response["methodResponses"][1][0], "Email/set", "{response}");
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.mail_from, "<jdoe@example.com>");
+    assert_eq!(message.rcpt_to, vec!["<jane_smith@remote.org>".to_string()]);
+    for needle in [
+        "report-type=\"disposition-notification\"",
+        "Reporting-UA: example.com; Stalwart JMAP",
+        "Final-Recipient: rfc822; jdoe@example.com",
+        "Original-Message-ID: <mdn-request@remote.org>",
+        "Disposition: manual-action/mdn-sent-manually; displayed",
+        "In-Reply-To: <mdn-request@remote.org>",
+    ] {
+        assert!(
+            message.message.contains(needle),
+            "{needle:?} not found in [{}]",
+            message.message
+        );
+    }
+
+    // The email should be flagged as $mdnsent
+    assert_eq!(
+        client
+            .email_get(&email_id, [Property::Keywords].into())
+            .await
+            .unwrap()
+            .unwrap()
+            .keywords(),
+        &["$mdnsent"]
+    );
+
+    // Sending a second MDN for the same email should fail
+    let response = jmap_json_request(&mdn_request, "jdoe@example.com", "12345").await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notSent"]["k1"]["type"], "mdnAlreadySent",
+        "{response}"
+    );
+    expect_nothing(&mut smtp_rx).await;
+
+    // Parse the MDN that was sent
+    let mdn_email_id = client
+        .email_import(
+            message.message.into_bytes(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let blob_id = client
+        .email_get(&mdn_email_id, [Property::BlobId].into())
+        .await
+        .unwrap()
+        .unwrap()
+        .take_blob_id();
+    let response = jmap_json_request(
+        r#"[[ "MDN/parse", {
+            "accountId": "$$",
+            "blobIds": [ "%%" ]
+          }, "0" ]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &blob_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let mdn = &response["methodResponses"][0][1]["parsed"][blob_id.as_str()];
+    assert_eq!(mdn["forEmailId"], email_id.as_str(), "{response}");
+    assert_eq!(mdn["subject"], "Read receipt", "{response}");
+    assert_eq!(
+        mdn["reportingUA"], "example.com; Stalwart JMAP",
+        "{response}"
+    );
+    assert_eq!(
+        mdn["finalRecipient"], "rfc822; jdoe@example.com",
+        "{response}"
+    );
+    assert_eq!(
+        mdn["originalMessageId"], "<mdn-request@remote.org>",
+        "{response}"
+    );
+    assert_eq!(mdn["includeOriginalMessage"], false, "{response}");
+    assert_eq!(
+        mdn["disposition"]["actionMode"], "manual-action",
+        "{response}"
+    );
+    assert_eq!(
+        mdn["disposition"]["sendingMode"], "mdn-sent-manually",
+        "{response}"
+    );
+    assert_eq!(mdn["disposition"]["type"], "displayed", "{response}");
+
+    // Identities using another account's address require delegated rights
+    create_test_user_with_email(directory, "jane.doe@example.com", "abcde", "Jane Doe").await;
+    let owner_id = server.get_account_id("jdoe@example.com").await.unwrap();
+    let delegate_id = server.get_account_id("jane.doe@example.com").await.unwrap();
+    client.set_default_account_id(Id::from(delegate_id).to_string());
+    match client
+        .identity_create("John Doe", "jdoe@example.com")
+        .await
+        .unwrap_err()
+    {
+        Error::Set(err) => assert_eq!(err.error(), &SetErrorType::InvalidProperties),
+        err => panic!("Unexpected error: {:?}", err),
+    }
+    server
+        .delegation_set(
+            owner_id,
+            delegate_id,
+            Bitmap::new().with_item(Acl::SendOnBehalf),
+        )
+        .await
+        .unwrap();
+    let delegated_identity_id = client
+        .identity_create("John Doe", "jdoe@example.com")
+        .await
+        .unwrap()
+        .take_id();
+
+    // Messages sent on behalf of another account include a Sender header
+    let delegated_mailbox_id = client
+        .mailbox_create("JMAP Delegation", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    let email_body =
+        "From: jdoe@example.com\r\nTo: jane_smith@remote.org\r\nSubject: hey\r\n\r\ntest";
+    let email_id = client
+        .email_import(
+            email_body.as_bytes().to_vec(),
+            [&delegated_mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    client
+        .email_submission_create(&email_id, &delegated_identity_id)
+        .await
+        .unwrap();
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.mail_from, "<jdoe@example.com>");
+    assert!(
+        message
+            .message
+            .contains("Sender: <jane.doe@example.com>\r\n"),
+        "{}",
+        message.message
+    );
+
+    // Submissions fail once the rights are revoked
+    server
+        .delegation_set(owner_id, delegate_id, Bitmap::new())
+        .await
+        .unwrap();
+    assert!(matches!(
+        client
+            .email_submission_create(&email_id, &delegated_identity_id)
+            .await,
+        Err(Error::Set(SetError {
+            type_: SetErrorType::ForbiddenFrom,
+            ..
+        }))
+    ));
+    expect_nothing(&mut smtp_rx).await;
+    client
+        .identity_destroy(&delegated_identity_id)
+        .await
+        .unwrap();
+    for id in client
+        .email_submission_query(None::<Filter>, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .take_ids()
+    {
+        client.email_submission_destroy(&id).await.unwrap();
+    }
+    destroy_all_mailboxes(client).await;
+    client.set_default_account_id(&account_id);
+
     smtp_settings.lock().do_stop = true;
 
     // Destroy the created mailbox, identity and all submissions
@@ -0,0 +1,195 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{sync::Arc, time::SystemTime};
+
+use jmap::{mailbox::INBOX_ID, JMAP};
+use jmap_client::{client::Client, mailbox::Role};
+use jmap_proto::types::{collection::Collection, id::Id};
+use store::BlobKind;
+
+use crate::{
+    directory::sql::create_test_user_with_email,
+    jmap::{jmap_json_request, mailbox::destroy_all_mailboxes},
+};
+
+pub async fn test(server: Arc<JMAP>, admin_client: &mut Client) {
+    println!("Running legal hold tests...");
+    let directory = server.directory.as_ref();
+    create_test_user_with_email(directory, "jdoe@example.com", "12345", "John Doe").await;
+    let account_id = server.get_account_id("jdoe@example.com").await.unwrap();
+    admin_client.set_default_account_id(Id::from(account_id).to_string());
+    let inbox_id = Id::from(INBOX_ID).to_string();
+
+    // Messages deleted before the hold is placed are not retained
+    let email_id = admin_client
+        .email_import(
+            b"From: bill@example.com\r\nSubject: not held\r\n\r\ntest".to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    admin_client.email_destroy(&email_id).await.unwrap();
+    assert!(server.legal_hold_list(account_id).await.unwrap().is_empty());
+
+    // Messages deleted while the hold is active are hidden but retained
+    server.legal_hold_enable(account_id).await.unwrap();
+    assert!(server.legal_hold_since(account_id).await.unwrap().is_some());
+    let raw_message = b"From: bill@example.com\r\nSubject: held\r\n\r\ntest".to_vec();
+    let email_id = admin_client
+        .email_import(raw_message.clone(), [&inbox_id], Some(["$flagged"]), None)
+        .await
+        .unwrap()
+        .take_id();
+    admin_client.email_destroy(&email_id).await.unwrap();
+    assert!(admin_client
+        .email_get(&email_id, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .is_none());
+    let held = server.legal_hold_list(account_id).await.unwrap();
+    assert_eq!(held.len(), 1);
+    assert_eq!(
+        held[0].document_id,
+        Id::from_bytes(email_id.as_bytes()).unwrap().document_id()
+    );
+    assert_eq!(held[0].mailboxes, ["Inbox"]);
+    assert_eq!(held[0].keywords, ["$flagged"]);
+    assert_eq!(
+        server
+            .store
+            .get_blob(&held_blob_kind(account_id, held[0].id), 0..u32::MAX)
+            .await
+            .unwrap(),
+        Some(raw_message)
+    );
+
+    // Messages expunged by the mailbox retention policy are also retained
+    let mailbox_id = admin_client
+        .mailbox_create("Retention", None::<&str>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    let response = jmap_json_request(
+        r#"[["Mailbox/set", {"accountId": "$$", "update": {"%%": {"retention": 30}}}, "R1"]]"#
+            .replace("$$", &Id::from(account_id).to_string())
+            .replace("%%", &mailbox_id),
+        "admin",
+        "secret",
+    )
+    .await;
+    assert!(
+        response
+            .pointer("/methodResponses/0/1/updated")
+            .and_then(|v| v.as_object())
+            .map_or(false, |updated| updated.contains_key(&mailbox_id)),
+        "Response: {:?}",
+        response
+    );
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let email_id = admin_client
+        .email_import(
+            b"From: bill@example.com\r\nSubject: expired\r\n\r\ntest".to_vec(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            Some(now - 31 * 86400),
+        )
+        .await
+        .unwrap()
+        .take_id();
+    assert_eq!(server.mailbox_expunge_expired(account_id).await.unwrap(), 1);
+    assert!(admin_client
+        .email_get(&email_id, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .is_none());
+    let held = server.legal_hold_list(account_id).await.unwrap();
+    assert_eq!(held.len(), 2);
+    let expired = held
+        .iter()
+        .find(|message| {
+            message.document_id == Id::from_bytes(email_id.as_bytes()).unwrap().document_id()
+        })
+        .unwrap();
+    assert_eq!(expired.mailboxes, ["Retention"]);
+    assert!(server
+        .store
+        .get_blob(&held_blob_kind(account_id, expired.id), 0..u32::MAX)
+        .await
+        .unwrap()
+        .is_some());
+
+    // Accounts on legal hold cannot be deleted
+    assert_eq!(
+        admin_get("/admin/account/delete/jdoe@example.com").await,
+        reqwest::StatusCode::CONFLICT
+    );
+    assert_eq!(
+        server.try_get_account_id("jdoe@example.com").await.unwrap(),
+        Some(account_id)
+    );
+
+    // Releasing the hold removes the retained messages
+    let held_ids = held.iter().map(|message| message.id).collect::<Vec<_>>();
+    assert_eq!(server.legal_hold_release(account_id).await.unwrap(), 2);
+    assert!(server.legal_hold_since(account_id).await.unwrap().is_none());
+    assert!(server.legal_hold_list(account_id).await.unwrap().is_empty());
+    for held_id in held_ids {
+        assert!(server
+            .store
+            .get_blob(&held_blob_kind(account_id, held_id), 0..u32::MAX)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    destroy_all_mailboxes(admin_client).await;
+    server.store.assert_is_empty().await;
+}
+
+fn held_blob_kind(account_id: u32, document_id: u32) -> BlobKind {
+    BlobKind::Linked {
+        account_id,
+        collection: Collection::LegalHold.into(),
+        document_id,
+    }
+}
+
+async fn admin_get(path: &str) -> reqwest::StatusCode {
+    reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .get(format!("https://127.0.0.1:8899{path}"))
+        .basic_auth("admin", Some("secret"))
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
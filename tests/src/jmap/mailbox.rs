@@ -21,7 +21,7 @@
  * for more details.
 */
 
-use std::sync::Arc;
+use std::{sync::Arc, time::SystemTime};
 
 use jmap::JMAP;
 use jmap_client::{
@@ -30,13 +30,16 @@ use jmap_client::{
         query::Filter,
         set::{SetError, SetErrorType, SetObject, SetRequest},
     },
+    email,
     mailbox::{self, Mailbox, Role},
     Error, Set,
 };
-use jmap_proto::types::{id::Id, state::State};
+use jmap_proto::types::{collection::Collection, id::Id, property::Property, state::State};
 use serde::{Deserialize, Serialize};
 use store::ahash::AHashMap;
 
+use crate::jmap::jmap_json_request;
+
 pub async fn test(server: Arc<JMAP>, client: &mut Client) {
     println!("Running Mailbox tests...");
 
@@ -604,6 +607,158 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         ["inbox", "sent", "spam"]
     );
 
+    // Messages older than the mailbox retention period should be expunged
+    let mailbox_id = client
+        .mailbox_create("Retention", None::<&str>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    let account_id = Id::from(0u64).to_string();
+    let response = jmap_json_request(
+        r#"[[
+            "Mailbox/set",
+            {"accountId": "$$", "update": {"%%": {"retention": 30}}},
+            "R1"
+           ],
+           [
+            "Mailbox/get",
+            {"accountId": "$$", "ids": ["%%"], "properties": ["retention"]},
+            "R2"
+           ]]"#
+        .replace("$$", &account_id)
+        .replace("%%", &mailbox_id),
+        "admin",
+        "secret",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/list/0/retention")
+            .and_then(|v| v.as_u64()),
+        Some(30),
+        "Response: {:?}",
+        response
+    );
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let mut email_ids = Vec::new();
+    for (subject, received_at) in [("expired", now - 31 * 86400), ("recent", now - 86400)] {
+        email_ids.push(
+            client
+                .email_import(
+                    format!("From: bill@example.com\r\nSubject: {subject}\r\n\r\ntest")
+                        .into_bytes(),
+                    [&mailbox_id],
+                    None::<Vec<&str>>,
+                    Some(received_at),
+                )
+                .await
+                .unwrap()
+                .take_id(),
+        );
+    }
+
+    // Messages also filed in other mailboxes are only untagged
+    let shared_id = client
+        .email_import(
+            b"From: bill@example.com\r\nSubject: shared\r\n\r\ntest".to_vec(),
+            [&mailbox_id, &id_map["inbox"]],
+            None::<Vec<&str>>,
+            Some(now - 31 * 86400),
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let shared_document_id = Id::from_bytes(shared_id.as_bytes()).unwrap().document_id();
+    let shared_cid = server
+        .get_property::<u64>(0, Collection::Email, shared_document_id, Property::Cid)
+        .await
+        .unwrap()
+        .unwrap();
+    let response = jmap_json_request(
+        r#"[["Email/get", {"accountId": "$$", "ids": []}, "R1"],
+            ["Mailbox/get", {"accountId": "$$", "ids": []}, "R2"]]"#
+            .replace("$$", &account_id),
+        "admin",
+        "secret",
+    )
+    .await;
+    let email_state = response
+        .pointer("/methodResponses/0/1/state")
+        .and_then(|v| v.as_str())
+        .unwrap()
+        .to_string();
+    let mailbox_state = response
+        .pointer("/methodResponses/1/1/state")
+        .and_then(|v| v.as_str())
+        .unwrap()
+        .to_string();
+
+    assert_eq!(server.mailbox_expunge_expired(0).await.unwrap(), 2);
+    assert_eq!(
+        client
+            .email_query(
+                email::query::Filter::in_mailbox(&mailbox_id).into(),
+                None::<Vec<_>>
+            )
+            .await
+            .unwrap()
+            .ids(),
+        [email_ids[1].as_str()]
+    );
+    assert_eq!(
+        client
+            .email_get(&shared_id, [email::Property::MailboxIds].into())
+            .await
+            .unwrap()
+            .unwrap()
+            .mailbox_ids(),
+        [id_map["inbox"].as_str()]
+    );
+    assert!(
+        server
+            .get_property::<u64>(0, Collection::Email, shared_document_id, Property::Cid)
+            .await
+            .unwrap()
+            .unwrap()
+            > shared_cid
+    );
+    let response = jmap_json_request(
+        r#"[["Email/changes", {"accountId": "$$", "sinceState": "%%"}, "R1"],
+            ["Mailbox/changes", {"accountId": "$$", "sinceState": "@@"}, "R2"]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &email_state)
+            .replace("@@", &mailbox_state),
+        "admin",
+        "secret",
+    )
+    .await;
+    assert_eq!(
+        response.pointer("/methodResponses/0/1/updated"),
+        Some(&serde_json::json!([shared_id])),
+        "Response: {:?}",
+        response
+    );
+    assert_eq!(
+        response.pointer("/methodResponses/0/1/destroyed"),
+        Some(&serde_json::json!([email_ids[0]])),
+        "Response: {:?}",
+        response
+    );
+    assert!(
+        response
+            .pointer("/methodResponses/1/1/updated")
+            .and_then(|v| v.as_array())
+            .map_or(false, |ids| ids
+                .iter()
+                .any(|id| id.as_str() == Some(mailbox_id.as_str()))),
+        "Response: {:?}",
+        response
+    );
+    assert_eq!(server.mailbox_expunge_expired(0).await.unwrap(), 0);
+
     destroy_all_mailboxes(client).await;
     client.set_default_account_id(Id::from(1u64));
     server.store.assert_is_empty().await;
@@ -23,6 +23,7 @@
 
 use std::{sync::Arc, time::Duration};
 
+use ::store::Store;
 use base64::{engine::general_purpose, Engine};
 use directory::config::ConfigDirectory;
 use jmap::{api::JmapSessionManager, services::IPC_CHANNEL_BUFFER, JMAP};
@@ -55,6 +56,7 @@ pub mod email_search_snippet;
 pub mod email_set;
 pub mod email_submission;
 pub mod event_source;
+pub mod legal_hold;
 pub mod mailbox;
 pub mod push_subscription;
 pub mod quota;
@@ -146,6 +148,9 @@ private-key = "file://{PK}"
 [jmap]
 directory = "sql"
 
+[jmap.fts.index]
+background = false
+
 [jmap.protocol]
 set.max-objects = 100000
 
@@ -253,6 +258,7 @@ pub async fn jmap_tests() {
     email_submission::test(params.server.clone(), &mut params.client).await;
     websocket::test(params.server.clone(), &mut params.client).await;
     quota::test(params.server.clone(), &mut params.client).await;
+    legal_hold::test(params.server.clone(), &mut params.client).await;
     crypto::test(params.server.clone(), &mut params.client).await;
     blob::test(params.server.clone(), &mut params.client).await;
 
@@ -297,12 +303,22 @@ async fn init_jmap_tests(delete_if_exists: bool) -> JMAPTest {
     // Start JMAP and SMTP servers
     servers.bind(&config);
     let (delivery_tx, delivery_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
-    let smtp = SMTP::init(&config, &servers, &directory, delivery_tx)
-        .await
-        .failed("Invalid configuration file");
-    let jmap = JMAP::init(&config, &directory, delivery_rx, smtp.clone())
+    let store = Arc::new(Store::open(&config).await.failed("Unable to open database"));
+    let smtp = SMTP::init(&config, &servers, &directory, store.clone(), delivery_tx)
         .await
-        .failed("Invalid configuration file");
+        .failed("Invalid configuration file")
+        .load();
+    let jmap = JMAP::init(
+        &config,
+        &directory,
+        store,
+        delivery_rx,
+        mpsc::channel(1).0,
+        smtp.clone(),
+    )
+    .await
+    .failed("Invalid configuration file")
+    .load();
     let (shutdown_tx, _) = servers.spawn(|server, shutdown_rx| {
         match &server.protocol {
             ServerProtocol::Smtp | ServerProtocol::Lmtp => {
@@ -43,6 +43,7 @@ use crate::{
     jmap::{
         delivery::SmtpConnection,
         email_submission::{assert_message_delivery, spawn_mock_smtp_server, MockMessage},
+        jmap_json_request,
         mailbox::destroy_all_mailboxes,
     },
 };
@@ -479,6 +480,218 @@ pub async fn test(server: Arc<JMAP>, client: &mut Client) {
         panic!("Email {:?} not found in: {:#?}", subject, emails);
     }
 
+    // Updating the mail filter before it is created should fail
+    let response = jmap_json_request(
+        r#"[[ "MailFilter/set", {
+                "accountId": "$$",
+                "update": {
+                    "singleton": {
+                        "isEnabled": true
+                    }
+                }
+            }, "0" ]]"#
+            .replace("$$", &account_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notUpdated"]["singleton"]["type"], "notFound",
+        "{response}"
+    );
+
+    // Create mail filter rules, which replace the active script
+    let rules = r#"[
+            {
+                "name": "TPS mailing list",
+                "conditions": [
+                    {
+                        "field": "listId",
+                        "comparator": "contains",
+                        "value": "tps.example.org"
+                    },
+                    {
+                        "field": "subject",
+                        "comparator": "contains",
+                        "not": true,
+                        "value": "urgent"
+                    }
+                ],
+                "actions": [
+                    { "type": "flag", "value": "$flagged" },
+                    { "type": "fileInto", "value": "TPS Reports" },
+                    { "type": "stop" }
+                ]
+            }
+        ]"#;
+    let response = jmap_json_request(
+        r#"[[ "MailFilter/set", {
+                "accountId": "$$",
+                "create": {
+                    "f1": {
+                        "isEnabled": true,
+                        "rules": @@
+                    }
+                }
+            }, "0" ]]"#
+            .replace("$$", &account_id)
+            .replace("@@", rules),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["created"]["f1"]["id"], "singleton",
+        "{response}"
+    );
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Weekly TPS digest\r\n",
+            "List-Id: TPS reports <tps.example.org>\r\n",
+            "\r\n",
+            "This week's TPS reports are ready, with the new cover sheets."
+        ),
+    )
+    .await;
+    let mailbox_id = client
+        .mailbox_query(
+            mailbox::query::Filter::name("TPS Reports").into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .expect("Mailbox \"TPS Reports\" was not created.");
+    let email_id = client
+        .email_query(
+            email::query::Filter::in_mailbox(&mailbox_id).into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .expect("Message was not filed into \"TPS Reports\".");
+    assert_eq!(
+        client
+            .email_get(&email_id, [email::Property::Keywords].into())
+            .await
+            .unwrap()
+            .unwrap()
+            .keywords(),
+        &["$flagged"]
+    );
+
+    // Rules should be returned while the script is not edited by hand
+    let get_request = r#"[[ "MailFilter/get", {
+            "accountId": "$$"
+        }, "0" ]]"#
+        .replace("$$", &account_id);
+    let response = jmap_json_request(&get_request, "jdoe@example.com", "12345").await;
+    let filter = &response["methodResponses"][0][1]["list"][0];
+    assert_eq!(filter["isEnabled"], true, "{response}");
+    assert_eq!(filter["rules"][0]["name"], "TPS mailing list", "{response}");
+    assert_eq!(
+        filter["rules"][0]["actions"][1]["value"], "TPS Reports",
+        "{response}"
+    );
+
+    // Edit the generated script by hand
+    let script_id = client
+        .sieve_script_query(Filter::name("mailfilter").into(), None::<Vec<_>>)
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .unwrap();
+    let blob_id = client
+        .upload(
+            None,
+            b"require \"fileinto\";\r\nfileinto \"Hand edited\";\r\n".to_vec(),
+            None,
+        )
+        .await
+        .unwrap()
+        .take_blob_id();
+    let response = jmap_json_request(
+        r#"[[ "SieveScript/set", {
+                "accountId": "$$",
+                "update": {
+                    "%%": {
+                        "blobId": "@@"
+                    }
+                }
+            }, "0" ]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &script_id)
+            .replace("@@", &blob_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response["methodResponses"][0][1]["updated"]
+            .as_object()
+            .map_or(false, |updated| updated.contains_key(&script_id)),
+        "{response}"
+    );
+    let response = jmap_json_request(&get_request, "jdoe@example.com", "12345").await;
+    let filter = &response["methodResponses"][0][1]["list"][0];
+    assert_eq!(filter["isEnabled"], true, "{response}");
+    assert!(filter["rules"].is_null(), "{response}");
+
+    // Invalid rules should be rejected
+    let set_request = r#"[[ "MailFilter/set", {
+            "accountId": "$$",
+            "update": {
+                "singleton": {
+                    "rules": @@
+                }
+            }
+        }, "0" ]]"#
+        .replace("$$", &account_id);
+    let response = jmap_json_request(
+        set_request.replace(
+            "@@",
+            r#"[{
+                "name": "Invalid",
+                "conditions": [{ "field": "subject", "comparator": "over", "value": "10K" }],
+                "actions": [{ "type": "stop" }]
+            }]"#,
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notUpdated"]["singleton"]["type"], "invalidProperties",
+        "{response}"
+    );
+
+    // Saving the rules again replaces the edited script
+    let response = jmap_json_request(
+        set_request.replace("@@", rules),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response["methodResponses"][0][1]["updated"]
+            .as_object()
+            .map_or(false, |updated| updated.contains_key("singleton")),
+        "{response}"
+    );
+    let response = jmap_json_request(&get_request, "jdoe@example.com", "12345").await;
+    assert_eq!(
+        response["methodResponses"][0][1]["list"][0]["rules"][0]["name"], "TPS mailing list",
+        "{response}"
+    );
+
     // Remove test data
     client.sieve_script_deactivate().await.unwrap();
     let mut request = client.build();
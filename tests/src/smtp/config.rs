@@ -442,6 +442,7 @@ fn parse_servers() {
                 nodelay: true,
             }],
             tls: None,
+            tls_certificates: None,
             tls_implicit: false,
             max_connections: 8192,
         },
@@ -470,6 +471,7 @@ fn parse_servers() {
                 },
             ],
             tls: None,
+            tls_certificates: None,
             tls_implicit: true,
             max_connections: 1024,
         },
@@ -488,6 +490,7 @@ fn parse_servers() {
                 nodelay: true,
             }],
             tls: None,
+            tls_certificates: None,
             tls_implicit: true,
             max_connections: 8192,
         },
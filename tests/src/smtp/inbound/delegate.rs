@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use directory::config::ConfigDirectory;
+use tokio::sync::mpsc;
+use utils::{
+    config::{Config, DynValue},
+    ipc::{DeliveryEvent, SendPermission},
+};
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+    ParseTestConfig, TestConfig, TestSMTP,
+};
+use smtp::{
+    config::{ConfigContext, EnvelopeKey, IfBlock},
+    core::{Session, SMTP},
+};
+
+const DIRECTORY: &str = r#"
+[directory."local"]
+type = "memory"
+
+[[directory."local".users]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = ["john@example.org", "jdoe@example.org"]
+"#;
+
+#[tokio::test]
+async fn sender_match() {
+    let mut core = SMTP::test();
+    let mut qr = core.init_test_queue("smtp_sender_match_test");
+    let mut ctx = ConfigContext::new(&[]);
+    ctx.directory = Config::new(DIRECTORY).unwrap().parse_directory().unwrap();
+
+    let config = &mut core.session.config.auth;
+    config.directory = r"[{if = 'remote-ip', eq = '10.0.0.1', then = 'local'},
+    {else = false}]"
+        .parse_if::<Option<DynValue<EnvelopeKey>>>(&ctx)
+        .map_if_block(&ctx.directory.directories, "", "")
+        .unwrap();
+    config.must_match_sender = IfBlock::new(true);
+    core.session.config.rcpt.relay = IfBlock::new(true);
+
+    // Answer delegation lookups on behalf of the JMAP server
+    let (delivery_tx, mut delivery_rx) = mpsc::channel(128);
+    core.delivery_tx = delivery_tx;
+    tokio::spawn(async move {
+        while let Some(event) = delivery_rx.recv().await {
+            if let DeliveryEvent::VerifySender {
+                address,
+                submitter,
+                result_tx,
+            } = event
+            {
+                assert_eq!(submitter, "john");
+                let _ = result_tx.send(match address.as_str() {
+                    "sales@example.org" => Some(SendPermission::SendAs),
+                    "ceo@example.org" => Some(SendPermission::SendOnBehalf {
+                        sender: "john@example.org".to_string(),
+                    }),
+                    "unavailable@example.org" => None,
+                    _ => Some(SendPermission::Denied),
+                });
+            }
+        }
+    });
+
+    let mut session = Session::test(core);
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.data.authenticated_as = "john".to_string();
+    session.ehlo("mx.example.org").await;
+
+    // Addresses owned by the account can be used without a Sender header
+    session
+        .send_message(
+            "jdoe@example.org",
+            &["bill@remote.org"],
+            "From: jdoe@example.org\r\nSubject: Owner\r\n\r\ntest",
+            "250",
+        )
+        .await;
+    qr.read_event()
+        .await
+        .unwrap_message()
+        .read_lines()
+        .assert_contains("From: jdoe@example.org")
+        .assert_not_contains("Sender: ");
+
+    // Send-as delegates can use the address without a Sender header
+    session
+        .send_message(
+            "sales@example.org",
+            &["bill@remote.org"],
+            "From: sales@example.org\r\nSubject: Send as\r\n\r\ntest",
+            "250",
+        )
+        .await;
+    qr.read_event()
+        .await
+        .unwrap_message()
+        .read_lines()
+        .assert_not_contains("Sender: ");
+
+    // Send-on-behalf delegates get a Sender header added
+    session
+        .send_message(
+            "ceo@example.org",
+            &["bill@remote.org"],
+            "From: ceo@example.org\r\nSubject: On behalf\r\n\r\ntest",
+            "250",
+        )
+        .await;
+    qr.read_event()
+        .await
+        .unwrap_message()
+        .read_lines()
+        .assert_contains("From: ceo@example.org")
+        .assert_contains("Sender: <john@example.org>");
+
+    // Other addresses are rejected in MAIL FROM
+    session.mail_from("bill@example.org", "550 5.7.1").await;
+
+    // Or in the From header when the envelope sender is allowed
+    session
+        .send_message(
+            "john@example.org",
+            &["bill@remote.org"],
+            "From: bill@example.org\r\nSubject: Denied\r\n\r\ntest",
+            "550 5.7.1",
+        )
+        .await;
+    qr.assert_empty_queue();
+
+    // Failed delegation lookups are temporary errors
+    session
+        .mail_from("unavailable@example.org", "451 4.4.3")
+        .await;
+    session
+        .send_message(
+            "john@example.org",
+            &["bill@remote.org"],
+            "From: unavailable@example.org\r\nSubject: Unavailable\r\n\r\ntest",
+            "451 4.4.3",
+        )
+        .await;
+    qr.assert_empty_queue();
+}
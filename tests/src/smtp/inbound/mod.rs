@@ -28,14 +28,16 @@ use tokio::sync::mpsc::error::TryRecvError;
 use smtp::{
     queue::{self, Message, OnHold, Schedule, WorkerResult},
     reporting::{self, DmarcEvent, TlsEvent},
+    webhook::{self, WebhookEvent},
 };
 
-use super::{QueueReceiver, ReportReceiver};
+use super::{QueueReceiver, ReportReceiver, WebhookReceiver};
 
 pub mod antispam;
 pub mod auth;
 pub mod basic;
 pub mod data;
+pub mod delegate;
 pub mod dmarc;
 pub mod ehlo;
 pub mod limits;
@@ -98,6 +100,25 @@ impl ReportReceiver {
     }
 }
 
+impl WebhookReceiver {
+    pub async fn read_webhook(&mut self) -> WebhookEvent {
+        match tokio::time::timeout(Duration::from_millis(100), self.webhook_rx.recv()).await {
+            Ok(Some(webhook::Event::Send(event))) => *event,
+            Ok(Some(event)) => panic!("Unexpected webhook event {event:?}"),
+            Ok(None) => panic!("Channel closed."),
+            Err(_) => panic!("No webhook event received."),
+        }
+    }
+
+    pub fn assert_no_webhooks(&mut self) {
+        match self.webhook_rx.try_recv() {
+            Err(TryRecvError::Empty) => (),
+            Ok(event) => panic!("Expected no webhooks but got {event:?}"),
+            Err(err) => panic!("Webhook error: {err:?}"),
+        }
+    }
+}
+
 pub trait TestQueueEvent {
     fn unwrap_message(self) -> Box<Message>;
     fn unwrap_schedule(self) -> Schedule<Box<Message>>;
@@ -21,11 +21,19 @@
  * for more details.
 */
 
-use std::time::{Duration, Instant};
+use std::{
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 use mail_auth::{IpLookupStrategy, MX};
 
-use ::smtp::{config::IfBlock, core::SMTP, outbound::NextHop};
+use ::smtp::{
+    config::{IfBlock, IpPool, IpWarmup, PoolIp},
+    core::SMTP,
+    outbound::NextHop,
+};
 use mail_parser::DateTime;
 use smtp::{
     config::AggregateFrequency,
@@ -75,6 +83,7 @@ async fn lookup_ip() {
         .resolve_host(
             &NextHop::MX("mx.foobar.org"),
             &RecipientDomain::new("envelope"),
+            None,
             2,
         )
         .await
@@ -93,6 +102,7 @@ async fn lookup_ip() {
         .resolve_host(
             &NextHop::MX("mx.foobar.org"),
             &RecipientDomain::new("envelope"),
+            None,
             2,
         )
         .await
@@ -106,6 +116,124 @@ async fn lookup_ip() {
         .contains(&"e:f::a".parse().unwrap()));
 }
 
+#[tokio::test]
+async fn lookup_ip_pool() {
+    let mut core = SMTP::test();
+    core.queue.config.source_ip.ipv4 = IfBlock::new(vec!["10.0.0.9".parse().unwrap()]);
+    core.queue.config.ip_strategy = IfBlock::new(IpLookupStrategy::Ipv4thenIpv6);
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    for pool in [
+        IpPool {
+            name: "bulk".to_string(),
+            ips: vec![
+                PoolIp {
+                    addr: "10.0.0.1".parse().unwrap(),
+                    warmup: IpWarmup {
+                        start: now,
+                        schedule: vec![1, 10],
+                    }
+                    .into(),
+                },
+                PoolIp {
+                    addr: "10.0.0.2".parse().unwrap(),
+                    warmup: None,
+                },
+            ],
+            fallback: "transactional".to_string().into(),
+            dnsbl: vec!["dnsbl.test".to_string()],
+            dnsbl_ttl: Duration::from_secs(60),
+        },
+        IpPool {
+            name: "transactional".to_string(),
+            ips: vec![PoolIp {
+                addr: "10.0.0.3".parse().unwrap(),
+                warmup: None,
+            }],
+            fallback: None,
+            dnsbl: vec![],
+            dnsbl_ttl: Duration::from_secs(60),
+        },
+    ] {
+        core.queue
+            .config
+            .source_ip
+            .pools
+            .insert(pool.name.clone(), Arc::new(pool));
+    }
+    core.resolvers.dns.ipv4_add(
+        "mx.foobar.org",
+        vec!["172.168.0.100".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.resolvers.dns.ipv6_add(
+        "mx.foobar.org",
+        vec!["e:f::a".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.resolvers.dns.ipv4_add(
+        "2.0.0.10.dnsbl.test",
+        vec!["127.0.0.2".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    // 10.0.0.2 is listed on the DNSBL and IPv6 hosts are skipped
+    let resolve_result = core
+        .resolve_host(
+            &NextHop::MX("mx.foobar.org"),
+            &RecipientDomain::new("envelope"),
+            Some("bulk"),
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        resolve_result.source_ipv4,
+        Some("10.0.0.1".parse::<IpAddr>().unwrap())
+    );
+    assert_eq!(resolve_result.source_ipv6, None);
+    assert_eq!(
+        resolve_result.remote_ips,
+        vec!["172.168.0.100".parse::<IpAddr>().unwrap()]
+    );
+    assert_eq!(resolve_result.provider.as_deref(), Some("foobar.org"));
+
+    // Warm-up volume for 10.0.0.1 is exhausted, fail over to the transactional pool
+    core.queue
+        .record_warmup("10.0.0.1".parse().unwrap(), "foobar.org");
+    let resolve_result = core
+        .resolve_host(
+            &NextHop::MX("mx.foobar.org"),
+            &RecipientDomain::new("envelope"),
+            Some("bulk"),
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        resolve_result.source_ipv4,
+        Some("10.0.0.3".parse::<IpAddr>().unwrap())
+    );
+
+    // Unknown pools use the default source addresses
+    let resolve_result = core
+        .resolve_host(
+            &NextHop::MX("mx.foobar.org"),
+            &RecipientDomain::new("envelope"),
+            Some("unknown"),
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        resolve_result.source_ipv4,
+        Some("10.0.0.9".parse::<IpAddr>().unwrap())
+    );
+    assert_eq!(resolve_result.provider, None);
+}
+
 #[test]
 fn to_remote_hosts() {
     let mx = vec![
@@ -26,6 +26,7 @@ use std::time::Duration;
 use hyper::header::AUTHORIZATION;
 use serde::{de::DeserializeOwned, Deserialize};
 
+pub mod quarantine;
 pub mod queue;
 pub mod report;
 
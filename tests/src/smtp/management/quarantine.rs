@@ -0,0 +1,310 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{sync::Arc, time::Duration};
+
+use directory::config::ConfigDirectory;
+use hyper::header::AUTHORIZATION;
+use serde::de::DeserializeOwned;
+use utils::config::{Config, ServerProtocol};
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    make_temp_dir,
+    management::{send_manage_request, Response},
+    outbound::start_test_server,
+    session::{TestSession, VerifyResponse},
+    TestConfig, TestSMTP,
+};
+use smtp::{
+    config::{scripts::ConfigSieve, ConfigContext, IfBlock},
+    core::{Session, SMTP},
+    queue::{quarantine::QuarantineItem, QueueId},
+};
+
+const CONFIG: &str = r#"
+[directory."local"]
+type = "memory"
+
+[directory."local".options]
+superuser-group = "superusers"
+
+[[directory."local".users]]
+name = "admin"
+description = "Superuser"
+secret = "secret"
+member-of = ["superusers"]
+
+[[directory."local".users]]
+name = "jane"
+description = "Jane Doe"
+secret = "secret"
+email = ["jane@example.org"]
+
+[sieve.trusted]
+hostname = "mx.example.org"
+
+[sieve.trusted.scripts]
+stage_data = '''
+require ["envelope", "vnd.stalwart.expressions", "reject"];
+
+if envelope :domain :is "from" "spammer.org" {
+    if eval "!quarantine('Suspicious sender', 7.5)" {
+        reject "Failed to quarantine message.";
+    }
+}
+'''
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_quarantine() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Prepare config
+    let mut core = SMTP::test();
+    let mut qr = core.init_test_queue("smtp_quarantine_queue");
+    let quarantine_dir = make_temp_dir("smtp_quarantine_test", true);
+    let config = Config::new(CONFIG).unwrap();
+    let mut ctx = ConfigContext::new(&[]);
+    ctx.directory = config.parse_directory().unwrap();
+    core.sieve = config.parse_sieve(&mut ctx).unwrap();
+    core.queue.config.management_lookup = ctx.directory.directories.get("local").unwrap().clone();
+    core.queue.config.quarantine.path = quarantine_dir.temp_dir.clone().into();
+    core.session.config.rcpt.relay = IfBlock::new(true);
+    core.session.config.data.script = IfBlock::new(ctx.scripts.get("stage_data").cloned());
+    let core = Arc::new(core);
+    let _rx_manage = start_test_server(core.clone(), &[ServerProtocol::Http]);
+
+    // Messages from spammer.org should be quarantined
+    let mut session = Session::test(core.clone());
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.spammer.org").await;
+    session
+        .send_message(
+            "bill@spammer.org",
+            &["jane@example.org", "john@example.org", "jim@example.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    qr.assert_empty_queue();
+
+    // Other messages should be queued
+    session
+        .send_message(
+            "bill@foobar.org",
+            &["jane@example.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    qr.read_event().await.unwrap_message();
+    qr.assert_empty_queue();
+
+    // Obtain quarantined message details
+    let ids = send_manage_request::<Vec<QueueId>>("/admin/quarantine/list")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(ids.len(), 1);
+    let id = ids[0];
+    let item = send_manage_request::<Vec<Option<QuarantineItem>>>(&format!(
+        "/admin/quarantine/status?id={id}"
+    ))
+    .await
+    .unwrap()
+    .unwrap_data()
+    .pop()
+    .unwrap()
+    .unwrap();
+    assert_eq!(item.return_path, "bill@spammer.org");
+    assert_eq!(
+        item.recipients,
+        ["jane@example.org", "john@example.org", "jim@example.org"]
+    );
+    assert_eq!(item.reason, "Suspicious sender");
+    assert_eq!(item.score, Some(7.5));
+    assert_eq!(item.subject.as_deref(), Some("Is dinner ready?"));
+    assert_eq!(item.remote_ip, Some("10.0.0.1".parse().unwrap()));
+    assert!(
+        send_manage_request::<Option<String>>(&format!("/admin/quarantine/preview?id={id}"))
+            .await
+            .unwrap()
+            .unwrap_data()
+            .unwrap()
+            .contains("Subject: Is dinner ready?")
+    );
+
+    // Users should only see their own recipients
+    assert_eq!(
+        send_user_request::<Vec<QueueId>>("/admin/quarantine/list")
+            .await
+            .unwrap_data(),
+        vec![id]
+    );
+    assert_eq!(
+        send_user_request::<Vec<Option<QuarantineItem>>>(&format!(
+            "/admin/quarantine/status?id={id}"
+        ))
+        .await
+        .unwrap_data()
+        .pop()
+        .unwrap()
+        .unwrap()
+        .recipients,
+        ["jane@example.org"]
+    );
+
+    // Users should not be able to access other management endpoints
+    assert!(send_user_request_raw("/admin/queue/list").await.is_err());
+
+    // Release the message to Jane
+    assert_eq!(
+        send_user_request::<Vec<bool>>(&format!("/admin/quarantine/release?id={id}"))
+            .await
+            .unwrap_data(),
+        vec![true]
+    );
+    let jane_message = qr.read_event().await.unwrap_message();
+    assert_eq!(jane_message.return_path, "bill@spammer.org");
+    assert_eq!(jane_message.recipients.len(), 1);
+    assert_eq!(jane_message.recipients[0].address, "jane@example.org");
+    assert_eq!(jane_message.domains.len(), 1);
+    jane_message
+        .read_lines()
+        .assert_contains("Subject: Is dinner ready?");
+    qr.assert_empty_queue();
+
+    // Jane should no longer see the message
+    assert_eq!(
+        send_user_request::<Vec<QueueId>>("/admin/quarantine/list")
+            .await
+            .unwrap_data(),
+        Vec::<QueueId>::new()
+    );
+    assert_eq!(
+        send_user_request::<Vec<bool>>(&format!("/admin/quarantine/release?id={id}"))
+            .await
+            .unwrap_data(),
+        vec![false]
+    );
+    let item = core.queue.quarantine_get(id).await.unwrap();
+    assert_eq!(item.recipients, ["john@example.org", "jim@example.org"]);
+
+    // A second partial release should be queued under a new id
+    assert_eq!(
+        send_manage_request::<Vec<bool>>(&format!(
+            "/admin/quarantine/release?id={id}&rcpt=john@example.org"
+        ))
+        .await
+        .unwrap()
+        .unwrap_data(),
+        vec![true]
+    );
+    let john_message = qr.read_event().await.unwrap_message();
+    assert_eq!(john_message.recipients.len(), 1);
+    assert_eq!(john_message.recipients[0].address, "john@example.org");
+    assert_ne!(john_message.id, jane_message.id);
+    assert_ne!(john_message.path, jane_message.path);
+    for message in [&jane_message, &john_message] {
+        message
+            .read_lines()
+            .assert_contains("Subject: Is dinner ready?");
+    }
+    qr.assert_empty_queue();
+    let item = core.queue.quarantine_get(id).await.unwrap();
+    assert_eq!(item.recipients, ["jim@example.org"]);
+
+    // Delete the remaining recipient
+    assert_eq!(
+        send_manage_request::<Vec<bool>>(&format!("/admin/quarantine/delete?id={id}"))
+            .await
+            .unwrap()
+            .unwrap_data(),
+        vec![true]
+    );
+    assert!(core.queue.quarantine_list().await.is_empty());
+    assert_eq!(
+        std::fs::read_dir(&quarantine_dir.temp_dir).unwrap().count(),
+        0
+    );
+    qr.assert_empty_queue();
+
+    // Expired messages should be purged
+    session
+        .send_message(
+            "bill@spammer.org",
+            &["jane@example.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    let mut item = core.queue.quarantine_list().await.pop().unwrap();
+    core.queue.purge_quarantine().await;
+    assert!(core.queue.quarantine_get(item.id).await.is_some());
+    item.expires = 0;
+    std::fs::write(
+        quarantine_dir
+            .temp_dir
+            .join(format!("{:016x}.json", item.id)),
+        serde_json::to_string(&item).unwrap(),
+    )
+    .unwrap();
+    core.queue.purge_quarantine().await;
+    assert!(core.queue.quarantine_get(item.id).await.is_none());
+    assert_eq!(
+        std::fs::read_dir(&quarantine_dir.temp_dir).unwrap().count(),
+        0
+    );
+    qr.assert_empty_queue();
+}
+
+async fn send_user_request<T: DeserializeOwned>(query: &str) -> Response<T> {
+    let result = send_user_request_raw(query).await.unwrap();
+    serde_json::from_str::<Response<T>>(&result).unwrap_or_else(|err| panic!("{err}: {result}"))
+}
+
+async fn send_user_request_raw(query: &str) -> Result<String, String> {
+    let response = reqwest::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .get(format!("https://127.0.0.1:9980{query}"))
+        .header(AUTHORIZATION, "Basic amFuZTpzZWNyZXQ=")
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if response.status().is_success() {
+        response.text().await.map_err(|err| err.to_string())
+    } else {
+        Err(response.status().to_string())
+    }
+}
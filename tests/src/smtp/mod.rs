@@ -39,20 +39,20 @@ use tokio::sync::mpsc;
 use smtp::{
     config::{
         if_block::ConfigIf, queue::ConfigQueue, scripts::SieveContext, session::ConfigSession,
-        throttle::ConfigThrottle, AggregateReport, ArcAuthConfig, Auth, ConfigContext, Connect,
-        Data, DkimAuthConfig, DmarcAuthConfig, Dsn, Ehlo, EnvelopeKey, Extensions, IfBlock,
-        IpRevAuthConfig, Mail, MailAuthConfig, Milter, QueueConfig, QueueOutboundSourceIp,
-        QueueOutboundTimeout, QueueOutboundTls, QueueQuotas, QueueThrottle, Rcpt, Report,
-        ReportAnalysis, ReportConfig, SessionConfig, SessionThrottle, SpfAuthConfig, Throttle,
-        VerifyStrategy,
+        throttle::ConfigThrottle, AdaptiveThrottleKey, AggregateReport, ArcAuthConfig, Auth,
+        ConfigContext, Connect, Data, DkimAuthConfig, DmarcAuthConfig, Dsn, Ehlo, EnvelopeKey,
+        Extensions, IfBlock, IpRevAuthConfig, Mail, MailAuthConfig, Milter, QueueAdaptiveThrottle,
+        QueueConfig, QueueJournal, QueueOutboundSourceIp, QueueOutboundTimeout, QueueOutboundTls,
+        QueueQuarantine, QueueQuotas, QueueThrottle, Rcpt, Report, ReportAnalysis, ReportConfig,
+        SessionConfig, SessionThrottle, SpfAuthConfig, Throttle, VerifyStrategy, WebhookConfig,
     },
     core::{
         throttle::ThrottleKeyHasherBuilder, QueueCore, ReportCore, Resolvers, SessionCore,
-        SieveConfig, SieveCore, TlsConnectors, SMTP,
+        SieveConfig, SieveCore, TlsConnectors, WebhookCore, SMTP,
     },
     outbound::dane::DnssecResolver,
 };
-use utils::config::{utils::ParseValues, Config};
+use utils::config::{utils::ParseValues, Config, Rate};
 
 pub mod config;
 pub mod inbound;
@@ -161,10 +161,12 @@ pub trait TestConfig {
 impl TestConfig for SMTP {
     fn test() -> Self {
         SMTP {
-            worker_pool: rayon::ThreadPoolBuilder::new()
-                .num_threads(num_cpus::get())
-                .build()
-                .unwrap(),
+            worker_pool: Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_cpus::get())
+                    .build()
+                    .unwrap(),
+            ),
             session: SessionCore::test(),
             queue: QueueCore::test(),
             resolvers: Resolvers {
@@ -182,6 +184,7 @@ impl TestConfig for SMTP {
             mail_auth: MailAuthConfig::test(),
             report: ReportCore::test(),
             sieve: SieveCore::test(),
+            webhook: WebhookCore::test(),
             delivery_tx: mpsc::channel(1).0,
         }
     }
@@ -191,11 +194,11 @@ impl TestConfig for SessionCore {
     fn test() -> Self {
         SessionCore {
             config: SessionConfig::test(),
-            throttle: DashMap::with_capacity_and_hasher_and_shard_amount(
+            throttle: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                 10,
                 ThrottleKeyHasherBuilder::default(),
                 16,
-            ),
+            )),
         }
     }
 }
@@ -222,6 +225,7 @@ impl TestConfig for SessionConfig {
             extensions: Extensions {
                 pipelining: IfBlock::new(true),
                 chunking: IfBlock::new(true),
+                burl: IfBlock::new(false),
                 requiretls: IfBlock::new(true),
                 no_soliciting: IfBlock::new("domain.org".to_string().into()),
                 future_release: IfBlock::new(None),
@@ -238,6 +242,7 @@ impl TestConfig for SessionConfig {
                 errors_max: IfBlock::new(10),
                 errors_wait: IfBlock::new(Duration::from_secs(1)),
                 allow_plain_text: IfBlock::new(false),
+                must_match_sender: IfBlock::new(false),
             },
             mail: Mail {
                 script: IfBlock::new(None),
@@ -274,18 +279,21 @@ impl TestConfig for QueueCore {
     fn test() -> Self {
         Self {
             config: QueueConfig::test(),
-            throttle: DashMap::with_capacity_and_hasher_and_shard_amount(
+            throttle: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                 10,
                 ThrottleKeyHasherBuilder::default(),
                 16,
-            ),
-            quota: DashMap::with_capacity_and_hasher_and_shard_amount(
+            )),
+            quota: Arc::new(DashMap::with_capacity_and_hasher_and_shard_amount(
                 10,
                 ThrottleKeyHasherBuilder::default(),
                 16,
-            ),
+            )),
             tx: mpsc::channel(1024).0,
-            id_seq: 0.into(),
+            adaptive: Arc::new(DashMap::with_capacity_and_shard_amount(10, 16)),
+            warmup: Arc::new(DashMap::with_capacity_and_shard_amount(10, 16)),
+            dnsbl: Arc::new(DashMap::with_capacity_and_shard_amount(10, 16)),
+            id_seq: Arc::new(0.into()),
             connectors: TlsConnectors {
                 pki_verify: build_tls_connector(false),
                 dummy_verify: build_tls_connector(true),
@@ -309,6 +317,9 @@ impl TestConfig for QueueConfig {
             source_ip: QueueOutboundSourceIp {
                 ipv4: IfBlock::new(vec![]),
                 ipv6: IfBlock::new(vec![]),
+                pool: IfBlock::default(),
+                pool_header: IfBlock::default(),
+                pools: AHashMap::new(),
             },
             ip_strategy: IfBlock::new(IpLookupStrategy::Ipv4thenIpv6),
             tls: QueueOutboundTls {
@@ -337,12 +348,35 @@ impl TestConfig for QueueConfig {
                 rcpt: vec![],
                 host: vec![],
             },
+            adaptive: QueueAdaptiveThrottle {
+                enable: IfBlock::new(false),
+                key: AdaptiveThrottleKey::Mx,
+                concurrency_min: 1,
+                concurrency_max: 10,
+                rate_min: 1,
+                rate_max: Rate {
+                    requests: 100,
+                    period: Duration::from_secs(60),
+                },
+                backoff: 0.5,
+                recover_after: 10,
+                jitter: 0.0,
+            },
             quota: QueueQuotas {
                 sender: vec![],
                 rcpt: vec![],
                 rcpt_domain: vec![],
             },
+            quarantine: QueueQuarantine {
+                path: None,
+                retention: Duration::from_secs(30 * 86400),
+            },
+            journal: QueueJournal {
+                address: IfBlock::new(None),
+                path: IfBlock::new(None),
+            },
             management_lookup: Arc::new(MemoryDirectory::default()),
+            metrics_max_domains: 25,
         }
     }
 }
@@ -377,6 +411,20 @@ impl TestConfig for ReportCore {
         Self {
             config: ReportConfig::test(),
             tx: mpsc::channel(1024).0,
+            store: None,
+        }
+    }
+}
+
+impl TestConfig for WebhookCore {
+    fn test() -> Self {
+        Self {
+            config: WebhookConfig {
+                path: Default::default(),
+                endpoints: vec![],
+                events: 0,
+            },
+            tx: mpsc::channel(1024).0,
         }
     }
 }
@@ -494,9 +542,14 @@ pub struct ReportReceiver {
     pub report_rx: mpsc::Receiver<smtp::reporting::Event>,
 }
 
+pub struct WebhookReceiver {
+    pub webhook_rx: mpsc::Receiver<smtp::webhook::Event>,
+}
+
 pub trait TestSMTP {
     fn init_test_queue(&mut self, test_name: &str) -> QueueReceiver;
     fn init_test_report(&mut self) -> ReportReceiver;
+    fn init_test_webhook(&mut self) -> WebhookReceiver;
 }
 
 impl TestSMTP for SMTP {
@@ -518,4 +571,10 @@ impl TestSMTP for SMTP {
         self.report.tx = report_tx;
         ReportReceiver { report_rx }
     }
+
+    fn init_test_webhook(&mut self) -> WebhookReceiver {
+        let (webhook_tx, webhook_rx) = mpsc::channel(128);
+        self.webhook.tx = webhook_tx;
+        WebhookReceiver { webhook_rx }
+    }
 }
@@ -28,6 +28,8 @@ use std::{
 };
 
 use mail_auth::MX;
+use smtp_proto::Response;
+use utils::config::Rate;
 
 use crate::smtp::{
     inbound::TestQueueEvent, queue::manager::new_message, session::TestSession, ParseTestConfig,
@@ -35,8 +37,11 @@ use crate::smtp::{
 };
 use smtp::{
     config::{ConfigContext, IfBlock},
-    core::{Session, SMTP},
-    queue::{manager::Queue, DeliveryAttempt, Message, QueueEnvelope},
+    core::{QueueCore, Session, SMTP},
+    queue::{
+        adaptive::Feedback, manager::Queue, throttle, DeliveryAttempt, Error, ErrorDetails,
+        HostResponse, Message, QueueEnvelope, Recipient, Status, RCPT_STATUS_CHANGED,
+    },
 };
 
 const THROTTLE: &str = "
@@ -298,6 +303,128 @@ async fn throttle_outbound() {
     ));
 }
 
+#[tokio::test]
+async fn throttle_adaptive() {
+    let mut core = QueueCore::test();
+    core.config.adaptive.enable = IfBlock::new(true);
+    core.config.adaptive.concurrency_max = 4;
+    core.config.adaptive.rate_max = Rate {
+        requests: 20,
+        period: Duration::from_secs(60),
+    };
+    core.config.adaptive.recover_after = 2;
+
+    let test_message = new_message(0);
+    let envelope = QueueEnvelope::test(&test_message, "example.org", "mx.example.org");
+    let span = tracing::info_span!("test");
+    let limits = |core: &QueueCore| {
+        let limiter = core.adaptive.get("mx.example.org").unwrap();
+        (
+            limiter.concurrency.max_concurrent,
+            limiter.rate.max_requests,
+            limiter.deferrals,
+        )
+    };
+
+    // The first connection starts at the configured maximum
+    let mut in_flight = vec![];
+    core.is_adaptive_allowed(&envelope, &mut in_flight, &span)
+        .await
+        .unwrap();
+    assert_eq!(limits(&core), (4, 20, 0));
+
+    // A 421 response lowers the limits
+    let deferred = Status::TemporaryFailure(Error::UnexpectedResponse(HostResponse {
+        hostname: ErrorDetails {
+            entity: "mx.example.org".to_string(),
+            details: "MAIL FROM".to_string(),
+        },
+        response: Response {
+            code: 421,
+            esc: [4, 7, 0],
+            message: "Try again later".to_string(),
+        },
+    }));
+    assert_eq!(
+        core.adaptive_feedback(&envelope, &deferred, std::iter::empty(), &span)
+            .await,
+        Feedback::Deferred
+    );
+    assert_eq!(limits(&core), (2, 10, 1));
+
+    // 4.7.x deferrals at the recipient level lower the limits as well
+    let rcpt = Recipient {
+        domain_idx: 0,
+        address: "john@example.org".to_string(),
+        address_lcase: "john@example.org".to_string(),
+        status: Status::TemporaryFailure(HostResponse {
+            hostname: ErrorDetails {
+                entity: "mx.example.org".to_string(),
+                details: "RCPT TO:<john@example.org>".to_string(),
+            },
+            response: Response {
+                code: 450,
+                esc: [4, 7, 28],
+                message: "Rate limited".to_string(),
+            },
+        }),
+        flags: RCPT_STATUS_CHANGED,
+        orcpt: None,
+    };
+    assert_eq!(
+        core.adaptive_feedback(&envelope, &Status::Scheduled, [&rcpt].into_iter(), &span)
+            .await,
+        Feedback::Deferred
+    );
+    assert_eq!(limits(&core), (1, 5, 2));
+
+    // Concurrency is now limited to a single connection
+    assert!(matches!(
+        core.is_adaptive_allowed(&envelope, &mut vec![], &span)
+            .await,
+        Err(throttle::Error::Concurrency { .. })
+    ));
+    in_flight.clear();
+
+    // Other temporary failures do not affect the limits
+    let failed = Status::TemporaryFailure(Error::UnexpectedResponse(HostResponse {
+        hostname: ErrorDetails {
+            entity: "mx.example.org".to_string(),
+            details: "DATA".to_string(),
+        },
+        response: Response {
+            code: 451,
+            esc: [4, 3, 0],
+            message: "Local error".to_string(),
+        },
+    }));
+    assert_eq!(
+        core.adaptive_feedback(&envelope, &failed, std::iter::empty(), &span)
+            .await,
+        Feedback::None
+    );
+    assert_eq!(limits(&core), (1, 5, 2));
+
+    // Successful deliveries slowly raise the limits
+    for _ in 0..2 {
+        assert_eq!(
+            core.adaptive_feedback(&envelope, &Status::Completed(()), std::iter::empty(), &span)
+                .await,
+            Feedback::Success
+        );
+    }
+    assert_eq!(limits(&core), (2, 7, 2));
+
+    // Disabled destinations are not tracked
+    core.config.adaptive.enable = IfBlock::new(false);
+    let envelope = QueueEnvelope::test(&test_message, "example.net", "mx.example.net");
+    core.is_adaptive_allowed(&envelope, &mut in_flight, &span)
+        .await
+        .unwrap();
+    assert!(in_flight.is_empty());
+    assert!(!core.adaptive.contains_key("mx.example.net"));
+}
+
 pub trait TestQueueEnvelope<'x> {
     fn test(message: &'x Message, domain: &'x str, mx: &'x str) -> Self;
 }
@@ -97,6 +97,7 @@ async fn generate_dsn() {
         flags: 0,
         env_id: None,
         priority: 0,
+        pool: None,
 
         queue_refs: vec![],
     });
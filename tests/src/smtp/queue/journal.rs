@@ -0,0 +1,121 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use smtp::{
+    config::{ConfigContext, IfBlock},
+    core::{Session, SMTP},
+};
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    make_temp_dir,
+    session::{TestSession, VerifyResponse},
+    ParseTestConfig, TestConfig, TestSMTP,
+};
+
+#[tokio::test]
+async fn journal() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish(),
+    )
+    .unwrap();*/
+
+    let mut core = SMTP::test();
+    let mut qr = core.init_test_queue("smtp_journal_test");
+    let journal_dir = make_temp_dir("smtp_journal_files", true);
+
+    // Journal messages addressed to foobar.org
+    core.session.config.rcpt.relay = IfBlock::new(true);
+    let config = &mut core.queue.config.journal;
+    config.address = r"[{if = 'rcpt-domain', eq = 'foobar.org', then = 'archive@journal.org'},
+    {else = false}]"
+        .parse_if(&ConfigContext::new(&[]));
+    config.path = format!(
+        "[{{if = 'rcpt', eq = 'jane@foobar.org', then = '{}'}}, {{else = false}}]",
+        journal_dir.temp_dir.display()
+    )
+    .as_str()
+    .parse_if(&ConfigContext::new(&[]));
+
+    let mut session = Session::test(core);
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+
+    // Recipients that do not match are not journaled
+    session
+        .send_message("john@doe.org", &["bill@example.org"], "test:no_dkim", "250")
+        .await;
+    let message = qr.read_event().await.unwrap_message();
+    assert_eq!(message.recipients.len(), 1);
+    assert_eq!(message.recipients[0].address, "bill@example.org");
+    qr.assert_empty_queue();
+    assert_eq!(std::fs::read_dir(&journal_dir.temp_dir).unwrap().count(), 0);
+
+    // A copy is queued for the journal address before the original message
+    session
+        .send_message(
+            "john@doe.org",
+            &["jane@foobar.org", "bill@example.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    let journal = qr.read_event().await.unwrap_message();
+    let message = qr.read_event().await.unwrap_message();
+    qr.assert_empty_queue();
+    assert_eq!(journal.return_path, "");
+    assert_eq!(journal.recipients.len(), 1);
+    assert_eq!(journal.recipients[0].address, "archive@journal.org");
+    assert_eq!(message.recipients.len(), 2);
+    journal
+        .read_lines()
+        .assert_contains("To: <archive@journal.org>")
+        .assert_contains("Sender: john@doe.org")
+        .assert_contains("Recipient: jane@foobar.org")
+        .assert_not_contains("Recipient: bill@example.org")
+        .assert_contains(&format!("Queue-Id: {:x}", message.id))
+        .assert_contains("Remote-IP: 10.0.0.1")
+        .assert_contains("Content-Type: message/rfc822");
+
+    // A single read-only copy is written to the journal location
+    let files = std::fs::read_dir(&journal_dir.temp_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(files.len(), 1);
+    assert_eq!(
+        files[0].file_name().unwrap().to_str().unwrap(),
+        format!("{}_{:x}.eml", message.created, message.id)
+    );
+    assert!(std::fs::metadata(&files[0])
+        .unwrap()
+        .permissions()
+        .readonly());
+    let contents = std::fs::read_to_string(&files[0]).unwrap();
+    assert!(contents.contains("Recipient: jane@foobar.org"));
+    assert!(!contents.contains("Recipient: bill@example.org"));
+    assert!(!contents.contains("To: <archive@journal.org>"));
+}
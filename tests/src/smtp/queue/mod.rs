@@ -22,6 +22,8 @@
 */
 
 pub mod dsn;
+pub mod journal;
 pub mod manager;
 pub mod retry;
 pub mod serialize;
+pub mod webhook;
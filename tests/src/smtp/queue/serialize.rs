@@ -95,6 +95,7 @@ async fn queue_serialize() {
         flags: MAIL_REQUIRETLS | MAIL_SMTPUTF8,
         env_id: "hello".to_string().into(),
         priority: -1,
+        pool: Some("bulk".to_string()),
 
         queue_refs: vec![],
     };
@@ -199,6 +200,7 @@ fn assert_msg_eq(msg: &Message, other: &Message) {
     assert_eq!(msg.flags, other.flags);
     assert_eq!(msg.env_id, other.env_id);
     assert_eq!(msg.priority, other.priority);
+    assert_eq!(msg.pool, other.pool);
     assert_eq!(msg.size, other.size);
 }
 
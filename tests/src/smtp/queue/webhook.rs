@@ -0,0 +1,271 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{sync::Arc, time::Duration};
+
+use smtp::{
+    config::{webhook::ConfigWebhook, ConfigContext},
+    core::{Session, SMTP},
+    webhook::{
+        self,
+        outbox::{sign, DeliveryResult, WEBHOOK_TEST_REQUESTS},
+        WebhookEvent, WebhookType,
+    },
+};
+use utils::config::Config;
+
+use crate::smtp::{
+    inbound::TestQueueEvent,
+    make_temp_dir,
+    session::{TestSession, VerifyResponse},
+    ParseTestConfig, TestConfig, TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[webhook]
+path = "/tmp"
+
+[webhook.endpoint."audit"]
+url = "https://127.0.0.1/audit"
+events = ["message.accepted", "rcpt.rejected", "auth.failed"]
+signature-key = "key"
+headers = ["Authorization: Bearer abc"]
+retry = ["1s", "2s"]
+
+[webhook.endpoint."delivery"]
+url = "https://127.0.0.1/delivery"
+events = ["message.accepted", "message.delivered", "message.bounced"]
+timeout = "5s"
+"#;
+
+#[tokio::test]
+async fn webhooks() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Parse configuration
+    let mut config = Config::new(CONFIG)
+        .unwrap()
+        .parse_webhooks()
+        .expect("Failed to parse webhooks");
+    assert_eq!(config.endpoints.len(), 2);
+    let audit = &config.endpoints[0];
+    assert_eq!(audit.id, "audit");
+    assert_eq!(
+        audit.events,
+        WebhookType::MessageAccepted.mask()
+            | WebhookType::RcptRejected.mask()
+            | WebhookType::AuthFailed.mask()
+    );
+    assert_eq!(audit.signature_key.as_deref(), Some(&b"key"[..]));
+    assert_eq!(
+        audit.headers,
+        vec![("Authorization".to_string(), "Bearer abc".to_string())]
+    );
+    assert_eq!(
+        audit.retry,
+        vec![Duration::from_secs(1), Duration::from_secs(2)]
+    );
+    let delivery = &config.endpoints[1];
+    assert_eq!(delivery.timeout, Duration::from_secs(5));
+    assert_eq!(delivery.retry.len(), 6);
+    assert!(delivery.signature_key.is_none());
+    assert_eq!(
+        config.events,
+        audit.events | WebhookType::MessageDelivered.mask() | WebhookType::MessageBounced.mask()
+    );
+    assert!(
+        Config::new("[webhook.endpoint.\"bad\"]\nurl = \"https://127.0.0.1\"\n")
+            .unwrap()
+            .parse_webhooks()
+            .is_err()
+    );
+
+    // Known HMAC-SHA256 test vector
+    assert_eq!(
+        sign(b"key", b"The quick brown fox jumps over the lazy dog"),
+        "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+
+    // Create temp dir, the outbox directory is created on first write
+    let mut core = SMTP::test();
+    let temp_dir = make_temp_dir("smtp_webhook_test", true);
+    config.path = temp_dir.temp_dir.join("outbox");
+    core.webhook.config = config;
+    let mut wr = core.init_test_webhook();
+    let mut qr = core.init_test_queue("smtp_webhook_queue_test");
+    core.session.config.rcpt.relay = r"[{if = 'remote-ip', eq = '10.0.0.2', then = true},
+    {else = false}]"
+        .parse_if(&ConfigContext::new(&[]));
+    let core = Arc::new(core);
+
+    // Rejected recipients are reported
+    let mut session = Session::test(core.clone());
+    session.data.remote_ip = "10.0.0.1".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    session.mail_from("john@doe.org", "250").await;
+    session.rcpt_to("bill@foobar.org", "550 5.1.2").await;
+    let event = wr.read_webhook().await;
+    assert_eq!(event.type_, WebhookType::RcptRejected);
+    assert_eq!(event.from.as_deref(), Some("john@doe.org"));
+    assert_eq!(event.to, vec!["bill@foobar.org".to_string()]);
+    assert_eq!(event.remote_ip, Some("10.0.0.1".parse().unwrap()));
+    assert_eq!(
+        event.response.as_deref(),
+        Some("550 5.1.2 Relay not allowed.")
+    );
+    wr.assert_no_webhooks();
+
+    // Accepted messages are reported once all recipients are known
+    let mut session = Session::test(core.clone());
+    session.data.remote_ip = "10.0.0.2".parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org", "jane@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    let message = qr.read_event().await.unwrap_message();
+    let event = wr.read_webhook().await;
+    assert_eq!(event.type_, WebhookType::MessageAccepted);
+    assert_eq!(event.queue_id, Some(message.id));
+    assert_eq!(event.size, Some(message.size));
+    assert_eq!(
+        event.to,
+        vec!["bill@foobar.org".to_string(), "jane@foobar.org".to_string()]
+    );
+    wr.assert_no_webhooks();
+
+    // Events are stored in the outbox for each subscribed endpoint
+    let mut outbox = core.webhook.read_outbox().await;
+    assert!(outbox.entries.is_empty());
+    outbox.enqueue(&core.webhook, event.clone()).await;
+    outbox
+        .enqueue(
+            &core.webhook,
+            WebhookEvent::new(WebhookType::AuthFailed).with_authenticated_as("john"),
+        )
+        .await;
+    assert_eq!(outbox.entries.len(), 3);
+    assert_eq!(std::fs::read_dir(&temp_dir.temp_dir).unwrap().count(), 3);
+
+    // Entries survive a restart
+    let mut outbox = core.webhook.read_outbox().await;
+    assert_eq!(outbox.entries.len(), 3);
+    let mut ids = Vec::new();
+    while let Some(id) = outbox.next_due() {
+        ids.push(id);
+    }
+    assert_eq!(ids.len(), 3);
+
+    // Deliver all entries
+    WEBHOOK_TEST_REQUESTS.lock().clear();
+    for id in &ids {
+        outbox.deliver(&core, *id).await;
+    }
+    let mut results = Vec::new();
+    for _ in 0..3 {
+        match tokio::time::timeout(Duration::from_millis(500), wr.webhook_rx.recv()).await {
+            Ok(Some(webhook::Event::Done(result))) => results.push(result),
+            result => panic!("Unexpected result {result:?}"),
+        }
+    }
+    assert!(results.iter().all(|r| r.success));
+    let requests = WEBHOOK_TEST_REQUESTS.lock().clone();
+    assert_eq!(requests.len(), 3);
+    for (signature, body) in &requests {
+        let event = serde_json::from_str::<WebhookEvent>(body).unwrap();
+        if signature.is_empty() {
+            // Unsigned endpoint only receives accepted messages
+            assert_eq!(event.type_, WebhookType::MessageAccepted);
+        } else {
+            assert_eq!(
+                signature,
+                &format!("sha256={}", sign(b"key", body.as_bytes()))
+            );
+        }
+    }
+    assert!(requests
+        .iter()
+        .any(|(_, body)| body.contains("\"type\":\"auth.failed\"")));
+
+    // Successful deliveries are removed from the outbox, failed ones are retried
+    let (failed, succeeded) = (ids[0], &ids[1..]);
+    for id in succeeded {
+        outbox
+            .done(
+                &core.webhook,
+                DeliveryResult {
+                    id: *id,
+                    success: true,
+                },
+            )
+            .await;
+    }
+    assert_eq!(outbox.entries.len(), 1);
+    assert_eq!(std::fs::read_dir(&temp_dir.temp_dir).unwrap().count(), 1);
+    let max_attempts = if outbox.entries[&failed].webhook == "audit" {
+        2
+    } else {
+        6
+    };
+    for attempt in 1..=max_attempts {
+        outbox
+            .done(
+                &core.webhook,
+                DeliveryResult {
+                    id: failed,
+                    success: false,
+                },
+            )
+            .await;
+        assert_eq!(outbox.entries[&failed].attempt, attempt);
+        assert!(outbox.wake_up_time() > Duration::from_millis(500));
+    }
+    assert_eq!(
+        core.webhook.read_outbox().await.entries[&failed].attempt,
+        max_attempts
+    );
+
+    // Discard after too many attempts
+    outbox
+        .done(
+            &core.webhook,
+            DeliveryResult {
+                id: failed,
+                success: false,
+            },
+        )
+        .await;
+    assert!(outbox.entries.is_empty());
+    assert_eq!(std::fs::read_dir(&temp_dir.temp_dir).unwrap().count(), 0);
+}
@@ -29,7 +29,10 @@ use crate::smtp::{
 use smtp::{
     config::{AddressMatch, IfBlock},
     core::{Session, SMTP},
+    reporting::inbox::{IncomingReportFilter, IncomingReportType, SourceSummary},
 };
+use store::Store;
+use utils::config::Config;
 
 #[tokio::test]
 async fn report_analyze() {
@@ -52,6 +55,26 @@ async fn report_analyze() {
     config.forward = false;
     config.store = report_dir.temp_dir.clone().into();
 
+    // Report summaries are kept in the store
+    let store_dir = make_temp_dir("smtp_report_store", true);
+    core.report.store = Arc::new(
+        Store::open(
+            &Config::new(&format!(
+                concat!(
+                    "store.blob.type = \"local\"\n",
+                    "store.blob.local.path = \"{}\"\n",
+                    "store.db.path = \"{}/sqlite.db\"\n"
+                ),
+                store_dir.temp_dir.display(),
+                store_dir.temp_dir.display()
+            ))
+            .unwrap(),
+        )
+        .await
+        .unwrap(),
+    )
+    .into();
+
     // Create test message
     let core = Arc::new(core);
     let mut session = Session::test(core.clone());
@@ -91,6 +114,126 @@ async fn report_analyze() {
     }
     assert_eq!(total_reports, total_reports_received);
 
+    // Query stored reports
+    assert_eq!(
+        core.report
+            .incoming_list(&IncomingReportFilter::default())
+            .await
+            .len(),
+        total_reports_received
+    );
+    for (type_, expected) in [
+        (IncomingReportType::Arf, 5),
+        (IncomingReportType::Dmarc, 5),
+        (IncomingReportType::Tls, 2),
+    ] {
+        let reports = core
+            .report
+            .incoming_list(&IncomingReportFilter {
+                type_: type_.into(),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(reports.len(), expected);
+        for report in reports {
+            assert_eq!(report.type_, type_);
+            assert_eq!(core.report.incoming_get(&report.id).await, Some(report));
+        }
+    }
+    assert_eq!(core.report.incoming_get("../dmarc_0_0").await, None);
+    assert_eq!(core.report.incoming_get("dmarc_0_0").await, None);
+
+    // Lookups by domain, reporter and date
+    for filter in [
+        IncomingReportFilter {
+            domain: "example.com".to_string().into(),
+            ..Default::default()
+        },
+        IncomingReportFilter {
+            reporter: "google".to_string().into(),
+            ..Default::default()
+        },
+        IncomingReportFilter {
+            from: 1577836800.into(),
+            ..Default::default()
+        },
+    ] {
+        let reports = core.report.incoming_list(&filter).await;
+        assert!(!reports.is_empty(), "{filter:?}");
+        assert!(reports.iter().all(|report| filter.matches(report)));
+    }
+    assert!(core
+        .report
+        .incoming_list(&IncomingReportFilter {
+            domain: "unknown.org".to_string().into(),
+            ..Default::default()
+        })
+        .await
+        .is_empty());
+
+    let reports = core
+        .report
+        .incoming_list(&IncomingReportFilter {
+            type_: IncomingReportType::Tls.into(),
+            domain: "example.com".to_string().into(),
+            reporter: "google".to_string().into(),
+            from: 1577836800.into(),
+            to: 1578441599.into(),
+        })
+        .await;
+    let report = reports
+        .iter()
+        .find(|report| report.report_id == "2020-01-01T00:00:00Z_example.com")
+        .unwrap();
+    assert_eq!(report.reporter, "Google Inc.");
+    assert_eq!(report.domains, ["example.com"]);
+    assert_eq!(report.range_from, 1577836800);
+    assert_eq!(
+        report.sources,
+        [
+            SourceSummary {
+                ip: Some("123.123.123.123".parse().unwrap()),
+                total: 1,
+                pass: 0,
+                fail: 1,
+                dkim_fail: 0,
+                spf_fail: 0,
+            },
+            SourceSummary {
+                ip: None,
+                total: 23,
+                pass: 23,
+                fail: 0,
+                dkim_fail: 0,
+                spf_fail: 0,
+            }
+        ]
+    );
+    assert!(core
+        .report
+        .incoming_list(&IncomingReportFilter {
+            domain: "example.com".to_string().into(),
+            to: 1577836799.into(),
+            ..Default::default()
+        })
+        .await
+        .iter()
+        .all(|report| report.report_id != "2020-01-01T00:00:00Z_example.com"));
+
+    // Aggregate results by source IP
+    let sources = SourceSummary::aggregate(&reports);
+    assert_eq!(
+        sources.iter().map(|source| source.total).sum::<u64>(),
+        reports
+            .iter()
+            .flat_map(|report| report.sources.iter())
+            .map(|source| source.total)
+            .sum::<u64>()
+    );
+    assert!(sources
+        .iter()
+        .any(|source| source.ip == Some("123.123.123.123".parse().unwrap()) && source.fail >= 1));
+
     // Test delivery to non-report addresses
     session
         .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")